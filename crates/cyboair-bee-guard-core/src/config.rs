@@ -0,0 +1,430 @@
+//! Run configuration for the bee-guard binaries.
+//!
+//! Built-in defaults, then an optional TOML file, then command-line flags,
+//! validated once merged. Both binaries share the layout below and differ
+//! only in their gain set ([`HiveGains`] or [`MicrospaceGains`]); each adds
+//! its own mode and output flags to [`command`].
+
+use std::path::PathBuf;
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use cyboair_corridor_safety::config::ConfigError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::output::OutputFormat;
+use crate::{invalid, BeeContext, Conditions, HiveGains, MicrospaceGains};
+
+/// A duty law's gains, as the config sees them.
+pub trait Gains:
+    std::fmt::Debug + Default + Clone + PartialEq + Serialize + DeserializeOwned
+{
+    /// Shard read when neither the file nor the flags name one.
+    const DEFAULT_INPUT: &'static str;
+    /// Whether the law reads a `[hive]` context.
+    const HIVE: bool;
+    /// `--flag` and help for each gain under `[gains]`.
+    const FLAGS: &'static [(&'static str, &'static str)];
+
+    fn field(&mut self, flag: &str) -> Option<&mut f64>;
+
+    fn validate(&self) -> Result<(), ConfigError>;
+}
+
+impl Gains for HiveGains {
+    const DEFAULT_INPUT: &'static str = "data/cyboair_nodes_hive_corridor.csv";
+    const HIVE: bool = true;
+    const FLAGS: &'static [(&'static str, &'static str)] = &[
+        ("m-ref", "Reference mass, kg [gains.m_ref]"),
+        ("k-ref", "Reference air karma [gains.k_ref]"),
+        ("c-power", "Normalized power cost [gains.c_power]"),
+        ("eta1", "Mass gain [gains.eta1]"),
+        ("eta2", "Air karma gain [gains.eta2]"),
+        ("eta3", "Geospatial gain [gains.eta3]"),
+        ("eta4", "Bee-safety gain [gains.eta4]"),
+        ("eta5", "Power gain [gains.eta5]"),
+    ];
+
+    fn field(&mut self, flag: &str) -> Option<&mut f64> {
+        Some(match flag {
+            "m-ref" => &mut self.m_ref,
+            "k-ref" => &mut self.k_ref,
+            "c-power" => &mut self.c_power,
+            "eta1" => &mut self.eta1,
+            "eta2" => &mut self.eta2,
+            "eta3" => &mut self.eta3,
+            "eta4" => &mut self.eta4,
+            "eta5" => &mut self.eta5,
+            _ => return None,
+        })
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        HiveGains::validate(self)
+    }
+}
+
+impl Gains for MicrospaceGains {
+    // Adjust path to your extended shard with bee columns
+    const DEFAULT_INPUT: &'static str =
+        "qpudatashards/particles/CyboAirTenMachinesPhoenix2026v1_bee.csv";
+    const HIVE: bool = false;
+    const FLAGS: &'static [(&'static str, &'static str)] = &[
+        ("m-ref", "Reference mass, kg [gains.m_ref]"),
+        ("k-ref", "Reference bee karma [gains.k_ref]"),
+        ("e-ref-bee", "Reference EMF score [gains.e_ref_bee]"),
+        ("eta1", "Mass gain [gains.eta1]"),
+        ("eta2", "Bee karma gain [gains.eta2]"),
+        ("eta3", "Bee geospatial gain [gains.eta3]"),
+        ("eta4", "EMF penalty [gains.eta4]"),
+    ];
+
+    fn field(&mut self, flag: &str) -> Option<&mut f64> {
+        Some(match flag {
+            "m-ref" => &mut self.m_ref,
+            "k-ref" => &mut self.k_ref,
+            "e-ref-bee" => &mut self.e_ref_bee,
+            "eta1" => &mut self.eta1,
+            "eta2" => &mut self.eta2,
+            "eta3" => &mut self.eta3,
+            "eta4" => &mut self.eta4,
+            _ => return None,
+        })
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        MicrospaceGains::validate(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, bound(deserialize = "G: Gains"))]
+pub struct Config<G> {
+    /// Shard CSV; columns are matched by header.
+    pub input: PathBuf,
+    /// Hive file; without it proximity comes from location names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hives: Option<PathBuf>,
+    pub conditions: Conditions,
+    pub gains: G,
+    /// The hive a [`Gains::HIVE`] law guards when no hives file is given;
+    /// always `None` for the others.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hive: Option<BeeContext>,
+}
+
+impl<G: Gains> Default for Config<G> {
+    fn default() -> Self {
+        Config {
+            input: PathBuf::from(G::DEFAULT_INPUT),
+            hives: None,
+            conditions: Conditions::default(),
+            gains: G::default(),
+            hive: G::HIVE.then(BeeContext::default),
+        }
+    }
+}
+
+impl<G: Gains> Config<G> {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        toml::from_str(s).map_err(|e| ConfigError::Toml(e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string(self).map_err(|e| ConfigError::Toml(e.to_string()))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.conditions.validate()?;
+        self.gains.validate()?;
+        match &self.hive {
+            Some(hive) if G::HIVE => hive.validate(),
+            Some(_) => Err(invalid("hive", "this duty law reads no hive context")),
+            None => Ok(()),
+        }
+    }
+
+    fn field(&mut self, flag: &str) -> &mut f64 {
+        match flag {
+            "temperature-k" => &mut self.conditions.temperature_k,
+            "pressure-pa" => &mut self.conditions.pressure_pa,
+            "molar-mass" => &mut self.conditions.molar_mass_kg_per_mol,
+            "colony-mass-kg" => &mut self.hive().colony_mass_kg,
+            "colony-baseline-kg" => &mut self.hive().colony_mass_baseline_kg,
+            "sbee-min" => &mut self.hive().sbee_min,
+            "kref-bee" => &mut self.hive().kref_bee,
+            "sbee-alpha" => &mut self.hive().alpha,
+            other => match self.gains.field(other) {
+                Some(field) => field,
+                None => unreachable!("no config field for --{other}"),
+            },
+        }
+    }
+
+    fn hive(&mut self) -> &mut BeeContext {
+        self.hive.get_or_insert_with(BeeContext::default)
+    }
+}
+
+// Numeric flags for [conditions], shared by every law
+const CONDITION_FLAGS: &[(&str, &str)] = &[
+    (
+        "temperature-k",
+        "Air temperature, K [conditions.temperature_k]",
+    ),
+    (
+        "pressure-pa",
+        "Station pressure, Pa [conditions.pressure_pa]",
+    ),
+    (
+        "molar-mass",
+        "Molar mass for mixing ratios, kg/mol [conditions.molar_mass_kg_per_mol]",
+    ),
+];
+
+// Numeric flags for [hive], offered only to laws that read it
+const HIVE_FLAGS: &[(&str, &str)] = &[
+    (
+        "colony-mass-kg",
+        "Current colony mass, kg [hive.colony_mass_kg]",
+    ),
+    (
+        "colony-baseline-kg",
+        "Baseline colony mass, kg [hive.colony_mass_baseline_kg]",
+    ),
+    ("sbee-min", "Minimum acceptable S_bee, 0..1 [hive.sbee_min]"),
+    ("kref-bee", "Reference bee karma [hive.kref_bee]"),
+    ("sbee-alpha", "S_bee steepness [hive.alpha]"),
+];
+
+fn numeric_flags<G: Gains>() -> impl Iterator<Item = &'static (&'static str, &'static str)> {
+    let hive: &[_] = if G::HIVE { HIVE_FLAGS } else { &[] };
+    CONDITION_FLAGS.iter().chain(G::FLAGS).chain(hive)
+}
+
+/// The flags every bee-guard binary takes: config file, input, hives,
+/// output and one per numeric config field.
+pub fn command<G: Gains>(name: &'static str) -> Command {
+    let mut cmd = Command::new(name)
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("TOML config; flags override its values"),
+        )
+        .arg(
+            Arg::new("input")
+                .long("input")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("Shard CSV [input]"),
+        )
+        .arg(
+            Arg::new("hives")
+                .long("hives")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("Hive file [hives]"),
+        )
+        .arg(
+            Arg::new("output-format")
+                .long("output-format")
+                .value_name("csv|json|ndjson")
+                .value_parser(|s: &str| s.parse::<OutputFormat>())
+                .default_value("csv"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("Write here instead of stdout"),
+        )
+        .arg(
+            Arg::new("print-effective-config")
+                .long("print-effective-config")
+                .action(ArgAction::SetTrue)
+                .help("Print the merged config as TOML and exit"),
+        );
+    if G::HIVE {
+        cmd = cmd.arg(
+            Arg::new("hive-id")
+                .long("hive-id")
+                .value_name("ID")
+                .help("Hive identifier [hive.hive_id]"),
+        );
+    }
+    for &(flag, help) in numeric_flags::<G>() {
+        cmd = cmd.arg(
+            Arg::new(flag)
+                .long(flag)
+                .value_name("X")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .help(help),
+        );
+    }
+    cmd
+}
+
+/// Merges `matches` from [`command`] over `file`, the text of the
+/// `--config` file if one was given, over the defaults.
+pub fn resolve<G: Gains>(
+    matches: &ArgMatches,
+    file: Option<&str>,
+) -> Result<Config<G>, ConfigError> {
+    let mut config = match file {
+        Some(text) => Config::from_toml(text)?,
+        None => Config::default(),
+    };
+    if let Some(input) = matches.get_one::<PathBuf>("input") {
+        config.input = input.clone();
+    }
+    if let Some(hives) = matches.get_one::<PathBuf>("hives") {
+        config.hives = Some(hives.clone());
+    }
+    if G::HIVE {
+        if let Some(hive_id) = matches.get_one::<String>("hive-id") {
+            config.hive().hive_id = hive_id.clone();
+        }
+    }
+    for &(flag, _) in numeric_flags::<G>() {
+        if let Some(&v) = matches.get_one::<f64>(flag) {
+            *config.field(flag) = v;
+        }
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIVE_FIXTURE: &str = include_str!("../fixtures/hive_guard.toml");
+    const MICROSPACE_FIXTURE: &str = include_str!("../fixtures/microspace_guard.toml");
+
+    fn resolve_args<G: Gains>(args: &[&str], file: Option<&str>) -> Result<Config<G>, ConfigError> {
+        let matches = command::<G>("bee-guard")
+            .try_get_matches_from(std::iter::once("bee-guard").chain(args.iter().copied()))
+            .unwrap();
+        resolve(&matches, file)
+    }
+
+    #[test]
+    fn flags_override_file_and_file_overrides_defaults() {
+        let config: Config<HiveGains> = resolve_args(
+            &["--eta2", "0.4", "--hive-id", "HIVE-CLI"],
+            Some(HIVE_FIXTURE),
+        )
+        .unwrap();
+        let hive = config.hive.as_ref().unwrap();
+        // From the file
+        assert_eq!(config.input, PathBuf::from("fixtures/hive_corridor.csv"));
+        assert_eq!(config.conditions.temperature_k, 305.0);
+        assert_eq!(config.gains.eta1, 0.15);
+        assert_eq!(hive.sbee_min, 0.7);
+        // From the flags, over the file
+        assert_eq!(config.gains.eta2, 0.4);
+        assert_eq!(hive.hive_id, "HIVE-CLI");
+        assert_eq!(config.hives, Some(PathBuf::from("fixtures/hives.json")));
+        // Left to the defaults
+        assert_eq!(config.gains.eta5, HiveGains::default().eta5);
+        assert_eq!(hive.kref_bee, BeeContext::default().kref_bee);
+
+        let config: Config<MicrospaceGains> = resolve_args(
+            &["--eta3", "0.3", "--hives", "hives.json"],
+            Some(MICROSPACE_FIXTURE),
+        )
+        .unwrap();
+        assert_eq!(config.gains.eta3, 0.3);
+        assert_eq!(config.hives, Some(PathBuf::from("hives.json")));
+        assert_eq!(config.gains.e_ref_bee, 0.8);
+        assert_eq!(config.gains.eta4, MicrospaceGains::default().eta4);
+        assert_eq!(config.hive, None);
+
+        assert_eq!(
+            resolve_args::<HiveGains>(&[], None).unwrap(),
+            Config::default()
+        );
+        assert_eq!(
+            resolve_args::<MicrospaceGains>(&[], None).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn effective_config_round_trips() {
+        let config: Config<HiveGains> =
+            resolve_args(&["--pressure-pa", "96500"], Some(HIVE_FIXTURE)).unwrap();
+        let text = config.to_toml().unwrap();
+        assert_eq!(Config::from_toml(&text).unwrap(), config);
+
+        let config: Config<MicrospaceGains> =
+            resolve_args(&["--molar-mass", "0.046"], Some(MICROSPACE_FIXTURE)).unwrap();
+        let text = config.to_toml().unwrap();
+        assert!(!text.contains("[hive]"));
+        assert_eq!(Config::from_toml(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_after_merging() {
+        let err =
+            resolve_args::<HiveGains>(&["--sbee-min", "1.5"], Some(HIVE_FIXTURE)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                field: "hive.sbee_min",
+                ..
+            }
+        ));
+
+        let err = resolve_args::<HiveGains>(&["--k-ref", "0"], None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: gains.k_ref: must be finite and > 0, got 0"
+        );
+
+        let err = resolve_args::<MicrospaceGains>(&["--e-ref-bee", "-1"], None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: gains.e_ref_bee: must be finite and > 0, got -1"
+        );
+
+        let err = resolve_args::<HiveGains>(&["--eta4", "NaN"], None).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                field: "gains.eta4",
+                ..
+            }
+        ));
+
+        let bad = HIVE_FIXTURE.replace("temperature_k = 305.0", "temperature_k = -1.0");
+        let err = resolve_args::<HiveGains>(&[], Some(&bad)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                field: "conditions.temperature_k",
+                ..
+            }
+        ));
+
+        let typo = HIVE_FIXTURE.replace("eta1", "eta_1");
+        assert!(matches!(
+            resolve_args::<HiveGains>(&[], Some(&typo)),
+            Err(ConfigError::Toml(_))
+        ));
+
+        // Only the hive-corridor law has a [hive] section or its flags.
+        let unused = format!("{MICROSPACE_FIXTURE}\n[hive]\nsbee_min = 0.8\n");
+        assert!(matches!(
+            resolve_args::<MicrospaceGains>(&[], Some(&unused)),
+            Err(ConfigError::Invalid { field: "hive", .. })
+        ));
+        assert!(command::<MicrospaceGains>("bee-guard")
+            .try_get_matches_from(["bee-guard", "--sbee-min", "0.8"])
+            .is_err());
+    }
+}
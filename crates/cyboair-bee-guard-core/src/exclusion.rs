@@ -0,0 +1,433 @@
+//! Hive exclusion zones in the corridor safety envelope.
+//!
+//! The duty laws only weigh hive proximity; an exclusion zone is absolute.
+//! [`HiveExclusionEnvelope`] fails any node closer to a hive than the
+//! effective radius, `base_radius_m` times the largest multiplier of the
+//! seasons covering today, e.g. 15 m doubled during swarming. Under
+//! `ViolationPolicy::ClampToSafe` such a node drops to sensing only.
+//!
+//! A node's position comes from [`NodePositions`], by `machine_id` and
+//! then by `location`; nodes with neither are not in any zone. Combine
+//! with the operating bounds through `CompositeSafetyEnvelope`.
+
+use std::collections::BTreeMap;
+
+use cyboair_corridor_safety::clock::{Clock, SystemClock};
+use cyboair_corridor_safety::config::ConfigError;
+use cyboair_corridor_safety::{CorridorRow, NodeState, SafetyEnvelope, SafetyError};
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{HiveRegistry, LatLon};
+use crate::Row;
+
+const DAY_MS: u64 = 86_400_000;
+
+/// A calendar day in any year, UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MonthDay {
+    pub month: u8,
+    pub day: u8,
+}
+
+impl MonthDay {
+    pub fn new(month: u8, day: u8) -> Self {
+        MonthDay { month, day }
+    }
+
+    /// The UTC calendar day of `unix_ms`.
+    pub fn from_unix_ms(unix_ms: u64) -> Self {
+        // Days to civil date, after H. Hinnant's `civil_from_days`.
+        let z = unix_ms / DAY_MS + 719_468;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        MonthDay {
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month) && (1..=31).contains(&self.day)
+    }
+}
+
+/// A radius multiplier for the days `start..=end`; a range whose end comes
+/// before its start runs over the new year.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeasonalRadius {
+    pub start: MonthDay,
+    pub end: MonthDay,
+    pub multiplier: f64,
+}
+
+impl SeasonalRadius {
+    pub fn contains(&self, date: MonthDay) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            date >= self.start || date <= self.end
+        }
+    }
+}
+
+/// Exclusion radius and its seasonal multipliers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionConfig {
+    pub base_radius_m: f64,
+    #[serde(default)]
+    pub seasons: Vec<SeasonalRadius>,
+}
+
+impl ExclusionConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field, reason: String| Err(ConfigError::Invalid { field, reason });
+        if !(self.base_radius_m.is_finite() && self.base_radius_m >= 0.0) {
+            return invalid(
+                "exclusion.base_radius_m",
+                format!("must be finite and >= 0, got {}", self.base_radius_m),
+            );
+        }
+        for (i, season) in self.seasons.iter().enumerate() {
+            if !(season.start.is_valid() && season.end.is_valid()) {
+                return invalid(
+                    "exclusion.seasons",
+                    format!("season {i}: invalid month or day"),
+                );
+            }
+            if !(season.multiplier.is_finite() && season.multiplier > 0.0) {
+                return invalid(
+                    "exclusion.seasons",
+                    format!(
+                        "season {i}: multiplier must be finite and > 0, got {}",
+                        season.multiplier
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Largest multiplier of the seasons covering `date`, 1 outside them.
+    pub fn multiplier_on(&self, date: MonthDay) -> f64 {
+        self.seasons
+            .iter()
+            .filter(|s| s.contains(date))
+            .map(|s| s.multiplier)
+            .reduce(f64::max)
+            .unwrap_or(1.0)
+    }
+
+    pub fn radius_on(&self, date: MonthDay) -> f64 {
+        self.base_radius_m * self.multiplier_on(date)
+    }
+}
+
+/// Node positions by `machine_id` and by `location`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodePositions {
+    #[serde(default)]
+    pub machines: BTreeMap<String, LatLon>,
+    #[serde(default)]
+    pub locations: BTreeMap<String, LatLon>,
+}
+
+impl NodePositions {
+    /// Positions of the shard rows that carry valid coordinates.
+    pub fn from_rows(rows: &[Row]) -> Self {
+        NodePositions {
+            machines: rows
+                .iter()
+                .filter_map(|r| Some((r.machine_id.clone(), r.position()?)))
+                .collect(),
+            locations: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_machine(mut self, machine_id: impl Into<String>, position: LatLon) -> Self {
+        self.machines.insert(machine_id.into(), position);
+        self
+    }
+
+    pub fn with_location(mut self, location: impl Into<String>, position: LatLon) -> Self {
+        self.locations.insert(location.into(), position);
+        self
+    }
+
+    /// The row's machine position, else its location's.
+    pub fn position(&self, row: &CorridorRow) -> Option<LatLon> {
+        self.machines
+            .get(&row.machine_id)
+            .or_else(|| self.locations.get(&row.location))
+            .copied()
+    }
+}
+
+/// Safety envelope keeping nodes out of hive exclusion zones; see the
+/// module docs.
+#[derive(Debug, Clone)]
+pub struct HiveExclusionEnvelope<C: Clock = SystemClock> {
+    pub hives: HiveRegistry,
+    pub config: ExclusionConfig,
+    pub positions: NodePositions,
+    /// Source of today's date for the seasonal radius.
+    pub clock: C,
+}
+
+impl<C: Clock> HiveExclusionEnvelope<C> {
+    pub fn new(
+        hives: HiveRegistry,
+        config: ExclusionConfig,
+        positions: NodePositions,
+        clock: C,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(HiveExclusionEnvelope {
+            hives,
+            config,
+            positions,
+            clock,
+        })
+    }
+
+    /// The exclusion radius in force now, m.
+    pub fn effective_radius_m(&self) -> f64 {
+        self.config
+            .radius_on(MonthDay::from_unix_ms(self.clock.now_unix_ms()))
+    }
+}
+
+impl<C: Clock> SafetyEnvelope for HiveExclusionEnvelope<C> {
+    /// Fails with the nearest hive whose zone holds the node.
+    fn check_envelope(&self, node: &NodeState) -> Result<(), SafetyError> {
+        let Some(here) = self.positions.position(&node.row) else {
+            return Ok(());
+        };
+        let Some((hive_id, distance_m)) = self.hives.nearest_hive(here.lat, here.lon) else {
+            return Ok(());
+        };
+        let radius_m = self.effective_radius_m();
+        if distance_m < radius_m {
+            return Err(SafetyError::HiveExclusion {
+                machine_id: node.row.machine_id.clone(),
+                hive_id: hive_id.to_string(),
+                distance_m,
+                radius_m,
+            });
+        }
+        Ok(())
+    }
+
+    /// Sensing only.
+    fn safe_duty(&self) -> Option<f64> {
+        Some(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyboair_corridor_safety::clock::ManualClock;
+    use cyboair_corridor_safety::{
+        CompositeSafetyEnvelope, ConstAltitude, EnvelopeField, OperationalMode, RectSafetyEnvelope,
+    };
+
+    // Noon UTC on each date.
+    const MAR_31: u64 = 1_774_958_400_000;
+    const APR_01: u64 = 1_775_044_800_000;
+    const JUN_15: u64 = 1_781_524_800_000;
+    const JUN_16: u64 = 1_781_611_200_000;
+    const DEC_31: u64 = 1_798_718_400_000;
+    const JAN_01: u64 = 1_798_804_800_000;
+
+    /// HIVE-PHX-01 and a node about 20 m north of it.
+    fn envelope(clock: ManualClock) -> HiveExclusionEnvelope<ManualClock> {
+        let hives =
+            HiveRegistry::from_json_reader(&include_bytes!("../fixtures/hives_phoenix.json")[..])
+                .unwrap();
+        let config = ExclusionConfig {
+            base_radius_m: 15.0,
+            seasons: vec![SeasonalRadius {
+                start: MonthDay::new(4, 1),
+                end: MonthDay::new(6, 15),
+                multiplier: 2.0,
+            }],
+        };
+        let positions = NodePositions::default()
+            .with_location("Phoenix-Apiary-1", LatLon::new(33.45118, -112.072));
+        HiveExclusionEnvelope::new(hives, config, positions, clock).unwrap()
+    }
+
+    fn node() -> NodeState {
+        NodeState {
+            row: CorridorRow {
+                machine_id: "CYB-AIR-APIARY-02".into(),
+                r#type: "UrbanNanoswarmCanopy".into(),
+                location: "Phoenix-Apiary-1".into(),
+                pollutant: "PM2.5".into(),
+                cin: 30.0,
+                cout: 20.0,
+                unit: "ugm3".into(),
+                airflow_m3_per_s: 1.0,
+                period_s: 3600.0,
+                lambda_hazard: 3.0,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.9,
+                trace_id: None,
+                shard_version: None,
+            },
+            mass_kg: 0.0,
+            karma_bytes: 0.0,
+            duty_cycle: 0.5,
+            power_w: 40.0,
+            geo_weight: 1.0,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        }
+    }
+
+    #[test]
+    fn swarming_season_boundaries_are_inclusive() {
+        let clock = ManualClock::new(MAR_31);
+        let envelope = envelope(clock.clone());
+        let node = node();
+        for (now, radius_m, excluded) in [
+            (MAR_31, 15.0, false),
+            (APR_01, 30.0, true),
+            (JUN_15, 30.0, true),
+            (JUN_16, 15.0, false),
+        ] {
+            clock.set(now);
+            assert_eq!(envelope.effective_radius_m(), radius_m);
+            assert_eq!(envelope.check_envelope(&node).is_err(), excluded, "{now}");
+        }
+
+        clock.set(APR_01);
+        match envelope.check_envelope(&node) {
+            Err(SafetyError::HiveExclusion {
+                hive_id,
+                distance_m,
+                radius_m,
+                ..
+            }) => {
+                assert_eq!(hive_id, "HIVE-PHX-01");
+                assert!((distance_m - 20.0).abs() < 0.5, "{distance_m}");
+                assert_eq!(radius_m, 30.0);
+            }
+            other => panic!("expected a hive exclusion, got {other:?}"),
+        }
+
+        // Nodes without a known position are in no zone.
+        let mut elsewhere = node.clone();
+        elsewhere.row.location = "Unmapped".into();
+        assert_eq!(envelope.check_envelope(&elsewhere), Ok(()));
+    }
+
+    #[test]
+    fn seasons_run_over_the_new_year() {
+        assert_eq!(MonthDay::from_unix_ms(DEC_31), MonthDay::new(12, 31));
+        assert_eq!(MonthDay::from_unix_ms(JAN_01), MonthDay::new(1, 1));
+        assert_eq!(MonthDay::from_unix_ms(0), MonthDay::new(1, 1));
+        let winter = SeasonalRadius {
+            start: MonthDay::new(12, 20),
+            end: MonthDay::new(1, 5),
+            multiplier: 1.5,
+        };
+        for (date, inside) in [
+            ((12, 19), false),
+            ((12, 31), true),
+            ((1, 5), true),
+            ((1, 6), false),
+        ] {
+            assert_eq!(winter.contains(MonthDay::new(date.0, date.1)), inside);
+        }
+
+        let config = ExclusionConfig {
+            base_radius_m: 10.0,
+            seasons: vec![
+                winter.clone(),
+                SeasonalRadius {
+                    multiplier: 3.0,
+                    ..winter
+                },
+            ],
+        };
+        assert_eq!(config.radius_on(MonthDay::new(1, 1)), 30.0);
+        assert_eq!(config.radius_on(MonthDay::new(7, 1)), 10.0);
+
+        let bad = ExclusionConfig {
+            base_radius_m: 10.0,
+            seasons: vec![SeasonalRadius {
+                start: MonthDay::new(13, 1),
+                end: MonthDay::new(1, 5),
+                multiplier: 1.5,
+            }],
+        };
+        assert!(matches!(
+            bad.validate(),
+            Err(ConfigError::Invalid {
+                field: "exclusion.seasons",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn composes_with_the_rectangular_envelope() {
+        let clock = ManualClock::new(APR_01);
+        let rect = RectSafetyEnvelope {
+            u_min: 0.0,
+            u_max: 0.8,
+            z_min_m: 5.0,
+            z_max_m: 600.0,
+            ecoimpact_min: 0.7,
+            ecoimpact_max: 1.0,
+            noise_max_db: None,
+            emf_max_vpm: None,
+            altitude: ConstAltitude(331.0),
+        };
+        let composite = CompositeSafetyEnvelope::builder()
+            .member("rect", rect)
+            .member("hive_exclusion", envelope(clock.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(composite.safe_duty(), Some(0.0));
+
+        let mut node = node();
+        node.duty_cycle = 0.9;
+        let kinds: Vec<_> = composite
+            .member_violations(&node)
+            .into_iter()
+            .map(|(name, e)| (name, e.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("rect", "envelope_violation"),
+                ("hive_exclusion", "hive_exclusion")
+            ]
+        );
+
+        // Out of season only the rectangle binds; inside it, nothing.
+        clock.set(JUN_16);
+        let err = composite.check_envelope(&node).unwrap_err();
+        assert!(matches!(
+            &err,
+            SafetyError::EnvelopeMember { member, .. } if member == "rect"
+        ));
+        assert!(matches!(
+            err.unattributed(),
+            SafetyError::EnvelopeViolation {
+                field: EnvelopeField::DutyCycle,
+                ..
+            }
+        ));
+        node.duty_cycle = 0.5;
+        assert_eq!(composite.check_envelope(&node), Ok(()));
+    }
+}
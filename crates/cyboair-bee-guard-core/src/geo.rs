@@ -0,0 +1,220 @@
+//! Hive proximity from coordinates.
+//!
+//! Distances are great-circle (haversine) on a spherical Earth of mean
+//! radius [`EARTH_RADIUS_M`]; at corridor scale the error against the
+//! ellipsoid is well under 0.5%, far below the spread in bee foraging
+//! ranges.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// IUGG mean Earth radius, m.
+pub const EARTH_RADIUS_M: f64 = 6_371_008.8;
+/// Within this distance of a hive a node counts as fully inside the
+/// foraging core.
+pub const FORAGE_CORE_M: f64 = 500.0;
+/// Beyond this distance a hive no longer raises a node's weight.
+pub const FORAGE_RANGE_M: f64 = 3_000.0;
+
+/// WGS84 position in decimal degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        LatLon { lat, lon }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.lat.is_finite()
+            && self.lon.is_finite()
+            && (-90.0..=90.0).contains(&self.lat)
+            && (-180.0..=180.0).contains(&self.lon)
+    }
+
+    /// Great-circle distance to `other`, m.
+    pub fn distance_m(&self, other: &LatLon) -> f64 {
+        haversine_m(*self, *other)
+    }
+}
+
+/// Great-circle distance between two positions, m.
+pub fn haversine_m(a: LatLon, b: LatLon) -> f64 {
+    let (phi1, phi2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dphi = phi2 - phi1;
+    let dlambda = (b.lon - a.lon).to_radians();
+    let h = (dphi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (dlambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+/// Closeness to a hive in [0, 1]: 1 inside [`FORAGE_CORE_M`], falling
+/// linearly to 0 at [`FORAGE_RANGE_M`].
+pub fn hive_proximity(distance_m: f64) -> f64 {
+    if distance_m.is_nan() {
+        return 0.0;
+    }
+    ((FORAGE_RANGE_M - distance_m) / (FORAGE_RANGE_M - FORAGE_CORE_M)).clamp(0.0, 1.0)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hive {
+    pub id: String,
+    #[serde(flatten)]
+    pub position: LatLon,
+}
+
+/// Errors from loading a hive registry.
+#[derive(Debug)]
+pub enum GeoError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    InvalidCoordinate { id: String, lat: f64, lon: f64 },
+}
+
+impl fmt::Display for GeoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoError::Io(e) => write!(f, "hive registry io error: {e}"),
+            GeoError::Json(e) => write!(f, "hive registry json error: {e}"),
+            GeoError::InvalidCoordinate { id, lat, lon } => {
+                write!(f, "hive {id}: invalid coordinate ({lat}, {lon})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeoError {}
+
+impl From<std::io::Error> for GeoError {
+    fn from(e: std::io::Error) -> Self {
+        GeoError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GeoError {
+    fn from(e: serde_json::Error) -> Self {
+        GeoError::Json(e)
+    }
+}
+
+/// Known hive sites, loadable from JSON:
+/// `{"hives": [{"id": "HIVE-PHX-01", "lat": 33.45, "lon": -112.07}]}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HiveRegistry {
+    pub hives: Vec<Hive>,
+}
+
+impl HiveRegistry {
+    /// Rejects hives with out-of-range or non-finite coordinates.
+    pub fn new(hives: Vec<Hive>) -> Result<Self, GeoError> {
+        if let Some(bad) = hives.iter().find(|h| !h.position.is_valid()) {
+            return Err(GeoError::InvalidCoordinate {
+                id: bad.id.clone(),
+                lat: bad.position.lat,
+                lon: bad.position.lon,
+            });
+        }
+        Ok(HiveRegistry { hives })
+    }
+
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self, GeoError> {
+        let raw: HiveRegistry = serde_json::from_reader(reader)?;
+        HiveRegistry::new(raw.hives)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, GeoError> {
+        HiveRegistry::from_json_reader(File::open(path)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hives.is_empty()
+    }
+
+    /// Closest hive to (`lat`, `lon`) and its distance in m; `None` for an
+    /// empty registry or an invalid position.
+    pub fn nearest_hive(&self, lat: f64, lon: f64) -> Option<(&str, f64)> {
+        let here = LatLon::new(lat, lon);
+        if !here.is_valid() {
+            return None;
+        }
+        self.hives
+            .iter()
+            .map(|h| (h.id.as_str(), here.distance_m(&h.position)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Relative tolerance for reference distances
+    fn assert_near(actual: f64, expected: f64, rel: f64) {
+        assert!(
+            (actual - expected).abs() <= rel * expected,
+            "expected {expected} m, got {actual} m"
+        );
+    }
+
+    #[test]
+    fn distances_match_reference_values() {
+        // One arc-minute of latitude is one nautical mile (1852 m) to
+        // within the sphere's 0.5% ellipsoid error.
+        let a = LatLon::new(33.45, -112.07);
+        let b = LatLon::new(33.45 + 1.0 / 60.0, -112.07);
+        assert_near(haversine_m(a, b), 1852.0, 0.005);
+
+        // 0.01 degree of longitude at Phoenix's latitude: the meridian arc
+        // scaled by cos(lat).
+        let c = LatLon::new(33.45, -112.06);
+        let arc = EARTH_RADIUS_M * 0.01_f64.to_radians() * 33.45_f64.to_radians().cos();
+        assert_near(a.distance_m(&c), arc, 1e-6);
+
+        // London to Paris, 343.5 km.
+        let london = LatLon::new(51.5074, -0.1278);
+        let paris = LatLon::new(48.8566, 2.3522);
+        assert_near(london.distance_m(&paris), 343_500.0, 0.005);
+
+        assert_eq!(haversine_m(a, a), 0.0);
+    }
+
+    #[test]
+    fn nearest_hive_picks_the_closest_site() {
+        let registry =
+            HiveRegistry::from_json_reader(&include_bytes!("../fixtures/hives_phoenix.json")[..])
+                .unwrap();
+        assert_eq!(registry.hives.len(), 3);
+
+        let (id, d) = registry.nearest_hive(33.4500, -112.0700).unwrap();
+        assert_eq!(id, "HIVE-PHX-01");
+        assert!(d < 300.0, "{d}");
+        let (id, _) = registry.nearest_hive(33.4260, -111.9420).unwrap();
+        assert_eq!(id, "HIVE-TEMPE-01");
+
+        assert_eq!(registry.nearest_hive(f64::NAN, -112.0), None);
+        assert_eq!(HiveRegistry::default().nearest_hive(33.45, -112.07), None);
+    }
+
+    #[test]
+    fn invalid_hive_coordinates_are_rejected() {
+        let json = r#"{"hives": [{"id": "H1", "lat": 95.0, "lon": -112.0}]}"#;
+        let err = HiveRegistry::from_json_reader(json.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "hive H1: invalid coordinate (95, -112)");
+    }
+
+    #[test]
+    fn proximity_falls_off_across_the_foraging_range() {
+        assert_eq!(hive_proximity(0.0), 1.0);
+        assert_eq!(hive_proximity(FORAGE_CORE_M), 1.0);
+        assert_eq!(hive_proximity(1_750.0), 0.5);
+        assert_eq!(hive_proximity(FORAGE_RANGE_M), 0.0);
+        assert_eq!(hive_proximity(f64::NAN), 0.0);
+    }
+}
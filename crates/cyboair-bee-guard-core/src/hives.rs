@@ -0,0 +1,473 @@
+//! Several hives per corridor.
+//!
+//! Each node is guarded by the hives its microspace touches: those named in
+//! its `hive_id` column, else every positioned hive within
+//! [`FORAGE_RANGE_M`] of its coordinates, else the nearest one. Nodes
+//! without either touch every hive, which reduces to the single-hive
+//! corridor when only one is loaded. Bee karma is totalled per hive, so a
+//! collapsing colony is not averaged away by healthy neighbors.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use cyboair_corridor_safety::config::ConfigError;
+use cyboair_corridor_safety::loader::{read_records, LoadError};
+use serde::Deserialize;
+
+use crate::geo::{Hive, HiveRegistry, LatLon, FORAGE_RANGE_M};
+use crate::{compute_sbee, residual_risk_ok, BeeContext, NodeState};
+
+/// Columns a hive CSV needs; sbee_min, kref_bee, alpha, lat and lon are
+/// optional.
+pub const HIVE_FILE_COLUMNS: &[&str] = &["hive_id|id", "colony_mass_kg", "colony_mass_baseline_kg"];
+
+/// One hive: its telemetry and, if known, where it stands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiveSite {
+    pub context: BeeContext,
+    pub position: Option<LatLon>,
+}
+
+/// Errors from loading or building a [`HiveSet`].
+#[derive(Debug)]
+pub enum HiveSetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Csv(LoadError),
+    Empty,
+    DuplicateId(String),
+    Invalid {
+        hive_id: String,
+        source: ConfigError,
+    },
+    InvalidPosition {
+        hive_id: String,
+        lat: Option<f64>,
+        lon: Option<f64>,
+    },
+}
+
+impl fmt::Display for HiveSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveSetError::Io(e) => write!(f, "hive file io error: {e}"),
+            HiveSetError::Json(e) => write!(f, "hive file json error: {e}"),
+            HiveSetError::Csv(e) => write!(f, "hive file csv error: {e}"),
+            HiveSetError::Empty => f.write_str("hive file lists no hives"),
+            HiveSetError::DuplicateId(id) => write!(f, "hive {id} is listed twice"),
+            HiveSetError::Invalid { hive_id, source } => write!(f, "hive {hive_id}: {source}"),
+            HiveSetError::InvalidPosition { hive_id, lat, lon } => write!(
+                f,
+                "hive {hive_id}: position needs both lat and lon in range (got {lat:?}, {lon:?})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HiveSetError {}
+
+impl From<std::io::Error> for HiveSetError {
+    fn from(e: std::io::Error) -> Self {
+        HiveSetError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for HiveSetError {
+    fn from(e: serde_json::Error) -> Self {
+        HiveSetError::Json(e)
+    }
+}
+
+impl From<LoadError> for HiveSetError {
+    fn from(e: LoadError) -> Self {
+        HiveSetError::Csv(e)
+    }
+}
+
+/// A hive as written in JSON or CSV; unset tuning falls back to
+/// [`BeeContext::default`].
+#[derive(Debug, Deserialize)]
+struct HiveRecord {
+    #[serde(alias = "id")]
+    hive_id: String,
+    colony_mass_kg: f64,
+    colony_mass_baseline_kg: f64,
+    #[serde(default)]
+    sbee_min: Option<f64>,
+    #[serde(default)]
+    kref_bee: Option<f64>,
+    #[serde(default)]
+    alpha: Option<f64>,
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HiveFile {
+    hives: Vec<HiveRecord>,
+}
+
+impl HiveRecord {
+    fn into_site(self) -> Result<HiveSite, HiveSetError> {
+        let defaults = BeeContext::default();
+        let position = match (self.lat, self.lon) {
+            (None, None) => None,
+            (Some(lat), Some(lon)) if LatLon::new(lat, lon).is_valid() => {
+                Some(LatLon::new(lat, lon))
+            }
+            (lat, lon) => {
+                return Err(HiveSetError::InvalidPosition {
+                    hive_id: self.hive_id,
+                    lat,
+                    lon,
+                })
+            }
+        };
+        Ok(HiveSite {
+            context: BeeContext {
+                hive_id: self.hive_id,
+                colony_mass_kg: self.colony_mass_kg,
+                colony_mass_baseline_kg: self.colony_mass_baseline_kg,
+                sbee_min: self.sbee_min.unwrap_or(defaults.sbee_min),
+                kref_bee: self.kref_bee.unwrap_or(defaults.kref_bee),
+                alpha: self.alpha.unwrap_or(defaults.alpha),
+            },
+            position,
+        })
+    }
+}
+
+/// The hives guarding one corridor; never empty, ids unique.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiveSet {
+    sites: Vec<HiveSite>,
+}
+
+impl HiveSet {
+    pub fn new(sites: Vec<HiveSite>) -> Result<Self, HiveSetError> {
+        if sites.is_empty() {
+            return Err(HiveSetError::Empty);
+        }
+        for (i, site) in sites.iter().enumerate() {
+            let id = &site.context.hive_id;
+            if sites[..i].iter().any(|s| &s.context.hive_id == id) {
+                return Err(HiveSetError::DuplicateId(id.clone()));
+            }
+            site.context
+                .validate()
+                .map_err(|source| HiveSetError::Invalid {
+                    hive_id: id.clone(),
+                    source,
+                })?;
+        }
+        Ok(HiveSet { sites })
+    }
+
+    /// One unpositioned hive guarding every node.
+    pub fn single(context: BeeContext) -> Self {
+        HiveSet {
+            sites: vec![HiveSite {
+                context,
+                position: None,
+            }],
+        }
+    }
+
+    /// `{"hives": [{"hive_id": ..., "colony_mass_kg": ..., ...}]}`; a
+    /// [`HiveRegistry`] file with colony masses added also loads.
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self, HiveSetError> {
+        let file: HiveFile = serde_json::from_reader(reader)?;
+        let sites = file
+            .hives
+            .into_iter()
+            .map(HiveRecord::into_site)
+            .collect::<Result<_, _>>()?;
+        HiveSet::new(sites)
+    }
+
+    /// Header-mapped CSV; see [`HIVE_FILE_COLUMNS`].
+    pub fn from_csv_reader<R: Read>(reader: R) -> Result<Self, HiveSetError> {
+        let records: Vec<HiveRecord> = read_records(reader, HIVE_FILE_COLUMNS)?;
+        let sites = records
+            .into_iter()
+            .map(HiveRecord::into_site)
+            .collect::<Result<_, _>>()?;
+        HiveSet::new(sites)
+    }
+
+    /// CSV for a `.csv` extension, JSON otherwise.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, HiveSetError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+        {
+            HiveSet::from_csv_reader(file)
+        } else {
+            HiveSet::from_json_reader(file)
+        }
+    }
+
+    pub fn sites(&self) -> &[HiveSite] {
+        &self.sites
+    }
+
+    /// The positioned hives, for [`crate::locate_hive`].
+    pub fn registry(&self) -> HiveRegistry {
+        HiveRegistry {
+            hives: self
+                .sites
+                .iter()
+                .filter_map(|s| {
+                    s.position.map(|position| Hive {
+                        id: s.context.hive_id.clone(),
+                        position,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Indices of the hives whose foraging range `node` falls in.
+    pub fn touching(&self, node: &NodeState) -> Vec<usize> {
+        if let Some(ids) = &node.row.hive_id {
+            let named: Vec<usize> = ids
+                .split(';')
+                .map(str::trim)
+                .filter_map(|id| self.sites.iter().position(|s| s.context.hive_id == id))
+                .collect();
+            if !named.is_empty() {
+                return named;
+            }
+        }
+        if let Some(here) = node.row.position() {
+            let distances: Vec<(usize, f64)> = self
+                .sites
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.position.map(|p| (i, here.distance_m(&p))))
+                .collect();
+            let in_range: Vec<usize> = distances
+                .iter()
+                .filter(|(_, d)| *d <= FORAGE_RANGE_M)
+                .map(|(i, _)| *i)
+                .collect();
+            if !in_range.is_empty() {
+                return in_range;
+            }
+            if let Some((i, _)) = distances.iter().min_by(|a, b| a.1.total_cmp(&b.1)) {
+                return vec![*i];
+            }
+        }
+        (0..self.sites.len()).collect()
+    }
+}
+
+/// One hive's state after [`assess_hives`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiveStatus {
+    pub hive_id: String,
+    /// Bee karma of every node touching this hive.
+    pub bee_karma_total: f64,
+    pub sbee: f64,
+    pub residual_risk_ok: bool,
+}
+
+impl HiveStatus {
+    /// Residual risk holds and S_bee meets the hive's minimum.
+    pub fn ok(&self, context: &BeeContext) -> bool {
+        self.residual_risk_ok && self.sbee >= context.sbee_min
+    }
+}
+
+/// Totals bee karma and S_bee per hive, then gives every node the minimum
+/// S_bee of the hives it touches and the hive that binds it: the worst
+/// failing hive if any fails, else the one with the lowest S_bee. A node is
+/// `hive_ok` only if all its hives are. Expects [`crate::update_karma`] to
+/// have run this step.
+pub fn assess_hives(nodes: &mut [NodeState], hives: &HiveSet) -> Vec<HiveStatus> {
+    let touching: Vec<Vec<usize>> = nodes.iter().map(|n| hives.touching(n)).collect();
+    let mut totals = vec![0.0; hives.sites.len()];
+    for (node, touched) in nodes.iter().zip(&touching) {
+        for &h in touched {
+            totals[h] += node.bee_karma_bytes;
+        }
+    }
+    let statuses: Vec<HiveStatus> = hives
+        .sites
+        .iter()
+        .zip(totals)
+        .map(|(site, total)| HiveStatus {
+            hive_id: site.context.hive_id.clone(),
+            bee_karma_total: total,
+            sbee: compute_sbee(total, site.context.kref_bee, site.context.alpha),
+            residual_risk_ok: residual_risk_ok(&site.context),
+        })
+        .collect();
+
+    for (node, touched) in nodes.iter_mut().zip(&touching) {
+        let ok = |h: usize| statuses[h].ok(&hives.sites[h].context);
+        let binding = touched
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                ok(a)
+                    .cmp(&ok(b))
+                    .then(statuses[a].sbee.total_cmp(&statuses[b].sbee))
+            })
+            .expect("a hive set is never empty");
+        node.sbee = touched
+            .iter()
+            .map(|&h| statuses[h].sbee)
+            .fold(f64::INFINITY, f64::min);
+        node.binding_hive = Some(statuses[binding].hive_id.clone());
+        node.hive_ok = touched.iter().all(|&h| ok(h));
+    }
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_rows, update_duty_hive, update_karma, Conditions, HiveGains, HIVE_COLUMNS};
+
+    const HIVES: &str = "hive_id,colony_mass_kg,colony_mass_baseline_kg,sbee_min,lat,lon
+HIVE-A,21.5,22.0,0.0,33.4500,-112.0800
+HIVE-B,17.0,22.0,0.0,33.4500,-112.0600
+HIVE-C,22.0,22.0,0.0,33.5500,-112.0700
+";
+
+    fn nodes(positions: &[(&str, f64, f64)]) -> Vec<NodeState> {
+        let mut csv = String::from(
+            "machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,\
+             lambda_hazard,beta_nb_per_kg,lat,lon\n",
+        );
+        for (id, lat, lon) in positions {
+            csv += &format!(
+                "{id},ApiaryCanopy,Phoenix-North,PM2.5,30,20,ug/m3,0.8,3600,3.5,5.0e8,{lat},{lon}\n"
+            );
+        }
+        let mut nodes: Vec<NodeState> = read_rows(csv.as_bytes(), HIVE_COLUMNS)
+            .unwrap()
+            .into_iter()
+            .map(NodeState::new)
+            .collect();
+        for node in nodes.iter_mut() {
+            update_karma(node, &Conditions::default()).unwrap();
+        }
+        nodes
+    }
+
+    #[test]
+    fn node_between_two_hives_is_bound_by_the_failing_one() {
+        let hives = HiveSet::from_csv_reader(HIVES.as_bytes()).unwrap();
+        let mut nodes = nodes(&[
+            // ~930 m from both A and B
+            ("BETWEEN", 33.4500, -112.0700),
+            // ~460 m from A, ~1.4 km from B: in both ranges
+            ("NEAR-A", 33.4500, -112.0750),
+            // only C is in range
+            ("NORTH", 33.5480, -112.0700),
+        ]);
+        assert_eq!(hives.touching(&nodes[0]), vec![0, 1]);
+        assert_eq!(hives.touching(&nodes[2]), vec![2]);
+
+        let statuses = assess_hives(&mut nodes, &hives);
+        // B lost 23% of its colony mass; A and C are within 10%.
+        assert!(statuses[0].residual_risk_ok);
+        assert!(!statuses[1].residual_risk_ok);
+        assert!(statuses[2].residual_risk_ok);
+        // A and B each collect karma from BETWEEN and NEAR-A; C only NORTH.
+        let k = nodes[0].bee_karma_bytes;
+        assert!((statuses[0].bee_karma_total - 2.0 * k).abs() < 1e-9);
+        assert!((statuses[1].bee_karma_total - 2.0 * k).abs() < 1e-9);
+        assert!((statuses[2].bee_karma_total - k).abs() < 1e-9);
+
+        assert_eq!(nodes[0].binding_hive.as_deref(), Some("HIVE-B"));
+        assert!(!nodes[0].hive_ok);
+        assert_eq!(nodes[2].binding_hive.as_deref(), Some("HIVE-C"));
+        assert!(nodes[2].hive_ok);
+        assert_eq!(nodes[0].sbee, statuses[0].sbee.min(statuses[1].sbee));
+
+        // phi_bee flips with the binding hive: same inputs, 2 * eta4 apart.
+        let gains = HiveGains {
+            eta1: 0.0,
+            ..HiveGains::default()
+        };
+        nodes[0].duty_cycle = 0.5;
+        let mut north_like = nodes[0].clone();
+        north_like.hive_ok = true;
+        update_duty_hive(&mut nodes[0], &gains);
+        update_duty_hive(&mut north_like, &gains);
+        assert!((north_like.duty_cycle - nodes[0].duty_cycle - 2.0 * gains.eta4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn explicit_assignment_wins_and_unpositioned_nodes_touch_every_hive() {
+        let hives = HiveSet::from_csv_reader(HIVES.as_bytes()).unwrap();
+        let mut nodes = nodes(&[("BETWEEN", 33.4500, -112.0700)]);
+        nodes[0].row.hive_id = Some("HIVE-C; HIVE-A".into());
+        assert_eq!(hives.touching(&nodes[0]), vec![2, 0]);
+
+        nodes[0].row.hive_id = Some("HIVE-X".into());
+        assert_eq!(hives.touching(&nodes[0]), vec![0, 1]);
+
+        nodes[0].row.hive_id = None;
+        nodes[0].row.lat = None;
+        assert_eq!(hives.touching(&nodes[0]), vec![0, 1, 2]);
+
+        // Far from every hive: the nearest still guards it.
+        nodes[0].row.lat = Some(33.30);
+        nodes[0].row.lon = Some(-112.08);
+        assert_eq!(hives.touching(&nodes[0]), vec![0]);
+    }
+
+    #[test]
+    fn json_and_csv_hive_files_agree() {
+        let json = r#"{"hives": [
+            {"hive_id": "HIVE-A", "colony_mass_kg": 21.5, "colony_mass_baseline_kg": 22.0,
+             "sbee_min": 0.0, "lat": 33.45, "lon": -112.08},
+            {"id": "HIVE-B", "colony_mass_kg": 17.0, "colony_mass_baseline_kg": 22.0,
+             "sbee_min": 0.0, "lat": 33.45, "lon": -112.06},
+            {"hive_id": "HIVE-C", "colony_mass_kg": 22.0, "colony_mass_baseline_kg": 22.0,
+             "sbee_min": 0.0, "lat": 33.55, "lon": -112.07}
+        ]}"#;
+        let from_json = HiveSet::from_json_reader(json.as_bytes()).unwrap();
+        let from_csv = HiveSet::from_csv_reader(HIVES.as_bytes()).unwrap();
+        assert_eq!(from_json, from_csv);
+        assert_eq!(from_csv.sites()[1].context.kref_bee, 1.0e12);
+        assert_eq!(from_csv.registry().hives.len(), 3);
+    }
+
+    #[test]
+    fn bad_hive_files_are_rejected() {
+        let dup = format!("{HIVES}HIVE-A,22.0,22.0,0.8,,\n");
+        assert_eq!(
+            HiveSet::from_csv_reader(dup.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "hive HIVE-A is listed twice"
+        );
+        let half = "hive_id,colony_mass_kg,colony_mass_baseline_kg,lat\nH,20,22,33.4\n";
+        assert!(matches!(
+            HiveSet::from_csv_reader(half.as_bytes()),
+            Err(HiveSetError::InvalidPosition { .. })
+        ));
+        let bad = "hive_id,colony_mass_kg,colony_mass_baseline_kg,sbee_min\nH,20,22,1.5\n";
+        assert_eq!(
+            HiveSet::from_csv_reader(bad.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "hive H: invalid config: hive.sbee_min: must be in [0, 1], got 1.5"
+        );
+        assert!(matches!(
+            HiveSet::from_json_reader(r#"{"hives": []}"#.as_bytes()),
+            Err(HiveSetError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,862 @@
+//! Shared model for the bee-guard binaries.
+//!
+//! `crates/cyboair-bee-guard` (hive corridor, Eq. 6) and
+//! `cybo_air_bee_guard` (bee microspaces) read the same shard rows and
+//! compute the same mass and karma; only their duty-cycle laws differ, and
+//! both laws live here.
+//!
+//! Where the two tools used to disagree, this crate settles it:
+//!
+//! - **ppb conversion.** Mixing ratios convert with the ideal-gas form
+//!   `x * P * MW / (R * T)` from
+//!   [`unit_to_kg_factor_at_pressure`], at [`Conditions::pressure_pa`].
+//!   The standard-pressure shortcut is the special case
+//!   `pressure_pa = P_STANDARD_PA`, which is the default.
+//! - **Mass.** `max(cin - cout, 0) * alpha * Q * dt`; a node that adds
+//!   pollutant removes nothing rather than negative mass. Mass, both
+//!   karmas and S_bee delegate to the reference formulas in
+//!   [`ceim`](cyboair_corridor_safety::ceim).
+//! - **Bee karma.** λ_bee and β_bee come from the per-pollutant tables in
+//!   [`Pollutant`]; a row flagged as a bee microspace additionally scales
+//!   by its `bee_weight`. The row's own `lambda_hazard` and
+//!   `beta_nb_per_kg` feed air karma only.
+//!
+//! Hive proximity comes from coordinates when a row has `lat`/`lon` and a
+//! [`HiveRegistry`] is loaded (see [`locate_hive`]); otherwise both duty
+//! laws fall back to matching site names in `location`. A corridor may be
+//! guarded by several hives; see [`hives`]. Absolute, seasonal exclusion
+//! zones around hives belong to the corridor envelope; see [`exclusion`].
+//!
+//! Both binaries take their run configuration from [`config`] and write
+//! telemetry through [`output`], so they only declare their own flags and
+//! record fields.
+
+use std::io::Read;
+
+use cyboair_corridor_safety::ceim;
+use cyboair_corridor_safety::config::ConfigError;
+use cyboair_corridor_safety::loader::{read_records, LoadError};
+use cyboair_corridor_safety::merge::{MergeFields, MergeRow};
+use cyboair_corridor_safety::pollutant::{Pollutant, BEE_BETA_UNLISTED, BEE_LAMBDA_UNLISTED};
+use cyboair_corridor_safety::units::{unit_to_kg_factor_at_pressure, UnitError, P_STANDARD_PA};
+use serde::{Deserialize, Serialize};
+
+pub mod config;
+pub mod exclusion;
+pub mod geo;
+pub mod hives;
+pub mod output;
+
+pub use exclusion::{
+    ExclusionConfig, HiveExclusionEnvelope, MonthDay, NodePositions, SeasonalRadius,
+};
+pub use geo::{HiveRegistry, LatLon};
+pub use hives::{assess_hives, HiveSet, HiveSite, HiveStatus};
+
+/// One shard row. The bee columns are optional so hive-corridor shards
+/// without them still load; see [`MICROSPACE_COLUMNS`] to require them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Row {
+    pub machine_id: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub location: String,
+    pub pollutant: String,
+    pub cin: f64,
+    pub cout: f64,
+    pub unit: String,
+    pub airflow_m3_per_s: f64,
+    pub period_s: f64,
+    pub lambda_hazard: f64,
+    pub beta_nb_per_kg: f64,
+    #[serde(default, alias = "ecoimpactscore")]
+    pub ecoimpact_score: Option<f64>,
+    /// 1 if the node sits in a bee foraging microspace.
+    #[serde(default)]
+    pub bee_flag: u8,
+    /// Additional hazard multiplier for bees, applied when `bee_flag` is 1.
+    #[serde(default = "unit_weight")]
+    pub bee_weight: f64,
+    #[serde(default)]
+    pub notes: String,
+    /// Node position, decimal degrees; both or neither.
+    #[serde(default)]
+    pub lat: Option<f64>,
+    #[serde(default)]
+    pub lon: Option<f64>,
+    /// Hives explicitly guarding this node, `;`-separated.
+    #[serde(default)]
+    pub hive_id: Option<String>,
+    /// Start of the row's `period_s` window, for merging co-located rows.
+    #[serde(default)]
+    pub window_start_unix_ms: Option<u64>,
+}
+
+fn unit_weight() -> f64 {
+    1.0
+}
+
+fn full_sbee() -> f64 {
+    1.0
+}
+
+impl Row {
+    pub fn in_bee_microspace(&self) -> bool {
+        self.bee_flag == 1
+    }
+
+    /// The row's coordinates, if both are present and in range.
+    pub fn position(&self) -> Option<LatLon> {
+        let p = LatLon::new(self.lat?, self.lon?);
+        p.is_valid().then_some(p)
+    }
+}
+
+impl MergeRow for Row {
+    fn corridor_fields(&self) -> MergeFields<'_> {
+        MergeFields {
+            machine_id: &self.machine_id,
+            pollutant: &self.pollutant,
+            unit: &self.unit,
+            cin: self.cin,
+            cout: self.cout,
+            airflow_m3_per_s: self.airflow_m3_per_s,
+            period_s: self.period_s,
+        }
+    }
+
+    fn window_start_unix_ms(&self) -> Option<u64> {
+        self.window_start_unix_ms
+    }
+
+    fn set_merged(&mut self, cin: f64, cout: f64, period_s: f64, start: Option<u64>) {
+        self.cin = cin;
+        self.cout = cout;
+        self.period_s = period_s;
+        self.window_start_unix_ms = start;
+    }
+}
+
+/// Columns every shard needs; anything else is optional.
+pub const HIVE_COLUMNS: &[&str] = &[
+    "machine_id",
+    "type",
+    "location",
+    "pollutant",
+    "cin",
+    "cout",
+    "unit",
+    "airflow_m3_per_s",
+    "period_s",
+    "lambda_hazard",
+    "beta_nb_per_kg",
+];
+
+/// [`HIVE_COLUMNS`] plus the eco-impact and bee columns.
+pub const MICROSPACE_COLUMNS: &[&str] = &[
+    "machine_id",
+    "type",
+    "location",
+    "pollutant",
+    "cin",
+    "cout",
+    "unit",
+    "airflow_m3_per_s",
+    "period_s",
+    "lambda_hazard",
+    "beta_nb_per_kg",
+    "ecoimpact_score|ecoimpactscore",
+    "bee_flag",
+    "bee_weight",
+];
+
+/// Reads header-mapped rows, requiring the `required` columns.
+pub fn read_rows<R: Read>(reader: R, required: &[&str]) -> Result<Vec<Row>, LoadError> {
+    read_records(reader, required)
+}
+
+/// Per-node state carried between steps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeState {
+    pub row: Row,
+    pub mass_kg: f64,
+    #[serde(default)]
+    pub air_karma_bytes: f64,
+    #[serde(alias = "karma_bee")]
+    pub bee_karma_bytes: f64,
+    pub duty_cycle: f64,
+    /// Lowest S_bee among the hives this node touches, set by
+    /// [`assess_hives`].
+    #[serde(default = "full_sbee")]
+    pub sbee: f64,
+    #[serde(default)]
+    pub emf_score: f64,
+    /// Nearest registered hive and its distance, set by [`locate_hive`].
+    #[serde(default)]
+    pub nearest_hive: Option<String>,
+    #[serde(default)]
+    pub distance_from_hive_m: Option<f64>,
+    /// The hive that decides this node's bee-safety term, and whether all
+    /// the hives it touches pass; set by [`assess_hives`].
+    #[serde(default)]
+    pub binding_hive: Option<String>,
+    #[serde(default)]
+    pub hive_ok: bool,
+}
+
+impl NodeState {
+    pub fn new(row: Row) -> Self {
+        NodeState {
+            row,
+            mass_kg: 0.0,
+            air_karma_bytes: 0.0,
+            bee_karma_bytes: 0.0,
+            duty_cycle: 0.0,
+            sbee: 1.0,
+            emf_score: 0.0,
+            nearest_hive: None,
+            distance_from_hive_m: None,
+            binding_hive: None,
+            hive_ok: false,
+        }
+    }
+}
+
+/// Air conditions for unit conversion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Conditions {
+    pub temperature_k: f64,
+    pub pressure_pa: f64,
+    pub molar_mass_kg_per_mol: f64,
+}
+
+impl Default for Conditions {
+    /// Phoenix summer at standard pressure, ozone surrogate MW.
+    fn default() -> Self {
+        Conditions {
+            temperature_k: 310.0,
+            pressure_pa: P_STANDARD_PA,
+            molar_mass_kg_per_mol: 0.048,
+        }
+    }
+}
+
+impl Conditions {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        positive("conditions.temperature_k", self.temperature_k)?;
+        positive("conditions.pressure_pa", self.pressure_pa)?;
+        positive(
+            "conditions.molar_mass_kg_per_mol",
+            self.molar_mass_kg_per_mol,
+        )
+    }
+}
+
+/// Eq. 2 mass balance M_j,h, kg.
+pub fn compute_mass_kg(row: &Row, conditions: &Conditions) -> Result<f64, UnitError> {
+    let alpha = unit_to_kg_factor_at_pressure(
+        &row.unit,
+        conditions.temperature_k,
+        conditions.pressure_pa,
+        conditions.molar_mass_kg_per_mol,
+    )?;
+    Ok(ceim::mass_kg(
+        row.cin,
+        row.cout,
+        alpha,
+        row.airflow_m3_per_s,
+        row.period_s,
+    ))
+}
+
+/// Air NanoKarmaBytes from the row's own hazard weights.
+pub fn compute_air_karmabytes(row: &Row, mass_kg: f64) -> f64 {
+    ceim::karma_bytes(row.lambda_hazard, row.beta_nb_per_kg, mass_kg)
+}
+
+/// Bee hazard tables (λ_bee,j and β_bee,j); unlisted pollutants get the
+/// conservative defaults.
+pub fn bee_hazard_for_pollutant(pollutant: &str) -> (f64, f64) {
+    match pollutant.parse::<Pollutant>() {
+        Ok(p) => (p.bee_lambda(), p.bee_beta_nb_per_kg()),
+        Err(_) => (BEE_LAMBDA_UNLISTED, BEE_BETA_UNLISTED),
+    }
+}
+
+/// Eq. 3 bee karma K_bee,j, scaled by `bee_weight` inside a microspace.
+pub fn compute_bee_karmabytes(row: &Row, mass_kg: f64) -> f64 {
+    let (lambda_bee, beta_bee) = bee_hazard_for_pollutant(&row.pollutant);
+    let weight = if row.in_bee_microspace() {
+        row.bee_weight
+    } else {
+        1.0
+    };
+    ceim::bee_karma_bytes(weight, lambda_bee, beta_bee, mass_kg)
+}
+
+/// Fills in mass and both karmas for the node's current row.
+pub fn update_karma(node: &mut NodeState, conditions: &Conditions) -> Result<(), UnitError> {
+    node.mass_kg = compute_mass_kg(&node.row, conditions)?;
+    node.air_karma_bytes = compute_air_karmabytes(&node.row, node.mass_kg);
+    node.bee_karma_bytes = compute_bee_karmabytes(&node.row, node.mass_kg);
+    Ok(())
+}
+
+/// Eq. 4 normalized S_bee.
+pub fn compute_sbee(bee_karma_tot: f64, kref_bee: f64, alpha: f64) -> f64 {
+    ceim::s_bee(bee_karma_tot, kref_bee, alpha)
+}
+
+/// Hive telemetry the corridor is guarding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BeeContext {
+    pub hive_id: String,
+    pub colony_mass_kg: f64,
+    pub colony_mass_baseline_kg: f64,
+    pub sbee_min: f64,
+    pub kref_bee: f64,
+    pub alpha: f64,
+}
+
+impl Default for BeeContext {
+    /// Example hive; in production, derive from real hive telemetry.
+    fn default() -> Self {
+        BeeContext {
+            hive_id: "HIVE-PHX-01".to_string(),
+            colony_mass_kg: 20.0,
+            colony_mass_baseline_kg: 22.0,
+            sbee_min: 0.8,
+            kref_bee: 1.0e12,
+            alpha: 1.0,
+        }
+    }
+}
+
+impl BeeContext {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.sbee_min) {
+            return Err(invalid(
+                "hive.sbee_min",
+                format!("must be in [0, 1], got {}", self.sbee_min),
+            ));
+        }
+        non_negative("hive.colony_mass_kg", self.colony_mass_kg)?;
+        positive("hive.colony_mass_baseline_kg", self.colony_mass_baseline_kg)?;
+        positive("hive.kref_bee", self.kref_bee)?;
+        finite("hive.alpha", self.alpha)
+    }
+}
+
+/// Residual-risk constraint (EFSA-style): colony mass within 10% of
+/// baseline.
+pub fn residual_risk_ok(ctx: &BeeContext) -> bool {
+    let delta =
+        (ctx.colony_mass_baseline_kg - ctx.colony_mass_kg) / ctx.colony_mass_baseline_kg.max(1e-6);
+    delta <= 0.10
+}
+
+/// Sets the node's nearest hive and distance from its row coordinates;
+/// clears both when the row has none or the registry is empty, so the duty
+/// laws fall back to the location heuristic.
+pub fn locate_hive(node: &mut NodeState, registry: &HiveRegistry) {
+    let nearest = node
+        .row
+        .position()
+        .and_then(|p| registry.nearest_hive(p.lat, p.lon));
+    node.nearest_hive = nearest.map(|(id, _)| id.to_string());
+    node.distance_from_hive_m = nearest.map(|(_, d)| d);
+}
+
+/// Location-name heuristic for the hive-corridor law.
+pub fn geo_weight(location: &str) -> f64 {
+    if location.contains("Apiary") || location.contains("School") {
+        1.0
+    } else if location.contains("Industrial") {
+        0.8
+    } else {
+        0.5
+    }
+}
+
+/// Hive-corridor weight for `node`: 0.5 plus up to 0.5 for hive proximity
+/// when its distance is known, else [`geo_weight`] on the location name.
+pub fn node_geo_weight(node: &NodeState) -> f64 {
+    match node.distance_from_hive_m {
+        Some(d) => 0.5 + 0.5 * geo::hive_proximity(d),
+        None => geo_weight(&node.row.location),
+    }
+}
+
+/// Reference scales and gains for [`update_duty_hive`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HiveGains {
+    pub m_ref: f64,
+    pub k_ref: f64,
+    /// Normalized power cost of running the node.
+    pub c_power: f64,
+    pub eta1: f64,
+    pub eta2: f64,
+    pub eta3: f64,
+    pub eta4: f64,
+    pub eta5: f64,
+}
+
+impl Default for HiveGains {
+    fn default() -> Self {
+        HiveGains {
+            m_ref: 1e-6,
+            k_ref: 1e10,
+            c_power: 0.3,
+            eta1: 0.1,
+            eta2: 0.1,
+            eta3: 0.2,
+            eta4: 0.2,
+            eta5: 0.05,
+        }
+    }
+}
+
+impl HiveGains {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        positive("gains.m_ref", self.m_ref)?;
+        positive("gains.k_ref", self.k_ref)?;
+        finite("gains.c_power", self.c_power)?;
+        finite("gains.eta1", self.eta1)?;
+        finite("gains.eta2", self.eta2)?;
+        finite("gains.eta3", self.eta3)?;
+        finite("gains.eta4", self.eta4)?;
+        finite("gains.eta5", self.eta5)
+    }
+}
+
+/// Eq. 6 bee-aware duty-cycle update for a hive corridor. Expects
+/// [`update_karma`] and [`assess_hives`] to have run this step.
+pub fn update_duty_hive(node: &mut NodeState, gains: &HiveGains) {
+    let phi_bee = if node.hive_ok { 1.0 } else { -1.0 };
+    let wi = node_geo_weight(node);
+
+    let uraw = node.duty_cycle
+        + gains.eta1 * (node.mass_kg / gains.m_ref.max(1e-12))
+        + gains.eta2 * (node.air_karma_bytes / gains.k_ref.max(1.0))
+        + gains.eta3 * wi
+        + gains.eta4 * phi_bee
+        - gains.eta5 * gains.c_power;
+    node.duty_cycle = uraw.clamp(0.0, 1.0);
+}
+
+/// Reference scales and gains for [`update_duty_microspace`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MicrospaceGains {
+    pub m_ref: f64,
+    pub k_ref: f64,
+    pub e_ref_bee: f64,
+    pub eta1: f64,
+    pub eta2: f64,
+    pub eta3: f64,
+    pub eta4: f64,
+}
+
+impl Default for MicrospaceGains {
+    fn default() -> Self {
+        MicrospaceGains {
+            m_ref: 1e-6,
+            k_ref: 1e10,
+            e_ref_bee: 1.0,
+            eta1: 0.1,
+            eta2: 0.1,
+            eta3: 0.2,
+            eta4: 0.05,
+        }
+    }
+}
+
+impl MicrospaceGains {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        positive("gains.m_ref", self.m_ref)?;
+        positive("gains.k_ref", self.k_ref)?;
+        positive("gains.e_ref_bee", self.e_ref_bee)?;
+        finite("gains.eta1", self.eta1)?;
+        finite("gains.eta2", self.eta2)?;
+        finite("gains.eta3", self.eta3)?;
+        finite("gains.eta4", self.eta4)
+    }
+}
+
+/// Local EMF proxy: high-airflow nodes in bee microspaces count as more
+/// EMF-sensitive.
+pub fn emf_score(row: &Row) -> f64 {
+    if row.in_bee_microspace() {
+        (row.airflow_m3_per_s / 3.0).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Bee-microspace duty-cycle update. Expects [`update_karma`] to have run
+/// this step; sets `emf_score` along with the duty cycle.
+pub fn update_duty_microspace(node: &mut NodeState, gains: &MicrospaceGains) {
+    node.emf_score = emf_score(&node.row);
+
+    let mut w_bee = 0.5;
+    if node.row.in_bee_microspace() {
+        w_bee += 0.3;
+    }
+    w_bee += 0.2
+        * match node.distance_from_hive_m {
+            Some(d) => geo::hive_proximity(d),
+            None => {
+                let loc = &node.row.location;
+                let forage_site =
+                    loc.contains("School") || loc.contains("Orchard") || loc.contains("Garden");
+                if forage_site {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+    w_bee -= (node.emf_score / gains.e_ref_bee).min(0.5);
+
+    let u = node.duty_cycle
+        + gains.eta1 * (node.mass_kg / gains.m_ref)
+        + gains.eta2 * (node.bee_karma_bytes / gains.k_ref)
+        + gains.eta3 * w_bee
+        - gains.eta4 * node.emf_score;
+    node.duty_cycle = u.clamp(0.0, 1.0);
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+fn finite(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite, got {v}")))
+    }
+}
+
+fn non_negative(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() && v >= 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite and >= 0, got {v}")))
+    }
+}
+
+fn positive(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() && v > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite and > 0, got {v}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<NodeState> {
+        read_rows(
+            &include_bytes!("../fixtures/corridor_bee.csv")[..],
+            MICROSPACE_COLUMNS,
+        )
+        .unwrap()
+        .into_iter()
+        .map(NodeState::new)
+        .collect()
+    }
+
+    fn hive() -> HiveSet {
+        HiveSet::single(BeeContext {
+            colony_mass_kg: 21.0,
+            ..BeeContext::default()
+        })
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-9 * expected.abs(),
+            "expected {expected:e}, got {actual:e}"
+        );
+    }
+
+    #[test]
+    fn hive_shards_load_without_bee_columns() {
+        let csv = "machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,\
+                   lambda_hazard,beta_nb_per_kg\n\
+                   A,ApiaryCanopy,Phoenix-Apiary-North,PM2.5,30,20,ug/m3,0.8,3600,3.5,5.0e8\n";
+        let rows = read_rows(csv.as_bytes(), HIVE_COLUMNS).unwrap();
+        assert_eq!(rows[0].ecoimpact_score, None);
+        assert!(!rows[0].in_bee_microspace());
+        assert_eq!(rows[0].bee_weight, 1.0);
+        assert_eq!(
+            read_rows(csv.as_bytes(), MICROSPACE_COLUMNS)
+                .err()
+                .unwrap()
+                .to_string(),
+            "missing required columns: ecoimpact_score, bee_flag, bee_weight"
+        );
+    }
+
+    #[test]
+    fn ppb_mass_follows_station_pressure() {
+        let row = fixture().remove(2).row;
+        assert_eq!(row.unit, "ppb");
+        let standard = compute_mass_kg(&row, &Conditions::default()).unwrap();
+        let high = Conditions {
+            pressure_pa: 96_500.0,
+            ..Conditions::default()
+        };
+        let at_altitude = compute_mass_kg(&row, &high).unwrap();
+        assert_close(at_altitude / standard, 96_500.0 / P_STANDARD_PA);
+    }
+
+    #[test]
+    fn fixture_mass_and_karma_are_pinned() {
+        let conditions = Conditions::default();
+        let mut nodes = fixture();
+        for node in nodes.iter_mut() {
+            update_karma(node, &conditions).unwrap();
+        }
+        // (mass kg, air karma, bee karma) per fixture row
+        let expected = [
+            (2.88e-5, 5.04e4, 7.776e4),
+            // BlackCarbon has no bee table entry: unlisted defaults
+            (2.43e-6, 5.103e3, 4.86e2),
+            // 7 ppb O3 at 310 K and standard pressure
+            (
+                2.377554951609993e-5,
+                1.426532970965996e4,
+                2.853065941931992e4,
+            ),
+            // cout > cin: a net source removes nothing
+            (0.0, 0.0, 0.0),
+        ];
+        assert_eq!(nodes.len(), expected.len());
+        for (node, &(mass, air, bee)) in nodes.iter().zip(&expected) {
+            assert_close(node.mass_kg, mass);
+            assert_close(node.air_karma_bytes, air);
+            assert_close(node.bee_karma_bytes, bee);
+        }
+    }
+
+    #[test]
+    fn fixture_duty_laws_are_pinned() {
+        let conditions = Conditions::default();
+        let mut hive_nodes = fixture();
+        for node in hive_nodes.iter_mut() {
+            update_karma(node, &conditions).unwrap();
+        }
+        // One unpositioned hive: every node touches it.
+        let statuses = assess_hives(&mut hive_nodes, &hive());
+        assert_close(statuses[0].sbee, 1.067766537410364e-7);
+        assert!(hive_nodes.iter().all(|n| n.sbee == statuses[0].sbee));
+        let mut micro_nodes = hive_nodes.clone();
+
+        for node in hive_nodes.iter_mut() {
+            update_duty_hive(node, &HiveGains::default());
+        }
+        for node in micro_nodes.iter_mut() {
+            update_duty_microspace(node, &MicrospaceGains::default());
+        }
+        // S_bee is far below sbee_min, so phi_bee = -1 for every node.
+        let hive_duty = [1.0, 0.22800005103000004, 1.0, 0.0];
+        let micro_duty = [1.0, 0.38300000486, 1.0, 0.06];
+        let emf = [0.26666666666666666, 0.0, 0.16666666666666666, 0.8];
+        for (i, node) in hive_nodes.iter().enumerate() {
+            assert_close(node.duty_cycle, hive_duty[i]);
+        }
+        for (i, node) in micro_nodes.iter().enumerate() {
+            assert_close(node.duty_cycle, micro_duty[i]);
+            assert_close(node.emf_score, emf[i]);
+        }
+    }
+
+    #[test]
+    fn defaults_validate_and_bad_values_name_their_field() {
+        assert_eq!(Conditions::default().validate(), Ok(()));
+        assert_eq!(HiveGains::default().validate(), Ok(()));
+        assert_eq!(MicrospaceGains::default().validate(), Ok(()));
+        assert_eq!(BeeContext::default().validate(), Ok(()));
+
+        let field = |r: Result<(), ConfigError>| match r {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            other => panic!("expected invalid field, got {other:?}"),
+        };
+        let gains = HiveGains {
+            eta3: f64::NAN,
+            ..HiveGains::default()
+        };
+        assert_eq!(field(gains.validate()), "gains.eta3");
+        let gains = MicrospaceGains {
+            e_ref_bee: 0.0,
+            ..MicrospaceGains::default()
+        };
+        assert_eq!(field(gains.validate()), "gains.e_ref_bee");
+        let hive = BeeContext {
+            sbee_min: 1.2,
+            ..BeeContext::default()
+        };
+        assert_eq!(field(hive.validate()), "hive.sbee_min");
+    }
+
+    #[test]
+    fn coordinates_replace_the_location_heuristic() {
+        let registry =
+            HiveRegistry::from_json_reader(&include_bytes!("../fixtures/hives_phoenix.json")[..])
+                .unwrap();
+        let mut nodes = fixture();
+
+        // No coordinates: the heuristic still applies.
+        locate_hive(&mut nodes[0], &registry);
+        assert_eq!(nodes[0].distance_from_hive_m, None);
+        assert_eq!(node_geo_weight(&nodes[0]), 1.0);
+
+        // An "Apiary" named site 4 km from any hive gets no proximity bonus.
+        nodes[0].row.lat = Some(33.4150);
+        nodes[0].row.lon = Some(-112.0720);
+        locate_hive(&mut nodes[0], &registry);
+        assert_eq!(nodes[0].nearest_hive.as_deref(), Some("HIVE-PHX-01"));
+        let d = nodes[0].distance_from_hive_m.unwrap();
+        assert!((d - 4003.0).abs() < 5.0, "{d}");
+        assert_eq!(node_geo_weight(&nodes[0]), 0.5);
+
+        // A plainly named site next to a hive gets the full weight.
+        nodes[1].row.lat = Some(33.4512);
+        nodes[1].row.lon = Some(-112.0722);
+        locate_hive(&mut nodes[1], &registry);
+        assert!(nodes[1].distance_from_hive_m.unwrap() < 50.0);
+        assert_eq!(node_geo_weight(&nodes[1]), 1.0);
+
+        // The microspace law uses the same proximity: the Garden site,
+        // placed out of range, loses its 0.2 site bonus. Mass and karma
+        // gains are zeroed so only the weight shows.
+        let gains = MicrospaceGains {
+            eta1: 0.0,
+            eta2: 0.0,
+            ..MicrospaceGains::default()
+        };
+        let mut far = nodes[2].clone();
+        far.row.lat = Some(33.60);
+        far.row.lon = Some(-112.30);
+        locate_hive(&mut far, &registry);
+        assert!(far.distance_from_hive_m.unwrap() > geo::FORAGE_RANGE_M);
+        update_duty_microspace(&mut nodes[2], &gains);
+        update_duty_microspace(&mut far, &gains);
+        assert!((nodes[2].duty_cycle - far.duty_cycle - gains.eta3 * 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn state_without_new_fields_still_loads() {
+        let mut node = fixture().remove(0);
+        node.duty_cycle = 0.4;
+        let mut json: serde_json::Value = serde_json::to_value(&node).unwrap();
+        let obj = json.as_object_mut().unwrap();
+        let karma = obj.remove("bee_karma_bytes").unwrap();
+        obj.insert("karma_bee".into(), karma);
+        obj.remove("sbee");
+        obj.remove("air_karma_bytes");
+        obj.remove("nearest_hive");
+        obj.remove("distance_from_hive_m");
+        obj.remove("binding_hive");
+        obj.remove("hive_ok");
+
+        let resumed: NodeState = serde_json::from_value(json).unwrap();
+        assert_eq!(resumed, node);
+    }
+
+    #[test]
+    fn ceim_entry_points_match_the_reference() {
+        for case in ceim::REFERENCE_ROWS {
+            let row = Row {
+                machine_id: case.name.into(),
+                r#type: "Reference".into(),
+                location: "Reference".into(),
+                pollutant: case.pollutant.as_str().into(),
+                cin: case.cin,
+                cout: case.cout,
+                unit: case.unit.as_str().into(),
+                airflow_m3_per_s: case.airflow_m3_per_s,
+                period_s: case.period_s,
+                lambda_hazard: case.lambda_hazard,
+                beta_nb_per_kg: case.beta_nb_per_kg,
+                ecoimpact_score: None,
+                bee_flag: u8::from(case.bee_weight != 1.0),
+                bee_weight: case.bee_weight,
+                notes: String::new(),
+                lat: None,
+                lon: None,
+                hive_id: None,
+                window_start_unix_ms: None,
+            };
+            let conditions = Conditions {
+                temperature_k: case.temperature_k,
+                pressure_pa: case.pressure_pa,
+                molar_mass_kg_per_mol: case.molar_mass(),
+            };
+            let mut node = NodeState::new(row.clone());
+            update_karma(&mut node, &conditions).unwrap();
+            let name = case.name;
+            ceim::assert_agree(
+                "mass_kg",
+                name,
+                &[
+                    ("ceim::mass_kg", case.mass_kg()),
+                    (
+                        "bee_guard_core::compute_mass_kg",
+                        compute_mass_kg(&row, &conditions).unwrap(),
+                    ),
+                    ("bee_guard_core::update_karma", node.mass_kg),
+                ],
+            );
+            let m = case.mass_kg();
+            ceim::assert_agree(
+                "karma_bytes",
+                name,
+                &[
+                    ("ceim::karma_bytes", case.karma_bytes()),
+                    (
+                        "bee_guard_core::compute_air_karmabytes",
+                        compute_air_karmabytes(&row, m),
+                    ),
+                    ("bee_guard_core::update_karma", node.air_karma_bytes),
+                ],
+            );
+            ceim::assert_agree(
+                "bee_karma_bytes",
+                name,
+                &[
+                    ("ceim::bee_karma_bytes", case.bee_karma_bytes()),
+                    (
+                        "bee_guard_core::compute_bee_karmabytes",
+                        compute_bee_karmabytes(&row, m),
+                    ),
+                    ("bee_guard_core::update_karma", node.bee_karma_bytes),
+                ],
+            );
+        }
+
+        for &(karma, kref, alpha) in ceim::REFERENCE_S_BEE {
+            ceim::assert_agree(
+                "s_bee",
+                &format!("K_bee = {karma:e}, K_ref = {kref:e}, alpha = {alpha}"),
+                &[
+                    ("ceim::s_bee", ceim::s_bee(karma, kref, alpha)),
+                    (
+                        "bee_guard_core::compute_sbee",
+                        compute_sbee(karma, kref, alpha),
+                    ),
+                ],
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Telemetry output for the bee-guard binaries: CSV plus JSON and NDJSON
+//! for ingestion, written to stdout or atomically to a file. Each binary
+//! defines its own record; see [`CsvRecord`].
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// Bump when a record field is renamed or removed; adding fields keeps the
+/// version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    /// One array per step.
+    Json,
+    /// One object per node per step.
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            other => Err(format!(
+                "unknown output format '{other}' (expected csv, json or ndjson)"
+            )),
+        }
+    }
+}
+
+/// A telemetry record's CSV form; JSON formats use its `Serialize`.
+pub trait CsvRecord {
+    fn csv_header() -> String;
+
+    fn csv_record(&self) -> String;
+}
+
+/// `records` in `format`; `csv_header` is ignored by the JSON formats.
+pub fn render<T: Serialize + CsvRecord>(
+    format: OutputFormat,
+    records: &[T],
+    csv_header: bool,
+) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        OutputFormat::Csv => {
+            if csv_header {
+                writeln!(out, "{}", T::csv_header())?;
+            }
+            for t in records {
+                writeln!(out, "{}", t.csv_record())?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut out, records)?;
+            out.push(b'\n');
+        }
+        OutputFormat::Ndjson => {
+            for t in records {
+                serde_json::to_writer(&mut out, t)?;
+                out.push(b'\n');
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Writes `bytes` to a sibling temp file and renames it over `path`, so
+/// readers never see a partial file.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Reading {
+        machine_id: String,
+        duty_cycle: f64,
+    }
+
+    impl CsvRecord for Reading {
+        fn csv_header() -> String {
+            "machine_id,duty_cycle".to_string()
+        }
+
+        fn csv_record(&self) -> String {
+            format!("{},{:.3}", self.machine_id, self.duty_cycle)
+        }
+    }
+
+    fn records() -> Vec<Reading> {
+        vec![
+            Reading {
+                machine_id: "CYB-AIR-APIARY-01".into(),
+                duty_cycle: 0.123_456_789,
+            },
+            Reading {
+                machine_id: "CYB-AIR-APIARY-02".into(),
+                duty_cycle: 1.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn formats_render_every_record() {
+        let records = records();
+        let ndjson =
+            String::from_utf8(render(OutputFormat::Ndjson, &records, true).unwrap()).unwrap();
+        let back: Vec<Reading> = ndjson
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back, records);
+
+        let json = render(OutputFormat::Json, &records, true).unwrap();
+        let back: Vec<Reading> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(back, records);
+
+        let csv = String::from_utf8(render(OutputFormat::Csv, &records, true).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "machine_id,duty_cycle\nCYB-AIR-APIARY-01,0.123\nCYB-AIR-APIARY-02,1.000\n"
+        );
+        let rows = render(OutputFormat::Csv, &records[..1], false).unwrap();
+        assert_eq!(rows, b"CYB-AIR-APIARY-01,0.123\n");
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn file_output_leaves_no_temp_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("bee-guard-core-{}.json", std::process::id()));
+        write_atomic(
+            &path,
+            &render(OutputFormat::Json, &records(), true).unwrap(),
+        )
+        .unwrap();
+        let back: Vec<Reading> = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(back.len(), 2);
+        let tmp = dir.join(format!("bee-guard-core-{}.json.tmp", std::process::id()));
+        assert!(!tmp.exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+// Hive-corridor telemetry records; formats and file output live in
+// cyboair_bee_guard_core::output.
+
+use chrono::{DateTime, Utc};
+use cyboair_bee_guard_core::output::{CsvRecord, SCHEMA_VERSION};
+use cyboair_bee_guard_core::NodeState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeTelemetry {
+    pub schema_version: u32,
+    pub timestamp: DateTime<Utc>,
+    pub machine_id: String,
+    pub location: String,
+    pub pollutant: String,
+    pub mass_kg: f64,
+    pub air_karma_bytes: f64,
+    pub bee_karma_bytes: f64,
+    pub sbee: f64,
+    pub duty_cycle: f64,
+    // Set when the row has coordinates and a hive registry is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_from_hive_m: Option<f64>,
+    // The hive whose S_bee and residual risk set this node's duty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hive_id: Option<String>,
+}
+
+impl NodeTelemetry {
+    pub fn new(node: &NodeState, timestamp: DateTime<Utc>) -> Self {
+        NodeTelemetry {
+            schema_version: SCHEMA_VERSION,
+            timestamp,
+            machine_id: node.row.machine_id.clone(),
+            location: node.row.location.clone(),
+            pollutant: node.row.pollutant.clone(),
+            mass_kg: node.mass_kg,
+            air_karma_bytes: node.air_karma_bytes,
+            bee_karma_bytes: node.bee_karma_bytes,
+            sbee: node.sbee,
+            duty_cycle: node.duty_cycle,
+            distance_from_hive_m: node.distance_from_hive_m,
+            hive_id: node.binding_hive.clone(),
+        }
+    }
+}
+
+impl CsvRecord for NodeTelemetry {
+    fn csv_header() -> String {
+        "machine_id,location,pollutant,mass_kg,air_karmabytes,bee_karmabytes,sbee,duty_cycle,hive_id"
+            .to_string()
+    }
+
+    fn csv_record(&self) -> String {
+        format!(
+            "{},{},{},{:.6e},{:.6e},{:.6e},{:.3},{:.3},{}",
+            self.machine_id,
+            self.location,
+            self.pollutant,
+            self.mass_kg,
+            self.air_karma_bytes,
+            self.bee_karma_bytes,
+            self.sbee,
+            self.duty_cycle,
+            self.hive_id.as_deref().unwrap_or("")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyboair_bee_guard_core::output::{render, OutputFormat};
+    use cyboair_bee_guard_core::Row;
+
+    fn records() -> Vec<NodeTelemetry> {
+        let node = NodeState {
+            row: Row {
+                machine_id: "CYB-AIR-APIARY-01".into(),
+                r#type: "ApiaryCanopy".into(),
+                location: "Phoenix-Apiary-North".into(),
+                pollutant: "PM2.5".into(),
+                cin: 30.0,
+                cout: 20.0,
+                unit: "ug/m3".into(),
+                airflow_m3_per_s: 0.8,
+                period_s: 3600.0,
+                lambda_hazard: 3.5,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: None,
+                bee_flag: 0,
+                bee_weight: 1.0,
+                notes: String::new(),
+                lat: None,
+                lon: None,
+                hive_id: None,
+                window_start_unix_ms: None,
+            },
+            mass_kg: 2.88e-5,
+            air_karma_bytes: 50_400.000_001,
+            bee_karma_bytes: 1.234_567_890_123e4,
+            duty_cycle: 0.987_654_321,
+            sbee: 0.000_123_456_789,
+            emf_score: 0.0,
+            nearest_hive: Some("HIVE-PHX-01".into()),
+            distance_from_hive_m: Some(412.5),
+            binding_hive: Some("HIVE-PHX-01".into()),
+            hive_ok: true,
+        };
+        vec![NodeTelemetry::new(&node, Utc::now())]
+    }
+
+    #[test]
+    fn ndjson_lines_parse_back_at_full_precision() {
+        let records = records();
+        let text =
+            String::from_utf8(render(OutputFormat::Ndjson, &records, true).unwrap()).unwrap();
+        let back: Vec<NodeTelemetry> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back, records);
+        assert_eq!(back[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(back[0].sbee, 0.000_123_456_789);
+    }
+
+    #[test]
+    fn csv_and_json_formats() {
+        let records = records();
+        let csv = String::from_utf8(render(OutputFormat::Csv, &records, true).unwrap()).unwrap();
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",0.000,0.988,HIVE-PHX-01"));
+
+        let json = render(OutputFormat::Json, &records, true).unwrap();
+        let back: Vec<NodeTelemetry> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(back, records);
+    }
+}
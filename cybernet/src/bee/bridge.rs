@@ -0,0 +1,308 @@
+//! Conversions between Beekarma's raw environmental samples and the
+//! stressor state Cybernet agents are gated on, so both sides derive their
+//! hazard indices from one computation.
+
+use cyboair_bee_karma::{
+    compute_h_poll, compute_h_rf, BeeEnvSample, BeerightsPolytope, HazardWeights,
+};
+
+use super::predicates::{corridor_slack, PolytopeError};
+use super::{BeeCorridorPolytope, BeeStressorState};
+
+/// A stressor state or pair of polytopes the bridge cannot reconcile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeError {
+    /// `field` is negative, non-finite or above 1.
+    OutOfRange { field: &'static str, value: f64 },
+    /// `field` sits at the ceiling of its index, so the raw reading behind
+    /// it is unknown.
+    Saturated { field: &'static str },
+    /// The corridor polytope cannot be evaluated.
+    Polytope(PolytopeError),
+    /// The two polytopes disagree on the same state by more than the
+    /// tolerance.
+    Inconsistent {
+        beerights_margin: f64,
+        corridor_slack: f64,
+    },
+}
+
+impl std::fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BridgeError::OutOfRange { field, value } => {
+                write!(f, "{field} = {value} is outside [0, 1]")
+            }
+            BridgeError::Saturated { field } => {
+                write!(
+                    f,
+                    "{field} is saturated; the raw reading cannot be recovered"
+                )
+            }
+            BridgeError::Polytope(e) => write!(f, "corridor polytope: {e}"),
+            BridgeError::Inconsistent {
+                beerights_margin,
+                corridor_slack,
+            } => write!(
+                f,
+                "beerights margin {beerights_margin} and corridor slack {corridor_slack} disagree"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+impl From<PolytopeError> for BridgeError {
+    fn from(e: PolytopeError) -> Self {
+        BridgeError::Polytope(e)
+    }
+}
+
+/// Hazard indices from the raw sample. `hq_pest` carries the normalized
+/// pesticide index. The hive-side fields have no counterpart in
+/// `BeeEnvSample` and are left unstressed (zero, full foraging success).
+impl From<(&BeeEnvSample, &HazardWeights)> for BeeStressorState {
+    fn from((env, cfg): (&BeeEnvSample, &HazardWeights)) -> Self {
+        BeeStressorState {
+            hq_pest: env.pesticide_index.clamp(0.0, 1.0),
+            h_rf: compute_h_rf(env, cfg),
+            h_poll: compute_h_poll(env, cfg),
+            d_h_bio: 0.0,
+            varroa_per_100: 0.0,
+            d_thive_c: 0.0,
+            q_forage: 1.0,
+        }
+    }
+}
+
+fn unit(field: &'static str, value: f64) -> Result<f64, BridgeError> {
+    if value.is_finite() && (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(BridgeError::OutOfRange { field, value })
+    }
+}
+
+fn unsaturated(field: &'static str, value: f64) -> Result<f64, BridgeError> {
+    if unit(field, value)? >= 1.0 {
+        Err(BridgeError::Saturated { field })
+    } else {
+        Ok(value)
+    }
+}
+
+/// A raw sample that maps back onto `state` under `cfg`.
+///
+/// Distance and temperature are not part of the stressor state and must be
+/// supplied. `h_poll` is split evenly across O3, AQHI and PM2.5, which is
+/// one of many readings with the same index. Saturated indices are
+/// refused, since any reading at or past the reference would do.
+pub fn env_sample_from_stressors(
+    state: &BeeStressorState,
+    cfg: &HazardWeights,
+    distance_from_hive_m: f64,
+    ambient_temp_c: f64,
+) -> Result<BeeEnvSample, BridgeError> {
+    let h_rf = unsaturated("h_rf", state.h_rf)?;
+    let h_poll = unsaturated("h_poll", state.h_poll)?;
+    let pesticide_index = unit("hq_pest", state.hq_pest)?;
+    Ok(BeeEnvSample {
+        distance_from_hive_m,
+        o3_ugm3: h_poll * cfg.o3_ref_ugm3,
+        aqhi: h_poll * cfg.aqhi_ref,
+        pm25_ugm3: h_poll * cfg.pm25_ref_ugm3,
+        emf_vpm: h_rf * cfg.emf_ref_vpm,
+        pesticide_index,
+        ambient_temp_c,
+    })
+}
+
+/// How far inside each representation a sample sits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BridgeCheck {
+    /// `BeerightsPolytope::margin` at the sample and duty cycle.
+    pub beerights_margin: f64,
+    /// `corridor_slack` of the bridged stressor state.
+    pub corridor_slack: f64,
+}
+
+impl BridgeCheck {
+    /// Both representations admit the sample.
+    pub fn is_inside(&self) -> bool {
+        self.beerights_margin >= 0.0 && self.corridor_slack >= 0.0
+    }
+}
+
+/// Evaluate `env` against both the Beekarma polytope and the corridor
+/// polytope kept for the same site, and fail if one admits what the other
+/// rejects. Verdicts within `tol` of either boundary are not compared, as
+/// the two are measured in different units.
+pub fn check_consistency(
+    env: &BeeEnvSample,
+    cfg: &HazardWeights,
+    duty_cycle: f64,
+    beerights: &BeerightsPolytope,
+    corridor: &BeeCorridorPolytope,
+    tol: f64,
+) -> Result<BridgeCheck, BridgeError> {
+    let x = [
+        env.distance_from_hive_m,
+        env.o3_ugm3,
+        env.emf_vpm,
+        duty_cycle.clamp(0.0, 1.0),
+    ];
+    let check = BridgeCheck {
+        beerights_margin: beerights.margin(&x),
+        corridor_slack: corridor_slack(&BeeStressorState::from((env, cfg)), corridor)?,
+    };
+    let borderline = check.beerights_margin.abs() <= tol || check.corridor_slack.abs() <= tol;
+    if !borderline && (check.beerights_margin >= 0.0) != (check.corridor_slack >= 0.0) {
+        return Err(BridgeError::Inconsistent {
+            beerights_margin: check.beerights_margin,
+            corridor_slack: check.corridor_slack,
+        });
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyboair_bee_karma::LinearConstraint;
+
+    fn sample(o3: f64, emf: f64, pesticide_index: f64) -> BeeEnvSample {
+        BeeEnvSample {
+            distance_from_hive_m: 200.0,
+            o3_ugm3: o3,
+            aqhi: o3 / 80.0 * 7.0,
+            pm25_ugm3: o3 / 80.0 * 25.0,
+            emf_vpm: emf,
+            pesticide_index,
+            ambient_temp_c: 25.0,
+        }
+    }
+
+    /// EMF <= 0.6 V/m and O3 <= 60 ug/m3 in raw units.
+    fn beerights() -> BeerightsPolytope {
+        BeerightsPolytope::new(vec![
+            LinearConstraint {
+                a: [0.0, 0.0, 1.0, 0.0],
+                b: -0.6,
+            },
+            LinearConstraint {
+                a: [0.0, 1.0, 0.0, 0.0],
+                b: -60.0,
+            },
+        ])
+        .unwrap()
+    }
+
+    /// The same limits in index units under default weights, with `h_rf`
+    /// bounded by `rf_bound`.
+    fn corridor(rf_bound: f64) -> BeeCorridorPolytope {
+        BeeCorridorPolytope {
+            a: vec![
+                vec![0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+            ],
+            b: vec![rf_bound, 0.75],
+            kappa_min: 0.4,
+        }
+    }
+
+    #[test]
+    fn samples_round_trip_through_stressor_state() {
+        let cfg = HazardWeights::default();
+        for env in [
+            sample(20.0, 0.1, 0.0),
+            sample(55.0, 0.5, 0.3),
+            sample(79.0, 0.95, 0.9),
+        ] {
+            let state = BeeStressorState::from((&env, &cfg));
+            let back =
+                env_sample_from_stressors(&state, &cfg, env.distance_from_hive_m, 25.0).unwrap();
+            assert!((back.o3_ugm3 - env.o3_ugm3).abs() < 1e-9);
+            assert!((back.aqhi - env.aqhi).abs() < 1e-9);
+            assert!((back.pm25_ugm3 - env.pm25_ugm3).abs() < 1e-9);
+            assert!((back.emf_vpm - env.emf_vpm).abs() < 1e-12);
+            assert_eq!(back.pesticide_index, env.pesticide_index);
+
+            let again = BeeStressorState::from((&back, &cfg));
+            assert!((again.h_poll - state.h_poll).abs() < 1e-12);
+            assert!((again.h_rf - state.h_rf).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn saturated_and_out_of_range_indices_are_refused() {
+        let cfg = HazardWeights::default();
+        let state = BeeStressorState::from((&sample(40.0, 3.0, 0.2), &cfg));
+        assert_eq!(state.h_rf, 1.0);
+        assert_eq!(
+            env_sample_from_stressors(&state, &cfg, 100.0, 25.0).unwrap_err(),
+            BridgeError::Saturated { field: "h_rf" }
+        );
+
+        let mut state = BeeStressorState::from((&sample(40.0, 0.2, 0.2), &cfg));
+        state.hq_pest = f64::NAN;
+        assert!(matches!(
+            env_sample_from_stressors(&state, &cfg, 100.0, 25.0),
+            Err(BridgeError::OutOfRange {
+                field: "hq_pest",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn matching_polytopes_agree() {
+        let cfg = HazardWeights::default();
+        for env in [
+            sample(30.0, 0.2, 0.1),
+            sample(70.0, 0.2, 0.1),
+            sample(30.0, 0.9, 0.1),
+        ] {
+            let check =
+                check_consistency(&env, &cfg, 0.2, &beerights(), &corridor(0.6), 1e-6).unwrap();
+            let raw_inside = env.o3_ugm3 <= 60.0 && env.emf_vpm <= 0.6;
+            assert_eq!(check.is_inside(), raw_inside);
+        }
+    }
+
+    #[test]
+    fn drifted_corridor_is_caught() {
+        let cfg = HazardWeights::default();
+        // The corridor was loosened to h_rf <= 0.9 without touching the
+        // Beekarma polytope; 0.8 V/m now passes one and fails the other.
+        let env = sample(30.0, 0.8, 0.1);
+        match check_consistency(&env, &cfg, 0.2, &beerights(), &corridor(0.9), 1e-6) {
+            Err(BridgeError::Inconsistent {
+                beerights_margin,
+                corridor_slack,
+            }) => {
+                assert!(beerights_margin < 0.0);
+                assert!(corridor_slack > 0.0);
+            }
+            other => panic!("expected inconsistency, got {other:?}"),
+        }
+
+        // Right at the boundary the disagreement is within tolerance.
+        let edge = sample(30.0, 0.6, 0.1);
+        assert!(check_consistency(&edge, &cfg, 0.2, &beerights(), &corridor(0.9), 1e-6).is_ok());
+    }
+
+    #[test]
+    fn malformed_corridor_is_reported() {
+        let cfg = HazardWeights::default();
+        let mut bad = corridor(0.6);
+        bad.b.pop();
+        assert_eq!(
+            check_consistency(&sample(30.0, 0.2, 0.1), &cfg, 0.2, &beerights(), &bad, 1e-6),
+            Err(BridgeError::Polytope(PolytopeError::BoundCountMismatch {
+                rows: 2,
+                bounds: 1
+            }))
+        );
+    }
+}
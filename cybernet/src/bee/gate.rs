@@ -0,0 +1,108 @@
+//! Agents' blood-gate levels for the corridor controller's `AgentGate` and
+//! the governance gate stage.
+
+use std::collections::HashMap;
+
+use cyboair_corridor_safety::GateResolver;
+use uuid::Uuid;
+
+use super::BeeKarmaEnvelope;
+
+/// Gate levels of known agents, and which agent operates each machine.
+///
+/// Resolves an agent UUID directly, or a machine id through the agent
+/// assigned to it. Keys that lead to no envelope resolve to `None`, which
+/// the gate policy treats as the most restrictive level.
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeGates {
+    levels: HashMap<Uuid, u8>,
+    machines: HashMap<String, Uuid>,
+}
+
+impl EnvelopeGates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `env`'s current level, replacing any earlier one.
+    pub fn insert(&mut self, env: &BeeKarmaEnvelope) {
+        self.levels.insert(env.agent_id, env.blood_gate_level);
+    }
+
+    /// `agent_id` operates `machine_id`.
+    pub fn assign(&mut self, machine_id: impl Into<String>, agent_id: Uuid) {
+        self.machines.insert(machine_id.into(), agent_id);
+    }
+
+    pub fn agent_for(&self, machine_id: &str) -> Option<Uuid> {
+        self.machines.get(machine_id).copied()
+    }
+}
+
+impl<'a> FromIterator<&'a BeeKarmaEnvelope> for EnvelopeGates {
+    fn from_iter<I: IntoIterator<Item = &'a BeeKarmaEnvelope>>(envelopes: I) -> Self {
+        let mut gates = EnvelopeGates::new();
+        for env in envelopes {
+            gates.insert(env);
+        }
+        gates
+    }
+}
+
+impl GateResolver for EnvelopeGates {
+    fn gate_level(&self, key: &str) -> Option<u8> {
+        let agent = Uuid::parse_str(key).ok().or_else(|| self.agent_for(key))?;
+        self.levels.get(&agent).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use cyboair_corridor_safety::{AgentGate, GateDutyPolicy};
+
+    use super::super::{BeeKarma, KarmaEventLog};
+    use super::*;
+
+    fn envelope(agent: u128, gate: u8) -> BeeKarmaEnvelope {
+        BeeKarmaEnvelope {
+            agent_id: Uuid::from_u128(agent),
+            corridor_id: Uuid::from_u128(1),
+            kappa: BeeKarma(0.5),
+            last_update: DateTime::<Utc>::UNIX_EPOCH,
+            realized_harm_score: 0.0,
+            predicted_harm_score: 0.0,
+            blood_gate_level: gate,
+            promotion_pending_since: None,
+            revision: 0,
+            events: KarmaEventLog::default(),
+        }
+    }
+
+    #[test]
+    fn machines_and_agents_resolve_to_their_envelope_cap() {
+        let envelopes: Vec<_> = (0..=3)
+            .map(|gate| envelope(10 + u128::from(gate), gate))
+            .collect();
+        let mut gates: EnvelopeGates = envelopes.iter().collect();
+        gates.assign("CYB-AIR-CANOPY-01", Uuid::from_u128(12));
+        gates.assign("CYB-AIR-ORPHAN-07", Uuid::from_u128(99));
+        let gate = AgentGate::new(GateDutyPolicy::default(), gates);
+
+        for (env, cap) in envelopes.iter().zip([0.0, 0.0, 0.4, 1.0]) {
+            assert_eq!(
+                gate.lookup(&env.agent_id.to_string()),
+                (Some(env.blood_gate_level), cap)
+            );
+        }
+        assert_eq!(gate.lookup("CYB-AIR-CANOPY-01"), (Some(2), 0.4));
+        // Unassigned machine, agent without an envelope, unknown UUID.
+        for key in [
+            "CYB-AIR-GHOST-99",
+            "CYB-AIR-ORPHAN-07",
+            &Uuid::from_u128(7).to_string(),
+        ] {
+            assert_eq!(gate.lookup(key), (None, 0.0), "{key}");
+        }
+    }
+}
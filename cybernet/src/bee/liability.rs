@@ -1,8 +1,14 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{BeeKarmaEnvelope, BeeKarma};
+pub mod ingest;
+
+use super::predicates::{gate_level_for, log_karma_event};
+use super::{BeeKarma, BeeKarmaEnvelope, KarmaReason};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BeeTwinSnapshot {
@@ -23,42 +29,358 @@ pub struct HarmAggregation {
     pub predicted_harm: f64,
     pub realized_harm: f64,
     pub delta_liability: f64,
+    /// `HarmPredictor::name` of the model behind `predicted_harm`.
+    pub predictor: String,
+}
+
+/// Weighted twin residual of one snapshot.
+pub fn residual_harm(s: &BeeTwinSnapshot, w_v: f64, w_d: f64, w_w: f64) -> f64 {
+    w_v * (s.vg_pred - s.vg_obs).abs()
+        + w_d * (s.dwv_pred - s.dwv_obs).abs()
+        + w_w * (s.weight_pred - s.weight_obs).abs()
+}
+
+/// Expected harm for a snapshot, so that only harm beyond the expectation
+/// counts toward liability. Intended to wrap ApisRAM/BEEHAVE runs.
+pub trait HarmPredictor {
+    fn name(&self) -> &str;
+    fn predict(&self, snapshot: &BeeTwinSnapshot) -> f64;
+}
+
+/// Expects no residual at all; every deviation is liability.
+pub struct ZeroPredictor;
+
+impl HarmPredictor for ZeroPredictor {
+    fn name(&self) -> &str {
+        "zero"
+    }
+
+    fn predict(&self, _snapshot: &BeeTwinSnapshot) -> f64 {
+        0.0
+    }
+}
+
+/// Expects the moving-average residual of recent history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearBaselinePredictor {
+    pub baseline: f64,
+}
+
+impl LinearBaselinePredictor {
+    /// Fit on the newest `window` snapshots (all of them if fewer). An
+    /// empty history fits a zero baseline.
+    pub fn fit(history: &[BeeTwinSnapshot], w_v: f64, w_d: f64, w_w: f64, window: usize) -> Self {
+        let mut recent: Vec<&BeeTwinSnapshot> = history.iter().collect();
+        recent.sort_by_key(|s| std::cmp::Reverse(s.t));
+        recent.truncate(window.max(1));
+        let baseline = if recent.is_empty() {
+            0.0
+        } else {
+            recent
+                .iter()
+                .map(|s| residual_harm(s, w_v, w_d, w_w))
+                .sum::<f64>()
+                / recent.len() as f64
+        };
+        LinearBaselinePredictor { baseline }
+    }
+}
+
+impl HarmPredictor for LinearBaselinePredictor {
+    fn name(&self) -> &str {
+        "linear-baseline"
+    }
+
+    fn predict(&self, _snapshot: &BeeTwinSnapshot) -> f64 {
+        self.baseline
+    }
+}
+
+/// Why snapshots could not be aggregated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HarmError {
+    EmptyInput,
+    /// Snapshot `index` belongs to `found`, not the first snapshot's corridor.
+    MixedCorridors {
+        expected: Uuid,
+        found: Uuid,
+        index: usize,
+    },
+}
+
+impl std::fmt::Display for HarmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HarmError::EmptyInput => write!(f, "no twin snapshots to aggregate"),
+            HarmError::MixedCorridors {
+                expected,
+                found,
+                index,
+            } => write!(
+                f,
+                "snapshot {index} is for corridor {found}, expected {expected}"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for HarmError {}
+
+/// Average twin residuals for one corridor.
+///
+/// With `recency_half_life` set, each snapshot is weighted by
+/// 0.5^(age / half_life), age measured back from the newest snapshot, so
+/// recent deviations dominate. `None` keeps the plain mean. Predicted harm
+/// comes from `predictor` under the same weighting.
 pub fn aggregate_harm(
     snapshots: &[BeeTwinSnapshot],
     w_v: f64,
     w_d: f64,
     w_w: f64,
-) -> HarmAggregation {
-    assert!(!snapshots.is_empty());
+    recency_half_life: Option<Duration>,
+    predictor: &dyn HarmPredictor,
+) -> Result<HarmAggregation, HarmError> {
+    let first = snapshots.first().ok_or(HarmError::EmptyInput)?;
+    let corridor_id = first.corridor_id;
+    if let Some((index, s)) = snapshots
+        .iter()
+        .enumerate()
+        .find(|(_, s)| s.corridor_id != corridor_id)
+    {
+        return Err(HarmError::MixedCorridors {
+            expected: corridor_id,
+            found: s.corridor_id,
+            index,
+        });
+    }
 
-    let corridor_id = snapshots[0].corridor_id;
+    let realized: Vec<_> = snapshots
+        .iter()
+        .map(|s| residual_harm(s, w_v, w_d, w_w))
+        .collect();
+    Ok(weighted_harm(
+        snapshots,
+        &realized,
+        corridor_id,
+        recency_half_life,
+        predictor,
+    ))
+}
+
+/// Recency-weighted means of `realized[i]` and the predictor's harm for
+/// `snapshots[i]`; `snapshots` is non-empty and single-corridor.
+fn weighted_harm(
+    snapshots: &[BeeTwinSnapshot],
+    realized: &[f64],
+    corridor_id: Uuid,
+    recency_half_life: Option<Duration>,
+    predictor: &dyn HarmPredictor,
+) -> HarmAggregation {
+    let newest = snapshots
+        .iter()
+        .map(|s| s.t)
+        .max()
+        .unwrap_or(snapshots[0].t);
+    let mut weight_sum = 0.0;
     let mut realized_sum = 0.0;
     let mut predicted_sum = 0.0;
 
-    for s in snapshots {
-        let h_real = w_v * (s.vg_pred - s.vg_obs).abs()
-            + w_d * (s.dwv_pred - s.dwv_obs).abs()
-            + w_w * (s.weight_pred - s.weight_obs).abs();
-
-        // For now, predicted harm is simply the model's own expectation of zero residual.
-        // In a full implementation, predicted_harm would be drawn from ApisRAM/BEEHAVE runs.
-        let h_pred = 0.0_f64;
+    for (s, &h_real) in snapshots.iter().zip(realized) {
+        let weight = match recency_half_life {
+            Some(half_life) if !half_life.is_zero() => {
+                let age_s = (newest - s.t).num_milliseconds() as f64 / 1000.0;
+                0.5_f64.powf(age_s / half_life.as_secs_f64())
+            }
+            _ => 1.0,
+        };
+        let h_pred = predictor.predict(s);
 
-        realized_sum += h_real;
-        predicted_sum += h_pred;
+        weight_sum += weight;
+        realized_sum += weight * h_real;
+        predicted_sum += weight * h_pred;
     }
 
-    let n = snapshots.len() as f64;
     HarmAggregation {
         corridor_id,
-        predicted_harm: predicted_sum / n,
-        realized_harm: realized_sum / n,
-        delta_liability: realized_sum / n - predicted_sum / n,
+        predicted_harm: predicted_sum / weight_sum,
+        realized_harm: realized_sum / weight_sum,
+        delta_liability: realized_sum / weight_sum - predicted_sum / weight_sum,
+        predictor: predictor.name().to_string(),
+    }
+}
+
+/// Twin quantity whose residual is smoothed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ResidualMetric {
+    Vg,
+    Dwv,
+    Weight,
+}
+
+/// Exponentially weighted moving average of |pred - obs| per corridor and
+/// metric, so one glitching reading cannot spike realized harm.
+///
+/// Each residual is first capped at `outlier_cap` times the current
+/// average (once that average is positive), then folded in as
+/// alpha * residual + (1 - alpha) * average. The first residual seen for a
+/// key starts its average. State persists across calls, so feed snapshots
+/// oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EwmaSmoother {
+    /// Weight of the newest residual, in (0, 1].
+    pub alpha: f64,
+    /// k: no residual counts for more than k times the running average.
+    pub outlier_cap: f64,
+    averages: HashMap<(Uuid, ResidualMetric), f64>,
+}
+
+impl EwmaSmoother {
+    pub fn new(alpha: f64, outlier_cap: f64) -> Self {
+        EwmaSmoother {
+            alpha: alpha.clamp(f64::EPSILON, 1.0),
+            outlier_cap,
+            averages: HashMap::new(),
+        }
+    }
+
+    /// Fold `residual` into the average for `(corridor_id, metric)` and
+    /// return the new average.
+    pub fn update(&mut self, corridor_id: Uuid, metric: ResidualMetric, residual: f64) -> f64 {
+        let average = match self.averages.get(&(corridor_id, metric)) {
+            None => residual,
+            Some(&avg) => {
+                let capped = if avg > 0.0 {
+                    residual.min(self.outlier_cap * avg)
+                } else {
+                    residual
+                };
+                self.alpha * capped + (1.0 - self.alpha) * avg
+            }
+        };
+        self.averages.insert((corridor_id, metric), average);
+        average
+    }
+
+    pub fn average(&self, corridor_id: Uuid, metric: ResidualMetric) -> Option<f64> {
+        self.averages.get(&(corridor_id, metric)).copied()
     }
 }
 
+/// `aggregate_harm` over smoothed residuals, next to the raw result it
+/// replaces so audits can compare the two.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothedHarm {
+    pub smoothed: HarmAggregation,
+    pub raw: HarmAggregation,
+}
+
+/// `aggregate_harm` with each snapshot's residuals passed through
+/// `smoother` (in timestamp order) before weighting. Predicted harm and
+/// recency weights are unchanged.
+pub fn aggregate_harm_smoothed(
+    snapshots: &[BeeTwinSnapshot],
+    w_v: f64,
+    w_d: f64,
+    w_w: f64,
+    recency_half_life: Option<Duration>,
+    predictor: &dyn HarmPredictor,
+    smoother: &mut EwmaSmoother,
+) -> Result<SmoothedHarm, HarmError> {
+    let raw = aggregate_harm(snapshots, w_v, w_d, w_w, recency_half_life, predictor)?;
+
+    let mut order: Vec<usize> = (0..snapshots.len()).collect();
+    order.sort_by_key(|&i| snapshots[i].t);
+    let mut realized = vec![0.0; snapshots.len()];
+    for i in order {
+        let s = &snapshots[i];
+        let mut smooth = |metric, pred: f64, obs: f64| {
+            smoother.update(s.corridor_id, metric, (pred - obs).abs())
+        };
+        realized[i] = w_v * smooth(ResidualMetric::Vg, s.vg_pred, s.vg_obs)
+            + w_d * smooth(ResidualMetric::Dwv, s.dwv_pred, s.dwv_obs)
+            + w_w * smooth(ResidualMetric::Weight, s.weight_pred, s.weight_obs);
+    }
+    let smoothed = weighted_harm(
+        snapshots,
+        &realized,
+        raw.corridor_id,
+        recency_half_life,
+        predictor,
+    );
+    Ok(SmoothedHarm { smoothed, raw })
+}
+
+/// Residual weights for `aggregate_harm_by_corridor`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HarmWeights {
+    pub w_v: f64,
+    pub w_d: f64,
+    pub w_w: f64,
+}
+
+/// A corridor left out of a batch aggregation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedCorridor {
+    pub corridor_id: Uuid,
+    pub snapshots: usize,
+    pub min_snapshots: usize,
+}
+
+/// Result of `aggregate_harm_by_corridor`; both lists are sorted by
+/// corridor id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorridorHarmReport {
+    pub aggregations: Vec<HarmAggregation>,
+    pub skipped: Vec<SkippedCorridor>,
+}
+
+/// Split a mixed batch by corridor and run `aggregate_harm` on each.
+///
+/// Corridors with fewer than `min_snapshots` snapshots are reported in
+/// `skipped` instead of producing a single-sample liability.
+pub fn aggregate_harm_by_corridor(
+    snapshots: &[BeeTwinSnapshot],
+    weights: &HarmWeights,
+    recency_half_life: Option<Duration>,
+    predictor: &dyn HarmPredictor,
+    min_snapshots: usize,
+) -> CorridorHarmReport {
+    let mut by_corridor: BTreeMap<Uuid, Vec<BeeTwinSnapshot>> = BTreeMap::new();
+    for s in snapshots {
+        by_corridor
+            .entry(s.corridor_id)
+            .or_default()
+            .push(s.clone());
+    }
+
+    let mut report = CorridorHarmReport {
+        aggregations: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (corridor_id, group) in by_corridor {
+        if group.len() < min_snapshots.max(1) {
+            report.skipped.push(SkippedCorridor {
+                corridor_id,
+                snapshots: group.len(),
+                min_snapshots,
+            });
+            continue;
+        }
+        // Groups are non-empty and single-corridor, so this cannot fail.
+        if let Ok(agg) = aggregate_harm(
+            &group,
+            weights.w_v,
+            weights.w_d,
+            weights.w_w,
+            recency_half_life,
+            predictor,
+        ) {
+            report.aggregations.push(agg);
+        }
+    }
+    report
+}
+
 pub fn apply_liability_to_envelope(
     env: &mut BeeKarmaEnvelope,
     harm: &HarmAggregation,
@@ -68,6 +390,7 @@ pub fn apply_liability_to_envelope(
 ) {
     env.predicted_harm_score = harm.predicted_harm;
     env.realized_harm_score = harm.realized_harm;
+    env.revision += 1;
 
     if harm.delta_liability <= warn_threshold {
         return;
@@ -75,24 +398,273 @@ pub fn apply_liability_to_envelope(
 
     let delta_over = harm.delta_liability - warn_threshold;
     let karma_delta = -karma_penalty_scale * delta_over;
+    let before = env.kappa.0;
     let mut k = env.kappa.0 + karma_delta;
     if k < 0.0 {
         k = 0.0;
     }
     env.kappa = BeeKarma(k);
 
-    env.blood_gate_level = if k >= 0.8 {
-        3
-    } else if k >= 0.6 {
-        2
-    } else if k >= 0.4 {
-        1
-    } else {
-        0
-    };
+    // Penalties only ever lower the level; recovery goes through
+    // `BloodGated::regenerate` and its promotion dwell.
+    let implied = gate_level_for(k);
+    if implied <= env.blood_gate_level {
+        env.blood_gate_level = implied;
+        env.promotion_pending_since = None;
+    }
 
     // Liability trigger: if harm is very high, force immediate downgrade.
     if harm.delta_liability >= downgrade_threshold {
         env.blood_gate_level = env.blood_gate_level.saturating_sub(1);
+        env.promotion_pending_since = None;
+    }
+
+    log_karma_event(
+        env,
+        before,
+        KarmaReason::LiabilityPenalty {
+            corridor_id: harm.corridor_id,
+            delta_liability: harm.delta_liability,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(corridor_id: Uuid, hours_ago: i64, vg_obs: f64) -> BeeTwinSnapshot {
+        BeeTwinSnapshot {
+            twin_id: Uuid::nil(),
+            corridor_id,
+            t: DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::days(30)
+                - chrono::Duration::hours(hours_ago),
+            vg_pred: 1.0,
+            vg_obs,
+            dwv_pred: 0.0,
+            dwv_obs: 0.0,
+            weight_pred: 0.0,
+            weight_obs: 0.0,
+        }
+    }
+
+    #[test]
+    fn empty_and_mixed_inputs_are_errors() {
+        assert_eq!(
+            aggregate_harm(&[], 1.0, 1.0, 1.0, None, &ZeroPredictor).unwrap_err(),
+            HarmError::EmptyInput
+        );
+
+        let a = Uuid::from_u128(1);
+        let b = Uuid::from_u128(2);
+        let mixed = [
+            snapshot(a, 0, 1.0),
+            snapshot(a, 1, 1.0),
+            snapshot(b, 2, 1.0),
+        ];
+        assert_eq!(
+            aggregate_harm(&mixed, 1.0, 1.0, 1.0, None, &ZeroPredictor).unwrap_err(),
+            HarmError::MixedCorridors {
+                expected: a,
+                found: b,
+                index: 2
+            }
+        );
+    }
+
+    #[test]
+    fn recent_deviation_dominates_weighted_aggregate() {
+        let c = Uuid::from_u128(7);
+        // A week of clean snapshots, then a fresh 0.8 deviation.
+        let mut snapshots: Vec<_> = (1..=7).map(|d| snapshot(c, 24 * d, 1.0)).collect();
+        snapshots.push(snapshot(c, 0, 0.2));
+
+        let plain = aggregate_harm(&snapshots, 1.0, 0.0, 0.0, None, &ZeroPredictor).unwrap();
+        assert!((plain.realized_harm - 0.1).abs() < 1e-12);
+
+        let weighted = aggregate_harm(
+            &snapshots,
+            1.0,
+            0.0,
+            0.0,
+            Some(Duration::from_secs(12 * 3600)),
+            &ZeroPredictor,
+        )
+        .unwrap();
+        assert!(weighted.realized_harm > 0.5, "{}", weighted.realized_harm);
+        assert_eq!(weighted.delta_liability, weighted.realized_harm);
+
+        // The same deviation a week ago barely registers.
+        let mut stale: Vec<_> = (0..7).map(|d| snapshot(c, 24 * d, 1.0)).collect();
+        stale.push(snapshot(c, 24 * 7, 0.2));
+        let weighted = aggregate_harm(
+            &stale,
+            1.0,
+            0.0,
+            0.0,
+            Some(Duration::from_secs(12 * 3600)),
+            &ZeroPredictor,
+        )
+        .unwrap();
+        assert!(weighted.realized_harm < 0.01);
+    }
+
+    #[test]
+    fn fitted_predictor_absorbs_expected_residual() {
+        let c = Uuid::from_u128(3);
+        // Steady 0.3 residual the twin is known to carry.
+        let history: Vec<_> = (1..=10).map(|h| snapshot(c, h, 0.7)).collect();
+        let current: Vec<_> = (0..3).map(|h| snapshot(c, h, 0.7)).collect();
+
+        let fitted = LinearBaselinePredictor::fit(&history, 1.0, 0.0, 0.0, 5);
+        let agg = aggregate_harm(&current, 1.0, 0.0, 0.0, None, &fitted).unwrap();
+        assert!(agg.delta_liability.abs() < 1e-12);
+        assert_eq!(agg.predictor, "linear-baseline");
+
+        let agg = aggregate_harm(&current, 1.0, 0.0, 0.0, None, &ZeroPredictor).unwrap();
+        assert!((agg.delta_liability - 0.3).abs() < 1e-12);
+        assert_eq!(agg.predictor, "zero");
+    }
+
+    #[test]
+    fn baseline_fit_uses_newest_window() {
+        let c = Uuid::from_u128(3);
+        let mut history: Vec<_> = (10..20).map(|h| snapshot(c, h, 0.0)).collect();
+        history.extend((0..4).map(|h| snapshot(c, h, 0.5)));
+        let fitted = LinearBaselinePredictor::fit(&history, 1.0, 0.0, 0.0, 4);
+        assert!((fitted.baseline - 0.5).abs() < 1e-12);
+        assert_eq!(
+            LinearBaselinePredictor::fit(&[], 1.0, 1.0, 1.0, 4).baseline,
+            0.0
+        );
+    }
+
+    #[test]
+    fn batch_is_split_sorted_and_thin_corridors_skipped() {
+        let (a, b, c) = (
+            Uuid::from_u128(30),
+            Uuid::from_u128(10),
+            Uuid::from_u128(20),
+        );
+        let mut batch = Vec::new();
+        for h in 0..4 {
+            batch.push(snapshot(a, h, 0.9));
+            batch.push(snapshot(b, h, 0.6));
+        }
+        batch.push(snapshot(c, 0, 0.0));
+        batch.push(snapshot(b, 5, 0.6));
+
+        let weights = HarmWeights {
+            w_v: 1.0,
+            w_d: 0.0,
+            w_w: 0.0,
+        };
+        let report = aggregate_harm_by_corridor(&batch, &weights, None, &ZeroPredictor, 3);
+
+        let ids: Vec<_> = report.aggregations.iter().map(|h| h.corridor_id).collect();
+        assert_eq!(ids, vec![b, a]);
+        assert!((report.aggregations[0].realized_harm - 0.4).abs() < 1e-12);
+        assert!((report.aggregations[1].realized_harm - 0.1).abs() < 1e-12);
+        assert_eq!(
+            report.skipped,
+            vec![SkippedCorridor {
+                corridor_id: c,
+                snapshots: 1,
+                min_snapshots: 3
+            }]
+        );
+
+        // Same math as calling aggregate_harm per corridor.
+        let only_b: Vec<_> = batch
+            .iter()
+            .filter(|s| s.corridor_id == b)
+            .cloned()
+            .collect();
+        let direct = aggregate_harm(&only_b, 1.0, 0.0, 0.0, None, &ZeroPredictor).unwrap();
+        assert_eq!(direct.realized_harm, report.aggregations[0].realized_harm);
+    }
+
+    #[test]
+    fn smoothing_absorbs_one_sample_scale_glitch() {
+        let c = Uuid::from_u128(5);
+        // Ten readings 0.1 kg off, then the scale glitches by 3 kg.
+        let mut snapshots: Vec<_> = (1..=10)
+            .map(|h| BeeTwinSnapshot {
+                weight_obs: 2.4,
+                weight_pred: 2.5,
+                ..snapshot(c, h, 1.0)
+            })
+            .collect();
+        snapshots.push(BeeTwinSnapshot {
+            weight_obs: 5.5,
+            weight_pred: 2.5,
+            ..snapshot(c, 0, 1.0)
+        });
+
+        let mut smoother = EwmaSmoother::new(0.3, 3.0);
+        let harm = aggregate_harm_smoothed(
+            &snapshots,
+            0.0,
+            0.0,
+            1.0,
+            None,
+            &ZeroPredictor,
+            &mut smoother,
+        )
+        .unwrap();
+        let warn = 0.3;
+        // Raw: (10 * 0.1 + 3.0) / 11.
+        assert!((harm.raw.delta_liability - 4.0 / 11.0).abs() < 1e-12);
+        assert!(harm.raw.delta_liability > warn);
+        // The spike is capped at 3 * 0.1 and averaged to 0.16.
+        assert!((harm.smoothed.delta_liability - 1.16 / 11.0).abs() < 1e-12);
+        assert!(harm.smoothed.delta_liability < warn);
+        let average = smoother.average(c, ResidualMetric::Weight).unwrap();
+        assert!((average - 0.16).abs() < 1e-12);
+
+        let raw = aggregate_harm(&snapshots, 0.0, 0.0, 1.0, None, &ZeroPredictor).unwrap();
+        assert_eq!(raw.delta_liability, harm.raw.delta_liability);
+    }
+
+    #[test]
+    fn penalty_is_logged_against_corridor() {
+        let corridor = Uuid::from_u128(9);
+        let mut env = BeeKarmaEnvelope {
+            agent_id: Uuid::nil(),
+            corridor_id: corridor,
+            kappa: BeeKarma(0.9),
+            last_update: DateTime::<Utc>::UNIX_EPOCH,
+            realized_harm_score: 0.0,
+            predicted_harm_score: 0.0,
+            blood_gate_level: 3,
+            promotion_pending_since: None,
+            revision: 0,
+            events: Default::default(),
+        };
+        let mut harm = HarmAggregation {
+            corridor_id: corridor,
+            predicted_harm: 0.0,
+            realized_harm: 0.3,
+            delta_liability: 0.3,
+            predictor: "zero".into(),
+        };
+
+        // Below the warning threshold: nothing to log.
+        apply_liability_to_envelope(&mut env, &harm, 0.5, 1.0, 1.0);
+        assert!(env.events.is_empty());
+
+        harm.delta_liability = 0.7;
+        apply_liability_to_envelope(&mut env, &harm, 0.5, 1.0, 1.0);
+        let event = env.events.iter().last().unwrap();
+        assert_eq!(
+            event.reason,
+            KarmaReason::LiabilityPenalty {
+                corridor_id: corridor,
+                delta_liability: 0.7
+            }
+        );
+        assert!((event.delta + 0.2).abs() < 1e-12);
+        assert!((event.resulting_kappa - 0.7).abs() < 1e-12);
+        assert_eq!(event.resulting_gate, 2);
     }
 }
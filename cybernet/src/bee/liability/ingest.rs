@@ -0,0 +1,269 @@
+//! Newline-delimited JSON twin snapshots from the simulation cluster.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::super::clock::Clock;
+use super::BeeTwinSnapshot;
+
+/// `BeeTwinSnapshot` as it arrives; a `null` value marks a non-finite
+/// number (see `ingest_snapshots`).
+#[derive(Deserialize)]
+struct SnapshotLine {
+    twin_id: Uuid,
+    corridor_id: Uuid,
+    t: DateTime<Utc>,
+    vg_pred: Option<f64>,
+    vg_obs: Option<f64>,
+    dwv_pred: Option<f64>,
+    dwv_obs: Option<f64>,
+    weight_pred: Option<f64>,
+    weight_obs: Option<f64>,
+}
+
+/// Why a line was not accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RejectReason {
+    /// Not a JSON snapshot; carries the parser message.
+    Malformed {
+        message: String,
+    },
+    NonFinite {
+        field: String,
+    },
+    /// `t` is more than the allowed skew past the ingest clock.
+    FromFuture {
+        t: DateTime<Utc>,
+        now: DateTime<Utc>,
+    },
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::Malformed { message } => write!(f, "malformed snapshot: {message}"),
+            RejectReason::NonFinite { field } => write!(f, "{field} is not a finite number"),
+            RejectReason::FromFuture { t, now } => {
+                write!(f, "timestamp {t} is ahead of ingest clock {now}")
+            }
+        }
+    }
+}
+
+/// One refused line; `line` is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rejection {
+    pub line: usize,
+    pub reason: RejectReason,
+}
+
+/// Result of `ingest_snapshots`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestReport {
+    /// Valid snapshots, one per `(twin_id, t)`, in order of the line kept.
+    pub accepted: Vec<BeeTwinSnapshot>,
+    pub rejected: Vec<Rejection>,
+    /// Lines (1-based) of valid snapshots replaced by a later line with the
+    /// same `(twin_id, t)`, i.e. retransmissions.
+    pub superseded: Vec<usize>,
+}
+
+/// Read one `BeeTwinSnapshot` per line from `reader`.
+///
+/// Blank lines are skipped. A line is rejected if it does not parse, if a
+/// predicted or observed value is not finite, or if `t` is more than
+/// `max_clock_skew` ahead of `clock`. Python's encoder writes non-finite
+/// floats as bare `NaN`, `Infinity` and `-Infinity`, which are not JSON;
+/// these are read as non-finite values rather than malformed lines, so the
+/// report names the field. Among valid snapshots sharing `(twin_id, t)` the
+/// last line wins. Only read failures are returned as errors.
+pub fn ingest_snapshots<R: BufRead>(
+    reader: R,
+    clock: &dyn Clock,
+    max_clock_skew: Duration,
+) -> io::Result<IngestReport> {
+    let latest_allowed = clock.now() + max_clock_skew;
+    let mut valid: Vec<(usize, BeeTwinSnapshot)> = Vec::new();
+    let mut rejected = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(&line, latest_allowed, clock) {
+            Ok(snapshot) => valid.push((index + 1, snapshot)),
+            Err(reason) => rejected.push(Rejection {
+                line: index + 1,
+                reason,
+            }),
+        }
+    }
+
+    let mut last_line: HashMap<(Uuid, DateTime<Utc>), usize> = HashMap::new();
+    for (line, s) in &valid {
+        last_line.insert((s.twin_id, s.t), *line);
+    }
+    let mut accepted = Vec::with_capacity(last_line.len());
+    let mut superseded = Vec::new();
+    for (line, s) in valid {
+        if last_line[&(s.twin_id, s.t)] == line {
+            accepted.push(s);
+        } else {
+            superseded.push(line);
+        }
+    }
+    Ok(IngestReport {
+        accepted,
+        rejected,
+        superseded,
+    })
+}
+
+fn parse_line(
+    line: &str,
+    latest_allowed: DateTime<Utc>,
+    clock: &dyn Clock,
+) -> Result<BeeTwinSnapshot, RejectReason> {
+    let parsed: SnapshotLine =
+        serde_json::from_str(&null_non_finite(line)).map_err(|e| RejectReason::Malformed {
+            message: e.to_string(),
+        })?;
+    let finite = |field: &str, value: Option<f64>| {
+        value
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| RejectReason::NonFinite {
+                field: field.to_string(),
+            })
+    };
+    let snapshot = BeeTwinSnapshot {
+        twin_id: parsed.twin_id,
+        corridor_id: parsed.corridor_id,
+        t: parsed.t,
+        vg_pred: finite("vg_pred", parsed.vg_pred)?,
+        vg_obs: finite("vg_obs", parsed.vg_obs)?,
+        dwv_pred: finite("dwv_pred", parsed.dwv_pred)?,
+        dwv_obs: finite("dwv_obs", parsed.dwv_obs)?,
+        weight_pred: finite("weight_pred", parsed.weight_pred)?,
+        weight_obs: finite("weight_obs", parsed.weight_obs)?,
+    };
+    if snapshot.t > latest_allowed {
+        return Err(RejectReason::FromFuture {
+            t: snapshot.t,
+            now: clock.now(),
+        });
+    }
+    Ok(snapshot)
+}
+
+/// Replace bare `NaN`, `Infinity` and `-Infinity` tokens outside strings
+/// with `null`.
+fn null_non_finite(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut in_string = false;
+    while let Some(c) = rest.chars().next() {
+        if in_string {
+            if c == '\\' {
+                let escaped: String = rest.chars().take(2).collect();
+                out.push_str(&escaped);
+                rest = &rest[escaped.len()..];
+                continue;
+            }
+            in_string = c != '"';
+        } else if c == '"' {
+            in_string = true;
+        } else if let Some(token) = ["NaN", "Infinity", "-Infinity"]
+            .into_iter()
+            .find(|t| rest.starts_with(t))
+        {
+            out.push_str("null");
+            rest = &rest[token.len()..];
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::clock::ManualClock;
+    use super::*;
+
+    const TWIN: &str = "00000000-0000-0000-0000-000000000001";
+    const CORRIDOR: &str = "00000000-0000-0000-0000-000000000007";
+
+    fn line(t: &str, vg_obs: &str) -> String {
+        format!(
+            r#"{{"twin_id":"{TWIN}","corridor_id":"{CORRIDOR}","t":"{t}","vg_pred":1.0,"vg_obs":{vg_obs},"dwv_pred":0.0,"dwv_obs":0.0,"weight_pred":2.5,"weight_obs":2.4}}"#
+        )
+    }
+
+    fn clock() -> ManualClock {
+        ManualClock::new("2025-07-01T12:00:00Z".parse().unwrap())
+    }
+
+    fn ingest(lines: &[String]) -> IngestReport {
+        let input = lines.join("\n");
+        ingest_snapshots(input.as_bytes(), &clock(), Duration::minutes(5)).unwrap()
+    }
+
+    #[test]
+    fn retransmissions_keep_the_last_copy() {
+        let report = ingest(&[
+            line("2025-07-01T11:00:00Z", "0.9"),
+            line("2025-07-01T11:05:00Z", "0.8"),
+            String::new(),
+            line("2025-07-01T11:00:00Z", "0.7"),
+        ]);
+        assert!(report.rejected.is_empty());
+        assert_eq!(report.superseded, [1]);
+        let vg: Vec<_> = report.accepted.iter().map(|s| s.vg_obs).collect();
+        assert_eq!(vg, [0.8, 0.7]);
+    }
+
+    #[test]
+    fn bad_lines_are_reported_with_line_numbers() {
+        let report = ingest(&[
+            line("2025-07-01T11:00:00Z", "NaN"),
+            r#"{"twin_id": "#.to_string(),
+            line("2025-07-01T11:10:00Z", "-Infinity"),
+            line("2025-07-01T12:04:59Z", "0.9"),
+            line("2025-07-01T12:05:01Z", "0.9"),
+            line("2025-07-01T11:15:00Z", "0.9"),
+        ]);
+        let rejected: Vec<_> = report
+            .rejected
+            .iter()
+            .map(|r| (r.line, r.reason.to_string()))
+            .collect();
+        assert_eq!(rejected[0], (1, "vg_obs is not a finite number".into()));
+        assert_eq!(rejected[1].0, 2);
+        assert!(rejected[1].1.starts_with("malformed snapshot: EOF"));
+        assert_eq!(rejected[2], (3, "vg_obs is not a finite number".into()));
+        assert_eq!(rejected[3].0, 5);
+        assert!(matches!(
+            report.rejected[3].reason,
+            RejectReason::FromFuture { .. }
+        ));
+        assert_eq!(rejected.len(), 4);
+        // Within the five-minute skew allowance.
+        assert_eq!(report.accepted.len(), 2);
+        assert!(report.superseded.is_empty());
+    }
+
+    #[test]
+    fn non_finite_tokens_inside_strings_are_left_alone() {
+        assert_eq!(
+            null_non_finite(r#"{"a":NaN,"b":"NaN \"Infinity\"","c":-Infinity}"#),
+            r#"{"a":null,"b":"NaN \"Infinity\"","c":null}"#
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -9,20 +11,20 @@ pub struct BeeKarma(pub f64); // 0.0 – 1.0, hard lower bounds enforced via pre
 /// Compact stressor vector projected into the bee-rights polytope.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BeeStressorState {
-    pub hq_pest: f64,       // pesticide HQ
-    pub h_rf: f64,          // RF-EMF hazard index
-    pub h_poll: f64,        // air pollutant hazard index
-    pub d_h_bio: f64,       // biomarker harm delta
+    pub hq_pest: f64, // pesticide HQ
+    pub h_rf: f64,    // RF-EMF hazard index
+    pub h_poll: f64,  // air pollutant hazard index
+    pub d_h_bio: f64, // biomarker harm delta
     pub varroa_per_100: f64,
-    pub d_thive_c: f64,     // |T_hive - 34.5|
-    pub q_forage: f64,      // normalized foraging success
+    pub d_thive_c: f64, // |T_hive - 34.5|
+    pub q_forage: f64,  // normalized foraging success
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BeeCorridorPolytope {
-    pub a: Vec<Vec<f64>>,   // rows of A
-    pub b: Vec<f64>,        // bounds
-    pub kappa_min: f64,     // admissible minimum bee_karma
+    pub a: Vec<Vec<f64>>, // rows of A
+    pub b: Vec<f64>,      // bounds
+    pub kappa_min: f64,   // admissible minimum bee_karma
 }
 
 /// Identity-bound governance envelope for a Cybernet agent.
@@ -35,4 +37,122 @@ pub struct BeeKarmaEnvelope {
     pub realized_harm_score: f64,  // reconciled ABM vs telemetry
     pub predicted_harm_score: f64, // from bee twin simulations
     pub blood_gate_level: u8,      // 0 = revoked, 1 = read-only, 2 = limited-write, 3 = full
+    /// When kappa first held above the next level's threshold; promotion
+    /// waits for the dwell to elapse from here.
+    #[serde(default)]
+    pub promotion_pending_since: Option<DateTime<Utc>>,
+    /// Bumped on every change; the store refuses to go backwards.
+    #[serde(default)]
+    pub revision: u64,
+    /// Recent kappa changes and why they happened.
+    #[serde(default)]
+    pub events: KarmaEventLog,
+}
+
+/// Why kappa moved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KarmaReason {
+    /// Twin reconciliation found harm beyond the warning threshold.
+    LiabilityPenalty {
+        corridor_id: Uuid,
+        delta_liability: f64,
+    },
+    /// An operator changed kappa by hand.
+    ManualAdjustment { operator: String },
+    /// Time-based recovery.
+    Regeneration,
+}
+
+/// One kappa change, stamped with the envelope clock (`last_update`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KarmaEvent {
+    pub timestamp: DateTime<Utc>,
+    /// Change actually applied, after clamping to [0, 1].
+    pub delta: f64,
+    pub reason: KarmaReason,
+    pub resulting_kappa: f64,
+    pub resulting_gate: u8,
+}
+
+/// Bounded history of karma events; the oldest is dropped once full.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "KarmaEventLogRepr")]
+pub struct KarmaEventLog {
+    capacity: usize,
+    events: VecDeque<KarmaEvent>,
+}
+
+/// Wire form of [`KarmaEventLog`], checked before it becomes one.
+#[derive(Deserialize)]
+struct KarmaEventLogRepr {
+    capacity: usize,
+    events: VecDeque<KarmaEvent>,
+}
+
+impl TryFrom<KarmaEventLogRepr> for KarmaEventLog {
+    type Error = String;
+
+    fn try_from(repr: KarmaEventLogRepr) -> Result<Self, Self::Error> {
+        if repr.capacity == 0 {
+            return Err("karma event log capacity must be at least 1".into());
+        }
+        if repr.events.len() > repr.capacity {
+            return Err(format!(
+                "karma event log holds {} events but its capacity is {}",
+                repr.events.len(),
+                repr.capacity
+            ));
+        }
+        let mut log = Self::with_capacity(repr.capacity);
+        log.events = repr.events;
+        Ok(log)
+    }
+}
+
+impl KarmaEventLog {
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    /// A log holding at most `capacity` events (at least one).
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn push(&mut self, event: KarmaEvent) {
+        while self.events.len() >= self.capacity && self.events.pop_front().is_some() {}
+        self.events.push_back(event);
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &KarmaEvent> {
+        self.events.iter()
+    }
+
+    /// Events stamped at or after `ts`, oldest first. Inclusive because
+    /// several changes can share one envelope timestamp.
+    pub fn since(&self, ts: DateTime<Utc>) -> impl Iterator<Item = &KarmaEvent> {
+        self.events.iter().filter(move |e| e.timestamp >= ts)
+    }
+}
+
+impl Default for KarmaEventLog {
+    fn default() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
 }
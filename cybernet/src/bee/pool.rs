@@ -0,0 +1,232 @@
+//! Corridor-level view of fleet karma for governance dashboards.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::BeeKarmaEnvelope;
+
+/// Karma and gate distribution of the agents in one corridor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorridorKarmaSummary {
+    pub corridor_id: Uuid,
+    pub agents: usize,
+    pub min_kappa: f64,
+    pub mean_kappa: f64,
+    /// Nearest-rank percentiles of kappa.
+    pub p10_kappa: f64,
+    pub p50_kappa: f64,
+    pub p90_kappa: f64,
+    /// Agents at each blood-gate level 0..=3; anything higher counts as 3.
+    pub gate_levels: [usize; 4],
+    pub min_gate_level: u8,
+    /// The corridor's kappa_min used for `below_kappa_min`.
+    pub kappa_min: f64,
+    pub below_kappa_min: usize,
+}
+
+impl CorridorKarmaSummary {
+    /// Agents gated below `level`.
+    pub fn gated_below(&self, level: u8) -> usize {
+        self.gate_levels
+            .iter()
+            .take(usize::from(level).min(self.gate_levels.len()))
+            .sum()
+    }
+}
+
+/// Groups envelopes by corridor and summarises each corridor.
+///
+/// Input may mix corridors in any order. Each agent's envelope should be
+/// added once; the pool does not deduplicate by `agent_id`.
+#[derive(Debug, Clone)]
+pub struct KarmaPool {
+    kappa_min: f64,
+    corridor_kappa_min: HashMap<Uuid, f64>,
+    corridors: BTreeMap<Uuid, Vec<(f64, u8)>>,
+}
+
+impl KarmaPool {
+    /// `kappa_min` applies to corridors without their own.
+    pub fn new(kappa_min: f64) -> Self {
+        KarmaPool {
+            kappa_min,
+            corridor_kappa_min: HashMap::new(),
+            corridors: BTreeMap::new(),
+        }
+    }
+
+    /// Use `kappa_min` (normally the corridor polytope's) for `corridor_id`.
+    pub fn with_corridor_kappa_min(mut self, corridor_id: Uuid, kappa_min: f64) -> Self {
+        self.corridor_kappa_min.insert(corridor_id, kappa_min);
+        self
+    }
+
+    pub fn add(&mut self, env: &BeeKarmaEnvelope) {
+        self.corridors
+            .entry(env.corridor_id)
+            .or_default()
+            .push((env.kappa.0, env.blood_gate_level));
+    }
+
+    pub fn summaries(&self) -> Vec<CorridorKarmaSummary> {
+        self.corridors
+            .iter()
+            .map(|(&corridor_id, agents)| self.summarize(corridor_id, agents))
+            .collect()
+    }
+
+    pub fn summary(&self, corridor_id: Uuid) -> Option<CorridorKarmaSummary> {
+        self.corridors
+            .get(&corridor_id)
+            .map(|agents| self.summarize(corridor_id, agents))
+    }
+
+    fn summarize(&self, corridor_id: Uuid, agents: &[(f64, u8)]) -> CorridorKarmaSummary {
+        let kappa_min = self
+            .corridor_kappa_min
+            .get(&corridor_id)
+            .copied()
+            .unwrap_or(self.kappa_min);
+        let mut kappas: Vec<f64> = agents.iter().map(|&(kappa, _)| kappa).collect();
+        kappas.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let rank = (p * kappas.len() as f64).ceil() as usize;
+            kappas[rank.clamp(1, kappas.len()) - 1]
+        };
+        let mut gate_levels = [0; 4];
+        for &(_, level) in agents {
+            gate_levels[usize::from(level.min(3))] += 1;
+        }
+        CorridorKarmaSummary {
+            corridor_id,
+            agents: agents.len(),
+            min_kappa: kappas[0],
+            mean_kappa: kappas.iter().sum::<f64>() / kappas.len() as f64,
+            p10_kappa: percentile(0.1),
+            p50_kappa: percentile(0.5),
+            p90_kappa: percentile(0.9),
+            gate_levels,
+            min_gate_level: agents.iter().map(|&(_, level)| level).min().unwrap_or(0),
+            kappa_min,
+            below_kappa_min: kappas.iter().filter(|&&k| k < kappa_min).count(),
+        }
+    }
+}
+
+impl<'a> Extend<&'a BeeKarmaEnvelope> for KarmaPool {
+    fn extend<I: IntoIterator<Item = &'a BeeKarmaEnvelope>>(&mut self, envelopes: I) {
+        for env in envelopes {
+            self.add(env);
+        }
+    }
+}
+
+/// When a corridor needs attention.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolHealthPolicy {
+    /// Largest share of agents, in [0, 1], allowed below level 2
+    /// (limited-write).
+    pub max_gated_fraction: f64,
+}
+
+/// False if more than `policy.max_gated_fraction` of the corridor's agents
+/// are gated below level 2.
+pub fn corridor_healthy(summary: &CorridorKarmaSummary, policy: &PoolHealthPolicy) -> bool {
+    if summary.agents == 0 {
+        return true;
+    }
+    let gated = summary.gated_below(2) as f64 / summary.agents as f64;
+    gated <= policy.max_gated_fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::super::{BeeKarma, KarmaEventLog};
+    use super::*;
+
+    fn envelope(agent: u128, corridor: u128, kappa: f64, gate: u8) -> BeeKarmaEnvelope {
+        BeeKarmaEnvelope {
+            agent_id: Uuid::from_u128(agent),
+            corridor_id: Uuid::from_u128(corridor),
+            kappa: BeeKarma(kappa),
+            last_update: DateTime::<Utc>::UNIX_EPOCH,
+            realized_harm_score: 0.0,
+            predicted_harm_score: 0.0,
+            blood_gate_level: gate,
+            promotion_pending_since: None,
+            revision: 0,
+            events: KarmaEventLog::default(),
+        }
+    }
+
+    /// Corridors 1, 2 and 3, interleaved as a fleet scan would return them.
+    fn fleet() -> Vec<BeeKarmaEnvelope> {
+        let corridors: [&[(f64, u8)]; 3] = [
+            &[(0.9, 3), (0.85, 3), (0.7, 2), (0.65, 2)],
+            &[(0.5, 1), (0.3, 0), (0.2, 0), (0.75, 2), (0.45, 1)],
+            &[(0.62, 2), (0.35, 0)],
+        ];
+        let mut fleet = Vec::new();
+        for i in 0..5 {
+            for (c, agents) in corridors.iter().enumerate() {
+                if let Some(&(kappa, gate)) = agents.get(i) {
+                    let agent = fleet.len() as u128;
+                    fleet.push(envelope(agent, c as u128 + 1, kappa, gate));
+                }
+            }
+        }
+        fleet
+    }
+
+    #[test]
+    fn mixed_fleet_is_partitioned_by_corridor() {
+        let mut pool = KarmaPool::new(0.4).with_corridor_kappa_min(Uuid::from_u128(3), 0.5);
+        pool.extend(&fleet());
+        let summaries = pool.summaries();
+        let ids: Vec<_> = summaries.iter().map(|s| s.corridor_id.as_u128()).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(
+            summaries.iter().map(|s| s.agents).collect::<Vec<_>>(),
+            [4, 5, 2]
+        );
+
+        let b = &summaries[1];
+        assert_eq!(b.min_kappa, 0.2);
+        assert!((b.mean_kappa - 0.44).abs() < 1e-12);
+        // Sorted: 0.2, 0.3, 0.45, 0.5, 0.75.
+        assert_eq!((b.p10_kappa, b.p50_kappa, b.p90_kappa), (0.2, 0.45, 0.75));
+        assert_eq!(b.gate_levels, [2, 2, 1, 0]);
+        assert_eq!(b.min_gate_level, 0);
+        assert_eq!(b.below_kappa_min, 2);
+
+        let c = pool.summary(Uuid::from_u128(3)).unwrap();
+        assert_eq!(c.kappa_min, 0.5);
+        assert_eq!(c.below_kappa_min, 1);
+        assert_eq!(summaries[0].min_gate_level, 2);
+        assert_eq!(summaries[0].below_kappa_min, 0);
+        assert!(pool.summary(Uuid::from_u128(4)).is_none());
+
+        let json = serde_json::to_value(b).unwrap();
+        assert_eq!(json["gate_levels"], serde_json::json!([2, 2, 1, 0]));
+    }
+
+    #[test]
+    fn corridors_with_too_many_gated_agents_are_flagged() {
+        let mut pool = KarmaPool::new(0.4);
+        pool.extend(&fleet());
+        let policy = PoolHealthPolicy {
+            max_gated_fraction: 0.5,
+        };
+        let healthy: Vec<_> = pool
+            .summaries()
+            .iter()
+            .map(|s| corridor_healthy(s, &policy))
+            .collect();
+        // Gated below level 2: none of 4, 4 of 5, exactly 1 of 2.
+        assert_eq!(healthy, [true, false, true]);
+    }
+}
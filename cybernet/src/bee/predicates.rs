@@ -1,33 +1,147 @@
-use super::{BeeCorridorPolytope, BeeKarma, BeeKarmaEnvelope, BeeStressorState};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use super::clock::Clock;
+use super::{
+    BeeCorridorPolytope, BeeKarma, BeeKarmaEnvelope, BeeStressorState, KarmaEvent, KarmaReason,
+};
+
+/// Number of coordinates in the stressor vector `is_inside_polytope` builds.
+pub const STRESSOR_DIM: usize = 7;
+
+/// A corridor polytope that cannot be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolytopeError {
+    /// Row `row` of A has `got` coefficients instead of `expected`.
+    DimensionMismatch {
+        row: usize,
+        expected: usize,
+        got: usize,
+    },
+    /// A and b disagree on the number of constraints.
+    BoundCountMismatch { rows: usize, bounds: usize },
+    /// Row `row` has a NaN or infinite coefficient or bound.
+    NonFinite { row: usize },
+}
+
+impl std::fmt::Display for PolytopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolytopeError::DimensionMismatch { row, expected, got } => {
+                write!(f, "row {row}: expected {expected} coefficients, got {got}")
+            }
+            PolytopeError::BoundCountMismatch { rows, bounds } => {
+                write!(f, "{rows} constraint rows but {bounds} bounds")
+            }
+            PolytopeError::NonFinite { row } => write!(f, "row {row}: non-finite value"),
+        }
+    }
+}
+
+impl std::error::Error for PolytopeError {}
 
 pub trait BeeAdmissible {
     fn bee_state(&self) -> &BeeStressorState;
     fn bee_corridor(&self) -> &BeeCorridorPolytope;
     fn bee_karma(&self) -> BeeKarma;
 
+    /// A·x <= b over the stressor vector, refusing malformed polytopes
+    /// instead of truncating or zero-padding rows.
+    fn check_inside_polytope(&self) -> Result<bool, PolytopeError> {
+        Ok(corridor_slack(self.bee_state(), self.bee_corridor())? >= 0.0)
+    }
+
+    /// False for malformed polytopes; use `check_inside_polytope` to see why.
     fn is_inside_polytope(&self) -> bool {
-        let x = self.bee_state();
-        let vec_x = vec![
-            x.hq_pest,
-            x.h_rf,
-            x.h_poll,
-            x.d_h_bio,
-            x.varroa_per_100,
-            x.d_thive_c,
-            1.0 - x.q_forage, // convert success into "stress"
-        ];
-        let p = self.bee_corridor();
-        for (row, &b_i) in p.a.iter().zip(p.b.iter()) {
-            let dot = row.iter().zip(vec_x.iter()).map(|(a, v)| a * v).sum::<f64>();
-            if dot > b_i {
-                return false;
-            }
-        }
-        true
+        self.check_inside_polytope().unwrap_or(false)
+    }
+
+    /// Admissibility with the polytope error, if any, kept for logging.
+    fn check_bee_admissible(&self) -> Result<bool, PolytopeError> {
+        let inside = self.check_inside_polytope()?;
+        Ok(inside && self.bee_karma().0 >= self.bee_corridor().kappa_min)
     }
 
+    /// Any polytope error makes the agent inadmissible.
     fn is_bee_admissible(&self) -> bool {
-        self.is_inside_polytope() && self.bee_karma().0 >= self.bee_corridor().kappa_min
+        self.check_bee_admissible().unwrap_or(false)
+    }
+}
+
+/// Smallest b_i - A_i·x over the corridor rows; negative when `state`
+/// violates a row, infinite when there are none.
+pub fn corridor_slack(
+    state: &BeeStressorState,
+    corridor: &BeeCorridorPolytope,
+) -> Result<f64, PolytopeError> {
+    let vec_x: [f64; STRESSOR_DIM] = [
+        state.hq_pest,
+        state.h_rf,
+        state.h_poll,
+        state.d_h_bio,
+        state.varroa_per_100,
+        state.d_thive_c,
+        1.0 - state.q_forage, // convert success into "stress"
+    ];
+    if corridor.a.len() != corridor.b.len() {
+        return Err(PolytopeError::BoundCountMismatch {
+            rows: corridor.a.len(),
+            bounds: corridor.b.len(),
+        });
+    }
+    let mut slack = f64::INFINITY;
+    for (i, (row, &b_i)) in corridor.a.iter().zip(corridor.b.iter()).enumerate() {
+        if row.len() != STRESSOR_DIM {
+            return Err(PolytopeError::DimensionMismatch {
+                row: i,
+                expected: STRESSOR_DIM,
+                got: row.len(),
+            });
+        }
+        if !b_i.is_finite() || row.iter().any(|a| !a.is_finite()) {
+            return Err(PolytopeError::NonFinite { row: i });
+        }
+        let dot = row
+            .iter()
+            .zip(vec_x.iter())
+            .map(|(a, v)| a * v)
+            .sum::<f64>();
+        slack = slack.min(b_i - dot);
+    }
+    Ok(slack)
+}
+
+/// Blood-gate level implied by kappa alone.
+pub fn gate_level_for(kappa: f64) -> u8 {
+    if kappa >= 0.8 {
+        3
+    } else if kappa >= 0.6 {
+        2
+    } else if kappa >= 0.4 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Move `env.blood_gate_level` toward what kappa implies, as of `now`.
+///
+/// Drops happen at once. A rise needs kappa to have stayed above the next
+/// level's threshold for `dwell`, and then climbs a single level; the dwell
+/// restarts before the one after.
+pub fn update_gate_level(env: &mut BeeKarmaEnvelope, now: DateTime<Utc>, dwell: Duration) {
+    let target = gate_level_for(env.kappa.0);
+    if target <= env.blood_gate_level {
+        env.blood_gate_level = target;
+        env.promotion_pending_since = None;
+        return;
+    }
+    let since = *env.promotion_pending_since.get_or_insert(now);
+    let dwell = chrono::Duration::from_std(dwell).unwrap_or(chrono::Duration::MAX);
+    if now - since >= dwell {
+        env.blood_gate_level += 1;
+        env.promotion_pending_since = (target > env.blood_gate_level).then_some(now);
     }
 }
 
@@ -35,8 +149,19 @@ pub trait BloodGated {
     fn envelope(&self) -> &BeeKarmaEnvelope;
     fn envelope_mut(&mut self) -> &mut BeeKarmaEnvelope;
 
-    fn apply_karma_delta(&mut self, delta: f64) {
+    /// How long kappa must hold above a level's threshold before the level
+    /// is granted.
+    fn promotion_dwell(&self) -> Duration {
+        Duration::from_secs(24 * 3600)
+    }
+
+    /// Kappa changes immediately; gate-level increases still wait out the
+    /// promotion dwell, measured from `last_update`. The change is logged
+    /// under `reason`.
+    fn apply_karma_delta(&mut self, delta: f64, reason: KarmaReason) {
+        let dwell = self.promotion_dwell();
         let env = self.envelope_mut();
+        let before = env.kappa.0;
         let mut k = env.kappa.0 + delta;
         if k > 1.0 {
             k = 1.0;
@@ -45,14 +170,388 @@ pub trait BloodGated {
             k = 0.0;
         }
         env.kappa = BeeKarma(k);
-        env.blood_gate_level = if k >= 0.8 {
-            3
-        } else if k >= 0.6 {
-            2
-        } else if k >= 0.4 {
-            1
-        } else {
-            0
-        };
+        env.revision += 1;
+        let now = env.last_update;
+        update_gate_level(env, now, dwell);
+        log_karma_event(env, before, reason);
+    }
+
+    /// Recover kappa by at most `rate_per_hour` over `elapsed`, never above
+    /// `max_kappa` (and never lowering a kappa already above it), then
+    /// advance `last_update` and apply the gate-level guard.
+    fn regenerate(&mut self, elapsed: Duration, rate_per_hour: f64, max_kappa: f64) {
+        let dwell = self.promotion_dwell();
+        let env = self.envelope_mut();
+        let before = env.kappa.0;
+        let hours = elapsed.as_secs_f64() / 3600.0;
+        let ceiling = max_kappa.clamp(0.0, 1.0).max(env.kappa.0);
+        let k = (env.kappa.0 + rate_per_hour.max(0.0) * hours).min(ceiling);
+        env.kappa = BeeKarma(k);
+        env.revision += 1;
+        env.last_update = chrono::Duration::from_std(elapsed)
+            .ok()
+            .and_then(|d| env.last_update.checked_add_signed(d))
+            .unwrap_or(env.last_update);
+        let now = env.last_update;
+        update_gate_level(env, now, dwell);
+        log_karma_event(env, before, KarmaReason::Regeneration);
+    }
+
+    /// `regenerate` over the time from `last_update` to `clock.now()`. A
+    /// clock behind `last_update` counts as no time passing.
+    fn regenerate_until(&mut self, clock: &dyn Clock, rate_per_hour: f64, max_kappa: f64) {
+        let elapsed = (clock.now() - self.envelope().last_update)
+            .to_std()
+            .unwrap_or_default();
+        self.regenerate(elapsed, rate_per_hour, max_kappa);
+    }
+}
+
+/// Record the move from `before` to the envelope's current kappa and gate.
+pub(crate) fn log_karma_event(env: &mut BeeKarmaEnvelope, before: f64, reason: KarmaReason) {
+    let event = KarmaEvent {
+        timestamp: env.last_update,
+        delta: env.kappa.0 - before,
+        reason,
+        resulting_kappa: env.kappa.0,
+        resulting_gate: env.blood_gate_level,
+    };
+    env.events.push(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bee::KarmaEventLog;
+
+    struct Agent {
+        state: BeeStressorState,
+        corridor: BeeCorridorPolytope,
+        karma: f64,
+    }
+
+    impl BeeAdmissible for Agent {
+        fn bee_state(&self) -> &BeeStressorState {
+            &self.state
+        }
+        fn bee_corridor(&self) -> &BeeCorridorPolytope {
+            &self.corridor
+        }
+        fn bee_karma(&self) -> BeeKarma {
+            BeeKarma(self.karma)
+        }
+    }
+
+    fn agent(a: Vec<Vec<f64>>, b: Vec<f64>) -> Agent {
+        Agent {
+            state: BeeStressorState {
+                hq_pest: 0.2,
+                h_rf: 0.1,
+                h_poll: 0.3,
+                d_h_bio: 0.0,
+                varroa_per_100: 1.0,
+                d_thive_c: 0.5,
+                q_forage: 0.9,
+            },
+            corridor: BeeCorridorPolytope {
+                a,
+                b,
+                kappa_min: 0.4,
+            },
+            karma: 0.7,
+        }
+    }
+
+    /// hq_pest <= 0.5 and varroa_per_100 <= 3.
+    fn rows() -> Vec<Vec<f64>> {
+        vec![
+            vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        ]
+    }
+
+    struct Gated(BeeKarmaEnvelope);
+
+    impl BloodGated for Gated {
+        fn envelope(&self) -> &BeeKarmaEnvelope {
+            &self.0
+        }
+        fn envelope_mut(&mut self) -> &mut BeeKarmaEnvelope {
+            &mut self.0
+        }
+        fn promotion_dwell(&self) -> Duration {
+            Duration::from_secs(48 * 3600)
+        }
+    }
+
+    fn revoked() -> Gated {
+        Gated(BeeKarmaEnvelope {
+            agent_id: uuid::Uuid::nil(),
+            corridor_id: uuid::Uuid::nil(),
+            kappa: BeeKarma(0.1),
+            last_update: DateTime::<Utc>::UNIX_EPOCH,
+            realized_harm_score: 0.0,
+            predicted_harm_score: 0.0,
+            blood_gate_level: 0,
+            promotion_pending_since: None,
+            revision: 0,
+            events: KarmaEventLog::default(),
+        })
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 3600);
+
+    fn manual() -> KarmaReason {
+        KarmaReason::ManualAdjustment {
+            operator: "ops".into(),
+        }
+    }
+
+    #[test]
+    fn recovery_climbs_one_level_per_dwell() {
+        let mut agent = revoked();
+        // 0.01/h for a month: kappa reaches 0.9 after ~33 days uncapped.
+        let mut levels = Vec::new();
+        for _ in 0..40 {
+            agent.regenerate(DAY, 0.01, 0.9);
+            levels.push(agent.envelope().blood_gate_level);
+        }
+        let k = agent.envelope().kappa.0;
+        assert!((k - 0.9).abs() < 1e-9, "kappa {k}");
+
+        // Kappa crosses 0.4 on day 2 (0.1 + 0.48); level 1 waits two more days.
+        assert_eq!(levels[1], 0);
+        assert_eq!(levels[2], 0);
+        assert_eq!(levels[3], 1);
+        // Never more than one level per tick.
+        for pair in levels.windows(2) {
+            assert!(pair[1] <= pair[0] + 1);
+        }
+        assert_eq!(*levels.last().unwrap(), 3);
+    }
+
+    #[test]
+    fn large_jump_still_waits_and_steps() {
+        let mut agent = revoked();
+        agent.apply_karma_delta(0.85, manual());
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+
+        agent.regenerate(DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+        agent.regenerate(DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 1);
+        agent.regenerate(2 * DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 2);
+        agent.regenerate(2 * DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 3);
+    }
+
+    #[test]
+    fn decreases_are_immediate_and_reset_dwell() {
+        let mut agent = revoked();
+        agent.apply_karma_delta(0.6, manual());
+        for _ in 0..4 {
+            agent.regenerate(DAY, 0.0, 1.0);
+        }
+        assert_eq!(agent.envelope().blood_gate_level, 2);
+
+        agent.apply_karma_delta(-0.45, manual());
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+        assert_eq!(agent.envelope().promotion_pending_since, None);
+
+        // Back above 0.4 for one day only, then below again: no promotion.
+        agent.apply_karma_delta(0.2, manual());
+        agent.regenerate(DAY, 0.0, 1.0);
+        agent.apply_karma_delta(-0.1, manual());
+        agent.regenerate(DAY, 0.0, 1.0);
+        agent.apply_karma_delta(0.1, manual());
+        agent.regenerate(DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+        agent.regenerate(DAY, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 1);
+    }
+
+    #[test]
+    fn regeneration_follows_the_manual_clock() {
+        use crate::bee::clock::ManualClock;
+
+        let clock = ManualClock::new(DateTime::<Utc>::UNIX_EPOCH);
+        let mut agent = revoked();
+        agent.apply_karma_delta(0.4, manual());
+
+        // Nothing elapsed, nothing regenerated.
+        agent.regenerate_until(&clock, 0.01, 1.0);
+        assert_eq!(agent.envelope().kappa.0, 0.5);
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+
+        clock.advance(chrono::Duration::hours(47));
+        agent.regenerate_until(&clock, 0.0, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 0);
+        clock.advance(chrono::Duration::hours(1));
+        agent.regenerate_until(&clock, 0.01, 1.0);
+        assert_eq!(agent.envelope().blood_gate_level, 1);
+        assert!((agent.envelope().kappa.0 - 0.51).abs() < 1e-12);
+        assert_eq!(agent.envelope().last_update, clock.now());
+
+        // A clock set back does not undo or repeat regeneration.
+        clock.set(DateTime::<Utc>::UNIX_EPOCH);
+        agent.regenerate_until(&clock, 0.01, 1.0);
+        assert!((agent.envelope().kappa.0 - 0.51).abs() < 1e-12);
+    }
+
+    #[test]
+    fn regeneration_respects_cap_and_ignores_negative_rate() {
+        let mut agent = revoked();
+        agent.regenerate(DAY, 1.0, 0.5);
+        assert_eq!(agent.envelope().kappa.0, 0.5);
+        agent.regenerate(DAY, -1.0, 0.5);
+        assert_eq!(agent.envelope().kappa.0, 0.5);
+        // Already above the cap: left alone rather than pulled down.
+        agent.apply_karma_delta(0.3, manual());
+        agent.regenerate(DAY, 1.0, 0.5);
+        assert!((agent.envelope().kappa.0 - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn karma_changes_are_logged_with_reasons() {
+        let mut agent = revoked();
+        agent.apply_karma_delta(0.5, manual());
+        agent.regenerate(DAY, 0.01, 1.0);
+        // Clamped at zero: the logged delta is what actually moved.
+        agent.apply_karma_delta(-2.0, manual());
+
+        let events: Vec<_> = agent.envelope().events.iter().cloned().collect();
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].reason, manual());
+        assert!((events[0].delta - 0.5).abs() < 1e-12);
+        assert!((events[0].resulting_kappa - 0.6).abs() < 1e-12);
+        assert_eq!(events[0].resulting_gate, 0);
+        assert_eq!(events[0].timestamp, DateTime::<Utc>::UNIX_EPOCH);
+
+        assert_eq!(events[1].reason, KarmaReason::Regeneration);
+        assert!((events[1].delta - 0.24).abs() < 1e-12);
+        assert_eq!(
+            events[1].timestamp,
+            DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::days(1)
+        );
+
+        assert!((events[2].delta + 0.84).abs() < 1e-12);
+        assert_eq!(events[2].resulting_kappa, 0.0);
+        assert_eq!(events[2].resulting_gate, 0);
+
+        let recent: Vec<_> = agent
+            .envelope()
+            .events
+            .since(DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::hours(1))
+            .collect();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].reason, KarmaReason::Regeneration);
+    }
+
+    #[test]
+    fn event_log_evicts_oldest_when_full() {
+        let mut agent = revoked();
+        agent.0.events = KarmaEventLog::with_capacity(3);
+        for i in 1..=5 {
+            agent.apply_karma_delta(
+                0.01,
+                KarmaReason::ManualAdjustment {
+                    operator: format!("op{i}"),
+                },
+            );
+        }
+        let log = &agent.envelope().events;
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.capacity(), 3);
+        let operators: Vec<_> = log
+            .iter()
+            .map(|e| match &e.reason {
+                KarmaReason::ManualAdjustment { operator } => operator.as_str(),
+                other => panic!("unexpected reason {other:?}"),
+            })
+            .collect();
+        assert_eq!(operators, ["op3", "op4", "op5"]);
+
+        // The log travels with the envelope.
+        let json = serde_json::to_string(agent.envelope()).unwrap();
+        let back: BeeKarmaEnvelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.events.capacity(), 3);
+        let reasons: Vec<_> = back.events.iter().map(|e| &e.reason).collect();
+        let expected: Vec<_> = log.iter().map(|e| &e.reason).collect();
+        assert_eq!(reasons, expected);
+
+        // A zero capacity would make `push` spin; more events than the
+        // capacity break the bound. Both are refused on load.
+        for capacity in [0, 2] {
+            let bad = json.replace("\"capacity\":3", &format!("\"capacity\":{capacity}"));
+            assert_ne!(bad, json);
+            assert!(serde_json::from_str::<BeeKarmaEnvelope>(&bad).is_err());
+        }
+    }
+
+    #[test]
+    fn well_formed_polytope_is_admissible() {
+        let a = agent(rows(), vec![0.5, 3.0]);
+        assert_eq!(a.check_bee_admissible(), Ok(true));
+        assert!(a.is_bee_admissible());
+
+        let over = agent(rows(), vec![0.1, 3.0]);
+        assert_eq!(over.check_inside_polytope(), Ok(false));
+        assert!(!over.is_bee_admissible());
+    }
+
+    #[test]
+    fn short_and_long_rows_are_rejected() {
+        let mut short = rows();
+        short[1].pop();
+        let a = agent(short, vec![0.5, 3.0]);
+        assert_eq!(
+            a.check_bee_admissible(),
+            Err(PolytopeError::DimensionMismatch {
+                row: 1,
+                expected: 7,
+                got: 6
+            })
+        );
+        assert!(!a.is_bee_admissible());
+
+        // The trailing coefficient would push the dot product over b if read.
+        let mut long = rows();
+        long[0].push(100.0);
+        let a = agent(long, vec![0.5, 3.0]);
+        assert_eq!(
+            a.check_inside_polytope(),
+            Err(PolytopeError::DimensionMismatch {
+                row: 0,
+                expected: 7,
+                got: 8
+            })
+        );
+        assert!(!a.is_inside_polytope());
+    }
+
+    #[test]
+    fn non_finite_and_unpaired_rows_are_rejected() {
+        let mut nan = rows();
+        nan[0][3] = f64::NAN;
+        let a = agent(nan, vec![0.5, 3.0]);
+        assert_eq!(
+            a.check_inside_polytope(),
+            Err(PolytopeError::NonFinite { row: 0 })
+        );
+
+        let a = agent(rows(), vec![0.5, f64::INFINITY]);
+        assert_eq!(
+            a.check_inside_polytope(),
+            Err(PolytopeError::NonFinite { row: 1 })
+        );
+
+        let a = agent(rows(), vec![0.5]);
+        assert_eq!(
+            a.check_inside_polytope(),
+            Err(PolytopeError::BoundCountMismatch { rows: 2, bounds: 1 })
+        );
+        assert!(!a.is_bee_admissible());
     }
 }
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::BeeKarmaEnvelope;
+
+/// Signs and verifies persisted envelopes.
+pub trait EnvelopeSigner {
+    /// Recorded with each snapshot so a rotated key is reported, not
+    /// mistaken for tampering.
+    fn key_id(&self) -> &str;
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+#[cfg(feature = "ed25519-dalek")]
+pub use ed25519::Ed25519EnvelopeSigner;
+
+#[cfg(feature = "ed25519-dalek")]
+mod ed25519 {
+    use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier as _};
+
+    use super::EnvelopeSigner;
+
+    pub struct Ed25519EnvelopeSigner {
+        key_id: String,
+        key: SigningKey,
+    }
+
+    impl Ed25519EnvelopeSigner {
+        pub fn new(key_id: impl Into<String>, key: SigningKey) -> Self {
+            Self {
+                key_id: key_id.into(),
+                key,
+            }
+        }
+    }
+
+    impl EnvelopeSigner for Ed25519EnvelopeSigner {
+        fn key_id(&self) -> &str {
+            &self.key_id
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            self.key.sign(message).to_bytes().to_vec()
+        }
+
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            Signature::from_slice(signature)
+                .map(|sig| self.key.verifying_key().verify(message, &sig).is_ok())
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// Where signed snapshots are appended. Records are opaque lines, oldest
+/// first; backends never rewrite or delete complete records.
+pub trait EnvelopeBackend {
+    fn append(&mut self, record: &str) -> io::Result<()>;
+    fn records(&self) -> io::Result<Vec<String>>;
+
+    /// Drop a final record whose append never finished, so the next append
+    /// starts a fresh record. Backends with atomic appends have none.
+    fn discard_torn_tail(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// In-process backend, for tests and ephemeral agents.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryBackend {
+    pub lines: Vec<String>,
+}
+
+impl EnvelopeBackend for MemoryBackend {
+    fn append(&mut self, record: &str) -> io::Result<()> {
+        self.lines.push(record.to_string());
+        Ok(())
+    }
+
+    fn records(&self) -> io::Result<Vec<String>> {
+        Ok(self.lines.clone())
+    }
+}
+
+/// JSON-lines file opened in append mode; each record is flushed and
+/// synced before `append` returns. Anything after the last newline is an
+/// append that never finished.
+#[derive(Debug, Clone)]
+pub struct AppendOnlyFile {
+    path: PathBuf,
+}
+
+impl AppendOnlyFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl EnvelopeBackend for AppendOnlyFile {
+    fn append(&mut self, record: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(format!("{record}\n").as_bytes())?;
+        file.sync_data()
+    }
+
+    fn records(&self) -> io::Result<Vec<String>> {
+        match File::open(&self.path) {
+            Ok(file) => BufReader::new(file).lines().collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn discard_torn_tail(&mut self) -> io::Result<()> {
+        let mut file = match OpenOptions::new().read(true).write(true).open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        if complete < bytes.len() {
+            file.set_len(complete as u64)?;
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// One persisted snapshot. The envelope is kept as the exact JSON text that
+/// was signed, so verification never depends on re-serializing floats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    pub payload: String,
+    pub key_id: String,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Record `line` (0-based) is not valid JSON.
+    Corrupt {
+        line: usize,
+    },
+    /// Record `line` was signed with another key.
+    UnknownKey {
+        line: usize,
+        key_id: String,
+    },
+    /// Record `line` does not match its signature.
+    BadSignature {
+        line: usize,
+    },
+    /// A snapshot would move `agent_id` back to an older revision.
+    Rollback {
+        agent_id: Uuid,
+        revision: u64,
+        latest: u64,
+    },
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "io error: {e}"),
+            StoreError::Json(e) => write!(f, "json error: {e}"),
+            StoreError::Corrupt { line } => write!(f, "record {line}: corrupt"),
+            StoreError::UnknownKey { line, key_id } => {
+                write!(f, "record {line}: signed with unknown key {key_id}")
+            }
+            StoreError::BadSignature { line } => write!(f, "record {line}: signature mismatch"),
+            StoreError::Rollback {
+                agent_id,
+                revision,
+                latest,
+            } => write!(
+                f,
+                "agent {agent_id}: revision {revision} is not newer than {latest}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(err: serde_json::Error) -> Self {
+        StoreError::Json(err)
+    }
+}
+
+/// Signed, append-only history of blood-gate envelopes, keyed by agent.
+///
+/// `open` replays the backend and verifies every record, so a restarted
+/// process resumes from the newest valid snapshot. A torn final record
+/// (crash mid-append) is discarded from the backend first; anything else
+/// that fails to parse or verify, or that goes back in revision, aborts the
+/// load.
+pub struct EnvelopeStore<B, S> {
+    backend: B,
+    signer: S,
+    latest: HashMap<Uuid, BeeKarmaEnvelope>,
+}
+
+impl<B: EnvelopeBackend, S: EnvelopeSigner> EnvelopeStore<B, S> {
+    pub fn open(mut backend: B, signer: S) -> Result<Self, StoreError> {
+        backend.discard_torn_tail()?;
+        let records = backend.records()?;
+        let mut latest: HashMap<Uuid, BeeKarmaEnvelope> = HashMap::new();
+        for (line, record) in records.iter().enumerate() {
+            let signed: SignedEnvelope =
+                serde_json::from_str(record).map_err(|_| StoreError::Corrupt { line })?;
+            if signed.key_id != signer.key_id() {
+                return Err(StoreError::UnknownKey {
+                    line,
+                    key_id: signed.key_id,
+                });
+            }
+            if !signer.verify(signed.payload.as_bytes(), &signed.signature) {
+                return Err(StoreError::BadSignature { line });
+            }
+            let envelope: BeeKarmaEnvelope = serde_json::from_str(&signed.payload)?;
+            check_revision(latest.get(&envelope.agent_id), &envelope)?;
+            latest.insert(envelope.agent_id, envelope);
+        }
+        Ok(Self {
+            backend,
+            signer,
+            latest,
+        })
+    }
+
+    /// Newest verified envelope for `agent_id`.
+    pub fn latest(&self, agent_id: &Uuid) -> Option<&BeeKarmaEnvelope> {
+        self.latest.get(agent_id)
+    }
+
+    /// Sign and append `envelope`; its revision must exceed the stored one.
+    pub fn save(&mut self, envelope: &BeeKarmaEnvelope) -> Result<(), StoreError> {
+        check_revision(self.latest.get(&envelope.agent_id), envelope)?;
+        let payload = serde_json::to_string(envelope)?;
+        let signed = SignedEnvelope {
+            key_id: self.signer.key_id().to_string(),
+            signature: self.signer.sign(payload.as_bytes()),
+            payload,
+        };
+        self.backend.append(&serde_json::to_string(&signed)?)?;
+        self.latest.insert(envelope.agent_id, envelope.clone());
+        Ok(())
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+}
+
+fn check_revision(
+    previous: Option<&BeeKarmaEnvelope>,
+    next: &BeeKarmaEnvelope,
+) -> Result<(), StoreError> {
+    match previous {
+        Some(prev) if next.revision <= prev.revision => Err(StoreError::Rollback {
+            agent_id: next.agent_id,
+            revision: next.revision,
+            latest: prev.revision,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::bee::predicates::BloodGated;
+    use crate::bee::{BeeKarma, KarmaEventLog, KarmaReason};
+
+    /// Keyed hash standing in for a real signature; not secure.
+    struct TestSigner(u64);
+
+    impl EnvelopeSigner for TestSigner {
+        fn key_id(&self) -> &str {
+            "test-key"
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let mut h = DefaultHasher::new();
+            self.0.hash(&mut h);
+            message.hash(&mut h);
+            h.finish().to_be_bytes().to_vec()
+        }
+
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            self.sign(message) == signature
+        }
+    }
+
+    struct Agent(BeeKarmaEnvelope);
+
+    impl BloodGated for Agent {
+        fn envelope(&self) -> &BeeKarmaEnvelope {
+            &self.0
+        }
+        fn envelope_mut(&mut self) -> &mut BeeKarmaEnvelope {
+            &mut self.0
+        }
+    }
+
+    fn manual() -> KarmaReason {
+        KarmaReason::ManualAdjustment {
+            operator: "test".into(),
+        }
+    }
+
+    fn agent() -> Agent {
+        Agent(BeeKarmaEnvelope {
+            agent_id: Uuid::from_u128(42),
+            corridor_id: Uuid::from_u128(7),
+            kappa: BeeKarma(0.5),
+            last_update: DateTime::<Utc>::UNIX_EPOCH,
+            realized_harm_score: 0.0,
+            predicted_harm_score: 0.0,
+            blood_gate_level: 1,
+            promotion_pending_since: None,
+            revision: 0,
+            events: KarmaEventLog::default(),
+        })
+    }
+
+    #[test]
+    fn tampered_kappa_is_detected() {
+        let mut store = EnvelopeStore::open(MemoryBackend::default(), TestSigner(1)).unwrap();
+        let mut a = agent();
+        a.apply_karma_delta(-0.3, manual());
+        store.save(a.envelope()).unwrap();
+
+        let mut backend = store.into_backend();
+        let (genuine, forged) = (r#"\"kappa\":0.2"#, r#"\"kappa\":1.0"#);
+        backend.lines[0] = backend.lines[0].replace(genuine, forged);
+        assert!(backend.lines[0].contains(forged));
+        assert!(matches!(
+            EnvelopeStore::open(backend.clone(), TestSigner(1)),
+            Err(StoreError::BadSignature { line: 0 })
+        ));
+        // A different key cannot vouch for the original either.
+        backend.lines[0] = backend.lines[0].replace(forged, genuine);
+        assert!(EnvelopeStore::open(backend.clone(), TestSigner(1)).is_ok());
+        assert!(matches!(
+            EnvelopeStore::open(backend, TestSigner(2)),
+            Err(StoreError::BadSignature { line: 0 })
+        ));
+    }
+
+    #[test]
+    fn rollback_is_refused_on_save_and_load() {
+        let mut store = EnvelopeStore::open(MemoryBackend::default(), TestSigner(1)).unwrap();
+        let mut a = agent();
+        a.apply_karma_delta(0.1, manual());
+        let old = a.envelope().clone();
+        store.save(&old).unwrap();
+        a.apply_karma_delta(-0.2, manual());
+        store.save(a.envelope()).unwrap();
+
+        assert!(matches!(
+            store.save(&old),
+            Err(StoreError::Rollback {
+                revision: 1,
+                latest: 2,
+                ..
+            })
+        ));
+
+        // Replaying the old, validly signed record into the log.
+        let mut backend = store.into_backend();
+        let replay = backend.lines[0].clone();
+        backend.lines.push(replay);
+        let err = EnvelopeStore::open(backend, TestSigner(1)).err();
+        assert!(matches!(err, Some(StoreError::Rollback { .. })), "{err:?}");
+    }
+
+    #[test]
+    fn crash_recovers_latest_valid_snapshot() {
+        let path = std::env::temp_dir().join(format!("envelopes-{}.jsonl", Uuid::new_v4()));
+        let mut store = EnvelopeStore::open(AppendOnlyFile::new(&path), TestSigner(1)).unwrap();
+        let mut a = agent();
+        for delta in [0.1, 0.1, -0.05] {
+            a.apply_karma_delta(delta, manual());
+            store.save(a.envelope()).unwrap();
+        }
+        drop(store);
+        // Torn write: the process died halfway through a fourth record.
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"payload\":\"{\\\"agent_id")
+            .unwrap();
+
+        let mut store = EnvelopeStore::open(AppendOnlyFile::new(&path), TestSigner(1)).unwrap();
+        let latest = store.latest(&Uuid::from_u128(42)).unwrap();
+        assert_eq!(latest.revision, 3);
+        assert!((latest.kappa.0 - 0.65).abs() < 1e-12);
+
+        // The torn fragment is gone, so the next save is a record of its own.
+        a.apply_karma_delta(0.05, manual());
+        store.save(a.envelope()).unwrap();
+        drop(store);
+        let store = EnvelopeStore::open(AppendOnlyFile::new(&path), TestSigner(1)).unwrap();
+        assert_eq!(store.latest(&Uuid::from_u128(42)).unwrap().revision, 4);
+        assert_eq!(store.into_backend().records().unwrap().len(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "ed25519-dalek")]
+    #[test]
+    fn ed25519_signer_round_trips() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let mut store = EnvelopeStore::open(
+            MemoryBackend::default(),
+            Ed25519EnvelopeSigner::new("k1", key),
+        )
+        .unwrap();
+        let mut a = agent();
+        a.apply_karma_delta(0.1, manual());
+        store.save(a.envelope()).unwrap();
+        let backend = store.into_backend();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let store = EnvelopeStore::open(backend, Ed25519EnvelopeSigner::new("k1", key)).unwrap();
+        assert_eq!(store.latest(&Uuid::from_u128(42)).unwrap().revision, 1);
+    }
+}
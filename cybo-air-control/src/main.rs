@@ -2,6 +2,8 @@ use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use cyboair_corridor_safety::units::{unit_to_kg_factor_at_pressure, UnitError, P_STANDARD_PA};
+
 #[derive(Debug, Clone)]
 struct CyboAirRow {
     machineid: String,
@@ -27,20 +29,6 @@ struct NodeState {
     duty_cycle: f64,
 }
 
-/// Convert concentration units to kg/m^3 factor using Phoenix T and pollutant MW.
-/// For PM (µg/m3, mg/m3), MW is not needed; for ppb gases, MW (kg/mol) is required.
-fn unit_to_kg_factor(unit: &str, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    match unit {
-        "ugm3" | "µg/m3" => 1e-9,
-        "mgm3" | "mg/m3" => 1e-6,
-        "ppb" => {
-            let r = 8.3145_f64;
-            (molar_mass_kg_per_mol / (r * temperature_k)) * 1e-9
-        }
-        _ => 0.0,
-    }
-}
-
 /// Parse a CSV line into CyboAirRow. Assumes no embedded commas in unquoted fields.
 fn parse_csv_row(line: &str) -> Result<CyboAirRow, Box<dyn Error>> {
     let mut parts: Vec<String> = Vec::new();
@@ -88,6 +76,7 @@ fn parse_csv_row(line: &str) -> Result<CyboAirRow, Box<dyn Error>> {
 fn update_node(
     node: &mut NodeState,
     temperature_k: f64,
+    pressure_pa: f64,
     molar_mass_kg_per_mol: f64,
     m_ref: f64,
     k_ref: f64,
@@ -99,9 +88,10 @@ fn update_node(
     eta4: f64,
     alpha_eco: f64,
     k0_eco: f64,
-) {
+) -> Result<(), UnitError> {
     let r = &node.row;
-    let alpha = unit_to_kg_factor(&r.unit, temperature_k, molar_mass_kg_per_mol);
+    let alpha =
+        unit_to_kg_factor_at_pressure(&r.unit, temperature_k, pressure_pa, molar_mass_kg_per_mol)?;
     let d_c = (r.cin - r.cout).max(0.0);
     let c_u = alpha * d_c;
 
@@ -128,6 +118,7 @@ fn update_node(
         u = 1.0;
     }
     node.duty_cycle = u;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -157,6 +148,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Phoenix‑representative parameters
     let temperature_k = 310.0_f64;
+    let pressure_pa = P_STANDARD_PA; // replace with station pressure
     // For simplicity, use one MW for gases here; in production this is per‑pollutant
     let molar_mass_kg_per_mol = 0.048_f64; // ~O3 surrogate
 
@@ -193,6 +185,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         update_node(
             node,
             temperature_k,
+            pressure_pa,
             molar_mass_kg_per_mol,
             m_ref,
             k_ref,
@@ -204,7 +197,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             eta4,
             alpha_eco,
             k0_eco,
-        );
+        )?;
     }
 
     // Print control‑relevant summary for all five machine classes
@@ -0,0 +1,834 @@
+//! Parser for the documented subset of the ALN table format.
+//!
+//! Ceilings, bee risk weights and RoH stressors are published as signed
+//! `.aln` tables (`BeeNeuralCorridorPhoenix*.aln`, `*.rohmodel.aln`). The
+//! subset read here is a sequence of named sections of typed entries,
+//! closed by one signature block:
+//!
+//! ```text
+//! // BeeNeuralCorridorPhoenix risk weights
+//! section bee_risk_weights {
+//!   v_safe: f64 = 0.9;
+//!   host_budget: f64 = 1.0;   // trailing comments are allowed
+//! }
+//! section ceiling.BeeThermal {
+//!   dw_ceiling: f64 = 2.4;
+//! }
+//! signature {
+//!   signer: str = "ops@cyboair.org";
+//!   alg: str = "ed25519";
+//!   value: hex = "9f3a…";
+//! }
+//! ```
+//!
+//! * One entry per line, `key: type = value;`. Types are `f64`, `i64`,
+//!   `u64`, `bool`, `str` (double-quoted, `\"` and `\\` escapes) and `hex`.
+//!   Any other type is [`AlnError::UnknownType`].
+//! * Section names are unique, and so are keys within a section.
+//! * The signature covers every byte before the `signature` line and must
+//!   be the last block. [`AlnDocument::verify`] checks it with a
+//!   [`SignatureVerifier`]; a table is only read after that.
+//!
+//! Typed extraction is strict too: a known section with a missing, mistyped
+//! or unknown key is an error rather than a default.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    BeeRiskWeights, BeeThermalCeiling, CorridorCeiling, MarineLarvaeThermalCeiling, MetricFamily,
+};
+
+/// Section holding [`BeeRiskWeights`].
+pub const BEE_RISK_WEIGHTS: &str = "bee_risk_weights";
+/// Prefix of ceiling sections, followed by the family: `ceiling.BeeThermal`.
+pub const CEILING_PREFIX: &str = "ceiling.";
+/// Prefix of RoH stressor sections, followed by the stressor name.
+pub const ROH_STRESSOR_PREFIX: &str = "roh.stressor.";
+
+/// Declared type of an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlnType {
+    F64,
+    I64,
+    U64,
+    Bool,
+    Str,
+    Hex,
+}
+
+impl AlnType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "f64" => AlnType::F64,
+            "i64" => AlnType::I64,
+            "u64" => AlnType::U64,
+            "bool" => AlnType::Bool,
+            "str" => AlnType::Str,
+            "hex" => AlnType::Hex,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            AlnType::F64 => "f64",
+            AlnType::I64 => "i64",
+            AlnType::U64 => "u64",
+            AlnType::Bool => "bool",
+            AlnType::Str => "str",
+            AlnType::Hex => "hex",
+        }
+    }
+}
+
+impl fmt::Display for AlnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlnValue {
+    F64(f64),
+    I64(i64),
+    U64(u64),
+    Bool(bool),
+    Str(String),
+    Hex(Vec<u8>),
+}
+
+impl AlnValue {
+    pub fn ty(&self) -> AlnType {
+        match self {
+            AlnValue::F64(_) => AlnType::F64,
+            AlnValue::I64(_) => AlnType::I64,
+            AlnValue::U64(_) => AlnType::U64,
+            AlnValue::Bool(_) => AlnType::Bool,
+            AlnValue::Str(_) => AlnType::Str,
+            AlnValue::Hex(_) => AlnType::Hex,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlnError {
+    /// Line `line` (1-based) is not valid ALN.
+    Syntax {
+        line: usize,
+        message: &'static str,
+    },
+    UnknownType {
+        line: usize,
+        ty: String,
+    },
+    /// The value does not parse as its declared type.
+    BadValue {
+        line: usize,
+        key: String,
+        ty: AlnType,
+    },
+    DuplicateSection {
+        line: usize,
+        section: String,
+    },
+    DuplicateKey {
+        line: usize,
+        section: String,
+        key: String,
+    },
+    MissingSignature,
+    /// The signature block lacks `key`, or has it with the wrong type.
+    BadSignatureBlock {
+        key: &'static str,
+    },
+    SignatureMismatch {
+        signer: String,
+    },
+    MissingSection {
+        section: String,
+    },
+    MissingKey {
+        section: String,
+        key: String,
+    },
+    UnknownKey {
+        section: String,
+        key: String,
+    },
+    WrongType {
+        section: String,
+        key: String,
+        expected: AlnType,
+        found: AlnType,
+    },
+    /// The value parsed but is outside what the key allows.
+    OutOfRange {
+        section: String,
+        key: String,
+        value: f64,
+    },
+    /// A ceiling section names a family with no sealed ceiling.
+    UnknownCeiling {
+        family: String,
+    },
+}
+
+impl fmt::Display for AlnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlnError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            AlnError::UnknownType { line, ty } => write!(f, "line {line}: unknown type {ty}"),
+            AlnError::BadValue { line, key, ty } => {
+                write!(f, "line {line}: {key} is not a valid {ty}")
+            }
+            AlnError::DuplicateSection { line, section } => {
+                write!(f, "line {line}: duplicate section {section}")
+            }
+            AlnError::DuplicateKey { line, section, key } => {
+                write!(f, "line {line}: duplicate key {section}.{key}")
+            }
+            AlnError::MissingSignature => f.write_str("table has no signature block"),
+            AlnError::BadSignatureBlock { key } => {
+                write!(f, "signature block needs {key}")
+            }
+            AlnError::SignatureMismatch { signer } => {
+                write!(f, "signature by {signer} does not match the table")
+            }
+            AlnError::MissingSection { section } => write!(f, "missing section {section}"),
+            AlnError::MissingKey { section, key } => write!(f, "missing key {section}.{key}"),
+            AlnError::UnknownKey { section, key } => write!(f, "unknown key {section}.{key}"),
+            AlnError::WrongType {
+                section,
+                key,
+                expected,
+                found,
+            } => write!(f, "{section}.{key} is {found}, expected {expected}"),
+            AlnError::OutOfRange {
+                section,
+                key,
+                value,
+            } => write!(f, "{section}.{key} = {value} is out of range"),
+            AlnError::UnknownCeiling { family } => {
+                write!(f, "no sealed ceiling for family {family}")
+            }
+        }
+    }
+}
+
+/// Checks a table signature, e.g. against a registry of Ed25519 keys.
+pub trait SignatureVerifier {
+    /// True if `signature`, by `signer` under `alg`, covers `message`.
+    fn verify(&self, signer: &str, alg: &str, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// The trailing signature block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlnSignature {
+    pub signer: String,
+    pub alg: String,
+    pub value: Vec<u8>,
+}
+
+/// A named section's entries, in file order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlnSection {
+    pub name: String,
+    pub entries: Vec<(String, AlnValue)>,
+}
+
+impl AlnSection {
+    pub fn get(&self, key: &str) -> Option<&AlnValue> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// The `f64` entry `key`; missing or another type is an error.
+    pub fn f64(&self, key: &str) -> Result<f64, AlnError> {
+        match self.get(key) {
+            Some(AlnValue::F64(v)) => Ok(*v),
+            Some(v) => Err(self.wrong_type(key, AlnType::F64, v.ty())),
+            None => Err(AlnError::MissingKey {
+                section: self.name.clone(),
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    /// Like [`f64`](Self::f64), but `None` if the key is absent.
+    pub fn opt_f64(&self, key: &str) -> Result<Option<f64>, AlnError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(_) => self.f64(key).map(Some),
+        }
+    }
+
+    /// Refuse keys other than `known`.
+    pub fn only_keys(&self, known: &[&str]) -> Result<(), AlnError> {
+        match self
+            .entries
+            .iter()
+            .find(|(k, _)| !known.contains(&k.as_str()))
+        {
+            Some((key, _)) => Err(AlnError::UnknownKey {
+                section: self.name.clone(),
+                key: key.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn wrong_type(&self, key: &str, expected: AlnType, found: AlnType) -> AlnError {
+        AlnError::WrongType {
+            section: self.name.clone(),
+            key: key.to_string(),
+            expected,
+            found,
+        }
+    }
+
+    fn out_of_range(&self, key: &str, value: f64) -> AlnError {
+        AlnError::OutOfRange {
+            section: self.name.clone(),
+            key: key.to_string(),
+            value,
+        }
+    }
+}
+
+/// A parsed table whose signature has not been checked yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlnDocument {
+    table: AlnTable,
+    signature: Option<AlnSignature>,
+    signed: String,
+}
+
+impl AlnDocument {
+    pub fn parse(text: &str) -> Result<Self, AlnError> {
+        Parser::default().parse(text)
+    }
+
+    pub fn signature(&self) -> Option<&AlnSignature> {
+        self.signature.as_ref()
+    }
+
+    /// Bytes the signature covers: everything before the `signature` line.
+    pub fn signed_bytes(&self) -> &[u8] {
+        self.signed.as_bytes()
+    }
+
+    /// The table, once `verifier` accepts its signature.
+    pub fn verify(self, verifier: &impl SignatureVerifier) -> Result<AlnTable, AlnError> {
+        let sig = self.signature.ok_or(AlnError::MissingSignature)?;
+        if verifier.verify(&sig.signer, &sig.alg, self.signed.as_bytes(), &sig.value) {
+            Ok(self.table)
+        } else {
+            Err(AlnError::SignatureMismatch { signer: sig.signer })
+        }
+    }
+
+    /// The table without checking any signature, for tooling and tests.
+    pub fn into_unverified(self) -> AlnTable {
+        self.table
+    }
+}
+
+/// Validated ceiling for a family with a sealed [`CorridorCeiling`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CeilingOverride {
+    pub family: MetricFamily,
+    /// Within the family's `[DW_CEILING_MIN, DW_CEILING_MAX]`.
+    pub dw_ceiling: f64,
+}
+
+/// Sections of a verified table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AlnTable {
+    pub sections: Vec<AlnSection>,
+}
+
+impl AlnTable {
+    pub fn section(&self, name: &str) -> Option<&AlnSection> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Sections named `prefix` + suffix, with their suffixes, in file order.
+    pub fn sections_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a AlnSection)> + 'a {
+        self.sections
+            .iter()
+            .filter_map(move |s| s.name.strip_prefix(prefix).map(|rest| (rest, s)))
+    }
+
+    /// The `bee_risk_weights` section: `v_safe` positive, each index weight
+    /// finite and non-negative.
+    pub fn bee_risk_weights(&self) -> Result<BeeRiskWeights, AlnError> {
+        let s = self
+            .section(BEE_RISK_WEIGHTS)
+            .ok_or_else(|| AlnError::MissingSection {
+                section: BEE_RISK_WEIGHTS.to_string(),
+            })?;
+        s.only_keys(&["v_safe", "host_budget", "eco_band", "dw_ceiling"])?;
+        let v_safe = s.f64("v_safe")?;
+        if !(v_safe.is_finite() && v_safe > 0.0) {
+            return Err(s.out_of_range("v_safe", v_safe));
+        }
+        let weight = |key| {
+            let w = s.f64(key)?;
+            if w.is_finite() && w >= 0.0 {
+                Ok(w)
+            } else {
+                Err(s.out_of_range(key, w))
+            }
+        };
+        Ok(BeeRiskWeights {
+            v_safe,
+            host_budget: weight("host_budget")?,
+            eco_band: weight("eco_band")?,
+            dw_ceiling: weight("dw_ceiling")?,
+        })
+    }
+
+    /// Every `ceiling.<Family>` section. A table can only move a ceiling
+    /// within its sealed bounds, never relax it past them.
+    pub fn ceiling_overrides(&self) -> Result<Vec<CeilingOverride>, AlnError> {
+        self.sections_with_prefix(CEILING_PREFIX)
+            .map(|(name, s)| {
+                let (family, min, max) =
+                    sealed_ceiling(name).ok_or_else(|| AlnError::UnknownCeiling {
+                        family: name.to_string(),
+                    })?;
+                s.only_keys(&["dw_ceiling"])?;
+                let dw_ceiling = s.f64("dw_ceiling")?;
+                if !(min..=max).contains(&dw_ceiling) {
+                    return Err(s.out_of_range("dw_ceiling", dw_ceiling));
+                }
+                Ok(CeilingOverride { family, dw_ceiling })
+            })
+            .collect()
+    }
+}
+
+fn sealed_ceiling(family: &str) -> Option<(MetricFamily, f64, f64)> {
+    fn bounds<C: CorridorCeiling>() -> (MetricFamily, f64, f64) {
+        (C::FAMILY, C::DW_CEILING_MIN, C::DW_CEILING_MAX)
+    }
+    match family {
+        "BeeThermal" => Some(bounds::<BeeThermalCeiling>()),
+        "MarineThermal" => Some(bounds::<MarineLarvaeThermalCeiling>()),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    sections: Vec<AlnSection>,
+    signature: Option<(usize, Vec<(String, AlnValue)>)>,
+}
+
+/// Where the parser is between lines.
+enum Block {
+    Top,
+    Section(AlnSection),
+    Signature(Vec<(String, AlnValue)>),
+    Done,
+}
+
+impl Parser {
+    fn parse(mut self, text: &str) -> Result<AlnDocument, AlnError> {
+        let mut block = Block::Top;
+        let mut offset = 0;
+        for (index, raw) in text.split_inclusive('\n').enumerate() {
+            let line = index + 1;
+            let start = offset;
+            offset += raw.len();
+            let content = strip_comment(raw).trim();
+            if content.is_empty() {
+                continue;
+            }
+            block = match block {
+                Block::Top => self.open(line, start, content)?,
+                Block::Section(section) if content == "}" => {
+                    self.sections.push(section);
+                    Block::Top
+                }
+                Block::Section(mut section) => {
+                    let (key, value) = entry(line, content)?;
+                    push_unique(line, &section.name, &mut section.entries, key, value)?;
+                    Block::Section(section)
+                }
+                Block::Signature(entries) if content == "}" => {
+                    if let Some((_, closed)) = &mut self.signature {
+                        *closed = entries;
+                    }
+                    Block::Done
+                }
+                Block::Signature(mut entries) => {
+                    let (key, value) = entry(line, content)?;
+                    push_unique(line, "signature", &mut entries, key, value)?;
+                    Block::Signature(entries)
+                }
+                Block::Done => {
+                    return Err(AlnError::Syntax {
+                        line,
+                        message: "content after the signature block",
+                    })
+                }
+            };
+        }
+        let (signature, signed) = match (block, self.signature) {
+            (Block::Top, _) => (None, text),
+            (Block::Done, Some((start, entries))) => {
+                (Some(signature_block(&entries)?), &text[..start])
+            }
+            _ => {
+                return Err(AlnError::Syntax {
+                    line: text.lines().count(),
+                    message: "unclosed block",
+                })
+            }
+        };
+        Ok(AlnDocument {
+            table: AlnTable {
+                sections: self.sections,
+            },
+            signature,
+            signed: signed.to_string(),
+        })
+    }
+
+    fn open(&mut self, line: usize, start: usize, content: &str) -> Result<Block, AlnError> {
+        let syntax = |message| AlnError::Syntax { line, message };
+        let head = content
+            .strip_suffix('{')
+            .ok_or(syntax("expected `section <name> {` or `signature {`"))?
+            .trim();
+        if head == "signature" {
+            self.signature = Some((start, Vec::new()));
+            return Ok(Block::Signature(Vec::new()));
+        }
+        let name = head
+            .strip_prefix("section")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim)
+            .ok_or(syntax("expected `section <name> {` or `signature {`"))?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(syntax("section names are letters, digits, `_` and `.`"));
+        }
+        if self.sections.iter().any(|s| s.name == name) {
+            return Err(AlnError::DuplicateSection {
+                line,
+                section: name.to_string(),
+            });
+        }
+        Ok(Block::Section(AlnSection {
+            name: name.to_string(),
+            entries: Vec::new(),
+        }))
+    }
+}
+
+fn push_unique(
+    line: usize,
+    section: &str,
+    entries: &mut Vec<(String, AlnValue)>,
+    key: String,
+    value: AlnValue,
+) -> Result<(), AlnError> {
+    if entries.iter().any(|(k, _)| *k == key) {
+        return Err(AlnError::DuplicateKey {
+            line,
+            section: section.to_string(),
+            key,
+        });
+    }
+    entries.push((key, value));
+    Ok(())
+}
+
+fn signature_block(entries: &[(String, AlnValue)]) -> Result<AlnSignature, AlnError> {
+    let get = |key: &'static str| {
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or(AlnError::BadSignatureBlock { key })
+    };
+    let text = |key| match get(key)? {
+        AlnValue::Str(s) => Ok(s.clone()),
+        _ => Err(AlnError::BadSignatureBlock { key }),
+    };
+    let value = match get("value")? {
+        AlnValue::Hex(bytes) => bytes.clone(),
+        _ => return Err(AlnError::BadSignatureBlock { key: "value" }),
+    };
+    Ok(AlnSignature {
+        signer: text("signer")?,
+        alg: text("alg")?,
+        value,
+    })
+}
+
+/// `line` without a `//` comment outside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut escaped = false;
+    let bytes = line.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if in_str => escaped = true,
+            b'"' => in_str = !in_str,
+            b'/' if !in_str && bytes.get(i + 1) == Some(&b'/') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// `key: type = value;`
+fn entry(line: usize, content: &str) -> Result<(String, AlnValue), AlnError> {
+    let syntax = |message| AlnError::Syntax { line, message };
+    let body = content
+        .strip_suffix(';')
+        .ok_or(syntax("entries end with `;`"))?;
+    let (key, rest) = body
+        .split_once(':')
+        .ok_or(syntax("expected `key: type = value;`"))?;
+    let (ty, value) = rest
+        .split_once('=')
+        .ok_or(syntax("expected `key: type = value;`"))?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(syntax("keys are letters, digits and `_`"));
+    }
+    let ty = ty.trim();
+    let ty = AlnType::parse(ty).ok_or_else(|| AlnError::UnknownType {
+        line,
+        ty: ty.to_string(),
+    })?;
+    let value = parse_value(ty, value.trim()).ok_or_else(|| AlnError::BadValue {
+        line,
+        key: key.to_string(),
+        ty,
+    })?;
+    Ok((key.to_string(), value))
+}
+
+fn parse_value(ty: AlnType, raw: &str) -> Option<AlnValue> {
+    Some(match ty {
+        AlnType::F64 => AlnValue::F64(raw.parse().ok().filter(|v: &f64| v.is_finite())?),
+        AlnType::I64 => AlnValue::I64(raw.parse().ok()?),
+        AlnType::U64 => AlnValue::U64(raw.parse().ok()?),
+        AlnType::Bool => AlnValue::Bool(match raw {
+            "true" => true,
+            "false" => false,
+            _ => return None,
+        }),
+        AlnType::Str => AlnValue::Str(unquote(raw)?),
+        AlnType::Hex => {
+            let digits = unquote(raw)?;
+            if digits.len() % 2 != 0 {
+                return None;
+            }
+            let bytes = (0..digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            AlnValue::Hex(bytes)
+        }
+    })
+}
+
+/// Body of a `"…"` literal with `\"` and `\\` unescaped.
+fn unquote(raw: &str) -> Option<String> {
+    let body = raw.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c @ ('"' | '\\') => out.push(c),
+                _ => return None,
+            },
+            '"' => return None,
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BeeCorridorInvariant;
+
+    const SIGNED: &str = include_str!("../fixtures/aln/BeeNeuralCorridorPhoenix.aln");
+    const TAMPERED: &str = include_str!("../fixtures/aln/BeeNeuralCorridorPhoenix.tampered.aln");
+    const DUPLICATE_KEY: &str = include_str!("../fixtures/aln/duplicate_key.aln");
+
+    /// Keyed FNV-1a stand-in for a real signature scheme: one known signer,
+    /// the digest of its key followed by the message.
+    struct TestKeys;
+
+    impl TestKeys {
+        fn sign(message: &[u8]) -> u64 {
+            b"phoenix-test-key"
+                .iter()
+                .chain(message)
+                .fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+                    (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+                })
+        }
+    }
+
+    impl SignatureVerifier for TestKeys {
+        fn verify(&self, signer: &str, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+            signer == "ops@cyboair.org"
+                && alg == "fnv1a-64-keyed"
+                && signature == Self::sign(message).to_be_bytes()
+        }
+    }
+
+    #[test]
+    fn signed_fixture_parses_into_typed_parameters() {
+        let doc = AlnDocument::parse(SIGNED).unwrap();
+        assert_eq!(doc.signature().unwrap().signer, "ops@cyboair.org");
+        let table = doc.verify(&TestKeys).unwrap();
+
+        let weights = table.bee_risk_weights().unwrap();
+        assert_eq!(
+            weights,
+            BeeRiskWeights {
+                v_safe: 0.9,
+                host_budget: 1.0,
+                eco_band: 0.8,
+                dw_ceiling: 1.2,
+            }
+        );
+        let inv = BeeCorridorInvariant::from(weights);
+        assert_eq!((inv.v_safe, inv.weights), (0.9, [1.0, 0.8, 1.2]));
+
+        assert_eq!(
+            table.ceiling_overrides().unwrap(),
+            [CeilingOverride {
+                family: MetricFamily::BeeThermal,
+                dw_ceiling: 2.4,
+            }]
+        );
+        let stressors: Vec<_> = table
+            .sections_with_prefix(ROH_STRESSOR_PREFIX)
+            .map(|(name, s)| (name, s.f64("weight").unwrap()))
+            .collect();
+        assert_eq!(stressors, [("pm25", 0.4), ("noise", 0.2)]);
+        let notes = table.section("provenance").unwrap();
+        assert_eq!(
+            notes.get("source"),
+            Some(&AlnValue::Str("Phoenix \"North\" apiary survey".into()))
+        );
+        assert_eq!(notes.get("revision"), Some(&AlnValue::U64(3)));
+    }
+
+    #[test]
+    fn tampered_tables_fail_verification() {
+        let doc = AlnDocument::parse(TAMPERED).unwrap();
+        // Parses fine: only the signature gives it away.
+        assert_eq!(
+            doc.clone()
+                .into_unverified()
+                .bee_risk_weights()
+                .unwrap()
+                .v_safe,
+            9.0
+        );
+        assert_eq!(
+            doc.verify(&TestKeys),
+            Err(AlnError::SignatureMismatch {
+                signer: "ops@cyboair.org".into()
+            })
+        );
+
+        let unsigned = SIGNED.split("signature {").next().unwrap();
+        assert_eq!(
+            AlnDocument::parse(unsigned).unwrap().verify(&TestKeys),
+            Err(AlnError::MissingSignature)
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_and_unknown_types_are_refused() {
+        assert_eq!(
+            AlnDocument::parse(DUPLICATE_KEY),
+            Err(AlnError::DuplicateKey {
+                line: 5,
+                section: "bee_risk_weights".into(),
+                key: "v_safe".into(),
+            })
+        );
+        let err = AlnDocument::parse("section s {\n  x: f32 = 1.0;\n}\n").unwrap_err();
+        assert_eq!(
+            err,
+            AlnError::UnknownType {
+                line: 2,
+                ty: "f32".into()
+            }
+        );
+        let twice = "section s {\n}\nsection s {\n}\n";
+        assert!(matches!(
+            AlnDocument::parse(twice),
+            Err(AlnError::DuplicateSection { line: 3, .. })
+        ));
+        let trailing = "signature {\n}\nsection s {\n}\n";
+        assert!(matches!(
+            AlnDocument::parse(trailing),
+            Err(AlnError::Syntax { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn typed_extraction_is_strict() {
+        let table = |body: &str| AlnDocument::parse(body).unwrap().into_unverified();
+        let weights = "section bee_risk_weights {\n  v_safe: f64 = 1.0;\n  host_budget: f64 = 1.0;\n  eco_band: f64 = 1.0;\n";
+        assert!(matches!(
+            table(&(weights.to_string() + "}\n")).bee_risk_weights(),
+            Err(AlnError::MissingKey { .. })
+        ));
+        assert!(matches!(
+            table(&(weights.to_string() + "  dw_ceiling: i64 = 1;\n}\n")).bee_risk_weights(),
+            Err(AlnError::WrongType {
+                expected: AlnType::F64,
+                found: AlnType::I64,
+                ..
+            })
+        ));
+        assert!(matches!(
+            table(&(weights.to_string() + "  dw_ceiling: f64 = 1.0;\n  tdi: f64 = 1.0;\n}\n"))
+                .bee_risk_weights(),
+            Err(AlnError::UnknownKey { .. })
+        ));
+        // Ceilings move within the sealed bounds only.
+        for (body, ok) in [
+            (
+                "section ceiling.BeeThermal {\n  dw_ceiling: f64 = 3.5;\n}\n",
+                false,
+            ),
+            (
+                "section ceiling.MarineThermal {\n  dw_ceiling: f64 = 1.0;\n}\n",
+                true,
+            ),
+            (
+                "section ceiling.UrbanNOx {\n  dw_ceiling: f64 = 1.0;\n}\n",
+                false,
+            ),
+        ] {
+            assert_eq!(table(body).ceiling_overrides().is_ok(), ok, "{body}");
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Delta-coded envelope telemetry for byte-starved links.
+//!
+//! A [`DeltaEncoder`] turns a stream of [`BeeEnvelope`]s into
+//! [`EnvelopeFrame`]s: a full keyframe, then deltas carrying only the
+//! indices that moved, as whole multiples of a `2^-step_shift` step
+//! (`step_shift = 10` is 1/1024). Deltas are taken against what the receiver
+//! has reconstructed rather than the previous true sample, so quantization
+//! error does not accumulate: between keyframes every reconstructed index is
+//! within `step / 2` of the true one (plus f64 rounding of the running sum,
+//! ~1e-15 per frame). A keyframe is forced every `keyframe_interval` frames,
+//! on a new trace lineage or metric family, and whenever a change does not
+//! fit in an `i16` of steps, which also bounds how long a lost frame can
+//! corrupt the receiver's view.
+//!
+//! A delta follows one step of a lineage: the current envelope's
+//! `parent_id` is the previous one's `trace_id`, as [`crate::spine`] links
+//! consecutive steps. Deltas carry the new trace id and sequence, so the
+//! receiver's reconstruction has the sender's lineage, not the keyframe's.
+//!
+//! Frames on the wire are `[FRAME_VERSION, frame type, postcard payload]`,
+//! the payload being the plain spine encoding of a keyframe's envelope or
+//! of the delta. Version 1 frames, from before lineage, still decode: their
+//! keyframes as roots and their deltas as [`LegacyDelta`]s.
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use uuid::Uuid;
+
+use crate::{BeeEnvelope, BeeEnvelopeV1, BinaryEcoTrace, EncodeError, EnvelopePoint};
+
+/// Version byte leading every envelope frame. Version 2 envelopes and
+/// deltas carry their lineage.
+pub const FRAME_VERSION: u8 = 2;
+
+/// Version of frames whose payloads predate lineage.
+pub const LEGACY_FRAME_VERSION: u8 = 1;
+
+/// Second byte of an envelope frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum FrameType {
+    Keyframe = 0,
+    Delta = 1,
+}
+
+impl FrameType {
+    pub fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            0 => Some(FrameType::Keyframe),
+            1 => Some(FrameType::Delta),
+            _ => None,
+        }
+    }
+}
+
+/// Errors from [`EnvelopeFrame::from_wire`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    /// Fewer than the two header bytes.
+    Truncated,
+    UnsupportedVersion(u8),
+    UnknownFrameType(u8),
+    /// The payload is not a valid postcard envelope or delta.
+    Deserialize,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Truncated => write!(f, "envelope frame shorter than its header"),
+            FrameError::UnsupportedVersion(v) => write!(f, "unsupported frame version {v}"),
+            FrameError::UnknownFrameType(t) => write!(f, "unknown frame type {t}"),
+            FrameError::Deserialize => write!(f, "malformed frame payload"),
+        }
+    }
+}
+
+/// Change from an envelope to its child, in quantization steps.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EnvelopeDelta {
+    /// The step is `2^-step_shift`.
+    pub step_shift: u8,
+    /// Steps per index, ordered as [`EnvelopePoint`]; `None` if unchanged.
+    pub steps: [Option<i16>; 3],
+    /// The child's trace id; its parent is the envelope the delta applies to.
+    pub trace_id: Uuid,
+    /// The child's sequence.
+    pub sequence: u32,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for EnvelopeDelta {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "EnvelopeDelta {{ step_shift: {}, steps: {}, trace_id: {=[u8]:02x}, sequence: {} }}",
+            self.step_shift,
+            self.steps,
+            &self.trace_id.as_bytes()[..],
+            self.sequence
+        );
+    }
+}
+
+/// Quantization step for `step_shift`.
+pub fn quant_step(step_shift: u8) -> f64 {
+    // Halving is exact until the subnormal range.
+    let mut step = 1.0;
+    for _ in 0..step_shift {
+        step /= 2.0;
+    }
+    step
+}
+
+fn indices(env: &BeeEnvelope) -> EnvelopePoint {
+    [env.band.host_budget, env.band.eco_band, env.band.dw_ceiling].map(f64::from)
+}
+
+fn round_to_i16(x: f64) -> Option<i16> {
+    if !x.is_finite() {
+        return None;
+    }
+    let r = if x >= 0.0 { x + 0.5 } else { x - 0.5 } as i64;
+    i16::try_from(r).ok()
+}
+
+impl EnvelopeDelta {
+    /// Delta taking `prev` to within `step / 2` of `current`, or `None` if
+    /// `current` is not `prev`'s child (its `parent_id` is not `prev`'s trace
+    /// id, or the family differs), an index is not finite, or a change
+    /// exceeds `i16::MAX` steps.
+    pub fn between(prev: &BeeEnvelope, current: &BeeEnvelope, step_shift: u8) -> Option<Self> {
+        if current.parent_id != Some(prev.trace_id) || prev.band.family != current.band.family {
+            return None;
+        }
+        let step = quant_step(step_shift);
+        let (from, to) = (indices(prev), indices(current));
+        let mut steps = [None; 3];
+        for axis in 0..3 {
+            let q = round_to_i16((to[axis] - from[axis]) / step)?;
+            steps[axis] = (q != 0).then_some(q);
+        }
+        Some(EnvelopeDelta {
+            step_shift,
+            steps,
+            trace_id: current.trace_id,
+            sequence: current.sequence,
+        })
+    }
+
+    /// The child of `prev` this delta describes: indices moved and clamped
+    /// into [0, 1], the delta's trace id and sequence, `prev` as parent and
+    /// `prev`'s source.
+    pub fn apply(&self, prev: &BeeEnvelope) -> BeeEnvelope {
+        let mut next = prev.clone();
+        next.trace_id = self.trace_id;
+        next.parent_id = Some(prev.trace_id);
+        next.sequence = self.sequence;
+        move_indices(&mut next, self.step_shift, self.steps);
+        next
+    }
+}
+
+/// Version 1 delta, which has no lineage of its own.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LegacyDelta {
+    pub step_shift: u8,
+    pub steps: [Option<i16>; 3],
+}
+
+impl LegacyDelta {
+    /// `prev` with its indices moved, keeping its trace fields as version 1
+    /// receivers did.
+    pub fn apply(&self, prev: &BeeEnvelope) -> BeeEnvelope {
+        let mut next = prev.clone();
+        move_indices(&mut next, self.step_shift, self.steps);
+        next
+    }
+}
+
+fn move_indices(env: &mut BeeEnvelope, step_shift: u8, steps: [Option<i16>; 3]) {
+    let step = quant_step(step_shift);
+    let band = &mut env.band;
+    for (index, steps) in [
+        &mut band.host_budget,
+        &mut band.eco_band,
+        &mut band.dw_ceiling,
+    ]
+    .into_iter()
+    .zip(steps)
+    {
+        if let Some(s) = steps {
+            *index = index.saturating_add(f64::from(s) * step);
+        }
+    }
+}
+
+/// One frame of a delta-coded envelope stream.
+#[derive(Clone, Debug)]
+pub enum EnvelopeFrame {
+    Keyframe(BeeEnvelope),
+    Delta(EnvelopeDelta),
+    /// Decoded from a version 1 frame; written back as one.
+    LegacyDelta(LegacyDelta),
+}
+
+impl EnvelopeFrame {
+    /// Header plus the largest payload, a keyframe.
+    pub const MAX_WIRE_LEN: usize = 2 + BeeEnvelope::MAX_WIRE_LEN;
+
+    pub fn frame_type(&self) -> FrameType {
+        match self {
+            EnvelopeFrame::Keyframe(_) => FrameType::Keyframe,
+            EnvelopeFrame::Delta(_) | EnvelopeFrame::LegacyDelta(_) => FrameType::Delta,
+        }
+    }
+
+    /// Receiver state after this frame; `None` for a delta with nothing to
+    /// apply it to.
+    pub fn reconstruct(&self, prev: Option<&BeeEnvelope>) -> Option<BeeEnvelope> {
+        match self {
+            EnvelopeFrame::Keyframe(env) => Some(env.clone()),
+            EnvelopeFrame::Delta(delta) => prev.map(|p| delta.apply(p)),
+            EnvelopeFrame::LegacyDelta(delta) => prev.map(|p| delta.apply(p)),
+        }
+    }
+
+    /// Encode into `buf`, returning the number of bytes written.
+    pub fn to_wire_slice(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let [version, kind, payload @ ..] = buf else {
+            return Err(EncodeError::BufferTooSmall);
+        };
+        *version = match self {
+            EnvelopeFrame::LegacyDelta(_) => LEGACY_FRAME_VERSION,
+            _ => FRAME_VERSION,
+        };
+        *kind = self.frame_type() as u8;
+        let len = match self {
+            EnvelopeFrame::Keyframe(env) => env.to_wire_slice(payload)?,
+            EnvelopeFrame::Delta(delta) => postcard::to_slice(delta, payload)?.len(),
+            EnvelopeFrame::LegacyDelta(delta) => postcard::to_slice(delta, payload)?.len(),
+        };
+        Ok(2 + len)
+    }
+
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, FrameError> {
+        let [version, kind, payload @ ..] = bytes else {
+            return Err(FrameError::Truncated);
+        };
+        let de = |_| FrameError::Deserialize;
+        let kind = FrameType::from_byte(*kind).ok_or(FrameError::UnknownFrameType(*kind));
+        match *version {
+            FRAME_VERSION => match kind? {
+                FrameType::Keyframe => Ok(EnvelopeFrame::Keyframe(
+                    postcard::from_bytes(payload).map_err(de)?,
+                )),
+                FrameType::Delta => Ok(EnvelopeFrame::Delta(
+                    postcard::from_bytes(payload).map_err(de)?,
+                )),
+            },
+            LEGACY_FRAME_VERSION => match kind? {
+                FrameType::Keyframe => Ok(EnvelopeFrame::Keyframe(
+                    postcard::from_bytes::<BeeEnvelopeV1>(payload)
+                        .map_err(de)?
+                        .into(),
+                )),
+                FrameType::Delta => Ok(EnvelopeFrame::LegacyDelta(
+                    postcard::from_bytes(payload).map_err(de)?,
+                )),
+            },
+            v => Err(FrameError::UnsupportedVersion(v)),
+        }
+    }
+}
+
+/// Sender side of a delta-coded stream; tracks what the receiver holds.
+#[derive(Clone, Debug)]
+pub struct DeltaEncoder {
+    step_shift: u8,
+    keyframe_interval: u16,
+    reference: Option<BeeEnvelope>,
+    since_keyframe: u16,
+}
+
+impl DeltaEncoder {
+    /// Quantize to `2^-step_shift` and send a keyframe at least every
+    /// `keyframe_interval` frames (0 or 1 sends only keyframes).
+    pub fn new(step_shift: u8, keyframe_interval: u16) -> Self {
+        DeltaEncoder {
+            step_shift,
+            keyframe_interval,
+            reference: None,
+            since_keyframe: 0,
+        }
+    }
+
+    /// Largest per-index error of the receiver's reconstruction between
+    /// keyframes, ignoring f64 rounding.
+    pub fn error_bound(&self) -> f64 {
+        quant_step(self.step_shift) / 2.0
+    }
+
+    /// Next frame for `current`.
+    pub fn encode(&mut self, current: &BeeEnvelope) -> EnvelopeFrame {
+        self.since_keyframe += 1;
+        let delta = match &self.reference {
+            Some(reference) if self.since_keyframe < self.keyframe_interval => {
+                EnvelopeDelta::between(reference, current, self.step_shift)
+                    .map(|d| (d.apply(reference), d))
+            }
+            _ => None,
+        };
+        match delta {
+            Some((reconstructed, d)) => {
+                self.reference = Some(reconstructed);
+                EnvelopeFrame::Delta(d)
+            }
+            None => {
+                self.reference = Some(current.clone());
+                self.since_keyframe = 0;
+                EnvelopeFrame::Keyframe(current.clone())
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{BeeBand, DeterministicRng, MetricFamily};
+    use uuid::Uuid;
+
+    fn envelope(p: EnvelopePoint) -> BeeEnvelope {
+        BeeEnvelope {
+            band: BeeBand::try_new(MetricFamily::BeeThermal, p[0], p[1], p[2]).unwrap(),
+            trace_id: Uuid::from_u128(0x0b47),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
+        }
+    }
+
+    /// `p` as the step after `parent`.
+    fn child(parent: &BeeEnvelope, p: EnvelopePoint) -> BeeEnvelope {
+        BeeEnvelope {
+            trace_id: Uuid::from_u128(parent.trace_id.as_u128() + 1),
+            parent_id: Some(parent.trace_id),
+            sequence: parent.sequence + 1,
+            ..envelope(p)
+        }
+    }
+
+    #[test]
+    fn deltas_carry_the_senders_lineage() {
+        let mut encoder = DeltaEncoder::new(10, 100);
+        let mut rng = DeterministicRng::new(7);
+        let mut sent = envelope([0.4, 0.6, 0.3]);
+        let mut received: Option<BeeEnvelope> = None;
+        let mut buf = [0u8; EnvelopeFrame::MAX_WIRE_LEN];
+        for i in 0..20 {
+            if i > 0 {
+                let p = indices(&sent).map(|x| x + (rng.next_f64() - 0.5) * 2e-3);
+                sent = child(&sent, p);
+            }
+            let frame = encoder.encode(&sent);
+            assert_eq!(frame.frame_type() == FrameType::Keyframe, i == 0);
+            let len = frame.to_wire_slice(&mut buf).unwrap();
+            received = EnvelopeFrame::from_wire(&buf[..len])
+                .unwrap()
+                .reconstruct(received.as_ref());
+            let got = received.as_ref().unwrap();
+            assert_eq!(got.trace_id, sent.trace_id);
+            assert_eq!(got.parent_id, sent.parent_id);
+            assert_eq!(got.sequence, sent.sequence);
+        }
+    }
+
+    #[test]
+    fn keyframes_are_forced_by_interval_lineage_and_range() {
+        let mut encoder = DeltaEncoder::new(10, 4);
+        let base = envelope([0.4, 0.6, 0.3]);
+        let mut current = base.clone();
+        let kinds: Vec<_> = (0..9)
+            .map(|_| {
+                let kind = encoder.encode(&current).frame_type();
+                current = child(&current, [0.4, 0.6, 0.3]);
+                kind
+            })
+            .collect();
+        assert_eq!(
+            kinds.iter().filter(|k| **k == FrameType::Keyframe).count(),
+            3
+        );
+
+        // Only a child follows its parent: not a repeat, not a stranger.
+        assert_eq!(EnvelopeDelta::between(&base, &base, 10), None);
+        let mut stranger = child(&base, [0.4, 0.6, 0.3]);
+        stranger.parent_id = Some(Uuid::from_u128(0x0bad));
+        assert_eq!(EnvelopeDelta::between(&base, &stranger, 10), None);
+        // 0.6 is more than i16::MAX steps of 2^-16.
+        let far = child(&base, [1.0, 0.6, 0.3]);
+        assert_eq!(EnvelopeDelta::between(&base, &far, 16), None);
+
+        let mut encoder = DeltaEncoder::new(16, 100);
+        encoder.encode(&base);
+        assert_eq!(encoder.encode(&far).frame_type(), FrameType::Keyframe);
+    }
+
+    #[test]
+    fn deltas_carry_only_changed_indices_and_are_small() {
+        let prev = envelope([0.4, 0.6, 0.3]);
+        let cur = child(&prev, [0.4 + 3.0 / 1024.0, 0.6, 0.3]);
+        let delta = EnvelopeDelta::between(&prev, &cur, 10).unwrap();
+        assert_eq!(delta.steps, [Some(3), None, None]);
+
+        let mut buf = [0u8; EnvelopeFrame::MAX_WIRE_LEN];
+        let delta_len = EnvelopeFrame::Delta(delta).to_wire_slice(&mut buf).unwrap();
+        assert_eq!(&buf[..2], &[FRAME_VERSION, FrameType::Delta as u8]);
+        let full_len = cur.to_wire_bytes().len();
+        let key_len = EnvelopeFrame::Keyframe(cur)
+            .to_wire_slice(&mut buf)
+            .unwrap();
+        assert_eq!(key_len, 2 + full_len);
+        // Header, shift, three indices, the 17-byte trace id and a sequence.
+        assert!(delta_len <= 25, "{delta_len}");
+        assert!(2 * delta_len < key_len + 2, "{delta_len} vs {key_len}");
+    }
+
+    #[test]
+    fn version_1_frames_still_decode() {
+        let mut keyframe = vec![LEGACY_FRAME_VERSION, FrameType::Keyframe as u8];
+        keyframe.extend_from_slice(&crate::tests::BASELINE_FRAME);
+        let base = EnvelopeFrame::from_wire(&keyframe)
+            .unwrap()
+            .reconstruct(None)
+            .unwrap();
+        assert_eq!(
+            base.trace_id,
+            Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef)
+        );
+        assert_eq!(base.parent_id, None);
+
+        // Shift 10, +3 steps on the host budget only.
+        let delta = [LEGACY_FRAME_VERSION, FrameType::Delta as u8, 10, 1, 6, 0, 0];
+        let frame = EnvelopeFrame::from_wire(&delta).unwrap();
+        let EnvelopeFrame::LegacyDelta(legacy) = &frame else {
+            panic!("expected a legacy delta, got {frame:?}");
+        };
+        assert_eq!(
+            *legacy,
+            LegacyDelta {
+                step_shift: 10,
+                steps: [Some(3), None, None],
+            }
+        );
+        let next = frame.reconstruct(Some(&base)).unwrap();
+        assert_eq!(next.trace_id, base.trace_id);
+        assert_eq!(f64::from(next.band.host_budget), 0.4 + 3.0 / 1024.0);
+
+        let mut buf = [0u8; EnvelopeFrame::MAX_WIRE_LEN];
+        let len = frame.to_wire_slice(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &delta);
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        assert_eq!(
+            EnvelopeFrame::from_wire(&[FRAME_VERSION]).unwrap_err(),
+            FrameError::Truncated
+        );
+        assert_eq!(
+            EnvelopeFrame::from_wire(&[9, 0]).unwrap_err(),
+            FrameError::UnsupportedVersion(9)
+        );
+        assert_eq!(
+            EnvelopeFrame::from_wire(&[FRAME_VERSION, 7]).unwrap_err(),
+            FrameError::UnknownFrameType(7)
+        );
+        assert_eq!(
+            EnvelopeFrame::from_wire(&[FRAME_VERSION, 1, 10]).unwrap_err(),
+            FrameError::Deserialize
+        );
+    }
+}
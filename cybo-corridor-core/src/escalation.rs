@@ -0,0 +1,507 @@
+//! Bridge from `cyboair_corridor_safety` check failures onto the core
+//! escalation enums.
+//!
+//! [`safety_trigger`] maps one [`SafetyError`] on a node to an
+//! [`EscalationTrigger`], using the node's pollutant where the error does
+//! not name one:
+//!
+//! * a DW ceiling breach on an NOx or NO2 node is
+//!   [`EscalationTrigger::UrbanNOxSpike`];
+//! * an EMF envelope breach is [`EscalationTrigger::BeeEMFOverload`];
+//! * anything else has no trigger.
+//!
+//! One breach is noise. [`EscalationBridge`] counts consecutive steps on
+//! which each node raises each trigger and escalates once a streak reaches
+//! [`EscalationConfig::repeat_threshold`], with the actions the config maps
+//! the trigger to. A streak escalates once; it has to break before the same
+//! node and trigger escalate again.
+//!
+//! [`apply_mode_actions`] carries out the escalations that change a node's
+//! operational mode: [`EscalationAction::EnterSensingOnly`] puts the node
+//! into sensing-only mode, with the trigger as the reason. A sensing-only
+//! node is not checked for actuation, so its streaks break.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use cyboair_corridor_safety::{
+    BeeGuard, CorridorAssessment, CorridorController, DwCeilingInvariant, EcoBandClassifier,
+    EnvelopeField, HostBudget, MetricsRecorder, NodeState, Pollutant, SafetyEnvelope, SafetyError,
+    StepInput, StepRecord,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{EscalationAction, EscalationTrigger};
+
+/// Trigger for `error` raised on a node measuring `pollutant`, if any.
+pub fn safety_trigger(
+    error: &SafetyError,
+    pollutant: Option<Pollutant>,
+) -> Option<EscalationTrigger> {
+    let pollutant = match error.unattributed() {
+        SafetyError::PollutantDwCeilingExceeded { pollutant, .. } => Some(*pollutant),
+        SafetyError::DwCeilingExceeded { .. } => pollutant,
+        SafetyError::EnvelopeViolation {
+            field: EnvelopeField::EmfVpm,
+            ..
+        } => return Some(EscalationTrigger::BeeEMFOverload),
+        _ => return None,
+    };
+    match pollutant {
+        Some(Pollutant::NOx | Pollutant::NO2) => Some(EscalationTrigger::UrbanNOxSpike),
+        _ => None,
+    }
+}
+
+/// Repeat threshold and the trigger to action table.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EscalationConfig {
+    /// Consecutive steps a trigger must fire before it escalates; 0 is
+    /// treated as 1.
+    pub repeat_threshold: u32,
+    /// Actions per trigger; the first entry for a trigger wins.
+    pub actions: Vec<(EscalationTrigger, Vec<EscalationAction>)>,
+    /// Actions for triggers missing from `actions`.
+    pub default_actions: Vec<EscalationAction>,
+}
+
+impl Default for EscalationConfig {
+    /// Three consecutive steps. An NOx spike throttles and audits, an EMF
+    /// overload throttles and alerts, anything else is audited.
+    fn default() -> Self {
+        EscalationConfig {
+            repeat_threshold: 3,
+            actions: vec![
+                (
+                    EscalationTrigger::UrbanNOxSpike,
+                    vec![
+                        EscalationAction::ThrottleDutyCycle,
+                        EscalationAction::TriggerAudit,
+                    ],
+                ),
+                (
+                    EscalationTrigger::BeeEMFOverload,
+                    vec![
+                        EscalationAction::ThrottleDutyCycle,
+                        EscalationAction::TriggerAlert,
+                    ],
+                ),
+            ],
+            default_actions: vec![EscalationAction::TriggerAudit],
+        }
+    }
+}
+
+impl EscalationConfig {
+    /// Actions `trigger` maps to.
+    pub fn actions_for(&self, trigger: &EscalationTrigger) -> &[EscalationAction] {
+        self.actions
+            .iter()
+            .find(|(t, _)| t == trigger)
+            .map_or(&self.default_actions, |(_, actions)| actions)
+    }
+}
+
+/// A node whose trigger streak reached the threshold this step.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeEscalation {
+    pub machine_id: String,
+    pub trigger: EscalationTrigger,
+    /// Consecutive steps the trigger has fired, this one included.
+    pub consecutive: u32,
+    pub actions: Vec<EscalationAction>,
+}
+
+/// A controller step with the escalations it raised.
+#[derive(Clone, Debug)]
+pub struct EscalatedStep {
+    pub record: StepRecord,
+    pub escalations: Vec<NodeEscalation>,
+}
+
+/// Tracks trigger streaks per node across steps.
+#[derive(Clone, Debug, Default)]
+pub struct EscalationBridge {
+    pub config: EscalationConfig,
+    streaks: BTreeMap<String, Vec<(EscalationTrigger, u32)>>,
+}
+
+impl EscalationBridge {
+    pub fn new(config: EscalationConfig) -> Self {
+        EscalationBridge {
+            config,
+            streaks: BTreeMap::new(),
+        }
+    }
+
+    /// Current streak of `trigger` on `machine_id`.
+    pub fn streak(&self, machine_id: &str, trigger: &EscalationTrigger) -> u32 {
+        self.streaks
+            .get(machine_id)
+            .and_then(|s| s.iter().find(|(t, _)| t == trigger))
+            .map_or(0, |&(_, n)| n)
+    }
+
+    /// Advance every streak by one step of `assessment` over `nodes` (in the
+    /// same order) and return the escalations it raises. Nodes absent from
+    /// the step, or not raising a trigger, break their streaks.
+    pub fn observe(
+        &mut self,
+        nodes: &[NodeState],
+        assessment: &CorridorAssessment,
+    ) -> Vec<NodeEscalation> {
+        let threshold = self.config.repeat_threshold.max(1);
+        let mut streaks = BTreeMap::new();
+        let mut escalations = Vec::new();
+        for (node, assessed) in nodes.iter().zip(&assessment.nodes) {
+            let pollutant = node.row.pollutant_kind().ok();
+            let mut fired: Vec<(EscalationTrigger, u32)> = Vec::new();
+            for error in &assessed.violations {
+                let Some(trigger) = safety_trigger(error, pollutant) else {
+                    continue;
+                };
+                if fired.iter().any(|(t, _)| *t == trigger) {
+                    continue;
+                }
+                let consecutive = self.streak(&assessed.machine_id, &trigger) + 1;
+                if consecutive == threshold {
+                    escalations.push(NodeEscalation {
+                        machine_id: assessed.machine_id.clone(),
+                        actions: self.config.actions_for(&trigger).to_vec(),
+                        trigger: trigger.clone(),
+                        consecutive,
+                    });
+                }
+                fired.push((trigger, consecutive));
+            }
+            if !fired.is_empty() {
+                streaks.insert(assessed.machine_id.clone(), fired);
+            }
+        }
+        self.streaks = streaks;
+        escalations
+    }
+
+    /// Forget every streak.
+    pub fn reset(&mut self) {
+        self.streaks.clear();
+    }
+}
+
+/// `CorridorController::run_steps` with escalation: before each step the
+/// nodes are dry-run at the step's band and `phi_dw`, and `bridge`
+/// observes the result. Records are numbered from 0 as in `run_steps`.
+pub fn run_steps_escalating<E, H, B, D, G, R, F>(
+    controller: &CorridorController<E, H, B, D, G, R>,
+    bridge: &mut EscalationBridge,
+    nodes: &mut [NodeState],
+    steps: usize,
+    mut input: F,
+) -> Result<Vec<EscalatedStep>, SafetyError>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+    F: FnMut(usize) -> StepInput,
+{
+    let mut log = Vec::with_capacity(steps);
+    for step in 0..steps {
+        let inp = input(step);
+        let before = nodes.to_vec();
+        let mut record = controller
+            .run_steps(nodes, 1, |_| inp)?
+            .pop()
+            .expect("one step was run");
+        record.step = step;
+        let assessment = controller.dry_run(&before, record.band, inp.phi_dw);
+        log.push(EscalatedStep {
+            escalations: bridge.observe(&before, &assessment),
+            record,
+        });
+    }
+    Ok(log)
+}
+
+/// Put every node named by an escalation carrying
+/// [`EscalationAction::EnterSensingOnly`] into sensing-only mode as of
+/// `now_ms`, and return the ids of the nodes that changed mode. Nodes
+/// already out of `Active`, or absent from `nodes`, are left alone.
+pub fn apply_mode_actions<E, H, B, D, G, R>(
+    controller: &CorridorController<E, H, B, D, G, R>,
+    nodes: &mut [NodeState],
+    escalations: &[NodeEscalation],
+    now_ms: u64,
+) -> Vec<String>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    let mut entered = Vec::new();
+    for escalation in escalations {
+        if !escalation
+            .actions
+            .contains(&EscalationAction::EnterSensingOnly)
+        {
+            continue;
+        }
+        let Some(node) = nodes
+            .iter_mut()
+            .find(|n| n.row.machine_id == escalation.machine_id)
+        else {
+            continue;
+        };
+        let reason = format!("{:?}", escalation.trigger);
+        if controller.enter_sensing_only(node, now_ms, reason) {
+            entered.push(escalation.machine_id.clone());
+        }
+    }
+    entered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyboair_corridor_safety::{
+        compute_karma_bytes, compute_mass_kg, ConstAltitude, ControllerConfig, CorridorRow,
+        OperationalMode,
+    };
+
+    const CONFIG: &str = r#"
+        corridor_area_m2 = 10.0
+        m_ref_kg = 1.0e-6
+        k_ref_nb = 1.0e10
+
+        [gains]
+        eta_m = 0.1
+        eta_k = 0.1
+        eta_w = 0.2
+        eta_b = 0.2
+        eta_p = 0.05
+        eta_dw = 0.1
+
+        [envelope]
+        u_min = 0.0
+        u_max = 1.0
+        z_min_m = 5.0
+        z_max_m = 600.0
+        ecoimpact_min = 0.7
+        ecoimpact_max = 1.0
+
+        [host_budget]
+        p_max_w = 150.0
+        e_step_max_j = 1.0e5
+        step_dt_s = 300.0
+
+        [eco_band]
+        theta_green_amber = 0.5
+        theta_amber_red = 1.0
+        gain_green = 0.0
+        gain_amber = 0.2
+        gain_red = 0.5
+
+        [dw_ceiling]
+        phi_dw_max = 1.0e-6
+    "#;
+
+    fn node(machine_id: &str, pollutant: &str) -> NodeState {
+        let row = CorridorRow {
+            machine_id: machine_id.into(),
+            r#type: "UrbanNanoswarmCanopy".into(),
+            location: "Phoenix-Intersection-A".into(),
+            pollutant: pollutant.into(),
+            cin: 40.0,
+            cout: 28.0,
+            unit: "ugm3".into(),
+            airflow_m3_per_s: 3.0,
+            period_s: 3600.0,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.92,
+            trace_id: None,
+            shard_version: None,
+        };
+        let kind = row.pollutant_kind().unwrap();
+        let mass_kg = compute_mass_kg(&row, kind, 310.0).unwrap();
+        NodeState {
+            karma_bytes: compute_karma_bytes(&row, mass_kg),
+            row,
+            mass_kg,
+            duty_cycle: 0.5,
+            power_w: 50.0,
+            geo_weight: 0.8,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        }
+    }
+
+    fn input(phi_dw: f64) -> StepInput {
+        StepInput {
+            phi_dw,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        }
+    }
+
+    #[test]
+    fn dw_breach_maps_by_pollutant() {
+        let breach = SafetyError::DwCeilingExceeded {
+            phi_dw: 2.0e-6,
+            phi_dw_max: 1.0e-6,
+        };
+        assert_eq!(
+            safety_trigger(&breach, Some(Pollutant::NOx)),
+            Some(EscalationTrigger::UrbanNOxSpike)
+        );
+        assert_eq!(safety_trigger(&breach, Some(Pollutant::PM25)), None);
+        let no2 = SafetyError::PollutantDwCeilingExceeded {
+            pollutant: Pollutant::NO2,
+            phi_dw: 2.0e-6,
+            phi_dw_max: 1.0e-6,
+        };
+        assert_eq!(
+            safety_trigger(&no2, None),
+            Some(EscalationTrigger::UrbanNOxSpike)
+        );
+        let battery = SafetyError::BatteryReserve {
+            machine_id: "n".into(),
+            soc: 0.1,
+            reserve_soc: 0.2,
+        };
+        assert_eq!(safety_trigger(&battery, Some(Pollutant::NOx)), None);
+    }
+
+    #[test]
+    fn three_consecutive_breaches_escalate_once() {
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut bridge = EscalationBridge::default();
+        let mut nodes = [
+            node("CYB-AIR-NOX-02", "NOx"),
+            node("CYB-AIR-CANOPY-01", "PM2.5"),
+        ];
+        // Over the ceiling on steps 0 and 2..=5, under it on step 1.
+        let phi = [2.0e-6, 5.0e-7, 2.0e-6, 2.0e-6, 2.0e-6, 2.0e-6];
+        let log = run_steps_escalating(&controller, &mut bridge, &mut nodes, phi.len(), |s| {
+            input(phi[s])
+        })
+        .unwrap();
+
+        let escalated: Vec<usize> = log
+            .iter()
+            .filter(|s| !s.escalations.is_empty())
+            .map(|s| s.record.step)
+            .collect();
+        assert_eq!(escalated, [4]);
+        let escalation = &log[4].escalations[0];
+        assert_eq!(escalation.machine_id, "CYB-AIR-NOX-02");
+        assert_eq!(escalation.trigger, EscalationTrigger::UrbanNOxSpike);
+        assert_eq!(escalation.consecutive, 3);
+        assert_eq!(
+            escalation.actions,
+            [
+                EscalationAction::ThrottleDutyCycle,
+                EscalationAction::TriggerAudit
+            ]
+        );
+        assert_eq!(
+            bridge.streak("CYB-AIR-NOX-02", &EscalationTrigger::UrbanNOxSpike),
+            4
+        );
+        assert_eq!(
+            bridge.streak("CYB-AIR-CANOPY-01", &EscalationTrigger::UrbanNOxSpike),
+            0
+        );
+    }
+
+    #[test]
+    fn enter_sensing_only_escalations_pin_the_node() {
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut bridge = EscalationBridge::new(EscalationConfig {
+            repeat_threshold: 2,
+            actions: vec![(
+                EscalationTrigger::UrbanNOxSpike,
+                vec![
+                    EscalationAction::EnterSensingOnly,
+                    EscalationAction::TriggerAudit,
+                ],
+            )],
+            default_actions: vec![],
+        });
+        let mut nodes = [
+            node("CYB-AIR-NOX-02", "NOx"),
+            node("CYB-AIR-CANOPY-01", "PM2.5"),
+        ];
+        let mut entered = Vec::new();
+        let mut duties = Vec::new();
+        for now_ms in [1_000, 2_000, 3_000, 4_000] {
+            let step =
+                run_steps_escalating(&controller, &mut bridge, &mut nodes, 1, |_| input(2.0e-6))
+                    .unwrap()
+                    .remove(0);
+            duties.push(step.record.nodes[0].duty_cycle);
+            entered.extend(apply_mode_actions(
+                &controller,
+                &mut nodes,
+                &step.escalations,
+                now_ms,
+            ));
+        }
+        assert_eq!(entered, ["CYB-AIR-NOX-02"]);
+        assert_eq!(
+            nodes[0].mode,
+            OperationalMode::SensingOnly {
+                since_ms: 2_000,
+                reason: "UrbanNOxSpike".into()
+            }
+        );
+        assert_eq!(duties[2..], [0.0, 0.0]);
+        assert!(nodes[1].mode.is_active());
+        // Not checked for actuation any more, so the streak broke.
+        assert_eq!(
+            bridge.streak("CYB-AIR-NOX-02", &EscalationTrigger::UrbanNOxSpike),
+            0
+        );
+    }
+
+    #[test]
+    fn configured_actions_override_the_defaults() {
+        let config = EscalationConfig {
+            repeat_threshold: 1,
+            actions: vec![(
+                EscalationTrigger::BeeEMFOverload,
+                vec![EscalationAction::DisableActuation],
+            )],
+            default_actions: vec![EscalationAction::TriggerAlert],
+        };
+        assert_eq!(
+            config.actions_for(&EscalationTrigger::BeeEMFOverload),
+            [EscalationAction::DisableActuation]
+        );
+        assert_eq!(
+            config.actions_for(&EscalationTrigger::UrbanNOxSpike),
+            [EscalationAction::TriggerAlert]
+        );
+    }
+}
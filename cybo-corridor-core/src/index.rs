@@ -0,0 +1,214 @@
+//! [`NormalizedIndex`], the type of every envelope index.
+//!
+//! Envelope indices used to be bare `f64`s in [0, 1] by convention only,
+//! so a percentage (42.0 for 42 %) passed for an index and every invariant
+//! judged nonsense. An index is now checked when it is made:
+//!
+//! * [`NormalizedIndex::new`] and `TryFrom<f64>` refuse anything outside
+//!   [0, 1], NaN included; [`NormalizedIndex::new_clamped`] clamps instead.
+//! * Deserializing refuses out-of-range values too. Fields that should
+//!   clamp on the way in opt in with `#[serde(with = "clamped")]`, see
+//!   [`clamped`].
+//! * `f64::from` (or `.get()`) reads the value back.
+//!
+//! Breaking change: `BeeBand`'s indices and the `HostBudgetEnvelope`
+//! accessors are `NormalizedIndex` rather than `f64`. Wire and JSON layouts
+//! are unchanged; payloads with an out-of-range index no longer decode.
+
+use core::fmt;
+use core::ops::Mul;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Value in [0, 1]; 1 is the edge of the band it indexes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NormalizedIndex(f64);
+
+/// A value that is not an index: outside [0, 1], or NaN.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IndexOutOfRange {
+    pub value: f64,
+}
+
+impl fmt::Display for IndexOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "normalized index {} is outside [0, 1]", self.value)
+    }
+}
+
+impl NormalizedIndex {
+    pub const ZERO: NormalizedIndex = NormalizedIndex(0.0);
+    /// The band edge.
+    pub const ONE: NormalizedIndex = NormalizedIndex(1.0);
+
+    pub fn new(value: f64) -> Result<Self, IndexOutOfRange> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(NormalizedIndex(value))
+        } else {
+            Err(IndexOutOfRange { value })
+        }
+    }
+
+    /// `value` clamped into [0, 1]. NaN is taken as the edge, the most
+    /// conservative reading of an unknown index.
+    pub fn new_clamped(value: f64) -> Self {
+        if value.is_nan() {
+            NormalizedIndex::ONE
+        } else {
+            NormalizedIndex(value.clamp(0.0, 1.0))
+        }
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// `self + delta`, clamped into [0, 1].
+    pub fn saturating_add(self, delta: f64) -> Self {
+        NormalizedIndex::new_clamped(self.0 + delta)
+    }
+}
+
+impl From<NormalizedIndex> for f64 {
+    fn from(index: NormalizedIndex) -> f64 {
+        index.0
+    }
+}
+
+impl TryFrom<f64> for NormalizedIndex {
+    type Error = IndexOutOfRange;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        NormalizedIndex::new(value)
+    }
+}
+
+/// Product of two indices, e.g. a squared residual term; stays in [0, 1].
+impl Mul for NormalizedIndex {
+    type Output = NormalizedIndex;
+
+    fn mul(self, rhs: NormalizedIndex) -> NormalizedIndex {
+        NormalizedIndex(self.0 * rhs.0)
+    }
+}
+
+/// A scaled bound such as `eco_band * 0.85`; not necessarily an index.
+impl Mul<f64> for NormalizedIndex {
+    type Output = f64;
+
+    fn mul(self, rhs: f64) -> f64 {
+        self.0 * rhs
+    }
+}
+
+impl PartialEq<f64> for NormalizedIndex {
+    fn eq(&self, other: &f64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<f64> for NormalizedIndex {
+    fn partial_cmp(&self, other: &f64) -> Option<core::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for NormalizedIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for NormalizedIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = f64::deserialize(deserializer)?;
+        NormalizedIndex::new(value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for NormalizedIndex {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> alloc::borrow::Cow<'static, str> {
+        "NormalizedIndex".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "number",
+            "format": "double",
+            "minimum": 0.0,
+            "maximum": 1.0
+        })
+    }
+}
+
+/// Lenient serde for a `NormalizedIndex` field, used as
+/// `#[serde(with = "cybo_corridor_core::index::clamped")]`: serialized as
+/// usual, but an out-of-range value is clamped on deserialization instead
+/// of refused.
+#[cfg(feature = "serde")]
+pub mod clamped {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        index: &NormalizedIndex,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        index.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<NormalizedIndex, D::Error> {
+        f64::deserialize(deserializer).map(NormalizedIndex::new_clamped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructors_check_or_clamp() {
+        assert_eq!(NormalizedIndex::new(0.42).unwrap().get(), 0.42);
+        for value in [42.0, -0.1, f64::NAN, f64::INFINITY] {
+            assert!(NormalizedIndex::new(value).is_err(), "{value}");
+        }
+        assert_eq!(NormalizedIndex::new_clamped(42.0), NormalizedIndex::ONE);
+        assert_eq!(NormalizedIndex::new_clamped(-3.0), NormalizedIndex::ZERO);
+        assert_eq!(NormalizedIndex::new_clamped(f64::NAN), NormalizedIndex::ONE);
+        assert_eq!(NormalizedIndex::ONE.saturating_add(0.5), 1.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialization_rejects_percentages_unless_clamping() {
+        let err = serde_json::from_str::<NormalizedIndex>("42.0").unwrap_err();
+        assert!(err.to_string().contains("outside [0, 1]"), "{err}");
+        assert_eq!(
+            serde_json::from_str::<NormalizedIndex>("0.42").unwrap(),
+            0.42
+        );
+
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Lenient {
+            #[serde(with = "clamped")]
+            host_budget: NormalizedIndex,
+        }
+        let lenient: Lenient = serde_json::from_str(r#"{"host_budget": 42.0}"#).unwrap();
+        assert_eq!(lenient.host_budget, NormalizedIndex::ONE);
+        assert_eq!(
+            serde_json::to_string(&lenient).unwrap(),
+            r#"{"host_budget":1.0}"#
+        );
+    }
+}
@@ -1,14 +1,86 @@
 #![cfg_attr(not(test), no_std)]
 
+//! Features (all but `defmt` on by default):
+//!
+//! * `alloc`: escalation policies, the consistency sweep, the escalation
+//!   simulator (`simulation`), `Vec` wire encoding and the `aln` table
+//!   parser. Without it the crate links no allocator and frames are
+//!   encoded with `BinaryEcoTrace::to_wire_slice`.
+//! * `serde`: `Serialize`/`Deserialize` on the public types.
+//! * `uuid`: corridor trace ids and lineage (`Traceable`, `trace`,
+//!   `BeeEnvelope::trace_id`).
+//! * `trace-gen`: `trace::TraceIdGen`, UUIDv7 ids from a `trace::Clock`;
+//!   implies `uuid`.
+//! * `wire`: the postcard spine format (`BinaryEcoTrace`) and delta-coded
+//!   envelope frames (`delta`); implies `serde` and `uuid`.
+//! * `defmt`: `defmt::Format` on the public types, for RTT logging.
+//! * `schema`: `schemars::JsonSchema` on the serde types; implies `serde`
+//!   and `alloc`.
+//! * `testing`: proptest strategies for bands, envelopes and states
+//!   (`testing`); implies `alloc` and `uuid`, and links `std`.
+//!
+//! With none of them the traits, ceilings, invariants and hysteresis rules
+//! depend on `core` only.
+
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 use core::fmt::Debug;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "uuid")]
 use uuid::Uuid;
 
+/// Checked envelope indices in [0, 1].
+pub mod index;
+
+pub use index::{IndexOutOfRange, NormalizedIndex};
+
+/// Signed ALN parameter tables: risk weights, ceilings, RoH stressors.
+#[cfg(feature = "alloc")]
+pub mod aln;
+
+/// Keyframe/delta envelope telemetry.
+#[cfg(feature = "wire")]
+pub mod delta;
+
+/// Envelope lineage and time-ordered trace ids.
+#[cfg(feature = "uuid")]
+pub mod trace;
+
+/// Proptest strategies for property tests here and downstream.
+#[cfg(feature = "testing")]
+pub mod testing;
+
+/// Closed-loop simulation of escalation actions against their triggers.
+#[cfg(feature = "alloc")]
+pub mod simulation;
+
+/// Spine frames from `cyboair_corridor_safety` duty updates.
+#[cfg(feature = "corridor-safety")]
+pub mod spine;
+
+/// Escalation triggers and actions from `cyboair_corridor_safety` errors.
+#[cfg(feature = "corridor-safety")]
+pub mod escalation;
+
 /// Metric families across bee, marine, and urban (UHI) domains.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Downstream corridors (bats, amphibians, ...) use `Custom` rather than
+/// forking the crate. First-party variants are only ever appended and are
+/// never named `Custom`, so a custom family serializes as
+/// `{"Custom": {"namespace": n, "id": i}}` in self-describing formats and
+/// under its own variant index on the wire, and cannot collide with a
+/// later first-party family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum MetricFamily {
     BeeThermal,
     BeeChem,
@@ -21,6 +93,14 @@ pub enum MetricFamily {
     UrbanHeatIndex,
     UrbanWBGT,
     UrbanNOx,
+    /// Family defined outside this crate: `namespace` identifies the
+    /// defining organisation, `id` the family within it. No
+    /// `CorridorCeiling` can name a custom family, so custom corridors never
+    /// inherit (or relax) the sealed bee and marine ceilings.
+    Custom {
+        namespace: u16,
+        id: u16,
+    },
     // Extend but never relax existing bee/marine bands.
 }
 
@@ -33,8 +113,56 @@ pub trait CorridorInvariant<T>: Clone + Debug + PartialEq {
     fn residual(&self, sample: &T) -> f64;
 }
 
+/// Samples whose normalized indices can be nudged one axis at a time.
+pub trait SamplePerturb: Sized {
+    /// Copy of `self` with index `axis` (0 host budget, 1 eco band, 2 DW
+    /// ceiling, as in [`EnvelopePoint`]) moved by `delta`.
+    fn perturbed(&self, axis: usize, delta: f64) -> Self;
+}
+
+/// Step used by [`finite_difference_gradient`] in the default
+/// [`CorridorInvariantGrad::residual_gradient`].
+pub const RESIDUAL_FD_STEP: f64 = 1e-6;
+
+/// Central-difference ∂residual/∂index over the three envelope axes.
+pub fn finite_difference_gradient<T, I>(inv: &I, sample: &T, h: f64) -> EnvelopePoint
+where
+    T: SamplePerturb,
+    I: CorridorInvariant<T>,
+{
+    let mut grad = [0.0; 3];
+    for (axis, g) in grad.iter_mut().enumerate() {
+        let up = inv.residual(&sample.perturbed(axis, h));
+        let down = inv.residual(&sample.perturbed(axis, -h));
+        *g = (up - down) / (2.0 * h);
+    }
+    grad
+}
+
+/// Residual gradient for projection-style hysteresis and controllers that
+/// descend the residual. Separate from [`CorridorInvariant`] so existing
+/// implementors are unaffected; the default is a finite difference.
+pub trait CorridorInvariantGrad<T: SamplePerturb>: CorridorInvariant<T> {
+    /// ∂residual/∂index, ordered as [`EnvelopePoint`].
+    fn residual_gradient(&self, sample: &T) -> EnvelopePoint {
+        finite_difference_gradient(self, sample, RESIDUAL_FD_STEP)
+    }
+}
+
+/// `Serialize + Deserialize` with the `serde` feature; no bound without it.
+#[cfg(feature = "serde")]
+pub trait SerdeBound: Serialize + for<'a> Deserialize<'a> {}
+#[cfg(feature = "serde")]
+impl<T: Serialize + for<'a> Deserialize<'a>> SerdeBound for T {}
+
+/// `Serialize + Deserialize` with the `serde` feature; no bound without it.
+#[cfg(not(feature = "serde"))]
+pub trait SerdeBound {}
+#[cfg(not(feature = "serde"))]
+impl<T> SerdeBound for T {}
+
 /// Anything that can be serialized into the corridor spine.
-pub trait EcoBandCapable: for<'a> Deserialize<'a> + Serialize {
+pub trait EcoBandCapable: SerdeBound {
     /// Metric family this band applies to.
     fn metric_family(&self) -> MetricFamily;
 }
@@ -44,21 +172,19 @@ pub trait HostBudgetEnvelope: Sized {
     type Band: EcoBandCapable;
     type Invariant: CorridorInvariant<Self>;
 
-    /// Normalized host‑budget index in [0,1]; 1 is corridor edge.
-    fn host_budget_index(&self) -> f64;
+    /// Normalized host‑budget index; 1 is corridor edge.
+    fn host_budget_index(&self) -> NormalizedIndex;
 
-    /// Normalized eco‑band index in [0,1]; 1 is band edge.
-    fn eco_band_index(&self) -> f64;
+    /// Normalized eco‑band index; 1 is band edge.
+    fn eco_band_index(&self) -> NormalizedIndex;
 
-    /// Normalized degradation‑weighted ceiling index in [0,1].
-    fn dw_ceiling_index(&self) -> f64;
+    /// Normalized degradation‑weighted ceiling index.
+    fn dw_ceiling_index(&self) -> NormalizedIndex;
 
-    /// Hard safety gate (used by CI and runtime).
+    /// Hard safety gate (used by CI and runtime). Indices cannot pass
+    /// their edge, so this is the invariant alone.
     fn is_within_envelope(&self, inv: &Self::Invariant) -> bool {
-        self.host_budget_index() <= 1.0
-            && self.eco_band_index() <= 1.0
-            && self.dw_ceiling_index() <= 1.0
-            && inv.holds(self)
+        inv.holds(self)
     }
 }
 
@@ -88,7 +214,11 @@ where
 }
 
 /// Example triggers across domains.
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum EscalationTrigger {
     BeeColonyStress,
     BeeThermalDrift,
@@ -99,11 +229,47 @@ pub enum EscalationTrigger {
     UrbanUHIOverheat,
     UrbanNightWBGTDrift,
     UrbanNOxSpike,
+    /// Downstream trigger, namespaced as for `MetricFamily::Custom`.
+    Custom {
+        namespace: u16,
+        id: u16,
+    },
+}
+
+/// Ordered severity of an escalation trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl EscalationTrigger {
+    /// Severity used when comparing escalation against hysteresis decisions.
+    /// Custom triggers are `High`: a downstream rule that fires one is
+    /// treated as rejecting the state.
+    pub fn severity(&self) -> Severity {
+        use EscalationTrigger::*;
+        match self {
+            BeeColonyStress | MarineLarvaeShearRisk => Severity::Critical,
+            BeeThermalDrift | BeeEMFOverload | MarinePHDrift | UrbanUHIOverheat => Severity::High,
+            MarineNoiseStress | UrbanNightWBGTDrift | UrbanNOxSpike => Severity::Medium,
+            Custom { .. } => Severity::High,
+        }
+    }
 }
 
 /// Abstract escalation action, to be bound by higher layers
 /// (routing, governance, throttle policies).
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum EscalationAction {
     ThrottleDutyCycle,
     DisableActuation,
@@ -111,9 +277,15 @@ pub enum EscalationAction {
     EnterSensingOnly,
     TriggerAudit,
     TriggerAlert,
+    /// Downstream action, namespaced as for `MetricFamily::Custom`.
+    Custom {
+        namespace: u16,
+        id: u16,
+    },
 }
 
 /// Escalation policy as supertrait: decides when to trigger domain actions.
+#[cfg(feature = "alloc")]
 pub trait EscalationPolicy<S>: HysteresisRule<S>
 where
     S: SafetyEnvelopeState,
@@ -128,7 +300,8 @@ where
 /// Degradation‑weighted ceiling per metric family.
 ///
 /// Sealed: only crate‑local implementations so external code
-/// cannot relax ceilings for bees or marine larvae.
+/// cannot relax ceilings for bees or marine larvae. `FAMILY` is never a
+/// `MetricFamily::Custom`; downstream corridors enforce their own ceilings.
 pub trait CorridorCeiling: private::Sealed {
     const FAMILY: MetricFamily;
     /// Minimal allowed DW ceiling (e.g. 2.1 °C for Apis mellifera deployment).
@@ -142,17 +315,19 @@ mod private {
 }
 
 /// Bee thermal ceiling (brood/hive corridors).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeThermalCeiling;
 impl private::Sealed for BeeThermalCeiling {}
 impl CorridorCeiling for BeeThermalCeiling {
     const FAMILY: MetricFamily = MetricFamily::BeeThermal;
-    // These are conservative placeholders: real values should come
-    // from signed BeeNeuralCorridorPhoenix*.aln tables.
+    // Conservative sealed bounds; signed BeeNeuralCorridorPhoenix*.aln
+    // tables set the value within them (`aln::AlnTable::ceiling_overrides`).
     const DW_CEILING_MIN: f64 = 2.1;
     const DW_CEILING_MAX: f64 = 3.0;
 }
 
 /// Marine larvae thermal ceiling (tighter than bee).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct MarineLarvaeThermalCeiling;
 impl private::Sealed for MarineLarvaeThermalCeiling {}
 impl CorridorCeiling for MarineLarvaeThermalCeiling {
@@ -162,7 +337,10 @@ impl CorridorCeiling for MarineLarvaeThermalCeiling {
 }
 
 /// Generic host‑budget band in integer hundredths.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct HostBudgetBand {
     /// Minimum budget (0–100, interpreted as percentage).
     pub min: u8,
@@ -171,7 +349,10 @@ pub struct HostBudgetBand {
 }
 
 /// Corridor band tying a host‑budget band to a specific ceiling.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct CorridorBand<C: CorridorCeiling> {
     pub budget: HostBudgetBand,
     pub _ceiling: core::marker::PhantomData<C>,
@@ -182,36 +363,113 @@ impl<C: CorridorCeiling> CorridorBand<C> {
     /// 0.85 × DW_CEILING_MIN (scaled into the same 0–100 space).
     pub const fn budget_within_ceiling(&self) -> bool {
         let ceiling_scaled = (C::DW_CEILING_MIN * 10.0) as u8; // map °C into [0,255]
-        // 85% bound.
+                                                               // 85% bound.
         let allowed = ((ceiling_scaled as u16) * 85 / 100) as u8;
         self.budget.max <= allowed
     }
 }
 
 /// Traceability: corridor IDs and wire format.
+#[cfg(feature = "uuid")]
 pub trait Traceable {
     fn corridor_trace_id(&self) -> Uuid;
 }
 
+/// Why a frame could not be written to the spine.
+#[cfg(feature = "wire")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EncodeError {
+    /// The buffer is shorter than the encoded frame; `MAX_WIRE_LEN` bytes
+    /// always suffice.
+    BufferTooSmall,
+    /// Any other serializer failure.
+    Serialize,
+}
+
+#[cfg(feature = "wire")]
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EncodeError::BufferTooSmall => f.write_str("wire buffer too small for frame"),
+            EncodeError::Serialize => f.write_str("frame failed to serialize"),
+        }
+    }
+}
+
+#[cfg(feature = "wire")]
+impl From<postcard::Error> for EncodeError {
+    fn from(e: postcard::Error) -> Self {
+        match e {
+            postcard::Error::SerializeBufferFull => EncodeError::BufferTooSmall,
+            _ => EncodeError::Serialize,
+        }
+    }
+}
+
+#[cfg(feature = "wire")]
 pub trait BinaryEcoTrace: Traceable {
-    /// Serialize into Corridor‑Research Spine wire format.
-    fn to_wire_bytes(&self) -> Vec<u8>;
+    /// Upper bound on the encoded length, for sizing static buffers.
+    const MAX_WIRE_LEN: usize;
+
+    /// Serialize into Corridor‑Research Spine wire format at the start of
+    /// `buf`, returning the number of bytes written.
+    fn to_wire_slice(&self, buf: &mut [u8]) -> Result<usize, EncodeError>;
+
+    /// `to_wire_slice` into a fresh buffer; empty if encoding fails.
+    #[cfg(feature = "alloc")]
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; Self::MAX_WIRE_LEN];
+        let len = self.to_wire_slice(&mut buf).unwrap_or(0);
+        buf.truncate(len);
+        buf
+    }
 }
 
 /* =========================
-   Concrete bee envelope
-   ========================= */
+Concrete bee envelope
+========================= */
 
 /// Bee corridor band with normalized indices.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeBand {
     pub family: MetricFamily,
-    /// Normalized host‑budget index [0,1].
-    pub host_budget: f64,
-    /// Normalized eco‑band index [0,1].
-    pub eco_band: f64,
-    /// Normalized DW ceiling index [0,1].
-    pub dw_ceiling: f64,
+    /// Normalized host‑budget index.
+    pub host_budget: NormalizedIndex,
+    /// Normalized eco‑band index.
+    pub eco_band: NormalizedIndex,
+    /// Normalized DW ceiling index.
+    pub dw_ceiling: NormalizedIndex,
+}
+
+impl BeeBand {
+    /// Band from raw indices, refusing any outside [0, 1].
+    pub fn try_new(
+        family: MetricFamily,
+        host_budget: f64,
+        eco_band: f64,
+        dw_ceiling: f64,
+    ) -> Result<Self, IndexOutOfRange> {
+        Ok(BeeBand {
+            family,
+            host_budget: host_budget.try_into()?,
+            eco_band: eco_band.try_into()?,
+            dw_ceiling: dw_ceiling.try_into()?,
+        })
+    }
+
+    /// Band from raw indices, clamping each into [0, 1].
+    pub fn clamped(family: MetricFamily, host_budget: f64, eco_band: f64, dw_ceiling: f64) -> Self {
+        BeeBand {
+            family,
+            host_budget: NormalizedIndex::new_clamped(host_budget),
+            eco_band: NormalizedIndex::new_clamped(eco_band),
+            dw_ceiling: NormalizedIndex::new_clamped(dw_ceiling),
+        }
+    }
 }
 
 impl EcoBandCapable for BeeBand {
@@ -221,34 +479,120 @@ impl EcoBandCapable for BeeBand {
 }
 
 /// Minimal bee envelope struct, compatible with Bee Safety Kernel semantics.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BeeEnvelope {
     pub band: BeeBand,
+    #[cfg(feature = "uuid")]
     pub trace_id: Uuid,
+    /// Trace id of the envelope this one was derived from; `None` for a
+    /// root. Defaulted so payloads from before lineage still decode.
+    #[cfg(feature = "uuid")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parent_id: Option<Uuid>,
+    /// Position in the lineage, increasing from 0 at the root.
+    #[cfg(feature = "uuid")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sequence: u32,
+    /// Trace id of the record the envelope was computed from, e.g. the
+    /// corridor shard row or telemetry frame, when known.
+    #[cfg(feature = "uuid")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_id: Option<Uuid>,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for BeeEnvelope {
+    fn format(&self, f: defmt::Formatter) {
+        #[cfg(feature = "uuid")]
+        defmt::write!(
+            f,
+            "BeeEnvelope {{ band: {}, trace_id: {=[u8]:02x}, sequence: {} }}",
+            self.band,
+            &self.trace_id.as_bytes()[..],
+            self.sequence
+        );
+        #[cfg(not(feature = "uuid"))]
+        defmt::write!(f, "BeeEnvelope {{ band: {} }}", self.band);
+    }
 }
 
 impl HostBudgetEnvelope for BeeEnvelope {
     type Band = BeeBand;
     type Invariant = BeeCorridorInvariant;
 
-    fn host_budget_index(&self) -> f64 {
+    fn host_budget_index(&self) -> NormalizedIndex {
         self.band.host_budget
     }
 
-    fn eco_band_index(&self) -> f64 {
+    fn eco_band_index(&self) -> NormalizedIndex {
         self.band.eco_band
     }
 
-    fn dw_ceiling_index(&self) -> f64 {
+    fn dw_ceiling_index(&self) -> NormalizedIndex {
         self.band.dw_ceiling
     }
 }
 
+/// Safe residual and per-index weights of Vbee = Σ w_x r_x², as published
+/// in signed `BeeRiskWeights` tables (see `aln`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BeeRiskWeights {
+    pub v_safe: f64,
+    pub host_budget: f64,
+    pub eco_band: f64,
+    pub dw_ceiling: f64,
+}
+
+impl Default for BeeRiskWeights {
+    /// Unit weights and one unit of residual.
+    fn default() -> Self {
+        BeeRiskWeights {
+            v_safe: 1.0,
+            host_budget: 1.0,
+            eco_band: 1.0,
+            dw_ceiling: 1.0,
+        }
+    }
+}
+
 /// Bee Lyapunov‑style invariant; residual approximates Vbee.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeCorridorInvariant {
     /// Safe residual threshold (e.g. Vsafe from your BeeRiskWeights).
     pub v_safe: f64,
+    /// Weight of each squared index, in `EnvelopePoint` order. Defaulted
+    /// to ones so payloads from before weights still decode.
+    #[cfg_attr(feature = "serde", serde(default = "unit_weights"))]
+    pub weights: EnvelopePoint,
+}
+
+#[cfg(feature = "serde")]
+fn unit_weights() -> EnvelopePoint {
+    [1.0; 3]
+}
+
+impl Default for BeeCorridorInvariant {
+    /// Default bee configuration: one unit of quadratic residual.
+    fn default() -> Self {
+        BeeRiskWeights::default().into()
+    }
+}
+
+impl From<BeeRiskWeights> for BeeCorridorInvariant {
+    fn from(w: BeeRiskWeights) -> Self {
+        BeeCorridorInvariant {
+            v_safe: w.v_safe,
+            weights: [w.host_budget, w.eco_band, w.dw_ceiling],
+        }
+    }
 }
 
 impl CorridorInvariant<BeeEnvelope> for BeeCorridorInvariant {
@@ -257,17 +601,48 @@ impl CorridorInvariant<BeeEnvelope> for BeeCorridorInvariant {
     }
 
     fn residual(&self, sample: &BeeEnvelope) -> f64 {
-        // Simple quadratic residual over normalized indices; this mirrors
-        // your Vbee = Σ w_x r_x^2 structure in a minimal form.
-        let hb = sample.band.host_budget.max(0.0);
-        let eco = sample.band.eco_band.max(0.0);
-        let dw = sample.band.dw_ceiling.max(0.0);
-        hb * hb + eco * eco + dw * dw
+        // Weighted quadratic residual over normalized indices:
+        // Vbee = Σ w_x r_x^2.
+        let band = &sample.band;
+        [band.host_budget, band.eco_band, band.dw_ceiling]
+            .into_iter()
+            .zip(self.weights)
+            .map(|(x, w)| w * (x * x).get())
+            .sum()
+    }
+}
+
+impl SamplePerturb for BeeEnvelope {
+    fn perturbed(&self, axis: usize, delta: f64) -> Self {
+        let mut out = self.clone();
+        let band = &mut out.band;
+        match axis {
+            0 => band.host_budget = band.host_budget.saturating_add(delta),
+            1 => band.eco_band = band.eco_band.saturating_add(delta),
+            2 => band.dw_ceiling = band.dw_ceiling.saturating_add(delta),
+            _ => {}
+        }
+        out
+    }
+}
+
+impl CorridorInvariantGrad<BeeEnvelope> for BeeCorridorInvariant {
+    /// `2·w·x` per index.
+    fn residual_gradient(&self, sample: &BeeEnvelope) -> EnvelopePoint {
+        let w = self.weights;
+        [
+            sample.band.host_budget * (2.0 * w[0]),
+            sample.band.eco_band * (2.0 * w[1]),
+            sample.band.dw_ceiling * (2.0 * w[2]),
+        ]
     }
 }
 
 /// Bee state inside an envelope; can be extended with TDI, MBI, etc.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeState {
     pub envelope: BeeEnvelope,
     /// Example: aggregate BeeHBScore in [0,1].
@@ -283,7 +658,10 @@ impl SafetyEnvelopeState for BeeState {
 }
 
 /// Domain invariant for bees: host_budget must always be ≤ 0.85 * eco_band.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeDomainInvariant;
 
 impl DomainInvariant for BeeDomainInvariant {
@@ -294,19 +672,23 @@ impl DomainInvariant for BeeDomainInvariant {
     }
 }
 
+impl DomainInvariant for BeeCorridorInvariant {
+    fn host_vs_eco_ok(&self) -> bool {
+        BeeDomainInvariant.host_vs_eco_ok()
+    }
+}
+
 /// Simple bee hysteresis: clamp proposed state if envelope or inequality fail.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct BeeHysteresisRule;
 
 impl HysteresisRule<BeeState> for BeeHysteresisRule {
     type Inv = BeeCorridorInvariant;
 
-    fn next_state(
-        &self,
-        current: &BeeState,
-        proposed: &BeeState,
-        inv: &Self::Inv,
-    ) -> BeeState {
+    fn next_state(&self, current: &BeeState, proposed: &BeeState, inv: &Self::Inv) -> BeeState {
         let env = &proposed.envelope;
         let hb = env.band.host_budget;
         let eco = env.band.eco_band;
@@ -315,7 +697,7 @@ impl HysteresisRule<BeeState> for BeeHysteresisRule {
         if eco <= 0.0 {
             return current.clone();
         }
-        if hb > 0.85 * eco {
+        if hb > eco * 0.85 {
             return current.clone();
         }
         if !env.is_within_envelope(inv) {
@@ -326,22 +708,28 @@ impl HysteresisRule<BeeState> for BeeHysteresisRule {
 }
 
 /// Escalation policy for bee states.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct BeeEscalationPolicy;
+///
+/// Hierarchy: any proposed state that `BeeHysteresisRule` would reject must
+/// raise at least a `Severity::High` trigger here, and any state it accepts
+/// must not. The policy therefore carries the same invariant the rule is
+/// evaluated against, so both agree on what "outside the envelope" means.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BeeEscalationPolicy {
+    pub inv: BeeCorridorInvariant,
+}
 
 impl HysteresisRule<BeeState> for BeeEscalationPolicy {
     type Inv = BeeCorridorInvariant;
 
-    fn next_state(
-        &self,
-        current: &BeeState,
-        proposed: &BeeState,
-        inv: &Self::Inv,
-    ) -> BeeState {
+    fn next_state(&self, current: &BeeState, proposed: &BeeState, inv: &Self::Inv) -> BeeState {
         BeeHysteresisRule.next_state(current, proposed, inv)
     }
 }
 
+#[cfg(feature = "alloc")]
 impl EscalationPolicy<BeeState> for BeeEscalationPolicy {
     fn classify_trigger(&self, state: &BeeState) -> Option<EscalationTrigger> {
         let env = &state.envelope;
@@ -350,7 +738,11 @@ impl EscalationPolicy<BeeState> for BeeEscalationPolicy {
 
         if hb > 0.9 {
             Some(EscalationTrigger::BeeColonyStress)
-        } else if hb > 0.85 * eco {
+        } else if eco <= 0.0 || hb > eco * 0.85 {
+            Some(EscalationTrigger::BeeThermalDrift)
+        } else if !env.is_within_envelope(&self.inv) {
+            // Residual or an index is past its edge even though the
+            // host/eco inequality holds: the rule rejects, so escalate too.
             Some(EscalationTrigger::BeeThermalDrift)
         } else {
             None
@@ -374,93 +766,622 @@ impl EscalationPolicy<BeeState> for BeeEscalationPolicy {
     }
 }
 
+#[cfg(feature = "uuid")]
 impl Traceable for BeeEnvelope {
     fn corridor_trace_id(&self) -> Uuid {
         self.trace_id
     }
 }
 
+#[cfg(feature = "wire")]
 impl BinaryEcoTrace for BeeEnvelope {
-    fn to_wire_bytes(&self) -> Vec<u8> {
-        // Use postcard or bincode; postcard fits no_std better.
-        postcard::to_allocvec(self).unwrap_or_default()
+    /// Postcard layout: family varint (1 byte while under 128 variants, plus
+    /// up to 3 bytes each for a `Custom` namespace and id), three
+    /// little-endian f64 indices, the trace id as a length-prefixed 16-byte
+    /// string, the optional parent id the same way behind a tag byte, the
+    /// sequence as a varint of up to 5 bytes, and the optional source id
+    /// like the parent id.
+    const MAX_WIRE_LEN: usize = (1 + 3 + 3) + 3 * 8 + (1 + 16) + (1 + 1 + 16) + 5 + (1 + 1 + 16);
+
+    fn to_wire_slice(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        Ok(postcard::to_slice(self, buf)?.len())
+    }
+}
+
+/// [`BeeEnvelope`]'s postcard layout before lineage: band and trace id.
+#[cfg(feature = "wire")]
+#[derive(Deserialize)]
+pub(crate) struct BeeEnvelopeV1 {
+    band: BeeBand,
+    trace_id: Uuid,
+}
+
+#[cfg(feature = "wire")]
+impl From<BeeEnvelopeV1> for BeeEnvelope {
+    fn from(v1: BeeEnvelopeV1) -> Self {
+        BeeEnvelope {
+            band: v1.band,
+            trace_id: v1.trace_id,
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
+        }
+    }
+}
+
+#[cfg(feature = "wire")]
+impl BeeEnvelope {
+    /// Decode a spine frame in the current layout or, failing that, in the
+    /// layout from before lineage, read as a root with no source.
+    pub fn from_wire_slice(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes).or_else(|e| {
+            postcard::from_bytes::<BeeEnvelopeV1>(bytes)
+                .map(BeeEnvelope::from)
+                .map_err(|_| e)
+        })
+    }
+}
+
+/* =========================
+Hysteresis / escalation consistency
+========================= */
+
+/// Small deterministic RNG (SplitMix64) so sampling is reproducible in CI
+/// and on `no_std` targets.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeterministicRng {
+    state: u64,
+}
+
+impl DeterministicRng {
+    pub fn new(seed: u64) -> Self {
+        DeterministicRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Sample point in normalized envelope space:
+/// `[host_budget_index, eco_band_index, dw_ceiling_index]`.
+pub type EnvelopePoint = [f64; 3];
+
+/// Sampling plan: a regular grid plus seeded random points over `[lo, hi]^3`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EnvelopeSampler {
+    pub lo: f64,
+    pub hi: f64,
+    /// Points per axis on the grid (0 disables the grid).
+    pub grid_steps: usize,
+    pub random_points: usize,
+    pub seed: u64,
+}
+
+impl Default for EnvelopeSampler {
+    fn default() -> Self {
+        EnvelopeSampler {
+            lo: 0.0,
+            hi: 1.2,
+            grid_steps: 13,
+            random_points: 2000,
+            seed: 0x00C0_FFEE,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl EnvelopeSampler {
+    pub fn points(&self) -> Vec<EnvelopePoint> {
+        let mut out = Vec::new();
+        let span = self.hi - self.lo;
+        if self.grid_steps > 0 {
+            let denom = if self.grid_steps > 1 {
+                (self.grid_steps - 1) as f64
+            } else {
+                1.0
+            };
+            for i in 0..self.grid_steps {
+                for j in 0..self.grid_steps {
+                    for k in 0..self.grid_steps {
+                        out.push([
+                            self.lo + span * i as f64 / denom,
+                            self.lo + span * j as f64 / denom,
+                            self.lo + span * k as f64 / denom,
+                        ]);
+                    }
+                }
+            }
+        }
+        let mut rng = DeterministicRng::new(self.seed);
+        for _ in 0..self.random_points {
+            out.push([
+                self.lo + span * rng.next_f64(),
+                self.lo + span * rng.next_f64(),
+                self.lo + span * rng.next_f64(),
+            ]);
+        }
+        out
+    }
+}
+
+/// Kind of disagreement between a hysteresis rule and its escalation policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InconsistencyKind {
+    /// Rule accepted the state but escalation fired at `Severity::High` or above.
+    AcceptedButEscalated,
+    /// Rule rejected the state but escalation stayed below `Severity::High`.
+    RejectedButSilent,
+}
+
+/// One offending sample point, kept for inspection.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Inconsistency {
+    pub point: EnvelopePoint,
+    pub kind: InconsistencyKind,
+    pub trigger: Option<EscalationTrigger>,
+}
+
+/// Result of a consistency sweep.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConsistencyReport {
+    pub samples: usize,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+#[cfg(feature = "alloc")]
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn same_envelope<E: HostBudgetEnvelope>(a: &E, b: &E) -> bool {
+    a.host_budget_index() == b.host_budget_index()
+        && a.eco_band_index() == b.eco_band_index()
+        && a.dw_ceiling_index() == b.dw_ceiling_index()
+}
+
+/// Sweep the envelope space and report where `policy.next_state` and
+/// `policy.classify_trigger` disagree.
+///
+/// `make_state` lifts a sample point into a domain state, so the same
+/// checker serves bee, marine, and urban policies. A point is "accepted"
+/// when the rule returns the proposed envelope rather than `current`'s;
+/// points whose envelope equals `current`'s are skipped as undecidable.
+#[cfg(feature = "alloc")]
+pub fn check_escalation_consistency<S, P, F>(
+    policy: &P,
+    current: &S,
+    inv: &P::Inv,
+    sampler: &EnvelopeSampler,
+    make_state: F,
+) -> ConsistencyReport
+where
+    S: SafetyEnvelopeState,
+    P: EscalationPolicy<S>,
+    F: Fn(&EnvelopePoint) -> S,
+{
+    let mut report = ConsistencyReport::default();
+    for point in sampler.points() {
+        let proposed = make_state(&point);
+        if same_envelope(proposed.envelope(), current.envelope()) {
+            continue;
+        }
+        report.samples += 1;
+
+        let next = policy.next_state(current, &proposed, inv);
+        let accepted = same_envelope(next.envelope(), proposed.envelope());
+        let trigger = policy.classify_trigger(&proposed);
+        let escalated = trigger
+            .as_ref()
+            .is_some_and(|t| t.severity() >= Severity::High);
+
+        let kind = match (accepted, escalated) {
+            (true, true) => Some(InconsistencyKind::AcceptedButEscalated),
+            (false, false) => Some(InconsistencyKind::RejectedButSilent),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            report.inconsistencies.push(Inconsistency {
+                point,
+                kind,
+                trigger,
+            });
+        }
     }
+    report
+}
+
+/// What `next_state` did with one proposed state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HysteresisOutcome {
+    /// The proposed envelope was taken as is.
+    Accepted,
+    /// The rule moved to an envelope that is neither proposed nor current.
+    Clamped,
+    /// The rule stayed at the current envelope.
+    Kept,
+}
+
+/// Errors from [`apply_hysteresis_batch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BatchError {
+    /// `current` and `proposed` have different lengths.
+    LengthMismatch { current: usize, proposed: usize },
+}
+
+impl core::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BatchError::LengthMismatch { current, proposed } => write!(
+                f,
+                "batch length mismatch: {current} current vs {proposed} proposed states"
+            ),
+        }
+    }
+}
+
+/// Next states of a batch, with what happened at each index.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct HysteresisBatch<S> {
+    pub states: Vec<S>,
+    pub outcomes: Vec<HysteresisOutcome>,
+}
+
+#[cfg(feature = "alloc")]
+impl<S> HysteresisBatch<S> {
+    /// Number of indices with the given outcome.
+    pub fn count(&self, outcome: HysteresisOutcome) -> usize {
+        self.outcomes.iter().filter(|o| **o == outcome).count()
+    }
+}
+
+/// Apply `rule.next_state` element-wise over `current` and `proposed`.
+///
+/// Outcomes compare envelopes as [`check_escalation_consistency`] does: a
+/// next state matching the proposed envelope is `Accepted` (even when that
+/// is also the current one), one matching the current envelope is `Kept`,
+/// anything else is `Clamped`.
+#[cfg(feature = "alloc")]
+pub fn apply_hysteresis_batch<S, R>(
+    rule: &R,
+    current: &[S],
+    proposed: &[S],
+    inv: &R::Inv,
+) -> Result<HysteresisBatch<S>, BatchError>
+where
+    S: SafetyEnvelopeState,
+    R: HysteresisRule<S>,
+{
+    if current.len() != proposed.len() {
+        return Err(BatchError::LengthMismatch {
+            current: current.len(),
+            proposed: proposed.len(),
+        });
+    }
+    let mut batch = HysteresisBatch {
+        states: Vec::with_capacity(current.len()),
+        outcomes: Vec::with_capacity(current.len()),
+    };
+    for (cur, prop) in current.iter().zip(proposed) {
+        let next = rule.next_state(cur, prop, inv);
+        let outcome = if same_envelope(next.envelope(), prop.envelope()) {
+            HysteresisOutcome::Accepted
+        } else if same_envelope(next.envelope(), cur.envelope()) {
+            HysteresisOutcome::Kept
+        } else {
+            HysteresisOutcome::Clamped
+        };
+        batch.states.push(next);
+        batch.outcomes.push(outcome);
+    }
+    Ok(batch)
 }
 
 /* =========================
-   Unit tests (std only)
-   ========================= */
+Unit tests (std only)
+========================= */
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc", feature = "wire"))]
 mod tests {
     use super::*;
 
     #[test]
     fn bee_band_metric_family() {
-        let band = BeeBand {
-            family: MetricFamily::BeeThermal,
-            host_budget: 0.4,
-            eco_band: 0.6,
-            dw_ceiling: 0.3,
-        };
+        let band = BeeBand::try_new(MetricFamily::BeeThermal, 0.4, 0.6, 0.3).unwrap();
         assert_eq!(band.metric_family(), MetricFamily::BeeThermal);
     }
 
     #[test]
     fn bee_envelope_invariant_residual() {
-        let band = BeeBand {
-            family: MetricFamily::BeeThermal,
-            host_budget: 0.4,
-            eco_band: 0.6,
-            dw_ceiling: 0.3,
-        };
+        let band = BeeBand::try_new(MetricFamily::BeeThermal, 0.4, 0.6, 0.3).unwrap();
         let env = BeeEnvelope {
             band,
             trace_id: Uuid::nil(),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
+        };
+        let inv = BeeCorridorInvariant {
+            v_safe: 1.0,
+            ..Default::default()
         };
-        let inv = BeeCorridorInvariant { v_safe: 1.0 };
         let res = inv.residual(&env);
         assert!(res >= 0.0);
         assert!(inv.holds(&env));
     }
 
     #[test]
-    fn bee_hysteresis_clamps_unsafe_state() {
-        let band_current = BeeBand {
-            family: MetricFamily::BeeThermal,
-            host_budget: 0.4,
-            eco_band: 0.6,
-            dw_ceiling: 0.3,
+    fn risk_weights_scale_the_residual_and_gradient() {
+        let env = BeeEnvelope {
+            band: BeeBand::try_new(MetricFamily::BeeThermal, 0.5, 0.5, 0.5).unwrap(),
+            trace_id: Uuid::nil(),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
         };
+        let inv = BeeCorridorInvariant::from(BeeRiskWeights {
+            v_safe: 0.7,
+            host_budget: 1.0,
+            eco_band: 0.8,
+            dw_ceiling: 1.2,
+        });
+        assert!((inv.residual(&env) - 0.75).abs() < 1e-12);
+        assert!(!inv.holds(&env));
+        assert_eq!(inv.residual_gradient(&env), [1.0, 0.8, 1.2]);
+        assert_eq!(
+            BeeCorridorInvariant::default().residual(&env),
+            0.75,
+            "unit weights keep the unweighted residual"
+        );
+    }
+
+    #[test]
+    fn bee_hysteresis_clamps_unsafe_state() {
+        let band_current = BeeBand::try_new(MetricFamily::BeeThermal, 0.4, 0.6, 0.3).unwrap();
         let env_current = BeeEnvelope {
             band: band_current,
             trace_id: Uuid::nil(),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
         };
         let state_current = BeeState {
             envelope: env_current,
             hb_score: 0.98,
         };
 
-        let band_proposed = BeeBand {
-            family: MetricFamily::BeeThermal,
-            host_budget: 0.95, // exceeds 0.85 * eco_band
-            eco_band: 0.6,
-            dw_ceiling: 0.4,
-        };
+        let band_proposed = BeeBand::try_new(
+            MetricFamily::BeeThermal,
+            0.95, // exceeds 0.85 * eco_band
+            0.6,
+            0.4,
+        )
+        .unwrap();
         let env_proposed = BeeEnvelope {
             band: band_proposed,
             trace_id: Uuid::nil(),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
         };
         let state_proposed = BeeState {
             envelope: env_proposed,
             hb_score: 0.9,
         };
 
-        let inv = BeeCorridorInvariant { v_safe: 10.0 };
+        let inv = BeeCorridorInvariant {
+            v_safe: 10.0,
+            ..Default::default()
+        };
         let rule = BeeHysteresisRule;
 
         let next = rule.next_state(&state_current, &state_proposed, &inv);
-        assert!((next.envelope.band.host_budget - 0.4).abs() < 1e-6);
+        assert!((next.envelope.band.host_budget.get() - 0.4).abs() < 1e-6);
+    }
+
+    fn bee_state_at(p: &EnvelopePoint) -> BeeState {
+        BeeState {
+            envelope: BeeEnvelope {
+                band: BeeBand::clamped(MetricFamily::BeeThermal, p[0], p[1], p[2]),
+                trace_id: Uuid::nil(),
+                parent_id: None,
+                sequence: 0,
+                source_id: None,
+            },
+            hb_score: 0.9,
+        }
+    }
+
+    #[test]
+    fn bee_residual_gradient_matches_finite_difference() {
+        let inv = BeeCorridorInvariant::default();
+        // Inside the unit cube: a difference straddling an edge is clamped.
+        let sampler = EnvelopeSampler {
+            lo: 0.05,
+            hi: 0.95,
+            grid_steps: 6,
+            random_points: 200,
+            ..EnvelopeSampler::default()
+        };
+        for p in sampler.points() {
+            let env = bee_state_at(&p).envelope;
+            let analytic = inv.residual_gradient(&env);
+            let numeric = finite_difference_gradient(&inv, &env, RESIDUAL_FD_STEP);
+            for axis in 0..3 {
+                assert!(
+                    (analytic[axis] - numeric[axis]).abs() < 1e-5,
+                    "{p:?} axis {axis}: {analytic:?} vs {numeric:?}"
+                );
+            }
+        }
+
+        // Out-of-range points are clamped onto the edges.
+        let env = bee_state_at(&[-0.5, 0.25, 1.1]).envelope;
+        assert_eq!(inv.residual_gradient(&env), [0.0, 0.5, 2.0]);
+    }
+
+    #[test]
+    fn hysteresis_batch_reports_each_outcome() {
+        /// Caps host budget at 0.85 × eco band instead of refusing.
+        struct CapHostBudget;
+        impl HysteresisRule<BeeState> for CapHostBudget {
+            type Inv = BeeCorridorInvariant;
+
+            fn next_state(
+                &self,
+                current: &BeeState,
+                proposed: &BeeState,
+                inv: &Self::Inv,
+            ) -> BeeState {
+                let mut next = proposed.clone();
+                let band = &mut next.envelope.band;
+                band.host_budget =
+                    NormalizedIndex::new_clamped(band.host_budget.get().min(band.eco_band * 0.85));
+                if next.envelope.is_within_envelope(inv) {
+                    next
+                } else {
+                    current.clone()
+                }
+            }
+        }
+
+        let inv = BeeCorridorInvariant::default();
+        let current = [
+            bee_state_at(&[0.1, 0.3, 0.1]),
+            bee_state_at(&[0.1, 0.3, 0.1]),
+            bee_state_at(&[0.1, 0.3, 0.1]),
+            bee_state_at(&[0.2, 0.4, 0.1]),
+        ];
+        let proposed = [
+            bee_state_at(&[0.2, 0.4, 0.1]),
+            bee_state_at(&[0.5, 0.4, 0.1]),
+            bee_state_at(&[0.2, 0.4, 1.5]),
+            bee_state_at(&[0.2, 0.4, 0.1]),
+        ];
+        let batch = apply_hysteresis_batch(&CapHostBudget, &current, &proposed, &inv).unwrap();
+        assert_eq!(
+            batch.outcomes,
+            [
+                HysteresisOutcome::Accepted,
+                HysteresisOutcome::Clamped,
+                HysteresisOutcome::Kept,
+                HysteresisOutcome::Accepted,
+            ]
+        );
+        assert!((batch.states[1].envelope.band.host_budget.get() - 0.34).abs() < 1e-12);
+        assert_eq!(batch.states[2].envelope.band.dw_ceiling, 0.1);
+        assert_eq!(batch.count(HysteresisOutcome::Accepted), 2);
+
+        // The stock bee rule never clamps: it keeps instead.
+        let batch = apply_hysteresis_batch(&BeeHysteresisRule, &current, &proposed, &inv).unwrap();
+        assert_eq!(batch.outcomes[1], HysteresisOutcome::Kept);
+
+        assert_eq!(
+            apply_hysteresis_batch(&BeeHysteresisRule, &current, &proposed[..3], &inv).unwrap_err(),
+            BatchError::LengthMismatch {
+                current: 4,
+                proposed: 3
+            }
+        );
+    }
+
+    #[test]
+    fn deterministic_rng_is_reproducible() {
+        let mut a = DeterministicRng::new(42);
+        let mut b = DeterministicRng::new(42);
+        for _ in 0..100 {
+            let x = a.next_f64();
+            assert_eq!(x, b.next_f64());
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn bee_hysteresis_and_escalation_agree() {
+        let policy = BeeEscalationPolicy::default();
+        let inv = BeeCorridorInvariant::default();
+        let current = bee_state_at(&[0.2, 0.5, 0.2]);
+
+        let report = check_escalation_consistency(
+            &policy,
+            &current,
+            &inv,
+            &EnvelopeSampler::default(),
+            bee_state_at,
+        );
+        assert!(report.samples > 0);
+        assert!(
+            report.is_consistent(),
+            "first offending points: {:?}",
+            &report.inconsistencies[..report.inconsistencies.len().min(5)]
+        );
+    }
+
+    #[test]
+    fn checker_flags_silent_rejections() {
+        // A policy that only looks at host/eco (the old behaviour) stays
+        // silent when the DW index is past its edge.
+        #[derive(Clone, Debug)]
+        struct HostEcoOnly;
+        impl HysteresisRule<BeeState> for HostEcoOnly {
+            type Inv = BeeCorridorInvariant;
+            fn next_state(
+                &self,
+                current: &BeeState,
+                proposed: &BeeState,
+                inv: &Self::Inv,
+            ) -> BeeState {
+                BeeHysteresisRule.next_state(current, proposed, inv)
+            }
+        }
+        impl EscalationPolicy<BeeState> for HostEcoOnly {
+            fn classify_trigger(&self, state: &BeeState) -> Option<EscalationTrigger> {
+                let band = &state.envelope.band;
+                if band.host_budget > band.eco_band * 0.85 {
+                    Some(EscalationTrigger::BeeThermalDrift)
+                } else {
+                    None
+                }
+            }
+            fn escalation_actions(&self, _trig: EscalationTrigger) -> Vec<EscalationAction> {
+                vec![EscalationAction::TriggerAlert]
+            }
+        }
+
+        let report = check_escalation_consistency(
+            &HostEcoOnly,
+            &bee_state_at(&[0.2, 0.5, 0.2]),
+            &BeeCorridorInvariant::default(),
+            &EnvelopeSampler::default(),
+            bee_state_at,
+        );
+        assert!(report
+            .inconsistencies
+            .iter()
+            .any(|i| i.kind == InconsistencyKind::RejectedButSilent && i.point[2] > 1.0));
     }
 
     #[test]
@@ -472,4 +1393,117 @@ mod tests {
         };
         assert!(corridor.budget_within_ceiling());
     }
+
+    fn wire_envelope() -> BeeEnvelope {
+        BeeEnvelope {
+            band: BeeBand::try_new(MetricFamily::BeeChem, 0.4, 0.6, 0.3).unwrap(),
+            trace_id: Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef),
+            parent_id: None,
+            sequence: 0,
+            source_id: None,
+        }
+    }
+
+    #[test]
+    fn wire_slice_fits_max_len_exactly() {
+        let mut env = wire_envelope();
+        env.band.family = MetricFamily::Custom {
+            namespace: u16::MAX,
+            id: u16::MAX,
+        };
+        env.parent_id = Some(Uuid::from_u128(1));
+        env.sequence = u32::MAX;
+        env.source_id = Some(Uuid::from_u128(2));
+        let mut buf = [0u8; BeeEnvelope::MAX_WIRE_LEN];
+        assert_eq!(env.to_wire_slice(&mut buf), Ok(BeeEnvelope::MAX_WIRE_LEN));
+
+        let mut short = [0u8; BeeEnvelope::MAX_WIRE_LEN - 1];
+        assert_eq!(
+            env.to_wire_slice(&mut short),
+            Err(EncodeError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn wire_bytes_match_slice_encoding() {
+        let env = wire_envelope();
+        let mut buf = [0u8; 64];
+        let len = env.to_wire_slice(&mut buf).unwrap();
+        assert_eq!(env.to_wire_bytes(), &buf[..len]);
+        let decoded: BeeEnvelope = postcard::from_bytes(&buf[..len]).unwrap();
+        assert_eq!(decoded.trace_id, env.trace_id);
+        assert_eq!(decoded.band.dw_ceiling, 0.3);
+    }
+
+    /// `wire_envelope()` as the baseline encoded it: BeeChem, the three
+    /// indices as little-endian f64 and the length-prefixed trace id.
+    pub(crate) const BASELINE_FRAME: [u8; 42] = [
+        0x01, // family
+        0x9a, 0x99, 0x99, 0x99, 0x99, 0x99, 0xd9, 0x3f, // 0.4
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0xe3, 0x3f, // 0.6
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0xd3, 0x3f, // 0.3
+        0x10, // trace id length
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, //
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+    ];
+
+    #[test]
+    fn baseline_frames_decode_as_roots() {
+        let old = BeeEnvelope::from_wire_slice(&BASELINE_FRAME).unwrap();
+        let env = wire_envelope();
+        assert_eq!(old.trace_id, env.trace_id);
+        assert_eq!(old.band.family, MetricFamily::BeeChem);
+        assert_eq!(
+            [old.band.host_budget, old.band.eco_band, old.band.dw_ceiling].map(f64::from),
+            [0.4, 0.6, 0.3]
+        );
+        assert_eq!(
+            (old.parent_id, old.sequence, old.source_id),
+            (None, 0, None)
+        );
+
+        // The current layout still decodes as itself, not as a prefix.
+        let mut child = env.clone();
+        child.parent_id = Some(Uuid::from_u128(1));
+        child.sequence = 3;
+        let decoded = BeeEnvelope::from_wire_slice(&child.to_wire_bytes()).unwrap();
+        assert_eq!(decoded.parent_id, child.parent_id);
+        assert_eq!(decoded.sequence, 3);
+        assert!(BeeEnvelope::from_wire_slice(&BASELINE_FRAME[..41]).is_err());
+    }
+
+    #[test]
+    fn custom_variants_round_trip() {
+        let family = MetricFamily::Custom {
+            namespace: 0x0b47,
+            id: 2,
+        };
+        let trigger = EscalationTrigger::Custom {
+            namespace: 0x0b47,
+            id: 9,
+        };
+        let action = EscalationAction::Custom {
+            namespace: 0x0b47,
+            id: 1,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&family).unwrap(),
+            r#"{"Custom":{"namespace":2887,"id":2}}"#
+        );
+        let back: MetricFamily =
+            serde_json::from_str(r#"{"Custom":{"namespace":2887,"id":2}}"#).unwrap();
+        assert_eq!(back, family);
+        // First-party variants keep their plain representation.
+        assert_eq!(
+            serde_json::to_string(&MetricFamily::UrbanNOx).unwrap(),
+            r#""UrbanNOx""#
+        );
+
+        let bytes = postcard::to_allocvec(&(family, trigger.clone(), action.clone())).unwrap();
+        let decoded: (MetricFamily, EscalationTrigger, EscalationAction) =
+            postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, (family, trigger.clone(), action));
+        assert_eq!(trigger.severity(), Severity::High);
+    }
 }
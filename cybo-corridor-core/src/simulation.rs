@@ -0,0 +1,334 @@
+//! Closed-loop check that an escalation policy's actions resolve its
+//! triggers.
+//!
+//! [`EscalationSimulator`] steps a state forward: the policy classifies
+//! the proposed state, as it does the corridor's readings, an
+//! [`ActionEffectModel`] applies the actions for the trigger to the next
+//! proposal, and the policy's hysteresis rule decides whether to commit
+//! it. Proposals chain from one step to the next, so an action that needs
+//! several steps to bring a state back inside gets them even while the
+//! rule keeps rejecting the intermediate proposals.
+
+use alloc::vec::Vec;
+
+use crate::{
+    BeeState, CorridorInvariant, EscalationAction, EscalationPolicy, EscalationTrigger,
+    NormalizedIndex, SafetyEnvelopeState,
+};
+
+/// How escalation actions change the corridor.
+pub trait ActionEffectModel<S> {
+    /// `proposed` after `action` has acted on it for one step.
+    fn apply(&self, action: &EscalationAction, proposed: &S) -> S;
+}
+
+/// Default bee effects: each rate is the fraction of an index an action
+/// removes per step.
+///
+/// `ThrottleDutyCycle` scales the host-budget index by `1 - throttle`,
+/// `DisableActuation` and `EnterSensingOnly` by `1 - disable` and
+/// `1 - sensing_only`, and `ReroutePath` scales the DW-ceiling index by
+/// `1 - reroute`. Audits, alerts and custom actions change nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParametricEffects {
+    pub throttle: f64,
+    pub disable: f64,
+    pub sensing_only: f64,
+    pub reroute: f64,
+}
+
+impl Default for ParametricEffects {
+    /// A fifth of the duty shed per throttled step, actuation and sensing-
+    /// only cutting it at once, and a tenth of the DW exposure rerouted.
+    fn default() -> Self {
+        ParametricEffects {
+            throttle: 0.2,
+            disable: 1.0,
+            sensing_only: 1.0,
+            reroute: 0.1,
+        }
+    }
+}
+
+fn scaled(index: NormalizedIndex, rate: f64) -> NormalizedIndex {
+    NormalizedIndex::new_clamped(index * (1.0 - rate))
+}
+
+impl ActionEffectModel<BeeState> for ParametricEffects {
+    fn apply(&self, action: &EscalationAction, proposed: &BeeState) -> BeeState {
+        let mut next = proposed.clone();
+        let band = &mut next.envelope.band;
+        match action {
+            EscalationAction::ThrottleDutyCycle => {
+                band.host_budget = scaled(band.host_budget, self.throttle)
+            }
+            EscalationAction::DisableActuation => {
+                band.host_budget = scaled(band.host_budget, self.disable)
+            }
+            EscalationAction::EnterSensingOnly => {
+                band.host_budget = scaled(band.host_budget, self.sensing_only)
+            }
+            EscalationAction::ReroutePath => {
+                band.dw_ceiling = scaled(band.dw_ceiling, self.reroute)
+            }
+            _ => {}
+        }
+        next
+    }
+}
+
+/// Something that went wrong along a simulated trajectory.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SimulationViolation {
+    /// The committed state left the invariant after being inside it.
+    LeftEnvelope { step: usize },
+    /// A proposal raised `trigger` again after clearing.
+    Retriggered {
+        step: usize,
+        trigger: EscalationTrigger,
+    },
+}
+
+/// One simulated step.
+#[derive(Clone, Debug)]
+pub struct SimulationStep<S> {
+    /// 1 for the first step.
+    pub step: usize,
+    /// Trigger on the previous proposal, or on the initial state.
+    pub trigger: Option<EscalationTrigger>,
+    pub actions: Vec<EscalationAction>,
+    pub proposed: S,
+    /// State committed at the end of the step.
+    pub state: S,
+    /// Invariant residual of `state`.
+    pub residual: f64,
+}
+
+/// Trajectory and verdict of one simulation.
+#[derive(Clone, Debug)]
+pub struct SimulationReport<S> {
+    pub initial_trigger: Option<EscalationTrigger>,
+    pub steps: Vec<SimulationStep<S>>,
+    /// First step whose proposal raised no trigger; 0 if the initial state
+    /// raised none.
+    pub cleared_at: Option<usize>,
+    pub violations: Vec<SimulationViolation>,
+    /// Invariant residual of the final committed state.
+    pub final_residual: f64,
+}
+
+impl<S> SimulationReport<S> {
+    /// Cleared within `k` steps and never violated anything.
+    pub fn resolved_within(&self, k: usize) -> bool {
+        self.cleared_at.is_some_and(|at| at <= k) && self.violations.is_empty()
+    }
+}
+
+/// Simulates an escalation policy against an action effect model.
+#[derive(Clone, Debug)]
+pub struct EscalationSimulator<'a, P, M, I> {
+    pub policy: &'a P,
+    pub model: &'a M,
+    pub inv: &'a I,
+}
+
+impl<'a, P, M, I> EscalationSimulator<'a, P, M, I> {
+    pub fn new(policy: &'a P, model: &'a M, inv: &'a I) -> Self {
+        EscalationSimulator { policy, model, inv }
+    }
+
+    /// Simulate `steps` steps from `initial`, with every proposal starting
+    /// from the previous one.
+    pub fn simulate<S>(&self, initial: &S, steps: usize) -> SimulationReport<S>
+    where
+        S: SafetyEnvelopeState,
+        P: EscalationPolicy<S, Inv = I>,
+        M: ActionEffectModel<S>,
+        I: CorridorInvariant<S::Envelope>,
+    {
+        self.simulate_driven(initial, steps, |_, proposed| proposed.clone())
+    }
+
+    /// As [`simulate`](Self::simulate), with `drive(step, previous)` giving
+    /// each step's proposal before any action acts on it, e.g. a disturbance
+    /// pushing the corridor back out.
+    pub fn simulate_driven<S, F>(
+        &self,
+        initial: &S,
+        steps: usize,
+        mut drive: F,
+    ) -> SimulationReport<S>
+    where
+        S: SafetyEnvelopeState,
+        P: EscalationPolicy<S, Inv = I>,
+        M: ActionEffectModel<S>,
+        I: CorridorInvariant<S::Envelope>,
+        F: FnMut(usize, &S) -> S,
+    {
+        let initial_trigger = self.policy.classify_trigger(initial);
+        let mut report = SimulationReport {
+            cleared_at: initial_trigger.is_none().then_some(0),
+            initial_trigger: initial_trigger.clone(),
+            steps: Vec::with_capacity(steps),
+            violations: Vec::new(),
+            final_residual: self.inv.residual(initial.envelope()),
+        };
+        let mut state = initial.clone();
+        let mut proposed = initial.clone();
+        let mut trigger = initial_trigger;
+        let mut inside = self.inv.holds(state.envelope());
+
+        for step in 1..=steps {
+            let actions = trigger
+                .clone()
+                .map(|t| self.policy.escalation_actions(t))
+                .unwrap_or_default();
+            proposed = actions
+                .iter()
+                .fold(drive(step, &proposed), |s, a| self.model.apply(a, &s));
+            let next = self.policy.next_state(&state, &proposed, self.inv);
+
+            let now_inside = self.inv.holds(next.envelope());
+            if inside && !now_inside {
+                report
+                    .violations
+                    .push(SimulationViolation::LeftEnvelope { step });
+            }
+            inside = now_inside;
+
+            let next_trigger = self.policy.classify_trigger(&proposed);
+            match (&next_trigger, report.cleared_at) {
+                (None, None) => report.cleared_at = Some(step),
+                (Some(t), Some(_)) if trigger.is_none() => {
+                    report.violations.push(SimulationViolation::Retriggered {
+                        step,
+                        trigger: t.clone(),
+                    })
+                }
+                _ => {}
+            }
+
+            report.steps.push(SimulationStep {
+                step,
+                trigger: trigger.take(),
+                actions,
+                proposed: proposed.clone(),
+                residual: self.inv.residual(next.envelope()),
+                state: next.clone(),
+            });
+            trigger = next_trigger;
+            state = next;
+        }
+        report.final_residual = self.inv.residual(state.envelope());
+        report
+    }
+}
+
+#[cfg(all(test, feature = "uuid"))]
+mod tests {
+    use super::*;
+    use crate::{
+        BeeBand, BeeCorridorInvariant, BeeEnvelope, BeeEscalationPolicy, EnvelopePoint,
+        EnvelopeSampler, MetricFamily,
+    };
+    use uuid::Uuid;
+
+    /// Steps the shipped bee policy gets to clear a trigger.
+    const K: usize = 32;
+
+    fn bee_state_at(p: &EnvelopePoint) -> BeeState {
+        BeeState {
+            envelope: BeeEnvelope {
+                band: BeeBand::clamped(MetricFamily::BeeThermal, p[0], p[1], p[2]),
+                trace_id: Uuid::nil(),
+                parent_id: None,
+                sequence: 0,
+                source_id: None,
+            },
+            hb_score: 0.9,
+        }
+    }
+
+    #[test]
+    fn bee_policy_actions_clear_their_triggers() {
+        let policy = BeeEscalationPolicy::default();
+        let effects = ParametricEffects::default();
+        let sim = EscalationSimulator::new(&policy, &effects, &policy.inv);
+
+        // Throttling a BeeThermalDrift state brings it back under 0.85·eco.
+        let drift = sim.simulate(&bee_state_at(&[0.8, 0.6, 0.2]), K);
+        assert_eq!(
+            drift.initial_trigger,
+            Some(EscalationTrigger::BeeThermalDrift)
+        );
+        assert!(drift.resolved_within(K), "{drift:?}");
+        let last = &drift.steps.last().unwrap().state.envelope.band;
+        assert!(last.host_budget <= last.eco_band * 0.85);
+        assert!(drift.final_residual <= policy.inv.v_safe);
+
+        // Every triggering state with an eco index the actions leave room
+        // under: the eco index alone keeps the residual under v_safe, and
+        // is far enough from 0 for the host budget to fit under it.
+        let sampler = EnvelopeSampler {
+            lo: 0.0,
+            hi: 1.0,
+            grid_steps: 11,
+            random_points: 500,
+            ..EnvelopeSampler::default()
+        };
+        let mut triggered = 0;
+        for p in sampler
+            .points()
+            .iter()
+            .filter(|p| (0.1..0.95).contains(&p[1]))
+        {
+            let report = sim.simulate(&bee_state_at(p), K);
+            if report.initial_trigger.is_some() {
+                triggered += 1;
+            }
+            assert!(report.resolved_within(K), "{p:?}: {report:?}");
+            assert!(report.final_residual <= policy.inv.v_safe, "{p:?}");
+        }
+        assert!(triggered > 100);
+    }
+
+    #[test]
+    fn stalled_and_relapsing_trajectories_are_reported() {
+        #[derive(Clone, Copy)]
+        struct NoEffect;
+        impl ActionEffectModel<BeeState> for NoEffect {
+            fn apply(&self, _: &EscalationAction, proposed: &BeeState) -> BeeState {
+                proposed.clone()
+            }
+        }
+
+        let policy = BeeEscalationPolicy::default();
+        let inv = BeeCorridorInvariant::default();
+        let start = bee_state_at(&[0.8, 0.6, 0.2]);
+
+        let stalled = EscalationSimulator::new(&policy, &NoEffect, &inv).simulate(&start, K);
+        assert_eq!(stalled.cleared_at, None);
+        assert!(stalled.steps.iter().all(|s| s.trigger.is_some()));
+        assert!(!stalled.resolved_within(K));
+
+        // The duty creeps back up whenever the policy stops throttling.
+        let effects = ParametricEffects::default();
+        let sim = EscalationSimulator::new(&policy, &effects, &inv);
+        let relapsing = sim.simulate_driven(&start, K, |_, s| {
+            let mut s = s.clone();
+            s.envelope.band.host_budget = s.envelope.band.host_budget.saturating_add(0.1);
+            s
+        });
+        assert!(relapsing.cleared_at.is_some());
+        assert!(matches!(
+            relapsing.violations[0],
+            SimulationViolation::Retriggered {
+                trigger: EscalationTrigger::BeeThermalDrift,
+                ..
+            }
+        ));
+        assert!(!relapsing.resolved_within(K));
+    }
+}
@@ -0,0 +1,438 @@
+//! Bridge from `cyboair_corridor_safety` duty updates onto the corridor
+//! spine.
+//!
+//! Each successful `update_node_duty` becomes one [`BeeEnvelope`] frame,
+//! with its indices set from the updated node against the controller's own
+//! reference scales:
+//!
+//! * `host_budget = P / P_max`, the controller's `power_fraction`;
+//! * `eco_band = K / K_ref`, the node's karma over `k_ref_nb`;
+//! * `dw_ceiling = M / M_ref`, the node's CEIM mass over `m_ref_kg`.
+//!
+//! A node past an edge (an index above 1) gets no frame: a clipped index
+//! would read as inside the envelope, so [`bee_envelope`] returns
+//! [`SpineError::PastEdge`] and the caller escalates instead. The family is [`MetricFamily::BeeChem`], since the corridor controller
+//! only ever reports pollutant load. The trace id is a UUIDv5 of
+//! `"{machine_id}/{step}"` under [`CORRIDOR_TRACE_NAMESPACE`], so a replay
+//! of the same step yields the same frame. Frames of one node form a
+//! lineage: the parent is the same node's id at `step - 1` and the sequence
+//! is the step (saturating at `u32::MAX`). The node row's `trace_id`, if
+//! any, is the frame's `source_id`, linking it back to the shard row.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use cyboair_corridor_safety::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope, SafetyError, UpdateReport,
+};
+use uuid::Uuid;
+
+use crate::{BeeBand, BeeEnvelope, BinaryEcoTrace, MetricFamily, NormalizedIndex};
+
+/// Fixed namespace for corridor trace ids; changing it changes every id.
+pub const CORRIDOR_TRACE_NAMESPACE: Uuid =
+    Uuid::from_u128(0x5b1c_6a55_8f0e_5d2a_9c41_3e7a_0d6b_c2f4);
+
+/// Errors from turning a duty update into a spine frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpineError {
+    /// The report was produced for a different node.
+    ReportMismatch { node: String, report: String },
+    /// A reference scale or index input was unusable.
+    Safety(SafetyError),
+    /// The node's `field` puts an index outside [0, 1].
+    PastEdge {
+        machine_id: String,
+        field: &'static str,
+        index: f64,
+    },
+}
+
+impl fmt::Display for SpineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpineError::ReportMismatch { node, report } => {
+                write!(f, "update report for {report} does not match node {node}")
+            }
+            SpineError::Safety(e) => write!(f, "{e}"),
+            SpineError::PastEdge {
+                machine_id,
+                field,
+                index,
+            } => write!(f, "{machine_id}: {field} index {index} is outside [0, 1]"),
+        }
+    }
+}
+
+impl From<SafetyError> for SpineError {
+    fn from(e: SafetyError) -> Self {
+        SpineError::Safety(e)
+    }
+}
+
+/// Trace id of `machine_id`'s frame at controller step `step`.
+pub fn corridor_trace_id(machine_id: &str, step: u64) -> Uuid {
+    Uuid::new_v5(
+        &CORRIDOR_TRACE_NAMESPACE,
+        format!("{machine_id}/{step}").as_bytes(),
+    )
+}
+
+/// Spine envelope for `node` after the update described by `report`.
+///
+/// Errors if `report` belongs to another node, if a reference scale is not
+/// finite and positive, or if an index comes out non-finite or past its
+/// edge.
+pub fn bee_envelope<E, H, B, D, G, R>(
+    controller: &CorridorController<E, H, B, D, G, R>,
+    node: &NodeState,
+    report: &UpdateReport,
+    step: u64,
+) -> Result<BeeEnvelope, SpineError>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    if report.machine_id != node.row.machine_id {
+        return Err(SpineError::ReportMismatch {
+            node: node.row.machine_id.clone(),
+            report: report.machine_id.clone(),
+        });
+    }
+    for (name, value) in [
+        ("m_ref_kg", controller.m_ref_kg),
+        ("k_ref_nb", controller.k_ref_nb),
+    ] {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(SafetyError::InvalidReference { name, value }.into());
+        }
+    }
+
+    let machine_id = &node.row.machine_id;
+    let mut indices = [NormalizedIndex::ZERO; 3];
+    for (index, (field, value)) in indices.iter_mut().zip([
+        ("power_w", controller.host_budget.power_fraction(node)),
+        ("karma_bytes", node.karma_bytes / controller.k_ref_nb),
+        ("mass_kg", node.mass_kg / controller.m_ref_kg),
+    ]) {
+        if !value.is_finite() {
+            return Err(SafetyError::NonFiniteNode {
+                machine_id: machine_id.clone(),
+                field,
+                value,
+            }
+            .into());
+        }
+        *index = NormalizedIndex::new(value).map_err(|_| SpineError::PastEdge {
+            machine_id: machine_id.clone(),
+            field,
+            index: value,
+        })?;
+    }
+    let [host_budget, eco_band, dw_ceiling] = indices;
+    Ok(BeeEnvelope {
+        band: BeeBand {
+            family: MetricFamily::BeeChem,
+            host_budget,
+            eco_band,
+            dw_ceiling,
+        },
+        trace_id: corridor_trace_id(machine_id, step),
+        parent_id: step
+            .checked_sub(1)
+            .map(|prev| corridor_trace_id(machine_id, prev)),
+        sequence: u32::try_from(step).unwrap_or(u32::MAX),
+        source_id: node.row.trace_id,
+    })
+}
+
+/// [`bee_envelope`] serialized with [`BinaryEcoTrace::to_wire_bytes`]; decode
+/// with [`BeeEnvelope::from_wire_slice`].
+pub fn wire_frame<E, H, B, D, G, R>(
+    controller: &CorridorController<E, H, B, D, G, R>,
+    node: &NodeState,
+    report: &UpdateReport,
+    step: u64,
+) -> Result<Vec<u8>, SpineError>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    Ok(bee_envelope(controller, node, report, step)?.to_wire_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::{verify_chain, TraceableLineage};
+    use crate::{HostBudgetEnvelope, Traceable};
+    use cyboair_corridor_safety::{
+        compute_karma_bytes, compute_mass_kg, loader, ConstAltitude, ControllerConfig, CorridorRow,
+        EcoBand, OperationalMode, Pollutant,
+    };
+
+    const CONFIG: &str = r#"
+        corridor_area_m2 = 10.0
+        m_ref_kg = 1.0e-3
+        k_ref_nb = 1.0e10
+
+        [gains]
+        eta_m = 0.1
+        eta_k = 0.1
+        eta_w = 0.2
+        eta_b = 0.2
+        eta_p = 0.05
+        eta_dw = 0.1
+
+        [envelope]
+        u_min = 0.0
+        u_max = 1.0
+        z_min_m = 5.0
+        z_max_m = 600.0
+        ecoimpact_min = 0.7
+        ecoimpact_max = 1.0
+
+        [host_budget]
+        p_max_w = 150.0
+        e_step_max_j = 1.0e5
+        step_dt_s = 300.0
+
+        [eco_band]
+        theta_green_amber = 0.5
+        theta_amber_red = 1.0
+        gain_green = 0.0
+        gain_amber = 0.2
+        gain_red = 0.5
+
+        [dw_ceiling]
+        phi_dw_max = 1.0e-6
+    "#;
+
+    fn canopy() -> NodeState {
+        let row = CorridorRow {
+            machine_id: "CYB-AIR-CANOPY-01".into(),
+            r#type: "UrbanNanoswarmCanopy".into(),
+            location: "Phoenix-Intersection-A".into(),
+            pollutant: "PM2.5".into(),
+            cin: 40.0,
+            cout: 28.0,
+            unit: "ugm3".into(),
+            airflow_m3_per_s: 3.0,
+            period_s: 3600.0,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.92,
+            trace_id: None,
+            shard_version: None,
+        };
+        let mass_kg = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+        let karma_bytes = compute_karma_bytes(&row, mass_kg);
+        NodeState {
+            row,
+            mass_kg,
+            karma_bytes,
+            duty_cycle: 0.5,
+            power_w: 50.0,
+            geo_weight: 0.8,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        }
+    }
+
+    #[test]
+    fn decoded_frame_matches_recomputed_indices() {
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Amber, 0.0)
+            .unwrap();
+
+        let bytes = wire_frame(&controller, &node, &report, 7).unwrap();
+        assert!(!bytes.is_empty());
+        let frame = BeeEnvelope::from_wire_slice(&bytes).unwrap();
+
+        let eps = 1e-12;
+        assert!((frame.host_budget_index().get() - node.power_w / 150.0).abs() < eps);
+        assert!((frame.eco_band_index().get() - node.karma_bytes / 1.0e10).abs() < eps);
+        assert!((frame.dw_ceiling_index().get() - node.mass_kg / 1.0e-3).abs() < eps);
+        assert_eq!(frame.band.family, MetricFamily::BeeChem);
+        assert_eq!(
+            frame.corridor_trace_id(),
+            corridor_trace_id("CYB-AIR-CANOPY-01", 7)
+        );
+        // 12 ug/m3 over 3 m^3/s for an hour is 1.296e-4 kg.
+        assert!((frame.dw_ceiling_index().get() - 0.1296).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nodes_past_an_edge_get_no_frame() {
+        let mut controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Amber, 0.0)
+            .unwrap();
+
+        // 129.6 x M_ref: a frame clipped to 1 would pass as inside.
+        controller.m_ref_kg = 1.0e-6;
+        match bee_envelope(&controller, &node, &report, 7) {
+            Err(SpineError::PastEdge {
+                machine_id,
+                field: "mass_kg",
+                index,
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-CANOPY-01");
+                assert!((index - 129.6).abs() < 1e-9);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn shard_row_trace_id_rides_through_to_the_frame() {
+        let shard = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpact_score
+CYB-AIR-CANOPY-01,UrbanNanoswarmCanopy,Phoenix-Intersection-A,PM2.5,40,28,ugm3,3.0,3600,3.0,5.0e8,0.92
+";
+        let loaded = loader::from_named_reader(shard.as_bytes(), "phoenix.csv").unwrap();
+        let trace_id = loaded[0].row.trace_id;
+        assert!(trace_id.is_some());
+
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        node.row = loaded[0].row.clone();
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Amber, 0.0)
+            .unwrap();
+        assert_eq!(report.trace_id, trace_id);
+
+        let bytes = wire_frame(&controller, &node, &report, 7).unwrap();
+        let frame = BeeEnvelope::from_wire_slice(&bytes).unwrap();
+        assert_eq!(frame.source_id, trace_id);
+    }
+
+    #[test]
+    fn trace_ids_are_deterministic_per_node_and_step() {
+        let a = corridor_trace_id("CYB-AIR-CANOPY-01", 3);
+        assert_eq!(a, corridor_trace_id("CYB-AIR-CANOPY-01", 3));
+        assert_ne!(a, corridor_trace_id("CYB-AIR-CANOPY-01", 4));
+        assert_ne!(a, corridor_trace_id("CYB-AIR-SCHOOL-05", 3));
+        assert_eq!(a.get_version_num(), 5);
+    }
+
+    #[test]
+    fn consecutive_steps_form_a_lineage() {
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        let lineage: Vec<_> = (0..3)
+            .map(|step| {
+                let report = controller
+                    .update_node_duty(&mut node, EcoBand::Green, 0.0)
+                    .unwrap();
+                bee_envelope(&controller, &node, &report, step)
+                    .unwrap()
+                    .lineage()
+            })
+            .collect();
+        assert_eq!(lineage[0].parent_id, None);
+        assert_eq!(lineage[2].sequence, 2);
+        assert_eq!(verify_chain(&lineage), Ok(()));
+    }
+
+    #[test]
+    fn delta_stream_of_spine_envelopes_stays_within_half_a_step() {
+        use crate::delta::{DeltaEncoder, EnvelopeFrame, FrameType};
+        use crate::DeterministicRng;
+
+        let controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        let mut encoder = DeltaEncoder::new(10, 1000);
+        let bound = encoder.error_bound() + 1e-12;
+        let mut rng = DeterministicRng::new(7);
+        let mut received: Option<BeeEnvelope> = None;
+        let mut buf = [0u8; EnvelopeFrame::MAX_WIRE_LEN];
+        for step in 0..100 {
+            node.mass_kg *= 1.0 + (rng.next_f64() - 0.5) * 2e-2;
+            let report = controller
+                .update_node_duty(&mut node, EcoBand::Green, 0.0)
+                .unwrap();
+            let sent = bee_envelope(&controller, &node, &report, step).unwrap();
+
+            let frame = encoder.encode(&sent);
+            assert_eq!(frame.frame_type() == FrameType::Keyframe, step == 0);
+            let len = frame.to_wire_slice(&mut buf).unwrap();
+            received = EnvelopeFrame::from_wire(&buf[..len])
+                .unwrap()
+                .reconstruct(received.as_ref());
+
+            let got = received.as_ref().unwrap();
+            assert_eq!(got.lineage(), sent.lineage());
+            assert_eq!(got.source_id, sent.source_id);
+            for (g, s) in [
+                (got.host_budget_index(), sent.host_budget_index()),
+                (got.eco_band_index(), sent.eco_band_index()),
+                (got.dw_ceiling_index(), sent.dw_ceiling_index()),
+            ] {
+                assert!((g.get() - s.get()).abs() <= bound, "step {step}");
+            }
+        }
+    }
+
+    #[test]
+    fn mismatched_report_and_bad_references_are_rejected() {
+        let mut controller = ControllerConfig::from_toml(CONFIG)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        let mut node = canopy();
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+
+        let mut other = node.clone();
+        other.row.machine_id = "CYB-AIR-SCHOOL-05".into();
+        assert_eq!(
+            bee_envelope(&controller, &other, &report, 0).unwrap_err(),
+            SpineError::ReportMismatch {
+                node: "CYB-AIR-SCHOOL-05".into(),
+                report: "CYB-AIR-CANOPY-01".into(),
+            }
+        );
+
+        controller.k_ref_nb = 0.0;
+        assert!(matches!(
+            bee_envelope(&controller, &node, &report, 0),
+            Err(SpineError::Safety(SafetyError::InvalidReference {
+                name: "k_ref_nb",
+                ..
+            }))
+        ));
+    }
+}
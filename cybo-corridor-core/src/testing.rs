@@ -0,0 +1,170 @@
+//! Proptest strategies for bands, envelopes and bee states.
+//!
+//! `any_*` strategies cover the whole value space: indices are clamped
+//! from arbitrary `f64`s (NaN, infinities and negatives included), so the
+//! edges 0 and 1 come up often, and `hb_score` is unconstrained. `valid_*`
+//! strategies draw indices and `hb_score` uniformly from [0, 1]; they may
+//! still fail the residual or host/eco checks. [`admissible_bee_state`] goes further and
+//! only yields states `BeeHysteresisRule` accepts.
+
+use proptest::num::f64::ANY as ANY_F64;
+use proptest::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    BeeBand, BeeCorridorInvariant, BeeEnvelope, BeeState, HostBudgetEnvelope, MetricFamily,
+    NormalizedIndex,
+};
+
+/// Every first-party family, plus custom ones.
+pub fn any_metric_family() -> impl Strategy<Value = MetricFamily> {
+    prop_oneof![
+        proptest::sample::select(
+            &[
+                MetricFamily::BeeThermal,
+                MetricFamily::BeeChem,
+                MetricFamily::BeeEMF,
+                MetricFamily::BeeNoise,
+                MetricFamily::MarineThermal,
+                MetricFamily::MarineSalinity,
+                MetricFamily::MarineShear,
+                MetricFamily::MarineNoise,
+                MetricFamily::UrbanHeatIndex,
+                MetricFamily::UrbanWBGT,
+                MetricFamily::UrbanNOx,
+            ][..]
+        ),
+        (any::<u16>(), any::<u16>())
+            .prop_map(|(namespace, id)| MetricFamily::Custom { namespace, id }),
+    ]
+}
+
+fn band(
+    family: impl Strategy<Value = MetricFamily>,
+    index: impl Strategy<Value = NormalizedIndex> + Clone,
+) -> impl Strategy<Value = BeeBand> {
+    (family, index.clone(), index.clone(), index).prop_map(
+        |(family, host_budget, eco_band, dw_ceiling)| BeeBand {
+            family,
+            host_budget,
+            eco_band,
+            dw_ceiling,
+        },
+    )
+}
+
+/// Any band, with arbitrary indices.
+pub fn any_bee_band() -> impl Strategy<Value = BeeBand> {
+    band(
+        any_metric_family(),
+        ANY_F64.prop_map(NormalizedIndex::new_clamped),
+    )
+}
+
+/// A bee band with every index in [0, 1].
+pub fn valid_bee_band() -> impl Strategy<Value = BeeBand> {
+    let family = proptest::sample::select(
+        &[
+            MetricFamily::BeeThermal,
+            MetricFamily::BeeChem,
+            MetricFamily::BeeEMF,
+            MetricFamily::BeeNoise,
+        ][..],
+    );
+    band(
+        family,
+        (0.0..=1.0f64).prop_map(NormalizedIndex::new_clamped),
+    )
+}
+
+fn envelope(band: impl Strategy<Value = BeeBand>) -> impl Strategy<Value = BeeEnvelope> {
+    (
+        band,
+        any::<u128>(),
+        proptest::option::of(any::<u128>()),
+        any::<u32>(),
+        proptest::option::of(any::<u128>()),
+    )
+        .prop_map(
+            |(band, trace_id, parent_id, sequence, source_id)| BeeEnvelope {
+                band,
+                trace_id: Uuid::from_u128(trace_id),
+                parent_id: parent_id.map(Uuid::from_u128),
+                sequence,
+                source_id: source_id.map(Uuid::from_u128),
+            },
+        )
+}
+
+/// Any envelope, over [`any_bee_band`] and arbitrary ids.
+pub fn any_bee_envelope() -> impl Strategy<Value = BeeEnvelope> {
+    envelope(any_bee_band())
+}
+
+/// An envelope over [`valid_bee_band`].
+pub fn valid_bee_envelope() -> impl Strategy<Value = BeeEnvelope> {
+    envelope(valid_bee_band())
+}
+
+/// Any state, over [`any_bee_envelope`] and an arbitrary score.
+pub fn any_bee_state() -> impl Strategy<Value = BeeState> {
+    (any_bee_envelope(), ANY_F64).prop_map(|(envelope, hb_score)| BeeState { envelope, hb_score })
+}
+
+/// A state over [`valid_bee_envelope`] with `hb_score` in [0, 1].
+pub fn valid_bee_state() -> impl Strategy<Value = BeeState> {
+    (valid_bee_envelope(), 0.0..=1.0f64)
+        .prop_map(|(envelope, hb_score)| BeeState { envelope, hb_score })
+}
+
+/// A valid state `BeeHysteresisRule` accepts under `inv`: positive eco
+/// band, `host_budget <= 0.85 * eco_band`, and inside the envelope.
+pub fn admissible_bee_state(inv: BeeCorridorInvariant) -> impl Strategy<Value = BeeState> {
+    (valid_bee_state(), 0.0..=0.85f64, 1.0e-3..=1.0f64)
+        .prop_map(|(mut state, ratio, eco)| {
+            state.envelope.band.eco_band = NormalizedIndex::new_clamped(eco);
+            state.envelope.band.host_budget = NormalizedIndex::new_clamped(ratio * eco);
+            state
+        })
+        .prop_filter("outside the envelope", move |state| {
+            state.envelope.is_within_envelope(&inv)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BeeHysteresisRule, HysteresisRule};
+
+    proptest! {
+        #[test]
+        fn valid_bands_stay_in_unit_cube(band in valid_bee_band()) {
+            for index in [band.host_budget, band.eco_band, band.dw_ceiling] {
+                prop_assert!((0.0..=1.0).contains(&index.get()));
+            }
+        }
+
+        #[test]
+        fn hysteresis_never_leaves_the_envelope(
+            current in admissible_bee_state(BeeCorridorInvariant::default()),
+            proposed in any_bee_state(),
+        ) {
+            let inv = BeeCorridorInvariant::default();
+            let next = BeeHysteresisRule.next_state(&current, &proposed, &inv);
+            let band = &next.envelope.band;
+            prop_assert!(next.envelope.is_within_envelope(&inv));
+            prop_assert!(band.eco_band > 0.0);
+            prop_assert!(band.host_budget <= band.eco_band * 0.85);
+        }
+
+        #[test]
+        fn hysteresis_keeps_admissible_proposals(
+            current in admissible_bee_state(BeeCorridorInvariant::default()),
+            proposed in admissible_bee_state(BeeCorridorInvariant::default()),
+        ) {
+            let inv = BeeCorridorInvariant::default();
+            let next = BeeHysteresisRule.next_state(&current, &proposed, &inv);
+            prop_assert_eq!(next.envelope.trace_id, proposed.envelope.trace_id);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Envelope lineage: which envelope produced which.
+//!
+//! A [`TraceLineage`] links a trace id to its parent's and numbers it within
+//! the lineage. Sequences strictly increase from parent to child but need
+//! not be contiguous, so a receiver that dropped frames can still check the
+//! ones it has with [`verify_chain`]. With the `trace-gen` feature,
+//! [`TraceIdGen`] mints UUIDv7 ids, which sort by creation time.
+
+use core::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[cfg(feature = "trace-gen")]
+use crate::DeterministicRng;
+use crate::{BeeEnvelope, Traceable};
+
+/// Position of a trace id in its lineage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TraceLineage {
+    pub trace_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub sequence: u32,
+}
+
+impl TraceLineage {
+    /// Start of a lineage.
+    pub fn root(trace_id: Uuid) -> Self {
+        TraceLineage {
+            trace_id,
+            parent_id: None,
+            sequence: 0,
+        }
+    }
+
+    /// Next link after `self`. The sequence saturates at `u32::MAX`, after
+    /// which [`follows`](Self::follows) rejects further children.
+    pub fn child(&self, trace_id: Uuid) -> Self {
+        TraceLineage {
+            trace_id,
+            parent_id: Some(self.trace_id),
+            sequence: self.sequence.saturating_add(1),
+        }
+    }
+
+    /// True if `self` names `parent` as its parent and comes after it.
+    pub fn follows(&self, parent: &TraceLineage) -> bool {
+        self.parent_id == Some(parent.trace_id) && self.sequence > parent.sequence
+    }
+}
+
+/// Why [`verify_chain`] rejected a lineage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LineageError {
+    /// `chain[index]` does not name `chain[index - 1]` as its parent.
+    BrokenLink { index: usize },
+    /// `chain[index]` does not have a larger sequence than its parent.
+    NonMonotonic {
+        index: usize,
+        parent: u32,
+        sequence: u32,
+    },
+}
+
+impl fmt::Display for LineageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineageError::BrokenLink { index } => {
+                write!(f, "link {index} does not follow its predecessor")
+            }
+            LineageError::NonMonotonic {
+                index,
+                parent,
+                sequence,
+            } => write!(
+                f,
+                "link {index} has sequence {sequence}, not after its parent's {parent}"
+            ),
+        }
+    }
+}
+
+/// Check that each link in `chain` is the child of the one before it.
+pub fn verify_chain(chain: &[TraceLineage]) -> Result<(), LineageError> {
+    for (index, pair) in chain.windows(2).enumerate() {
+        let (parent, link) = (&pair[0], &pair[1]);
+        let index = index + 1;
+        if link.parent_id != Some(parent.trace_id) {
+            return Err(LineageError::BrokenLink { index });
+        }
+        if link.sequence <= parent.sequence {
+            return Err(LineageError::NonMonotonic {
+                index,
+                parent: parent.sequence,
+                sequence: link.sequence,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Traceable types that also record where they came from.
+pub trait TraceableLineage: Traceable {
+    fn lineage(&self) -> TraceLineage;
+}
+
+impl TraceableLineage for BeeEnvelope {
+    fn lineage(&self) -> TraceLineage {
+        TraceLineage {
+            trace_id: self.trace_id,
+            parent_id: self.parent_id,
+            sequence: self.sequence,
+        }
+    }
+}
+
+impl BeeEnvelope {
+    /// Set this envelope's trace fields from `lineage`.
+    pub fn set_lineage(&mut self, lineage: TraceLineage) {
+        self.trace_id = lineage.trace_id;
+        self.parent_id = lineage.parent_id;
+        self.sequence = lineage.sequence;
+    }
+}
+
+/// Source of wall-clock time for [`TraceIdGen`].
+#[cfg(feature = "trace-gen")]
+pub trait Clock {
+    /// Milliseconds since the Unix epoch.
+    fn now_unix_ms(&self) -> u64;
+}
+
+/// UUIDv7 generator: 48-bit millisecond timestamp, a 12-bit counter that
+/// keeps ids from one generator strictly increasing within a millisecond
+/// (and across clock steps backwards), then seeded random bits.
+#[cfg(feature = "trace-gen")]
+#[derive(Clone, Debug)]
+pub struct TraceIdGen<C> {
+    clock: C,
+    rng: DeterministicRng,
+    last_ms: u64,
+    counter: u16,
+}
+
+#[cfg(feature = "trace-gen")]
+impl<C: Clock> TraceIdGen<C> {
+    /// Generators sharing a clock should use distinct seeds.
+    pub fn new(clock: C, seed: u64) -> Self {
+        TraceIdGen {
+            clock,
+            rng: DeterministicRng::new(seed),
+            last_ms: 0,
+            counter: 0,
+        }
+    }
+
+    pub fn next_id(&mut self) -> Uuid {
+        let now = self.clock.now_unix_ms();
+        if now > self.last_ms {
+            self.last_ms = now;
+            self.counter = 0;
+        } else if self.counter < 0x0FFF {
+            self.counter += 1;
+        } else {
+            // Counter exhausted: borrow the next millisecond.
+            self.last_ms += 1;
+            self.counter = 0;
+        }
+        let mut bytes = [0u8; 10];
+        bytes[..2].copy_from_slice(&self.counter.to_be_bytes());
+        bytes[2..].copy_from_slice(&self.rng.next_u64().to_be_bytes());
+        uuid::Builder::from_unix_timestamp_millis(self.last_ms, &bytes).into_uuid()
+    }
+
+    /// Lineage root with a fresh id.
+    pub fn root(&mut self) -> TraceLineage {
+        TraceLineage::root(self.next_id())
+    }
+
+    /// Child of `parent` with a fresh id.
+    pub fn child_of(&mut self, parent: &TraceLineage) -> TraceLineage {
+        parent.child(self.next_id())
+    }
+}
+
+#[cfg(all(test, feature = "wire"))]
+mod tests {
+    use super::*;
+    use crate::MetricFamily;
+
+    #[test]
+    fn chains_must_link_and_increase() {
+        let root = TraceLineage::root(Uuid::from_u128(1));
+        let a = root.child(Uuid::from_u128(2));
+        let b = a.child(Uuid::from_u128(3));
+        assert!(b.follows(&a) && !b.follows(&root));
+        assert_eq!(verify_chain(&[root, a, b]), Ok(()));
+        // Gaps in the sequence are fine.
+        let c = TraceLineage {
+            sequence: 9,
+            ..b.child(Uuid::from_u128(4))
+        };
+        assert_eq!(verify_chain(&[a, b, c]), Ok(()));
+
+        assert_eq!(
+            verify_chain(&[root, b]),
+            Err(LineageError::BrokenLink { index: 1 })
+        );
+        let stale = TraceLineage {
+            sequence: 1,
+            ..b.child(Uuid::from_u128(5))
+        };
+        assert_eq!(
+            verify_chain(&[a, b, stale]),
+            Err(LineageError::NonMonotonic {
+                index: 2,
+                parent: 2,
+                sequence: 1
+            })
+        );
+    }
+
+    #[test]
+    fn envelopes_without_lineage_fields_still_decode() {
+        let old = r#"{
+            "band": {"family": "BeeChem", "host_budget": 0.4, "eco_band": 0.6, "dw_ceiling": 0.3},
+            "trace_id": "0123456789abcdef0123456789abcdef"
+        }"#;
+        let env: BeeEnvelope = serde_json::from_str(old).unwrap();
+        assert_eq!(
+            env.lineage(),
+            TraceLineage::root(Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef))
+        );
+
+        let mut child = env.clone();
+        child.set_lineage(env.lineage().child(Uuid::from_u128(7)));
+        let back: BeeEnvelope =
+            serde_json::from_str(&serde_json::to_string(&child).unwrap()).unwrap();
+        assert!(back.lineage().follows(&env.lineage()));
+        assert_eq!(back.band.family, MetricFamily::BeeChem);
+    }
+
+    #[cfg(feature = "trace-gen")]
+    #[test]
+    fn generated_ids_are_time_ordered() {
+        use core::cell::Cell;
+
+        struct Steps<'a>(&'a Cell<u64>);
+        impl Clock for Steps<'_> {
+            fn now_unix_ms(&self) -> u64 {
+                self.0.get()
+            }
+        }
+
+        let now = Cell::new(1_750_000_000_000);
+        let mut gen = TraceIdGen::new(Steps(&now), 7);
+        let mut ids = Vec::new();
+        for ms in [0, 0, 0, 5, 5, 3, 9] {
+            // The clock stepping back (5 to 3) must not reorder ids.
+            now.set(1_750_000_000_000 + ms);
+            ids.push(gen.next_id());
+        }
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "{ids:?}");
+        assert!(ids.iter().all(|id| id.get_version_num() == 7));
+        let (secs, _) = ids[6].get_timestamp().unwrap().to_unix();
+        assert_eq!(secs, 1_750_000_000);
+
+        let root = gen.root();
+        let child = gen.child_of(&root);
+        assert_eq!(verify_chain(&[root, child]), Ok(()));
+        assert!(root.trace_id < child.trace_id);
+    }
+}
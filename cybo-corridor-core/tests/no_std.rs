@@ -0,0 +1,48 @@
+//! CI guard: the core-only build must keep compiling for a bare-metal
+//! target, so no default feature's dependencies leak into it.
+
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+fn target_installed() -> bool {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let Ok(out) = Command::new(rustc).args(["--print", "sysroot"]).output() else {
+        return false;
+    };
+    let sysroot = String::from_utf8_lossy(&out.stdout);
+    Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(TARGET)
+        .exists()
+}
+
+fn check(features: &[&str]) {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+    let mut cmd = Command::new(env!("CARGO"));
+    cmd.args(["check", "--lib", "--no-default-features"])
+        .args(["--target", TARGET])
+        .arg("--manifest-path")
+        .arg(manifest)
+        .env("CARGO_TARGET_DIR", target_dir);
+    if !features.is_empty() {
+        cmd.args(["--features", &features.join(",")]);
+    }
+    let status = cmd.status().expect("run cargo check");
+    assert!(status.success(), "no_std check failed for {features:?}");
+}
+
+#[test]
+fn core_only_build_checks_for_bare_metal() {
+    if !target_installed() {
+        eprintln!("skipping: `rustup target add {TARGET}` to run this check");
+        return;
+    }
+    check(&[]);
+    check(&["defmt"]);
+    check(&["wire"]);
+    check(&["trace-gen"]);
+    check(&["alloc", "wire", "defmt"]);
+}
@@ -1,224 +1,261 @@
 use std::error::Error;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-#[derive(Debug, Clone)]
-struct CyboAirRow {
-    machine_id: String,
-    r#type: String,
-    location: String,
-    pollutant: String,
-    cin: f64,
-    cout: f64,
-    unit: String,
-    airflow_m3_per_s: f64,
-    period_s: f64,
-    lambda_hazard: f64,
-    beta_nb_per_kg: f64,
-    ecoimpact_score: f64,
-    bee_flag: u8,      // 1 if in bee foraging microspace
-    bee_weight: f64,   // additional hazard multiplier for bees
-    notes: String,
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::{value_parser, Arg, ArgAction, Command};
+use cyboair_bee_guard_core::config::{self, Config};
+use cyboair_bee_guard_core::output::OutputFormat;
+use cyboair_bee_guard_core::{
+    locate_hive, update_duty_microspace, update_karma, Conditions, HiveRegistry, MicrospaceGains,
+    NodeState, Row, MICROSPACE_COLUMNS,
+};
+use cyboair_corridor_safety::loader::LoadError;
+use cyboair_corridor_safety::merge::{merge_colocated, MergeOptions};
+use cyboair_corridor_safety::units::UnitError;
+
+use output::{NodeTelemetry, TelemetryWriter};
+use validate::ReportFormat;
+
+mod output;
+mod stream;
+mod validate;
+
+fn read_rows<R: Read>(reader: R) -> Result<Vec<Row>, LoadError> {
+    cyboair_bee_guard_core::read_rows(reader, MICROSPACE_COLUMNS)
 }
 
-#[derive(Debug, Clone)]
-struct NodeState {
-    row: CyboAirRow,
-    mass_kg: f64,
-    karma_bee: f64,
-    duty_cycle: f64,
-    emf_score: f64,
+// Per-step model parameters shared by the single-pass and streaming modes
+#[derive(Debug, Clone, Default)]
+struct BeeParams {
+    conditions: Conditions,
+    gains: MicrospaceGains,
+    hives: HiveRegistry,
 }
 
-fn unit_to_kg_factor(unit: &str, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    match unit {
-        "ug/m3" => 1e-9,
-        "mg/m3" => 1e-6,
-        "ppb" => {
-            let r = 8.3145_f64;
-            molar_mass_kg_per_mol / (r * temperature_k) * 1e-9
-        }
-        _ => 0.0,
+impl BeeParams {
+    fn update(&self, node: &mut NodeState) -> Result<(), UnitError> {
+        locate_hive(node, &self.hives);
+        update_karma(node, &self.conditions)?;
+        update_duty_microspace(node, &self.gains);
+        Ok(())
+    }
+}
+
+fn parse_tick(s: &str) -> Result<Duration, String> {
+    match s.parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs > 0.0 => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!("invalid --tick-secs '{s}'")),
     }
 }
 
-fn parse_csv_row(line: &str) -> Result<CyboAirRow, Box<dyn Error>> {
-    let mut parts: Vec<String> = Vec::new();
-    let mut current = String::new();
-    let mut in_quotes = false;
-
-    for c in line.chars() {
-        match c {
-            '"' => {
-                in_quotes = !in_quotes;
-            }
-            ',' if !in_quotes => {
-                parts.push(current.trim().to_string());
-                current.clear();
-            }
-            _ => current.push(c),
+// The shared config flags plus the streaming and validation modes, which
+// are flags only
+fn command() -> Command {
+    config::command::<MicrospaceGains>("cybo_air_bee_guard")
+        .about("Bee-microspace duty cycles, once or as a streaming loop")
+        .mut_arg("input", |a| a.help("Shard CSV, or - for stdin [input]"))
+        .mut_arg("hives", |a| {
+            a.help("Hive registry JSON; rows with lat/lon then use hive distance [hives]")
+        })
+        .arg(
+            Arg::new("shard")
+                .value_name("SHARD")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("input")
+                .help("Same as --input"),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .action(ArgAction::SetTrue)
+                .help("Keep running, picking up appended rows every tick"),
+        )
+        .arg(
+            Arg::new("tick-secs")
+                .long("tick-secs")
+                .value_name("N")
+                .value_parser(parse_tick)
+                .default_value("300"),
+        )
+        .arg(
+            Arg::new("state")
+                .long("state")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .default_value("cybo_air_bee_guard_state.json"),
+        )
+        .arg(
+            Arg::new("validate")
+                .long("validate")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("stream")
+                .help("Check the shard without updating any node; exit 1 on errors"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("text|json")
+                .value_parser(|s: &str| s.parse::<ReportFormat>())
+                .default_value("text")
+                .help("Report format for --validate"),
+        )
+        .arg(
+            Arg::new("max-mass-kg")
+                .long("max-mass-kg")
+                .value_name("X")
+                .value_parser(value_parser!(f64))
+                .help("With --validate, the most mass a row may imply, kg"),
+        )
+        .arg(
+            Arg::new("max-karma")
+                .long("max-karma")
+                .value_name("X")
+                .value_parser(value_parser!(f64))
+                .help("With --validate, the most karma a row may imply"),
+        )
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = command().get_matches();
+    let file = match matches.get_one::<PathBuf>("config") {
+        Some(path) => Some(fs::read_to_string(path)?),
+        None => None,
+    };
+    let mut cfg: Config<MicrospaceGains> = match config::resolve(&matches, file.as_deref()) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
         }
+    };
+    if let Some(shard) = matches.get_one::<PathBuf>("shard") {
+        cfg.input = shard.clone();
     }
-    if !current.is_empty() {
-        parts.push(current.trim().to_string());
+    if matches.get_flag("print-effective-config") {
+        print!("{}", cfg.to_toml()?);
+        return Ok(());
     }
 
-    if parts.len() < 14 {
-        return Err("Not enough columns in CyboAir+Bee row".into());
+    if matches.get_flag("validate") {
+        let options = validate::Options {
+            conditions: cfg.conditions.clone(),
+            max_mass_kg: matches.get_one::<f64>("max-mass-kg").copied(),
+            max_karma_bytes: matches.get_one::<f64>("max-karma").copied(),
+        };
+        let report = if cfg.input.as_os_str() == "-" {
+            validate::validate_shard(std::io::stdin().lock(), &options)
+        } else {
+            validate::validate_shard(File::open(&cfg.input)?, &options)
+        };
+        let format = *matches.get_one::<ReportFormat>("format").unwrap();
+        report.write(format, std::io::stdout().lock())?;
+        std::process::exit(report.exit_code());
     }
 
-    Ok(CyboAirRow {
-        machine_id: parts[0].clone(),
-        r#type: parts[1].clone(),
-        location: parts[2].clone(),
-        pollutant: parts[3].clone(),
-        cin: parts[4].parse()?,
-        cout: parts[5].parse()?,
-        unit: parts[6].clone(),
-        airflow_m3_per_s: parts[7].parse()?,
-        period_s: parts[8].parse()?,
-        lambda_hazard: parts[9].parse()?,
-        beta_nb_per_kg: parts[10].parse()?,
-        ecoimpact_score: parts[11].parse()?,
-        bee_flag: parts[12].parse()?,
-        bee_weight: parts[13].parse()?,
-        notes: if parts.len() > 14 { parts[14..].join(",") } else { String::new() },
-    })
-}
-
-fn update_node_bee(
-    node: &mut NodeState,
-    temperature_k: f64,
-    molar_mass_kg_per_mol: f64,
-    m_ref: f64,
-    k_ref: f64,
-    e_ref_bee: f64,
-    eta1: f64,
-    eta2: f64,
-    eta3: f64,
-    eta4: f64,
-) {
-    let r = &node.row;
-    let alpha = unit_to_kg_factor(&r.unit, temperature_k, molar_mass_kg_per_mol);
-    let d_c = (r.cin - r.cout).max(0.0);
-    let c_u = alpha * d_c;
-
-    // CEIM-style mass for this pollutant
-    node.mass_kg = c_u * r.airflow_m3_per_s * r.period_s;
-
-    // Bee-weighted hazard: existing lambda * bee_weight if flag set
-    let lambda_bee = if node.row.bee_flag == 1 {
-        r.lambda_hazard * r.bee_weight
-    } else {
-        r.lambda_hazard
+    let streaming = matches.get_flag("stream");
+    let stream_config = stream::StreamConfig {
+        source: if cfg.input.as_os_str() == "-" {
+            stream::Source::Stdin
+        } else {
+            stream::Source::File(cfg.input.clone())
+        },
+        tick: *matches.get_one::<Duration>("tick-secs").unwrap(),
+        state_path: matches.get_one::<PathBuf>("state").unwrap().clone(),
     };
+    let format = *matches.get_one::<OutputFormat>("output-format").unwrap();
+    let output = matches.get_one::<PathBuf>("output").cloned();
 
-    node.karma_bee = lambda_bee * r.beta_nb_per_kg * node.mass_kg;
+    let params = BeeParams {
+        conditions: cfg.conditions,
+        gains: cfg.gains,
+        hives: match &cfg.hives {
+            Some(path) => HiveRegistry::from_path(path)?,
+            None => HiveRegistry::default(),
+        },
+    };
+    let mut writer = TelemetryWriter::new(format, output, streaming);
+    if streaming {
+        return stream::run(&stream_config, &params, &mut writer);
+    }
 
-    // Simple local EMF score proxy: treat high-airflow nodes in bee zones as more EMF-sensitive
-    node.emf_score = if node.row.bee_flag == 1 {
-        (r.airflow_m3_per_s / 3.0).min(1.0)
-    } else {
-        0.0
+    let rows = match &stream_config.source {
+        stream::Source::File(path) => read_rows(File::open(path)?)?,
+        stream::Source::Stdin => read_rows(std::io::stdin().lock())?,
     };
+    // Redundant sensors on one machine become one node
+    let (rows, _) = merge_colocated(rows, &MergeOptions::default())?;
+    let mut nodes: Vec<NodeState> = rows.into_iter().map(NodeState::new).collect();
 
-    // Bee-aware geospatial weight (simplified)
-    let mut w_bee = 0.5;
-    if node.row.bee_flag == 1 {
-        w_bee += 0.3;
-    }
-    if node.row.location.contains("School")
-        || node.row.location.contains("Orchard")
-        || node.row.location.contains("Garden")
-    {
-        w_bee += 0.2;
-    }
-    w_bee -= (node.emf_score / e_ref_bee).min(0.5);
-
-    // Duty-cycle update with projection to [0,1]
-    let mut u = node.duty_cycle
-        + eta1 * (node.mass_kg / m_ref)
-        + eta2 * (node.karma_bee / k_ref)
-        + eta3 * w_bee
-        - eta4 * node.emf_score;
-
-    if u < 0.0 {
-        u = 0.0;
-    } else if u > 1.0 {
-        u = 1.0;
+    // Single update step; use --stream to keep duty cycles evolving
+    for node in nodes.iter_mut() {
+        params.update(node)?;
     }
-    node.duty_cycle = u;
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Adjust path to your extended shard with bee columns
-    let file = File::open("qpudatashards/particles/CyboAirTenMachinesPhoenix2026v1_bee.csv")?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
+    let now = Utc::now();
+    let records: Vec<NodeTelemetry> = nodes
+        .iter()
+        .map(|node| NodeTelemetry::new(node, 0, now))
+        .collect();
+    writer.emit(&records)?;
 
-    // Skip header
-    let _header = lines.next();
+    Ok(())
+}
 
-    let mut nodes: Vec<NodeState> = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for line_res in lines {
-        let line = line_res?;
-        if line.trim().is_empty() {
-            continue;
-        }
-        let row = parse_csv_row(&line)?;
-        nodes.push(NodeState {
-            row,
-            mass_kg: 0.0,
-            karma_bee: 0.0,
-            duty_cycle: 0.0,
-            emf_score: 0.0,
-        });
+    #[test]
+    fn fixture_with_quoted_notes_parses() {
+        let rows = read_rows(&include_bytes!("../fixtures/ten_machines_bee.csv")[..]).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].notes, "Upwind of apiary, reducing PM2.5 near hives");
+        assert_eq!(rows[1].notes, "Drop-off loop, 7-9am; \"quiet\" fan profile");
+        assert_eq!(rows[1].bee_flag, 0);
+        assert_eq!(rows[2].notes, "");
+        assert_eq!(rows[2].ecoimpact_score, Some(0.90));
     }
 
-    // Representative parameters (Phoenix summer, ozone surrogate MW)
-    let temperature_k = 310.0_f64;
-    let molar_mass_kg_per_mol = 0.048_f64;
-    let m_ref = 1e-6_f64;
-    let k_ref = 1e10_f64;
-    let e_ref_bee = 1.0_f64;
-
-    // Control gains
-    let eta1 = 0.1_f64;
-    let eta2 = 0.1_f64;
-    let eta3 = 0.2_f64;
-    let eta4 = 0.05_f64;
-
-    // Single update step; in deployment, this runs in a loop
-    for node in nodes.iter_mut() {
-        update_node_bee(
-            node,
-            temperature_k,
-            molar_mass_kg_per_mol,
-            m_ref,
-            k_ref,
-            e_ref_bee,
-            eta1,
-            eta2,
-            eta3,
-            eta4,
+    #[test]
+    fn broken_fixture_names_line_and_column() {
+        let err = read_rows(&include_bytes!("../fixtures/ten_machines_bee_broken.csv")[..])
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "line 3, column bee_flag: invalid integer 'yes'"
         );
     }
 
-    println!("machine_id,location,type,pollutant,mass_kg,karma_bee,duty_cycle,emf_score");
-    for node in nodes.iter() {
-        println!(
-            "{},{},{},{},{:.6e},{:.6e},{:.3},{:.3}",
-            node.row.machine_id,
-            node.row.location,
-            node.row.r#type,
-            node.row.pollutant,
-            node.mass_kg,
-            node.karma_bee,
-            node.duty_cycle,
-            node.emf_score,
+    #[test]
+    fn shard_may_be_positional_but_not_both() {
+        let m = command()
+            .try_get_matches_from(["cybo_air_bee_guard", "a.csv", "--tick-secs", "0.5"])
+            .unwrap();
+        assert_eq!(m.get_one::<PathBuf>("shard"), Some(&PathBuf::from("a.csv")));
+        assert_eq!(
+            m.get_one::<Duration>("tick-secs"),
+            Some(&Duration::from_millis(500))
         );
+
+        let bad_tick = command().try_get_matches_from(["cybo_air_bee_guard", "--tick-secs", "0"]);
+        assert!(bad_tick.is_err());
+        let clash =
+            command().try_get_matches_from(["cybo_air_bee_guard", "a.csv", "--input", "b.csv"]);
+        assert!(clash.is_err());
     }
 
-    Ok(())
+    #[test]
+    fn missing_columns_are_listed() {
+        let csv = "machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,\
+                   lambda_hazard,beta_nb_per_kg,notes\n";
+        let err = read_rows(csv.as_bytes()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "missing required columns: ecoimpact_score, bee_flag, bee_weight"
+        );
+    }
 }
@@ -0,0 +1,244 @@
+// Bee-microspace telemetry records and the per-step writer; formats and
+// file output live in cyboair_bee_guard_core::output.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use cyboair_bee_guard_core::output::{
+    render, write_atomic, CsvRecord, OutputFormat, SCHEMA_VERSION,
+};
+use cyboair_bee_guard_core::NodeState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeTelemetry {
+    pub schema_version: u32,
+    pub timestamp: DateTime<Utc>,
+    // 0 for a single pass; the tick number in streaming mode
+    pub step: u64,
+    pub machine_id: String,
+    pub location: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub pollutant: String,
+    pub mass_kg: f64,
+    pub karma_bee: f64,
+    pub duty_cycle: f64,
+    pub emf_score: f64,
+    // Set when the row has coordinates and a hive registry is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_from_hive_m: Option<f64>,
+}
+
+impl NodeTelemetry {
+    pub fn new(node: &NodeState, step: u64, timestamp: DateTime<Utc>) -> Self {
+        NodeTelemetry {
+            schema_version: SCHEMA_VERSION,
+            timestamp,
+            step,
+            machine_id: node.row.machine_id.clone(),
+            location: node.row.location.clone(),
+            r#type: node.row.r#type.clone(),
+            pollutant: node.row.pollutant.clone(),
+            mass_kg: node.mass_kg,
+            karma_bee: node.bee_karma_bytes,
+            duty_cycle: node.duty_cycle,
+            emf_score: node.emf_score,
+            distance_from_hive_m: node.distance_from_hive_m,
+        }
+    }
+}
+
+impl CsvRecord for NodeTelemetry {
+    fn csv_header() -> String {
+        "machine_id,location,type,pollutant,mass_kg,karma_bee,duty_cycle,emf_score".to_string()
+    }
+
+    fn csv_record(&self) -> String {
+        format!(
+            "{},{},{},{},{:.6e},{:.6e},{:.3},{:.3}",
+            self.machine_id,
+            self.location,
+            self.r#type,
+            self.pollutant,
+            self.mass_kg,
+            self.karma_bee,
+            self.duty_cycle,
+            self.emf_score,
+        )
+    }
+}
+
+// A record whose CSV row leads with its step, as streaming mode writes it
+#[derive(Serialize)]
+#[serde(transparent)]
+struct Ticked<'a>(&'a NodeTelemetry);
+
+impl CsvRecord for Ticked<'_> {
+    fn csv_header() -> String {
+        format!("tick,{}", NodeTelemetry::csv_header())
+    }
+
+    fn csv_record(&self) -> String {
+        format!("{},{}", self.0.step, self.0.csv_record())
+    }
+}
+
+// Emits one batch of records per step. On stdout batches follow each other
+// (the CSV header only once); a file holds only the latest batch.
+pub struct TelemetryWriter {
+    format: OutputFormat,
+    path: Option<PathBuf>,
+    // Prefix CSV rows with the step, as streaming mode does
+    csv_step_column: bool,
+    header_written: bool,
+}
+
+impl TelemetryWriter {
+    pub fn new(format: OutputFormat, path: Option<PathBuf>, csv_step_column: bool) -> Self {
+        TelemetryWriter {
+            format,
+            path,
+            csv_step_column,
+            header_written: false,
+        }
+    }
+
+    pub fn render(&self, records: &[NodeTelemetry], with_header: bool) -> io::Result<Vec<u8>> {
+        if self.csv_step_column {
+            let ticked: Vec<Ticked> = records.iter().map(Ticked).collect();
+            render(self.format, &ticked, with_header)
+        } else {
+            render(self.format, records, with_header)
+        }
+    }
+
+    pub fn emit(&mut self, records: &[NodeTelemetry]) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write_atomic(path, &self.render(records, true)?),
+            None => {
+                let bytes = self.render(records, !self.header_written)?;
+                self.header_written = true;
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&bytes)?;
+                stdout.flush()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use cyboair_bee_guard_core::Row;
+
+    fn node(machine_id: &str, duty_cycle: f64) -> NodeState {
+        NodeState {
+            row: Row {
+                machine_id: machine_id.into(),
+                r#type: "ApiaryCanopy".into(),
+                location: "Phoenix-Garden-North".into(),
+                pollutant: "PM2.5".into(),
+                cin: 30.0,
+                cout: 20.0,
+                unit: "ug/m3".into(),
+                airflow_m3_per_s: 0.8,
+                period_s: 3600.0,
+                lambda_hazard: 3.5,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: Some(0.93),
+                bee_flag: 1,
+                bee_weight: 1.5,
+                notes: String::new(),
+                lat: None,
+                lon: None,
+                hive_id: None,
+                window_start_unix_ms: None,
+            },
+            mass_kg: 2.88e-5,
+            air_karma_bytes: 50_400.0,
+            bee_karma_bytes: 75_600.123456789,
+            duty_cycle,
+            sbee: 1.0,
+            emf_score: 0.266_666_666_666_666_7,
+            nearest_hive: None,
+            distance_from_hive_m: None,
+            binding_hive: None,
+            hive_ok: false,
+        }
+    }
+
+    fn records() -> Vec<NodeTelemetry> {
+        let now = Utc::now();
+        vec![
+            NodeTelemetry::new(&node("CYB-AIR-APIARY-01", 0.123_456_789), 3, now),
+            NodeTelemetry::new(&node("CYB-AIR-APIARY-02", 1.0), 3, now),
+        ]
+    }
+
+    #[test]
+    fn ndjson_lines_parse_back_at_full_precision() {
+        let records = records();
+        let w = TelemetryWriter::new(OutputFormat::Ndjson, None, true);
+        let text = String::from_utf8(w.render(&records, true).unwrap()).unwrap();
+        let back: Vec<NodeTelemetry> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(back[0].machine_id, "CYB-AIR-APIARY-01");
+        assert_eq!(back[0].r#type, "ApiaryCanopy");
+        assert_eq!(back[0].duty_cycle, 0.123_456_789);
+        assert_eq!(back[0].emf_score, records[0].emf_score);
+        assert_eq!(back[1].step, 3);
+        assert_eq!(back[1].timestamp, records[1].timestamp);
+    }
+
+    #[test]
+    fn json_is_one_array_and_csv_keeps_legacy_columns() {
+        let records = records();
+        let json = TelemetryWriter::new(OutputFormat::Json, None, false)
+            .render(&records, true)
+            .unwrap();
+        let back: Vec<NodeTelemetry> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(back, records);
+
+        let csv = TelemetryWriter::new(OutputFormat::Csv, None, false)
+            .render(&records, true)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(NodeTelemetry::csv_header().as_str()));
+        assert!(lines.next().unwrap().ends_with(",0.123,0.267"));
+
+        let csv = TelemetryWriter::new(OutputFormat::Csv, None, true)
+            .render(&records, true)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("tick,machine_id,"));
+        assert!(lines.next().unwrap().starts_with("3,CYB-AIR-APIARY-01,"));
+    }
+
+    #[test]
+    fn file_output_is_replaced_atomically() {
+        let path =
+            std::env::temp_dir().join(format!("bee-guard-out-{}.ndjson", std::process::id()));
+        let mut w = TelemetryWriter::new(OutputFormat::Ndjson, Some(path.clone()), true);
+        w.emit(&records()).unwrap();
+        w.emit(&records()[..1]).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        assert!(!path
+            .with_file_name(format!(
+                "{}.tmp",
+                path.file_name().unwrap().to_string_lossy()
+            ))
+            .exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
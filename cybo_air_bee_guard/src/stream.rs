@@ -0,0 +1,469 @@
+// Streaming mode: a long-running corridor loop that keeps per-node state
+// across ticks, picks up rows appended to the shard (or piped on stdin),
+// and saves its state on SIGINT so the next start resumes.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Utc;
+use cyboair_bee_guard_core::{NodeState, Row};
+use cyboair_corridor_safety::loader::LoadError;
+use cyboair_corridor_safety::units::UnitError;
+
+use crate::output::{NodeTelemetry, TelemetryWriter};
+use crate::{read_rows, BeeParams};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(PathBuf),
+    // Header line first, then one row per line
+    Stdin,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    pub source: Source,
+    pub tick: Duration,
+    pub state_path: PathBuf,
+}
+
+// Parse one data line against the source's header, reporting errors at the
+// line's position in the source rather than in the two-line snippet
+fn parse_line(header: &str, line: &str, line_no: u64) -> Result<Row, LoadError> {
+    let text = format!("{header}\n{line}\n");
+    match read_rows(text.as_bytes()) {
+        Ok(mut rows) if rows.len() == 1 => Ok(rows.remove(0)),
+        Ok(rows) => Err(LoadError::Row {
+            line: line_no,
+            message: format!("expected one row, found {}", rows.len()),
+        }),
+        Err(LoadError::Field {
+            column, message, ..
+        }) => Err(LoadError::Field {
+            line: line_no,
+            column,
+            message,
+        }),
+        Err(LoadError::Row { message, .. }) => Err(LoadError::Row {
+            line: line_no,
+            message,
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+// Bytes from the top of the shard kept to recognise it across polls
+const HEAD_LEN: usize = 4096;
+
+// Follows a shard file, returning the rows appended since the last poll.
+// A partial final line is left for the next poll. A file that was replaced
+// or rewritten in place (shrunk, new inode, touched without growing, or
+// different leading bytes) is read again from the top.
+pub struct FileTail {
+    path: PathBuf,
+    offset: u64,
+    header: Option<String>,
+    line_no: u64,
+    head: Vec<u8>,
+    inode: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl FileTail {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileTail {
+            path: path.into(),
+            offset: 0,
+            header: None,
+            line_no: 0,
+            head: Vec::new(),
+            inode: None,
+            modified: None,
+        }
+    }
+
+    fn rewritten(&self, file: &mut File, meta: &Metadata) -> io::Result<bool> {
+        if meta.len() < self.offset || inode(meta) != self.inode {
+            return Ok(true);
+        }
+        // Appends always grow the file, so a change at the same length is a
+        // rewrite
+        if meta.len() == self.offset && meta.modified().ok() != self.modified {
+            return Ok(true);
+        }
+        let mut head = vec![0; self.head.len()];
+        file.read_exact(&mut head)?;
+        Ok(head != self.head)
+    }
+
+    pub fn poll(&mut self) -> io::Result<Vec<Result<Row, LoadError>>> {
+        let mut file = File::open(&self.path)?;
+        let meta = file.metadata()?;
+        if self.offset > 0 && self.rewritten(&mut file, &meta)? {
+            self.offset = 0;
+            self.header = None;
+            self.line_no = 0;
+            self.head.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        // Taken after the read, so an append racing it shows up as growth
+        let meta = file.metadata()?;
+        self.inode = inode(&meta);
+        self.modified = meta.modified().ok();
+
+        let complete = match text.rfind('\n') {
+            Some(i) => &text[..=i],
+            None => return Ok(Vec::new()),
+        };
+        self.offset += complete.len() as u64;
+        let room = HEAD_LEN - self.head.len();
+        self.head
+            .extend_from_slice(&complete.as_bytes()[..room.min(complete.len())]);
+
+        let mut rows = Vec::new();
+        for line in complete.lines() {
+            self.line_no += 1;
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                continue;
+            }
+            match &self.header {
+                None => self.header = Some(line.to_string()),
+                Some(header) => rows.push(parse_line(header, line, self.line_no)),
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(unix)]
+fn inode(meta: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+// Reads stdin on a helper thread so ticks are not blocked waiting for input
+struct StdinLines {
+    lines: Receiver<io::Result<String>>,
+    header: Option<String>,
+    line_no: u64,
+    closed: bool,
+}
+
+impl StdinLines {
+    fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        StdinLines {
+            lines: rx,
+            header: None,
+            line_no: 0,
+            closed: false,
+        }
+    }
+
+    fn poll(&mut self) -> io::Result<Vec<Result<Row, LoadError>>> {
+        let mut rows = Vec::new();
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            };
+            self.line_no += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match &self.header {
+                None => self.header = Some(line),
+                Some(header) => rows.push(parse_line(header, &line, self.line_no)),
+            }
+        }
+        Ok(rows)
+    }
+}
+
+// Nodes keyed by machine_id. A row for a known node replaces its inputs but
+// keeps the duty cycle, so control evolves across ticks.
+#[derive(Debug, Default)]
+pub struct Corridor {
+    pub nodes: BTreeMap<String, NodeState>,
+}
+
+impl Corridor {
+    // Missing state file means a fresh start
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Corridor {
+                nodes: serde_json::from_str(&text)?,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Corridor::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Written to a sibling temp file and renamed, so a crash mid-save never
+    // leaves a truncated state behind
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.nodes)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn ingest(&mut self, row: Row) {
+        match self.nodes.get_mut(&row.machine_id) {
+            Some(node) => node.row = row,
+            None => {
+                self.nodes
+                    .insert(row.machine_id.clone(), NodeState::new(row));
+            }
+        }
+    }
+
+    pub fn tick(&mut self, params: &BeeParams) -> Result<(), UnitError> {
+        for node in self.nodes.values_mut() {
+            params.update(node)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(
+    config: &StreamConfig,
+    params: &BeeParams,
+    writer: &mut TelemetryWriter,
+) -> Result<(), Box<dyn Error>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = Arc::clone(&stop);
+        ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst))?;
+    }
+
+    let mut corridor = Corridor::load(&config.state_path)?;
+    let mut file = match &config.source {
+        Source::File(path) => Some(FileTail::new(path)),
+        Source::Stdin => None,
+    };
+    let mut stdin = match &config.source {
+        Source::Stdin => Some(StdinLines::spawn()),
+        Source::File(_) => None,
+    };
+
+    let mut tick: u64 = 0;
+    let result = loop {
+        let rows = match (&mut file, &mut stdin) {
+            (Some(f), _) => f.poll(),
+            (_, Some(s)) => s.poll(),
+            _ => unreachable!("one source is always set"),
+        };
+        match rows {
+            Ok(rows) => {
+                for row in rows {
+                    match row {
+                        Ok(row) => corridor.ingest(row),
+                        Err(e) => eprintln!("skipping row: {e}"),
+                    }
+                }
+            }
+            // The shard may be mid-rotation; retry next tick
+            Err(e) => eprintln!("input unavailable: {e}"),
+        }
+
+        if let Err(e) = corridor.tick(params) {
+            break Err(e.into());
+        }
+        let now = Utc::now();
+        let records: Vec<NodeTelemetry> = corridor
+            .nodes
+            .values()
+            .map(|node| NodeTelemetry::new(node, tick, now))
+            .collect();
+        if let Err(e) = writer.emit(&records) {
+            break Err(e.into());
+        }
+        tick += 1;
+
+        if stdin.as_ref().is_some_and(|s| s.closed) {
+            break Ok(());
+        }
+        let deadline = Instant::now() + config.tick;
+        while !stop.load(Ordering::SeqCst) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100).min(config.tick));
+        }
+        if stop.load(Ordering::SeqCst) {
+            break Ok(());
+        }
+    };
+
+    corridor.save(&config.state_path)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use super::*;
+
+    const HEADER: &str = "machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,\
+                          period_s,lambda_hazard,beta_nb_per_kg,ecoimpactscore,bee_flag,\
+                          bee_weight,notes";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bee-guard-{}-{name}", std::process::id()))
+    }
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    fn row(id: &str, cin: f64) -> String {
+        format!(
+            "{id},ApiaryCanopy,Phoenix-Garden-North,PM2.5,{cin},20,ug/m3,0.8,3600,3.5,5.0e8,\
+             0.93,1,1.5,\"near hives, upwind\"\n"
+        )
+    }
+
+    #[test]
+    fn appended_rows_update_persistent_nodes() {
+        let path = temp_path("shard.csv");
+        let _ = fs::remove_file(&path);
+        append(&path, &format!("{HEADER}\n"));
+        append(&path, &row("CYB-AIR-APIARY-01", 20.0));
+
+        let params = BeeParams::default();
+        let mut tail = FileTail::new(&path);
+        let mut corridor = Corridor::default();
+        for r in tail.poll().unwrap() {
+            corridor.ingest(r.unwrap());
+        }
+        corridor.tick(&params).unwrap();
+        let first = corridor.nodes["CYB-AIR-APIARY-01"].duty_cycle;
+        assert!(first > 0.0 && first < 1.0, "{first}");
+
+        // Nothing new: the node keeps evolving from its previous duty.
+        assert!(tail.poll().unwrap().is_empty());
+        corridor.tick(&params).unwrap();
+        let second = corridor.nodes["CYB-AIR-APIARY-01"].duty_cycle;
+        assert!(second > first);
+
+        // A second node, a bad row, an update to the first and half a line.
+        append(&path, &row("CYB-AIR-APIARY-02", 20.0));
+        append(
+            &path,
+            &row("CYB-AIR-APIARY-03", 20.0).replace("5.0e8", "5.0e8x"),
+        );
+        append(&path, &row("CYB-AIR-APIARY-01", 30.0));
+        append(&path, "CYB-AIR-APIARY-04,Apiary");
+        let rows = tail.poll().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1].as_ref().unwrap_err().to_string(),
+            "line 4, column beta_nb_per_kg: invalid float '5.0e8x'"
+        );
+        for r in rows.into_iter().flatten() {
+            corridor.ingest(r);
+        }
+        assert_eq!(corridor.nodes.len(), 2);
+        let node = &corridor.nodes["CYB-AIR-APIARY-01"];
+        assert_eq!(node.row.cin, 30.0);
+        assert_eq!(node.duty_cycle, second);
+
+        // The partial line completes on the next poll.
+        let full = row("CYB-AIR-APIARY-04", 20.0);
+        append(
+            &path,
+            full.strip_prefix("CYB-AIR-APIARY-04,Apiary").unwrap(),
+        );
+        let rows = tail.poll().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].as_ref().unwrap().machine_id, "CYB-AIR-APIARY-04");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rewrite_at_equal_length_is_read_from_the_top() {
+        let path = temp_path("rewrite.csv");
+        fs::write(
+            &path,
+            format!("{HEADER}\n{}", row("CYB-AIR-APIARY-01", 20.0)),
+        )
+        .unwrap();
+        let mut tail = FileTail::new(&path);
+        assert_eq!(tail.poll().unwrap()[0].as_ref().unwrap().cin, 20.0);
+        assert!(tail.poll().unwrap().is_empty());
+
+        // Same header, same length, new reading: not an append.
+        fs::write(
+            &path,
+            format!("{HEADER}\n{}", row("CYB-AIR-APIARY-01", 30.0)),
+        )
+        .unwrap();
+        let rows = tail.poll().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].as_ref().unwrap().cin, 30.0);
+        assert!(tail.poll().unwrap().is_empty());
+
+        // Rows appended after the rewrite follow on as usual.
+        append(&path, &row("CYB-AIR-APIARY-02", 20.0));
+        let rows = tail.poll().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].as_ref().unwrap().machine_id, "CYB-AIR-APIARY-02");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn state_round_trips_through_json() {
+        let path = temp_path("state.json");
+        let _ = fs::remove_file(&path);
+        assert!(Corridor::load(&path).unwrap().nodes.is_empty());
+
+        let text = format!("{HEADER}\n{}", row("CYB-AIR-APIARY-01", 20.0));
+        let mut corridor = Corridor::default();
+        for r in read_rows(text.as_bytes()).unwrap() {
+            corridor.ingest(r);
+        }
+        corridor.tick(&BeeParams::default()).unwrap();
+        corridor.save(&path).unwrap();
+
+        let resumed = Corridor::load(&path).unwrap();
+        let a = &corridor.nodes["CYB-AIR-APIARY-01"];
+        let b = &resumed.nodes["CYB-AIR-APIARY-01"];
+        assert!((a.duty_cycle - b.duty_cycle).abs() < 1e-12);
+        assert_eq!(b.row.notes, "near hives, upwind");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,358 @@
+// Shard acceptance checks for --validate: governance and physics checks
+// on every row, reported by line, without updating any node.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use cyboair_bee_guard_core::{Conditions, MICROSPACE_COLUMNS};
+use cyboair_corridor_safety::loader::{read_numbered_records, LoadError};
+use eibon_core::{compute_karma_bytes, compute_mass_kg_at_pressure, row_violations, GovernanceRow};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!(
+                "unknown report format '{other}' (expected text or json)"
+            )),
+        }
+    }
+}
+
+// Budgets are optional; without one that check is skipped
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub conditions: Conditions,
+    pub max_mass_kg: Option<f64>,
+    pub max_karma_bytes: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    // 1-based line in the shard; 1 is the header
+    pub line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    pub severity: Severity,
+    pub check: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShardReport {
+    pub rows: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    // Errors first, then by line
+    pub findings: Vec<Finding>,
+}
+
+impl ShardReport {
+    fn new(rows: usize, mut findings: Vec<Finding>) -> Self {
+        findings.sort_by_key(|f| (f.severity, f.line));
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        ShardReport {
+            rows,
+            errors,
+            warnings: findings.len() - errors,
+            findings,
+        }
+    }
+
+    // Non-zero if the shard must not be deployed
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self.errors > 0)
+    }
+
+    pub fn write<W: Write>(&self, format: ReportFormat, mut out: W) -> io::Result<()> {
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut out, self)?;
+                writeln!(out)
+            }
+            ReportFormat::Text => write!(out, "{self}"),
+        }
+    }
+}
+
+impl fmt::Display for ShardReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (severity, title, count) in [
+            (Severity::Error, "errors", self.errors),
+            (Severity::Warning, "warnings", self.warnings),
+        ] {
+            if count == 0 {
+                continue;
+            }
+            writeln!(f, "{title} ({count}):")?;
+            for finding in self.findings.iter().filter(|x| x.severity == severity) {
+                write!(f, "  line {}", finding.line)?;
+                if let Some(id) = &finding.machine_id {
+                    write!(f, " {id}")?;
+                }
+                writeln!(f, ": {}: {}", finding.check, finding.message)?;
+            }
+        }
+        writeln!(
+            f,
+            "checked {} rows: {} errors, {} warnings",
+            self.rows, self.errors, self.warnings
+        )
+    }
+}
+
+// The microspace columns, with everything the checks look at optional
+#[derive(Debug, Deserialize)]
+struct ShardLine {
+    machine_id: String,
+    #[serde(rename = "type")]
+    r#type: String,
+    location: String,
+    pollutant: String,
+    cin: f64,
+    cout: f64,
+    unit: String,
+    airflow_m3_per_s: f64,
+    period_s: f64,
+    lambda_hazard: f64,
+    beta_nb_per_kg: f64,
+    #[serde(default, alias = "ecoimpactscore")]
+    ecoimpact_score: Option<f64>,
+    #[serde(default)]
+    bee_flag: Option<u8>,
+    #[serde(default)]
+    bee_weight: Option<f64>,
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+}
+
+// A shard that cannot be read at all is reported as one error finding
+pub fn validate_shard<R: Read>(reader: R, options: &Options) -> ShardReport {
+    let lines: Vec<(u64, ShardLine)> = match read_numbered_records(reader, MICROSPACE_COLUMNS) {
+        Ok(lines) => lines,
+        Err(e) => {
+            let line = match &e {
+                LoadError::Row { line, .. } | LoadError::Field { line, .. } => *line,
+                _ => 1,
+            };
+            return ShardReport::new(
+                0,
+                vec![Finding {
+                    line,
+                    machine_id: None,
+                    severity: Severity::Error,
+                    check: "parse".into(),
+                    message: e.to_string(),
+                }],
+            );
+        }
+    };
+
+    let mut findings = Vec::new();
+    for (line, row) in &lines {
+        let mut report = |severity, check: &str, message: String| {
+            findings.push(Finding {
+                line: *line,
+                machine_id: Some(row.machine_id.clone()),
+                severity,
+                check: check.into(),
+                message,
+            })
+        };
+        check_row(row, options, &mut report);
+    }
+    ShardReport::new(lines.len(), findings)
+}
+
+fn check_row(row: &ShardLine, options: &Options, report: &mut impl FnMut(Severity, &str, String)) {
+    let governance = GovernanceRow {
+        machine_id: row.machine_id.clone(),
+        r#type: row.r#type.clone(),
+        location: row.location.clone(),
+        pollutant: row.pollutant.clone(),
+        cin: row.cin,
+        cout: row.cout,
+        unit: row.unit.clone(),
+        airflow_m3_per_s: row.airflow_m3_per_s,
+        period_s: row.period_s,
+        lambda_hazard: row.lambda_hazard,
+        beta_nb_per_kg: row.beta_nb_per_kg,
+        // A missing score is reported on its own below
+        ecoimpact_score: row.ecoimpact_score.unwrap_or(0.0),
+    };
+    for violation in row_violations(&governance, options.conditions.temperature_k) {
+        report(Severity::Error, "governance", violation.to_string());
+    }
+    if row.ecoimpact_score.is_none() {
+        report(
+            Severity::Warning,
+            "ecoimpact",
+            "no ecoimpact_score; the row is not scored".into(),
+        );
+    }
+
+    match (row.bee_flag, row.bee_weight) {
+        (Some(1), None) => report(
+            Severity::Error,
+            "bee_weight",
+            "bee-flagged row has no bee_weight".into(),
+        ),
+        (Some(0 | 1) | None, _) => {}
+        (Some(flag), _) => report(
+            Severity::Error,
+            "bee_flag",
+            format!("bee_flag must be 0 or 1, got {flag}"),
+        ),
+    }
+    match row.bee_weight {
+        Some(w) if !(w.is_finite() && w > 0.0) => report(
+            Severity::Error,
+            "bee_weight",
+            format!("bee_weight must be finite and > 0, got {w}"),
+        ),
+        Some(w) if w < 1.0 && row.bee_flag == Some(1) => report(
+            Severity::Warning,
+            "bee_weight",
+            format!("bee_weight {w} is below 1 and lowers the bee hazard"),
+        ),
+        _ => {}
+    }
+    if row.lat.is_some() != row.lon.is_some() {
+        report(
+            Severity::Warning,
+            "position",
+            "only one of lat and lon is given; hive distance is not used".into(),
+        );
+    }
+
+    // Budgets need a mass; unit and pollutant problems were reported above
+    let Ok(pollutant) = governance.pollutant_kind() else {
+        return;
+    };
+    let Ok(mass_kg) = compute_mass_kg_at_pressure(
+        &governance,
+        pollutant,
+        options.conditions.temperature_k,
+        options.conditions.pressure_pa,
+    ) else {
+        return;
+    };
+    let karma = compute_karma_bytes(&governance, mass_kg);
+    if let Some(max) = options.max_mass_kg.filter(|&max| mass_kg > max) {
+        report(
+            Severity::Error,
+            "mass_budget",
+            format!("implied mass {mass_kg:.3e} kg exceeds budget {max:.3e} kg"),
+        );
+    }
+    if let Some(max) = options.max_karma_bytes.filter(|&max| karma > max) {
+        report(
+            Severity::Error,
+            "karma_budget",
+            format!("implied karma {karma:.3e} exceeds budget {max:.3e}"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVALID: &[u8] = include_bytes!("../fixtures/ten_machines_bee_invalid.csv");
+
+    #[test]
+    fn seeded_violations_fail_the_shard() {
+        let report = validate_shard(INVALID, &Options::default());
+        assert_eq!(report.rows, 4);
+        assert_eq!((report.errors, report.warnings), (2, 1));
+        assert_eq!(report.exit_code(), 1);
+
+        let found: Vec<_> = report
+            .findings
+            .iter()
+            .map(|f| (f.severity, f.line, f.check.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Severity::Error, 3, "governance"),
+                (Severity::Error, 4, "bee_weight"),
+                (Severity::Warning, 5, "bee_weight"),
+            ]
+        );
+        assert_eq!(
+            report.findings[0].machine_id.as_deref(),
+            Some("CYB-AIR-SCHOOL-02")
+        );
+
+        let mut text = Vec::new();
+        report.write(ReportFormat::Text, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("errors (2):\n  line 3 CYB-AIR-SCHOOL-02: governance: "));
+        assert!(text.contains(
+            "  line 4 CYB-AIR-GARDEN-03: bee_weight: bee-flagged row has no bee_weight\n"
+        ));
+        assert!(text.ends_with("checked 4 rows: 2 errors, 1 warnings\n"));
+
+        let mut json = Vec::new();
+        report.write(ReportFormat::Json, &mut json).unwrap();
+        let parsed: ShardReport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn budgets_and_unreadable_shards() {
+        // The fixture's first row alone is clean.
+        let clean: Vec<&str> = std::str::from_utf8(INVALID)
+            .unwrap()
+            .lines()
+            .take(2)
+            .collect();
+        let clean = clean.join("\n");
+        let report = validate_shard(clean.as_bytes(), &Options::default());
+        assert_eq!(report.exit_code(), 0);
+        assert!(report.findings.is_empty());
+
+        // It implies 2.88e-5 kg.
+        let options = Options {
+            max_mass_kg: Some(1e-5),
+            ..Options::default()
+        };
+        let report = validate_shard(clean.as_bytes(), &options);
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!(report.findings[0].check, "mass_budget");
+        assert_eq!(report.findings[0].line, 2);
+
+        let broken = include_bytes!("../fixtures/ten_machines_bee_broken.csv");
+        let report = validate_shard(&broken[..], &Options::default());
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!(report.findings[0].line, 3);
+        assert_eq!(
+            report.findings[0].message,
+            "line 3, column bee_flag: invalid integer 'yes'"
+        );
+    }
+}
@@ -1,3 +1,9 @@
+use chrono::NaiveDate;
+use cyboair_corridor_safety::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBandClassifier, EcoLoadBreakdown,
+    HostBudget, MetricsRecorder, NodeState, SafetyEnvelope, SafetyError,
+};
+
 #[derive(Debug, Clone)]
 pub struct MineralSheet {
     pub area_m2: f64,
@@ -9,6 +15,232 @@ pub struct MineralSheet {
 impl MineralSheet {
     /// Net embodied CO₂ per m² after full carbonation potential.
     pub fn net_co2_kg_per_m2(&self) -> f64 {
-        self.e_prod_kgco2_per_m2 - self.beta_carb_kgco2_per_kg * self.m_carb_kg_per_m2
+        self.e_prod_kgco2_per_m2 - self.uptake_kgco2_per_m2()
+    }
+
+    /// CO₂ bound per m² once fully carbonated.
+    pub fn uptake_kgco2_per_m2(&self) -> f64 {
+        self.beta_carb_kgco2_per_kg * self.m_carb_kg_per_m2
+    }
+}
+
+/// Identical sheets installed together.
+#[derive(Debug, Clone)]
+pub struct SheetBatch {
+    pub sheet: MineralSheet,
+    pub count: u32,
+    pub installed_on: NaiveDate,
+}
+
+impl SheetBatch {
+    pub fn area_m2(&self) -> f64 {
+        self.sheet.area_m2 * f64::from(self.count)
+    }
+}
+
+/// Mineral sheets deployed along a corridor.
+///
+/// Carbonation is diffusion-limited, so a batch reaches
+/// sqrt(days installed / `full_carbonation_days`) of its potential, capped
+/// at one. Batches count from their install date; before it they add
+/// neither embodied emissions nor uptake.
+#[derive(Debug, Clone)]
+pub struct SheetInventory {
+    pub batches: Vec<SheetBatch>,
+    pub full_carbonation_days: f64,
+}
+
+impl SheetInventory {
+    pub fn new(full_carbonation_days: f64) -> Self {
+        SheetInventory {
+            batches: Vec::new(),
+            full_carbonation_days,
+        }
+    }
+
+    pub fn with_batch(mut self, sheet: MineralSheet, count: u32, installed_on: NaiveDate) -> Self {
+        self.batches.push(SheetBatch {
+            sheet,
+            count,
+            installed_on,
+        });
+        self
+    }
+
+    /// Share of the carbonation potential reached by `date` for sheets
+    /// installed on `installed_on`.
+    pub fn carbonated_fraction(&self, installed_on: NaiveDate, date: NaiveDate) -> f64 {
+        let days = (date - installed_on).num_days();
+        if days < 0 {
+            return 0.0;
+        }
+        if self.full_carbonation_days <= 0.0 {
+            return 1.0;
+        }
+        (days as f64 / self.full_carbonation_days).sqrt().min(1.0)
+    }
+
+    /// Net CO₂ of the installed inventory at `date`, kg; negative once
+    /// uptake exceeds embodied production emissions.
+    pub fn total_net_co2_at(&self, date: NaiveDate) -> f64 {
+        self.batches
+            .iter()
+            .filter(|b| b.installed_on <= date)
+            .map(|b| {
+                let f = self.carbonated_fraction(b.installed_on, date);
+                b.area_m2() * (b.sheet.e_prod_kgco2_per_m2 - f * b.sheet.uptake_kgco2_per_m2())
+            })
+            .sum()
+    }
+
+    /// CO₂ that may be credited against the corridor at `date`, kg: uptake
+    /// beyond the inventory's own embodied emissions, or zero.
+    pub fn credited_uptake_at(&self, date: NaiveDate) -> f64 {
+        (-self.total_net_co2_at(date)).max(0.0)
+    }
+}
+
+/// Corridor eco-load at `date` with the inventory's credited uptake
+/// subtracted, converted at `ecoload_per_kg_co2`. The controller floors the
+/// result at zero and reports how much of the credit it applied; a negative
+/// or non-finite factor is rejected as an invalid offset.
+#[allow(clippy::too_many_arguments)]
+pub fn eco_load_with_sheets<E, H, B, D, G, R>(
+    controller: &CorridorController<E, H, B, D, G, R>,
+    nodes: &[NodeState],
+    alpha_m: f64,
+    alpha_k: f64,
+    inventory: &SheetInventory,
+    date: NaiveDate,
+    ecoload_per_kg_co2: f64,
+) -> Result<EcoLoadBreakdown, SafetyError>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    let offset = inventory.credited_uptake_at(date) * ecoload_per_kg_co2;
+    controller.eco_load_with_offset(nodes, alpha_m, alpha_k, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cyboair_corridor_safety::{ConstAltitude, ControllerConfig, CorridorRow, OperationalMode};
+
+    fn sheet() -> MineralSheet {
+        MineralSheet {
+            area_m2: 2.0,
+            e_prod_kgco2_per_m2: 4.0,
+            m_carb_kg_per_m2: 20.0,
+            beta_carb_kgco2_per_kg: 0.5,
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn node(mass_kg: f64, karma_bytes: f64) -> NodeState {
+        NodeState {
+            row: CorridorRow {
+                machine_id: "CYB-AIR-CANOPY-01".into(),
+                r#type: "UrbanNanoswarmCanopy".into(),
+                location: "Phoenix-Intersection-A".into(),
+                pollutant: "PM2.5".into(),
+                cin: 40.0,
+                cout: 28.0,
+                unit: "ug/m3".into(),
+                airflow_m3_per_s: 3.0,
+                period_s: 3600.0,
+                lambda_hazard: 3.0,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.92,
+                trace_id: None,
+                shard_version: None,
+            },
+            mass_kg,
+            karma_bytes,
+            duty_cycle: 0.5,
+            power_w: 50.0,
+            geo_weight: 0.8,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        }
+    }
+
+    #[test]
+    fn uptake_grows_with_sqrt_time_and_saturates() {
+        let installed = date(2026, 1, 1);
+        let inv = SheetInventory::new(400.0).with_batch(sheet(), 10, installed);
+
+        // Not yet installed: nothing counts.
+        assert_eq!(inv.total_net_co2_at(date(2025, 12, 31)), 0.0);
+        // Day zero: embodied emissions only, 20 m2 * 4 kg.
+        assert_eq!(inv.total_net_co2_at(installed), 80.0);
+        assert_eq!(inv.credited_uptake_at(installed), 0.0);
+
+        // 100 of 400 days: half the 10 kg/m2 potential.
+        let d100 = date(2026, 4, 11);
+        assert!((inv.total_net_co2_at(d100) - 20.0 * (4.0 - 5.0)).abs() < 1e-9);
+        assert!((inv.credited_uptake_at(d100) - 20.0).abs() < 1e-9);
+
+        // Past full carbonation the net matches the sheet's own figure.
+        let late = date(2030, 1, 1);
+        assert!((inv.total_net_co2_at(late) - 20.0 * sheet().net_co2_kg_per_m2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn credit_partially_offsets_corridor_load() {
+        let controller = ControllerConfig::from_toml(include_str!(
+            "../../cyboair_corridor_safety/config/phoenix.toml"
+        ))
+        .unwrap()
+        .build(ConstAltitude(331.0))
+        .unwrap();
+        let nodes = [node(1.0e-6, 1.0e10), node(1.0e-6, 1.0e10)];
+        let gross = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        assert!((gross - 2.0).abs() < 1e-12);
+
+        // 120 kg credited after full carbonation (200 kg uptake - 80 kg).
+        let inv = SheetInventory::new(365.0).with_batch(sheet(), 10, date(2024, 1, 1));
+        let today = date(2026, 6, 1);
+        assert!((inv.credited_uptake_at(today) - 120.0).abs() < 1e-9);
+
+        let partial =
+            eco_load_with_sheets(&controller, &nodes, 0.5, 0.5, &inv, today, 0.005).unwrap();
+        assert!((partial.offset_applied - 0.6).abs() < 1e-9);
+        assert!((partial.net - 1.4).abs() < 1e-9);
+        assert_eq!(partial.gross, gross);
+    }
+
+    #[test]
+    fn large_inventory_floors_load_at_zero() {
+        let controller = ControllerConfig::from_toml(include_str!(
+            "../../cyboair_corridor_safety/config/phoenix.toml"
+        ))
+        .unwrap()
+        .build(ConstAltitude(331.0))
+        .unwrap();
+        let nodes = [node(1.0e-6, 1.0e10)];
+        let inv = SheetInventory::new(365.0)
+            .with_batch(sheet(), 500, date(2023, 3, 1))
+            .with_batch(sheet(), 500, date(2024, 3, 1));
+        let today = date(2026, 6, 1);
+
+        let load = eco_load_with_sheets(&controller, &nodes, 0.5, 0.5, &inv, today, 0.005).unwrap();
+        assert!(load.offset_requested > load.gross);
+        assert_eq!(load.offset_applied, load.gross);
+        assert_eq!(load.net, 0.0);
+
+        assert!(matches!(
+            eco_load_with_sheets(&controller, &nodes, 0.5, 0.5, &inv, today, -1.0),
+            Err(SafetyError::InvalidEcoOffset { .. })
+        ));
     }
 }
@@ -0,0 +1,198 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{enforce_bee_rights, BeeEnvSample, BeeRightsOutcome, BeerightsPolytope};
+
+/// One transmitter near a node: its own radio, a 5G small cell, a power
+/// line. Field strength falls off as 1/d in the far field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmfSource {
+    /// RMS field strength 1 m from the source, V/m.
+    pub field_at_1m_vpm: f64,
+    pub distance_m: f64,
+}
+
+impl EmfSource {
+    /// Field this source contributes at `distance_m`.
+    pub fn field_vpm(&self) -> f64 {
+        self.field_at_1m_vpm / self.distance_m
+    }
+}
+
+/// Why a set of EMF sources could not be combined.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmfError {
+    /// Source `index` is at zero, negative or non-finite distance, where
+    /// 1/d falloff has no meaning.
+    NearField { index: usize, distance_m: f64 },
+    /// Source `index` has a negative or non-finite field strength.
+    InvalidField { index: usize, field_at_1m_vpm: f64 },
+}
+
+impl fmt::Display for EmfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmfError::NearField { index, distance_m } => {
+                write!(
+                    f,
+                    "emf source {index}: distance {distance_m} m is not positive"
+                )
+            }
+            EmfError::InvalidField {
+                index,
+                field_at_1m_vpm,
+            } => write!(
+                f,
+                "emf source {index}: field {field_at_1m_vpm} V/m at 1 m is invalid"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EmfError {}
+
+/// Combined field at the node: each source falls off as 1/d and the
+/// uncorrelated sources add in RMS, sqrt(sum (E_i / d_i)^2). No sources
+/// means no field.
+pub fn compute_emf_vpm(sources: &[EmfSource]) -> Result<f64, EmfError> {
+    let mut sum_sq = 0.0;
+    for (index, s) in sources.iter().enumerate() {
+        if !(s.distance_m.is_finite() && s.distance_m > 0.0) {
+            return Err(EmfError::NearField {
+                index,
+                distance_m: s.distance_m,
+            });
+        }
+        if !(s.field_at_1m_vpm.is_finite() && s.field_at_1m_vpm >= 0.0) {
+            return Err(EmfError::InvalidField {
+                index,
+                field_at_1m_vpm: s.field_at_1m_vpm,
+            });
+        }
+        sum_sq += s.field_vpm().powi(2);
+    }
+    Ok(sum_sq.sqrt())
+}
+
+/// EMF at a node, either measured as one field or described by its sources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmfExposure {
+    FieldVpm(f64),
+    Sources(Vec<EmfSource>),
+}
+
+impl EmfExposure {
+    pub fn field_vpm(&self) -> Result<f64, EmfError> {
+        match self {
+            EmfExposure::FieldVpm(vpm) => Ok(*vpm),
+            EmfExposure::Sources(sources) => compute_emf_vpm(sources),
+        }
+    }
+}
+
+impl BeeEnvSample {
+    /// Same sample with `emf_vpm` taken from `emf`, so `compute_h_rf` and
+    /// the polytope checks see the combined field.
+    pub fn with_emf(mut self, emf: &EmfExposure) -> Result<Self, EmfError> {
+        self.emf_vpm = emf.field_vpm()?;
+        Ok(self)
+    }
+}
+
+/// `enforce_bee_rights` with the EMF coordinate given by `emf` instead of
+/// `env.emf_vpm`.
+pub fn enforce_bee_rights_with_emf(
+    env: &BeeEnvSample,
+    emf: &EmfExposure,
+    proposed_duty_cycle: f64,
+    polytope: &BeerightsPolytope,
+) -> Result<BeeRightsOutcome, EmfError> {
+    let env = env.clone().with_emf(emf)?;
+    Ok(enforce_bee_rights(&env, proposed_duty_cycle, polytope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_h_rf, HazardWeights};
+
+    fn source(field_at_1m_vpm: f64, distance_m: f64) -> EmfSource {
+        EmfSource {
+            field_at_1m_vpm,
+            distance_m,
+        }
+    }
+
+    fn env() -> BeeEnvSample {
+        BeeEnvSample {
+            distance_from_hive_m: 120.0,
+            o3_ugm3: 40.0,
+            aqhi: 3.0,
+            pm25_ugm3: 10.0,
+            emf_vpm: 0.0,
+            pesticide_index: 0.0,
+            ambient_temp_c: 30.0,
+        }
+    }
+
+    #[test]
+    fn sources_combine_in_rms_with_inverse_distance() {
+        // Own radio 0.6 V/m at 1 m, 2 m away: 0.3 V/m. Small cell 8 V/m at
+        // 1 m, 20 m away: 0.4 V/m. sqrt(0.09 + 0.16) = 0.5 V/m.
+        let sources = [source(0.6, 2.0), source(8.0, 20.0)];
+        assert!((compute_emf_vpm(&sources).unwrap() - 0.5).abs() < 1e-12);
+        assert_eq!(compute_emf_vpm(&[]).unwrap(), 0.0);
+
+        let cfg = HazardWeights::default();
+        let scalar = env().with_emf(&EmfExposure::FieldVpm(0.5)).unwrap();
+        let combined = env()
+            .with_emf(&EmfExposure::Sources(sources.to_vec()))
+            .unwrap();
+        assert!((compute_h_rf(&combined, &cfg) - compute_h_rf(&scalar, &cfg)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn near_field_sources_are_rejected() {
+        let err = compute_emf_vpm(&[source(0.6, 2.0), source(8.0, 0.0)]).unwrap_err();
+        assert_eq!(
+            err,
+            EmfError::NearField {
+                index: 1,
+                distance_m: 0.0
+            }
+        );
+        assert!(matches!(
+            compute_emf_vpm(&[source(0.6, -1.0)]),
+            Err(EmfError::NearField { index: 0, .. })
+        ));
+        assert!(matches!(
+            compute_emf_vpm(&[source(f64::INFINITY, 1.0)]),
+            Err(EmfError::InvalidField { index: 0, .. })
+        ));
+        let polytope = BeerightsPolytope::default_conservative();
+        let exposure = EmfExposure::Sources(vec![source(8.0, 0.0)]);
+        assert!(enforce_bee_rights_with_emf(&env(), &exposure, 0.2, &polytope).is_err());
+    }
+
+    #[test]
+    fn enforcement_accepts_either_form() {
+        let polytope = BeerightsPolytope::default_conservative();
+        // 1.5 V/m combined breaks the 1 V/m EMF face at any duty.
+        let strong = EmfExposure::Sources(vec![source(3.0, 2.0)]);
+        assert_eq!(
+            enforce_bee_rights_with_emf(&env(), &strong, 0.2, &polytope).unwrap(),
+            BeeRightsOutcome::Infeasible
+        );
+        let weak = EmfExposure::FieldVpm(0.5);
+        assert_eq!(
+            enforce_bee_rights_with_emf(&env(), &weak, 0.2, &polytope).unwrap(),
+            enforce_bee_rights(&env().with_emf(&weak).unwrap(), 0.2, &polytope)
+        );
+
+        let json = r#"{"sources": [{"field_at_1m_vpm": 0.6, "distance_m": 2.0}]}"#;
+        let parsed: EmfExposure = serde_json::from_str(json).unwrap();
+        assert!((parsed.field_vpm().unwrap() - 0.3).abs() < 1e-12);
+    }
+}
@@ -1,28 +1,62 @@
 #![forbid(unsafe_code)]
 
+use cyboair_corridor_safety::{BeeGuard, NodeState};
 use serde::{Deserialize, Serialize};
 
+mod emf;
+mod loader;
+mod pesticide;
+mod profiles;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use emf::{compute_emf_vpm, enforce_bee_rights_with_emf, EmfError, EmfExposure, EmfSource};
+pub use loader::{default_probe_points, PolytopeError, ProbePoint, MAX_CONSTRAINTS};
+pub use pesticide::{CompoundToxicity, PesticideApplication, PesticideModel};
+pub use profiles::{HazardProfile, HazardProfileSet, MonthDay, ProfileError, SeasonWindow};
+
 /// Parameter vector x = [distance_from_hive_m,
 ///                       o3_concentration_ugm3,
 ///                       emf_intensity_vpm,
 ///                       duty_cycle]
 pub type ParameterVector = [f64; 4];
 
+/// Unit of each parameter axis for [`BeerightsPolytope::scaled_margin`]:
+/// the default polytope's hive distance, O3 limit and EMF limit, and full
+/// duty.
+pub const AXIS_SCALE: ParameterVector = [50.0, 80.0, 1.0, 1.0];
+
 /// A single half-space constraint a·x + b <= 0.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LinearConstraint {
     pub a: ParameterVector,
     pub b: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BeerightsPolytope {
     pub constraints: Vec<LinearConstraint>,
 }
 
+/// Where a parameter vector sits relative to the polytope boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolytopeZone {
+    Inside,
+    /// Inside, but within the warning margin of some face.
+    Warning,
+    Outside,
+}
+
 impl BeerightsPolytope {
+    /// Build a polytope, rejecting non-finite or all-zero constraint rows.
+    /// Unlike `from_json_reader`, this does not check feasibility.
+    pub fn new(constraints: Vec<LinearConstraint>) -> Result<Self, PolytopeError> {
+        loader::check_rows(&constraints)?;
+        Ok(BeerightsPolytope { constraints })
+    }
+
     /// A very conservative default box; real deployments should load
-    /// site-specific constraints from a shard or config.
+    /// site-specific constraints with `from_path` or `from_json_reader`.
     pub fn default_conservative() -> Self {
         // Example constraints (a·x + b <= 0):
         // 1) distance_from_hive_m >= 50  ->  -x0 + 50 <= 0
@@ -54,11 +88,68 @@ impl BeerightsPolytope {
     /// Returns true if all a·x + b <= 0 are satisfied (within tolerance).
     pub fn is_inside(&self, x: &ParameterVector, tol: f64) -> bool {
         self.constraints.iter().all(|c| {
-            let dot =
-                c.a[0] * x[0] + c.a[1] * x[1] + c.a[2] * x[2] + c.a[3] * x[3] + c.b;
+            let dot = c.a[0] * x[0] + c.a[1] * x[1] + c.a[2] * x[2] + c.a[3] * x[3] + c.b;
             dot <= tol
         })
     }
+
+    /// Constraints of both polytopes, identical rows kept once. The result
+    /// is never less protective than either input.
+    pub fn intersect(&self, other: &Self) -> Self {
+        let mut constraints: Vec<LinearConstraint> = Vec::new();
+        for c in self.constraints.iter().chain(&other.constraints) {
+            if !constraints.contains(c) {
+                constraints.push(c.clone());
+            }
+        }
+        BeerightsPolytope { constraints }
+    }
+
+    /// Smallest Euclidean distance from `x` to any constraint face,
+    /// -(a·x + b) / |a|; negative when `x` violates that constraint.
+    /// Zero rows (only reachable by building the struct directly) are
+    /// skipped, and a polytope without usable rows has infinite margin.
+    pub fn margin(&self, x: &ParameterVector) -> f64 {
+        self.constraints
+            .iter()
+            .filter_map(|c| {
+                let norm = c.a.iter().map(|v| v * v).sum::<f64>().sqrt();
+                if norm == 0.0 {
+                    return None;
+                }
+                let dot = c.a[0] * x[0] + c.a[1] * x[1] + c.a[2] * x[2] + c.a[3] * x[3] + c.b;
+                Some(-dot / norm)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// `margin` with each axis measured in units of `scale`, so faces on
+    /// axes with different units compare fairly.
+    pub fn scaled_margin(&self, x: &ParameterVector, scale: &ParameterVector) -> f64 {
+        let scaled = BeerightsPolytope {
+            constraints: self
+                .constraints
+                .iter()
+                .map(|c| LinearConstraint {
+                    a: std::array::from_fn(|i| c.a[i] * scale[i]),
+                    b: c.b,
+                })
+                .collect(),
+        };
+        scaled.margin(&std::array::from_fn(|i| x[i] / scale[i]))
+    }
+
+    /// Outside below zero margin, Warning below `warn_margin`, else Inside.
+    pub fn classify(&self, x: &ParameterVector, warn_margin: f64) -> PolytopeZone {
+        let m = self.margin(x);
+        if m < 0.0 {
+            PolytopeZone::Outside
+        } else if m < warn_margin {
+            PolytopeZone::Warning
+        } else {
+            PolytopeZone::Inside
+        }
+    }
 }
 
 /// Raw environmental inputs to Beekarma.
@@ -68,12 +159,16 @@ pub struct BeeEnvSample {
     pub o3_ugm3: f64,
     pub aqhi: f64,
     pub pm25_ugm3: f64,
+    /// Combined field at the node; see `BeeEnvSample::with_emf` to derive
+    /// it from individual sources.
     pub emf_vpm: f64,
-    pub pesticide_index: f64, // normalized 0–1 (from shard or API)
+    pub pesticide_index: f64, // normalized 0–1, see `PesticideModel::pesticide_index`
+    pub ambient_temp_c: f64,
 }
 
-/// Hazard index configuration.
-#[derive(Debug, Clone)]
+/// Hazard index configuration. Weights that vary by region or season can
+/// be kept in a `HazardProfileSet`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HazardWeights {
     pub w_poll: f64,
     pub w_bio: f64,
@@ -82,6 +177,15 @@ pub struct HazardWeights {
     pub aqhi_ref: f64,
     pub pm25_ref_ugm3: f64,
     pub emf_ref_vpm: f64,
+    /// Weight of heat stress; 0 reproduces the pre-thermal index.
+    pub w_thermal: f64,
+    /// Thermal stress is 0 at or below this temperature.
+    pub temp_comfort_c: f64,
+    /// Thermal stress saturates at 1 from this temperature.
+    pub temp_ref_c: f64,
+    /// Foraging activity by hour for `compute_h_bee_at`; `None` treats
+    /// every hour as peak foraging.
+    pub foraging: Option<ForagingProfile>,
 }
 
 impl HazardWeights {
@@ -94,10 +198,42 @@ impl HazardWeights {
             aqhi_ref: 7.0,
             pm25_ref_ugm3: 25.0,
             emf_ref_vpm: 1.0,
+            w_thermal: 0.0,
+            temp_comfort_c: 35.0,
+            temp_ref_c: 45.0,
+            foraging: Some(ForagingProfile::default()),
+        }
+    }
+}
+
+/// Relative forager activity for each local hour, 0 (hive closed) to 1 (peak).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForagingProfile {
+    pub weights: [f64; 24],
+}
+
+impl Default for ForagingProfile {
+    /// Diurnal curve: no flight overnight, peaks after dawn and before
+    /// dusk, a shallower plateau through midday heat.
+    fn default() -> Self {
+        ForagingProfile {
+            weights: [
+                0.0, 0.0, 0.0, 0.0, 0.0, 0.3, // 00–05
+                0.7, 1.0, 0.9, 0.8, 0.75, 0.7, // 06–11
+                0.7, 0.7, 0.7, 0.75, 0.85, 1.0, // 12–17
+                0.9, 0.5, 0.15, 0.0, 0.0, 0.0, // 18–23
+            ],
         }
     }
 }
 
+impl ForagingProfile {
+    /// Weight for `hour` (taken mod 24), clamped to [0, 1].
+    pub fn weight(&self, hour: u8) -> f64 {
+        self.weights[usize::from(hour % 24)].clamp(0.0, 1.0)
+    }
+}
+
 /// Compute H_poll from normalized O3, AQHI, PM2.5.
 /// Evidence shows higher O3, AQHI, and temperature correlate with bee mortality.[web:60][web:61]
 pub fn compute_h_poll(env: &BeeEnvSample, cfg: &HazardWeights) -> f64 {
@@ -119,24 +255,254 @@ pub fn compute_h_rf(env: &BeeEnvSample, cfg: &HazardWeights) -> f64 {
     rf.min(1.0).max(0.0)
 }
 
+/// Compute H_thermal: 0 up to the comfort threshold, rising linearly to 1
+/// at `temp_ref_c`. Hives overheat and foragers collapse in extreme heat.
+pub fn compute_h_thermal(env: &BeeEnvSample, cfg: &HazardWeights) -> f64 {
+    let span = cfg.temp_ref_c - cfg.temp_comfort_c;
+    if span <= 0.0 {
+        return if env.ambient_temp_c >= cfg.temp_ref_c {
+            1.0
+        } else {
+            0.0
+        };
+    }
+    ((env.ambient_temp_c - cfg.temp_comfort_c) / span).clamp(0.0, 1.0)
+}
+
+/// Hazard term of H_bee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum HazardComponent {
+    Poll,
+    Bio,
+    Rf,
+    Thermal,
+}
+
+/// Every term behind an H_bee value, for logging and weight tuning.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BeeHazardBreakdown {
+    pub h_poll: f64,
+    pub h_bio: f64,
+    pub h_rf: f64,
+    pub h_thermal: f64,
+    pub w_poll: f64,
+    pub w_bio: f64,
+    pub w_rf: f64,
+    pub w_thermal: f64,
+    /// H_bee: weighted mean of the terms, clamped to [0,1]; 1.0 (full
+    /// hazard) when an input or weight is not finite or the mean is
+    /// undefined.
+    pub weighted_total: f64,
+    /// Term with the largest weight × value. Ties go to the earlier term in
+    /// the order Poll, Bio, Rf, Thermal.
+    pub dominant: HazardComponent,
+}
+
+impl BeeHazardBreakdown {
+    fn from_terms(
+        env: &BeeEnvSample,
+        cfg: &HazardWeights,
+        h_poll: f64,
+        h_bio: f64,
+        h_rf: f64,
+        h_thermal: f64,
+    ) -> Self {
+        let terms = [
+            (HazardComponent::Poll, cfg.w_poll * h_poll),
+            (HazardComponent::Bio, cfg.w_bio * h_bio),
+            (HazardComponent::Rf, cfg.w_rf * h_rf),
+            (HazardComponent::Thermal, cfg.w_thermal * h_thermal),
+        ];
+        let mut dominant = terms[0];
+        for term in &terms[1..] {
+            if term.1 > dominant.1 {
+                dominant = *term;
+            }
+        }
+        let num: f64 = terms.iter().map(|t| t.1).sum();
+        let den = cfg.w_poll + cfg.w_bio + cfg.w_rf + cfg.w_thermal;
+        let mean = num / den;
+        BeeHazardBreakdown {
+            h_poll,
+            h_bio,
+            h_rf,
+            h_thermal,
+            w_poll: cfg.w_poll,
+            w_bio: cfg.w_bio,
+            w_rf: cfg.w_rf,
+            w_thermal: cfg.w_thermal,
+            weighted_total: if mean.is_finite() && check_hazard_inputs(env, cfg).is_ok() {
+                mean.clamp(0.0, 1.0)
+            } else {
+                1.0
+            },
+            dominant: dominant.0,
+        }
+    }
+
+    /// The weighted mean before clamping, NaN or infinite included.
+    fn weighted_mean(&self) -> f64 {
+        let num = self.w_poll * self.h_poll
+            + self.w_bio * self.h_bio
+            + self.w_rf * self.h_rf
+            + self.w_thermal * self.h_thermal;
+        num / (self.w_poll + self.w_bio + self.w_rf + self.w_thermal)
+    }
+}
+
+/// Compute every H_bee term and the weighted total.
+pub fn compute_h_bee_breakdown(env: &BeeEnvSample, cfg: &HazardWeights) -> BeeHazardBreakdown {
+    BeeHazardBreakdown::from_terms(
+        env,
+        cfg,
+        compute_h_poll(env, cfg),
+        compute_h_bio(env),
+        compute_h_rf(env, cfg),
+        compute_h_thermal(env, cfg),
+    )
+}
+
 /// Aggregate into H_bee in [0,1].
 pub fn compute_h_bee(env: &BeeEnvSample, cfg: &HazardWeights) -> f64 {
-    let h_poll = compute_h_poll(env, cfg);
-    let h_bio = compute_h_bio(env);
-    let h_rf = compute_h_rf(env, cfg);
+    compute_h_bee_breakdown(env, cfg).weighted_total
+}
+
+/// H_bee at a local hour: pollution and RF exposure only matter while bees
+/// fly, so both are scaled by the foraging weight. H_bio is left unscaled
+/// because pesticide residues stay on forage overnight; nor is H_thermal,
+/// since the colony has to cool the hive whether or not bees are flying.
+pub fn compute_h_bee_at(env: &BeeEnvSample, cfg: &HazardWeights, hour: u8) -> f64 {
+    h_bee_breakdown_at(env, cfg, hour).weighted_total
+}
+
+fn h_bee_breakdown_at(env: &BeeEnvSample, cfg: &HazardWeights, hour: u8) -> BeeHazardBreakdown {
+    let foraging = cfg.foraging.as_ref().map_or(1.0, |p| p.weight(hour));
+    BeeHazardBreakdown::from_terms(
+        env,
+        cfg,
+        compute_h_poll(env, cfg) * foraging,
+        compute_h_bio(env),
+        compute_h_rf(env, cfg) * foraging,
+        compute_h_thermal(env, cfg),
+    )
+}
 
-    let num = cfg.w_poll * h_poll + cfg.w_bio * h_bio + cfg.w_rf * h_rf;
-    let den = cfg.w_poll + cfg.w_bio + cfg.w_rf;
-    (num / den).min(1.0).max(0.0)
+/// Why `checked_h_bee` produced no hazard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HazardError {
+    /// An input, weight or the result is NaN or infinite.
+    NonFinite { field: &'static str, value: f64 },
 }
 
-/// Check bee rights and optionally downscale duty cycle.
+impl std::fmt::Display for HazardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HazardError::NonFinite { field, value } => {
+                write!(f, "{field} is not finite ({value})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HazardError {}
+
+/// `compute_h_bee`, refusing non-finite samples and weights. Unchecked, a
+/// NaN reading can score as either no hazard or full hazard (`min` drops
+/// NaN), and all-zero weights leave the mean undefined, which
+/// `compute_h_bee` reports as full hazard.
+pub fn checked_h_bee(env: &BeeEnvSample, cfg: &HazardWeights) -> Result<f64, HazardError> {
+    check_hazard_inputs(env, cfg)?;
+    checked_total(compute_h_bee_breakdown(env, cfg))
+}
+
+/// `compute_h_bee_at` with the checks of `checked_h_bee`.
+pub fn checked_h_bee_at(
+    env: &BeeEnvSample,
+    cfg: &HazardWeights,
+    hour: u8,
+) -> Result<f64, HazardError> {
+    check_hazard_inputs(env, cfg)?;
+    checked_total(h_bee_breakdown_at(env, cfg, hour))
+}
+
+fn check_hazard_inputs(env: &BeeEnvSample, cfg: &HazardWeights) -> Result<(), HazardError> {
+    for (field, value) in [
+        ("o3_ugm3", env.o3_ugm3),
+        ("aqhi", env.aqhi),
+        ("pm25_ugm3", env.pm25_ugm3),
+        ("emf_vpm", env.emf_vpm),
+        ("pesticide_index", env.pesticide_index),
+        ("ambient_temp_c", env.ambient_temp_c),
+        ("w_poll", cfg.w_poll),
+        ("w_bio", cfg.w_bio),
+        ("w_rf", cfg.w_rf),
+        ("w_thermal", cfg.w_thermal),
+        ("o3_ref_ugm3", cfg.o3_ref_ugm3),
+        ("aqhi_ref", cfg.aqhi_ref),
+        ("pm25_ref_ugm3", cfg.pm25_ref_ugm3),
+        ("emf_ref_vpm", cfg.emf_ref_vpm),
+        ("temp_comfort_c", cfg.temp_comfort_c),
+        ("temp_ref_c", cfg.temp_ref_c),
+    ] {
+        if !value.is_finite() {
+            return Err(HazardError::NonFinite { field, value });
+        }
+    }
+    Ok(())
+}
+
+fn checked_total(breakdown: BeeHazardBreakdown) -> Result<f64, HazardError> {
+    let mean = breakdown.weighted_mean();
+    if mean.is_finite() {
+        Ok(breakdown.weighted_total)
+    } else {
+        Err(HazardError::NonFinite {
+            field: "h_bee",
+            value: mean,
+        })
+    }
+}
+
+/// Outcome of checking a proposed duty cycle against the beerights polytope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeeRightsOutcome {
+    /// The proposed (clamped) duty cycle already satisfies every constraint.
+    AlreadySafe,
+    /// Nearest duty cycle inside the polytope, environment held fixed.
+    ProjectedTo(f64),
+    /// No duty cycle in [0, 1] satisfies the constraints for this
+    /// environment; the node should be held at 0.0.
+    Infeasible,
+}
+
+impl BeeRightsOutcome {
+    /// Duty cycle to actuate with, given what was proposed.
+    pub fn duty_cycle(&self, proposed_duty_cycle: f64) -> f64 {
+        match self {
+            BeeRightsOutcome::AlreadySafe => proposed_duty_cycle.clamp(0.0, 1.0),
+            BeeRightsOutcome::ProjectedTo(dc) => *dc,
+            BeeRightsOutcome::Infeasible => 0.0,
+        }
+    }
+}
+
+/// Check bee rights and project the duty cycle back into the polytope.
 /// This is the main function CyboAir should call before actuating.
+///
+/// Only the duty-cycle coordinate is controllable, so the least-distance
+/// projection onto {x : a·x + b <= 0} reduces to a 1-D LP: each constraint
+/// bounds the duty cycle from above or below (or not at all, in which case
+/// the environment alone must satisfy it), and the proposal is clamped into
+/// the resulting interval.
+///
+/// A non-finite environment field or proposal is `Infeasible`: NaN fails
+/// every comparison, so it would otherwise bind no constraint at all.
 pub fn enforce_bee_rights(
     env: &BeeEnvSample,
     proposed_duty_cycle: f64,
     polytope: &BeerightsPolytope,
-) -> (bool, f64) {
+) -> BeeRightsOutcome {
+    const TOL: f64 = 1e-9;
     let dc_clamped = proposed_duty_cycle.clamp(0.0, 1.0);
 
     let x: ParameterVector = [
@@ -146,21 +512,318 @@ pub fn enforce_bee_rights(
         dc_clamped,
     ];
 
-    if polytope.is_inside(&x, 1e-9) {
-        (true, dc_clamped)
+    if !proposed_duty_cycle.is_finite() || x.iter().any(|v| !v.is_finite()) {
+        return BeeRightsOutcome::Infeasible;
+    }
+    if polytope.is_inside(&x, TOL) {
+        return BeeRightsOutcome::AlreadySafe;
+    }
+    match duty_bounds(&x, polytope) {
+        Some((lo, hi)) => BeeRightsOutcome::ProjectedTo(dc_clamped.clamp(lo, hi)),
+        None => BeeRightsOutcome::Infeasible,
+    }
+}
+
+/// The duty cycles in [0, 1] that `polytope` admits with the environment of
+/// `x` held fixed, or `None` if there are none.
+fn duty_bounds(x: &ParameterVector, polytope: &BeerightsPolytope) -> Option<(f64, f64)> {
+    const TOL: f64 = 1e-9;
+    let (mut lo, mut hi) = (0.0_f64, 1.0_f64);
+    for c in &polytope.constraints {
+        // a3·d + rest <= 0, with rest fixed by the environment.
+        let rest = c.a[0] * x[0] + c.a[1] * x[1] + c.a[2] * x[2] + c.b;
+        if c.a[3].abs() <= TOL {
+            if rest > TOL {
+                return None;
+            }
+        } else if c.a[3] > 0.0 {
+            hi = hi.min(-rest / c.a[3]);
+        } else {
+            lo = lo.max(-rest / c.a[3]);
+        }
+    }
+    if lo > hi + TOL {
+        None
     } else {
-        // Simple mitigation strategy:
-        // - If too close or too polluted, drop duty cycle to a safe minimum.
-        // In a full implementation, this should solve a small LP to project
-        // back into the polytope, but here we enforce a hard de-rate.
-        let safe_dc = 0.0;
-        (false, safe_dc)
+        Some((lo, hi.max(lo)))
+    }
+}
+
+/// `enforce_bee_rights` with the constraints behind the decision, for
+/// telemetry. Constraint indices follow `BeerightsPolytope::constraints`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum BeeRightsDetail {
+    /// The proposed (clamped) duty cycle satisfies every constraint.
+    Allowed { duty: f64 },
+    /// The proposal broke `violated_constraints` and was projected to
+    /// `duty`. `margins[i]` is the proposal's signed distance to face
+    /// `violated_constraints[i]`, as in `BeerightsPolytope::margin`, so
+    /// always negative.
+    Derated {
+        duty: f64,
+        violated_constraints: Vec<usize>,
+        margins: Vec<f64>,
+    },
+    /// No duty cycle satisfies the polytope in this environment; the node
+    /// is held at 0.0. Lists the constraints the proposal broke.
+    Infeasible { violated_constraints: Vec<usize> },
+}
+
+impl BeeRightsDetail {
+    pub fn duty_cycle(&self) -> f64 {
+        match self {
+            BeeRightsDetail::Allowed { duty } | BeeRightsDetail::Derated { duty, .. } => *duty,
+            BeeRightsDetail::Infeasible { .. } => 0.0,
+        }
+    }
+}
+
+impl From<&BeeRightsDetail> for BeeRightsOutcome {
+    fn from(detail: &BeeRightsDetail) -> Self {
+        match detail {
+            BeeRightsDetail::Allowed { .. } => BeeRightsOutcome::AlreadySafe,
+            BeeRightsDetail::Derated { duty, .. } => BeeRightsOutcome::ProjectedTo(*duty),
+            BeeRightsDetail::Infeasible { .. } => BeeRightsOutcome::Infeasible,
+        }
+    }
+}
+
+/// `enforce_bee_rights`, also naming the constraints the proposal broke and
+/// how far outside each face it was.
+pub fn enforce_bee_rights_detailed(
+    env: &BeeEnvSample,
+    proposed_duty_cycle: f64,
+    polytope: &BeerightsPolytope,
+) -> BeeRightsDetail {
+    const TOL: f64 = 1e-9;
+    let dc_clamped = proposed_duty_cycle.clamp(0.0, 1.0);
+    let x: ParameterVector = [
+        env.distance_from_hive_m,
+        env.o3_ugm3,
+        env.emf_vpm,
+        dc_clamped,
+    ];
+    let mut violated_constraints = Vec::new();
+    let mut margins = Vec::new();
+    for (i, c) in polytope.constraints.iter().enumerate() {
+        let dot = c.a[0] * x[0] + c.a[1] * x[1] + c.a[2] * x[2] + c.a[3] * x[3] + c.b;
+        if dot > TOL {
+            let norm = c.a.iter().map(|v| v * v).sum::<f64>().sqrt();
+            violated_constraints.push(i);
+            margins.push(if norm == 0.0 {
+                f64::NEG_INFINITY
+            } else {
+                -dot / norm
+            });
+        }
+    }
+
+    match enforce_bee_rights(env, proposed_duty_cycle, polytope) {
+        BeeRightsOutcome::AlreadySafe => BeeRightsDetail::Allowed { duty: dc_clamped },
+        BeeRightsOutcome::ProjectedTo(duty) => BeeRightsDetail::Derated {
+            duty,
+            violated_constraints,
+            margins,
+        },
+        BeeRightsOutcome::Infeasible => BeeRightsDetail::Infeasible {
+            violated_constraints,
+        },
+    }
+}
+
+/// `enforce_bee_rights` with a graduated de-rate near the boundary.
+///
+/// After projection, the environmental margin is measured over every
+/// constraint that is not a pure duty-cycle bound (those are already
+/// enforced exactly by the projection), in units of [`AXIS_SCALE`], so
+/// `warn_margin` is a fraction of each axis rather than metres on one and
+/// V/m on another. Inside `warn_margin` of such a face the duty is
+/// interpolated linearly, from the full projected duty at `warn_margin`
+/// down to the lowest duty the polytope admits at the face itself.
+pub fn enforce_bee_rights_graduated(
+    env: &BeeEnvSample,
+    proposed_duty_cycle: f64,
+    polytope: &BeerightsPolytope,
+    warn_margin: f64,
+) -> BeeRightsOutcome {
+    let outcome = enforce_bee_rights(env, proposed_duty_cycle, polytope);
+    if outcome == BeeRightsOutcome::Infeasible || warn_margin <= 0.0 {
+        return outcome;
+    }
+    let dc = outcome.duty_cycle(proposed_duty_cycle);
+    let environmental = BeerightsPolytope {
+        constraints: polytope
+            .constraints
+            .iter()
+            .filter(|c| c.a[..3].iter().any(|v| *v != 0.0))
+            .cloned()
+            .collect(),
+    };
+    let x = [env.distance_from_hive_m, env.o3_ugm3, env.emf_vpm, dc];
+    let m = environmental.scaled_margin(&x, &AXIS_SCALE);
+    if m >= warn_margin {
+        return outcome;
+    }
+    let Some((lo, _)) = duty_bounds(&x, polytope) else {
+        return BeeRightsOutcome::Infeasible;
+    };
+    BeeRightsOutcome::ProjectedTo(lo + (dc - lo) * (m / warn_margin).max(0.0))
+}
+
+/// One hive's contribution to `enforce_bee_rights_multi`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiveDecision {
+    /// Index into the `envs` slice.
+    pub hive: usize,
+    pub outcome: BeeRightsOutcome,
+    /// Duty cycle this hive alone would allow.
+    pub duty_cycle: f64,
+    /// Polytope margin at the proposed (clamped) duty cycle.
+    pub margin: f64,
+}
+
+/// Combined decision across every hive in foraging range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiHiveDecision {
+    /// Minimum safe duty cycle over all hives.
+    pub duty_cycle: f64,
+    /// First hive whose limit set `duty_cycle`, or `None` if no hive
+    /// restricted the proposal.
+    pub binding_hive: Option<usize>,
+    pub hives: Vec<HiveDecision>,
+}
+
+impl MultiHiveDecision {
+    /// True if no hive needed the proposal changed.
+    pub fn is_safe(&self) -> bool {
+        self.binding_hive.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultiHiveError {
+    /// No hives were given; an empty set must not read as "allowed".
+    NoHives,
+}
+
+impl std::fmt::Display for MultiHiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultiHiveError::NoHives => write!(f, "no hives given for bee-rights enforcement"),
+        }
+    }
+}
+
+impl std::error::Error for MultiHiveError {}
+
+/// Run `enforce_bee_rights` against every hive a node can reach, each with
+/// its own environment and site polytope, and keep the most restrictive duty.
+pub fn enforce_bee_rights_multi(
+    envs: &[(BeeEnvSample, &BeerightsPolytope)],
+    proposed_duty: f64,
+) -> Result<MultiHiveDecision, MultiHiveError> {
+    if envs.is_empty() {
+        return Err(MultiHiveError::NoHives);
+    }
+    let proposed = proposed_duty.clamp(0.0, 1.0);
+    let hives: Vec<HiveDecision> = envs
+        .iter()
+        .enumerate()
+        .map(|(hive, (env, polytope))| {
+            let outcome = enforce_bee_rights(env, proposed, polytope);
+            let x = [env.distance_from_hive_m, env.o3_ugm3, env.emf_vpm, proposed];
+            HiveDecision {
+                hive,
+                outcome,
+                duty_cycle: outcome.duty_cycle(proposed),
+                margin: polytope.margin(&x),
+            }
+        })
+        .collect();
+
+    let duty_cycle = hives.iter().map(|h| h.duty_cycle).fold(proposed, f64::min);
+    let binding_hive = hives
+        .iter()
+        .find(|h| h.outcome != BeeRightsOutcome::AlreadySafe && h.duty_cycle <= duty_cycle)
+        .map(|h| h.hive);
+    Ok(MultiHiveDecision {
+        duty_cycle,
+        binding_hive,
+        hives,
+    })
+}
+
+/// Largest factor by which duty-cycle bounds may be relaxed when no bees fly.
+pub const MAX_NIGHT_RELAXATION: f64 = 2.0;
+
+/// Foraging weight at or below which an hour counts as night.
+pub const NIGHT_FORAGING_THRESHOLD: f64 = 0.0;
+
+/// Duty cycle that no time-of-day relaxation may exceed.
+pub const NIGHT_DUTY_HARD_CAP: f64 = 0.5;
+
+/// `enforce_bee_rights` at a local hour.
+///
+/// While any bees forage the polytope applies unchanged. At night, when
+/// the foraging weight is at or below `NIGHT_FORAGING_THRESHOLD`, upper
+/// bounds on duty cycle are relaxed by `MAX_NIGHT_RELAXATION` and then
+/// capped at `NIGHT_DUTY_HARD_CAP`. Purely environmental constraints (hive
+/// distance, O3, EMF) are never relaxed.
+pub fn enforce_bee_rights_at(
+    env: &BeeEnvSample,
+    proposed_duty_cycle: f64,
+    polytope: &BeerightsPolytope,
+    hour: u8,
+    profile: &ForagingProfile,
+) -> BeeRightsOutcome {
+    if profile.weight(hour) > NIGHT_FORAGING_THRESHOLD {
+        return enforce_bee_rights(env, proposed_duty_cycle, polytope);
+    }
+    let relax = MAX_NIGHT_RELAXATION;
+    let mut constraints: Vec<LinearConstraint> = polytope
+        .constraints
+        .iter()
+        .map(|c| {
+            let mut c = c.clone();
+            if c.a[3] > 0.0 {
+                // a3·d + rest <= 0  ->  d <= relax · (-rest / a3)
+                c.a[3] /= relax;
+            }
+            c
+        })
+        .collect();
+    constraints.push(LinearConstraint {
+        a: [0.0, 0.0, 0.0, 1.0],
+        b: -NIGHT_DUTY_HARD_CAP,
+    });
+    enforce_bee_rights(env, proposed_duty_cycle, &BeerightsPolytope { constraints })
+}
+
+/// Corridor controller bee guard backed by a beerights polytope.
+/// `env` maps a node to its bee environment (hive distance, O3, EMF).
+pub struct PolytopeBeeGuard<F> {
+    pub polytope: BeerightsPolytope,
+    pub env: F,
+}
+
+impl<F> BeeGuard for PolytopeBeeGuard<F>
+where
+    F: Fn(&NodeState) -> BeeEnvSample,
+{
+    fn check_bee_rights(&self, node: &NodeState, u_new: f64) -> Option<f64> {
+        let env = (self.env)(node);
+        match enforce_bee_rights(&env, u_new, &self.polytope) {
+            BeeRightsOutcome::AlreadySafe => None,
+            outcome => Some(outcome.duty_cycle(u_new)),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cyboair_corridor_safety::{CorridorRow, OperationalMode};
 
     #[test]
     fn test_polytope_inside_and_outside() {
@@ -184,15 +847,17 @@ mod tests {
             pm25_ugm3: 20.0,
             emf_vpm: 0.5,
             pesticide_index: 0.4,
+            ambient_temp_c: 30.0,
         };
         let cfg = HazardWeights::default();
         let h_bee = compute_h_bee(&env, &cfg);
         assert!(h_bee >= 0.0 && h_bee <= 1.0);
 
         let poly = BeerightsPolytope::default_conservative();
-        let (ok, dc) = enforce_bee_rights(&env, 0.2, &poly);
-        assert!(ok);
-        assert_eq!(dc, 0.2);
+        assert_eq!(
+            enforce_bee_rights(&env, 0.2, &poly),
+            BeeRightsOutcome::AlreadySafe
+        );
 
         let env_bad = BeeEnvSample {
             distance_from_hive_m: 20.0,
@@ -201,9 +866,580 @@ mod tests {
             pm25_ugm3: 50.0,
             emf_vpm: 2.0,
             pesticide_index: 0.9,
+            ambient_temp_c: 30.0,
+        };
+        let outcome = enforce_bee_rights(&env_bad, 0.8, &poly);
+        assert_eq!(outcome, BeeRightsOutcome::Infeasible);
+        assert_eq!(outcome.duty_cycle(0.8), 0.0);
+    }
+
+    #[test]
+    fn detailed_outcome_names_binding_constraints() {
+        let poly = BeerightsPolytope::default_conservative();
+
+        // Duty only: 0.5 > 0.3 breaks constraint 3, 0.2 past its face.
+        let detail = enforce_bee_rights_detailed(&apiary_env(), 0.5, &poly);
+        let BeeRightsDetail::Derated {
+            duty,
+            violated_constraints,
+            margins,
+        } = &detail
+        else {
+            panic!("expected derate, got {detail:?}");
+        };
+        assert_eq!(*duty, 0.3);
+        assert_eq!(violated_constraints, &[3]);
+        assert!((margins[0] + 0.2).abs() < 1e-12);
+        assert_eq!(
+            BeeRightsOutcome::from(&detail),
+            enforce_bee_rights(&apiary_env(), 0.5, &poly)
+        );
+
+        // O3 only: 100 > 80 breaks constraint 1 whatever the duty.
+        let smoggy = BeeEnvSample {
+            o3_ugm3: 100.0,
+            ..apiary_env()
+        };
+        let detail = enforce_bee_rights_detailed(&smoggy, 0.2, &poly);
+        assert_eq!(
+            detail,
+            BeeRightsDetail::Infeasible {
+                violated_constraints: vec![1]
+            }
+        );
+        assert_eq!(detail.duty_cycle(), 0.0);
+
+        let allowed = enforce_bee_rights_detailed(&apiary_env(), 0.2, &poly);
+        assert_eq!(allowed, BeeRightsDetail::Allowed { duty: 0.2 });
+        let json = serde_json::to_value(&allowed).unwrap();
+        assert_eq!(json, serde_json::json!({"outcome": "allowed", "duty": 0.2}));
+    }
+
+    fn apiary_env() -> BeeEnvSample {
+        BeeEnvSample {
+            distance_from_hive_m: 60.0,
+            o3_ugm3: 70.0,
+            aqhi: 6.0,
+            pm25_ugm3: 20.0,
+            emf_vpm: 0.5,
+            pesticide_index: 0.4,
+            ambient_temp_c: 30.0,
+        }
+    }
+
+    #[test]
+    fn checked_h_bee_refuses_non_finite_inputs() {
+        let env_setters: [fn(&mut BeeEnvSample, f64); 6] = [
+            |e, v| e.o3_ugm3 = v,
+            |e, v| e.aqhi = v,
+            |e, v| e.pm25_ugm3 = v,
+            |e, v| e.emf_vpm = v,
+            |e, v| e.pesticide_index = v,
+            |e, v| e.ambient_temp_c = v,
+        ];
+        let cfg_setters: [fn(&mut HazardWeights, f64); 10] = [
+            |c, v| c.w_poll = v,
+            |c, v| c.w_bio = v,
+            |c, v| c.w_rf = v,
+            |c, v| c.w_thermal = v,
+            |c, v| c.o3_ref_ugm3 = v,
+            |c, v| c.aqhi_ref = v,
+            |c, v| c.pm25_ref_ugm3 = v,
+            |c, v| c.emf_ref_vpm = v,
+            |c, v| c.temp_comfort_c = v,
+            |c, v| c.temp_ref_c = v,
+        ];
+        let cfg = HazardWeights::default();
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            for set in env_setters {
+                let mut env = apiary_env();
+                set(&mut env, value);
+                assert!(checked_h_bee(&env, &cfg).is_err());
+                assert!(checked_h_bee_at(&env, &cfg, 12).is_err());
+            }
+            for set in cfg_setters {
+                let mut cfg = HazardWeights::default();
+                set(&mut cfg, value);
+                assert!(checked_h_bee(&apiary_env(), &cfg).is_err());
+            }
+        }
+
+        // NaN ozone reads as saturated unchecked.
+        let mut env = apiary_env();
+        env.o3_ugm3 = f64::NAN;
+        assert!(compute_h_poll(&env, &cfg) > compute_h_poll(&apiary_env(), &cfg));
+        assert_eq!(
+            checked_h_bee(&env, &cfg).unwrap_err().to_string(),
+            "o3_ugm3 is not finite (NaN)"
+        );
+
+        // Finite inputs, undefined result.
+        let unweighted = HazardWeights {
+            w_poll: 0.0,
+            w_bio: 0.0,
+            w_rf: 0.0,
+            w_thermal: 0.0,
+            ..HazardWeights::default()
+        };
+        assert_eq!(compute_h_bee(&apiary_env(), &unweighted), 1.0);
+        assert_eq!(
+            checked_h_bee(&apiary_env(), &unweighted)
+                .unwrap_err()
+                .to_string(),
+            "h_bee is not finite (NaN)"
+        );
+        assert_eq!(
+            checked_h_bee(&apiary_env(), &cfg),
+            Ok(compute_h_bee(&apiary_env(), &cfg))
+        );
+    }
+
+    #[test]
+    fn unchecked_h_bee_fails_safe_on_non_finite_inputs() {
+        let cfg = HazardWeights::default();
+        assert!(compute_h_bee(&apiary_env(), &cfg) < 1.0);
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut env = apiary_env();
+            env.ambient_temp_c = value;
+            assert_eq!(compute_h_bee(&env, &cfg), 1.0, "ambient_temp_c = {value}");
+            assert_eq!(compute_h_bee_at(&env, &cfg, 2), 1.0);
+
+            let cfg = HazardWeights {
+                w_thermal: value,
+                ..HazardWeights::default()
+            };
+            assert_eq!(
+                compute_h_bee(&apiary_env(), &cfg),
+                1.0,
+                "w_thermal = {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn foraging_scales_poll_and_rf_but_not_bio() {
+        let env = apiary_env();
+        let cfg = HazardWeights::default();
+        let night = compute_h_bee_at(&env, &cfg, 2);
+        let noon = compute_h_bee_at(&env, &cfg, 12);
+        assert!(night < noon);
+        // At 02:00 only the pesticide term remains.
+        let bio_only = cfg.w_bio * compute_h_bio(&env) / (cfg.w_poll + cfg.w_bio + cfg.w_rf);
+        assert!((night - bio_only).abs() < 1e-12);
+
+        let flat = HazardWeights {
+            foraging: None,
+            ..HazardWeights::default()
+        };
+        assert_eq!(compute_h_bee_at(&env, &flat, 2), compute_h_bee(&env, &flat));
+    }
+
+    #[test]
+    fn breakdown_matches_scalar_and_names_dominant_term() {
+        let cfg = HazardWeights::default();
+        let env = apiary_env();
+        let b = compute_h_bee_breakdown(&env, &cfg);
+        assert_eq!(b.weighted_total, compute_h_bee(&env, &cfg));
+        assert_eq!(b.h_bio, compute_h_bio(&env));
+        assert_eq!(b.w_poll, cfg.w_poll);
+        assert_eq!(b.dominant, HazardComponent::Poll);
+
+        // Heavy residues, clean air: bio dominates.
+        let sprayed = BeeEnvSample {
+            o3_ugm3: 0.0,
+            aqhi: 0.0,
+            pm25_ugm3: 0.0,
+            emf_vpm: 0.0,
+            pesticide_index: 1.0,
+            ..apiary_env()
+        };
+        assert_eq!(
+            compute_h_bee_breakdown(&sprayed, &cfg).dominant,
+            HazardComponent::Bio
+        );
+
+        // Heat only counts once it carries weight.
+        let heatwave = BeeEnvSample {
+            ambient_temp_c: 47.0,
+            ..sprayed
+        };
+        let hot = HazardWeights {
+            w_thermal: 0.5,
+            ..HazardWeights::default()
+        };
+        let b = compute_h_bee_breakdown(&heatwave, &hot);
+        assert_eq!(b.dominant, HazardComponent::Thermal);
+        assert_eq!(b.weighted_total, compute_h_bee(&heatwave, &hot));
+
+        // All-zero terms tie; the earliest component wins.
+        let clean = BeeEnvSample {
+            pesticide_index: 0.0,
+            ..sprayed
+        };
+        assert_eq!(
+            compute_h_bee_breakdown(&clean, &cfg).dominant,
+            HazardComponent::Poll
+        );
+        let json = serde_json::to_value(compute_h_bee_breakdown(&env, &cfg)).unwrap();
+        assert_eq!(json["dominant"], "Poll");
+    }
+
+    #[test]
+    fn thermal_stress_comfort_ramp_and_saturation() {
+        let cfg = HazardWeights {
+            w_thermal: 0.4,
+            ..HazardWeights::default()
+        };
+        let at = |t: f64| BeeEnvSample {
+            ambient_temp_c: t,
+            ..apiary_env()
+        };
+        assert_eq!(compute_h_thermal(&at(20.0), &cfg), 0.0);
+        assert_eq!(compute_h_thermal(&at(35.0), &cfg), 0.0);
+        assert!((compute_h_thermal(&at(40.0), &cfg) - 0.5).abs() < 1e-12);
+        assert_eq!(compute_h_thermal(&at(45.0), &cfg), 1.0);
+        assert_eq!(compute_h_thermal(&at(52.0), &cfg), 1.0);
+
+        assert!(compute_h_bee(&at(45.0), &cfg) > compute_h_bee(&at(30.0), &cfg));
+        for t in [-10.0, 30.0, 40.0, 45.0, 60.0] {
+            for hour in [2, 12] {
+                let h = compute_h_bee_at(&at(t), &cfg, hour);
+                assert!((0.0..=1.0).contains(&h));
+            }
+            assert!((0.0..=1.0).contains(&compute_h_bee(&at(t), &cfg)));
+        }
+
+        // The default leaves thermal stress out of H_bee entirely.
+        let legacy = HazardWeights::default();
+        assert_eq!(
+            compute_h_bee(&at(48.0), &legacy),
+            compute_h_bee(&at(20.0), &legacy)
+        );
+    }
+
+    #[test]
+    fn night_relaxes_duty_bound_up_to_hard_cap() {
+        let env = apiary_env();
+        let poly = BeerightsPolytope::default_conservative();
+        let profile = ForagingProfile::default();
+
+        assert_eq!(
+            enforce_bee_rights_at(&env, 0.45, &poly, 2, &profile),
+            BeeRightsOutcome::AlreadySafe
+        );
+        // Any foraging at all keeps the polytope's own bound.
+        for hour in (0..24).filter(|&h| profile.weight(h) > 0.0) {
+            assert_eq!(
+                enforce_bee_rights_at(&env, 0.45, &poly, hour, &profile),
+                BeeRightsOutcome::ProjectedTo(0.3),
+                "hour {hour}"
+            );
+        }
+        // 0.3 relaxed 2x would be 0.6; the hard cap wins.
+        assert_eq!(
+            enforce_bee_rights_at(&env, 0.9, &poly, 2, &profile),
+            BeeRightsOutcome::ProjectedTo(NIGHT_DUTY_HARD_CAP)
+        );
+        // Hive distance is not relaxed.
+        let near = BeeEnvSample {
+            distance_from_hive_m: 20.0,
+            ..apiary_env()
         };
-        let (ok2, dc2) = enforce_bee_rights(&env_bad, 0.8, &poly);
-        assert!(!ok2);
-        assert_eq!(dc2, 0.0);
+        assert_eq!(
+            enforce_bee_rights_at(&near, 0.1, &poly, 2, &profile),
+            BeeRightsOutcome::Infeasible
+        );
+    }
+
+    #[test]
+    fn margin_inside_on_face_and_outside() {
+        let p = BeerightsPolytope::default_conservative();
+        // Nearest face is duty: 0.3 - 0.1.
+        assert!((p.margin(&[100.0, 40.0, 0.2, 0.1]) - 0.2).abs() < 1e-12);
+        assert_eq!(
+            p.classify(&[100.0, 40.0, 0.2, 0.1], 0.05),
+            PolytopeZone::Inside
+        );
+        // On the duty face.
+        assert_eq!(p.margin(&[100.0, 40.0, 0.2, 0.3]), 0.0);
+        assert_eq!(
+            p.classify(&[100.0, 40.0, 0.2, 0.3], 0.05),
+            PolytopeZone::Warning
+        );
+        // 30 m inside the hive exclusion distance.
+        assert_eq!(p.margin(&[20.0, 40.0, 0.2, 0.1]), -30.0);
+        assert_eq!(
+            p.classify(&[20.0, 40.0, 0.2, 0.1], 0.05),
+            PolytopeZone::Outside
+        );
+
+        // Rows are normalised: 2·x3 - 0.6 <= 0 is the same face as x3 <= 0.3.
+        let scaled = BeerightsPolytope::new(vec![LinearConstraint {
+            a: [0.0, 0.0, 0.0, 2.0],
+            b: -0.6,
+        }])
+        .unwrap();
+        assert!((scaled.margin(&[0.0, 0.0, 0.0, 0.1]) - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zero_norm_row_is_rejected_at_construction() {
+        let rows = vec![
+            LinearConstraint {
+                a: [0.0, 0.0, 0.0, 1.0],
+                b: -0.3,
+            },
+            LinearConstraint {
+                a: [0.0; 4],
+                b: -1.0,
+            },
+        ];
+        assert!(matches!(
+            BeerightsPolytope::new(rows),
+            Err(PolytopeError::ZeroNorm { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn warning_band_derates_linearly() {
+        let poly = BeerightsPolytope::default_conservative();
+        let env = |distance: f64, o3: f64| BeeEnvSample {
+            distance_from_hive_m: distance,
+            o3_ugm3: o3,
+            emf_vpm: 0.0,
+            ..apiary_env()
+        };
+        // Margins are in axis units: the warning band is 5% of 80 ugm3 of
+        // O3, or 2.5 m of hive distance.
+        assert_eq!(
+            enforce_bee_rights_graduated(&env(200.0, 70.0), 0.2, &poly, 0.05),
+            BeeRightsOutcome::AlreadySafe
+        );
+        match enforce_bee_rights_graduated(&env(200.0, 78.0), 0.2, &poly, 0.05) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.1).abs() < 1e-9),
+            other => panic!("expected de-rate, got {other:?}"),
+        }
+        match enforce_bee_rights_graduated(&env(51.0, 40.0), 0.2, &poly, 0.05) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.08).abs() < 1e-9),
+            other => panic!("expected de-rate, got {other:?}"),
+        }
+        // Over-duty is projected first, then de-rated.
+        match enforce_bee_rights_graduated(&env(200.0, 78.0), 0.5, &poly, 0.05) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.15).abs() < 1e-9),
+            other => panic!("expected de-rate, got {other:?}"),
+        }
+        assert_eq!(
+            enforce_bee_rights_graduated(&env(200.0, 81.0), 0.2, &poly, 0.05),
+            BeeRightsOutcome::Infeasible
+        );
+
+        // At the face the duty falls to the polytope's lower bound, not to 0.
+        let floored = poly.intersect(
+            &BeerightsPolytope::new(vec![LinearConstraint {
+                a: [0.0, 0.0, 0.0, -1.0],
+                b: 0.1,
+            }])
+            .unwrap(),
+        );
+        match enforce_bee_rights_graduated(&env(200.0, 78.0), 0.2, &floored, 0.05) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.15).abs() < 1e-9),
+            other => panic!("expected de-rate, got {other:?}"),
+        }
+        match enforce_bee_rights_graduated(&env(200.0, 80.0), 0.2, &floored, 0.05) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.1).abs() < 1e-9),
+            other => panic!("expected de-rate, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn nearer_hive_binds_multi_hive_decision() {
+        let default = BeerightsPolytope::default_conservative();
+        // Site polytope for a hive next to a school garden: duty <= 0.002 per metre.
+        let near_site = BeerightsPolytope::new(vec![
+            LinearConstraint {
+                a: [-1.0, 0.0, 0.0, 0.0],
+                b: 50.0,
+            },
+            LinearConstraint {
+                a: [-0.002, 0.0, 0.0, 1.0],
+                b: 0.0,
+            },
+        ])
+        .unwrap();
+        let far = BeeEnvSample {
+            distance_from_hive_m: 300.0,
+            ..apiary_env()
+        };
+        let near = BeeEnvSample {
+            distance_from_hive_m: 60.0,
+            ..apiary_env()
+        };
+
+        let decision =
+            enforce_bee_rights_multi(&[(far, &default), (near, &near_site)], 0.25).unwrap();
+        assert!((decision.duty_cycle - 0.12).abs() < 1e-9);
+        assert_eq!(decision.binding_hive, Some(1));
+        assert!(!decision.is_safe());
+        assert_eq!(decision.hives[0].outcome, BeeRightsOutcome::AlreadySafe);
+        assert_eq!(decision.hives[0].duty_cycle, 0.25);
+        assert!(decision.hives[0].margin > 0.0);
+        assert!(decision.hives[1].margin < 0.0);
+
+        // Low enough for both: nothing binds.
+        let decision =
+            enforce_bee_rights_multi(&[(apiary_env(), &default), (apiary_env(), &near_site)], 0.1)
+                .unwrap();
+        assert!(decision.is_safe());
+        assert_eq!(decision.duty_cycle, 0.1);
+    }
+
+    #[test]
+    fn multi_hive_rejects_empty_input() {
+        assert_eq!(
+            enforce_bee_rights_multi(&[], 0.2),
+            Err(MultiHiveError::NoHives)
+        );
+    }
+
+    #[test]
+    fn excess_duty_projects_to_polytope_boundary() {
+        let env = BeeEnvSample {
+            distance_from_hive_m: 60.0,
+            o3_ugm3: 70.0,
+            aqhi: 6.0,
+            pm25_ugm3: 20.0,
+            emf_vpm: 0.5,
+            pesticide_index: 0.4,
+            ambient_temp_c: 30.0,
+        };
+        let poly = BeerightsPolytope::default_conservative();
+        match enforce_bee_rights(&env, 0.35, &poly) {
+            BeeRightsOutcome::ProjectedTo(dc) => assert!((dc - 0.3).abs() < 1e-12),
+            other => panic!("expected projection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn ozone_alone_is_infeasible_at_any_duty() {
+        let env = BeeEnvSample {
+            distance_from_hive_m: 200.0,
+            o3_ugm3: 95.0,
+            aqhi: 4.0,
+            pm25_ugm3: 10.0,
+            emf_vpm: 0.2,
+            pesticide_index: 0.1,
+            ambient_temp_c: 30.0,
+        };
+        let poly = BeerightsPolytope::default_conservative();
+        for dc in [0.0, 0.1, 0.3, 0.9] {
+            assert_eq!(
+                enforce_bee_rights(&env, dc, &poly),
+                BeeRightsOutcome::Infeasible
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_inputs_hold_the_node_at_zero() {
+        let env = BeeEnvSample {
+            distance_from_hive_m: 200.0,
+            o3_ugm3: 40.0,
+            aqhi: 4.0,
+            pm25_ugm3: 10.0,
+            emf_vpm: 0.2,
+            pesticide_index: 0.1,
+            ambient_temp_c: 30.0,
+        };
+        let poly = BeerightsPolytope::default_conservative();
+        let nan_distance = BeeEnvSample {
+            distance_from_hive_m: f64::NAN,
+            ..env.clone()
+        };
+        let inf_emf = BeeEnvSample {
+            emf_vpm: f64::INFINITY,
+            ..env.clone()
+        };
+        for bad in [&nan_distance, &inf_emf] {
+            assert_eq!(
+                enforce_bee_rights(bad, 0.8, &poly),
+                BeeRightsOutcome::Infeasible
+            );
+            assert_eq!(
+                enforce_bee_rights_graduated(bad, 0.8, &poly, 0.05),
+                BeeRightsOutcome::Infeasible
+            );
+            assert_eq!(
+                enforce_bee_rights_detailed(bad, 0.8, &poly).duty_cycle(),
+                0.0
+            );
+        }
+        assert_eq!(
+            enforce_bee_rights(&env, f64::NAN, &poly),
+            BeeRightsOutcome::Infeasible
+        );
+        let multi = enforce_bee_rights_multi(&[(env, &poly), (nan_distance, &poly)], 0.8).unwrap();
+        assert_eq!(multi.duty_cycle, 0.0);
+        assert_eq!(multi.binding_hive, Some(1));
+    }
+
+    fn apiary_node(location: &str) -> NodeState {
+        NodeState {
+            row: CorridorRow {
+                machine_id: "CYB-AIR-APIARY-01".to_string(),
+                r#type: "UrbanNanoswarmCanopy".to_string(),
+                location: location.to_string(),
+                pollutant: "PM2.5".to_string(),
+                cin: 30.0,
+                cout: 20.0,
+                unit: "ugm3".to_string(),
+                airflow_m3_per_s: 0.8,
+                period_s: 3600.0,
+                lambda_hazard: 3.5,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.93,
+                trace_id: None,
+                shard_version: None,
+            },
+            mass_kg: 0.0,
+            karma_bytes: 0.0,
+            duty_cycle: 0.3,
+            power_w: 50.0,
+            geo_weight: 0.8,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        }
+    }
+
+    fn site_env(node: &NodeState) -> BeeEnvSample {
+        BeeEnvSample {
+            distance_from_hive_m: if node.row.location == "Phoenix-Apiary-1" {
+                20.0
+            } else {
+                200.0
+            },
+            o3_ugm3: 60.0,
+            aqhi: 5.0,
+            pm25_ugm3: 15.0,
+            emf_vpm: 0.3,
+            pesticide_index: 0.2,
+            ambient_temp_c: 30.0,
+        }
+    }
+
+    #[test]
+    fn polytope_guard_holds_node_near_hive_and_projects_far_one() {
+        let guard = PolytopeBeeGuard {
+            polytope: BeerightsPolytope::default_conservative(),
+            env: site_env,
+        };
+
+        let near = apiary_node("Phoenix-Apiary-1");
+        assert_eq!(guard.check_bee_rights(&near, 0.8), Some(0.0));
+
+        let far = apiary_node("Phoenix-Intersection-A");
+        assert_eq!(guard.check_bee_rights(&far, 0.2), None);
+        assert_eq!(guard.check_bee_rights(&far, 0.8), Some(0.3));
     }
 }
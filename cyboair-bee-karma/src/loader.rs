@@ -0,0 +1,513 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{
+    enforce_bee_rights, BeeEnvSample, BeerightsPolytope, LinearConstraint, ParameterVector,
+};
+
+/// Index of the duty-cycle coordinate in a `ParameterVector`.
+const DUTY: usize = 3;
+
+/// Largest polytope accepted from a file. Each feasibility check is a
+/// simplex over at most `MAX_CONSTRAINTS + 4` rows, and `validate` runs one
+/// per constraint plus one per default face.
+pub const MAX_CONSTRAINTS: usize = 16;
+
+const TOL: f64 = 1e-9;
+/// Margin used when asking whether a region reaches strictly past a bound.
+const EPS: f64 = 1e-6;
+
+/// Why a site polytope was refused.
+#[derive(Debug)]
+pub enum PolytopeError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    NoConstraints,
+    TooManyConstraints {
+        count: usize,
+        max: usize,
+    },
+    /// Coefficient or offset of this constraint is NaN or infinite.
+    NonFinite {
+        index: usize,
+    },
+    /// Every coefficient of this constraint is zero, so it has no normal.
+    ZeroNorm {
+        index: usize,
+    },
+    /// Constraints `0..=index` already leave no point with duty in [0, 1].
+    Empty {
+        index: usize,
+    },
+    /// No constraint bounds the duty cycle from above.
+    DutyUnbounded,
+    /// Somewhere the site polytope allows more duty than constraint
+    /// `default_constraint` of `default_conservative()` allows at the same
+    /// hive distance.
+    ExceedsDefault {
+        default_constraint: usize,
+    },
+    /// At probe `probe` ([distance_m, o3_ugm3, emf_vpm]) the site polytope
+    /// allows more duty than the floor.
+    RelaxesFloor {
+        probe: usize,
+        point: ProbePoint,
+        site_duty: f64,
+        floor_duty: f64,
+    },
+}
+
+impl fmt::Display for PolytopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolytopeError::Io(e) => write!(f, "io error: {e}"),
+            PolytopeError::Json(e) => write!(f, "json error: {e}"),
+            PolytopeError::NoConstraints => write!(f, "polytope has no constraints"),
+            PolytopeError::TooManyConstraints { count, max } => {
+                write!(f, "polytope has {count} constraints, at most {max} allowed")
+            }
+            PolytopeError::NonFinite { index } => {
+                write!(f, "constraint {index}: non-finite coefficient")
+            }
+            PolytopeError::ZeroNorm { index } => {
+                write!(f, "constraint {index}: all coefficients are zero")
+            }
+            PolytopeError::Empty { index } => {
+                write!(f, "constraint {index}: feasible region is empty")
+            }
+            PolytopeError::DutyUnbounded => {
+                write!(f, "no constraint bounds duty_cycle from above")
+            }
+            PolytopeError::ExceedsDefault { default_constraint } => write!(
+                f,
+                "allows more duty than default constraint {default_constraint} at the same hive distance"
+            ),
+            PolytopeError::RelaxesFloor {
+                probe,
+                point,
+                site_duty,
+                floor_duty,
+            } => write!(
+                f,
+                "probe {probe} {point:?}: site allows duty {site_duty}, floor allows {floor_duty}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PolytopeError {}
+
+impl From<std::io::Error> for PolytopeError {
+    fn from(err: std::io::Error) -> Self {
+        PolytopeError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PolytopeError {
+    fn from(err: serde_json::Error) -> Self {
+        PolytopeError::Json(err)
+    }
+}
+
+/// Environmental point `[distance_from_hive_m, o3_ugm3, emf_vpm]` at which
+/// site and floor polytopes are compared.
+pub type ProbePoint = [f64; 3];
+
+/// Grid used by `merge_with_floor`: hive distances from 0 to 500 m crossed
+/// with clean-to-polluted O3 and EMF levels.
+pub fn default_probe_points() -> Vec<ProbePoint> {
+    let mut probes = Vec::new();
+    for distance in [0.0, 25.0, 50.0, 75.0, 100.0, 200.0, 500.0] {
+        for o3 in [0.0, 40.0, 80.0, 120.0] {
+            for emf in [0.0, 0.5, 1.0, 2.0] {
+                probes.push([distance, o3, emf]);
+            }
+        }
+    }
+    probes
+}
+
+/// Largest feasible duty cycle at `point`, 0.0 where none is.
+fn max_duty_at(polytope: &BeerightsPolytope, point: &ProbePoint) -> f64 {
+    let env = BeeEnvSample {
+        distance_from_hive_m: point[0],
+        o3_ugm3: point[1],
+        aqhi: 0.0,
+        pm25_ugm3: 0.0,
+        emf_vpm: point[2],
+        pesticide_index: 0.0,
+        ambient_temp_c: 0.0,
+    };
+    enforce_bee_rights(&env, 1.0, polytope).duty_cycle(1.0)
+}
+
+/// Half-space a·x + b <= 0, one row of the [`feasible`] simplex tableau.
+type Row = (ParameterVector, f64);
+
+impl BeerightsPolytope {
+    /// Load a site polytope from JSON (`{"constraints": [{"a": [..], "b": ..}]}`)
+    /// and validate it before returning.
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self, PolytopeError> {
+        let polytope: BeerightsPolytope = serde_json::from_reader(reader)?;
+        polytope.validate()?;
+        Ok(polytope)
+    }
+
+    /// Convenience wrapper around `from_json_reader` for a file on disk.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, PolytopeError> {
+        Self::from_json_reader(File::open(path)?)
+    }
+
+    /// Check that the polytope is usable and no looser than
+    /// `default_conservative()` near hives.
+    ///
+    /// Every constraint must be finite, some duty cycle in [0, 1] must be
+    /// feasible, and for each default constraint that only involves hive
+    /// distance and duty cycle, no point of this polytope with positive duty
+    /// may violate it. Environmental coordinates are left free throughout.
+    pub fn validate(&self) -> Result<(), PolytopeError> {
+        let count = self.constraints.len();
+        if count == 0 {
+            return Err(PolytopeError::NoConstraints);
+        }
+        if count > MAX_CONSTRAINTS {
+            return Err(PolytopeError::TooManyConstraints {
+                count,
+                max: MAX_CONSTRAINTS,
+            });
+        }
+        check_rows(&self.constraints)?;
+        if !self.constraints.iter().any(|c| c.a[DUTY] > TOL) {
+            return Err(PolytopeError::DutyUnbounded);
+        }
+
+        let mut rows = duty_bounds();
+        for (index, c) in self.constraints.iter().enumerate() {
+            rows.push((c.a, c.b));
+            if !feasible(rows.clone()) {
+                return Err(PolytopeError::Empty { index });
+            }
+        }
+
+        let default = BeerightsPolytope::default_conservative();
+        for (k, d) in default.constraints.iter().enumerate() {
+            if d.a[1] != 0.0 || d.a[2] != 0.0 {
+                continue;
+            }
+            // Positive duty that violates the default constraint by EPS.
+            let mut probe = rows.clone();
+            probe.push(([0.0, 0.0, 0.0, -1.0], EPS));
+            probe.push((d.a.map(|v| -v), EPS - d.b));
+            if feasible(probe) {
+                return Err(PolytopeError::ExceedsDefault {
+                    default_constraint: k,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// `merge_with_floor_at` over `default_probe_points()`.
+    pub fn merge_with_floor(site: &Self, floor: &Self) -> Result<Self, PolytopeError> {
+        Self::merge_with_floor_at(site, floor, &default_probe_points())
+    }
+
+    /// Combine a site polytope with a protective floor (normally
+    /// `default_conservative()`).
+    ///
+    /// The site file is refused if, at any probe, it alone would allow a
+    /// higher duty cycle than the floor: a site that tries to relax
+    /// protection is a configuration error, not something to silently
+    /// paper over. Otherwise the intersection of both is returned.
+    pub fn merge_with_floor_at(
+        site: &Self,
+        floor: &Self,
+        probes: &[ProbePoint],
+    ) -> Result<Self, PolytopeError> {
+        for (probe, point) in probes.iter().enumerate() {
+            let site_duty = max_duty_at(site, point);
+            let floor_duty = max_duty_at(floor, point);
+            if site_duty > floor_duty + TOL {
+                return Err(PolytopeError::RelaxesFloor {
+                    probe,
+                    point: *point,
+                    site_duty,
+                    floor_duty,
+                });
+            }
+        }
+        Ok(site.intersect(floor))
+    }
+}
+
+/// Per-row checks shared by `BeerightsPolytope::new` and `validate`.
+pub(crate) fn check_rows(constraints: &[LinearConstraint]) -> Result<(), PolytopeError> {
+    for (index, c) in constraints.iter().enumerate() {
+        if !c.b.is_finite() || c.a.iter().any(|v| !v.is_finite()) {
+            return Err(PolytopeError::NonFinite { index });
+        }
+        if c.a.iter().all(|v| *v == 0.0) {
+            return Err(PolytopeError::ZeroNorm { index });
+        }
+    }
+    Ok(())
+}
+
+/// 0 <= duty_cycle <= 1.
+fn duty_bounds() -> Vec<Row> {
+    vec![([0.0, 0.0, 0.0, -1.0], 0.0), ([0.0, 0.0, 0.0, 1.0], -1.0)]
+}
+
+/// Is there a point with every `a·x + b <= 0`? Phase one of the simplex
+/// method with Bland's rule, which cannot cycle. Coordinates are free, so
+/// each is split into x⁺ − x⁻; every row gets a slack and an artificial,
+/// and the rows are feasible iff the artificials can all be driven to 0.
+fn feasible(rows: Vec<Row>) -> bool {
+    let m = rows.len();
+    let slack = 8;
+    let artificial = slack + m;
+    let rhs = artificial + m;
+    let mut t = vec![vec![0.0; rhs + 1]; m];
+    let mut basis: Vec<usize> = (artificial..rhs).collect();
+    let mut scale = 1.0;
+    for (i, (a, b)) in rows.iter().enumerate() {
+        // a·x + s = −b, with the row flipped so the right-hand side is >= 0.
+        let sign = if *b > 0.0 { -1.0 } else { 1.0 };
+        for k in 0..4 {
+            t[i][k] = sign * a[k];
+            t[i][4 + k] = -sign * a[k];
+        }
+        t[i][slack + i] = sign;
+        t[i][artificial + i] = 1.0;
+        t[i][rhs] = -sign * b;
+        scale += t[i][rhs];
+    }
+
+    // Reduced costs of minimising the sum of the artificials.
+    let mut cost = vec![0.0; rhs + 1];
+    for row in &t {
+        for (j, v) in row.iter().enumerate().take(artificial) {
+            cost[j] -= v;
+        }
+        cost[rhs] -= row[rhs];
+    }
+
+    // Artificials never re-enter the basis.
+    while let Some(enter) = (0..artificial).find(|&j| cost[j] < -TOL) {
+        let leave = (0..m).filter(|&i| t[i][enter] > TOL).min_by(|&p, &q| {
+            let (rp, rq) = (t[p][rhs] / t[p][enter], t[q][rhs] / t[q][enter]);
+            rp.total_cmp(&rq).then(basis[p].cmp(&basis[q]))
+        });
+        // Phase one is bounded below by 0, so some row always limits it.
+        let Some(leave) = leave else { break };
+
+        let pivot = t[leave][enter];
+        for v in t[leave].iter_mut() {
+            *v /= pivot;
+        }
+        let pivot_row = t[leave].clone();
+        for (i, row) in t.iter_mut().enumerate() {
+            let f = row[enter];
+            if i != leave && f != 0.0 {
+                for (v, p) in row.iter_mut().zip(&pivot_row) {
+                    *v -= f * p;
+                }
+            }
+        }
+        let f = cost[enter];
+        for (v, p) in cost.iter_mut().zip(&pivot_row) {
+            *v -= f * p;
+        }
+        basis[leave] = enter;
+    }
+    -cost[rhs] <= TOL * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polytope(constraints: &[(ParameterVector, f64)]) -> BeerightsPolytope {
+        BeerightsPolytope {
+            constraints: constraints
+                .iter()
+                .map(|&(a, b)| LinearConstraint { a, b })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn default_round_trips_through_json() {
+        let default = BeerightsPolytope::default_conservative();
+        let json = serde_json::to_string(&default).unwrap();
+        let loaded = BeerightsPolytope::from_json_reader(json.as_bytes()).unwrap();
+        assert_eq!(loaded, default);
+    }
+
+    #[test]
+    fn stricter_site_polytope_loads() {
+        let json = r#"{"constraints": [
+            {"a": [-1.0, 0.0, 0.0, 0.0], "b": 80.0},
+            {"a": [0.0, 1.0, 0.0, 0.0], "b": -60.0},
+            {"a": [0.0, 0.0, 0.0, 1.0], "b": -0.2},
+            {"a": [0.0, 0.0, 0.0, 1.0], "b": -0.2}
+        ]}"#;
+        let site = BeerightsPolytope::from_json_reader(json.as_bytes()).unwrap();
+        assert_eq!(site.constraints.len(), 4);
+    }
+
+    #[test]
+    fn malformed_polytopes_are_rejected() {
+        assert!(matches!(
+            BeerightsPolytope::from_json_reader(r#"{"constraints": []}"#.as_bytes()),
+            Err(PolytopeError::NoConstraints)
+        ));
+        assert!(matches!(
+            BeerightsPolytope::from_json_reader("{".as_bytes()),
+            Err(PolytopeError::Json(_))
+        ));
+        assert!(matches!(
+            BeerightsPolytope::from_path("/nonexistent/polytope.json"),
+            Err(PolytopeError::Io(_))
+        ));
+
+        let nan = polytope(&[
+            ([0.0, 0.0, 0.0, 1.0], -0.3),
+            ([0.0, f64::NAN, 0.0, 0.0], -80.0),
+        ]);
+        assert!(matches!(
+            nan.validate(),
+            Err(PolytopeError::NonFinite { index: 1 })
+        ));
+
+        let no_duty_cap = polytope(&[([-1.0, 0.0, 0.0, 0.0], 50.0)]);
+        assert!(matches!(
+            no_duty_cap.validate(),
+            Err(PolytopeError::DutyUnbounded)
+        ));
+    }
+
+    #[test]
+    fn tightening_site_merges_with_floor() {
+        let floor = BeerightsPolytope::default_conservative();
+        // Wider hive exclusion, lower O3 and duty caps; repeats the EMF row.
+        let site = polytope(&[
+            ([-1.0, 0.0, 0.0, 0.0], 100.0),
+            ([0.0, 1.0, 0.0, 0.0], -60.0),
+            ([0.0, 0.0, 0.0, 1.0], -0.2),
+            ([0.0, 0.0, 1.0, 0.0], -1.0),
+        ]);
+        let merged = BeerightsPolytope::merge_with_floor(&site, &floor).unwrap();
+        assert_eq!(merged.constraints.len(), 7);
+        for point in default_probe_points() {
+            assert!(max_duty_at(&merged, &point) <= max_duty_at(&floor, &point));
+        }
+        assert!((max_duty_at(&merged, &[200.0, 40.0, 0.5]) - 0.2).abs() < 1e-12);
+        assert_eq!(max_duty_at(&merged, &[75.0, 40.0, 0.5]), 0.0);
+    }
+
+    #[test]
+    fn relaxing_site_is_refused_with_probe_point() {
+        let floor = BeerightsPolytope::default_conservative();
+        // Duty cap 0.4 and no O3 limit.
+        let site = polytope(&[([-1.0, 0.0, 0.0, 0.0], 50.0), ([0.0, 0.0, 0.0, 1.0], -0.4)]);
+        let probes = [[200.0, 40.0, 0.5], [200.0, 120.0, 0.5]];
+        match BeerightsPolytope::merge_with_floor_at(&site, &floor, &probes) {
+            Err(PolytopeError::RelaxesFloor {
+                probe,
+                point,
+                site_duty,
+                floor_duty,
+            }) => {
+                assert_eq!(probe, 0);
+                assert_eq!(point, [200.0, 40.0, 0.5]);
+                assert!((site_duty - 0.4).abs() < 1e-12);
+                assert!((floor_duty - 0.3).abs() < 1e-12);
+            }
+            other => panic!("expected RelaxesFloor, got {other:?}"),
+        }
+
+        // Same duty cap as the floor, but no O3 limit: caught at the polluted probe.
+        let site = polytope(&[([-1.0, 0.0, 0.0, 0.0], 50.0), ([0.0, 0.0, 0.0, 1.0], -0.3)]);
+        let err = BeerightsPolytope::merge_with_floor_at(&site, &floor, &probes).unwrap_err();
+        assert!(matches!(err, PolytopeError::RelaxesFloor { probe: 1, .. }));
+    }
+
+    #[test]
+    fn contradictory_constraints_name_the_first_culprit() {
+        // duty <= 0.2, distance >= 60, then duty >= 0.25.
+        let empty = polytope(&[
+            ([0.0, 0.0, 0.0, 1.0], -0.2),
+            ([-1.0, 0.0, 0.0, 0.0], 60.0),
+            ([0.0, 0.0, 0.0, -1.0], 0.25),
+        ]);
+        let err = empty.validate().unwrap_err();
+        assert!(matches!(err, PolytopeError::Empty { index: 2 }));
+        assert_eq!(err.to_string(), "constraint 2: feasible region is empty");
+    }
+
+    #[test]
+    fn looser_than_default_is_refused() {
+        // Duty up to 0.5 anywhere past 50 m.
+        let loose_duty = polytope(&[([-1.0, 0.0, 0.0, 0.0], 50.0), ([0.0, 0.0, 0.0, 1.0], -0.5)]);
+        assert!(matches!(
+            loose_duty.validate(),
+            Err(PolytopeError::ExceedsDefault {
+                default_constraint: 3
+            })
+        ));
+
+        // Default duty cap, but actuation allowed from 20 m.
+        let too_close = polytope(&[([-1.0, 0.0, 0.0, 0.0], 20.0), ([0.0, 0.0, 0.0, 1.0], -0.3)]);
+        assert!(matches!(
+            too_close.validate(),
+            Err(PolytopeError::ExceedsDefault {
+                default_constraint: 0
+            })
+        ));
+
+        // Mixed constraint: duty <= 0.006 * distance caps at 0.3 only from 50 m,
+        // but leaves a little duty inside 50 m.
+        let sloped = polytope(&[([-0.006, 0.0, 0.0, 1.0], 0.0), ([0.0, 0.0, 0.0, 1.0], -0.3)]);
+        assert!(matches!(
+            sloped.validate(),
+            Err(PolytopeError::ExceedsDefault {
+                default_constraint: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn dense_polytope_validates_quickly() {
+        // Faces tangent to a unit ball (per axis scale) around a point well
+        // inside the default, every coefficient non-zero, plus the default's
+        // own distance and duty faces.
+        let centre = [120.0, 40.0, 0.5, 0.1];
+        let scale = [100.0, 50.0, 1.0, 0.2];
+        let mut constraints: Vec<(ParameterVector, f64)> = (0..MAX_CONSTRAINTS - 2)
+            .map(|k| {
+                let k = k as f64;
+                let u = [
+                    (1.3 * k + 0.1).cos(),
+                    (0.7 * k + 0.4).sin(),
+                    (2.1 * k + 0.9).cos(),
+                    (1.7 * k + 1.3).sin(),
+                ];
+                let a: ParameterVector = std::array::from_fn(|i| u[i] / scale[i]);
+                let at_centre: f64 = a.iter().zip(centre).map(|(a, x)| a * x).sum();
+                (a, -at_centre - 1.0)
+            })
+            .collect();
+        constraints.push(([-1.0, 0.0, 0.0, 0.0], 50.0));
+        constraints.push(([0.0, 0.0, 0.0, 1.0], -0.3));
+        assert!(constraints[..MAX_CONSTRAINTS - 2]
+            .iter()
+            .all(|(a, _)| a.iter().all(|v| v.abs() > 1e-6)));
+
+        let started = std::time::Instant::now();
+        polytope(&constraints).validate().unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// One spray or seed-treatment event near a hive, as recorded in a shard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PesticideApplication {
+    pub compound: String,
+    /// Active ingredient applied, g/ha.
+    pub application_rate: f64,
+    pub days_since_application: f64,
+    /// Distance from the treated field to the hive.
+    pub distance_m: f64,
+}
+
+/// Toxicity of one active ingredient to honey bees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompoundToxicity {
+    /// Acute contact LD50, µg per bee. Hazard quotient = rate / LD50.
+    pub ld50_ug_per_bee: f64,
+    /// Dissipation half-life on foliage and forage, days.
+    pub half_life_days: f64,
+}
+
+impl CompoundToxicity {
+    const fn new(ld50_ug_per_bee: f64, half_life_days: f64) -> Self {
+        CompoundToxicity {
+            ld50_ug_per_bee,
+            half_life_days,
+        }
+    }
+}
+
+/// Derives `BeeEnvSample::pesticide_index` from application records.
+///
+/// Each application contributes its hazard quotient, halved every
+/// `half_life_days` and attenuated as exp(-distance / `drift_scale_m`).
+/// Contributions add, and the sum S maps to 1 - exp(-S / `hq_reference`),
+/// so an index of ~0.63 corresponds to the reference quotient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PesticideModel {
+    /// Keyed by lower-case compound name.
+    pub compounds: HashMap<String, CompoundToxicity>,
+    /// Used for compounds missing from the table; deliberately more toxic
+    /// and more persistent than anything listed.
+    pub unknown: CompoundToxicity,
+    pub drift_scale_m: f64,
+    /// Hazard quotient treated as clearly harmful (EPPO's trigger is 50).
+    pub hq_reference: f64,
+}
+
+impl Default for PesticideModel {
+    fn default() -> Self {
+        let compounds = [
+            ("imidacloprid", CompoundToxicity::new(0.081, 30.0)),
+            ("clothianidin", CompoundToxicity::new(0.044, 30.0)),
+            ("thiamethoxam", CompoundToxicity::new(0.024, 25.0)),
+            ("acetamiprid", CompoundToxicity::new(8.09, 3.0)),
+            ("chlorpyrifos", CompoundToxicity::new(0.059, 10.0)),
+            ("lambda-cyhalothrin", CompoundToxicity::new(0.038, 7.0)),
+            ("glyphosate", CompoundToxicity::new(100.0, 15.0)),
+        ]
+        .into_iter()
+        .map(|(name, tox)| (name.to_string(), tox))
+        .collect();
+        PesticideModel {
+            compounds,
+            unknown: CompoundToxicity::new(0.02, 60.0),
+            drift_scale_m: 150.0,
+            hq_reference: 50.0,
+        }
+    }
+}
+
+impl PesticideModel {
+    pub fn toxicity(&self, compound: &str) -> &CompoundToxicity {
+        self.compounds
+            .get(&compound.trim().to_lowercase())
+            .unwrap_or(&self.unknown)
+    }
+
+    /// Hazard quotient one application still carries at the hive.
+    pub fn hazard(&self, app: &PesticideApplication) -> f64 {
+        let tox = self.toxicity(&app.compound);
+        let hq = app.application_rate.max(0.0) / tox.ld50_ug_per_bee;
+        let decay = 0.5_f64.powf(app.days_since_application.max(0.0) / tox.half_life_days);
+        let drift = (-app.distance_m.max(0.0) / self.drift_scale_m).exp();
+        hq * decay * drift
+    }
+
+    /// Pesticide index in [0,1]; no applications means no residue hazard.
+    pub fn pesticide_index(&self, applications: &[PesticideApplication]) -> f64 {
+        let total: f64 = applications.iter().map(|a| self.hazard(a)).sum();
+        if !total.is_finite() {
+            return 1.0;
+        }
+        (1.0 - (-total / self.hq_reference).exp()).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(compound: &str, days: f64, distance_m: f64) -> PesticideApplication {
+        PesticideApplication {
+            compound: compound.into(),
+            application_rate: 10.0,
+            days_since_application: days,
+            distance_m,
+        }
+    }
+
+    #[test]
+    fn residue_halves_every_half_life() {
+        let model = PesticideModel::default();
+        let fresh = model.hazard(&app("Imidacloprid", 0.0, 0.0));
+        let aged = model.hazard(&app("Imidacloprid", 30.0, 0.0));
+        assert!((aged / fresh - 0.5).abs() < 1e-12);
+
+        let index = |days| model.pesticide_index(&[app("imidacloprid", days, 50.0)]);
+        assert!(index(0.0) > index(10.0));
+        assert!(index(10.0) > index(120.0));
+        assert!(index(0.0) <= 1.0 && index(365.0) >= 0.0);
+    }
+
+    #[test]
+    fn drift_attenuates_with_distance() {
+        let model = PesticideModel::default();
+        let near = model.hazard(&app("chlorpyrifos", 0.0, 0.0));
+        let far = model.hazard(&app("chlorpyrifos", 0.0, 150.0));
+        assert!((far / near - (-1.0_f64).exp()).abs() < 1e-12);
+        assert!(
+            model.pesticide_index(&[app("chlorpyrifos", 0.0, 2000.0)])
+                < model.pesticide_index(&[app("chlorpyrifos", 0.0, 100.0)])
+        );
+    }
+
+    #[test]
+    fn overlapping_applications_accumulate() {
+        let model = PesticideModel::default();
+        let one = [app("thiamethoxam", 5.0, 300.0)];
+        let two = [
+            app("thiamethoxam", 5.0, 300.0),
+            app("clothianidin", 2.0, 400.0),
+        ];
+        let a = model.pesticide_index(&one);
+        let b = model.pesticide_index(&two);
+        assert!(b > a && b < 1.0);
+        assert_eq!(model.pesticide_index(&[]), 0.0);
+    }
+
+    #[test]
+    fn unknown_compound_is_not_harmless() {
+        let model = PesticideModel::default();
+        let unknown = model.pesticide_index(&[app("sulfoxaflor-x", 3.0, 200.0)]);
+        let known_worst = model.pesticide_index(&[app("thiamethoxam", 3.0, 200.0)]);
+        assert!(unknown > 0.0);
+        assert!(unknown >= known_worst);
+    }
+
+    #[test]
+    fn applications_deserialize_from_shard_json() {
+        let json = r#"[{"compound": "glyphosate", "application_rate": 720.0,
+                        "days_since_application": 4.0, "distance_m": 80.0}]"#;
+        let apps: Vec<PesticideApplication> = serde_json::from_str(json).unwrap();
+        assert_eq!(apps[0].compound, "glyphosate");
+        let index = PesticideModel::default().pesticide_index(&apps);
+        assert!(index > 0.0 && index < 0.5);
+    }
+}
@@ -0,0 +1,452 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::HazardWeights;
+
+/// Calendar day without a year, written `"MM-DD"`. February 29 is
+/// accepted, so windows can be written for leap years.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MonthDay {
+    month: u8,
+    day: u8,
+}
+
+const DAYS_IN_MONTH: [u8; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+impl MonthDay {
+    pub fn new(month: u8, day: u8) -> Result<Self, ProfileError> {
+        let valid =
+            (1..=12).contains(&month) && day >= 1 && day <= DAYS_IN_MONTH[month as usize - 1];
+        if valid {
+            Ok(MonthDay { month, day })
+        } else {
+            Err(ProfileError::InvalidDate(format!("{month:02}-{day:02}")))
+        }
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// 0-based day of a leap year.
+    fn ordinal(&self) -> u16 {
+        let before: u16 = DAYS_IN_MONTH[..self.month as usize - 1]
+            .iter()
+            .map(|&d| u16::from(d))
+            .sum();
+        before + u16::from(self.day) - 1
+    }
+}
+
+impl fmt::Display for MonthDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+impl TryFrom<String> for MonthDay {
+    type Error = ProfileError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || ProfileError::InvalidDate(s.clone());
+        let (month, day) = s.split_once('-').ok_or_else(invalid)?;
+        let month = month.parse().map_err(|_| invalid())?;
+        let day = day.parse().map_err(|_| invalid())?;
+        MonthDay::new(month, day).map_err(|_| invalid())
+    }
+}
+
+impl From<MonthDay> for String {
+    fn from(md: MonthDay) -> Self {
+        md.to_string()
+    }
+}
+
+/// Days of the year from `start` (inclusive) to `end` (exclusive). A window
+/// whose end comes before its start wraps over the new year, e.g. winter
+/// from `"11-01"` to `"03-01"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeasonWindow {
+    pub start: MonthDay,
+    pub end: MonthDay,
+}
+
+impl SeasonWindow {
+    pub fn contains(&self, date: MonthDay) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date < self.end
+        } else {
+            date >= self.start || date < self.end
+        }
+    }
+
+    /// Days from the most recent `start` up to `date`.
+    fn days_since_start(&self, date: MonthDay) -> u16 {
+        (date.ordinal() + 366 - self.start.ordinal()) % 366
+    }
+}
+
+/// Hazard weights for one region and season.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HazardProfile {
+    pub name: String,
+    /// `/`-separated region path such as `"us/az/phoenix"`; a profile
+    /// applies to its region and everything below it. `None` applies
+    /// everywhere.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// `None` applies all year.
+    #[serde(default)]
+    pub window: Option<SeasonWindow>,
+    /// Fallback when no profile matches. At most one profile may be marked.
+    #[serde(default)]
+    pub default: bool,
+    pub weights: HazardWeights,
+}
+
+impl HazardProfile {
+    /// Number of region segments matched, or `None` if `region` is outside
+    /// this profile's region.
+    fn region_specificity(&self, region: &str) -> Option<usize> {
+        let Some(own) = &self.region else {
+            return Some(0);
+        };
+        let mut query = region.split('/');
+        let mut depth = 0;
+        for segment in own.split('/') {
+            if query.next() != Some(segment) {
+                return None;
+            }
+            depth += 1;
+        }
+        Some(depth)
+    }
+}
+
+/// Why a profile set was refused or no profile could be selected.
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// Not a valid `"MM-DD"` day.
+    InvalidDate(String),
+    DuplicateName(String),
+    /// Start and end are the same day; leave `window` out for all year.
+    EmptyWindow(String),
+    MultipleDefaults {
+        first: String,
+        second: String,
+    },
+    /// No profile covers this region and date, and none is the default.
+    NoMatch {
+        region: String,
+        date: MonthDay,
+    },
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(e) => write!(f, "io error: {e}"),
+            ProfileError::Json(e) => write!(f, "json error: {e}"),
+            ProfileError::InvalidDate(s) => write!(f, "invalid month-day {s:?}, expected MM-DD"),
+            ProfileError::DuplicateName(name) => write!(f, "duplicate profile {name:?}"),
+            ProfileError::EmptyWindow(name) => {
+                write!(
+                    f,
+                    "profile {name:?}: window starts and ends on the same day"
+                )
+            }
+            ProfileError::MultipleDefaults { first, second } => {
+                write!(
+                    f,
+                    "profiles {first:?} and {second:?} are both marked default"
+                )
+            }
+            ProfileError::NoMatch { region, date } => {
+                write!(
+                    f,
+                    "no hazard profile for region {region:?} on {date}, and no default"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(err: std::io::Error) -> Self {
+        ProfileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProfileError {
+    fn from(err: serde_json::Error) -> Self {
+        ProfileError::Json(err)
+    }
+}
+
+/// Named `HazardWeights` for different regions and seasons, so a
+/// deployment can follow e.g. Phoenix summer and winter bee sensitivity
+/// without editing weights at startup.
+///
+/// `select` considers the profiles whose region contains the query region
+/// and whose window contains the date. Among those, the most specific
+/// region wins (most matching segments; a profile without region is least
+/// specific), then the window that started most recently before the date
+/// (an all-year profile counts as starting earliest), then the profile
+/// listed first. With no candidate the default profile is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HazardProfileSet {
+    pub profiles: Vec<HazardProfile>,
+}
+
+impl HazardProfileSet {
+    pub fn new(profiles: Vec<HazardProfile>) -> Result<Self, ProfileError> {
+        let set = HazardProfileSet { profiles };
+        set.validate()?;
+        Ok(set)
+    }
+
+    /// Load `{"profiles": [..]}` and validate it before returning.
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self, ProfileError> {
+        let set: HazardProfileSet = serde_json::from_reader(reader)?;
+        set.validate()?;
+        Ok(set)
+    }
+
+    /// Convenience wrapper around `from_json_reader` for a file on disk.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ProfileError> {
+        Self::from_json_reader(File::open(path)?)
+    }
+
+    /// Names must be unique, windows non-empty and at most one profile
+    /// marked default.
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        let mut default: Option<&str> = None;
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if self.profiles[..i].iter().any(|p| p.name == profile.name) {
+                return Err(ProfileError::DuplicateName(profile.name.clone()));
+            }
+            if profile.window.is_some_and(|w| w.start == w.end) {
+                return Err(ProfileError::EmptyWindow(profile.name.clone()));
+            }
+            if profile.default {
+                if let Some(first) = default {
+                    return Err(ProfileError::MultipleDefaults {
+                        first: first.to_string(),
+                        second: profile.name.clone(),
+                    });
+                }
+                default = Some(&profile.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile in effect for `region` on `date`; see the type docs for
+    /// how overlaps are resolved.
+    pub fn select_profile(
+        &self,
+        region: &str,
+        date: MonthDay,
+    ) -> Result<&HazardProfile, ProfileError> {
+        // Larger ranks higher: more region segments, then fewer days since
+        // the window opened.
+        let mut best: Option<(&HazardProfile, (usize, Reverse<u16>))> = None;
+        for profile in &self.profiles {
+            let Some(specificity) = profile.region_specificity(region) else {
+                continue;
+            };
+            let since_start = match profile.window {
+                Some(window) if !window.contains(date) => continue,
+                Some(window) => window.days_since_start(date),
+                None => u16::MAX,
+            };
+            let rank = (specificity, Reverse(since_start));
+            if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+                best = Some((profile, rank));
+            }
+        }
+        best.map(|(profile, _)| profile)
+            .or_else(|| self.profiles.iter().find(|p| p.default))
+            .ok_or_else(|| ProfileError::NoMatch {
+                region: region.to_string(),
+                date,
+            })
+    }
+
+    /// Weights in effect for `region` on `date`, to pass to `compute_h_bee`.
+    pub fn select(&self, region: &str, date: MonthDay) -> Result<&HazardWeights, ProfileError> {
+        self.select_profile(region, date).map(|p| &p.weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md(s: &str) -> MonthDay {
+        MonthDay::try_from(s.to_string()).unwrap()
+    }
+
+    fn profile(name: &str, region: Option<&str>, window: Option<(&str, &str)>) -> HazardProfile {
+        HazardProfile {
+            name: name.into(),
+            region: region.map(str::to_string),
+            window: window.map(|(start, end)| SeasonWindow {
+                start: md(start),
+                end: md(end),
+            }),
+            default: false,
+            weights: HazardWeights::default(),
+        }
+    }
+
+    fn selected<'a>(set: &'a HazardProfileSet, region: &str, date: &str) -> &'a str {
+        &set.select_profile(region, md(date)).unwrap().name
+    }
+
+    #[test]
+    fn window_start_is_inclusive_and_end_exclusive() {
+        let set = HazardProfileSet::new(vec![
+            profile("summer", None, Some(("05-15", "10-01"))),
+            profile("winter", None, Some(("11-01", "03-01"))),
+        ])
+        .unwrap();
+        assert!(set.select("us/az", md("05-14")).is_err());
+        assert_eq!(selected(&set, "us/az", "05-15"), "summer");
+        assert_eq!(selected(&set, "us/az", "09-30"), "summer");
+        assert!(set.select("us/az", md("10-01")).is_err());
+
+        // Wrapping over the new year.
+        assert_eq!(selected(&set, "us/az", "11-01"), "winter");
+        assert_eq!(selected(&set, "us/az", "01-01"), "winter");
+        assert_eq!(selected(&set, "us/az", "02-29"), "winter");
+        assert!(set.select("us/az", md("03-01")).is_err());
+    }
+
+    #[test]
+    fn overlaps_prefer_specific_region_then_latest_start() {
+        let mut heat = profile(
+            "phoenix-heatwave",
+            Some("us/az/phoenix"),
+            Some(("07-01", "08-15")),
+        );
+        heat.weights.w_thermal = 0.4;
+        let set = HazardProfileSet::new(vec![
+            profile("baseline", None, None),
+            profile("az-summer", Some("us/az"), Some(("05-01", "10-01"))),
+            profile(
+                "phoenix-summer",
+                Some("us/az/phoenix"),
+                Some(("05-15", "10-01")),
+            ),
+            heat,
+            profile(
+                "phoenix-monsoon",
+                Some("us/az/phoenix"),
+                Some(("06-15", "09-30")),
+            ),
+        ])
+        .unwrap();
+
+        // Region beats window: Tucson only gets the state profile.
+        assert_eq!(selected(&set, "us/az/tucson", "07-10"), "az-summer");
+        assert_eq!(
+            selected(&set, "us/az/phoenix/downtown", "05-20"),
+            "phoenix-summer"
+        );
+        // Same region: the window that started last wins.
+        assert_eq!(selected(&set, "us/az/phoenix", "06-20"), "phoenix-monsoon");
+        assert_eq!(selected(&set, "us/az/phoenix", "07-10"), "phoenix-heatwave");
+        assert_eq!(selected(&set, "us/az/phoenix", "08-20"), "phoenix-monsoon");
+        assert_eq!(
+            set.select("us/az/phoenix", md("07-10")).unwrap().w_thermal,
+            0.4
+        );
+        // A region sharing only a name prefix is not inside "us/az".
+        assert_eq!(selected(&set, "us/azx", "07-10"), "baseline");
+        assert_eq!(selected(&set, "us/az", "12-01"), "baseline");
+    }
+
+    #[test]
+    fn no_match_falls_back_to_default_or_errors() {
+        let mut set = HazardProfileSet::new(vec![profile(
+            "phoenix-summer",
+            Some("us/az/phoenix"),
+            Some(("05-15", "10-01")),
+        )])
+        .unwrap();
+        let err = set.select("ca/bc", md("07-01")).unwrap_err();
+        assert!(
+            matches!(err, ProfileError::NoMatch { ref region, date } if region == "ca/bc" && date == md("07-01"))
+        );
+        assert_eq!(
+            err.to_string(),
+            "no hazard profile for region \"ca/bc\" on 07-01, and no default"
+        );
+
+        let mut fallback = profile("conservative", Some("us"), Some(("01-01", "02-01")));
+        fallback.default = true;
+        set.profiles.push(fallback);
+        set.validate().unwrap();
+        assert_eq!(selected(&set, "ca/bc", "07-01"), "conservative");
+    }
+
+    #[test]
+    fn loads_and_validates_json() {
+        let json = r#"{"profiles": [
+            {"name": "default", "default": true, "weights": {
+                "w_poll": 0.5, "w_bio": 0.3, "w_rf": 0.2,
+                "o3_ref_ugm3": 80.0, "aqhi_ref": 7.0, "pm25_ref_ugm3": 25.0,
+                "emf_ref_vpm": 1.0, "w_thermal": 0.0,
+                "temp_comfort_c": 35.0, "temp_ref_c": 45.0}},
+            {"name": "phoenix-summer", "region": "us/az/phoenix",
+             "window": {"start": "05-15", "end": "10-01"}, "weights": {
+                "w_poll": 0.4, "w_bio": 0.2, "w_rf": 0.1,
+                "o3_ref_ugm3": 60.0, "aqhi_ref": 6.0, "pm25_ref_ugm3": 20.0,
+                "emf_ref_vpm": 1.0, "w_thermal": 0.3,
+                "temp_comfort_c": 32.0, "temp_ref_c": 42.0}}
+        ]}"#;
+        let set = HazardProfileSet::from_json_reader(json.as_bytes()).unwrap();
+        assert_eq!(
+            set.select("us/az/phoenix", md("06-01"))
+                .unwrap()
+                .o3_ref_ugm3,
+            60.0
+        );
+        assert!(set
+            .select("us/az/phoenix", md("12-01"))
+            .unwrap()
+            .foraging
+            .is_none());
+
+        let bad_date = json.replace("05-15", "02-30");
+        assert!(matches!(
+            HazardProfileSet::from_json_reader(bad_date.as_bytes()),
+            Err(ProfileError::Json(_))
+        ));
+        let two_defaults = json.replace(r#""region": "us/az/phoenix","#, r#""default": true,"#);
+        assert!(matches!(
+            HazardProfileSet::from_json_reader(two_defaults.as_bytes()),
+            Err(ProfileError::MultipleDefaults { .. })
+        ));
+        let empty = json.replace("10-01", "05-15");
+        assert!(matches!(
+            HazardProfileSet::from_json_reader(empty.as_bytes()),
+            Err(ProfileError::EmptyWindow(name)) if name == "phoenix-summer"
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! Proptest strategies for bee environment samples.
+//!
+//! `any_bee_env_sample` covers the whole value space, NaN, infinities and
+//! negative readings included. `valid_bee_env_sample` keeps readings finite
+//! and non-negative, `pesticide_index` in [0, 1] and temperatures between
+//! -20 and 60 °C.
+
+use proptest::num::f64::ANY as ANY_F64;
+use proptest::prelude::*;
+
+use crate::BeeEnvSample;
+
+fn sample(readings: impl Strategy<Value = [f64; 7]>) -> impl Strategy<Value = BeeEnvSample> {
+    readings.prop_map(
+        |[distance_from_hive_m, o3_ugm3, aqhi, pm25_ugm3, emf_vpm, pesticide_index, ambient_temp_c]| {
+            BeeEnvSample {
+                distance_from_hive_m,
+                o3_ugm3,
+                aqhi,
+                pm25_ugm3,
+                emf_vpm,
+                pesticide_index,
+                ambient_temp_c,
+            }
+        },
+    )
+}
+
+/// Any sample, including ones `checked_h_bee` refuses.
+pub fn any_bee_env_sample() -> impl Strategy<Value = BeeEnvSample> {
+    sample([ANY_F64; 7])
+}
+
+/// A sample a field sensor could report.
+pub fn valid_bee_env_sample() -> impl Strategy<Value = BeeEnvSample> {
+    sample([
+        0.0..=5_000.0f64,
+        0.0..=400.0f64,
+        0.0..=15.0f64,
+        0.0..=300.0f64,
+        0.0..=20.0f64,
+        0.0..=1.0f64,
+        -20.0..=60.0f64,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checked_h_bee, checked_h_bee_at, HazardWeights};
+
+    proptest! {
+        #[test]
+        fn valid_samples_score_in_unit_interval(env in valid_bee_env_sample(), hour in 0u8..24) {
+            let cfg = HazardWeights::default();
+            let h = checked_h_bee(&env, &cfg).unwrap();
+            prop_assert!((0.0..=1.0).contains(&h));
+            let h_at = checked_h_bee_at(&env, &cfg, hour).unwrap();
+            prop_assert!(h_at <= h);
+        }
+
+        #[test]
+        fn any_sample_is_refused_or_in_unit_interval(env in any_bee_env_sample()) {
+            if let Ok(h) = checked_h_bee(&env, &HazardWeights::default()) {
+                prop_assert!((0.0..=1.0).contains(&h));
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Write the partner-facing JSON Schemas into a directory (default
+//! `schemas`):
+//!
+//! ```text
+//! cargo run --example export_schemas --features schema -- schemas
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cyboair_governance::schema::{write_schemas, SCHEMA_VERSION};
+
+fn main() -> ExitCode {
+    let dir = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("schemas"));
+    match write_schemas(&dir) {
+        Ok(written) => {
+            for path in written {
+                println!("{}", path.display());
+            }
+            println!("schema version {SCHEMA_VERSION}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {e}", dir.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,467 @@
+#![forbid(unsafe_code)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use cyboair_corridor_safety::ShardRow;
+
+use crate::audit::now_unix_ms;
+use crate::reason::{ReasonCode, VerdictReason};
+use crate::{Principal, Proposal, Role, Verdict, VerdictState, Verifier};
+
+/// Process-local key for a proposal's directives, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProposalHash(pub u64);
+
+impl ProposalHash {
+    pub fn of(proposal: &Proposal) -> Self {
+        let mut hasher = DefaultHasher::new();
+        for d in &proposal.directives {
+            d.node_id.hash(&mut hasher);
+            d.new_duty_cycle.to_bits().hash(&mut hasher);
+            d.horizon_seconds.hash(&mut hasher);
+            d.ramp_shape.hash(&mut hasher);
+        }
+        ProposalHash(hasher.finish())
+    }
+}
+
+impl fmt::Display for ProposalHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Decides which proposals need a human quorum.
+pub type ImpactPredicate = Box<dyn Fn(&Proposal, &HashMap<String, ShardRow>) -> bool + Send + Sync>;
+
+/// Default classifier: high impact if the summed proposed duty exceeds
+/// `max_aggregate_duty` or any touched node has `bee_flag = 1`. A node
+/// missing from the shard may be a bee node, so it counts as one.
+pub fn duty_or_bee_predicate(max_aggregate_duty: f64) -> ImpactPredicate {
+    Box::new(move |proposal, rows| {
+        let aggregate: f64 = proposal.directives.iter().map(|d| d.new_duty_cycle).sum();
+        let touches_bees = proposal.node_ids().any(|id| match rows.get(id) {
+            Some(row) => row.bee.as_ref().is_some_and(|b| b.bee_flag == 1),
+            None => true,
+        });
+        aggregate > max_aggregate_duty || touches_bees
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalError {
+    UnknownProposal(ProposalHash),
+    RoleNotPermitted { approver: String, role: Role },
+    DuplicateApprover(String),
+    Expired(ProposalHash),
+}
+
+impl fmt::Display for ApprovalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalError::UnknownProposal(h) => write!(f, "no pending proposal {h}"),
+            ApprovalError::RoleNotPermitted { approver, role } => {
+                write!(f, "{approver} ({role:?}) may not approve proposals")
+            }
+            ApprovalError::DuplicateApprover(id) => write!(f, "{id} already approved"),
+            ApprovalError::Expired(h) => write!(f, "approval window for {h} has closed"),
+        }
+    }
+}
+
+impl std::error::Error for ApprovalError {}
+
+struct PendingApproval {
+    opened_at_ms: u64,
+    approvers: BTreeSet<String>,
+}
+
+impl PendingApproval {
+    fn is_expired(&self, ttl_ms: u64, now_ms: u64) -> bool {
+        now_ms >= self.opened_at_ms.saturating_add(ttl_ms)
+    }
+}
+
+/// M-of-N human approval for high-impact proposals.
+///
+/// Only Staff and Superchair principals may approve, each at most once per
+/// proposal. A proposal still short of quorum `ttl_ms` after it was opened
+/// is rejected on finalize; windows nobody finalizes are dropped by
+/// `expire`, which also runs whenever a new window opens.
+pub struct ApprovalTracker {
+    required: usize,
+    ttl_ms: u64,
+    is_high_impact: ImpactPredicate,
+    pending: HashMap<ProposalHash, PendingApproval>,
+}
+
+impl ApprovalTracker {
+    pub fn new(required: usize, ttl_ms: u64, is_high_impact: ImpactPredicate) -> Self {
+        Self {
+            required,
+            ttl_ms,
+            is_high_impact,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn is_high_impact(&self, proposal: &Proposal, rows: &HashMap<String, ShardRow>) -> bool {
+        (self.is_high_impact)(proposal, rows)
+    }
+
+    /// Number of open approval windows, expired ones included until the
+    /// next `expire`.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn expire(&mut self) -> Vec<ProposalHash> {
+        self.expire_at(now_unix_ms())
+    }
+
+    /// Drop every window whose TTL has passed; returns their hashes, in
+    /// order.
+    pub fn expire_at(&mut self, now_ms: u64) -> Vec<ProposalHash> {
+        let mut expired: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.is_expired(self.ttl_ms, now_ms))
+            .map(|(hash, _)| *hash)
+            .collect();
+        expired.sort();
+        for hash in &expired {
+            self.pending.remove(hash);
+        }
+        expired
+    }
+
+    /// Start (or look up) the approval window for `hash`. An expired window
+    /// for the same proposal is replaced by a fresh one.
+    fn open(&mut self, hash: ProposalHash, now_ms: u64) -> VerdictState {
+        self.expire_at(now_ms);
+        let entry = self.pending.entry(hash).or_insert_with(|| PendingApproval {
+            opened_at_ms: now_ms,
+            approvers: BTreeSet::new(),
+        });
+        VerdictState::PendingQuorum {
+            required: self.required,
+            received: entry.approvers.len(),
+        }
+    }
+
+    pub fn approve(
+        &mut self,
+        hash: ProposalHash,
+        approver: &Principal,
+    ) -> Result<usize, ApprovalError> {
+        self.approve_at(hash, approver, now_unix_ms())
+    }
+
+    /// Record `approver`'s approval; returns the approvals received so far.
+    pub fn approve_at(
+        &mut self,
+        hash: ProposalHash,
+        approver: &Principal,
+        now_ms: u64,
+    ) -> Result<usize, ApprovalError> {
+        if !matches!(approver.role, Role::Staff | Role::Superchair) {
+            return Err(ApprovalError::RoleNotPermitted {
+                approver: approver.id.clone(),
+                role: approver.role.clone(),
+            });
+        }
+        let pending = self
+            .pending
+            .get_mut(&hash)
+            .ok_or(ApprovalError::UnknownProposal(hash))?;
+        if pending.is_expired(self.ttl_ms, now_ms) {
+            return Err(ApprovalError::Expired(hash));
+        }
+        if !pending.approvers.insert(approver.id.clone()) {
+            return Err(ApprovalError::DuplicateApprover(approver.id.clone()));
+        }
+        Ok(pending.approvers.len())
+    }
+
+    pub fn finalize(&mut self, hash: ProposalHash) -> Verdict {
+        self.finalize_at(hash, now_unix_ms())
+    }
+
+    /// Approve once quorum is met, reject once the TTL has passed, and
+    /// otherwise report the proposal as still pending. Approved and
+    /// rejected proposals are dropped from the tracker.
+    pub fn finalize_at(&mut self, hash: ProposalHash, now_ms: u64) -> Verdict {
+        let Some(pending) = self.pending.get(&hash) else {
+            return Verdict::reject(vec![VerdictReason::new(
+                ReasonCode::UnknownProposal,
+                ApprovalError::UnknownProposal(hash).to_string(),
+            )]);
+        };
+        let received = pending.approvers.len();
+        if received >= self.required {
+            let approvers: Vec<_> = pending.approvers.iter().cloned().collect();
+            self.pending.remove(&hash);
+            return Verdict::approve(VerdictReason::new(
+                ReasonCode::Passed,
+                format!("quorum reached ({})", approvers.join(", ")),
+            ));
+        }
+        if pending.is_expired(self.ttl_ms, now_ms) {
+            self.pending.remove(&hash);
+            return Verdict::reject(vec![VerdictReason::new(
+                ReasonCode::QuorumNotReached,
+                format!(
+                    "quorum not reached before TTL ({received} of {})",
+                    self.required
+                ),
+            )
+            .with_values(received as f64, self.required as f64)]);
+        }
+        Verdict {
+            approved: false,
+            state: VerdictState::PendingQuorum {
+                required: self.required,
+                received,
+            },
+            reasons: vec![VerdictReason::new(
+                ReasonCode::QuorumPending,
+                format!("awaiting approvals ({received} of {})", self.required),
+            )
+            .with_values(received as f64, self.required as f64)],
+            suggestions: Vec::new(),
+            degraded: None,
+        }
+    }
+}
+
+impl Verifier {
+    /// `verify`, then hold high-impact proposals for human approval.
+    ///
+    /// Proposals that pass and are classified high impact by `tracker` come
+    /// back as `PendingQuorum`; use `ProposalHash::of` to approve and
+    /// finalize them.
+    pub fn verify_with_quorum(
+        proposal: &Proposal,
+        rows: &HashMap<String, ShardRow>,
+        tracker: &mut ApprovalTracker,
+    ) -> Verdict {
+        Self::verify_with_quorum_at(proposal, rows, tracker, now_unix_ms())
+    }
+
+    pub fn verify_with_quorum_at(
+        proposal: &Proposal,
+        rows: &HashMap<String, ShardRow>,
+        tracker: &mut ApprovalTracker,
+        now_ms: u64,
+    ) -> Verdict {
+        let verdict = Self::verify(proposal);
+        if !verdict.approved || !tracker.is_high_impact(proposal, rows) {
+            return verdict;
+        }
+        let hash = ProposalHash::of(proposal);
+        Verdict {
+            approved: false,
+            state: tracker.open(hash, now_ms),
+            reasons: vec![VerdictReason::new(
+                ReasonCode::QuorumPending,
+                format!("high-impact proposal {hash} requires quorum"),
+            )],
+            suggestions: Vec::new(),
+            degraded: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::directives;
+    use cyboair_corridor_safety::{BeeExtension, CorridorRow};
+
+    const TTL_MS: u64 = 60_000;
+
+    fn row(node_id: &str, bee_flag: Option<u8>) -> ShardRow {
+        ShardRow {
+            row: CorridorRow {
+                machine_id: node_id.into(),
+                r#type: "UrbanNanoswarmCanopy".into(),
+                location: "Phoenix-Apiary-1".into(),
+                pollutant: "PM2.5".into(),
+                cin: 30.0,
+                cout: 20.0,
+                unit: "ug/m3".into(),
+                airflow_m3_per_s: 0.8,
+                period_s: 3600.0,
+                lambda_hazard: 3.5,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.93,
+                trace_id: None,
+                shard_version: None,
+            },
+            bee: bee_flag.map(|bee_flag| BeeExtension {
+                bee_flag,
+                bee_weight: 1.5,
+                notes: String::new(),
+            }),
+            calibrated: false,
+            window_start_unix_ms: None,
+        }
+    }
+
+    fn shard() -> HashMap<String, ShardRow> {
+        HashMap::from([
+            ("node_01".to_string(), row("node_01", None)),
+            ("node_02".to_string(), row("node_02", Some(0))),
+            ("node_hive".to_string(), row("node_hive", Some(1))),
+        ])
+    }
+
+    fn staff(id: &str) -> Principal {
+        Principal {
+            id: id.into(),
+            role: Role::Staff,
+            attributes: vec![],
+        }
+    }
+
+    fn tracker() -> ApprovalTracker {
+        ApprovalTracker::new(2, TTL_MS, duty_or_bee_predicate(1.0))
+    }
+
+    fn hive_proposal() -> Proposal {
+        directives(&[("node_hive", 0.4)])
+    }
+
+    #[test]
+    fn low_impact_passes_straight_through() {
+        let mut tracker = tracker();
+        let proposal = directives(&[("node_01", 0.4)]);
+        let verdict = Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 0);
+        assert!(verdict.approved);
+        assert_eq!(verdict.state, VerdictState::Approved);
+    }
+
+    #[test]
+    fn aggregate_duty_and_bee_flag_need_quorum() {
+        let mut tracker = tracker();
+        let busy = directives(&[("node_01", 0.7), ("node_02", 0.6)]);
+        // Without a shard row nothing rules out bees.
+        let unknown = directives(&[("node_unlisted", 0.1)]);
+        for proposal in [busy, hive_proposal(), unknown] {
+            let verdict = Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 0);
+            assert!(!verdict.approved);
+            assert_eq!(
+                verdict.state,
+                VerdictState::PendingQuorum {
+                    required: 2,
+                    received: 0
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn duplicate_and_unprivileged_approvers_are_rejected() {
+        let mut tracker = tracker();
+        let proposal = hive_proposal();
+        Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 0);
+        let hash = ProposalHash::of(&proposal);
+
+        assert_eq!(tracker.approve_at(hash, &staff("ops-a"), 10), Ok(1));
+        assert_eq!(
+            tracker.approve_at(hash, &staff("ops-a"), 20),
+            Err(ApprovalError::DuplicateApprover("ops-a".into()))
+        );
+        let stakeholder = Principal {
+            id: "sh@org.com".into(),
+            role: Role::Stakeholder,
+            attributes: vec![],
+        };
+        assert!(matches!(
+            tracker.approve_at(hash, &stakeholder, 30),
+            Err(ApprovalError::RoleNotPermitted { .. })
+        ));
+        assert_eq!(
+            tracker.finalize_at(hash, 40).state,
+            VerdictState::PendingQuorum {
+                required: 2,
+                received: 1
+            }
+        );
+    }
+
+    #[test]
+    fn quorum_reached_approves() {
+        let mut tracker = tracker();
+        let proposal = hive_proposal();
+        Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 0);
+        let hash = ProposalHash::of(&proposal);
+
+        tracker.approve_at(hash, &staff("ops-a"), 10).unwrap();
+        let chair = Principal {
+            id: "admin@cyboair.org".into(),
+            role: Role::Superchair,
+            attributes: vec![],
+        };
+        assert_eq!(tracker.approve_at(hash, &chair, 20), Ok(2));
+
+        let verdict = tracker.finalize_at(hash, 30);
+        assert!(verdict.approved);
+        assert_eq!(verdict.state, VerdictState::Approved);
+        // Finalized proposals leave the tracker.
+        assert_eq!(tracker.finalize_at(hash, 40).state, VerdictState::Rejected);
+    }
+
+    #[test]
+    fn ttl_expiry_rejects() {
+        let mut tracker = tracker();
+        let proposal = hive_proposal();
+        Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 1_000);
+        let hash = ProposalHash::of(&proposal);
+        tracker.approve_at(hash, &staff("ops-a"), 2_000).unwrap();
+
+        let late = 1_000 + TTL_MS;
+        assert_eq!(
+            tracker.approve_at(hash, &staff("ops-b"), late),
+            Err(ApprovalError::Expired(hash))
+        );
+        let verdict = tracker.finalize_at(hash, late);
+        assert!(!verdict.approved);
+        assert_eq!(verdict.state, VerdictState::Rejected);
+    }
+
+    #[test]
+    fn abandoned_windows_expire() {
+        let mut tracker = tracker();
+        let proposal = hive_proposal();
+        Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, 0);
+        let hash = ProposalHash::of(&proposal);
+        tracker.approve_at(hash, &staff("ops-a"), 10).unwrap();
+        assert_eq!(tracker.expire_at(TTL_MS - 1), vec![]);
+        assert_eq!(tracker.pending_len(), 1);
+
+        // Opening another window sweeps the abandoned one.
+        let other = directives(&[("node_01", 0.6), ("node_02", 0.6)]);
+        Verifier::verify_with_quorum_at(&other, &shard(), &mut tracker, TTL_MS);
+        assert_eq!(tracker.pending_len(), 1);
+        assert_eq!(
+            tracker.approve_at(hash, &staff("ops-b"), TTL_MS),
+            Err(ApprovalError::UnknownProposal(hash))
+        );
+
+        // Resubmitting starts over rather than inheriting stale approvals.
+        let verdict = Verifier::verify_with_quorum_at(&proposal, &shard(), &mut tracker, TTL_MS);
+        assert_eq!(
+            verdict.state,
+            VerdictState::PendingQuorum {
+                required: 2,
+                received: 0
+            }
+        );
+        assert_eq!(tracker.expire_at(3 * TTL_MS).len(), 2);
+        assert_eq!(tracker.pending_len(), 0);
+    }
+}
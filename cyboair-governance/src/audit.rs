@@ -0,0 +1,182 @@
+#![forbid(unsafe_code)]
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use cybo_corridor_core::EscalationAction;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::request::RequestContext;
+use crate::{Action, Role};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AuditDecision {
+    Granted,
+    Denied,
+}
+
+/// Outcome of one policy within an authorization decision.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolicyTrailEntry {
+    pub policy: String,
+    pub granted: bool,
+    pub reason: Option<String>,
+}
+
+/// What was being decided. Escalations the gate refused are a kind of
+/// their own so safety reviews can list suppressed actions directly.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum AuditKind {
+    Access {
+        action: Action,
+    },
+    Escalation {
+        action: EscalationAction,
+    },
+    SuppressedEscalation {
+        action: EscalationAction,
+    },
+    /// Break-glass submission; `review_id` is set once a review is opened.
+    EmergencyOverride {
+        justification: String,
+        review_id: Option<u64>,
+    },
+    /// A Superchair restored the override privilege of the principal in
+    /// `resource_id`, closing its overdue `reviews`.
+    SuspensionLifted {
+        justification: String,
+        reviews: Vec<u64>,
+    },
+}
+
+/// Shard row behind a node the audited proposal referenced.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeTrace {
+    pub node_id: String,
+    /// The row's `trace_id`, as assigned when its shard was loaded.
+    pub trace_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard_version: Option<String>,
+}
+
+/// One authorize() decision, as recorded for compliance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditEntry {
+    pub principal_id: String,
+    pub role: Role,
+    #[serde(flatten)]
+    pub kind: AuditKind,
+    pub resource_id: String,
+    pub decision: AuditDecision,
+    /// Every policy consulted, in evaluation order.
+    pub policy_trail: Vec<PolicyTrailEntry>,
+    /// Inbound request the decision answered, when the caller supplied one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<RequestContext>,
+    /// Traced shard rows of the nodes a proposal referenced, in proposal
+    /// order; nodes whose row has no `trace_id` are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_traces: Vec<NodeTrace>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_unix_ms: u64,
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub(crate) fn now_unix_ms() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `SystemTime::now` panics on wasm32-unknown-unknown; ask the JS host.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub(crate) fn now_unix_ms() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+/// Destination for authorization audit entries.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, entry: AuditEntry);
+}
+
+/// In-memory ring buffer keeping the most recent `capacity` entries.
+pub struct MemoryAuditSink {
+    capacity: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Snapshot of the buffered entries, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, entry: AuditEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// Writes each entry as one JSON line to the wrapped writer.
+pub struct JsonLinesAuditSink<W: Write + Send> {
+    writer: Mutex<W>,
+    write_errors: AtomicUsize,
+}
+
+impl<W: Write + Send> JsonLinesAuditSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            write_errors: AtomicUsize::new(0),
+        }
+    }
+
+    /// Entries that could not be serialized or written.
+    pub fn write_errors(&self) -> usize {
+        self.write_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesAuditSink<W> {
+    fn record(&self, entry: AuditEntry) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let written = serde_json::to_writer(&mut *writer, &entry)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+        if written.is_err() {
+            self.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
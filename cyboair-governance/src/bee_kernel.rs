@@ -0,0 +1,245 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+use async_trait::async_trait;
+use cyboair_bee_karma::{enforce_bee_rights, BeeEnvSample, BeeRightsOutcome, BeerightsPolytope};
+use cyboair_corridor_safety::{BeeGuard, Coordinates, CorridorBundle, NodeState};
+
+use crate::pipeline::{StageResult, VerificationContext, VerificationStage, BEE_VETO_STAGE};
+use crate::reason::{ReasonCode, VerdictReason};
+use crate::Proposal;
+
+/// Mean Earth radius, for hive distances.
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BeeKernelError {
+    /// The bundle lists no hive to measure distances from.
+    NoHives,
+    /// A microspace node has no coordinates in the bundle's topology.
+    MissingCoordinates(String),
+}
+
+impl fmt::Display for BeeKernelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeeKernelError::NoHives => write!(f, "corridor bundle lists no hives"),
+            BeeKernelError::MissingCoordinates(id) => {
+                write!(f, "microspace node {id} has no coordinates")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BeeKernelError {}
+
+/// The bee kernel: a site's bee-rights polytope, enforced on its bee
+/// microspace nodes.
+///
+/// It is both the pipeline's [`BEE_VETO_STAGE`], vetoing directives that
+/// leave the polytope, and the controller's [`BeeGuard`], clamping duties
+/// to the same bound, so what governance approves is what the corridor
+/// runs. Nodes outside the microspace pass untouched.
+#[derive(Debug, Clone)]
+pub struct BeeKernel {
+    polytope: BeerightsPolytope,
+    env: HashMap<String, BeeEnvSample>,
+}
+
+impl BeeKernel {
+    /// A kernel with no microspace nodes yet.
+    pub fn new(polytope: BeerightsPolytope) -> Self {
+        Self {
+            polytope,
+            env: HashMap::new(),
+        }
+    }
+
+    /// Enforce the polytope on `node_id` in `env`.
+    pub fn with_node(mut self, node_id: impl Into<String>, env: BeeEnvSample) -> Self {
+        self.env.insert(node_id.into(), env);
+        self
+    }
+
+    /// Every `microspace` node of `bundle` in `ambient`, at its distance
+    /// from the nearest of the bundle's hives.
+    pub fn from_bundle<I>(
+        polytope: BeerightsPolytope,
+        bundle: &CorridorBundle,
+        microspace: I,
+        ambient: &BeeEnvSample,
+    ) -> Result<Self, BeeKernelError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        if bundle.hives.is_empty() {
+            return Err(BeeKernelError::NoHives);
+        }
+        let mut kernel = Self::new(polytope);
+        for id in microspace {
+            let id = id.into();
+            let Some(at) = bundle.topology.get(&id).and_then(|m| m.coordinates) else {
+                return Err(BeeKernelError::MissingCoordinates(id));
+            };
+            let distance = bundle
+                .hives
+                .iter()
+                .map(|hive| distance_m(at, hive.coordinates))
+                .fold(f64::INFINITY, f64::min);
+            let env = BeeEnvSample {
+                distance_from_hive_m: distance,
+                ..ambient.clone()
+            };
+            kernel.env.insert(id, env);
+        }
+        Ok(kernel)
+    }
+
+    pub fn polytope(&self) -> &BeerightsPolytope {
+        &self.polytope
+    }
+
+    pub fn env(&self, node_id: &str) -> Option<&BeeEnvSample> {
+        self.env.get(node_id)
+    }
+
+    /// Where `duty` on `node_id` leaves the polytope; `None` if it is safe
+    /// or the node is outside the microspace.
+    pub fn veto(&self, node_id: &str, duty: f64) -> Option<BeeRightsOutcome> {
+        let env = self.env.get(node_id)?;
+        match enforce_bee_rights(env, duty, &self.polytope) {
+            BeeRightsOutcome::AlreadySafe => None,
+            outcome => Some(outcome),
+        }
+    }
+}
+
+/// Equirectangular distance; exact enough at corridor scale.
+fn distance_m(from: Coordinates, to: Coordinates) -> f64 {
+    let north = (to.lat_deg - from.lat_deg).to_radians();
+    let east = (to.lon_deg - from.lon_deg).to_radians() * from.lat_deg.to_radians().cos();
+    EARTH_RADIUS_M * north.hypot(east)
+}
+
+impl BeeGuard for BeeKernel {
+    fn check_bee_rights(&self, node: &NodeState, u_new: f64) -> Option<f64> {
+        self.veto(&node.row.machine_id, u_new)
+            .map(|outcome| outcome.duty_cycle(u_new))
+    }
+}
+
+#[async_trait]
+impl VerificationStage for BeeKernel {
+    fn name(&self) -> &str {
+        BEE_VETO_STAGE
+    }
+
+    async fn run(&self, proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+        let reasons: Vec<_> = proposal
+            .directives
+            .iter()
+            .filter_map(|d| {
+                let safe = self
+                    .veto(&d.node_id, d.new_duty_cycle)?
+                    .duty_cycle(d.new_duty_cycle);
+                let reason = VerdictReason::new(
+                    ReasonCode::BeeVeto,
+                    format!(
+                        "node {}: duty {} leaves the bee-rights polytope, at most {safe} allowed",
+                        d.node_id, d.new_duty_cycle
+                    ),
+                );
+                Some(
+                    reason
+                        .for_node(d.node_id.clone())
+                        .with_values(d.new_duty_cycle, safe),
+                )
+            })
+            .collect();
+        if reasons.is_empty() {
+            StageResult::Pass(Vec::new())
+        } else {
+            StageResult::Fail(reasons)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::directives;
+
+    const BUNDLE: &str = include_str!("../tests/fixtures/phoenix_bundle.toml");
+    const GARDEN: &str = "CYB-AIR-GARDEN-07";
+
+    fn ambient() -> BeeEnvSample {
+        BeeEnvSample {
+            distance_from_hive_m: 0.0,
+            o3_ugm3: 40.0,
+            aqhi: 4.0,
+            pm25_ugm3: 12.0,
+            emf_vpm: 0.2,
+            pesticide_index: 0.1,
+            ambient_temp_c: 31.0,
+        }
+    }
+
+    fn kernel() -> BeeKernel {
+        let bundle = CorridorBundle::from_toml(BUNDLE).unwrap();
+        BeeKernel::from_bundle(
+            BeerightsPolytope::default_conservative(),
+            &bundle,
+            [GARDEN],
+            &ambient(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn microspace_nodes_sit_at_their_hive_distance() {
+        // The garden is 0.00086 degrees of longitude west of the hive.
+        let distance = kernel().env(GARDEN).unwrap().distance_from_hive_m;
+        assert!((distance - 80.0).abs() < 1.0, "{distance}");
+
+        let mut bundle = CorridorBundle::from_toml(BUNDLE).unwrap();
+        let polytope = BeerightsPolytope::default_conservative;
+        assert_eq!(
+            BeeKernel::from_bundle(polytope(), &bundle, ["CYB-AIR-NOWHERE"], &ambient())
+                .unwrap_err(),
+            BeeKernelError::MissingCoordinates("CYB-AIR-NOWHERE".into())
+        );
+        bundle.hives.clear();
+        assert_eq!(
+            BeeKernel::from_bundle(polytope(), &bundle, [GARDEN], &ambient()).unwrap_err(),
+            BeeKernelError::NoHives
+        );
+    }
+
+    #[tokio::test]
+    async fn stage_vetoes_what_the_guard_clamps() {
+        let kernel = kernel();
+        let ctx = VerificationContext::default();
+        let safe = kernel.veto(GARDEN, 0.8).unwrap().duty_cycle(0.8);
+        assert!((safe - 0.3).abs() < 1e-9, "{safe}");
+
+        let StageResult::Fail(reasons) = kernel.run(&directives(&[(GARDEN, 0.8)]), &ctx).await
+        else {
+            panic!("expected a bee veto");
+        };
+        assert_eq!(reasons[0].code, ReasonCode::BeeVeto);
+        assert!(matches!(
+            kernel.run(&directives(&[(GARDEN, 0.2)]), &ctx).await,
+            StageResult::Pass(_)
+        ));
+        // Outside the microspace the kernel has no say.
+        assert!(matches!(
+            kernel
+                .run(&directives(&[("CYB-AIR-CANOPY-01", 0.8)]), &ctx)
+                .await,
+            StageResult::Pass(_)
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+#![forbid(unsafe_code)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use gatehouse::AccessDecision;
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, Authorization, Principal, Resource, Role};
+
+/// Lifetimes and capacity of a [`DecisionCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionCacheConfig {
+    /// How long a granted decision is reused, in milliseconds.
+    pub grant_ttl_ms: u64,
+    /// How long a denial is reused, in milliseconds; capped at
+    /// `grant_ttl_ms` so a denial never outlives a grant.
+    pub deny_ttl_ms: u64,
+    /// Most decisions kept; the least recently used is evicted first.
+    /// 0 disables caching.
+    pub max_entries: usize,
+}
+
+impl Default for DecisionCacheConfig {
+    fn default() -> Self {
+        Self {
+            grant_ttl_ms: 60_000,
+            deny_ttl_ms: 5_000,
+            max_entries: 4_096,
+        }
+    }
+}
+
+impl DecisionCacheConfig {
+    /// TTL that applies to `decision`.
+    pub fn ttl_ms(&self, decision: &AccessDecision) -> u64 {
+        match decision {
+            AccessDecision::Granted => self.grant_ttl_ms,
+            AccessDecision::Denied => self.deny_ttl_ms.min(self.grant_ttl_ms),
+        }
+    }
+}
+
+/// Canonical form of everything a policy may read for one request.
+///
+/// Attribute lists are sorted, so the same attributes in a different order
+/// map to the same entry. The full key is compared on lookup, not just its
+/// hash, so a hash collision can never return another request's decision.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecisionKey {
+    principal_id: String,
+    role: Role,
+    principal_attributes: Vec<(String, String)>,
+    action: Action,
+    resource_id: String,
+    owner: Option<String>,
+    resource_attributes: Vec<(String, String)>,
+}
+
+impl DecisionKey {
+    pub fn new(principal: &Principal, action: &Action, resource: &Resource) -> Self {
+        let sorted = |attributes: &[(String, String)]| {
+            let mut attributes = attributes.to_vec();
+            attributes.sort_unstable();
+            attributes
+        };
+        Self {
+            principal_id: principal.id.clone(),
+            role: principal.role.clone(),
+            principal_attributes: sorted(&principal.attributes),
+            action: action.clone(),
+            resource_id: resource.resource_id.clone(),
+            owner: resource.owner.clone(),
+            resource_attributes: sorted(&resource.attributes),
+        }
+    }
+
+    pub fn resource_id(&self) -> &str {
+        &self.resource_id
+    }
+}
+
+#[derive(Debug)]
+struct CachedDecision {
+    authorization: Authorization,
+    expires_at_ms: u64,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<DecisionKey, CachedDecision>,
+    /// `last_used` tick -> key, oldest first.
+    lru: BTreeMap<u64, DecisionKey>,
+    next_tick: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        tick
+    }
+
+    fn remove(&mut self, key: &DecisionKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+        }
+    }
+}
+
+/// Bounded, time-limited store of authorization outcomes.
+///
+/// Times are milliseconds since the Unix epoch, supplied by the caller so
+/// the cache follows whatever clock the `GovernanceCore` reads. Shared by
+/// reference; the state sits behind a mutex.
+#[derive(Debug, Default)]
+pub struct DecisionCache {
+    config: DecisionCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl DecisionCache {
+    pub fn new(config: DecisionCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &DecisionCacheConfig {
+        &self.config
+    }
+
+    /// Live decision for `key`, marking it most recently used. Expired
+    /// entries are dropped on the way.
+    pub fn get(&self, key: &DecisionKey, now_ms: u64) -> Option<Authorization> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let expires_at_ms = state.entries.get(key)?.expires_at_ms;
+        if now_ms >= expires_at_ms {
+            state.remove(key);
+            return None;
+        }
+        let tick = state.tick();
+        let entry = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let authorization = entry.authorization.clone();
+        state.lru.remove(&previous);
+        state.lru.insert(tick, key.clone());
+        Some(authorization)
+    }
+
+    /// Store `authorization` for its decision's TTL from `now_ms`,
+    /// evicting the least recently used entries beyond `max_entries`.
+    pub fn insert(&self, key: DecisionKey, authorization: Authorization, now_ms: u64) {
+        let ttl_ms = self.config.ttl_ms(&authorization.decision);
+        if ttl_ms == 0 || self.config.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.remove(&key);
+        while state.entries.len() >= self.config.max_entries {
+            let Some((_, oldest)) = state.lru.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        let tick = state.tick();
+        state.lru.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            CachedDecision {
+                authorization,
+                expires_at_ms: now_ms.saturating_add(ttl_ms),
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Drop every decision about `resource_id`; returns how many.
+    pub fn invalidate_resource(&self, resource_id: &str) -> usize {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let stale: Vec<_> = state
+            .entries
+            .keys()
+            .filter(|k| k.resource_id == resource_id)
+            .cloned()
+            .collect();
+        for key in &stale {
+            state.remove(key);
+        }
+        stale.len()
+    }
+
+    /// Drop every decision.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.clear();
+        state.lru.clear();
+    }
+
+    /// Entries held, including expired ones not yet looked up.
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration};
+    use gatehouse::{Policy, PolicyEvalResult};
+
+    use crate::clock::ManualClock;
+    use crate::delegation::DelegationGrant;
+    use crate::{GovContext, GovernanceCore};
+
+    const START_MS: i64 = 1_750_000_000_000;
+
+    /// Grants everything and counts how often it was asked.
+    struct CountingPolicy {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Policy<Principal, Resource, Action, GovContext> for CountingPolicy {
+        async fn evaluate_access(
+            &self,
+            _principal: &Principal,
+            _action: &Action,
+            _resource: &Resource,
+            _ctx: &GovContext,
+        ) -> PolicyEvalResult {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            PolicyEvalResult::granted("CountingPolicy", None)
+        }
+
+        fn policy_type(&self) -> String {
+            "CountingPolicy".to_string()
+        }
+    }
+
+    fn cached_core(
+        config: DecisionCacheConfig,
+    ) -> (GovernanceCore, Arc<AtomicUsize>, Arc<ManualClock>) {
+        let start = DateTime::from_timestamp_millis(START_MS).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut core = GovernanceCore::new()
+            .with_clock(clock.clone())
+            .with_decision_cache(config);
+        core.add_policy(CountingPolicy {
+            calls: calls.clone(),
+        });
+        (core, calls, clock)
+    }
+
+    fn principal(id: &str, role: Role) -> Principal {
+        Principal {
+            id: id.into(),
+            role,
+            attributes: vec![],
+        }
+    }
+
+    fn node(id: &str) -> Resource {
+        Resource {
+            resource_id: id.into(),
+            owner: Some("other@org.com".into()),
+            attributes: vec![
+                ("zone".into(), "phoenix".into()),
+                ("visibility".into(), "restricted".into()),
+            ],
+        }
+    }
+
+    async fn propose(core: &GovernanceCore, who: &Principal, resource: &Resource) -> bool {
+        let auth = core
+            .authorize(
+                who,
+                &Action::ProposeControl,
+                resource,
+                &GovContext::default(),
+            )
+            .await;
+        matches!(auth.decision, AccessDecision::Granted)
+    }
+
+    #[tokio::test]
+    async fn hit_skips_policy_evaluation() {
+        let (core, calls, _) = cached_core(DecisionCacheConfig::default());
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        let mut reordered = node("node_07");
+        reordered.attributes.reverse();
+
+        assert!(propose(&core, &staff, &node("node_07")).await);
+        assert!(propose(&core, &staff, &reordered).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A different role or resource is a different request.
+        let superchair = principal("ops@cyboair.org", Role::Superchair);
+        assert!(propose(&core, &superchair, &node("node_07")).await);
+        assert!(propose(&core, &staff, &node("node_08")).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn denials_expire_before_grants() {
+        let config = DecisionCacheConfig {
+            grant_ttl_ms: 60_000,
+            deny_ttl_ms: 5_000,
+            max_entries: 16,
+        };
+        let (core, calls, clock) = cached_core(config);
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        let stakeholder = principal("sh@org.com", Role::Stakeholder);
+
+        assert!(propose(&core, &staff, &node("node_07")).await);
+        assert!(!propose(&core, &stakeholder, &node("node_07")).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        clock.advance(Duration::seconds(5));
+        assert!(propose(&core, &staff, &node("node_07")).await);
+        assert!(!propose(&core, &stakeholder, &node("node_07")).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        clock.advance(Duration::seconds(55));
+        assert!(propose(&core, &staff, &node("node_07")).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn invalidation_forces_re_evaluation() {
+        let (mut core, calls, _) = cached_core(DecisionCacheConfig::default());
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        propose(&core, &staff, &node("node_07")).await;
+        propose(&core, &staff, &node("node_08")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        assert_eq!(core.invalidate_resource("node_07"), 1);
+        propose(&core, &staff, &node("node_07")).await;
+        propose(&core, &staff, &node("node_08")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        core.flush_decision_cache();
+        propose(&core, &staff, &node("node_07")).await;
+        propose(&core, &staff, &node("node_08")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        // Registering a policy changes every outcome.
+        core.add_policy(CountingPolicy {
+            calls: calls.clone(),
+        });
+        propose(&core, &staff, &node("node_07")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 7);
+    }
+
+    #[tokio::test]
+    async fn new_delegation_replaces_a_cached_denial() {
+        let (core, _, _) = cached_core(DecisionCacheConfig::default());
+        let stakeholder = principal("sh@org.com", Role::Stakeholder);
+        // Their own node, so RBAC is the only policy denying them.
+        let own = Resource {
+            owner: Some(stakeholder.id.clone()),
+            ..node("node_07")
+        };
+        assert!(!propose(&core, &stakeholder, &own).await);
+
+        core.register_delegation(DelegationGrant {
+            grantor: principal("ops@cyboair.org", Role::Staff),
+            grantee: stakeholder.id.clone(),
+            resource_id: "node_07".into(),
+            actions: vec![Action::ProposeControl],
+            expires_at: START_MS as u64 + 3_600_000,
+        });
+        assert!(propose(&core, &stakeholder, &own).await);
+        // Delegated grants track expiry and revocation, so are never cached.
+        assert!(core.decision_cache().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_is_evicted() {
+        let config = DecisionCacheConfig {
+            max_entries: 2,
+            ..DecisionCacheConfig::default()
+        };
+        let (core, calls, _) = cached_core(config);
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        propose(&core, &staff, &node("a")).await;
+        propose(&core, &staff, &node("b")).await;
+        propose(&core, &staff, &node("a")).await;
+        propose(&core, &staff, &node("c")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(core.decision_cache().unwrap().len(), 2);
+
+        // "b" was evicted, "a" survived.
+        propose(&core, &staff, &node("a")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        propose(&core, &staff, &node("b")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
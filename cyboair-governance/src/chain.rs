@@ -0,0 +1,194 @@
+#![forbid(unsafe_code)]
+
+//! Governance decisions on a tamper-evident event chain.
+//!
+//! Audit entries, including escalations and suppressed escalations, go
+//! through [`ChainAuditSink`]; pipeline verdicts through
+//! [`VerifierPipeline::run_chained`]. Both take any
+//! [`ChainAppender`], so they can share a `FileEventChain` with the
+//! corridor simulator's duty changes.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use cyboair_corridor_safety::{ChainAppender, ChainError, ChainHead};
+use serde::Serialize;
+
+use crate::audit::{AuditEntry, AuditSink};
+use crate::pipeline::{PipelineReport, VerificationContext, VerifierPipeline};
+use crate::reason::VerdictReason;
+use crate::{Proposal, Verdict};
+
+/// Kind of the entries [`ChainAuditSink`] appends.
+pub const AUDIT: &str = "audit";
+/// Kind of the entries [`VerifierPipeline::run_chained`] appends.
+pub const VERDICT: &str = "verdict";
+/// Kind of a verdict reached with fail-open stages skipped, so replays and
+/// auditors can pick them out without parsing payloads.
+pub const DEGRADED_VERDICT: &str = "degraded_verdict";
+
+/// Payload of a [`VERDICT`] or [`DEGRADED_VERDICT`] entry.
+#[derive(Debug, Serialize)]
+pub struct VerdictEvent<'a> {
+    pub proposal: &'a Proposal,
+    pub verdict: &'a Verdict,
+    pub warnings: &'a [VerdictReason],
+}
+
+/// Appends each audit entry to the wrapped chain.
+pub struct ChainAuditSink<C: ChainAppender + Send> {
+    chain: Mutex<C>,
+    append_errors: AtomicUsize,
+}
+
+impl<C: ChainAppender + Send> ChainAuditSink<C> {
+    pub fn new(chain: C) -> Self {
+        Self {
+            chain: Mutex::new(chain),
+            append_errors: AtomicUsize::new(0),
+        }
+    }
+
+    /// Entries that could not be appended.
+    pub fn append_errors(&self) -> usize {
+        self.append_errors.load(Ordering::Relaxed)
+    }
+
+    /// Run `f` on the chain, e.g. to read its head for anchoring.
+    pub fn with_chain<R>(&self, f: impl FnOnce(&mut C) -> R) -> R {
+        f(&mut self.chain.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn into_inner(self) -> C {
+        self.chain.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<C: ChainAppender + Send> AuditSink for ChainAuditSink<C> {
+    fn record(&self, entry: AuditEntry) {
+        if self
+            .with_chain(|chain| chain.append(AUDIT, &entry))
+            .is_err()
+        {
+            self.append_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl VerifierPipeline {
+    /// `run`, then append the verdict to `chain`, as a
+    /// [`DEGRADED_VERDICT`] if stages were skipped. The report is only
+    /// returned once its verdict is on the chain.
+    pub async fn run_chained<C: ChainAppender>(
+        &self,
+        proposal: &Proposal,
+        ctx: &VerificationContext,
+        chain: &mut C,
+    ) -> Result<(PipelineReport, ChainHead), ChainError> {
+        let report = self.run(proposal, ctx).await;
+        let event = VerdictEvent {
+            proposal,
+            verdict: &report.verdict,
+            warnings: &report.warnings,
+        };
+        let kind = if report.verdict.is_degraded() {
+            DEGRADED_VERDICT
+        } else {
+            VERDICT
+        };
+        let head = chain.append(kind, &event)?;
+        Ok((report, head))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use cybo_corridor_core::EscalationAction;
+    use cyboair_corridor_safety::{verify_chain, EventChain};
+
+    use crate::pipeline::{FailureMode, StageHealth, StageResult, VerificationStage};
+    use crate::{GovernanceCore, Principal, Resource, Role};
+
+    #[tokio::test]
+    async fn escalations_and_verdicts_share_one_chain() {
+        let sink = Arc::new(ChainAuditSink::new(EventChain::new()));
+        let core = GovernanceCore::new_with_sink(sink.clone());
+        let bot = Principal {
+            id: "bot-07".into(),
+            role: Role::Bot,
+            attributes: vec![],
+        };
+        let node = Resource {
+            resource_id: "node_07".into(),
+            owner: None,
+            attributes: vec![],
+        };
+        core.authorize_escalation(&bot, &EscalationAction::TriggerAlert, &node)
+            .await;
+        core.authorize_escalation(&bot, &EscalationAction::DisableActuation, &node)
+            .await;
+        assert_eq!(sink.append_errors(), 0);
+
+        drop(core);
+        let mut chain = Arc::try_unwrap(sink).ok().unwrap().into_inner();
+
+        let proposal = crate::tests::directives(&[("node_07", 1.5)]);
+        let (report, head) = VerifierPipeline::new()
+            .run_chained(&proposal, &VerificationContext::default(), &mut chain)
+            .await
+            .unwrap();
+        assert!(!report.verdict.approved);
+        assert_eq!(head.len, 3);
+
+        assert_eq!(verify_chain(chain.entries()).unwrap(), head);
+        let kinds: Vec<_> = chain.entries().iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, [AUDIT, AUDIT, VERDICT]);
+        assert_eq!(chain.entries()[1].payload["kind"], "SuppressedEscalation");
+        assert_eq!(chain.entries()[2].payload["verdict"]["approved"], false);
+    }
+
+    /// A fail-open stage whose backend is down.
+    struct OfflineStage;
+
+    #[async_trait]
+    impl VerificationStage for OfflineStage {
+        fn name(&self) -> &str {
+            "offline"
+        }
+
+        async fn run(&self, _proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+            unreachable!("not run while unavailable")
+        }
+
+        async fn health(&self, _ctx: &VerificationContext) -> StageHealth {
+            StageHealth::Unavailable("model file missing".into())
+        }
+
+        fn failure_mode(&self) -> FailureMode {
+            FailureMode::FailOpenWithFlag
+        }
+    }
+
+    #[tokio::test]
+    async fn degraded_verdicts_have_their_own_entry_kind() {
+        let mut chain = EventChain::new();
+        let proposal = crate::tests::directives(&[("node_07", 0.5)]);
+        let (report, _) = VerifierPipeline::new()
+            .add_stage(OfflineStage)
+            .run_chained(&proposal, &VerificationContext::default(), &mut chain)
+            .await
+            .unwrap();
+        assert!(report.verdict.approved && report.verdict.is_degraded());
+
+        let entry = &chain.entries()[0];
+        assert_eq!(entry.kind, DEGRADED_VERDICT);
+        assert_eq!(
+            entry.payload["verdict"]["degraded"]["skipped"][0]["stage"],
+            "offline"
+        );
+    }
+}
@@ -0,0 +1,519 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use gatehouse::{Policy, PolicyEvalResult};
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::{Action, GovContext, Principal, RbacPolicy, Resource};
+
+/// Temporary, resource-scoped elevation from a grantor to a grantee.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DelegationGrant {
+    /// Delegating principal; its role must itself permit every action.
+    pub grantor: Principal,
+    /// Principal id receiving the elevation.
+    pub grantee: String,
+    pub resource_id: String,
+    pub actions: Vec<Action>,
+    /// Milliseconds since the Unix epoch; the grant is void from this instant.
+    pub expires_at: u64,
+}
+
+impl DelegationGrant {
+    fn covers(&self, principal: &Principal, action: &Action, resource: &Resource) -> bool {
+        self.grantee == principal.id
+            && self.resource_id == resource.resource_id
+            && self.actions.contains(action)
+    }
+}
+
+/// Shared grant registry; clones see the same grants.
+#[derive(Debug, Clone, Default)]
+pub struct DelegationStore {
+    inner: Arc<RwLock<DelegationState>>,
+}
+
+#[derive(Debug, Default)]
+struct DelegationState {
+    next_id: u64,
+    grants: HashMap<u64, DelegationGrant>,
+}
+
+impl DelegationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a grant and return its id for later revocation.
+    pub fn register(&self, grant: DelegationGrant) -> u64 {
+        let mut state = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let id = state.next_id;
+        state.next_id += 1;
+        state.grants.insert(id, grant);
+        id
+    }
+
+    /// Revoke and drop a grant. Returns false if the id is unknown or
+    /// already gone.
+    pub fn revoke(&self, id: u64) -> bool {
+        let mut state = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        state.grants.remove(&id).is_some()
+    }
+
+    /// Drop every grant void at `now_ms`; returns how many were dropped.
+    pub fn prune(&self, now_ms: u64) -> usize {
+        let mut state = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let before = state.grants.len();
+        state.grants.retain(|_, g| now_ms < g.expires_at);
+        before - state.grants.len()
+    }
+
+    pub fn len(&self) -> usize {
+        let state = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        state.grants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// True if any held grant, live or not, names this principal, action
+    /// and resource.
+    pub fn has_grant_for(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+    ) -> bool {
+        let state = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        state
+            .grants
+            .values()
+            .any(|g| g.covers(principal, action, resource))
+    }
+
+    fn matching(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+    ) -> Vec<DelegationGrant> {
+        let state = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let mut found: Vec<_> = state
+            .grants
+            .iter()
+            .filter(|(_, g)| g.covers(principal, action, resource))
+            .collect();
+        found.sort_by_key(|(id, _)| **id);
+        found.into_iter().map(|(_, g)| g.clone()).collect()
+    }
+}
+
+/// Grants an action when a live delegation covers it.
+///
+/// A grant is live if it has not been revoked, `now < expires_at`, and RBAC
+/// would allow the grantor the same action on the same resource. `now`
+/// comes from the policy's clock, `SystemClock` unless set with
+/// `with_clock`. `GovernanceCore` holds grantors to its full policy set
+/// instead of RBAC alone; see [`DelegationPolicy::unexpired`].
+pub struct DelegationPolicy {
+    store: DelegationStore,
+    clock: Arc<dyn Clock>,
+}
+
+impl DelegationPolicy {
+    pub fn new(store: DelegationStore) -> Self {
+        Self::with_clock(store, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(store: DelegationStore, clock: Arc<dyn Clock>) -> Self {
+        Self { store, clock }
+    }
+
+    pub fn now_unix_ms(&self) -> u64 {
+        self.clock.now_unix_ms()
+    }
+
+    /// Unexpired grants covering the request at `now_ms`, oldest first,
+    /// or why there are none. Grantors are not checked.
+    pub fn unexpired(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+        now_ms: u64,
+    ) -> Result<Vec<DelegationGrant>, String> {
+        let mut last_denial = "no delegation grant".to_string();
+        let mut live = Vec::new();
+        for grant in self.store.matching(principal, action, resource) {
+            if now_ms >= grant.expires_at {
+                last_denial = format!("grant from {} expired", grant.grantor.id);
+            } else {
+                live.push(grant);
+            }
+        }
+        if live.is_empty() {
+            Err(last_denial)
+        } else {
+            Ok(live)
+        }
+    }
+
+    /// Evaluate at an explicit instant (milliseconds since the Unix epoch).
+    pub async fn evaluate_at(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+        ctx: &GovContext,
+        now_ms: u64,
+    ) -> PolicyEvalResult {
+        let grants = match self.unexpired(principal, action, resource, now_ms) {
+            Ok(grants) => grants,
+            Err(reason) => return PolicyEvalResult::denied("DelegationPolicy", reason),
+        };
+        let mut last_denial = String::new();
+        for grant in grants {
+            let grantor_allowed = RbacPolicy
+                .evaluate_access(&grant.grantor, action, resource, ctx)
+                .await
+                .is_granted();
+            if grantor_allowed {
+                return delegated_by(&grant);
+            }
+            last_denial = grantor_lacks(&grant, action);
+        }
+        PolicyEvalResult::denied("DelegationPolicy", last_denial)
+    }
+}
+
+pub(crate) fn delegated_by(grant: &DelegationGrant) -> PolicyEvalResult {
+    PolicyEvalResult::granted(
+        "DelegationPolicy",
+        Some(format!(
+            "delegated by {} until {}",
+            grant.grantor.id, grant.expires_at
+        )),
+    )
+}
+
+pub(crate) fn grantor_lacks(grant: &DelegationGrant, action: &Action) -> String {
+    format!("grantor {} lacks {:?}", grant.grantor.id, action)
+}
+
+#[async_trait]
+impl Policy<Principal, Resource, Action, GovContext> for DelegationPolicy {
+    async fn evaluate_access(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+        ctx: &GovContext,
+    ) -> PolicyEvalResult {
+        let now_ms = self.now_unix_ms();
+        self.evaluate_at(principal, action, resource, ctx, now_ms)
+            .await
+    }
+
+    fn policy_type(&self) -> String {
+        "DelegationPolicy".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::now_unix_ms;
+    use crate::{GovernanceCore, Role};
+    use gatehouse::AccessDecision;
+
+    fn principal(id: &str, role: Role) -> Principal {
+        Principal {
+            id: id.into(),
+            role,
+            attributes: vec![],
+        }
+    }
+
+    /// Owned by the grantee, so only RBAC denies them ProposeControl.
+    fn node(id: &str) -> Resource {
+        Resource {
+            resource_id: id.into(),
+            owner: Some("sh@org.com".into()),
+            attributes: vec![],
+        }
+    }
+
+    fn grant(grantor: Principal, resource_id: &str, expires_at: u64) -> DelegationGrant {
+        DelegationGrant {
+            grantor,
+            grantee: "sh@org.com".into(),
+            resource_id: resource_id.into(),
+            actions: vec![Action::ProposeControl],
+            expires_at,
+        }
+    }
+
+    fn in_one_hour() -> u64 {
+        now_unix_ms() + 3_600_000
+    }
+
+    async fn propose(core: &GovernanceCore, resource: &Resource) -> crate::Authorization {
+        let grantee = principal("sh@org.com", Role::Stakeholder);
+        core.authorize(
+            &grantee,
+            &Action::ProposeControl,
+            resource,
+            &GovContext::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn live_grant_elevates_only_named_node() {
+        let core = GovernanceCore::new();
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        core.register_delegation(grant(staff, "node_07", in_one_hour()));
+
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Granted));
+        let last = auth.trail.last().unwrap();
+        assert_eq!(last.policy, "DelegationPolicy");
+        assert!(last.granted);
+
+        // Resource mismatch: no grant names node_08, so nothing is elevated.
+        let auth = propose(&core, &node("node_08")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert!(auth.trail.iter().all(|p| p.policy != "DelegationPolicy"));
+
+        // Nor does it elevate anyone else on the named node.
+        let other = principal("sh2@org.com", Role::Stakeholder);
+        let theirs = Resource {
+            owner: Some(other.id.clone()),
+            ..node("node_07")
+        };
+        let auth = core
+            .authorize(
+                &other,
+                &Action::ProposeControl,
+                &theirs,
+                &GovContext::default(),
+            )
+            .await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert!(auth.trail.iter().all(|p| p.policy != "DelegationPolicy"));
+    }
+
+    #[tokio::test]
+    async fn expired_grant_is_denied() {
+        let core = GovernanceCore::new();
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        core.register_delegation(grant(staff.clone(), "node_07", now_unix_ms() - 1));
+
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        let last = auth.trail.last().unwrap();
+        assert!(!last.granted);
+        assert_eq!(
+            last.reason.as_deref(),
+            Some("grant from ops@cyboair.org expired")
+        );
+
+        // Boundary: a grant is void at exactly expires_at.
+        let store = DelegationStore::new();
+        store.register(grant(staff, "node_07", 1_000));
+        let policy = DelegationPolicy::new(store);
+        let grantee = principal("sh@org.com", Role::Stakeholder);
+        let node_07 = node("node_07");
+        let ctx = GovContext::default();
+        let at = |t| policy.evaluate_at(&grantee, &Action::ProposeControl, &node_07, &ctx, t);
+        assert!(at(999).await.is_granted());
+        assert!(!at(1_000).await.is_granted());
+    }
+
+    #[tokio::test]
+    async fn revoked_grant_is_denied() {
+        let core = GovernanceCore::new();
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        let id = core.register_delegation(grant(staff, "node_07", in_one_hour()));
+        assert!(matches!(
+            propose(&core, &node("node_07")).await.decision,
+            AccessDecision::Granted
+        ));
+
+        assert!(core.revoke_delegation(id));
+        assert!(!core.revoke_delegation(id));
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert!(auth.trail.iter().all(|p| p.policy != "DelegationPolicy"));
+    }
+
+    #[tokio::test]
+    async fn delegation_lifts_only_rbac_denials() {
+        use crate::CombinationStrategy;
+
+        /// Denies everything on resources tagged "frozen=true".
+        struct FreezePolicy;
+
+        #[async_trait]
+        impl Policy<Principal, Resource, Action, GovContext> for FreezePolicy {
+            async fn evaluate_access(
+                &self,
+                _principal: &Principal,
+                _action: &Action,
+                resource: &Resource,
+                _ctx: &GovContext,
+            ) -> PolicyEvalResult {
+                if resource
+                    .attributes
+                    .iter()
+                    .any(|(k, v)| k == "frozen" && v == "true")
+                {
+                    PolicyEvalResult::denied("FreezePolicy", "resource frozen")
+                } else {
+                    PolicyEvalResult::granted("FreezePolicy", None)
+                }
+            }
+
+            fn policy_type(&self) -> String {
+                "FreezePolicy".to_string()
+            }
+        }
+
+        let mut core = GovernanceCore::new().with_strategy(CombinationStrategy::DenyOverrides);
+        core.add_policy(FreezePolicy);
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        core.register_delegation(grant(staff, "node_07", in_one_hour()));
+        let frozen = Resource {
+            attributes: vec![("frozen".into(), "true".into())],
+            ..node("node_07")
+        };
+
+        let auth = propose(&core, &frozen).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        let outcome: Vec<_> = auth
+            .trail
+            .iter()
+            .map(|p| (p.policy.as_str(), p.granted))
+            .collect();
+        assert_eq!(
+            outcome,
+            [
+                ("RbacPolicy", false),
+                ("AbacPolicy", true),
+                ("FreezePolicy", false)
+            ]
+        );
+
+        // Unfrozen, RBAC is the only denial and the grant lifts it.
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Granted));
+    }
+
+    #[tokio::test]
+    async fn grantor_authority_is_rechecked_at_use() {
+        /// Denies every principal listed as suspended.
+        struct SuspensionPolicy(Arc<RwLock<Vec<String>>>);
+
+        #[async_trait]
+        impl Policy<Principal, Resource, Action, GovContext> for SuspensionPolicy {
+            async fn evaluate_access(
+                &self,
+                principal: &Principal,
+                _action: &Action,
+                _resource: &Resource,
+                _ctx: &GovContext,
+            ) -> PolicyEvalResult {
+                if self.0.read().unwrap().contains(&principal.id) {
+                    PolicyEvalResult::denied("SuspensionPolicy", "principal suspended")
+                } else {
+                    PolicyEvalResult::granted("SuspensionPolicy", None)
+                }
+            }
+
+            fn policy_type(&self) -> String {
+                "SuspensionPolicy".to_string()
+            }
+        }
+
+        let suspended = Arc::new(RwLock::new(Vec::new()));
+        let mut core = GovernanceCore::new();
+        core.add_policy(SuspensionPolicy(suspended.clone()));
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        core.register_delegation(grant(staff, "node_07", in_one_hour()));
+        assert!(matches!(
+            propose(&core, &node("node_07")).await.decision,
+            AccessDecision::Granted
+        ));
+
+        // RBAC still grants the grantor, but it has lost its authority.
+        suspended.write().unwrap().push("ops@cyboair.org".into());
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(
+            auth.trail.last().unwrap().reason.as_deref(),
+            Some("grantor ops@cyboair.org lacks ProposeControl")
+        );
+    }
+
+    #[tokio::test]
+    async fn grantor_cannot_delegate_what_it_lacks() {
+        let core = GovernanceCore::new();
+        // RBAC denies ProposeControl to bots, so a bot cannot hand it out.
+        let bot = principal("bot-07", Role::Bot);
+        core.register_delegation(grant(bot, "node_07", in_one_hour()));
+
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(
+            auth.trail.last().unwrap().reason.as_deref(),
+            Some("grantor bot-07 lacks ProposeControl")
+        );
+    }
+
+    #[tokio::test]
+    async fn grant_expires_when_the_manual_clock_reaches_it() {
+        use crate::audit::MemoryAuditSink;
+        use crate::clock::ManualClock;
+        use chrono::{DateTime, Duration};
+
+        let start = DateTime::from_timestamp_millis(1_750_000_000_000).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let sink = Arc::new(MemoryAuditSink::new(8));
+        let core = GovernanceCore::new_with_sink(sink.clone()).with_clock(clock.clone());
+        let staff = principal("ops@cyboair.org", Role::Staff);
+        core.register_delegation(grant(staff, "node_07", 1_750_000_000_000 + 3_600_000));
+
+        assert!(matches!(
+            propose(&core, &node("node_07")).await.decision,
+            AccessDecision::Granted
+        ));
+        clock.advance(Duration::minutes(59));
+        assert!(matches!(
+            propose(&core, &node("node_07")).await.decision,
+            AccessDecision::Granted
+        ));
+        clock.advance(Duration::minutes(1));
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(
+            auth.trail.last().unwrap().reason.as_deref(),
+            Some("grant from ops@cyboair.org expired")
+        );
+
+        let stamps: Vec<_> = sink.entries().iter().map(|e| e.timestamp_unix_ms).collect();
+        assert_eq!(
+            stamps,
+            [1_750_000_000_000, 1_750_003_540_000, 1_750_003_600_000]
+        );
+
+        // The expired grant was pruned once reported.
+        let auth = propose(&core, &node("node_07")).await;
+        assert!(auth.trail.iter().all(|p| p.policy != "DelegationPolicy"));
+    }
+}
@@ -0,0 +1,542 @@
+#![forbid(unsafe_code)]
+
+//! Predicted effect of a proposal on its corridor, for operators to review
+//! before approving.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use cyboair_corridor_safety::{
+    compute_karma_bytes, duty_at, AltitudeProvider, ConfigError, ControllerConfig, CorridorRow,
+    EcoBand, EcoBandClassifier, NodeState, OperationalMode,
+};
+
+use crate::guards::{InputGuard, RampShape};
+use crate::{project_ceim, Proposal, Verifier};
+
+/// Eco-load weights a_M, a_K used for the prediction.
+const ALPHA_M: f64 = 0.5;
+const ALPHA_K: f64 = 0.5;
+
+/// Intervals the longest horizon is cut into when sampling ramps; the
+/// interior points are checked.
+const RAMP_SAMPLES: u32 = 4;
+
+/// One corridor node as `Verifier::dry_run` sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ShardNode {
+    /// Airflow at full duty, as in `Verifier::verify_with_shard`.
+    pub row: CorridorRow,
+    /// Duty the node runs at now.
+    pub duty_cycle: f64,
+    /// Draw at full duty; scales linearly with duty.
+    pub rated_power_w: f64,
+    pub geo_weight: f64,
+}
+
+/// One node over one horizon step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodePrediction {
+    /// Duty held through the step.
+    pub duty_cycle: f64,
+    /// Duty the corridor controller would move to next; `None` when a
+    /// failed check makes the duty law incomputable.
+    pub next_duty_cycle: Option<f64>,
+    /// CEIM mass removed over the row's period; `None` if the projection
+    /// failed.
+    pub mass_kg: Option<f64>,
+    pub karma_bytes: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeDiff {
+    pub node_id: String,
+    /// Under current duties.
+    pub before: NodePrediction,
+    /// Under the proposed duties.
+    pub after: NodePrediction,
+}
+
+/// Corridor aggregates over one horizon step. Nodes whose projection
+/// failed are left out; the load, band and flux are `None` when the
+/// controller rejects the corridor as a whole.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CorridorPrediction {
+    pub mass_kg: f64,
+    pub karma_bytes: f64,
+    pub eco_load: Option<f64>,
+    pub band: Option<EcoBand>,
+    /// DW flux density, kg m^-2 s^-1.
+    pub phi_dw: Option<f64>,
+}
+
+/// A check the proposal would fail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FailedCheck {
+    pub node_id: Option<String>,
+    /// `"proposal"` for directive checks, `"ceim"` for projection failures,
+    /// otherwise the corridor controller's `SafetyError::kind`.
+    pub check: String,
+    pub message: String,
+    /// Seconds into the ramps, for a check failing only part way; `None`
+    /// for the proposed duties.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_s: Option<f64>,
+}
+
+/// Before/after comparison returned by `Verifier::dry_run`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProposalDiff {
+    /// Every corridor node, by node id.
+    pub nodes: Vec<NodeDiff>,
+    pub before: CorridorPrediction,
+    pub after: CorridorPrediction,
+    /// Checks failing under the proposed duties, in node order.
+    pub failed_checks: Vec<FailedCheck>,
+}
+
+impl ProposalDiff {
+    pub fn band_changed(&self) -> bool {
+        self.before.band != self.after.band
+    }
+
+    pub fn node(&self, node_id: &str) -> Option<&NodeDiff> {
+        self.nodes.iter().find(|n| n.node_id == node_id)
+    }
+}
+
+impl Verifier {
+    /// Predict what `proposal` would do to the corridor in `nodes`.
+    ///
+    /// Runs one corridor-controller step, built from `config` with
+    /// `altitude`, twice: under current duties and with each directive's
+    /// `new_duty_cycle` applied. The step also runs at interior points
+    /// along the ramps, the longest horizon cut into `RAMP_SAMPLES`, with
+    /// each node at its `duty_at` duty (a directive without a shape is a
+    /// step); a check failing only there is listed once, at the earliest
+    /// such point. Per node, mass and karma come from the
+    /// CEIM projection at `temperature_k`, as in `verify_with_shard`, with
+    /// airflow at each duty under `config.airflow` (linear if unset); the
+    /// corridor eco-load weighs mass and karma equally. Nothing is recorded
+    /// or mutated; the controller runs without a metrics recorder. Errors
+    /// only if `config` is invalid.
+    pub fn dry_run<A: AltitudeProvider>(
+        proposal: &Proposal,
+        nodes: &HashMap<String, ShardNode>,
+        config: &ControllerConfig,
+        altitude: A,
+        temperature_k: f64,
+    ) -> Result<ProposalDiff, ConfigError> {
+        let controller = config.build(altitude)?;
+        let mut failed_checks: Vec<_> = InputGuard::control_proposal_errors(proposal)
+            .into_iter()
+            .map(|e| FailedCheck {
+                node_id: e.node_id().map(str::to_string),
+                check: "proposal".into(),
+                message: e.to_string(),
+                at_s: None,
+            })
+            .collect();
+        for node_id in proposal.node_ids() {
+            if !node_id.is_empty() && !nodes.contains_key(node_id) {
+                failed_checks.push(FailedCheck {
+                    node_id: Some(node_id.to_string()),
+                    check: "proposal".into(),
+                    message: format!("no shard row for node {node_id}"),
+                    at_s: None,
+                });
+            }
+        }
+
+        let sorted: BTreeMap<_, _> = nodes.iter().collect();
+        let current: Vec<_> = sorted.values().map(|n| n.duty_cycle).collect();
+        let directives: Vec<_> = sorted
+            .keys()
+            .map(|id| proposal.directives.iter().find(|d| &d.node_id == *id))
+            .collect();
+        let duties_at = |t_s: f64| -> Vec<f64> {
+            current
+                .iter()
+                .zip(&directives)
+                .map(|(&from, d)| match d {
+                    Some(d) => duty_at(
+                        t_s,
+                        from,
+                        d.new_duty_cycle,
+                        d.horizon_seconds as f64,
+                        d.ramp_shape.unwrap_or(RampShape::Step),
+                    ),
+                    None => from,
+                })
+                .collect()
+        };
+        let horizon_s = directives
+            .iter()
+            .flatten()
+            .map(|d| d.horizon_seconds)
+            .max()
+            .unwrap_or(0) as f64;
+        let proposed = duties_at(horizon_s);
+
+        let airflow = config.airflow.clone().unwrap_or_default();
+        let predict = |duties: &[f64]| {
+            let mut failures = Vec::new();
+            let mut states = Vec::with_capacity(duties.len());
+            let mut predictions = Vec::with_capacity(duties.len());
+            for ((node_id, node), &duty) in sorted.iter().zip(duties) {
+                let state = match project_ceim(
+                    &node.row,
+                    duty,
+                    airflow.for_row(&node.row),
+                    temperature_k,
+                ) {
+                    Ok((row, mass_kg)) => Some(NodeState {
+                        karma_bytes: compute_karma_bytes(&row, mass_kg),
+                        row,
+                        mass_kg,
+                        duty_cycle: duty,
+                        power_w: node.rated_power_w * duty,
+                        geo_weight: node.geo_weight,
+                        noise_db: None,
+                        emf_vpm: None,
+                        suspicion: None,
+                        mode: OperationalMode::Active,
+                    }),
+                    Err(e) => {
+                        failures.push(FailedCheck {
+                            node_id: Some(node_id.to_string()),
+                            check: "ceim".into(),
+                            message: format!("node {node_id}: CEIM projection failed: {e}"),
+                            at_s: None,
+                        });
+                        None
+                    }
+                };
+                predictions.push(NodePrediction {
+                    duty_cycle: duty,
+                    next_duty_cycle: None,
+                    mass_kg: state.as_ref().map(|s| s.mass_kg),
+                    karma_bytes: state.as_ref().map(|s| s.karma_bytes),
+                });
+                states.extend(state.map(|s| (predictions.len() - 1, s)));
+            }
+
+            let corridor: Vec<_> = states.iter().map(|(_, s)| s.clone()).collect();
+            let mut safety_error = |e: cyboair_corridor_safety::SafetyError| {
+                failures.push(FailedCheck {
+                    node_id: None,
+                    check: e.kind().into(),
+                    message: e.to_string(),
+                    at_s: None,
+                });
+            };
+            let eco_load = controller
+                .eco_load_with_offset(&corridor, ALPHA_M, ALPHA_K, 0.0)
+                .map(|b| b.net)
+                .map_err(&mut safety_error)
+                .ok();
+            let phi_dw = controller
+                .dw_flux_density(&corridor)
+                .map_err(&mut safety_error)
+                .ok();
+            let band = eco_load.map(|load| controller.eco_band.classify(load));
+
+            if let (Some(band), Some(phi_dw)) = (band, phi_dw) {
+                for (i, state) in &states {
+                    let assessment = controller.dry_run_node(state, band, phi_dw);
+                    predictions[*i].next_duty_cycle =
+                        assessment.projected.as_ref().map(|r| r.duty_after);
+                    failures.extend(assessment.violations.iter().map(|e| FailedCheck {
+                        node_id: Some(assessment.machine_id.clone()),
+                        check: e.kind().into(),
+                        message: e.to_string(),
+                        at_s: None,
+                    }));
+                }
+            }
+            let prediction = CorridorPrediction {
+                mass_kg: corridor.iter().map(|s| s.mass_kg).sum(),
+                karma_bytes: corridor.iter().map(|s| s.karma_bytes).sum(),
+                eco_load,
+                band,
+                phi_dw,
+            };
+            (predictions, prediction, failures)
+        };
+
+        let (nodes_before, before, _) = predict(&current);
+        let (nodes_after, after, failures) = predict(&proposed);
+        failed_checks.extend(failures);
+        if horizon_s > 0.0 {
+            for k in 1..RAMP_SAMPLES {
+                let t_s = horizon_s * f64::from(k) / f64::from(RAMP_SAMPLES);
+                let (_, _, failures) = predict(&duties_at(t_s));
+                for mut failure in failures {
+                    let known = failed_checks
+                        .iter()
+                        .any(|c| c.node_id == failure.node_id && c.check == failure.check);
+                    if !known {
+                        failure.message = format!("at {t_s} s into the ramp: {}", failure.message);
+                        failure.at_s = Some(t_s);
+                        failed_checks.push(failure);
+                    }
+                }
+            }
+        }
+        Ok(ProposalDiff {
+            nodes: sorted
+                .keys()
+                .zip(nodes_before.into_iter().zip(nodes_after))
+                .map(|(node_id, (before, after))| NodeDiff {
+                    node_id: node_id.to_string(),
+                    before,
+                    after,
+                })
+                .collect(),
+            before,
+            after,
+            failed_checks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::directives;
+    use cyboair_corridor_safety::ConstAltitude;
+
+    const CONFIG: &str = r#"
+        corridor_area_m2 = 10.0
+        m_ref_kg = 1.0e-4
+        k_ref_nb = 1.0e6
+
+        [gains]
+        eta_m = 0.1
+        eta_k = 0.1
+        eta_w = 0.2
+        eta_b = 0.2
+        eta_p = 0.05
+        eta_dw = 0.1
+
+        [envelope]
+        u_min = 0.0
+        u_max = 1.0
+        z_min_m = 5.0
+        z_max_m = 600.0
+        ecoimpact_min = 0.7
+        ecoimpact_max = 1.0
+
+        [host_budget]
+        p_max_w = 150.0
+        e_step_max_j = 1.0e5
+        step_dt_s = 300.0
+
+        [eco_band]
+        theta_green_amber = 0.5
+        theta_amber_red = 1.0
+        gain_green = 0.0
+        gain_amber = 0.2
+        gain_red = 0.5
+
+        [dw_ceiling]
+        phi_dw_max = 1.0e-6
+    "#;
+
+    fn node(id: &str, cin: f64, cout: f64, airflow: f64, period: f64) -> ShardNode {
+        ShardNode {
+            row: CorridorRow {
+                machine_id: id.into(),
+                r#type: "UrbanNanoswarmCanopy".into(),
+                location: "Phoenix-Intersection-A".into(),
+                pollutant: "PM2.5".into(),
+                cin,
+                cout,
+                unit: "ugm3".into(),
+                airflow_m3_per_s: airflow,
+                period_s: period,
+                lambda_hazard: 3.0,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.92,
+                trace_id: None,
+                shard_version: None,
+            },
+            duty_cycle: 0.2,
+            rated_power_w: 100.0,
+            geo_weight: 1.0,
+        }
+    }
+
+    /// Full duty removes 1.296e-4 kg at the canopy, 3.24e-5 kg at the school.
+    fn corridor() -> HashMap<String, ShardNode> {
+        [
+            node("CYB-AIR-CANOPY-01", 40.0, 28.0, 3.0, 3600.0),
+            node("CYB-AIR-SCHOOL-05", 30.0, 18.0, 1.0, 2700.0),
+        ]
+        .into_iter()
+        .map(|n| (n.row.machine_id.clone(), n))
+        .collect()
+    }
+
+    fn dry_run(proposal: &Proposal, nodes: &HashMap<String, ShardNode>) -> ProposalDiff {
+        let config = ControllerConfig::from_toml(CONFIG).unwrap();
+        Verifier::dry_run(proposal, nodes, &config, ConstAltitude(331.0), 310.0).unwrap()
+    }
+
+    #[test]
+    fn raising_canopy_duty_flips_the_corridor_to_amber() {
+        let nodes = corridor();
+        let snapshot = serde_json::to_value(&nodes).unwrap();
+        let diff = dry_run(&directives(&[("CYB-AIR-CANOPY-01", 0.8)]), &nodes);
+        assert_eq!(serde_json::to_value(&nodes).unwrap(), snapshot);
+
+        assert_eq!(diff.before.band, Some(EcoBand::Green));
+        assert_eq!(diff.after.band, Some(EcoBand::Amber));
+        assert!(diff.band_changed());
+        // 0.5 * m / 1e-4 + 0.5 * 1.5e9 m / 1e6 with m = 3.24e-5, then 1.1016e-4 kg.
+        let close = |a: Option<f64>, b: f64| (a.unwrap() - b).abs() < 1e-9;
+        assert!(close(diff.before.eco_load, 0.186_30), "{:?}", diff.before);
+        assert!(close(diff.after.eco_load, 0.633_42), "{:?}", diff.after);
+        assert!((diff.after.mass_kg - 1.1016e-4).abs() < 1e-15);
+        assert!(diff.failed_checks.is_empty(), "{:?}", diff.failed_checks);
+
+        let canopy = diff.node("CYB-AIR-CANOPY-01").unwrap();
+        assert_eq!(
+            (canopy.before.duty_cycle, canopy.after.duty_cycle),
+            (0.2, 0.8)
+        );
+        assert!(canopy.after.mass_kg.unwrap() > 3.0 * canopy.before.mass_kg.unwrap());
+        // The controller adds the amber band gain on top of the new duty.
+        assert!(canopy.after.next_duty_cycle.unwrap() > canopy.before.next_duty_cycle.unwrap());
+
+        let school = diff.node("CYB-AIR-SCHOOL-05").unwrap();
+        assert_eq!(school.before.mass_kg, school.after.mass_kg);
+        assert_ne!(school.before.next_duty_cycle, school.after.next_duty_cycle);
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(json["before"]["band"], "Green");
+        assert_eq!(json["after"]["band"], "Amber");
+    }
+
+    #[test]
+    fn predicted_mass_follows_the_proposed_duty() {
+        let nodes = corridor();
+        let corridor_mass = |config: &ControllerConfig, canopy_duty| {
+            let proposal = directives(&[("CYB-AIR-CANOPY-01", canopy_duty)]);
+            Verifier::dry_run(&proposal, &nodes, config, ConstAltitude(331.0), 310.0)
+                .unwrap()
+                .after
+                .mass_kg
+        };
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-15;
+
+        // Linear by default: the school contributes 0.2 * 3.24e-5 kg.
+        let linear = ControllerConfig::from_toml(CONFIG).unwrap();
+        assert!(close(corridor_mass(&linear, 0.0), 6.48e-6));
+        assert!(close(corridor_mass(&linear, 0.5), 7.128e-5));
+        assert!(close(corridor_mass(&linear, 1.0), 1.3608e-4));
+
+        // A 0.3 m^3/s bleed keeps 10% of the canopy's and 30% of the
+        // school's full-duty flow when idle.
+        let bleed = ControllerConfig::from_toml(&format!(
+            "{CONFIG}\n[airflow.default]\nmodel = \"affine\"\nmin_airflow_m3_per_s = 0.3\n"
+        ))
+        .unwrap();
+        let idle = 0.1 * 1.296e-4 + (0.3 + 0.7 * 0.2) * 3.24e-5;
+        assert!(close(corridor_mass(&bleed, 0.0), idle));
+        assert!(corridor_mass(&bleed, 0.0) > corridor_mass(&linear, 0.0));
+        assert!(close(
+            corridor_mass(&bleed, 1.0),
+            corridor_mass(&linear, 1.0) + 0.24 * 3.24e-5
+        ));
+    }
+
+    #[test]
+    fn failures_under_the_proposal_are_listed() {
+        let mut nodes = corridor();
+        nodes.get_mut("CYB-AIR-CANOPY-01").unwrap().rated_power_w = 400.0;
+        let diff = dry_run(
+            &directives(&[("CYB-AIR-CANOPY-01", 0.8), ("CYB-AIR-GHOST-99", 0.5)]),
+            &nodes,
+        );
+        let checks: Vec<_> = diff
+            .failed_checks
+            .iter()
+            .map(|c| (c.node_id.as_deref(), c.check.as_str()))
+            .collect();
+        assert_eq!(
+            checks,
+            [
+                (Some("CYB-AIR-GHOST-99"), "proposal"),
+                (Some("CYB-AIR-CANOPY-01"), "host_budget_exceeded"),
+            ]
+        );
+        // 80 W at the current duty is within budget; an empty proposal is
+        // flagged for itself and for nothing else.
+        let current = dry_run(&Proposal::default(), &nodes);
+        let checks: Vec<_> = current
+            .failed_checks
+            .iter()
+            .map(|c| (c.node_id.as_deref(), c.message.as_str()))
+            .collect();
+        assert_eq!(checks, [(None, "proposal has no directives")]);
+        assert!(!current.band_changed());
+    }
+
+    #[test]
+    fn a_ramp_passing_through_a_violation_is_rejected() {
+        // Full duty puts 3.6e-9 of Phi_dw at the canopy, 1.2e-9 at the school.
+        let config = ControllerConfig::from_toml(
+            &CONFIG.replace("phi_dw_max = 1.0e-6", "phi_dw_max = 3.4e-9"),
+        )
+        .unwrap();
+        let mut nodes = corridor();
+        nodes.get_mut("CYB-AIR-SCHOOL-05").unwrap().duty_cycle = 0.8;
+        let swap = |canopy: RampShape| {
+            let mut proposal =
+                directives(&[("CYB-AIR-CANOPY-01", 0.8), ("CYB-AIR-SCHOOL-05", 0.2)]);
+            proposal.directives[0].ramp_shape = Some(canopy);
+            Verifier::dry_run(&proposal, &nodes, &config, ConstAltitude(331.0), 310.0).unwrap()
+        };
+
+        // Linear both ways: Phi_dw climbs from 1.68e-9 to 3.12e-9.
+        let linear = swap(RampShape::Linear);
+        assert!(
+            linear.failed_checks.is_empty(),
+            "{:?}",
+            linear.failed_checks
+        );
+
+        // The canopy jumps while the school is still high: 3.66e-9 at 75 s.
+        let step = swap(RampShape::Step);
+        assert_eq!(step.after, linear.after);
+        let checks: Vec<_> = step
+            .failed_checks
+            .iter()
+            .map(|c| (c.node_id.as_deref(), c.check.as_str(), c.at_s))
+            .collect();
+        assert_eq!(
+            checks,
+            [
+                (Some("CYB-AIR-CANOPY-01"), "dw_ceiling_exceeded", Some(75.0)),
+                (Some("CYB-AIR-SCHOOL-05"), "dw_ceiling_exceeded", Some(75.0)),
+            ]
+        );
+        assert!(step.failed_checks[0]
+            .message
+            .starts_with("at 75 s into the ramp: "));
+
+        // An early exponential approach overshoots the linear path too.
+        let exp = swap(RampShape::ExpApproach { tau_s: 30.0 });
+        assert!(!exp.failed_checks.is_empty());
+        let slow = swap(RampShape::ExpApproach { tau_s: 1.0e6 });
+        assert!(slow.failed_checks.is_empty(), "{:?}", slow.failed_checks);
+    }
+}
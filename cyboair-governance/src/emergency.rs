@@ -0,0 +1,689 @@
+#![forbid(unsafe_code)]
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{now_unix_ms, AuditDecision, AuditEntry, AuditKind, AuditSink, NodeTrace};
+use crate::pipeline::{VerificationContext, VerifierPipeline, BEE_VETO_STAGE};
+use crate::reason::{ReasonCode, VerdictReason};
+use crate::{Principal, Proposal, Role, Verdict};
+
+/// Break-glass limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EmergencyConfig {
+    /// Time a second Superchair has to close the follow-up review.
+    pub review_window_ms: u64,
+    pub min_justification_chars: usize,
+}
+
+impl Default for EmergencyConfig {
+    fn default() -> Self {
+        Self {
+            review_window_ms: 24 * 3_600_000,
+            min_justification_chars: 40,
+        }
+    }
+}
+
+/// A proposal submitted on the emergency path.
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EmergencyRequest {
+    #[serde(flatten)]
+    pub proposal: Proposal,
+    pub emergency: bool,
+    pub justification: String,
+}
+
+/// Follow-up review opened by every accepted override.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReviewRecord {
+    pub id: u64,
+    pub invoked_by: String,
+    pub justification: String,
+    pub opened_at_ms: u64,
+    pub due_by_ms: u64,
+    pub closed_by: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EmergencyError {
+    /// The mandatory pipeline has no `BEE_VETO_STAGE`.
+    MissingBeeVeto,
+    NotFlagged,
+    NotSuperchair(Role),
+    JustificationTooShort {
+        chars: usize,
+        min: usize,
+    },
+    /// An earlier override by this principal was not reviewed in time.
+    Suspended(String),
+    /// InputGuard or a mandatory veto stage rejected the proposal.
+    Vetoed(Verdict),
+    UnknownReview(u64),
+    ReviewAlreadyClosed(u64),
+    ReviewExpired(u64),
+    /// Reviews must be closed by a different Superchair.
+    InvalidReviewer(String),
+    NotSuspended(String),
+}
+
+impl fmt::Display for EmergencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmergencyError::MissingBeeVeto => {
+                write!(f, "emergency pipeline has no {BEE_VETO_STAGE:?} veto stage")
+            }
+            EmergencyError::NotFlagged => write!(f, "proposal is not flagged emergency"),
+            EmergencyError::NotSuperchair(role) => {
+                write!(f, "{role:?} may not invoke an emergency override")
+            }
+            EmergencyError::JustificationTooShort { chars, min } => {
+                write!(
+                    f,
+                    "justification has {chars} characters, need at least {min}"
+                )
+            }
+            EmergencyError::Suspended(id) => {
+                write!(
+                    f,
+                    "override privilege suspended for {id} pending an overdue review"
+                )
+            }
+            EmergencyError::Vetoed(verdict) => write!(f, "emergency proposal vetoed: {verdict}"),
+            EmergencyError::UnknownReview(id) => write!(f, "no review {id}"),
+            EmergencyError::ReviewAlreadyClosed(id) => write!(f, "review {id} already closed"),
+            EmergencyError::ReviewExpired(id) => write!(f, "review {id} is past its window"),
+            EmergencyError::InvalidReviewer(id) => {
+                write!(f, "{id} may not close this review")
+            }
+            EmergencyError::NotSuspended(id) => write!(f, "{id} is not suspended"),
+        }
+    }
+}
+
+impl std::error::Error for EmergencyError {}
+
+#[derive(Default)]
+struct ReviewState {
+    next_id: u64,
+    reviews: BTreeMap<u64, ReviewRecord>,
+    suspended: HashSet<String>,
+}
+
+impl ReviewState {
+    /// Suspend everyone with a review still open past its deadline.
+    fn sweep(&mut self, now_ms: u64) {
+        for review in self.reviews.values() {
+            if review.closed_by.is_none() && now_ms >= review.due_by_ms {
+                self.suspended.insert(review.invoked_by.clone());
+            }
+        }
+    }
+}
+
+/// Emergency path around quorum and CEIM.
+///
+/// Only a Superchair may invoke it, with a written justification. The
+/// `mandatory` pipeline still runs (structural checks, the bee-kernel veto
+/// and whatever other stages the deployment registers), every attempt is
+/// written to the audit sink, and each accepted override opens a review
+/// that another Superchair must close within `review_window_ms`, or the
+/// invoker loses the override privilege until another Superchair lifts the
+/// suspension.
+pub struct EmergencyOverride {
+    config: EmergencyConfig,
+    mandatory: VerifierPipeline,
+    audit: Arc<dyn AuditSink>,
+    state: Mutex<ReviewState>,
+}
+
+impl EmergencyOverride {
+    /// Fails with `MissingBeeVeto` unless `mandatory` has a stage named
+    /// [`BEE_VETO_STAGE`]: no override may bypass the bees.
+    pub fn new(
+        config: EmergencyConfig,
+        mandatory: VerifierPipeline,
+        audit: Arc<dyn AuditSink>,
+    ) -> Result<Self, EmergencyError> {
+        if !mandatory.has_stage(BEE_VETO_STAGE) {
+            return Err(EmergencyError::MissingBeeVeto);
+        }
+        Ok(Self {
+            config,
+            mandatory,
+            audit,
+            state: Mutex::new(ReviewState::default()),
+        })
+    }
+
+    pub async fn submit(
+        &self,
+        principal: &Principal,
+        request: &EmergencyRequest,
+        ctx: &VerificationContext,
+    ) -> Result<Verdict, EmergencyError> {
+        self.submit_at(principal, request, ctx, now_unix_ms()).await
+    }
+
+    /// Returns the approving verdict; its reason names the opened review.
+    pub async fn submit_at(
+        &self,
+        principal: &Principal,
+        request: &EmergencyRequest,
+        ctx: &VerificationContext,
+        now_ms: u64,
+    ) -> Result<Verdict, EmergencyError> {
+        let result = self.admit(principal, request, ctx, now_ms).await;
+        let review_id = match &result {
+            Ok(_) => Some(self.open_review(principal, request, now_ms)),
+            Err(_) => None,
+        };
+        self.audit.record(AuditEntry {
+            principal_id: principal.id.clone(),
+            role: principal.role.clone(),
+            kind: AuditKind::EmergencyOverride {
+                justification: request.justification.clone(),
+                review_id,
+            },
+            resource_id: request.proposal.node_ids().collect::<Vec<_>>().join(","),
+            decision: if result.is_ok() {
+                AuditDecision::Granted
+            } else {
+                AuditDecision::Denied
+            },
+            policy_trail: vec![],
+            request: None,
+            node_traces: node_traces(&request.proposal, ctx).await,
+            timestamp_unix_ms: now_ms,
+        });
+
+        result?;
+        let review_id = review_id.expect("review opened for accepted override");
+        Ok(Verdict::approve(VerdictReason::new(
+            ReasonCode::EmergencyOverride,
+            format!(
+                "emergency override by {}; review {review_id} due in {} ms",
+                principal.id, self.config.review_window_ms
+            ),
+        )))
+    }
+
+    async fn admit(
+        &self,
+        principal: &Principal,
+        request: &EmergencyRequest,
+        ctx: &VerificationContext,
+        now_ms: u64,
+    ) -> Result<(), EmergencyError> {
+        if !request.emergency {
+            return Err(EmergencyError::NotFlagged);
+        }
+        if principal.role != Role::Superchair {
+            return Err(EmergencyError::NotSuperchair(principal.role.clone()));
+        }
+        if self.is_suspended_at(&principal.id, now_ms) {
+            return Err(EmergencyError::Suspended(principal.id.clone()));
+        }
+        let chars = request.justification.trim().chars().count();
+        if chars < self.config.min_justification_chars {
+            return Err(EmergencyError::JustificationTooShort {
+                chars,
+                min: self.config.min_justification_chars,
+            });
+        }
+        let report = self.mandatory.run(&request.proposal, ctx).await;
+        if !report.verdict.approved {
+            return Err(EmergencyError::Vetoed(report.verdict));
+        }
+        Ok(())
+    }
+
+    fn open_review(&self, principal: &Principal, request: &EmergencyRequest, now_ms: u64) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let id = state.next_id;
+        state.next_id += 1;
+        state.reviews.insert(
+            id,
+            ReviewRecord {
+                id,
+                invoked_by: principal.id.clone(),
+                justification: request.justification.clone(),
+                opened_at_ms: now_ms,
+                due_by_ms: now_ms.saturating_add(self.config.review_window_ms),
+                closed_by: None,
+            },
+        );
+        id
+    }
+
+    pub fn close_review(&self, id: u64, reviewer: &Principal) -> Result<(), EmergencyError> {
+        self.close_review_at(id, reviewer, now_unix_ms())
+    }
+
+    pub fn close_review_at(
+        &self,
+        id: u64,
+        reviewer: &Principal,
+        now_ms: u64,
+    ) -> Result<(), EmergencyError> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sweep(now_ms);
+        let review = state
+            .reviews
+            .get_mut(&id)
+            .ok_or(EmergencyError::UnknownReview(id))?;
+        if review.closed_by.is_some() {
+            return Err(EmergencyError::ReviewAlreadyClosed(id));
+        }
+        if reviewer.role != Role::Superchair || reviewer.id == review.invoked_by {
+            return Err(EmergencyError::InvalidReviewer(reviewer.id.clone()));
+        }
+        if now_ms >= review.due_by_ms {
+            return Err(EmergencyError::ReviewExpired(id));
+        }
+        review.closed_by = Some(reviewer.id.clone());
+        Ok(())
+    }
+
+    pub fn review(&self, id: u64) -> Option<ReviewRecord> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.reviews.get(&id).cloned()
+    }
+
+    pub fn is_suspended_at(&self, principal_id: &str, now_ms: u64) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sweep(now_ms);
+        state.suspended.contains(principal_id)
+    }
+
+    pub fn lift_suspension(
+        &self,
+        principal_id: &str,
+        by: &Principal,
+        justification: &str,
+    ) -> Result<(), EmergencyError> {
+        self.lift_suspension_at(principal_id, by, justification, now_unix_ms())
+    }
+
+    /// Restore `principal_id`'s override privilege. Another Superchair must
+    /// lift it with a justification, which closes the overdue reviews that
+    /// caused it; every attempt is audited.
+    pub fn lift_suspension_at(
+        &self,
+        principal_id: &str,
+        by: &Principal,
+        justification: &str,
+        now_ms: u64,
+    ) -> Result<(), EmergencyError> {
+        let result = self.lift(principal_id, by, justification, now_ms);
+        self.audit.record(AuditEntry {
+            principal_id: by.id.clone(),
+            role: by.role.clone(),
+            kind: AuditKind::SuspensionLifted {
+                justification: justification.into(),
+                reviews: result.clone().unwrap_or_default(),
+            },
+            resource_id: principal_id.into(),
+            decision: if result.is_ok() {
+                AuditDecision::Granted
+            } else {
+                AuditDecision::Denied
+            },
+            policy_trail: vec![],
+            request: None,
+            node_traces: vec![],
+            timestamp_unix_ms: now_ms,
+        });
+        result.map(|_| ())
+    }
+
+    /// Ids of the reviews closed by lifting.
+    fn lift(
+        &self,
+        principal_id: &str,
+        by: &Principal,
+        justification: &str,
+        now_ms: u64,
+    ) -> Result<Vec<u64>, EmergencyError> {
+        if by.role != Role::Superchair || by.id == principal_id {
+            return Err(EmergencyError::InvalidReviewer(by.id.clone()));
+        }
+        let chars = justification.trim().chars().count();
+        if chars < self.config.min_justification_chars {
+            return Err(EmergencyError::JustificationTooShort {
+                chars,
+                min: self.config.min_justification_chars,
+            });
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sweep(now_ms);
+        if !state.suspended.remove(principal_id) {
+            return Err(EmergencyError::NotSuspended(principal_id.into()));
+        }
+        let mut closed = Vec::new();
+        for review in state.reviews.values_mut() {
+            if review.invoked_by == principal_id
+                && review.closed_by.is_none()
+                && now_ms >= review.due_by_ms
+            {
+                review.closed_by = Some(by.id.clone());
+                closed.push(review.id);
+            }
+        }
+        Ok(closed)
+    }
+}
+
+/// Shard trace of each node `proposal` touches, when the context has a
+/// shard and the node's row carries a `trace_id`.
+async fn node_traces(proposal: &Proposal, ctx: &VerificationContext) -> Vec<NodeTrace> {
+    let Some(shard) = &ctx.shard else {
+        return vec![];
+    };
+    let mut traces = Vec::new();
+    for node_id in proposal.node_ids() {
+        if let Some(row) = shard.row(node_id).await {
+            if let Some(trace_id) = row.trace_id {
+                traces.push(NodeTrace {
+                    node_id: node_id.into(),
+                    trace_id,
+                    shard_version: row.shard_version,
+                });
+            }
+        }
+    }
+    traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::MemoryAuditSink;
+    use crate::pipeline::{StageResult, VerificationStage};
+    use crate::tests::{directives, fixture_rows};
+    use async_trait::async_trait;
+    use uuid::Uuid;
+
+    const WINDOW_MS: u64 = 3_600_000;
+    const WHY: &str = "Hive H-12 brood temperature rising under canopy exhaust; cut duty now.";
+
+    /// Stand-in for the bee kernel: vetoes any duty above 0.5.
+    struct BeeVeto;
+
+    #[async_trait]
+    impl VerificationStage for BeeVeto {
+        fn name(&self) -> &str {
+            "bee-kernel"
+        }
+
+        async fn run(&self, proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+            if proposal.directives.iter().any(|d| d.new_duty_cycle > 0.5) {
+                StageResult::Fail(vec![VerdictReason::new(
+                    ReasonCode::BeeVeto,
+                    "duty above bee ceiling",
+                )])
+            } else {
+                StageResult::Pass(vec![])
+            }
+        }
+    }
+
+    fn principal(id: &str, role: Role) -> Principal {
+        Principal {
+            id: id.into(),
+            role,
+            attributes: vec![],
+        }
+    }
+
+    fn request(duty: f64, justification: &str) -> EmergencyRequest {
+        EmergencyRequest {
+            proposal: directives(&[("CYB-AIR-APIARY-01", duty)]),
+            emergency: true,
+            justification: justification.into(),
+        }
+    }
+
+    fn setup() -> (EmergencyOverride, Arc<MemoryAuditSink>) {
+        let sink = Arc::new(MemoryAuditSink::new(16));
+        let config = EmergencyConfig {
+            review_window_ms: WINDOW_MS,
+            ..EmergencyConfig::default()
+        };
+        let flow = EmergencyOverride::new(
+            config,
+            VerifierPipeline::new().add_stage(BeeVeto),
+            sink.clone(),
+        )
+        .unwrap();
+        (flow, sink)
+    }
+
+    #[test]
+    fn override_requires_a_bee_veto_stage() {
+        let sink = Arc::new(MemoryAuditSink::new(16));
+        let flow =
+            EmergencyOverride::new(EmergencyConfig::default(), VerifierPipeline::new(), sink);
+        assert!(matches!(flow, Err(EmergencyError::MissingBeeVeto)));
+    }
+
+    #[tokio::test]
+    async fn only_superchair_may_invoke() {
+        let (flow, sink) = setup();
+        let ctx = VerificationContext::default();
+        for role in [Role::Staff, Role::Stakeholder, Role::Bot, Role::Guest] {
+            let err = flow
+                .submit_at(&principal("ops", role), &request(0.0, WHY), &ctx, 0)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, EmergencyError::NotSuperchair(_)), "{err}");
+        }
+        let chair = principal("chair-a", Role::Superchair);
+        let verdict = flow
+            .submit_at(&chair, &request(0.0, WHY), &ctx, 0)
+            .await
+            .unwrap();
+        assert!(verdict.has_code(ReasonCode::EmergencyOverride));
+
+        // Every attempt is force-logged, denied ones included.
+        let entries = sink.entries();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0].decision, AuditDecision::Denied);
+        assert_eq!(
+            entries[4].kind,
+            AuditKind::EmergencyOverride {
+                justification: WHY.into(),
+                review_id: Some(0),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn justification_and_veto_still_apply() {
+        let (flow, _) = setup();
+        let chair = principal("chair-a", Role::Superchair);
+        let ctx = VerificationContext::default();
+
+        let err = flow
+            .submit_at(&chair, &request(0.0, "   urgent   "), &ctx, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EmergencyError::JustificationTooShort { chars: 6, min: 40 }
+        ));
+
+        let err = flow
+            .submit_at(&chair, &request(0.8, WHY), &ctx, 0)
+            .await
+            .unwrap_err();
+        match err {
+            EmergencyError::Vetoed(v) => assert!(v.has_code(ReasonCode::BeeVeto)),
+            other => panic!("expected bee veto, got {other}"),
+        }
+
+        // InputGuard runs first.
+        let err = flow
+            .submit_at(&chair, &request(1.5, WHY), &ctx, 0)
+            .await
+            .unwrap_err();
+        match err {
+            EmergencyError::Vetoed(v) => assert!(v.has_code(ReasonCode::DutyOutOfRange)),
+            other => panic!("expected structural rejection, got {other}"),
+        }
+
+        let mut unflagged = request(0.0, WHY);
+        unflagged.emergency = false;
+        assert!(matches!(
+            flow.submit_at(&chair, &unflagged, &ctx, 0).await,
+            Err(EmergencyError::NotFlagged)
+        ));
+    }
+
+    #[tokio::test]
+    async fn audit_entry_carries_shard_trace_ids() {
+        let (flow, sink) = setup();
+        let trace_id = Uuid::from_u128(0x2122);
+        let mut rows = fixture_rows();
+        let canopy = rows.get_mut("CYB-AIR-CANOPY-01").unwrap();
+        canopy.trace_id = Some(trace_id);
+        canopy.shard_version = Some("2026.10".into());
+        let ctx = VerificationContext::default().with_shard(Arc::new(rows));
+
+        let request = EmergencyRequest {
+            proposal: directives(&[
+                ("CYB-AIR-SCHOOL-05", 0.0),
+                ("CYB-AIR-CANOPY-01", 0.0),
+                ("CYB-AIR-APIARY-01", 0.0),
+            ]),
+            emergency: true,
+            justification: WHY.into(),
+        };
+        flow.submit_at(&principal("chair-a", Role::Superchair), &request, &ctx, 0)
+            .await
+            .unwrap();
+
+        // The school row has no trace id and the apiary is not in the shard.
+        let entry = &sink.entries()[0];
+        assert_eq!(
+            entry.node_traces,
+            [NodeTrace {
+                node_id: "CYB-AIR-CANOPY-01".into(),
+                trace_id,
+                shard_version: Some("2026.10".into()),
+            }]
+        );
+        let json = serde_json::to_value(entry).unwrap();
+        assert_eq!(json["node_traces"][0]["trace_id"], trace_id.to_string());
+    }
+
+    #[tokio::test]
+    async fn review_by_another_superchair_keeps_privilege() {
+        let (flow, _) = setup();
+        let chair_a = principal("chair-a", Role::Superchair);
+        let chair_b = principal("chair-b", Role::Superchair);
+        let ctx = VerificationContext::default();
+        flow.submit_at(&chair_a, &request(0.0, WHY), &ctx, 0)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            flow.close_review_at(0, &chair_a, 10),
+            Err(EmergencyError::InvalidReviewer(_))
+        ));
+        flow.close_review_at(0, &chair_b, 10).unwrap();
+        assert_eq!(
+            flow.review(0).unwrap().closed_by.as_deref(),
+            Some("chair-b")
+        );
+        assert!(!flow.is_suspended_at("chair-a", WINDOW_MS * 2));
+    }
+
+    #[tokio::test]
+    async fn overdue_review_suspends_invoker() {
+        let (flow, _) = setup();
+        let chair_a = principal("chair-a", Role::Superchair);
+        let chair_b = principal("chair-b", Role::Superchair);
+        let ctx = VerificationContext::default();
+        flow.submit_at(&chair_a, &request(0.0, WHY), &ctx, 0)
+            .await
+            .unwrap();
+
+        assert!(!flow.is_suspended_at("chair-a", WINDOW_MS - 1));
+        assert!(matches!(
+            flow.close_review_at(0, &chair_b, WINDOW_MS),
+            Err(EmergencyError::ReviewExpired(0))
+        ));
+        let err = flow
+            .submit_at(&chair_a, &request(0.0, WHY), &ctx, WINDOW_MS + 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EmergencyError::Suspended(_)));
+        // Only the invoker is suspended.
+        flow.submit_at(&chair_b, &request(0.0, WHY), &ctx, WINDOW_MS + 1)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn another_superchair_lifts_a_suspension_on_the_record() {
+        let (flow, sink) = setup();
+        let chair_a = principal("chair-a", Role::Superchair);
+        let chair_b = principal("chair-b", Role::Superchair);
+        let ctx = VerificationContext::default();
+        flow.submit_at(&chair_a, &request(0.0, WHY), &ctx, 0)
+            .await
+            .unwrap();
+        let late = WINDOW_MS + 1;
+        assert!(flow.is_suspended_at("chair-a", late));
+
+        assert!(matches!(
+            flow.lift_suspension_at("chair-a", &chair_a, WHY, late),
+            Err(EmergencyError::InvalidReviewer(_))
+        ));
+        assert!(matches!(
+            flow.lift_suspension_at("chair-a", &chair_b, "ok", late),
+            Err(EmergencyError::JustificationTooShort { .. })
+        ));
+        assert!(matches!(
+            flow.lift_suspension_at("chair-b", &chair_a, WHY, late),
+            Err(EmergencyError::NotSuspended(_))
+        ));
+        flow.lift_suspension_at("chair-a", &chair_b, WHY, late)
+            .unwrap();
+
+        // The overdue review is closed, so the next sweep does not re-suspend.
+        assert!(!flow.is_suspended_at("chair-a", late + 1));
+        assert_eq!(
+            flow.review(0).unwrap().closed_by.as_deref(),
+            Some("chair-b")
+        );
+        flow.submit_at(&chair_a, &request(0.0, WHY), &ctx, late + 1)
+            .await
+            .unwrap();
+
+        let entries = sink.entries();
+        let lifts: Vec<_> = entries
+            .iter()
+            .filter(|e| matches!(e.kind, AuditKind::SuspensionLifted { .. }))
+            .collect();
+        assert_eq!(lifts.len(), 4);
+        assert_eq!(lifts[0].decision, AuditDecision::Denied);
+        assert_eq!(lifts[3].decision, AuditDecision::Granted);
+        assert_eq!(lifts[3].principal_id, "chair-b");
+        assert_eq!(lifts[3].resource_id, "chair-a");
+        assert_eq!(
+            lifts[3].kind,
+            AuditKind::SuspensionLifted {
+                justification: WHY.into(),
+                reviews: vec![0],
+            }
+        );
+    }
+}
@@ -0,0 +1,332 @@
+#![forbid(unsafe_code)]
+
+//! Resource properties derived from the loaded shard.
+//!
+//! Hand-maintained properties drift from the shard (a node recorded as
+//! "Tempe" that the shard places in Phoenix). A [`ResourceEnricher`] looks
+//! the resource up by `resource_id` as a machine id and derives `city`,
+//! `bee_zone`, `pollutant` and `ecoimpact_band` from its row before the
+//! policies see it. [`Precedence`] decides whether an explicit property or
+//! the shard wins when they disagree.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cyboair_corridor_safety::ShardRow;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{PropertyValue, Resource};
+
+/// City parsed from the row's location, e.g. "Phoenix" from
+/// "Phoenix-Intersection-A".
+pub const CITY_PROPERTY: &str = "city";
+/// True if the row's bee flag is set.
+pub const BEE_ZONE_PROPERTY: &str = "bee_zone";
+pub const POLLUTANT_PROPERTY: &str = "pollutant";
+/// "high", "medium" or "low"; see [`EcoimpactBands`].
+pub const ECOIMPACT_BAND_PROPERTY: &str = "ecoimpact_band";
+
+/// Looks up the shard row of a machine.
+pub trait ShardRowProvider {
+    fn shard_row(&self, machine_id: &str) -> Option<ShardRow>;
+}
+
+impl ShardRowProvider for HashMap<String, ShardRow> {
+    fn shard_row(&self, machine_id: &str) -> Option<ShardRow> {
+        self.get(machine_id).cloned()
+    }
+}
+
+/// Which value a property keeps when the resource and the shard disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Precedence {
+    /// Explicit properties stand; the shard only fills in missing ones.
+    ExplicitWins,
+    /// The shard overrides explicit properties.
+    #[default]
+    ShardWins,
+}
+
+/// Lower bounds of the `ecoimpact_band` values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EcoimpactBands {
+    pub high: f64,
+    pub medium: f64,
+}
+
+impl Default for EcoimpactBands {
+    fn default() -> Self {
+        EcoimpactBands {
+            high: 0.9,
+            medium: 0.7,
+        }
+    }
+}
+
+impl EcoimpactBands {
+    pub fn band(&self, score: f64) -> &'static str {
+        if score >= self.high {
+            "high"
+        } else if score >= self.medium {
+            "medium"
+        } else {
+            "low"
+        }
+    }
+}
+
+/// A derived property that differed from the resource's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnrichedProperty {
+    pub name: String,
+    /// The resource's value before enrichment; `None` if it had none.
+    pub previous: Option<PropertyValue>,
+    pub derived: PropertyValue,
+    /// False if an explicit value was kept under `ExplicitWins`.
+    pub applied: bool,
+}
+
+/// What [`ResourceEnricher::enrich`] did to one resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnrichmentReport {
+    pub resource_id: String,
+    pub precedence: Precedence,
+    /// False if the provider had no row; the resource is then unchanged.
+    pub row_found: bool,
+    /// Derived properties that were missing or different, in derivation
+    /// order; ones that already matched are left out.
+    pub properties: Vec<EnrichedProperty>,
+}
+
+impl EnrichmentReport {
+    /// Explicit values the shard contradicted, whether or not they won.
+    pub fn conflicts(&self) -> impl Iterator<Item = &EnrichedProperty> {
+        self.properties.iter().filter(|p| p.previous.is_some())
+    }
+}
+
+/// Derives resource properties from shard rows.
+#[derive(Clone)]
+pub struct ResourceEnricher {
+    pub provider: Arc<dyn ShardRowProvider + Send + Sync>,
+    pub precedence: Precedence,
+    pub ecoimpact_bands: EcoimpactBands,
+}
+
+impl ResourceEnricher {
+    pub fn new(provider: impl ShardRowProvider + Send + Sync + 'static) -> Self {
+        ResourceEnricher {
+            provider: Arc::new(provider),
+            precedence: Precedence::default(),
+            ecoimpact_bands: EcoimpactBands::default(),
+        }
+    }
+
+    pub fn with_precedence(mut self, precedence: Precedence) -> Self {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn with_ecoimpact_bands(mut self, bands: EcoimpactBands) -> Self {
+        self.ecoimpact_bands = bands;
+        self
+    }
+
+    /// Properties the shard implies for `row`. A location without a '-'
+    /// names no city.
+    pub fn derive(&self, row: &ShardRow) -> Vec<(&'static str, PropertyValue)> {
+        let mut derived = Vec::with_capacity(4);
+        if let Some((city, _)) = row.row.location.split_once('-') {
+            derived.push((CITY_PROPERTY, PropertyValue::Str(city.trim().to_string())));
+        }
+        let bee_zone = row.bee.as_ref().is_some_and(|b| b.bee_flag == 1);
+        derived.push((BEE_ZONE_PROPERTY, PropertyValue::Bool(bee_zone)));
+        derived.push((
+            POLLUTANT_PROPERTY,
+            PropertyValue::Str(row.row.pollutant.clone()),
+        ));
+        derived.push((
+            ECOIMPACT_BAND_PROPERTY,
+            PropertyValue::Str(self.ecoimpact_bands.band(row.row.ecoimpact_score).into()),
+        ));
+        derived
+    }
+
+    /// Populate or override `resource`'s properties from its shard row.
+    pub fn enrich(&self, resource: &mut Resource) -> EnrichmentReport {
+        let mut report = EnrichmentReport {
+            resource_id: resource.resource_id.clone(),
+            precedence: self.precedence,
+            row_found: false,
+            properties: Vec::new(),
+        };
+        let Some(row) = self.provider.shard_row(&resource.resource_id) else {
+            return report;
+        };
+        report.row_found = true;
+        for (name, derived) in self.derive(&row) {
+            let previous = resource.properties.get(name).cloned();
+            if previous.as_ref() == Some(&derived) {
+                continue;
+            }
+            let applied = previous.is_none() || self.precedence == Precedence::ShardWins;
+            if applied {
+                resource
+                    .properties
+                    .insert(name.to_string(), derived.clone());
+            }
+            report.properties.push(EnrichedProperty {
+                name: name.to_string(),
+                previous,
+                derived,
+                applied,
+            });
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use cyboair_corridor_safety::{BeeExtension, CorridorRow};
+    use gatehouse::AccessDecision;
+
+    use crate::policy::GovernanceCore;
+    use crate::types::{Action, AttributeValue, EnvironmentCtx, ResourceType, Role, User};
+
+    fn shard() -> HashMap<String, ShardRow> {
+        let row = ShardRow {
+            row: CorridorRow {
+                machine_id: "CYB-AIR-CANOPY-01".into(),
+                r#type: "UrbanNanoswarmCanopy".into(),
+                location: "Phoenix-Intersection-A".into(),
+                pollutant: "PM2.5".into(),
+                cin: 40.0,
+                cout: 28.0,
+                unit: "ug/m3".into(),
+                airflow_m3_per_s: 3.0,
+                period_s: 3600.0,
+                lambda_hazard: 3.5,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.92,
+                trace_id: None,
+                shard_version: None,
+            },
+            bee: Some(BeeExtension {
+                bee_flag: 1,
+                bee_weight: 1.5,
+                notes: String::new(),
+            }),
+            calibrated: false,
+            window_start_unix_ms: None,
+        };
+        HashMap::from([(row.row.machine_id.clone(), row)])
+    }
+
+    /// The node as the hand-kept store has it: in Tempe, pollutant matching.
+    fn stale_node() -> Resource {
+        Resource {
+            resource_id: "CYB-AIR-CANOPY-01".into(),
+            resource_type: ResourceType::Node,
+            properties: HashMap::from([
+                (
+                    CITY_PROPERTY.to_string(),
+                    PropertyValue::Str("Tempe".into()),
+                ),
+                (
+                    POLLUTANT_PROPERTY.to_string(),
+                    PropertyValue::Str("PM2.5".into()),
+                ),
+            ]),
+        }
+    }
+
+    fn city(resource: &Resource) -> &PropertyValue {
+        &resource.properties[CITY_PROPERTY]
+    }
+
+    #[test]
+    fn stale_city_is_corrected_only_when_the_shard_wins() {
+        let phoenix = PropertyValue::Str("Phoenix".into());
+        let tempe = PropertyValue::Str("Tempe".into());
+        for (precedence, expected) in [
+            (Precedence::ShardWins, &phoenix),
+            (Precedence::ExplicitWins, &tempe),
+        ] {
+            let enricher = ResourceEnricher::new(shard()).with_precedence(precedence);
+            let mut node = stale_node();
+            let report = enricher.enrich(&mut node);
+
+            assert!(report.row_found);
+            assert_eq!(city(&node), expected, "{precedence:?}");
+            let conflicts: Vec<_> = report.conflicts().collect();
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].name, CITY_PROPERTY);
+            assert_eq!(conflicts[0].previous.as_ref(), Some(&tempe));
+            assert_eq!(conflicts[0].applied, precedence == Precedence::ShardWins);
+
+            // Missing properties are filled in either way; matching ones
+            // are not reported.
+            let added: Vec<_> = report
+                .properties
+                .iter()
+                .filter(|p| p.previous.is_none())
+                .map(|p| (p.name.as_str(), p.applied))
+                .collect();
+            assert_eq!(
+                added,
+                [(BEE_ZONE_PROPERTY, true), (ECOIMPACT_BAND_PROPERTY, true)]
+            );
+            assert_eq!(
+                node.properties[BEE_ZONE_PROPERTY],
+                PropertyValue::Bool(true)
+            );
+            assert_eq!(
+                node.properties[ECOIMPACT_BAND_PROPERTY],
+                PropertyValue::Str("high".into())
+            );
+        }
+
+        let mut unknown = Resource {
+            resource_id: "CYB-AIR-GHOST-99".into(),
+            ..stale_node()
+        };
+        let report = ResourceEnricher::new(shard()).enrich(&mut unknown);
+        assert!(!report.row_found && report.properties.is_empty());
+        assert_eq!(city(&unknown), &PropertyValue::Str("Tempe".into()));
+    }
+
+    #[tokio::test]
+    async fn phoenix_ops_is_checked_against_the_shard_city() {
+        let outsider = User {
+            user_id: "ops@tempe.gov".into(),
+            role: Role::Staff,
+            attributes: HashMap::from([(
+                "department".to_string(),
+                AttributeValue::Str("TempeOps".into()),
+            )]),
+        };
+        let env = EnvironmentCtx {
+            time_utc: Utc.with_ymd_and_hms(2025, 7, 1, 16, 0, 0).unwrap(),
+            ip_address: "10.0.0.5".into(),
+            is_encrypted_channel: true,
+        };
+        let execute = Action::ExecuteControlProposal;
+
+        let stale = GovernanceCore::new()
+            .authorize(&outsider, &stale_node(), &execute, &env)
+            .await;
+        assert!(matches!(stale.decision, AccessDecision::Granted));
+
+        let core = GovernanceCore::new().with_enricher(ResourceEnricher::new(shard()));
+        let (eval, report) = core
+            .authorize_with_report(&outsider, &stale_node(), &execute, &env)
+            .await;
+        assert!(matches!(eval.decision, AccessDecision::Denied));
+        assert!(report.unwrap().conflicts().all(|p| p.applied));
+    }
+}
@@ -0,0 +1,151 @@
+#![forbid(unsafe_code)]
+
+use async_trait::async_trait;
+use cybo_corridor_core::EscalationAction;
+use gatehouse::{Policy, PolicyEvalResult};
+
+use crate::{GovContext, Principal, Resource, Role};
+
+/// Which roles may execute which corridor escalations.
+///
+/// Staff and Superchair may execute anything. Bots run the automatic,
+/// reversible responses (throttle, sensing-only, audit, alert) but can never
+/// disable actuation, reroute, or run a custom or future action. Stakeholders may only raise alerts and
+/// audits; guests may execute nothing.
+pub struct EscalationActionGate;
+
+impl EscalationActionGate {
+    pub fn permits(role: &Role, action: &EscalationAction) -> bool {
+        use EscalationAction::*;
+        use Role::*;
+
+        match (role, action) {
+            (Superchair, _) | (Staff, _) => true,
+            (Bot, ThrottleDutyCycle)
+            | (Bot, EnterSensingOnly)
+            | (Bot, TriggerAudit)
+            | (Bot, TriggerAlert) => true,
+            // Disable, reroute, and anything added later or downstream.
+            (Bot, _) => false,
+            (Stakeholder, TriggerAudit) | (Stakeholder, TriggerAlert) => true,
+            (Stakeholder, _) => false,
+            (Guest, _) => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Policy<Principal, Resource, EscalationAction, GovContext> for EscalationActionGate {
+    async fn evaluate_access(
+        &self,
+        principal: &Principal,
+        action: &EscalationAction,
+        _resource: &Resource,
+        _ctx: &GovContext,
+    ) -> PolicyEvalResult {
+        if Self::permits(&principal.role, action) {
+            PolicyEvalResult::granted(
+                "EscalationActionGate",
+                Some("role may execute escalation".into()),
+            )
+        } else {
+            PolicyEvalResult::denied(
+                "EscalationActionGate",
+                format!("{:?} may not execute {:?}", principal.role, action),
+            )
+        }
+    }
+
+    fn policy_type(&self) -> String {
+        "EscalationActionGate".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditDecision, AuditKind, MemoryAuditSink};
+    use crate::GovernanceCore;
+    use gatehouse::AccessDecision;
+    use std::sync::Arc;
+
+    const ACTIONS: [EscalationAction; 6] = [
+        EscalationAction::ThrottleDutyCycle,
+        EscalationAction::DisableActuation,
+        EscalationAction::ReroutePath,
+        EscalationAction::EnterSensingOnly,
+        EscalationAction::TriggerAudit,
+        EscalationAction::TriggerAlert,
+    ];
+
+    #[test]
+    fn role_action_matrix() {
+        // Columns follow ACTIONS: throttle, disable, reroute, sensing-only, audit, alert.
+        let matrix = [
+            (Role::Superchair, [true, true, true, true, true, true]),
+            (Role::Staff, [true, true, true, true, true, true]),
+            (Role::Bot, [true, false, false, true, true, true]),
+            (Role::Stakeholder, [false, false, false, false, true, true]),
+            (Role::Guest, [false, false, false, false, false, false]),
+        ];
+        for (role, row) in matrix {
+            for (action, expected) in ACTIONS.iter().zip(row) {
+                assert_eq!(
+                    EscalationActionGate::permits(&role, action),
+                    expected,
+                    "{role:?} / {action:?}"
+                );
+            }
+        }
+
+        let custom = EscalationAction::Custom {
+            namespace: 0x0b47,
+            id: 1,
+        };
+        assert!(EscalationActionGate::permits(&Role::Staff, &custom));
+        assert!(!EscalationActionGate::permits(&Role::Bot, &custom));
+        assert!(!EscalationActionGate::permits(&Role::Stakeholder, &custom));
+    }
+
+    #[tokio::test]
+    async fn suppressed_escalations_are_audited() {
+        let sink = Arc::new(MemoryAuditSink::new(8));
+        let core = GovernanceCore::new_with_sink(sink.clone());
+        let bot = Principal {
+            id: "bot-07".into(),
+            role: Role::Bot,
+            attributes: vec![],
+        };
+        let node = Resource {
+            resource_id: "node_07".into(),
+            owner: None,
+            attributes: vec![],
+        };
+
+        let auth = core
+            .authorize_escalation(&bot, &EscalationAction::TriggerAlert, &node)
+            .await;
+        assert!(matches!(auth.decision, AccessDecision::Granted));
+        let auth = core
+            .authorize_escalation(&bot, &EscalationAction::DisableActuation, &node)
+            .await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(auth.trail[0].policy, "EscalationActionGate");
+
+        let entries = sink.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].kind,
+            AuditKind::Escalation {
+                action: EscalationAction::TriggerAlert
+            }
+        );
+        assert_eq!(
+            entries[1].kind,
+            AuditKind::SuppressedEscalation {
+                action: EscalationAction::DisableActuation
+            }
+        );
+        assert_eq!(entries[1].decision, AuditDecision::Denied);
+    }
+}
@@ -0,0 +1,269 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+
+use crate::types::Role;
+
+/// Label used when no enclosing object names a zone for a coarsened location.
+pub const UNKNOWN_ZONE: &str = "unknown-zone";
+
+/// Environment variable holding the key for [`Redaction::Hash`].
+pub const HASH_KEY_ENV: &str = "CYBOAIR_EXPORT_HASH_KEY";
+
+/// What to do with a matching field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Redaction {
+    /// Remove the field.
+    Drop,
+    /// Replace with a keyed, non-reversible tag ("h:" + 32 hex digits of
+    /// HMAC-SHA256), stable for a given key so exports can still be joined
+    /// on it. Dropped instead when the filter has no key.
+    Hash,
+    /// Round every number in the field to `decimals`; negative values round
+    /// to tens, hundreds, etc.
+    Round { decimals: i32 },
+    /// Replace the field with the string in `zone_field`, looked up in the
+    /// same object or the nearest enclosing one that has it.
+    CoarsenToZone { zone_field: String },
+}
+
+/// Apply `redaction` to every field named `field`, at any depth.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RedactionRule {
+    pub field: String,
+    #[serde(flatten)]
+    pub redaction: Redaction,
+}
+
+/// Per-role redaction spec for the Export action.
+///
+/// Roles without an entry export nothing; an empty list exports unredacted.
+/// The hash key is never part of the spec: it comes from [`HASH_KEY_ENV`] or
+/// a secret store via [`ExportFilter::with_hash_key`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExportFilter {
+    pub roles: HashMap<Role, Vec<RedactionRule>>,
+    #[serde(skip)]
+    hash_key: Option<Hmac<Sha256>>,
+}
+
+impl ExportFilter {
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// Key [`Redaction::Hash`] tags with `key`.
+    pub fn with_hash_key(mut self, key: &[u8]) -> Self {
+        self.hash_key = Some(Hmac::new_from_slice(key).expect("HMAC takes keys of any length"));
+        self
+    }
+
+    /// Key [`Redaction::Hash`] tags from [`HASH_KEY_ENV`], if it is set and
+    /// non-empty.
+    pub fn with_hash_key_from_env(self) -> Self {
+        match std::env::var(HASH_KEY_ENV) {
+            Ok(key) if !key.is_empty() => self.with_hash_key(key.as_bytes()),
+            _ => self,
+        }
+    }
+
+    /// Redacted copy of `payload` as seen by `role`, or `None` if the spec
+    /// has no entry for `role`.
+    pub fn apply(&self, role: &Role, payload: &Value) -> Option<Value> {
+        let rules = self.roles.get(role)?;
+        let mut out = payload.clone();
+        self.redact(&mut out, rules, &HashMap::new());
+        Some(out)
+    }
+
+    /// `zones` carries zone labels from this object and its ancestors, so a
+    /// location nested below the object that names its zone still coarsens.
+    fn redact(&self, value: &mut Value, rules: &[RedactionRule], zones: &HashMap<String, String>) {
+        match value {
+            Value::Object(map) => {
+                let mut zones = zones.clone();
+                for rule in rules {
+                    if let Redaction::CoarsenToZone { zone_field } = &rule.redaction {
+                        if let Some(zone) = map.get(zone_field).and_then(Value::as_str) {
+                            zones.insert(zone_field.clone(), zone.to_string());
+                        }
+                    }
+                }
+                for rule in rules {
+                    if map.contains_key(&rule.field) {
+                        self.apply_rule(map, &zones, rule);
+                    }
+                }
+                for child in map.values_mut() {
+                    self.redact(child, rules, &zones);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.redact(item, rules, zones);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_rule(
+        &self,
+        map: &mut Map<String, Value>,
+        zones: &HashMap<String, String>,
+        rule: &RedactionRule,
+    ) {
+        match &rule.redaction {
+            Redaction::Drop => {
+                map.remove(&rule.field);
+            }
+            Redaction::Hash => match &self.hash_key {
+                Some(key) => {
+                    if let Some(v) = map.get_mut(&rule.field) {
+                        *v = Value::String(hash_tag(key, v));
+                    }
+                }
+                None => {
+                    map.remove(&rule.field);
+                }
+            },
+            Redaction::Round { decimals } => {
+                if let Some(v) = map.get_mut(&rule.field) {
+                    round_numbers(v, *decimals);
+                }
+            }
+            Redaction::CoarsenToZone { zone_field } => {
+                let zone = zones.get(zone_field).map_or(UNKNOWN_ZONE, String::as_str);
+                map.insert(rule.field.clone(), Value::String(zone.to_string()));
+            }
+        }
+    }
+}
+
+fn hash_tag(key: &Hmac<Sha256>, value: &Value) -> String {
+    let mut mac = key.clone();
+    mac.update(value.to_string().as_bytes());
+    let digest = mac.finalize().into_bytes();
+    digest[..16].iter().fold(String::from("h:"), |mut tag, b| {
+        let _ = write!(tag, "{b:02x}");
+        tag
+    })
+}
+
+fn round_numbers(value: &mut Value, decimals: i32) {
+    match value {
+        Value::Number(n) => {
+            if let Some(x) = n.as_f64() {
+                let scale = 10f64.powi(decimals);
+                if let Some(r) = serde_json::Number::from_f64((x * scale).round() / scale) {
+                    *n = r;
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| round_numbers(v, decimals)),
+        Value::Object(map) => map.values_mut().for_each(|v| round_numbers(v, decimals)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter() -> ExportFilter {
+        ExportFilter::from_json(include_str!("../config/export_redaction.json"))
+            .unwrap()
+            .with_hash_key(b"test-export-key")
+    }
+
+    fn payload() -> Value {
+        json!({
+            "shard": "phoenix-apiary",
+            "nodes": [{
+                "machine_id": "CYB-AIR-APIARY-01",
+                "owner_email": "keeper@example.org",
+                "zone": "Phoenix-Apiary-Zone-1",
+                "hive": {
+                    "hive_location": { "lat": 33.448376, "lon": -112.074036 },
+                    "karma_liability_nb": 1234567.891
+                }
+            }]
+        })
+    }
+
+    #[test]
+    fn roles_see_different_redactions() {
+        let f = filter();
+        let staff = f.apply(&Role::Staff, &payload()).unwrap();
+        let stakeholder = f.apply(&Role::Stakeholder, &payload()).unwrap();
+
+        let node = &staff["nodes"][0];
+        assert!(node["owner_email"].as_str().unwrap().starts_with("h:"));
+        assert_eq!(
+            node["hive"]["hive_location"],
+            json!({ "lat": 33.45, "lon": -112.07 })
+        );
+        assert_eq!(node["hive"]["karma_liability_nb"], json!(1234568.0));
+
+        let node = &stakeholder["nodes"][0];
+        assert!(node.get("owner_email").is_none());
+        // The zone comes from the enclosing node, one level up from the hive.
+        assert_eq!(
+            node["hive"]["hive_location"],
+            json!("Phoenix-Apiary-Zone-1")
+        );
+        assert_eq!(node["hive"]["karma_liability_nb"], json!(1000000.0));
+        assert_eq!(node["machine_id"], json!("CYB-AIR-APIARY-01"));
+    }
+
+    #[test]
+    fn coarsening_without_zone_uses_placeholder() {
+        let f = filter();
+        let bare = json!({ "hive_location": [33.4, -112.0] });
+        assert_eq!(
+            f.apply(&Role::Stakeholder, &bare).unwrap()["hive_location"],
+            json!(UNKNOWN_ZONE)
+        );
+    }
+
+    #[test]
+    fn hashing_is_keyed_and_stable() {
+        let f = filter();
+        let tag = |f: &ExportFilter| {
+            f.apply(&Role::Staff, &payload()).unwrap()["nodes"][0]["owner_email"].clone()
+        };
+        assert_eq!(tag(&f), tag(&f));
+        assert_eq!(tag(&f).as_str().unwrap().len(), 2 + 32);
+        assert_ne!(tag(&f), tag(&f.clone().with_hash_key(b"other-key")));
+
+        // Without a key the field is dropped rather than hashed unkeyed.
+        let unkeyed =
+            ExportFilter::from_json(include_str!("../config/export_redaction.json")).unwrap();
+        let node = &unkeyed.apply(&Role::Staff, &payload()).unwrap()["nodes"][0];
+        assert!(node.get("owner_email").is_none());
+        assert_eq!(node["machine_id"], json!("CYB-AIR-APIARY-01"));
+    }
+
+    #[test]
+    fn only_listed_roles_export() {
+        let f = filter();
+        assert_eq!(f.apply(&Role::Superchair, &payload()), Some(payload()));
+        assert_eq!(f.apply(&Role::Guest, &payload()), None);
+        assert_eq!(f.apply(&Role::Bot, &payload()), None);
+        assert_eq!(
+            ExportFilter::default().apply(&Role::Staff, &payload()),
+            None
+        );
+    }
+}
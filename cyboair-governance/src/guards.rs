@@ -1,35 +1,762 @@
 #![forbid(unsafe_code)]
 
-use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
 
-/// Minimal control proposal schema seen at the governance boundary.
-/// The LLM or UI may only send this shape, never arbitrary commands.
-#[derive(Debug, Clone, Deserialize)]
-pub struct ControlProposal {
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+pub use cyboair_corridor_safety::RampShape;
+use cyboair_corridor_safety::{ConcentrationUnit, Pollutant};
+
+use crate::audit::now_unix_ms;
+use crate::reason::{ReasonCode, VerdictReason};
+
+/// Duty-cycle change for one node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeDirective {
     pub node_id: String,
     pub new_duty_cycle: f64,
+    /// Time to reach `new_duty_cycle`.
     pub horizon_seconds: u64,
+    /// `None` leaves the ramp to the node's controller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp_shape: Option<RampShape>,
+}
+
+/// Control proposal schema seen at the governance boundary.
+/// The LLM or UI may only send this shape, never arbitrary commands.
+///
+/// Serializes as `{"directives": [...]}`. The legacy single-node shape, a
+/// bare `NodeDirective` object, still deserializes as a one-directive
+/// proposal.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "ProposalWire")]
+pub struct ControlProposal {
+    pub directives: Vec<NodeDirective>,
+}
+
+impl ControlProposal {
+    pub fn node_ids(&self) -> impl Iterator<Item = &str> {
+        self.directives.iter().map(|d| d.node_id.as_str())
+    }
+}
+
+impl From<NodeDirective> for ControlProposal {
+    fn from(directive: NodeDirective) -> Self {
+        ControlProposal {
+            directives: vec![directive],
+        }
+    }
+}
+
+/// Every accepted wire shape of `ControlProposal`, told apart by the
+/// `directives` key.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(untagged))]
+enum ProposalWire {
+    Directives { directives: Vec<NodeDirective> },
+    SingleNode(NodeDirective),
+}
+
+impl<'de> Deserialize<'de> for ProposalWire {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Directives {
+            directives: Vec<NodeDirective>,
+        }
+
+        // Not `#[serde(untagged)]`: picking the shape up front keeps the
+        // field-level error ("missing field `new_duty_cycle`") for callers.
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let wire = if value.get("directives").is_some() {
+            serde_json::from_value::<Directives>(value).map(|d| ProposalWire::Directives {
+                directives: d.directives,
+            })
+        } else {
+            serde_json::from_value(value).map(ProposalWire::SingleNode)
+        };
+        wire.map_err(de::Error::custom)
+    }
+}
+
+impl From<ProposalWire> for ControlProposal {
+    fn from(wire: ProposalWire) -> Self {
+        match wire {
+            ProposalWire::Directives { directives } => ControlProposal { directives },
+            ProposalWire::SingleNode(directive) => directive.into(),
+        }
+    }
+}
+
+/// Most directives one proposal may carry.
+pub const MAX_DIRECTIVES: usize = 64;
+
+/// Why a control proposal was rejected by `InputGuard`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProposalError {
+    /// No directives at all; there is nothing to verify.
+    EmptyProposal,
+    TooManyDirectives {
+        count: usize,
+        max: usize,
+    },
+    /// `index` is the directive's position in the proposal.
+    EmptyNodeId {
+        index: usize,
+    },
+    DutyOutOfRange {
+        node_id: String,
+        duty: f64,
+    },
+    ZeroHorizon {
+        node_id: String,
+    },
+    DuplicateNode {
+        node_id: String,
+    },
+    /// `ExpApproach` with a time constant that is not positive and finite.
+    InvalidRamp {
+        node_id: String,
+        tau_s: f64,
+    },
+}
+
+impl ProposalError {
+    /// Node the finding is about, where there is one.
+    pub fn node_id(&self) -> Option<&str> {
+        match self {
+            ProposalError::DutyOutOfRange { node_id, .. }
+            | ProposalError::ZeroHorizon { node_id }
+            | ProposalError::DuplicateNode { node_id }
+            | ProposalError::InvalidRamp { node_id, .. } => Some(node_id),
+            ProposalError::EmptyProposal
+            | ProposalError::TooManyDirectives { .. }
+            | ProposalError::EmptyNodeId { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ProposalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProposalError::EmptyProposal => write!(f, "proposal has no directives"),
+            ProposalError::TooManyDirectives { count, max } => {
+                write!(f, "{count} directives exceed the limit of {max}")
+            }
+            ProposalError::EmptyNodeId { index } => {
+                write!(f, "directive {index}: node_id must not be empty")
+            }
+            ProposalError::DutyOutOfRange { node_id, duty } => write!(
+                f,
+                "node {node_id}: new_duty_cycle {duty} must be between 0.0 and 1.0"
+            ),
+            ProposalError::ZeroHorizon { node_id } => {
+                write!(f, "node {node_id}: horizon_seconds must be > 0")
+            }
+            ProposalError::DuplicateNode { node_id } => {
+                write!(f, "node {node_id} has more than one directive")
+            }
+            ProposalError::InvalidRamp { node_id, tau_s } => write!(
+                f,
+                "node {node_id}: exp_approach tau_s {tau_s} must be positive and finite"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProposalError {}
+
+impl From<&ProposalError> for VerdictReason {
+    fn from(e: &ProposalError) -> Self {
+        let code = match e {
+            ProposalError::EmptyProposal => ReasonCode::InvalidProposal,
+            ProposalError::TooManyDirectives { .. } => ReasonCode::TooManyDirectives,
+            ProposalError::EmptyNodeId { .. } => ReasonCode::InvalidProposal,
+            ProposalError::DutyOutOfRange { .. } => ReasonCode::DutyOutOfRange,
+            ProposalError::ZeroHorizon { .. } => ReasonCode::InvalidHorizon,
+            ProposalError::DuplicateNode { .. } => ReasonCode::DuplicateNode,
+            ProposalError::InvalidRamp { .. } => ReasonCode::InvalidProposal,
+        };
+        let mut reason = VerdictReason::new(code, e.to_string());
+        if let Some(node_id) = e.node_id() {
+            reason = reason.for_node(node_id);
+        }
+        match *e {
+            ProposalError::TooManyDirectives { count, max } => {
+                reason.with_values(count as f64, max as f64)
+            }
+            ProposalError::DutyOutOfRange { duty, .. } => reason.with_values(duty, 1.0),
+            _ => reason,
+        }
+    }
 }
 
 /// InputGuard: first line of defense against malformed or hostile payloads.
 pub struct InputGuard;
 
 impl InputGuard {
-    pub fn validate_control_proposal(p: &ControlProposal) -> Result<(), String> {
-        if p.node_id.is_empty() {
-            return Err("node_id must not be empty".into());
+    /// First finding of `control_proposal_errors`, if any.
+    pub fn validate_control_proposal(p: &ControlProposal) -> Result<(), ProposalError> {
+        match Self::control_proposal_errors(p).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Every finding against `p`: at least one directive and at most
+    /// [`MAX_DIRECTIVES`], then per directive a non-empty node id, duty in
+    /// [0, 1], a positive horizon, a usable ramp shape, and no second
+    /// directive for the same node.
+    pub fn control_proposal_errors(p: &ControlProposal) -> Vec<ProposalError> {
+        let mut errors = Vec::new();
+        if p.directives.is_empty() {
+            errors.push(ProposalError::EmptyProposal);
+        }
+        if p.directives.len() > MAX_DIRECTIVES {
+            errors.push(ProposalError::TooManyDirectives {
+                count: p.directives.len(),
+                max: MAX_DIRECTIVES,
+            });
+        }
+        let mut seen = HashSet::new();
+        for (index, d) in p.directives.iter().enumerate() {
+            if d.node_id.is_empty() {
+                errors.push(ProposalError::EmptyNodeId { index });
+                continue;
+            }
+            if !(0.0..=1.0).contains(&d.new_duty_cycle) {
+                errors.push(ProposalError::DutyOutOfRange {
+                    node_id: d.node_id.clone(),
+                    duty: d.new_duty_cycle,
+                });
+            }
+            if d.horizon_seconds == 0 {
+                errors.push(ProposalError::ZeroHorizon {
+                    node_id: d.node_id.clone(),
+                });
+            }
+            if let Some(RampShape::ExpApproach { tau_s }) = d.ramp_shape {
+                if !(tau_s.is_finite() && tau_s > 0.0) {
+                    errors.push(ProposalError::InvalidRamp {
+                        node_id: d.node_id.clone(),
+                        tau_s,
+                    });
+                }
+            }
+            if !seen.insert(d.node_id.as_str()) {
+                errors.push(ProposalError::DuplicateNode {
+                    node_id: d.node_id.clone(),
+                });
+            }
+        }
+        errors
+    }
+
+    pub fn validate_shard_write(
+        p: &ShardWritePayload,
+        bounds: &PayloadBounds,
+    ) -> Result<(), PayloadError> {
+        Self::validate_shard_write_at(p, bounds, now_unix_ms())
+    }
+
+    pub fn validate_shard_write_at(
+        p: &ShardWritePayload,
+        bounds: &PayloadBounds,
+        now_ms: u64,
+    ) -> Result<(), PayloadError> {
+        validate_reading(&p.reading, bounds)?;
+        if p.window_start_unix_ms > p.window_end_unix_ms {
+            return Err(PayloadError::InvertedWindow {
+                start_unix_ms: p.window_start_unix_ms,
+                end_unix_ms: p.window_end_unix_ms,
+            });
+        }
+        check_not_future(p.window_end_unix_ms, now_ms, bounds.max_clock_skew_ms)
+    }
+
+    pub fn validate_telemetry(
+        s: &TelemetrySample,
+        bounds: &PayloadBounds,
+    ) -> Result<(), PayloadError> {
+        Self::validate_telemetry_at(s, bounds, now_unix_ms())
+    }
+
+    pub fn validate_telemetry_at(
+        s: &TelemetrySample,
+        bounds: &PayloadBounds,
+        now_ms: u64,
+    ) -> Result<(), PayloadError> {
+        validate_reading(&s.reading, bounds)?;
+        check_not_future(s.sampled_at_unix_ms, now_ms, bounds.max_clock_skew_ms)
+    }
+
+    // Similar guards can be defined for export filters.
+}
+
+/// Measurement fields shared by shard writes and telemetry samples; these
+/// feed CEIM directly, so they must parse and be physically plausible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Reading {
+    pub node_id: String,
+    pub pollutant: String,
+    pub unit: String,
+    pub cin: f64,
+    pub cout: f64,
+    pub airflow_m3_per_s: f64,
+    pub period_s: f64,
+}
+
+/// qpudatashard row update covering one averaging window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ShardWritePayload {
+    #[serde(flatten)]
+    pub reading: Reading,
+    pub window_start_unix_ms: u64,
+    pub window_end_unix_ms: u64,
+}
+
+/// One telemetry point from a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TelemetrySample {
+    #[serde(flatten)]
+    pub reading: Reading,
+    pub sampled_at_unix_ms: u64,
+}
+
+/// Physical plausibility limits for incoming readings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PayloadBounds {
+    pub airflow_m3_per_s: (f64, f64),
+    pub period_s: (f64, f64),
+    /// Allowed `cout - cin` as a fraction of `cin`, for sensor noise.
+    pub cout_tolerance: f64,
+    /// How far ahead of the verifier's clock a timestamp may be.
+    pub max_clock_skew_ms: u64,
+}
+
+impl Default for PayloadBounds {
+    fn default() -> Self {
+        Self {
+            airflow_m3_per_s: (0.0, 100.0),
+            period_s: (1.0, 86_400.0),
+            cout_tolerance: 0.02,
+            max_clock_skew_ms: 300_000,
         }
-        if !(0.0..=1.0).contains(&p.new_duty_cycle) {
-            return Err("new_duty_cycle must be between 0.0 and 1.0".into());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PayloadError {
+    EmptyNodeId,
+    UnknownPollutant(String),
+    UnknownUnit(String),
+    NegativeConcentration {
+        field: &'static str,
+        value: f64,
+    },
+    OutletExceedsInlet {
+        cin: f64,
+        cout: f64,
+        tolerance: f64,
+    },
+    OutOfBounds {
+        field: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    InvertedWindow {
+        start_unix_ms: u64,
+        end_unix_ms: u64,
+    },
+    FutureTimestamp {
+        timestamp_unix_ms: u64,
+        now_unix_ms: u64,
+        max_skew_ms: u64,
+    },
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::EmptyNodeId => write!(f, "node_id must not be empty"),
+            PayloadError::UnknownPollutant(p) => write!(f, "unknown pollutant {p:?}"),
+            PayloadError::UnknownUnit(u) => write!(f, "unknown concentration unit {u:?}"),
+            PayloadError::NegativeConcentration { field, value } => {
+                write!(f, "{field} must be a non-negative number (got {value})")
+            }
+            PayloadError::OutletExceedsInlet {
+                cin,
+                cout,
+                tolerance,
+            } => write!(
+                f,
+                "cout {cout} exceeds cin {cin} beyond tolerance {tolerance}"
+            ),
+            PayloadError::OutOfBounds {
+                field,
+                value,
+                min,
+                max,
+            } => write!(f, "{field} {value} outside [{min}, {max}]"),
+            PayloadError::InvertedWindow {
+                start_unix_ms,
+                end_unix_ms,
+            } => write!(
+                f,
+                "window starts at {start_unix_ms} after it ends at {end_unix_ms}"
+            ),
+            PayloadError::FutureTimestamp {
+                timestamp_unix_ms,
+                now_unix_ms,
+                max_skew_ms,
+            } => write!(
+                f,
+                "timestamp {timestamp_unix_ms} is more than {max_skew_ms} ms ahead of {now_unix_ms}"
+            ),
         }
-        if p.horizon_seconds == 0 {
-            return Err("horizon_seconds must be > 0".into());
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+fn validate_reading(r: &Reading, bounds: &PayloadBounds) -> Result<(), PayloadError> {
+    if r.node_id.is_empty() {
+        return Err(PayloadError::EmptyNodeId);
+    }
+    if r.pollutant.parse::<Pollutant>().is_err() {
+        return Err(PayloadError::UnknownPollutant(r.pollutant.clone()));
+    }
+    if r.unit.parse::<ConcentrationUnit>().is_err() {
+        return Err(PayloadError::UnknownUnit(r.unit.clone()));
+    }
+    for (field, value) in [("cin", r.cin), ("cout", r.cout)] {
+        // Written this way so NaN is rejected too.
+        if !(value >= 0.0 && value.is_finite()) {
+            return Err(PayloadError::NegativeConcentration { field, value });
         }
+    }
+    let tolerance = r.cin * bounds.cout_tolerance;
+    if r.cout > r.cin + tolerance {
+        return Err(PayloadError::OutletExceedsInlet {
+            cin: r.cin,
+            cout: r.cout,
+            tolerance,
+        });
+    }
+    check_range(
+        "airflow_m3_per_s",
+        r.airflow_m3_per_s,
+        bounds.airflow_m3_per_s,
+    )?;
+    check_range("period_s", r.period_s, bounds.period_s)
+}
+
+fn check_range(
+    field: &'static str,
+    value: f64,
+    (min, max): (f64, f64),
+) -> Result<(), PayloadError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(PayloadError::OutOfBounds {
+            field,
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+fn check_not_future(timestamp_ms: u64, now_ms: u64, max_skew_ms: u64) -> Result<(), PayloadError> {
+    if timestamp_ms > now_ms.saturating_add(max_skew_ms) {
+        Err(PayloadError::FutureTimestamp {
+            timestamp_unix_ms: timestamp_ms,
+            now_unix_ms: now_ms,
+            max_skew_ms,
+        })
+    } else {
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_750_000_000_000;
+
+    fn reading() -> Reading {
+        Reading {
+            node_id: "CYB-AIR-CANOPY-01".into(),
+            pollutant: "PM2.5".into(),
+            unit: "ug/m3".into(),
+            cin: 40.0,
+            cout: 28.0,
+            airflow_m3_per_s: 3.0,
+            period_s: 3600.0,
+        }
+    }
+
+    fn sample(reading: Reading) -> TelemetrySample {
+        TelemetrySample {
+            reading,
+            sampled_at_unix_ms: NOW,
+        }
+    }
+
+    fn check(reading: Reading) -> Result<(), PayloadError> {
+        InputGuard::validate_telemetry_at(&sample(reading), &PayloadBounds::default(), NOW)
+    }
+
+    #[test]
+    fn valid_payloads_pass() {
+        assert_eq!(check(reading()), Ok(()));
+        let write = ShardWritePayload {
+            reading: reading(),
+            window_start_unix_ms: NOW - 3_600_000,
+            window_end_unix_ms: NOW,
+        };
+        assert_eq!(
+            InputGuard::validate_shard_write_at(&write, &PayloadBounds::default(), NOW),
+            Ok(())
+        );
+        // Flattened wire shape.
+        let json = r#"{"node_id":"n1","pollutant":"O3","unit":"ppb","cin":40,"cout":35,
+            "airflow_m3_per_s":1.0,"period_s":60,"sampled_at_unix_ms":1750000000000}"#;
+        let parsed: TelemetrySample = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            InputGuard::validate_telemetry_at(&parsed, &PayloadBounds::default(), NOW),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_unit_and_pollutant() {
+        let mut r = reading();
+        r.unit = "g/ft3".into();
+        assert_eq!(check(r), Err(PayloadError::UnknownUnit("g/ft3".into())));
+        let mut r = reading();
+        r.pollutant = "radon".into();
+        assert_eq!(
+            check(r),
+            Err(PayloadError::UnknownPollutant("radon".into()))
+        );
+        let mut r = reading();
+        r.node_id.clear();
+        assert_eq!(check(r), Err(PayloadError::EmptyNodeId));
+    }
+
+    #[test]
+    fn rejects_negative_and_nan_concentrations() {
+        let mut r = reading();
+        r.cin = -1.0;
+        assert!(matches!(
+            check(r),
+            Err(PayloadError::NegativeConcentration { field: "cin", .. })
+        ));
+        let mut r = reading();
+        r.cout = f64::NAN;
+        assert!(matches!(
+            check(r),
+            Err(PayloadError::NegativeConcentration { field: "cout", .. })
+        ));
+    }
+
+    #[test]
+    fn outlet_may_exceed_inlet_only_within_tolerance() {
+        let mut r = reading();
+        r.cout = 40.5; // 2% of 40 is 0.8.
+        assert_eq!(check(r), Ok(()));
+        let mut r = reading();
+        r.cout = 41.0;
+        assert!(matches!(
+            check(r),
+            Err(PayloadError::OutletExceedsInlet { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_implausible_airflow_and_period() {
+        let mut r = reading();
+        r.airflow_m3_per_s = 1_000.0;
+        assert!(matches!(
+            check(r),
+            Err(PayloadError::OutOfBounds {
+                field: "airflow_m3_per_s",
+                ..
+            })
+        ));
+        let mut r = reading();
+        r.period_s = 0.0;
+        assert!(matches!(
+            check(r),
+            Err(PayloadError::OutOfBounds {
+                field: "period_s",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_future_timestamps_beyond_skew() {
+        let bounds = PayloadBounds::default();
+        let mut s = sample(reading());
+        s.sampled_at_unix_ms = NOW + bounds.max_clock_skew_ms;
+        assert_eq!(InputGuard::validate_telemetry_at(&s, &bounds, NOW), Ok(()));
+        s.sampled_at_unix_ms += 1;
+        assert!(matches!(
+            InputGuard::validate_telemetry_at(&s, &bounds, NOW),
+            Err(PayloadError::FutureTimestamp { .. })
+        ));
+
+        let write = ShardWritePayload {
+            reading: reading(),
+            window_start_unix_ms: NOW,
+            window_end_unix_ms: NOW - 1,
+        };
+        assert!(matches!(
+            InputGuard::validate_shard_write_at(&write, &bounds, NOW),
+            Err(PayloadError::InvertedWindow { .. })
+        ));
+    }
 
-    // Similar guards can be defined for:
-    // - shard write payloads (qpudatashard updates),
-    // - telemetry streams,
-    // - export filters.
+    fn directive(node_id: &str, duty: f64) -> NodeDirective {
+        NodeDirective {
+            node_id: node_id.into(),
+            new_duty_cycle: duty,
+            horizon_seconds: 300,
+            ramp_shape: None,
+        }
+    }
+
+    #[test]
+    fn duplicate_nodes_are_rejected() {
+        let proposal = ControlProposal {
+            directives: vec![
+                directive("CYB-AIR-CANOPY-01", 0.4),
+                directive("CYB-AIR-SCHOOL-05", 0.2),
+                directive("CYB-AIR-CANOPY-01", 0.4),
+            ],
+        };
+        assert_eq!(
+            InputGuard::control_proposal_errors(&proposal),
+            [ProposalError::DuplicateNode {
+                node_id: "CYB-AIR-CANOPY-01".into()
+            }]
+        );
+        assert!(InputGuard::validate_control_proposal(&ControlProposal {
+            directives: proposal.directives[..2].to_vec()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn empty_proposals_are_rejected() {
+        assert_eq!(
+            InputGuard::validate_control_proposal(&ControlProposal::default()),
+            Err(ProposalError::EmptyProposal)
+        );
+        let empty: ControlProposal = serde_json::from_str(r#"{"directives":[]}"#).unwrap();
+        assert_eq!(
+            InputGuard::control_proposal_errors(&empty),
+            [ProposalError::EmptyProposal]
+        );
+        let verdict = crate::pipeline::Verifier::verify(&empty);
+        assert!(!verdict.approved);
+        assert!(verdict.has_code(ReasonCode::InvalidProposal));
+    }
+
+    #[test]
+    fn directive_count_is_capped() {
+        let at_cap = ControlProposal {
+            directives: (0..MAX_DIRECTIVES)
+                .map(|i| directive(&format!("node_{i:03}"), 0.5))
+                .collect(),
+        };
+        assert_eq!(InputGuard::validate_control_proposal(&at_cap), Ok(()));
+
+        let mut over = at_cap;
+        over.directives.push(directive("node_999", 0.5));
+        assert_eq!(
+            InputGuard::validate_control_proposal(&over),
+            Err(ProposalError::TooManyDirectives {
+                count: MAX_DIRECTIVES + 1,
+                max: MAX_DIRECTIVES
+            })
+        );
+    }
+
+    #[test]
+    fn each_directive_is_checked() {
+        let mut zero_horizon = directive("node_02", 0.5);
+        zero_horizon.horizon_seconds = 0;
+        let mut bad_ramp = directive("node_03", 0.5);
+        bad_ramp.ramp_shape = Some(RampShape::ExpApproach { tau_s: 0.0 });
+        let proposal = ControlProposal {
+            directives: vec![
+                directive("", 0.5),
+                directive("node_01", f64::NAN),
+                zero_horizon,
+                bad_ramp,
+            ],
+        };
+        let errors = InputGuard::control_proposal_errors(&proposal);
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0], ProposalError::EmptyNodeId { index: 0 });
+        assert!(
+            matches!(&errors[1], ProposalError::DutyOutOfRange { node_id, .. } if node_id == "node_01")
+        );
+        assert_eq!(
+            errors[2],
+            ProposalError::ZeroHorizon {
+                node_id: "node_02".into()
+            }
+        );
+        assert_eq!(
+            errors[3],
+            ProposalError::InvalidRamp {
+                node_id: "node_03".into(),
+                tau_s: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn legacy_single_node_shape_decodes() {
+        let legacy: ControlProposal = serde_json::from_str(
+            r#"{"node_id":"CYB-AIR-CANOPY-01","new_duty_cycle":0.4,"horizon_seconds":300}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy, directive("CYB-AIR-CANOPY-01", 0.4).into());
+
+        // Re-encoding always produces the directives shape.
+        let json = serde_json::to_value(&legacy).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"directives": [
+                {"node_id": "CYB-AIR-CANOPY-01", "new_duty_cycle": 0.4, "horizon_seconds": 300}
+            ]})
+        );
+        assert_eq!(
+            serde_json::from_value::<ControlProposal>(json).unwrap(),
+            legacy
+        );
+
+        let ramped: ControlProposal = serde_json::from_str(
+            r#"{"directives":[{"node_id":"n1","new_duty_cycle":0.1,"horizon_seconds":60,"ramp_shape":"s_curve"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(ramped.directives[0].ramp_shape, Some(RampShape::SCurve));
+
+        let err = serde_json::from_str::<ControlProposal>(r#"{"node_id":"n1"}"#).unwrap_err();
+        assert!(err.to_string().contains("new_duty_cycle"), "{err}");
+    }
 }
@@ -1,14 +1,62 @@
 #![forbid(unsafe_code)]
 
+pub mod approval;
+pub mod audit;
+#[cfg(feature = "bee-kernel")]
+pub mod bee_kernel;
+pub mod cache;
+pub mod chain;
+pub mod clock;
+pub mod delegation;
+pub mod dry_run;
+pub mod emergency;
+pub mod enrich;
+pub mod escalation;
+pub mod export;
+pub mod guards;
+pub mod pipeline;
+pub mod policy;
+pub mod reason;
+pub mod request;
+pub mod roh;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "ed25519-dalek")]
+pub mod signing;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use gatehouse::{AccessDecision, Policy, PolicyEvalResult};
 use serde::{Deserialize, Serialize};
-use gatehouse::{
-    AccessEvaluation, PermissionChecker, Policy, PolicyEvalResult,
+
+use cybo_corridor_core::EscalationAction;
+use cyboair_corridor_safety::{
+    compute_karma_bytes, compute_mass_kg_at_duty, AirflowModel, AirflowModels, CorridorRow,
 };
-use async_trait::async_trait;
+
+use crate::audit::{AuditDecision, AuditEntry, AuditKind, AuditSink, PolicyTrailEntry};
+use crate::cache::{DecisionCache, DecisionCacheConfig, DecisionKey};
+use crate::clock::{Clock, SystemClock};
+use crate::delegation::{DelegationGrant, DelegationPolicy, DelegationStore};
+use crate::escalation::EscalationActionGate;
+use crate::reason::{write_reasons, ReasonCode, VerdictReason};
+use crate::request::{timed, PolicyTiming, RequestContext};
+use crate::roh::{RohInputs, RohModel, ROH_CEILING};
 
 // ---- Domain core types ----------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Role {
     Superchair,
     Stakeholder,
@@ -17,7 +65,8 @@ pub enum Role {
     Bot,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Action {
     ReadShard,
     WriteTelemetry,
@@ -25,6 +74,7 @@ pub enum Action {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Principal {
     pub id: String,
     pub role: Role,
@@ -33,6 +83,7 @@ pub struct Principal {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Resource {
     pub resource_id: String,
     /// Owner DID or stakeholder id for ABAC checks.
@@ -43,7 +94,19 @@ pub struct Resource {
 
 // Simple context wrapper if you need extra metadata (tenant, time, etc.).
 #[derive(Debug, Clone, Default)]
-pub struct GovContext;
+pub struct GovContext {
+    /// Set by front ends so the decision and its audit entry can be
+    /// correlated with the inbound request.
+    pub request: Option<RequestContext>,
+}
+
+impl GovContext {
+    pub fn for_request(request: RequestContext) -> Self {
+        Self {
+            request: Some(request),
+        }
+    }
+}
 
 // ---- Gatehouse policies: RBAC + ABAC composition -------------------------
 
@@ -111,10 +174,7 @@ impl Policy<Principal, Resource, Action, GovContext> for AbacPolicy {
                     );
                 }
             } else {
-                return PolicyEvalResult::denied(
-                    "AbacPolicy",
-                    "resource has no owner metadata",
-                );
+                return PolicyEvalResult::denied("AbacPolicy", "resource has no owner metadata");
             }
         }
 
@@ -144,16 +204,162 @@ impl Policy<Principal, Resource, Action, GovContext> for AbacPolicy {
 
 // ---- GovernanceCore: single entry point for callers -----------------------
 
+/// How per-policy results combine into the final decision.
+///
+/// Every registered policy is evaluated and kept in the trail regardless of
+/// strategy. Policies here either grant or deny, so `DenyOverrides` and
+/// `AllMustGrant` only differ when no policy is registered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CombinationStrategy {
+    /// Any deny wins; otherwise granted (also with no policies).
+    DenyOverrides,
+    /// Any single grant suffices.
+    GrantOverrides,
+    /// Every policy must grant, and at least one must be registered.
+    #[default]
+    AllMustGrant,
+}
+
+impl CombinationStrategy {
+    pub fn combine(self, trail: &[PolicyTrailEntry]) -> AccessDecision {
+        let granted = match self {
+            CombinationStrategy::DenyOverrides => trail.iter().all(|p| p.granted),
+            CombinationStrategy::GrantOverrides => trail.iter().any(|p| p.granted),
+            CombinationStrategy::AllMustGrant => {
+                !trail.is_empty() && trail.iter().all(|p| p.granted)
+            }
+        };
+        if granted {
+            AccessDecision::Granted
+        } else {
+            AccessDecision::Denied
+        }
+    }
+}
+
+/// Final decision of `GovernanceCore::authorize` with the full policy trail.
+#[derive(Debug, Clone)]
+pub struct Authorization {
+    pub decision: AccessDecision,
+    pub strategy: CombinationStrategy,
+    /// Every registered policy, in registration order, followed by
+    /// `DelegationPolicy` when a delegation was consulted.
+    pub trail: Vec<PolicyTrailEntry>,
+    /// The request this decision answers, from `GovContext::request`.
+    pub request: Option<RequestContext>,
+    /// Evaluation time of each `trail` entry, in the same order; empty when
+    /// the decision came from the decision cache.
+    pub timings: Vec<PolicyTiming>,
+}
+
+type GovPolicy = dyn Policy<Principal, Resource, Action, GovContext>;
+
 pub struct GovernanceCore {
-    checker: PermissionChecker<Principal, Resource, Action, GovContext>,
+    policies: Vec<Box<GovPolicy>>,
+    strategy: CombinationStrategy,
+    delegation: DelegationPolicy,
+    delegations: DelegationStore,
+    audit: Option<Arc<dyn AuditSink>>,
+    clock: Arc<dyn Clock>,
+    cache: Option<DecisionCache>,
+}
+
+impl Default for GovernanceCore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GovernanceCore {
+    /// RBAC + ABAC under `CombinationStrategy::AllMustGrant`, on
+    /// `SystemClock`.
     pub fn new() -> Self {
-        let mut checker = PermissionChecker::new();
-        checker.add_policy(RbacPolicy);
-        checker.add_policy(AbacPolicy);
-        Self { checker }
+        let delegations = DelegationStore::new();
+        let mut core = Self {
+            policies: Vec::new(),
+            strategy: CombinationStrategy::default(),
+            delegation: DelegationPolicy::new(delegations.clone()),
+            delegations,
+            audit: None,
+            clock: Arc::new(SystemClock),
+            cache: None,
+        };
+        core.add_policy(RbacPolicy);
+        core.add_policy(AbacPolicy);
+        core
+    }
+
+    /// Same policies as `new`, with every decision recorded to `sink`.
+    pub fn new_with_sink(sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            audit: Some(sink),
+            ..Self::new()
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: CombinationStrategy) -> Self {
+        self.strategy = strategy;
+        self.flush_decision_cache();
+        self
+    }
+
+    /// Read `clock` for delegation expiry and audit timestamps.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.delegation = DelegationPolicy::with_clock(self.delegations.clone(), clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Reuse `authorize` outcomes for up to the configured TTLs, keyed by
+    /// principal id, role, action, resource and their attributes. Cache
+    /// hits are still audited. Decisions that consulted a delegation are
+    /// never cached, since the grant may expire or be revoked at any time.
+    pub fn with_decision_cache(mut self, config: DecisionCacheConfig) -> Self {
+        self.cache = Some(DecisionCache::new(config));
+        self
+    }
+
+    pub fn decision_cache(&self) -> Option<&DecisionCache> {
+        self.cache.as_ref()
+    }
+
+    /// Forget cached decisions about `resource_id`, e.g. after its owner or
+    /// attributes changed; returns how many were dropped.
+    pub fn invalidate_resource(&self, resource_id: &str) -> usize {
+        self.cache
+            .as_ref()
+            .map_or(0, |cache| cache.invalidate_resource(resource_id))
+    }
+
+    /// Forget every cached decision. Role and attribute changes need no
+    /// flush, as both are part of the cache key.
+    pub fn flush_decision_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Register a deployment-specific policy after the built-in ones.
+    pub fn add_policy<P>(&mut self, policy: P)
+    where
+        P: Policy<Principal, Resource, Action, GovContext> + 'static,
+    {
+        self.policies.push(Box::new(policy));
+        self.flush_decision_cache();
+    }
+
+    /// Register a temporary elevation; returns the id used to revoke it.
+    /// Cached denials on the grant's resource are dropped, and so are
+    /// grants that have expired.
+    pub fn register_delegation(&self, grant: DelegationGrant) -> u64 {
+        self.invalidate_resource(&grant.resource_id);
+        self.delegations.prune(self.clock.now_unix_ms());
+        self.delegations.register(grant)
+    }
+
+    pub fn revoke_delegation(&self, id: u64) -> bool {
+        self.delegations.revoke(id)
     }
 
     pub async fn authorize(
@@ -162,10 +368,223 @@ impl GovernanceCore {
         action: &Action,
         resource: &Resource,
         ctx: &GovContext,
-    ) -> AccessEvaluation {
-        self.checker
-            .evaluate_access(principal, action, resource, ctx)
-            .await
+    ) -> Authorization {
+        let kind = AuditKind::Access {
+            action: action.clone(),
+        };
+        let cached = self.cache.as_ref().map(|cache| {
+            let key = DecisionKey::new(principal, action, resource);
+            (cache, key)
+        });
+        if let Some((cache, key)) = &cached {
+            if let Some(hit) = cache.get(key, self.clock.now_unix_ms()) {
+                let request = ctx.request.as_ref();
+                let hit = Authorization {
+                    request: request.cloned(),
+                    timings: Vec::new(),
+                    ..hit
+                };
+                self.record(
+                    principal,
+                    kind,
+                    resource,
+                    &hit.decision,
+                    &hit.trail,
+                    request,
+                );
+                return hit;
+            }
+        }
+
+        let request = ctx.request.as_ref();
+        let mut trail = Vec::with_capacity(self.policies.len());
+        let mut timings = Vec::with_capacity(self.policies.len());
+        for policy in &self.policies {
+            let evaluation = policy.evaluate_access(principal, action, resource, ctx);
+            let (result, timing) = timed(policy.policy_type(), request, evaluation).await;
+            timings.push(timing);
+            let (granted, reason) = trail_outcome(result);
+            trail.push(PolicyTrailEntry {
+                policy: policy.policy_type(),
+                granted,
+                reason,
+            });
+        }
+        let mut decision = self.strategy.combine(&trail);
+
+        // A live delegation lifts an RBAC denial and nothing else: every
+        // other policy must still grant. It is only consulted (and recorded)
+        // when a grant names this exact request.
+        let rbac = RbacPolicy.policy_type();
+        let delegated = matches!(decision, AccessDecision::Denied)
+            && trail.iter().all(|p| p.granted || p.policy == rbac)
+            && self.delegations.has_grant_for(principal, action, resource);
+        if delegated {
+            let evaluation = self.evaluate_delegation(principal, action, resource, ctx);
+            let (result, timing) = timed(self.delegation.policy_type(), request, evaluation).await;
+            timings.push(timing);
+            let (granted, reason) = trail_outcome(result);
+            if granted {
+                decision = AccessDecision::Granted;
+            }
+            trail.push(PolicyTrailEntry {
+                policy: self.delegation.policy_type(),
+                granted,
+                reason,
+            });
+        }
+
+        self.record(principal, kind, resource, &decision, &trail, request);
+
+        let authorization = Authorization {
+            decision,
+            strategy: self.strategy,
+            trail,
+            request: request.cloned(),
+            timings,
+        };
+        if let Some((cache, key)) = cached.filter(|_| !delegated) {
+            cache.insert(key, authorization.clone(), self.clock.now_unix_ms());
+        }
+        authorization
+    }
+
+    /// `DelegationPolicy` as the core applies it: the first unexpired grant
+    /// whose grantor the registered policies, combined as usual, grant the
+    /// same request right now. Expired grants are pruned afterwards.
+    async fn evaluate_delegation(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+        ctx: &GovContext,
+    ) -> PolicyEvalResult {
+        let now_ms = self.clock.now_unix_ms();
+        let result = match self
+            .delegation
+            .unexpired(principal, action, resource, now_ms)
+        {
+            Ok(grants) => {
+                let mut result = None;
+                for grant in &grants {
+                    if self.holds(&grant.grantor, action, resource, ctx).await {
+                        result = Some(delegation::delegated_by(grant));
+                        break;
+                    }
+                }
+                result.unwrap_or_else(|| {
+                    let last = grants.last().expect("unexpired grants are never empty");
+                    PolicyEvalResult::denied(
+                        "DelegationPolicy",
+                        delegation::grantor_lacks(last, action),
+                    )
+                })
+            }
+            Err(reason) => PolicyEvalResult::denied("DelegationPolicy", reason),
+        };
+        self.delegations.prune(now_ms);
+        result
+    }
+
+    /// Whether the registered policies grant `principal` the request, with
+    /// no delegation, cache or audit involved.
+    async fn holds(
+        &self,
+        principal: &Principal,
+        action: &Action,
+        resource: &Resource,
+        ctx: &GovContext,
+    ) -> bool {
+        let mut trail = Vec::with_capacity(self.policies.len());
+        for policy in &self.policies {
+            let result = policy
+                .evaluate_access(principal, action, resource, ctx)
+                .await;
+            let (granted, reason) = trail_outcome(result);
+            trail.push(PolicyTrailEntry {
+                policy: policy.policy_type(),
+                granted,
+                reason,
+            });
+        }
+        matches!(self.strategy.combine(&trail), AccessDecision::Granted)
+    }
+
+    /// Gate a corridor escalation through `EscalationActionGate` only; the
+    /// combination strategy does not apply and the trail has one entry.
+    /// Denials are audited as `AuditKind::SuppressedEscalation`.
+    pub async fn authorize_escalation(
+        &self,
+        principal: &Principal,
+        action: &EscalationAction,
+        resource: &Resource,
+    ) -> Authorization {
+        let gate = EscalationActionGate;
+        let ctx = GovContext::default();
+        let evaluation = gate.evaluate_access(principal, action, resource, &ctx);
+        let (result, timing) = timed(gate.policy_type(), None, evaluation).await;
+        let (granted, reason) = trail_outcome(result);
+        let trail = vec![PolicyTrailEntry {
+            policy: gate.policy_type(),
+            granted,
+            reason,
+        }];
+        let (decision, kind) = if granted {
+            let kind = AuditKind::Escalation {
+                action: action.clone(),
+            };
+            (AccessDecision::Granted, kind)
+        } else {
+            let kind = AuditKind::SuppressedEscalation {
+                action: action.clone(),
+            };
+            (AccessDecision::Denied, kind)
+        };
+        self.record(principal, kind, resource, &decision, &trail, None);
+
+        Authorization {
+            decision,
+            strategy: self.strategy,
+            trail,
+            request: None,
+            timings: vec![timing],
+        }
+    }
+
+    fn record(
+        &self,
+        principal: &Principal,
+        kind: AuditKind,
+        resource: &Resource,
+        decision: &AccessDecision,
+        trail: &[PolicyTrailEntry],
+        request: Option<&RequestContext>,
+    ) {
+        if let Some(sink) = &self.audit {
+            sink.record(AuditEntry {
+                principal_id: principal.id.clone(),
+                role: principal.role.clone(),
+                kind,
+                resource_id: resource.resource_id.clone(),
+                decision: match decision {
+                    AccessDecision::Granted => AuditDecision::Granted,
+                    AccessDecision::Denied => AuditDecision::Denied,
+                },
+                policy_trail: trail.to_vec(),
+                request: request.cloned(),
+                node_traces: vec![],
+                timestamp_unix_ms: self.clock.now_unix_ms(),
+            });
+        }
+    }
+}
+
+fn trail_outcome(result: PolicyEvalResult) -> (bool, Option<String>) {
+    match result {
+        PolicyEvalResult::Granted { reason, .. } => (true, reason),
+        PolicyEvalResult::Denied { reason, .. } => (false, Some(reason)),
+        #[allow(unreachable_patterns)]
+        other => (other.is_granted(), None),
     }
 }
 
@@ -190,17 +609,78 @@ impl InputGuard {
 
 // ---- Generator–verifier pipeline types -----------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct Proposal {
-    pub node_ids: Vec<String>,
-    pub duty_cycles: Vec<f64>,
-    // plus CEIM, NanoKarma, Beekarma deltas, horizon, etc.
-}
+/// The generator–verifier pipeline's name for [`guards::ControlProposal`].
+pub type Proposal = guards::ControlProposal;
 
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Verdict {
+    /// True only in `VerdictState::Approved`; the proposal may execute.
     pub approved: bool,
-    pub message: String,
+    pub state: VerdictState,
+    /// Every finding, in check order; `Display` joins their messages.
+    pub reasons: Vec<VerdictReason>,
+    /// Repaired duties for the rejected directives, from a pipeline with
+    /// repair enabled; see [`pipeline::VerifierPipeline::with_repair`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<pipeline::DutySuggestion>,
+    /// Set when stages were skipped because a dependency was down; see
+    /// [`pipeline::FailureMode::FailOpenWithFlag`]. Such a verdict is not a
+    /// full verification, whatever `state` says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<pipeline::DegradedVerification>,
+}
+
+impl Verdict {
+    pub fn approve(reason: VerdictReason) -> Self {
+        Self {
+            approved: true,
+            state: VerdictState::Approved,
+            reasons: vec![reason],
+            suggestions: Vec::new(),
+            degraded: None,
+        }
+    }
+
+    pub fn reject(reasons: Vec<VerdictReason>) -> Self {
+        Self {
+            approved: false,
+            state: VerdictState::Rejected,
+            reasons,
+            suggestions: Vec::new(),
+            degraded: None,
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.is_some()
+    }
+
+    pub fn has_code(&self, code: ReasonCode) -> bool {
+        self.reasons.iter().any(|r| r.code == code)
+    }
+}
+
+impl fmt::Display for Verdict {
+    /// Degraded verdicts lead with `DEGRADED (skipped: ...): `.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(degraded) = &self.degraded {
+            write!(f, "{degraded}: ")?;
+        }
+        write_reasons(f, &self.reasons)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum VerdictState {
+    Approved,
+    Rejected,
+    /// Passed the automated checks but needs `required` human approvals.
+    PendingQuorum {
+        required: usize,
+        received: usize,
+    },
 }
 
 pub struct Generator;
@@ -210,10 +690,7 @@ impl Generator {
     pub fn generate_proposal(_task: &str) -> Proposal {
         // In production, this would call your LLM/heuristic and
         // enforce output schema.
-        Proposal {
-            node_ids: Vec::new(),
-            duty_cycles: Vec::new(),
-        }
+        Proposal::default()
     }
 }
 
@@ -221,34 +698,219 @@ pub struct Verifier;
 
 impl Verifier {
     /// Core safety and governance checks; only source of "approved".
+    /// Every failing check is reported, not just the first.
     pub fn verify(proposal: &Proposal) -> Verdict {
-        // 1. Size and basic sanity constraints.
-        if proposal.node_ids.len() != proposal.duty_cycles.len() {
-            return Verdict {
-                approved: false,
-                message: "node_ids and duty_cycles length mismatch".into(),
-            };
+        let reasons = Self::structural_reasons(proposal);
+
+        // CEIM corridor budgets need shard rows: see `verify_with_shard`.
+        // RoH_after <= RoH_before <= 0.3 needs stressor inputs: see `verify_roh`.
+        // Deployment-specific checks compose as stages: see `pipeline::VerifierPipeline`.
+        // TODO: integrate NanoKarma, Beekarma, BeeSafetyKernel:
+        // - enforce BeeNeuralSafe & BeeHBScore invariants,
+        // - enforce TECHPolicyDocument budgets.
+
+        if reasons.is_empty() {
+            Verdict::approve(VerdictReason::new(
+                ReasonCode::Passed,
+                "proposal passed core governance checks",
+            ))
+        } else {
+            Verdict::reject(reasons)
         }
+    }
 
-        // 2. Local numeric checks.
-        for dc in &proposal.duty_cycles {
-            if let Err(e) = InputGuard::validate_duty_cycle(*dc) {
-                return Verdict {
-                    approved: false,
-                    message: format!("invalid duty_cycle: {e}"),
-                };
+    /// `verify` over a JSON-encoded `Proposal`. Malformed input is a
+    /// rejection with `InvalidProposal`, so callers always get a verdict.
+    pub fn verify_json(json: &str) -> Verdict {
+        match serde_json::from_str::<Proposal>(json) {
+            Ok(proposal) => Self::verify(&proposal),
+            Err(e) => Verdict::reject(vec![VerdictReason::new(
+                ReasonCode::InvalidProposal,
+                format!("invalid proposal JSON: {e}"),
+            )]),
+        }
+    }
+
+    /// `guards::InputGuard`'s directive checks; the first stage of every
+    /// pipeline.
+    pub(crate) fn structural_reasons(proposal: &Proposal) -> Vec<VerdictReason> {
+        guards::InputGuard::control_proposal_errors(proposal)
+            .iter()
+            .map(VerdictReason::from)
+            .collect()
+    }
+}
+
+/// Corridor-aggregate CEIM budgets for one control step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CorridorBudgets {
+    pub max_mass_kg: f64,
+    pub max_karma_nb: f64,
+    /// Air temperature for mixing-ratio unit conversion (K).
+    pub temperature_k: f64,
+    /// How airflow follows the proposed duty; linear unless configured.
+    #[serde(default)]
+    pub airflow: AirflowModels,
+}
+
+impl Verifier {
+    /// `verify`, plus the CEIM corridor step: each node's shard row is
+    /// projected with airflow at its proposed duty cycle under
+    /// `budgets.airflow`, and the
+    /// proposal is rejected once corridor-aggregate mass or karma exceeds
+    /// `budgets`. The message names the node that tripped the budget.
+    pub fn verify_with_shard(
+        proposal: &Proposal,
+        rows: &HashMap<String, CorridorRow>,
+        budgets: &CorridorBudgets,
+    ) -> Verdict {
+        let verdict = Self::verify(proposal);
+        if !verdict.approved {
+            return verdict;
+        }
+        match Self::ceim_totals(proposal, rows, budgets) {
+            Ok((mass_total, karma_total)) => Verdict::approve(VerdictReason::new(
+                ReasonCode::Passed,
+                format!(
+                    "proposal within CEIM corridor budgets (mass {mass_total:.3e} kg, karma {karma_total:.3e})"
+                ),
+            )),
+            Err(reasons) => Verdict::reject(reasons),
+        }
+    }
+
+    /// Corridor-aggregate (mass kg, karma) for a structurally valid
+    /// proposal, or every CEIM finding against it.
+    pub(crate) fn ceim_totals(
+        proposal: &Proposal,
+        rows: &HashMap<String, CorridorRow>,
+        budgets: &CorridorBudgets,
+    ) -> Result<(f64, f64), Vec<VerdictReason>> {
+        let mut reasons = Vec::new();
+        let mut mass_total = 0.0;
+        let mut karma_total = 0.0;
+        for directive in &proposal.directives {
+            let (node_id, dc) = (&directive.node_id, directive.new_duty_cycle);
+            let Some(row) = rows.get(node_id) else {
+                reasons.push(
+                    VerdictReason::new(
+                        ReasonCode::MissingShardRow,
+                        format!("no shard row for node {node_id}"),
+                    )
+                    .for_node(node_id.clone()),
+                );
+                continue;
+            };
+            let model = budgets.airflow.for_row(row);
+            let (projected, mass) = match project_ceim(row, dc, model, budgets.temperature_k) {
+                Ok(projection) => projection,
+                Err(e) => {
+                    reasons.push(
+                        VerdictReason::new(
+                            ReasonCode::CeimProjectionFailed,
+                            format!("node {node_id}: CEIM projection failed: {e}"),
+                        )
+                        .for_node(node_id.clone()),
+                    );
+                    continue;
+                }
+            };
+            let was_within = (
+                mass_total <= budgets.max_mass_kg,
+                karma_total <= budgets.max_karma_nb,
+            );
+            mass_total += mass;
+            karma_total += compute_karma_bytes(&projected, mass);
+
+            // Each budget is reported once, against the node that tripped it.
+            if was_within.0 && mass_total > budgets.max_mass_kg {
+                reasons.push(
+                    VerdictReason::new(
+                        ReasonCode::CeimBudgetExceeded,
+                        format!(
+                            "node {node_id}: corridor mass budget exceeded ({mass_total:.3e} kg > {:.3e} kg)",
+                            budgets.max_mass_kg
+                        ),
+                    )
+                    .for_node(node_id.clone())
+                    .with_values(mass_total, budgets.max_mass_kg),
+                );
+            }
+            if was_within.1 && karma_total > budgets.max_karma_nb {
+                reasons.push(
+                    VerdictReason::new(
+                        ReasonCode::CeimBudgetExceeded,
+                        format!(
+                            "node {node_id}: corridor karma budget exceeded ({karma_total:.3e} > {:.3e})",
+                            budgets.max_karma_nb
+                        ),
+                    )
+                    .for_node(node_id.clone())
+                    .with_values(karma_total, budgets.max_karma_nb),
+                );
             }
         }
 
-        // 3. TODO: integrate CEIM, RoH, NanoKarma, Beekarma, BeeSafetyKernel:
-        //    - project proposal into qpudatashards and CEIM corridors,
-        //    - enforce RoH_after <= RoH_before <= 0.3,
-        //    - enforce BeeNeuralSafe & BeeHBScore invariants,
-        //    - enforce TECHPolicyDocument budgets.
+        if reasons.is_empty() {
+            Ok((mass_total, karma_total))
+        } else {
+            Err(reasons)
+        }
+    }
+}
+
+/// `row` with its airflow, taken as the full-duty airflow, at `duty` under
+/// `model`, and its CEIM mass at `temperature_k`.
+pub(crate) fn project_ceim(
+    row: &CorridorRow,
+    duty: f64,
+    model: &AirflowModel,
+    temperature_k: f64,
+) -> Result<(CorridorRow, f64), String> {
+    let pollutant = row.pollutant_kind().map_err(|e| e.to_string())?;
+    let mass = compute_mass_kg_at_duty(row, duty, model, pollutant, temperature_k)
+        .map_err(|e| e.to_string())?;
+    let mut projected = row.clone();
+    projected.airflow_m3_per_s = model.effective_airflow(row.airflow_m3_per_s, duty);
+    Ok((projected, mass))
+}
 
-        Verdict {
-            approved: true,
-            message: "proposal passed core governance checks".into(),
+impl Verifier {
+    /// `verify`, plus the RoH stage over stressor inputs before and after
+    /// applying the proposal.
+    pub fn verify_roh(
+        proposal: &Proposal,
+        model: &RohModel,
+        before: &RohInputs,
+        after: &RohInputs,
+    ) -> Verdict {
+        let verdict = Self::verify(proposal);
+        if !verdict.approved {
+            return verdict;
+        }
+        match model.check_invariant(before, after) {
+            Ok((roh_before, roh_after)) => Verdict::approve(VerdictReason::new(
+                ReasonCode::Passed,
+                format!(
+                    "RoH invariant holds (RoH_before {roh_before:.3}, RoH_after {roh_after:.3})"
+                ),
+            )),
+            Err(e) => {
+                // Report the bound that failed: the ceiling on RoH_before,
+                // else RoH_before as the bound on RoH_after.
+                let (roh_before, roh_after) = (model.compute_roh(before), model.compute_roh(after));
+                let (observed, limit) = if roh_before > ROH_CEILING {
+                    (roh_before, ROH_CEILING)
+                } else {
+                    (roh_after, roh_before)
+                };
+                Verdict::reject(vec![VerdictReason::new(
+                    ReasonCode::RohViolation,
+                    format!("RoH invariant violated: {e}"),
+                )
+                .with_values(observed, limit)])
+            }
         }
     }
 }
@@ -264,6 +926,21 @@ mod tests {
         GovernanceCore::new()
     }
 
+    /// Linear five-minute ramps to each `(node_id, duty)`.
+    pub(crate) fn directives(nodes: &[(&str, f64)]) -> Proposal {
+        Proposal {
+            directives: nodes
+                .iter()
+                .map(|&(node_id, duty)| guards::NodeDirective {
+                    node_id: node_id.into(),
+                    new_duty_cycle: duty,
+                    horizon_seconds: 300,
+                    ramp_shape: Some(guards::RampShape::Linear),
+                })
+                .collect(),
+        }
+    }
+
     #[tokio::test]
     async fn test_authorization_schema() {
         let core = mk_core();
@@ -287,17 +964,478 @@ mod tests {
 
         // Superchair: allowed to propose control.
         let eval = core
-            .authorize(&superchair, &Action::ProposeControl, &resource, &GovContext)
+            .authorize(
+                &superchair,
+                &Action::ProposeControl,
+                &resource,
+                &GovContext::default(),
+            )
             .await;
         assert!(matches!(eval.decision, AccessDecision::Granted));
 
         // Stakeholder: not allowed to propose control.
         let eval = core
-            .authorize(&stakeholder, &Action::ProposeControl, &resource, &GovContext)
+            .authorize(
+                &stakeholder,
+                &Action::ProposeControl,
+                &resource,
+                &GovContext::default(),
+            )
             .await;
         assert!(matches!(eval.decision, AccessDecision::Denied));
     }
 
+    #[tokio::test]
+    async fn test_audit_sink_records_grant_and_deny() {
+        use crate::audit::MemoryAuditSink;
+
+        let sink = Arc::new(MemoryAuditSink::new(16));
+        let core = GovernanceCore::new_with_sink(sink.clone());
+
+        let staff = Principal {
+            id: "ops@cyboair.org".into(),
+            role: Role::Staff,
+            attributes: vec![],
+        };
+        let guest = Principal {
+            id: "guest".into(),
+            role: Role::Guest,
+            attributes: vec![],
+        };
+        let resource = Resource {
+            resource_id: "node_01".into(),
+            owner: Some("sh@org.com".into()),
+            attributes: vec![("visibility".into(), "restricted".into())],
+        };
+
+        core.authorize(
+            &staff,
+            &Action::ProposeControl,
+            &resource,
+            &GovContext::default(),
+        )
+        .await;
+        core.authorize(
+            &guest,
+            &Action::ProposeControl,
+            &resource,
+            &GovContext::default(),
+        )
+        .await;
+
+        let entries = sink.entries();
+        assert_eq!(entries.len(), 2);
+
+        let grant = &entries[0];
+        assert_eq!(grant.principal_id, "ops@cyboair.org");
+        assert_eq!(grant.decision, AuditDecision::Granted);
+        assert_eq!(grant.resource_id, "node_01");
+        let names: Vec<_> = grant
+            .policy_trail
+            .iter()
+            .map(|p| p.policy.as_str())
+            .collect();
+        assert_eq!(names, ["RbacPolicy", "AbacPolicy"]);
+        assert!(grant.policy_trail.iter().all(|p| p.granted));
+
+        let deny = &entries[1];
+        assert_eq!(deny.role, Role::Guest);
+        assert_eq!(deny.decision, AuditDecision::Denied);
+        let rbac = &deny.policy_trail[0];
+        assert_eq!(rbac.policy, "RbacPolicy");
+        assert!(!rbac.granted);
+        assert_eq!(rbac.reason.as_deref(), Some("role does not grant action"));
+    }
+
+    #[tokio::test]
+    async fn test_request_context_reaches_audit_entry_and_timings() {
+        use crate::audit::MemoryAuditSink;
+        use chrono::{TimeZone, Utc};
+
+        let sink = Arc::new(MemoryAuditSink::new(4));
+        let core = GovernanceCore::new_with_sink(sink.clone());
+        let guest = Principal {
+            id: "guest".into(),
+            role: Role::Guest,
+            attributes: vec![],
+        };
+        let resource = Resource {
+            resource_id: "node_01".into(),
+            owner: None,
+            attributes: vec![],
+        };
+        let received_at = Utc.with_ymd_and_hms(2025, 7, 1, 18, 0, 0).unwrap();
+        let request = RequestContext::new(received_at).with_parent_span("b7ad6b7169203331");
+        let ctx = GovContext::for_request(request.clone());
+
+        let auth = core
+            .authorize(&guest, &Action::ProposeControl, &resource, &ctx)
+            .await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(auth.request.as_ref(), Some(&request));
+        let timed: Vec<_> = auth.timings.iter().map(|t| t.policy.as_str()).collect();
+        assert_eq!(timed.len(), core.policies.len());
+        assert_eq!(timed, ["RbacPolicy", "AbacPolicy"]);
+
+        let entry = &sink.entries()[0];
+        let audited = entry.request.as_ref().unwrap();
+        assert_eq!(audited.request_id, request.request_id);
+        assert_eq!(audited.parent_span_id.as_deref(), Some("b7ad6b7169203331"));
+        let line = serde_json::to_value(entry).unwrap();
+        assert_eq!(
+            line["request"]["request_id"],
+            request.request_id.to_string()
+        );
+
+        // Callers without a request context audit as before.
+        let ctx = GovContext::default();
+        core.authorize(&guest, &Action::ReadShard, &resource, &ctx)
+            .await;
+        assert_eq!(sink.entries()[1].request, None);
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_sink_writes_one_line_per_decision() {
+        use crate::audit::JsonLinesAuditSink;
+
+        let sink = Arc::new(JsonLinesAuditSink::new(Vec::new()));
+        let core = GovernanceCore::new_with_sink(sink.clone());
+        let superchair = Principal {
+            id: "admin@cyboair.org".into(),
+            role: Role::Superchair,
+            attributes: vec![],
+        };
+        let resource = Resource {
+            resource_id: "shard_phx".into(),
+            owner: None,
+            attributes: vec![],
+        };
+        core.authorize(
+            &superchair,
+            &Action::ReadShard,
+            &resource,
+            &GovContext::default(),
+        )
+        .await;
+        drop(core);
+
+        let sink = Arc::try_unwrap(sink).ok().expect("core dropped its handle");
+        assert_eq!(sink.write_errors(), 0);
+        let out = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let entry: AuditEntry = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(
+            entry.kind,
+            AuditKind::Access {
+                action: Action::ReadShard
+            }
+        );
+        assert!(lines[0].contains(r#""kind":"Access","action":"ReadShard""#));
+        assert_eq!(entry.decision, AuditDecision::Granted);
+    }
+
+    #[test]
+    fn test_memory_sink_drops_oldest() {
+        use crate::audit::MemoryAuditSink;
+
+        let sink = MemoryAuditSink::new(2);
+        for i in 0..3 {
+            sink.record(AuditEntry {
+                principal_id: format!("p{i}"),
+                role: Role::Bot,
+                kind: AuditKind::Access {
+                    action: Action::WriteTelemetry,
+                },
+                resource_id: "node_01".into(),
+                decision: AuditDecision::Granted,
+                policy_trail: vec![],
+                request: None,
+                node_traces: vec![],
+                timestamp_unix_ms: i,
+            });
+        }
+        let ids: Vec<_> = sink.entries().into_iter().map(|e| e.principal_id).collect();
+        assert_eq!(ids, ["p1", "p2"]);
+    }
+
+    pub(crate) fn fixture_rows() -> HashMap<String, CorridorRow> {
+        let row = |id: &str, cin: f64, cout: f64, airflow: f64, period: f64| CorridorRow {
+            machine_id: id.into(),
+            r#type: "UrbanNanoswarmCanopy".into(),
+            location: "Phoenix-Intersection-A".into(),
+            pollutant: "PM2.5".into(),
+            cin,
+            cout,
+            unit: "ugm3".into(),
+            airflow_m3_per_s: airflow,
+            period_s: period,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.92,
+            trace_id: None,
+            shard_version: None,
+        };
+        [
+            row("CYB-AIR-CANOPY-01", 40.0, 28.0, 3.0, 3600.0),
+            row("CYB-AIR-SCHOOL-05", 30.0, 18.0, 1.0, 2700.0),
+        ]
+        .into_iter()
+        .map(|r| (r.machine_id.clone(), r))
+        .collect()
+    }
+
+    fn budgets() -> CorridorBudgets {
+        CorridorBudgets {
+            max_mass_kg: 1.0e-4,
+            max_karma_nb: 1.0e6,
+            temperature_k: 310.0,
+            airflow: AirflowModels::default(),
+        }
+    }
+
+    #[test]
+    fn test_ceim_under_budget_is_approved() {
+        // Full duty: 1.296e-4 + 3.24e-5 kg; half duty stays under 1e-4 kg.
+        let proposal = directives(&[("CYB-AIR-CANOPY-01", 0.5), ("CYB-AIR-SCHOOL-05", 0.5)]);
+        let verdict = Verifier::verify_with_shard(&proposal, &fixture_rows(), &budgets());
+        assert!(verdict.approved, "{verdict}");
+    }
+
+    #[test]
+    fn test_ceim_over_budget_names_node_and_budget() {
+        let proposal = directives(&[("CYB-AIR-SCHOOL-05", 1.0), ("CYB-AIR-CANOPY-01", 1.0)]);
+        let verdict = Verifier::verify_with_shard(&proposal, &fixture_rows(), &budgets());
+        assert!(!verdict.approved);
+        assert!(
+            verdict.to_string().contains("CYB-AIR-CANOPY-01"),
+            "{verdict}"
+        );
+        assert!(verdict.to_string().contains("mass budget"), "{verdict}");
+        assert!(verdict.has_code(ReasonCode::CeimBudgetExceeded));
+
+        let mut tight = budgets();
+        tight.max_karma_nb = 1.0e4;
+        let proposal = directives(&[("CYB-AIR-SCHOOL-05", 0.5)]);
+        let verdict = Verifier::verify_with_shard(&proposal, &fixture_rows(), &tight);
+        assert!(!verdict.approved);
+        assert!(
+            verdict.to_string().contains("CYB-AIR-SCHOOL-05"),
+            "{verdict}"
+        );
+        assert!(verdict.to_string().contains("karma budget"), "{verdict}");
+    }
+
+    #[test]
+    fn test_ceim_unknown_node_is_rejected() {
+        let proposal = directives(&[("CYB-AIR-GHOST-99", 0.2)]);
+        let verdict = Verifier::verify_with_shard(&proposal, &fixture_rows(), &budgets());
+        assert!(!verdict.approved);
+        assert!(verdict.to_string().contains("no shard row"));
+        assert_eq!(verdict.reasons[0].code, ReasonCode::MissingShardRow);
+        assert_eq!(
+            verdict.reasons[0].node_id.as_deref(),
+            Some("CYB-AIR-GHOST-99")
+        );
+    }
+
+    const ROH_MODEL: &str = r#"{
+        "stressors": [
+            {"name": "pm25", "weight": 0.4},
+            {"name": "o3", "weight": 0.3},
+            {"name": "noise", "weight": 0.2, "default": 0.1},
+            {"name": "heat", "weight": 0.1}
+        ]
+    }"#;
+
+    fn roh_inputs(pm25: f64, o3: f64) -> RohInputs {
+        RohInputs::from([("pm25".to_string(), pm25), ("o3".to_string(), o3)])
+    }
+
+    fn one_node() -> Proposal {
+        directives(&[("CYB-AIR-CANOPY-01", 0.6)])
+    }
+
+    #[test]
+    fn test_roh_defaults_for_missing_stressors() {
+        let model = RohModel::from_json(ROH_MODEL).unwrap();
+        // 0.4*0.5 + 0.3*0.2 + noise default 0.2*0.1 + heat default 0.
+        let roh = model.compute_roh(&roh_inputs(0.5, 0.2));
+        assert!((roh - 0.28).abs() < 1e-12);
+        assert_eq!(model.compute_roh(&roh_inputs(5.0, 5.0)), 0.72);
+    }
+
+    #[test]
+    fn test_roh_decrease_is_approved() {
+        let model = RohModel::from_json(ROH_MODEL).unwrap();
+        let verdict = Verifier::verify_roh(
+            &one_node(),
+            &model,
+            &roh_inputs(0.5, 0.2),
+            &roh_inputs(0.4, 0.2),
+        );
+        assert!(verdict.approved, "{verdict}");
+        assert!(verdict.to_string().contains("0.280"), "{verdict}");
+    }
+
+    #[test]
+    fn test_roh_rejections() {
+        let model = RohModel::from_json(ROH_MODEL).unwrap();
+
+        // RoH_before above the 0.3 ceiling.
+        let verdict = Verifier::verify_roh(
+            &one_node(),
+            &model,
+            &roh_inputs(0.6, 0.2),
+            &roh_inputs(0.1, 0.1),
+        );
+        assert!(!verdict.approved);
+        assert!(
+            verdict.to_string().contains("RoH_before 0.320"),
+            "{verdict}"
+        );
+        let reason = &verdict.reasons[0];
+        assert!((reason.observed.unwrap() - 0.32).abs() < 1e-12);
+        assert_eq!(reason.limit, Some(ROH_CEILING));
+
+        // RoH increases.
+        let verdict = Verifier::verify_roh(
+            &one_node(),
+            &model,
+            &roh_inputs(0.4, 0.2),
+            &roh_inputs(0.5, 0.2),
+        );
+        assert!(!verdict.approved);
+        assert!(
+            verdict
+                .to_string()
+                .contains("RoH_after 0.280 exceeds RoH_before 0.240"),
+            "{verdict}"
+        );
+        assert!(verdict.has_code(ReasonCode::RohViolation));
+        let reason = &verdict.reasons[0];
+        assert!((reason.observed.unwrap() - 0.28).abs() < 1e-12);
+        assert!((reason.limit.unwrap() - 0.24).abs() < 1e-12);
+
+        // Non-finite stressor input.
+        let verdict = Verifier::verify_roh(
+            &one_node(),
+            &model,
+            &roh_inputs(0.4, 0.2),
+            &roh_inputs(f64::NAN, 0.2),
+        );
+        assert!(!verdict.approved);
+        assert!(verdict.to_string().contains("non-finite"), "{verdict}");
+    }
+
+    /// Denies everything on resources tagged "frozen=true".
+    struct FreezePolicy;
+
+    #[async_trait]
+    impl Policy<Principal, Resource, Action, GovContext> for FreezePolicy {
+        async fn evaluate_access(
+            &self,
+            _principal: &Principal,
+            _action: &Action,
+            resource: &Resource,
+            _ctx: &GovContext,
+        ) -> PolicyEvalResult {
+            if resource
+                .attributes
+                .iter()
+                .any(|(k, v)| k == "frozen" && v == "true")
+            {
+                PolicyEvalResult::denied("FreezePolicy", "resource frozen")
+            } else {
+                PolicyEvalResult::granted("FreezePolicy", None)
+            }
+        }
+
+        fn policy_type(&self) -> String {
+            "FreezePolicy".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_combination_strategies_differ() {
+        // Stakeholder on their own node: RBAC denies ProposeControl, ABAC grants.
+        let stakeholder = Principal {
+            id: "sh@org.com".into(),
+            role: Role::Stakeholder,
+            attributes: vec![],
+        };
+        let own_node = Resource {
+            resource_id: "node_01".into(),
+            owner: Some("sh@org.com".into()),
+            attributes: vec![],
+        };
+
+        let decide = |strategy| {
+            let core = GovernanceCore::new().with_strategy(strategy);
+            let (p, r) = (stakeholder.clone(), own_node.clone());
+            async move {
+                core.authorize(&p, &Action::ProposeControl, &r, &GovContext::default())
+                    .await
+            }
+        };
+
+        let all = decide(CombinationStrategy::AllMustGrant).await;
+        let deny = decide(CombinationStrategy::DenyOverrides).await;
+        let grant = decide(CombinationStrategy::GrantOverrides).await;
+        assert!(matches!(all.decision, AccessDecision::Denied));
+        assert!(matches!(deny.decision, AccessDecision::Denied));
+        assert!(matches!(grant.decision, AccessDecision::Granted));
+
+        // The trail is complete whatever the strategy.
+        for auth in [&all, &deny, &grant] {
+            let outcome: Vec<_> = auth
+                .trail
+                .iter()
+                .map(|p| (p.policy.as_str(), p.granted))
+                .collect();
+            assert_eq!(outcome, [("RbacPolicy", false), ("AbacPolicy", true)]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_policy_via_add_policy() {
+        let mut core = GovernanceCore::new().with_strategy(CombinationStrategy::DenyOverrides);
+        core.add_policy(FreezePolicy);
+        let superchair = Principal {
+            id: "admin@cyboair.org".into(),
+            role: Role::Superchair,
+            attributes: vec![],
+        };
+        let frozen = Resource {
+            resource_id: "node_07".into(),
+            owner: None,
+            attributes: vec![("frozen".into(), "true".into())],
+        };
+        let auth = core
+            .authorize(
+                &superchair,
+                &Action::ReadShard,
+                &frozen,
+                &GovContext::default(),
+            )
+            .await;
+        assert!(matches!(auth.decision, AccessDecision::Denied));
+        assert_eq!(auth.trail.len(), 3);
+        assert_eq!(auth.trail[2].reason.as_deref(), Some("resource frozen"));
+    }
+
+    #[test]
+    fn test_empty_policy_set() {
+        assert!(matches!(
+            CombinationStrategy::AllMustGrant.combine(&[]),
+            AccessDecision::Denied
+        ));
+        assert!(matches!(
+            CombinationStrategy::DenyOverrides.combine(&[]),
+            AccessDecision::Granted
+        ));
+    }
+
     #[test]
     fn test_input_guard_duty_cycle() {
         assert!(InputGuard::validate_duty_cycle(0.0).is_ok());
@@ -306,4 +1444,47 @@ mod tests {
         assert!(InputGuard::validate_duty_cycle(-0.1).is_err());
         assert!(InputGuard::validate_duty_cycle(1.1).is_err());
     }
+
+    #[test]
+    fn test_verify_accumulates_all_failures() {
+        let mut proposal = directives(&[("node_01", 1.4), ("node_02", 0.5), ("node_01", -0.2)]);
+        proposal.directives[1].horizon_seconds = 0;
+        let verdict = Verifier::verify(&proposal);
+        assert!(!verdict.approved);
+        let codes: Vec<_> = verdict.reasons.iter().map(|r| r.code).collect();
+        assert_eq!(
+            codes,
+            [
+                ReasonCode::DutyOutOfRange,
+                ReasonCode::InvalidHorizon,
+                ReasonCode::DutyOutOfRange,
+                ReasonCode::DuplicateNode,
+            ]
+        );
+        assert_eq!(verdict.reasons[0].node_id.as_deref(), Some("node_01"));
+        assert_eq!(verdict.reasons[0].observed, Some(1.4));
+        assert_eq!(verdict.reasons[1].node_id.as_deref(), Some("node_02"));
+        assert!(verdict.to_string().starts_with(
+            "node node_01: new_duty_cycle 1.4 must be between 0.0 and 1.0; \
+             node node_02: horizon_seconds must be > 0"
+        ));
+    }
+
+    #[test]
+    fn test_verify_json() {
+        let verdict = Verifier::verify_json(
+            r#"{"directives":[{"node_id":"node_01","new_duty_cycle":0.4,"horizon_seconds":60}]}"#,
+        );
+        assert!(verdict.approved);
+
+        // Legacy single-node shape.
+        let verdict = Verifier::verify_json(
+            r#"{"node_id":"node_01","new_duty_cycle":1.5,"horizon_seconds":60}"#,
+        );
+        assert!(verdict.has_code(ReasonCode::DutyOutOfRange));
+
+        let verdict = Verifier::verify_json(r#"{"node_ids":["node_01"],"duty_cycles":[0.4]}"#);
+        assert_eq!(verdict.state, VerdictState::Rejected);
+        assert!(verdict.has_code(ReasonCode::InvalidProposal));
+    }
 }
@@ -1,11 +1,52 @@
 #![forbid(unsafe_code)]
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cyboair_corridor_safety::{AgentGate, CorridorRow};
+use serde::{Deserialize, Serialize};
+
 use crate::guards::{ControlProposal, InputGuard};
+use crate::reason::{write_reasons, ReasonCode, VerdictReason};
+use crate::{CorridorBudgets, Proposal, Verdict};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VerifierVerdict {
     pub approved: bool,
-    pub reason: String,
+    pub reasons: Vec<VerdictReason>,
+}
+
+impl VerifierVerdict {
+    pub fn reject(reason: VerdictReason) -> Self {
+        Self {
+            approved: false,
+            reasons: vec![reason],
+        }
+    }
+
+    pub fn has_code(&self, code: ReasonCode) -> bool {
+        self.reasons.iter().any(|r| r.code == code)
+    }
+}
+
+/// The pipeline's verdict in wire form; repair suggestions and the degraded
+/// flag are dropped.
+impl From<Verdict> for VerifierVerdict {
+    fn from(verdict: Verdict) -> Self {
+        Self {
+            approved: verdict.approved,
+            reasons: verdict.reasons,
+        }
+    }
+}
+
+impl fmt::Display for VerifierVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_reasons(f, &self.reasons)
+    }
 }
 
 /// Verifier: the only module allowed to bless proposals for execution.
@@ -14,22 +55,22 @@ pub struct Verifier;
 
 impl Verifier {
     pub fn verify(proposal: &ControlProposal) -> VerifierVerdict {
-        // 1. Structural validation (redundant but safe).
-        if let Err(e) = InputGuard::validate_control_proposal(proposal) {
+        // 1. Structural validation of every directive (redundant but safe).
+        let errors = InputGuard::control_proposal_errors(proposal);
+        if !errors.is_empty() {
             return VerifierVerdict {
                 approved: false,
-                reason: format!("invalid proposal: {e}"),
+                reasons: errors.iter().map(VerdictReason::from).collect(),
             };
         }
 
         // 2. TODO: CEIM mass/energy corridors:
-        //    - load qpudatashard and CEIM shard for node_id,
-        //    - predict impact of new_duty_cycle,
+        //    - load qpudatashard and CEIM shard for each directive's node_id,
+        //    - predict impact of its new_duty_cycle over horizon_seconds,
         //    - reject if mass/energy corridors would be violated.
 
-        // 3. TODO: RoH invariants:
-        //    - compute RoH_before, RoH_after from .rohmodel.aln,
-        //    - enforce RoH_after <= RoH_before <= 0.3.
+        // 3. RoH invariants need stressor inputs: see `verify_roh`, with the
+        //    model from a signed .rohmodel.aln (`RohModel::from_signed_aln`).
 
         // 4. TODO: NanoKarma and Beekarma:
         //    - ensure karma scores remain feasible,
@@ -40,7 +81,969 @@ impl Verifier {
 
         VerifierVerdict {
             approved: true,
-            reason: "proposal passed governance checks (stub)".into(),
+            reasons: vec![VerdictReason::new(
+                ReasonCode::Passed,
+                "proposal passed governance checks (stub)",
+            )],
+        }
+    }
+}
+
+// ---- Staged verification -------------------------------------------------
+
+/// Shard state source for stages; implementations may hit the network.
+#[async_trait]
+pub trait ShardLookup: Send + Sync {
+    async fn row(&self, node_id: &str) -> Option<CorridorRow>;
+
+    /// Whether the store can be reached; an in-memory map always can.
+    async fn health(&self) -> StageHealth {
+        StageHealth::Available
+    }
+}
+
+#[async_trait]
+impl ShardLookup for HashMap<String, CorridorRow> {
+    async fn row(&self, node_id: &str) -> Option<CorridorRow> {
+        self.get(node_id).cloned()
+    }
+}
+
+/// What every stage may consult besides the proposal itself.
+#[derive(Clone, Default)]
+pub struct VerificationContext {
+    /// Absent when the deployment runs no shard-backed stages.
+    pub shard: Option<Arc<dyn ShardLookup>>,
+    pub budgets: Option<CorridorBudgets>,
+    /// Free-form per-deployment stage settings.
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    /// Agent or machine id of whoever submitted the proposal.
+    pub submitter: Option<String>,
+}
+
+impl VerificationContext {
+    pub fn with_shard(mut self, shard: Arc<dyn ShardLookup>) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    pub fn with_budgets(mut self, budgets: CorridorBudgets) -> Self {
+        self.budgets = Some(budgets);
+        self
+    }
+
+    pub fn with_submitter(mut self, submitter: impl Into<String>) -> Self {
+        self.submitter = Some(submitter.into());
+        self
+    }
+}
+
+/// Outcome of one stage. `Fail` stops the pipeline; `Warn` is recorded and
+/// evaluation continues.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StageResult {
+    Pass(Vec<VerdictReason>),
+    Warn(Vec<VerdictReason>),
+    Fail(Vec<VerdictReason>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageOutcome {
+    Pass,
+    Warn,
+    Fail,
+    /// Not run: a dependency was down and the stage fails closed.
+    Unavailable,
+    /// Not run: a dependency was down and the stage fails open.
+    Skipped,
+}
+
+/// Whether a stage's dependencies (shard store, model file, ...) are up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageHealth {
+    Available,
+    /// Which dependency is down, and why.
+    Unavailable(String),
+}
+
+/// What the pipeline does with a proposal when a stage is unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FailureMode {
+    /// Reject it with [`ReasonCode::StageUnavailable`].
+    #[default]
+    FailClosed,
+    /// Skip the stage and carry on; the verdict lists it under
+    /// [`Verdict::degraded`].
+    FailOpenWithFlag,
+}
+
+#[async_trait]
+pub trait VerificationStage: Send + Sync {
+    fn name(&self) -> &str;
+    async fn run(&self, proposal: &Proposal, ctx: &VerificationContext) -> StageResult;
+
+    /// Checked before each `run`; a stage whose backend is down says so
+    /// here rather than failing, or passing, inside `run`.
+    async fn health(&self, _ctx: &VerificationContext) -> StageHealth {
+        StageHealth::Available
+    }
+
+    /// Mode when `health` reports the stage unavailable, unless the
+    /// deployment overrides it; see [`VerifierPipeline::with_failure_mode`].
+    fn failure_mode(&self) -> FailureMode {
+        FailureMode::FailClosed
+    }
+}
+
+/// Name the bee-rights veto stage registers under.
+pub const BEE_VETO_STAGE: &str = "bee-kernel";
+
+/// Stages that always fail closed; deployments may not override them.
+pub const PINNED_STAGES: [&str; 2] = ["structural", BEE_VETO_STAGE];
+
+/// Why [`VerifierPipeline::with_failure_mode`] refused an override.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureModeError {
+    /// No stage of the pipeline has this name.
+    UnknownStage(String),
+    /// The stage is in [`PINNED_STAGES`].
+    Pinned(String),
+}
+
+impl fmt::Display for FailureModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureModeError::UnknownStage(name) => write!(f, "no stage named {name:?}"),
+            FailureModeError::Pinned(name) => {
+                write!(f, "stage {name:?} always fails closed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FailureModeError {}
+
+/// `Verifier::verify`'s per-directive checks.
+pub struct StructuralStage;
+
+#[async_trait]
+impl VerificationStage for StructuralStage {
+    fn name(&self) -> &str {
+        "structural"
+    }
+
+    async fn run(&self, proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+        let reasons = crate::Verifier::structural_reasons(proposal);
+        if reasons.is_empty() {
+            StageResult::Pass(Vec::new())
+        } else {
+            StageResult::Fail(reasons)
+        }
+    }
+}
+
+/// CEIM corridor budgets over rows fetched through `ctx.shard`.
+pub struct CeimStage;
+
+#[async_trait]
+impl VerificationStage for CeimStage {
+    fn name(&self) -> &str {
+        "ceim"
+    }
+
+    /// The shard store's health. A missing lookup is a configuration
+    /// error rather than an outage, so `run` rejects it.
+    async fn health(&self, ctx: &VerificationContext) -> StageHealth {
+        match &ctx.shard {
+            Some(shard) => shard.health().await,
+            None => StageHealth::Available,
+        }
+    }
+
+    async fn run(&self, proposal: &Proposal, ctx: &VerificationContext) -> StageResult {
+        let (Some(shard), Some(budgets)) = (&ctx.shard, &ctx.budgets) else {
+            return StageResult::Fail(vec![VerdictReason::new(
+                ReasonCode::MissingShardRow,
+                "CEIM stage needs a shard lookup and corridor budgets",
+            )]);
+        };
+        let mut rows = HashMap::new();
+        for node_id in proposal.node_ids() {
+            if let Some(row) = shard.row(node_id).await {
+                rows.insert(node_id.to_string(), row);
+            }
+        }
+        match crate::Verifier::ceim_totals(proposal, &rows, budgets) {
+            Ok((mass, karma)) => StageResult::Pass(vec![VerdictReason::new(
+                ReasonCode::Passed,
+                format!(
+                    "proposal within CEIM corridor budgets (mass {mass:.3e} kg, karma {karma:.3e})"
+                ),
+            )]),
+            Err(reasons) => StageResult::Fail(reasons),
+        }
+    }
+}
+
+/// Rejects directives above the submitting agent's blood-gate duty cap.
+/// A proposal without `ctx.submitter`, or from an agent the gate's
+/// resolver does not know, is held to the most restrictive cap.
+pub struct GateStage(pub AgentGate);
+
+#[async_trait]
+impl VerificationStage for GateStage {
+    fn name(&self) -> &str {
+        "gate"
+    }
+
+    async fn run(&self, proposal: &Proposal, ctx: &VerificationContext) -> StageResult {
+        let submitter = ctx.submitter.as_deref().unwrap_or_default();
+        let (level, cap) = match &ctx.submitter {
+            Some(submitter) => self.0.lookup(submitter),
+            None => (None, self.0.policy.cap(None)),
+        };
+        let level = level.map_or_else(|| "unknown".to_string(), |l| l.to_string());
+        let reasons: Vec<_> = proposal
+            .directives
+            .iter()
+            .filter(|d| d.new_duty_cycle > cap)
+            .map(|d| {
+                VerdictReason::new(
+                    ReasonCode::GateCapExceeded,
+                    format!(
+                        "node {}: duty {} exceeds the cap {cap} for agent {submitter:?} at gate level {level}",
+                        d.node_id, d.new_duty_cycle
+                    ),
+                )
+                .for_node(d.node_id.clone())
+                .with_values(d.new_duty_cycle, cap)
+            })
+            .collect();
+        if reasons.is_empty() {
+            StageResult::Pass(Vec::new())
+        } else {
+            StageResult::Fail(reasons)
+        }
+    }
+}
+
+/// Which stages ran and how each ended.
+#[derive(Debug, Clone)]
+pub struct StageRecord {
+    pub stage: String,
+    pub outcome: StageOutcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineReport {
+    /// Reasons from every stage that ran, in stage order.
+    pub verdict: Verdict,
+    pub stages: Vec<StageRecord>,
+    pub warnings: Vec<VerdictReason>,
+}
+
+/// A stage a degraded verdict was reached without.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SkippedStage {
+    pub stage: String,
+    /// The dependency that was down, from [`StageHealth::Unavailable`].
+    pub reason: String,
+}
+
+/// Flag on a verdict reached with fail-open stages skipped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DegradedVerification {
+    /// In stage order.
+    pub skipped: Vec<SkippedStage>,
+}
+
+impl fmt::Display for DegradedVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.skipped.iter().map(|s| s.stage.as_str()).collect();
+        write!(f, "DEGRADED (skipped: {})", names.join(", "))
+    }
+}
+
+/// Suggested replacement for a rejected directive's duty.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DutySuggestion {
+    pub node_id: String,
+    pub proposed: f64,
+    /// Highest duty found to pass every stage; `None` if the node fails
+    /// even at 0.0.
+    pub suggested_duty: Option<f64>,
+}
+
+/// Default bisection steps per rejected directive; each step runs every
+/// stage once and halves the remaining interval.
+pub const DEFAULT_REPAIR_ITERATIONS: u32 = 16;
+
+/// Ordered verification stages; always starts with `StructuralStage`.
+///
+/// Each stage's health is checked before it runs. An unavailable stage
+/// rejects the proposal or is skipped, by its [`FailureMode`].
+pub struct VerifierPipeline {
+    stages: Vec<Box<dyn VerificationStage>>,
+    repair_iterations: Option<u32>,
+    failure_modes: HashMap<String, FailureMode>,
+}
+
+impl Default for VerifierPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerifierPipeline {
+    pub fn new() -> Self {
+        Self {
+            stages: vec![Box::new(StructuralStage)],
+            repair_iterations: None,
+            failure_modes: HashMap::new(),
+        }
+    }
+
+    pub fn add_stage<S: VerificationStage + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// On rejection, attach a [`DutySuggestion`] per rejected directive,
+    /// searched with at most `max_iterations` bisection steps each.
+    pub fn with_repair(mut self, max_iterations: u32) -> Self {
+        self.repair_iterations = Some(max_iterations);
+        self
+    }
+
+    /// Override the failure mode the stage named `stage` declares, for
+    /// this deployment. The stage must already be added and must not be
+    /// one of [`PINNED_STAGES`].
+    pub fn with_failure_mode(
+        mut self,
+        stage: impl Into<String>,
+        mode: FailureMode,
+    ) -> Result<Self, FailureModeError> {
+        let stage = stage.into();
+        if PINNED_STAGES.contains(&stage.as_str()) {
+            return Err(FailureModeError::Pinned(stage));
+        }
+        if !self.has_stage(&stage) {
+            return Err(FailureModeError::UnknownStage(stage));
+        }
+        self.failure_modes.insert(stage, mode);
+        Ok(self)
+    }
+
+    /// The mode `stage` runs under here; always `FailClosed` for
+    /// [`PINNED_STAGES`], whatever the stage declares.
+    pub fn failure_mode(&self, stage: &dyn VerificationStage) -> FailureMode {
+        if PINNED_STAGES.contains(&stage.name()) {
+            return FailureMode::FailClosed;
+        }
+        self.failure_modes
+            .get(stage.name())
+            .copied()
+            .unwrap_or_else(|| stage.failure_mode())
+    }
+
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|s| s.name()).collect()
+    }
+
+    pub fn has_stage(&self, name: &str) -> bool {
+        self.stages.iter().any(|s| s.name() == name)
+    }
+
+    /// Run stages in order, stopping at the first `Fail` or fail-closed
+    /// unavailable stage, then repair a rejected proposal if enabled. No
+    /// duty passes a stage that cannot run, so an outage is not repaired.
+    pub async fn run(&self, proposal: &Proposal, ctx: &VerificationContext) -> PipelineReport {
+        let (mut report, failed) = self.evaluate(proposal, ctx).await;
+        let outage = report
+            .stages
+            .last()
+            .is_some_and(|s| s.outcome == StageOutcome::Unavailable);
+        if let (Some(iterations), Some(failed), false) = (self.repair_iterations, failed, outage) {
+            report.verdict.suggestions = self.repair(proposal, ctx, &failed, iterations).await;
+        }
+        report
+    }
+
+    /// The report, and the failing stage's reasons if one failed.
+    async fn evaluate(
+        &self,
+        proposal: &Proposal,
+        ctx: &VerificationContext,
+    ) -> (PipelineReport, Option<Vec<VerdictReason>>) {
+        let mut reasons = Vec::new();
+        let mut warnings = Vec::new();
+        let mut stages = Vec::new();
+        let mut skipped = Vec::new();
+        for stage in &self.stages {
+            let name = stage.name();
+            if let StageHealth::Unavailable(why) = stage.health(ctx).await {
+                if self.failure_mode(stage.as_ref()) == FailureMode::FailOpenWithFlag {
+                    stages.push(StageRecord {
+                        stage: name.to_string(),
+                        outcome: StageOutcome::Skipped,
+                    });
+                    reasons.push(VerdictReason::new(
+                        ReasonCode::VerificationDegraded,
+                        format!("{name} stage skipped, dependency unavailable: {why}"),
+                    ));
+                    skipped.push(SkippedStage {
+                        stage: name.to_string(),
+                        reason: why,
+                    });
+                    continue;
+                }
+                stages.push(StageRecord {
+                    stage: name.to_string(),
+                    outcome: StageOutcome::Unavailable,
+                });
+                let found = vec![VerdictReason::new(
+                    ReasonCode::StageUnavailable,
+                    format!("{name} stage unavailable: {why}"),
+                )];
+                reasons.extend(found.iter().cloned());
+                let mut verdict = Verdict::reject(reasons);
+                verdict.degraded = degraded(skipped);
+                let report = PipelineReport {
+                    verdict,
+                    stages,
+                    warnings,
+                };
+                return (report, Some(found));
+            }
+
+            let (outcome, found) = match stage.run(proposal, ctx).await {
+                StageResult::Pass(r) => (StageOutcome::Pass, r),
+                StageResult::Warn(r) => {
+                    warnings.extend(r.iter().cloned());
+                    (StageOutcome::Warn, r)
+                }
+                StageResult::Fail(r) => (StageOutcome::Fail, r),
+            };
+            stages.push(StageRecord {
+                stage: name.to_string(),
+                outcome,
+            });
+            if outcome == StageOutcome::Fail {
+                reasons.extend(found.iter().cloned());
+                let mut verdict = Verdict::reject(reasons);
+                verdict.degraded = degraded(skipped);
+                let report = PipelineReport {
+                    verdict,
+                    stages,
+                    warnings,
+                };
+                return (report, Some(found));
+            }
+            reasons.extend(found);
+        }
+
+        if reasons.is_empty() {
+            reasons.push(VerdictReason::new(
+                ReasonCode::Passed,
+                "proposal passed all verification stages",
+            ));
+        }
+        let report = PipelineReport {
+            verdict: Verdict {
+                approved: true,
+                state: crate::VerdictState::Approved,
+                reasons,
+                suggestions: Vec::new(),
+                degraded: degraded(skipped),
+            },
+            stages,
+            warnings,
+        };
+        (report, None)
+    }
+
+    async fn passes(&self, proposal: &Proposal, ctx: &VerificationContext) -> bool {
+        self.evaluate(proposal, ctx).await.1.is_none()
+    }
+
+    /// Search the duty axis of each directive named in `failed` (every
+    /// directive if none is named) by re-running the stages.
+    ///
+    /// Rejected directives start at 0.0; those still named by a failure
+    /// there get no suggestion and are left out of the search. The rest
+    /// are raised one at a time, in directive order, to the highest duty
+    /// that keeps the whole proposal passing. The proposal with the
+    /// infeasible directives dropped and every suggestion applied is the
+    /// last one that passed, so it passes re-verification.
+    async fn repair(
+        &self,
+        proposal: &Proposal,
+        ctx: &VerificationContext,
+        failed: &[VerdictReason],
+        iterations: u32,
+    ) -> Vec<DutySuggestion> {
+        let named = |reasons: &[VerdictReason], node_id: &str| {
+            reasons
+                .iter()
+                .any(|r| r.node_id.as_deref() == Some(node_id))
+        };
+        let mut rejected: Vec<usize> = (0..proposal.directives.len())
+            .filter(|&i| named(failed, &proposal.directives[i].node_id))
+            .collect();
+        if rejected.is_empty() {
+            rejected = (0..proposal.directives.len()).collect();
+        }
+
+        let mut working = proposal.clone();
+        for &i in &rejected {
+            working.directives[i].new_duty_cycle = 0.0;
+        }
+        let mut infeasible = vec![false; proposal.directives.len()];
+        let without_infeasible = |working: &Proposal, infeasible: &[bool]| Proposal {
+            directives: working
+                .directives
+                .iter()
+                .zip(infeasible)
+                .filter(|(_, &out)| !out)
+                .map(|(d, _)| d.clone())
+                .collect(),
+        };
+        // Each round drops at least one directive, or gives up on all.
+        loop {
+            let candidate = without_infeasible(&working, &infeasible);
+            let Some(failed) = self.evaluate(&candidate, ctx).await.1 else {
+                break;
+            };
+            let still: Vec<usize> = rejected
+                .iter()
+                .copied()
+                .filter(|&i| !infeasible[i] && named(&failed, &proposal.directives[i].node_id))
+                .collect();
+            if still.is_empty() {
+                for &i in &rejected {
+                    infeasible[i] = true;
+                }
+                break;
+            }
+            for i in still {
+                infeasible[i] = true;
+            }
+        }
+
+        for &i in rejected.iter().filter(|&&i| !infeasible[i]) {
+            let mut trial = working.clone();
+            let mut passes_at = |duty: f64| {
+                trial.directives[i].new_duty_cycle = duty;
+                let candidate = without_infeasible(&trial, &infeasible);
+                async move { self.passes(&candidate, ctx).await }
+            };
+            let proposed = proposal.directives[i].new_duty_cycle;
+            let (mut lo, mut hi) = (0.0, proposed);
+            if passes_at(hi).await {
+                lo = hi;
+            } else {
+                for _ in 0..iterations {
+                    let mid = 0.5 * (lo + hi);
+                    if passes_at(mid).await {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+            }
+            working.directives[i].new_duty_cycle = lo;
+        }
+
+        rejected
+            .iter()
+            .map(|&i| DutySuggestion {
+                node_id: proposal.directives[i].node_id.clone(),
+                proposed: proposal.directives[i].new_duty_cycle,
+                suggested_duty: (!infeasible[i]).then(|| working.directives[i].new_duty_cycle),
+            })
+            .collect()
+    }
+}
+
+fn degraded(skipped: Vec<SkippedStage>) -> Option<DegradedVerification> {
+    (!skipped.is_empty()).then_some(DegradedVerification { skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records its name when run, then returns a canned result.
+    struct MockStage {
+        name: &'static str,
+        result: StageResult,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl VerificationStage for MockStage {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn run(&self, _proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+            self.log.lock().unwrap().push(self.name);
+            self.result.clone()
+        }
+    }
+
+    fn proposal(duty: f64) -> Proposal {
+        crate::tests::directives(&[("node_hive", duty)])
+    }
+
+    fn reason(code: ReasonCode, message: &str) -> VerdictReason {
+        VerdictReason::new(code, message).for_node("node_hive")
+    }
+
+    fn pipeline(log: &Arc<Mutex<Vec<&'static str>>>) -> VerifierPipeline {
+        let stage = |name, result| MockStage {
+            name,
+            result,
+            log: log.clone(),
+        };
+        VerifierPipeline::new()
+            .add_stage(stage(
+                "tech-budget",
+                StageResult::Warn(vec![reason(ReasonCode::Passed, "TECH budget at 90%")]),
+            ))
+            .add_stage(stage(
+                "bee-kernel",
+                StageResult::Fail(vec![reason(ReasonCode::BeeVeto, "hive foraging window")]),
+            ))
+            .add_stage(stage("roh", StageResult::Pass(Vec::new())))
+    }
+
+    #[tokio::test]
+    async fn stages_run_in_order_and_stop_at_first_failure() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pipeline = pipeline(&log);
+        assert_eq!(
+            pipeline.stage_names(),
+            ["structural", "tech-budget", "bee-kernel", "roh"]
+        );
+
+        let report = pipeline
+            .run(&proposal(0.4), &VerificationContext::default())
+            .await;
+        assert_eq!(*log.lock().unwrap(), ["tech-budget", "bee-kernel"]);
+        let outcomes: Vec<_> = report
+            .stages
+            .iter()
+            .map(|s| (s.stage.as_str(), s.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("structural", StageOutcome::Pass),
+                ("tech-budget", StageOutcome::Warn),
+                ("bee-kernel", StageOutcome::Fail),
+            ]
+        );
+
+        assert!(!report.verdict.approved);
+        assert!(report.verdict.has_code(ReasonCode::BeeVeto));
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(
+            report.verdict.to_string(),
+            "TECH budget at 90%; hive foraging window"
+        );
+    }
+
+    #[tokio::test]
+    async fn structural_failure_short_circuits_everything() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let report = pipeline(&log)
+            .run(&proposal(1.5), &VerificationContext::default())
+            .await;
+        assert!(log.lock().unwrap().is_empty());
+        assert_eq!(report.stages.len(), 1);
+        assert!(report.verdict.has_code(ReasonCode::DutyOutOfRange));
+    }
+
+    fn hive_row() -> CorridorRow {
+        CorridorRow {
+            machine_id: "node_hive".into(),
+            r#type: "UrbanNanoswarmCanopy".into(),
+            location: "Phoenix-Apiary-1".into(),
+            pollutant: "PM2.5".into(),
+            cin: 30.0,
+            cout: 20.0,
+            unit: "ug/m3".into(),
+            airflow_m3_per_s: 0.8,
+            period_s: 3600.0,
+            lambda_hazard: 3.5,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.93,
+            trace_id: None,
+            shard_version: None,
         }
     }
+
+    #[tokio::test]
+    async fn ceim_stage_uses_shard_hook() {
+        let shard: HashMap<String, CorridorRow> = HashMap::from([("node_hive".into(), hive_row())]);
+        let ctx = VerificationContext::default()
+            .with_shard(Arc::new(shard))
+            .with_budgets(CorridorBudgets {
+                max_mass_kg: 1.0,
+                max_karma_nb: 1.0e12,
+                temperature_k: 310.0,
+                airflow: Default::default(),
+            });
+        let pipeline = VerifierPipeline::new().add_stage(CeimStage);
+
+        let report = pipeline.run(&proposal(0.4), &ctx).await;
+        assert!(report.verdict.approved, "{}", report.verdict);
+
+        let report = pipeline
+            .run(&proposal(0.4), &VerificationContext::default())
+            .await;
+        assert!(!report.verdict.approved);
+    }
+
+    #[tokio::test]
+    async fn gate_stage_holds_each_agent_to_its_level() {
+        use cyboair_corridor_safety::{GateDutyPolicy, MapGateResolver};
+
+        let resolver = (0..=3).fold(MapGateResolver::new(), |r, level| {
+            r.with_level(format!("agent-{level}"), level)
+        });
+        let pipeline = VerifierPipeline::new().add_stage(GateStage(AgentGate::new(
+            GateDutyPolicy::default(),
+            resolver,
+        )));
+        for (submitter, allowed) in [
+            (Some("agent-0"), 0.0),
+            (Some("agent-1"), 0.0),
+            (Some("agent-2"), 0.4),
+            (Some("agent-3"), 1.0),
+            // Unknown or anonymous submitters get the most restrictive cap.
+            (Some("agent-ghost"), 0.0),
+            (None, 0.0),
+        ] {
+            let ctx = VerificationContext {
+                submitter: submitter.map(str::to_string),
+                ..Default::default()
+            };
+            let report = pipeline.run(&proposal(allowed), &ctx).await;
+            assert!(report.verdict.approved, "{submitter:?}: {}", report.verdict);
+
+            let report = pipeline.run(&proposal(allowed + 0.1), &ctx).await;
+            if allowed == 1.0 {
+                // Caught by the structural stage instead.
+                assert_ne!(report.verdict.reasons[0].code, ReasonCode::GateCapExceeded);
+                continue;
+            }
+            let reason = &report.verdict.reasons[0];
+            assert_eq!(reason.code, ReasonCode::GateCapExceeded, "{submitter:?}");
+            assert_eq!(
+                (reason.observed, reason.limit),
+                (Some(allowed + 0.1), Some(allowed))
+            );
+            assert_eq!(reason.node_id.as_deref(), Some("node_hive"));
+        }
+    }
+
+    #[tokio::test]
+    async fn repair_suggests_the_highest_passing_duty() {
+        // 2.88e-5 kg at full duty, so the budget allows just under 0.35.
+        let shard: HashMap<String, CorridorRow> = HashMap::from([("node_hive".into(), hive_row())]);
+        let ctx = VerificationContext::default()
+            .with_shard(Arc::new(shard))
+            .with_budgets(CorridorBudgets {
+                max_mass_kg: 1.0e-5,
+                max_karma_nb: 1.0e12,
+                temperature_k: 310.0,
+                airflow: Default::default(),
+            });
+        let pipeline = VerifierPipeline::new()
+            .add_stage(CeimStage)
+            .with_repair(DEFAULT_REPAIR_ITERATIONS);
+        // node_ghost has no shard row, so no duty can pass.
+        let proposal = crate::tests::directives(&[("node_hive", 0.8), ("node_ghost", 0.3)]);
+
+        let report = pipeline.run(&proposal, &ctx).await;
+        assert!(!report.verdict.approved);
+        let [hive, ghost] = &report.verdict.suggestions[..] else {
+            panic!("{:?}", report.verdict.suggestions);
+        };
+        assert_eq!(
+            (ghost.node_id.as_str(), ghost.suggested_duty),
+            ("node_ghost", None)
+        );
+        assert_eq!((hive.node_id.as_str(), hive.proposed), ("node_hive", 0.8));
+        let duty = hive.suggested_duty.unwrap();
+        assert!((0.34..0.35).contains(&duty), "{duty}");
+
+        let resolution = 0.8 / f64::from(1 << DEFAULT_REPAIR_ITERATIONS);
+        let repaired = pipeline
+            .run(&crate::tests::directives(&[("node_hive", duty)]), &ctx)
+            .await;
+        assert!(repaired.verdict.approved, "{}", repaired.verdict);
+        assert!(repaired.verdict.suggestions.is_empty());
+        let above = pipeline
+            .run(
+                &crate::tests::directives(&[("node_hive", duty + 2.0 * resolution)]),
+                &ctx,
+            )
+            .await;
+        assert!(!above.verdict.approved);
+
+        // Without repair, a rejection carries no suggestions.
+        let plain = VerifierPipeline::new().add_stage(CeimStage);
+        assert!(plain
+            .run(&proposal, &ctx)
+            .await
+            .verdict
+            .suggestions
+            .is_empty());
+    }
+
+    /// A shard store that cannot be reached.
+    struct DownShard;
+
+    #[async_trait]
+    impl ShardLookup for DownShard {
+        async fn row(&self, _node_id: &str) -> Option<CorridorRow> {
+            None
+        }
+
+        async fn health(&self) -> StageHealth {
+            StageHealth::Unavailable("shard store timed out".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn unavailable_stage_rejects_or_degrades_by_failure_mode() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let ctx = VerificationContext::default()
+            .with_shard(Arc::new(DownShard))
+            .with_budgets(CorridorBudgets {
+                max_mass_kg: 1.0,
+                max_karma_nb: 1.0e12,
+                temperature_k: 310.0,
+                airflow: Default::default(),
+            });
+        let stages = || {
+            VerifierPipeline::new()
+                .add_stage(CeimStage)
+                .add_stage(MockStage {
+                    name: "after",
+                    result: StageResult::Pass(vec![]),
+                    log: log.clone(),
+                })
+                .with_repair(DEFAULT_REPAIR_ITERATIONS)
+        };
+
+        // CEIM fails closed by default: rejected, later stages not run,
+        // no repair attempted against a store that cannot answer.
+        let report = stages().run(&proposal(0.5), &ctx).await;
+        assert!(!report.verdict.approved);
+        assert!(!report.verdict.is_degraded());
+        assert_eq!(report.verdict.reasons[0].code, ReasonCode::StageUnavailable);
+        assert_eq!(report.stages[1].outcome, StageOutcome::Unavailable);
+        assert!(report.verdict.suggestions.is_empty());
+        assert!(log.lock().unwrap().is_empty());
+
+        let report = stages()
+            .with_failure_mode("ceim", FailureMode::FailOpenWithFlag)
+            .unwrap()
+            .run(&proposal(0.5), &ctx)
+            .await;
+        assert!(report.verdict.approved, "{}", report.verdict);
+        assert_eq!(report.stages[1].outcome, StageOutcome::Skipped);
+        assert_eq!(*log.lock().unwrap(), ["after"]);
+        let degraded = report.verdict.degraded.as_ref().unwrap();
+        assert_eq!(
+            degraded.skipped,
+            [SkippedStage {
+                stage: "ceim".into(),
+                reason: "shard store timed out".into(),
+            }]
+        );
+        assert_eq!(
+            report.verdict.reasons[0].code,
+            ReasonCode::VerificationDegraded
+        );
+        assert!(report
+            .verdict
+            .to_string()
+            .starts_with("DEGRADED (skipped: ceim): "));
+
+        // A later failure still rejects, and the verdict keeps the flag.
+        let report = stages()
+            .add_stage(MockStage {
+                name: "veto",
+                result: StageResult::Fail(vec![reason(
+                    ReasonCode::BeeVeto,
+                    "hive foraging window",
+                )]),
+                log: log.clone(),
+            })
+            .with_failure_mode("ceim", FailureMode::FailOpenWithFlag)
+            .unwrap()
+            .run(&proposal(0.5), &ctx)
+            .await;
+        assert!(!report.verdict.approved);
+        assert!(report.verdict.is_degraded());
+    }
+
+    /// A bee kernel whose model is missing and which asks to fail open.
+    struct DownBeeKernel;
+
+    #[async_trait]
+    impl VerificationStage for DownBeeKernel {
+        fn name(&self) -> &str {
+            BEE_VETO_STAGE
+        }
+
+        async fn run(&self, _proposal: &Proposal, _ctx: &VerificationContext) -> StageResult {
+            StageResult::Pass(vec![])
+        }
+
+        async fn health(&self, _ctx: &VerificationContext) -> StageHealth {
+            StageHealth::Unavailable("polytope not loaded".into())
+        }
+
+        fn failure_mode(&self) -> FailureMode {
+            FailureMode::FailOpenWithFlag
+        }
+    }
+
+    #[tokio::test]
+    async fn structural_and_bee_veto_stages_always_fail_closed() {
+        let stages = || VerifierPipeline::new().add_stage(DownBeeKernel);
+        for name in PINNED_STAGES {
+            assert_eq!(
+                stages()
+                    .with_failure_mode(name, FailureMode::FailOpenWithFlag)
+                    .err(),
+                Some(FailureModeError::Pinned(name.into()))
+            );
+        }
+        assert_eq!(
+            stages()
+                .with_failure_mode("bee_kernel", FailureMode::FailOpenWithFlag)
+                .err(),
+            Some(FailureModeError::UnknownStage("bee_kernel".into()))
+        );
+
+        // The stage's own fail-open declaration is ignored too.
+        let report = stages()
+            .run(&proposal(0.5), &VerificationContext::default())
+            .await;
+        assert!(!report.verdict.approved);
+        assert!(!report.verdict.is_degraded());
+        assert_eq!(report.verdict.reasons[0].code, ReasonCode::StageUnavailable);
+    }
 }
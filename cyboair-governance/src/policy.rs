@@ -2,8 +2,13 @@
 
 use crate::types::*;
 use async_trait::async_trait;
+use chrono::Timelike;
 use gatehouse::{AccessDecision, AccessEvaluation, PermissionChecker, Policy, PolicyEvalResult};
 
+use crate::enrich::{EnrichmentReport, ResourceEnricher};
+use crate::export::ExportFilter;
+use crate::request::{timed, RequestContext};
+
 /// RBAC: static role -> coarse permissions.
 pub struct RbacPolicy;
 
@@ -12,8 +17,8 @@ impl Policy<User, Resource, Action, EnvironmentCtx> for RbacPolicy {
     async fn evaluate_access(
         &self,
         user: &User,
-        _res: &Resource,
         action: &Action,
+        _res: &Resource,
         _env: &EnvironmentCtx,
     ) -> PolicyEvalResult {
         use Action::*;
@@ -21,10 +26,12 @@ impl Policy<User, Resource, Action, EnvironmentCtx> for RbacPolicy {
 
         let allowed = match (&user.role, action) {
             (Superchair, _) => true,
-            (Stakeholder, Read) | (Stakeholder, Write) => true,
-            (Stakeholder, ExecuteControlProposal) | (Stakeholder, Export) => false,
-            (Staff, Read) | (Staff, Write) | (Staff, ExecuteControlProposal) => true,
-            (Staff, Export) => false,
+            // Stakeholder and Staff exports are redacted by ExportFilter.
+            (Stakeholder, Read) | (Stakeholder, Write) | (Stakeholder, Export) => true,
+            (Stakeholder, ExecuteControlProposal) => false,
+            (Staff, Read) | (Staff, Write) | (Staff, ExecuteControlProposal) | (Staff, Export) => {
+                true
+            }
             (Guest, Read) => true,
             (Guest, Write) | (Guest, ExecuteControlProposal) | (Guest, Export) => false,
             (Bot, Read) | (Bot, Write) => true,
@@ -51,8 +58,8 @@ impl Policy<User, Resource, Action, EnvironmentCtx> for AbacPolicy {
     async fn evaluate_access(
         &self,
         user: &User,
-        res: &Resource,
         action: &Action,
+        res: &Resource,
         env: &EnvironmentCtx,
     ) -> PolicyEvalResult {
         use Action::*;
@@ -103,7 +110,7 @@ impl Policy<User, Resource, Action, EnvironmentCtx> for AbacPolicy {
             );
         }
 
-        // Additional time-based or IP-based conditions can be plugged here.
+        // Time-based conditions live in TimeWindowPolicy; IP-based ones can be plugged here.
 
         PolicyEvalResult::granted("AbacPolicy", Some("ABAC conditions satisfied".into()))
     }
@@ -113,9 +120,318 @@ impl Policy<User, Resource, Action, EnvironmentCtx> for AbacPolicy {
     }
 }
 
+/// Business hours applied to Export when a resource declares none.
+pub const DEFAULT_BUSINESS_HOURS: &str = "08:00-18:00";
+
+/// Daily time window in local minutes since midnight, end exclusive.
+/// A window whose end precedes its start crosses midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start_min: u32,
+    pub end_min: u32,
+}
+
+impl TimeWindow {
+    /// Parse "HH:MM-HH:MM".
+    pub fn parse(s: &str) -> Result<Self, String> {
+        fn hhmm(t: &str) -> Result<u32, String> {
+            let (h, m) = t
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| format!("expected HH:MM, got {t:?}"))?;
+            let h: u32 = h.parse().map_err(|_| format!("bad hour in {t:?}"))?;
+            let m: u32 = m.parse().map_err(|_| format!("bad minute in {t:?}"))?;
+            if h > 23 || m > 59 {
+                return Err(format!("time out of range: {t:?}"));
+            }
+            Ok(h * 60 + m)
+        }
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got {s:?}"))?;
+        let window = TimeWindow {
+            start_min: hhmm(start)?,
+            end_min: hhmm(end)?,
+        };
+        if window.start_min == window.end_min {
+            return Err(format!("empty window {s:?}"));
+        }
+        Ok(window)
+    }
+
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start_min < self.end_min {
+            (self.start_min..self.end_min).contains(&minute_of_day)
+        } else {
+            minute_of_day >= self.start_min || minute_of_day < self.end_min
+        }
+    }
+}
+
+/// Time windows from resource properties, evaluated in resource-local time:
+/// - "quiet_hours" = "22:00-06:00" forbids ExecuteControlProposal inside the window;
+/// - "business_hours" (default `DEFAULT_BUSINESS_HOURS`) limits Export to the window,
+///   except for Superchair;
+/// - "utc_offset_minutes" (Int) shifts EnvironmentCtx.time_utc to local time.
+///
+/// Malformed window or offset properties deny with the parse error as reason.
+pub struct TimeWindowPolicy;
+
+impl TimeWindowPolicy {
+    fn window(
+        res: &Resource,
+        key: &str,
+        default: Option<&str>,
+    ) -> Result<Option<TimeWindow>, String> {
+        match res.properties.get(key) {
+            Some(PropertyValue::Str(s)) => TimeWindow::parse(s)
+                .map(Some)
+                .map_err(|e| format!("malformed {key} window: {e}")),
+            Some(_) => Err(format!("malformed {key} window: expected a string")),
+            None => Ok(default.map(|d| TimeWindow::parse(d).expect("default window parses"))),
+        }
+    }
+
+    fn local_minute(res: &Resource, env: &EnvironmentCtx) -> Result<u32, String> {
+        let offset = match res.properties.get("utc_offset_minutes") {
+            Some(PropertyValue::Int(m)) if m.abs() <= 14 * 60 => *m,
+            Some(_) => return Err("malformed utc_offset_minutes".into()),
+            None => 0,
+        };
+        let utc = i64::from(env.time_utc.hour() * 60 + env.time_utc.minute());
+        Ok((utc + offset).rem_euclid(24 * 60) as u32)
+    }
+}
+
+#[async_trait]
+impl Policy<User, Resource, Action, EnvironmentCtx> for TimeWindowPolicy {
+    async fn evaluate_access(
+        &self,
+        user: &User,
+        action: &Action,
+        res: &Resource,
+        env: &EnvironmentCtx,
+    ) -> PolicyEvalResult {
+        let (key, default) = match action {
+            Action::ExecuteControlProposal => ("quiet_hours", None),
+            Action::Export if !matches!(user.role, Role::Superchair) => {
+                ("business_hours", Some(DEFAULT_BUSINESS_HOURS))
+            }
+            _ => {
+                return PolicyEvalResult::granted(
+                    "TimeWindowPolicy",
+                    Some("no time window applies".into()),
+                )
+            }
+        };
+
+        let window = match Self::window(res, key, default) {
+            Ok(Some(w)) => w,
+            Ok(None) => {
+                return PolicyEvalResult::granted(
+                    "TimeWindowPolicy",
+                    Some(format!("resource declares no {key}")),
+                )
+            }
+            Err(reason) => return PolicyEvalResult::denied("TimeWindowPolicy", reason),
+        };
+        let minute = match Self::local_minute(res, env) {
+            Ok(m) => m,
+            Err(reason) => return PolicyEvalResult::denied("TimeWindowPolicy", reason),
+        };
+
+        // Quiet hours forbid inside the window; business hours allow only inside it.
+        let inside = window.contains(minute);
+        let allowed = if key == "quiet_hours" {
+            !inside
+        } else {
+            inside
+        };
+        let local = format!("{:02}:{:02}", minute / 60, minute % 60);
+        if allowed {
+            PolicyEvalResult::granted(
+                "TimeWindowPolicy",
+                Some(format!("{local} local is permitted by {key}")),
+            )
+        } else {
+            PolicyEvalResult::denied("TimeWindowPolicy", format!("{local} local violates {key}"))
+        }
+    }
+
+    fn policy_type(&self) -> String {
+        "TimeWindowPolicy".into()
+    }
+}
+
+/// Resource property naming the resource's zone.
+pub const ZONE_PROPERTY: &str = "zone";
+/// User attribute listing the zone patterns a principal may act in.
+pub const ZONES_ATTRIBUTE: &str = "zones";
+
+/// Hierarchical zone such as "phoenix/downtown/school-district-3": one or
+/// more `/`-separated segments of lowercase ASCII letters, digits, `-` and `_`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZonePath {
+    segments: Vec<String>,
+}
+
+impl ZonePath {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let segments = s
+            .split('/')
+            .map(|seg| {
+                let valid = !seg.is_empty()
+                    && seg.bytes().all(|b| {
+                        b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_'
+                    });
+                if valid {
+                    Ok(seg.to_string())
+                } else {
+                    Err(format!("bad zone segment {seg:?} in {s:?}"))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ZonePath { segments })
+    }
+}
+
+/// Zone glob, matched segment by segment (no regex):
+/// - a literal segment matches only the same segment;
+/// - `*` as a whole segment matches exactly one segment, except as the last
+///   segment, where it matches one or more, i.e. the whole subtree below the
+///   prefix but not the prefix itself;
+/// - a pattern without a trailing `*` matches that exact zone only.
+///
+/// So "phoenix/*" covers "phoenix/downtown/school-district-3" but neither
+/// "phoenix" nor "phoenixville/north". Partial-segment globs ("school-*"),
+/// `**` and empty segments are malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZonePattern {
+    /// `None` is a `*` segment.
+    segments: Vec<Option<String>>,
+}
+
+impl ZonePattern {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let segments = s
+            .split('/')
+            .map(|seg| match seg {
+                "*" => Ok(None),
+                _ => ZonePath::parse(seg)
+                    .map(|p| p.segments.into_iter().next())
+                    .map_err(|_| format!("bad zone pattern segment {seg:?} in {s:?}")),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ZonePattern { segments })
+    }
+
+    pub fn matches(&self, zone: &ZonePath) -> bool {
+        let subtree = matches!(self.segments.last(), Some(None));
+        let len_ok = if subtree {
+            zone.segments.len() >= self.segments.len()
+        } else {
+            zone.segments.len() == self.segments.len()
+        };
+        len_ok
+            && self
+                .segments
+                .iter()
+                .zip(&zone.segments)
+                .all(|(pat, seg)| pat.is_none() || pat.as_ref() == Some(seg))
+    }
+}
+
+/// Zone scoping for Write and ExecuteControlProposal.
+///
+/// A principal is zone-scoped when its `ZONES_ATTRIBUTE` holds a
+/// comma-separated list of `ZonePattern`s, e.g. "phoenix/*, tucson/north";
+/// it may then only act on resources whose `ZONE_PROPERTY` matches one of
+/// them. Unscoped principals and Superchair are not restricted here. A
+/// scoped principal is denied on resources without a zone, and malformed
+/// zones or patterns deny with the parse error as reason.
+pub struct ZonePolicy;
+
+impl ZonePolicy {
+    fn scopes(user: &User) -> Option<Result<Vec<ZonePattern>, String>> {
+        let scopes = match user.attributes.get(ZONES_ATTRIBUTE)? {
+            AttributeValue::Str(s) => s
+                .split(',')
+                .map(|p| ZonePattern::parse(p.trim()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("malformed {ZONES_ATTRIBUTE}: {e}")),
+            _ => Err(format!("malformed {ZONES_ATTRIBUTE}: expected a string")),
+        };
+        Some(scopes)
+    }
+
+    fn zone(res: &Resource) -> Result<Option<ZonePath>, String> {
+        match res.properties.get(ZONE_PROPERTY) {
+            Some(PropertyValue::Str(s)) => ZonePath::parse(s)
+                .map(Some)
+                .map_err(|e| format!("malformed {ZONE_PROPERTY}: {e}")),
+            Some(_) => Err(format!("malformed {ZONE_PROPERTY}: expected a string")),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Policy<User, Resource, Action, EnvironmentCtx> for ZonePolicy {
+    async fn evaluate_access(
+        &self,
+        user: &User,
+        action: &Action,
+        res: &Resource,
+        _env: &EnvironmentCtx,
+    ) -> PolicyEvalResult {
+        if !matches!(action, Action::Write | Action::ExecuteControlProposal)
+            || matches!(user.role, Role::Superchair)
+        {
+            return PolicyEvalResult::granted("ZonePolicy", Some("no zone scope applies".into()));
+        }
+        let scopes = match Self::scopes(user) {
+            None => {
+                return PolicyEvalResult::granted(
+                    "ZonePolicy",
+                    Some("principal is not zone-scoped".into()),
+                )
+            }
+            Some(Ok(scopes)) => scopes,
+            Some(Err(reason)) => return PolicyEvalResult::denied("ZonePolicy", reason),
+        };
+        let zone = match Self::zone(res) {
+            Ok(Some(zone)) => zone,
+            Ok(None) => {
+                return PolicyEvalResult::denied(
+                    "ZonePolicy",
+                    "resource has no zone for zone-scoped principal",
+                )
+            }
+            Err(reason) => return PolicyEvalResult::denied("ZonePolicy", reason),
+        };
+        let zone_str = zone.segments.join("/");
+        if scopes.iter().any(|p| p.matches(&zone)) {
+            PolicyEvalResult::granted(
+                "ZonePolicy",
+                Some(format!("zone {zone_str} is within principal scope")),
+            )
+        } else {
+            PolicyEvalResult::denied(
+                "ZonePolicy",
+                format!("zone {zone_str} is outside principal scope"),
+            )
+        }
+    }
+
+    fn policy_type(&self) -> String {
+        "ZonePolicy".into()
+    }
+}
+
 /// Central governance core: this is your F_policy implementation.
 pub struct GovernanceCore {
     checker: PermissionChecker<User, Resource, Action, EnvironmentCtx>,
+    enricher: Option<ResourceEnricher>,
 }
 
 impl GovernanceCore {
@@ -123,7 +439,18 @@ impl GovernanceCore {
         let mut checker = PermissionChecker::new();
         checker.add_policy(RbacPolicy);
         checker.add_policy(AbacPolicy);
-        Self { checker }
+        checker.add_policy(TimeWindowPolicy);
+        checker.add_policy(ZonePolicy);
+        Self {
+            checker,
+            enricher: None,
+        }
+    }
+
+    /// Derive resource properties from the shard before every evaluation.
+    pub fn with_enricher(mut self, enricher: ResourceEnricher) -> Self {
+        self.enricher = Some(enricher);
+        self
     }
 
     pub async fn authorize(
@@ -133,6 +460,384 @@ impl GovernanceCore {
         action: &Action,
         env: &EnvironmentCtx,
     ) -> AccessEvaluation {
-        self.checker.evaluate_access(user, res, action, env).await
+        self.authorize_with_report(user, res, action, env).await.0
+    }
+
+    /// `authorize` on behalf of the inbound `request`. With the `tracing`
+    /// feature the evaluation runs in a span carrying the request id.
+    pub async fn authorize_request(
+        &self,
+        user: &User,
+        res: &Resource,
+        action: &Action,
+        env: &EnvironmentCtx,
+        request: &RequestContext,
+    ) -> AccessEvaluation {
+        let evaluation = self.authorize(user, res, action, env);
+        timed("GovernanceCore".into(), Some(request), evaluation)
+            .await
+            .0
+    }
+
+    /// `authorize`, also returning what the enricher changed; `None`
+    /// without an enricher.
+    pub async fn authorize_with_report(
+        &self,
+        user: &User,
+        res: &Resource,
+        action: &Action,
+        env: &EnvironmentCtx,
+    ) -> (AccessEvaluation, Option<EnrichmentReport>) {
+        let Some(enricher) = &self.enricher else {
+            return (
+                self.checker.evaluate_access(user, action, res, env).await,
+                None,
+            );
+        };
+        let mut enriched = res.clone();
+        let report = enricher.enrich(&mut enriched);
+        let eval = self
+            .checker
+            .evaluate_access(user, action, &enriched, env)
+            .await;
+        (eval, Some(report))
+    }
+
+    /// Authorize Export on `res`, then redact `payload` for the user's role.
+    /// A denial returns the evaluation so callers can report why; a role the
+    /// filter does not list is denied by `ExportFilter`.
+    pub async fn authorize_and_filter_export(
+        &self,
+        user: &User,
+        res: &Resource,
+        env: &EnvironmentCtx,
+        payload: &serde_json::Value,
+        filter: &ExportFilter,
+    ) -> Result<serde_json::Value, AccessEvaluation> {
+        let eval = self.authorize(user, res, &Action::Export, env).await;
+        if let AccessDecision::Denied = eval.decision {
+            return Err(eval);
+        }
+        filter
+            .apply(&user.role, payload)
+            .ok_or_else(|| AccessEvaluation {
+                decision: AccessDecision::Denied,
+                results: vec![PolicyEvalResult::denied(
+                    "ExportFilter",
+                    "no export redaction spec for role",
+                )],
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn ops_staff() -> User {
+        User {
+            user_id: "ops@cyboair.org".into(),
+            role: Role::Staff,
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Residential node in Phoenix local time (UTC-7, no DST).
+    fn residential_node(quiet_hours: &str) -> Resource {
+        Resource {
+            resource_id: "node_res_03".into(),
+            resource_type: ResourceType::Node,
+            properties: HashMap::from([
+                (
+                    "quiet_hours".to_string(),
+                    PropertyValue::Str(quiet_hours.into()),
+                ),
+                ("utc_offset_minutes".to_string(), PropertyValue::Int(-420)),
+            ]),
+        }
+    }
+
+    fn at_utc(h: u32, m: u32) -> EnvironmentCtx {
+        EnvironmentCtx {
+            time_utc: Utc.with_ymd_and_hms(2025, 7, 1, h, m, 0).unwrap(),
+            ip_address: "10.0.0.5".into(),
+            is_encrypted_channel: true,
+        }
+    }
+
+    #[test]
+    fn windows_cross_midnight() {
+        let quiet = TimeWindow::parse("22:00-06:00").unwrap();
+        assert!(quiet.contains(23 * 60 + 30));
+        assert!(quiet.contains(2 * 60));
+        assert!(!quiet.contains(6 * 60));
+        assert!(!quiet.contains(9 * 60));
+
+        let day = TimeWindow::parse("08:00-18:00").unwrap();
+        assert!(day.contains(9 * 60) && !day.contains(18 * 60));
+        assert!(TimeWindow::parse("25:00-06:00").is_err());
+        assert!(TimeWindow::parse("22:00").is_err());
+    }
+
+    #[tokio::test]
+    async fn execute_in_quiet_hours_is_denied() {
+        let core = GovernanceCore::new();
+        let node = residential_node("22:00-06:00");
+
+        // 06:30 UTC is 23:30 in Phoenix.
+        let eval = core
+            .authorize(
+                &ops_staff(),
+                &node,
+                &Action::ExecuteControlProposal,
+                &at_utc(6, 30),
+            )
+            .await;
+        assert!(matches!(eval.decision, AccessDecision::Denied));
+
+        // 16:00 UTC is 09:00 in Phoenix.
+        let eval = core
+            .authorize(
+                &ops_staff(),
+                &node,
+                &Action::ExecuteControlProposal,
+                &at_utc(16, 0),
+            )
+            .await;
+        assert!(matches!(eval.decision, AccessDecision::Granted));
+    }
+
+    #[tokio::test]
+    async fn malformed_window_denies_with_reason() {
+        let node = residential_node("10pm-6am");
+        let result = TimeWindowPolicy
+            .evaluate_access(
+                &ops_staff(),
+                &Action::ExecuteControlProposal,
+                &node,
+                &at_utc(16, 0),
+            )
+            .await;
+        match result {
+            PolicyEvalResult::Denied { reason, .. } => {
+                assert!(reason.contains("malformed quiet_hours window"), "{reason}");
+            }
+            other => panic!("expected deny, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn export_outside_business_hours_needs_superchair() {
+        let node = residential_node("22:00-06:00");
+        let bot = User {
+            user_id: "exporter".into(),
+            role: Role::Bot,
+            attributes: HashMap::new(),
+        };
+        let superchair = User {
+            role: Role::Superchair,
+            ..bot.clone()
+        };
+
+        // 03:00 UTC is 20:00 in Phoenix, after the default 08:00-18:00.
+        let late = at_utc(3, 0);
+        assert!(!TimeWindowPolicy
+            .evaluate_access(&bot, &Action::Export, &node, &late)
+            .await
+            .is_granted());
+        assert!(TimeWindowPolicy
+            .evaluate_access(&superchair, &Action::Export, &node, &late)
+            .await
+            .is_granted());
+        assert!(TimeWindowPolicy
+            .evaluate_access(&bot, &Action::Export, &node, &at_utc(17, 0))
+            .await
+            .is_granted());
+    }
+
+    #[tokio::test]
+    async fn export_is_redacted_per_role() {
+        let core = GovernanceCore::new();
+        let filter = ExportFilter::from_json(include_str!("../config/export_redaction.json"))
+            .unwrap()
+            .with_hash_key(b"test-export-key");
+        let mut node = residential_node("22:00-06:00");
+        node.properties.insert(
+            "owner_id".into(),
+            PropertyValue::Str("owner@example.org".into()),
+        );
+        let payload = json!({
+            "machine_id": "CYB-AIR-APIARY-01",
+            "owner_email": "keeper@example.org",
+            "zone": "Phoenix-Apiary-Zone-1",
+            "hive_location": { "lat": 33.448376, "lon": -112.074036 }
+        });
+        // 20:00 UTC is 13:00 in Phoenix, inside business hours.
+        let midday = at_utc(20, 0);
+
+        let staff = core
+            .authorize_and_filter_export(&ops_staff(), &node, &midday, &payload, &filter)
+            .await
+            .unwrap();
+        let owner = User {
+            user_id: "owner@example.org".into(),
+            role: Role::Stakeholder,
+            attributes: HashMap::new(),
+        };
+        let stakeholder = core
+            .authorize_and_filter_export(&owner, &node, &midday, &payload, &filter)
+            .await
+            .unwrap();
+        assert_ne!(staff, stakeholder);
+        assert!(staff["owner_email"].as_str().unwrap().starts_with("h:"));
+        assert!(stakeholder.get("owner_email").is_none());
+        assert_eq!(stakeholder["hive_location"], json!("Phoenix-Apiary-Zone-1"));
+
+        let guest = User {
+            user_id: "visitor".into(),
+            role: Role::Guest,
+            attributes: HashMap::new(),
+        };
+        assert!(core
+            .authorize_and_filter_export(&guest, &node, &midday, &payload, &filter)
+            .await
+            .is_err());
+    }
+
+    fn zoned_node(zone: Option<&str>) -> Resource {
+        let mut node = residential_node("22:00-06:00");
+        if let Some(zone) = zone {
+            node.properties
+                .insert(ZONE_PROPERTY.into(), PropertyValue::Str(zone.into()));
+        }
+        node
+    }
+
+    fn scoped_staff(zones: &str) -> User {
+        let mut user = ops_staff();
+        user.attributes
+            .insert(ZONES_ATTRIBUTE.into(), AttributeValue::Str(zones.into()));
+        user
+    }
+
+    async fn zone_check(user: &User, zone: Option<&str>) -> PolicyEvalResult {
+        ZonePolicy
+            .evaluate_access(
+                user,
+                &Action::ExecuteControlProposal,
+                &zoned_node(zone),
+                &at_utc(16, 0),
+            )
+            .await
+    }
+
+    fn denial(result: PolicyEvalResult) -> String {
+        match result {
+            PolicyEvalResult::Denied { reason, .. } => reason,
+            other => panic!("expected deny, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn zone_scopes_match_exactly_or_by_subtree() {
+        let school = Some("phoenix/downtown/school-district-3");
+        let exact = scoped_staff("phoenix/downtown/school-district-3");
+        assert!(zone_check(&exact, school).await.is_granted());
+        assert!(!zone_check(&exact, Some("phoenix/downtown"))
+            .await
+            .is_granted());
+
+        let phoenix = scoped_staff("tucson/north, phoenix/*");
+        assert!(zone_check(&phoenix, school).await.is_granted());
+        assert!(zone_check(&phoenix, Some("phoenix/west"))
+            .await
+            .is_granted());
+        assert!(!zone_check(&phoenix, Some("phoenix")).await.is_granted());
+
+        let any_district = scoped_staff("phoenix/*/school-district-3");
+        assert!(zone_check(&any_district, school).await.is_granted());
+        assert!(
+            !zone_check(&any_district, Some("phoenix/downtown/school-district-4"))
+                .await
+                .is_granted()
+        );
+    }
+
+    #[tokio::test]
+    async fn sibling_zones_are_outside_scope() {
+        let downtown = scoped_staff("phoenix/downtown/*");
+        let reason = denial(zone_check(&downtown, Some("phoenix/uptown/school-district-3")).await);
+        assert_eq!(
+            reason,
+            "zone phoenix/uptown/school-district-3 is outside principal scope"
+        );
+        // Prefixes compare whole segments, not characters.
+        let phoenix = scoped_staff("phoenix/*");
+        assert!(!zone_check(&phoenix, Some("phoenixville/north"))
+            .await
+            .is_granted());
+    }
+
+    #[tokio::test]
+    async fn scoped_principal_needs_a_well_formed_zone() {
+        let phoenix = scoped_staff("phoenix/*");
+        assert_eq!(
+            denial(zone_check(&phoenix, None).await),
+            "resource has no zone for zone-scoped principal"
+        );
+        for zone in [
+            "phoenix//north",
+            "phoenix/*",
+            "Phoenix/north",
+            "phoenix/north/",
+        ] {
+            let reason = denial(zone_check(&phoenix, Some(zone)).await);
+            assert!(reason.starts_with("malformed zone"), "{zone}: {reason}");
+        }
+        for zones in ["phoenix/school-*", "phoenix/**", "phoenix/*,", ""] {
+            let reason = denial(zone_check(&scoped_staff(zones), Some("phoenix/west")).await);
+            assert!(reason.starts_with("malformed zones"), "{zones:?}: {reason}");
+        }
+
+        // Unscoped principals, Superchair and other actions are not restricted.
+        assert!(zone_check(&ops_staff(), None).await.is_granted());
+        let superchair = User {
+            role: Role::Superchair,
+            ..phoenix.clone()
+        };
+        assert!(zone_check(&superchair, Some("tucson/north"))
+            .await
+            .is_granted());
+        assert!(ZonePolicy
+            .evaluate_access(&phoenix, &Action::Read, &zoned_node(None), &at_utc(16, 0))
+            .await
+            .is_granted());
+    }
+
+    #[tokio::test]
+    async fn core_applies_zone_scopes_to_writes() {
+        let core = GovernanceCore::new();
+        let phoenix = scoped_staff("phoenix/*");
+        let eval = core
+            .authorize(
+                &phoenix,
+                &zoned_node(Some("phoenix/downtown")),
+                &Action::Write,
+                &at_utc(16, 0),
+            )
+            .await;
+        assert!(matches!(eval.decision, AccessDecision::Granted));
+        let eval = core
+            .authorize(
+                &phoenix,
+                &zoned_node(Some("tucson/north")),
+                &Action::Write,
+                &at_utc(16, 0),
+            )
+            .await;
+        assert!(matches!(eval.decision, AccessDecision::Denied));
     }
 }
@@ -0,0 +1,92 @@
+#![forbid(unsafe_code)]
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Stable, machine-readable verdict codes. Automation should branch on
+/// these rather than on message text; new codes are only ever appended.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReasonCode {
+    /// Informational: a check passed.
+    Passed,
+    InvalidProposal,
+    LengthMismatch,
+    DutyOutOfRange,
+    MissingShardRow,
+    CeimProjectionFailed,
+    CeimBudgetExceeded,
+    RohViolation,
+    BeeVeto,
+    QuorumPending,
+    QuorumNotReached,
+    UnknownProposal,
+    UnknownSigner,
+    BadSignature,
+    Replay,
+    Unauthorized,
+    EmergencyOverride,
+    TooManyDirectives,
+    InvalidHorizon,
+    DuplicateNode,
+    GateCapExceeded,
+    /// A fail-closed stage's dependency was unavailable.
+    StageUnavailable,
+    /// A fail-open stage was skipped; the verdict is degraded.
+    VerificationDegraded,
+}
+
+/// One finding behind a verdict.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VerdictReason {
+    pub code: ReasonCode,
+    pub node_id: Option<String>,
+    /// Offending value, where the check is numeric.
+    pub observed: Option<f64>,
+    /// Limit `observed` was checked against.
+    pub limit: Option<f64>,
+    /// Human-readable rendering; not stable.
+    pub message: String,
+}
+
+impl VerdictReason {
+    pub fn new(code: ReasonCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            node_id: None,
+            observed: None,
+            limit: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn for_node(mut self, node_id: impl Into<String>) -> Self {
+        self.node_id = Some(node_id.into());
+        self
+    }
+
+    pub fn with_values(mut self, observed: f64, limit: f64) -> Self {
+        self.observed = Some(observed);
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl fmt::Display for VerdictReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Render reasons the way verdict messages have always read, joined by "; ".
+pub(crate) fn write_reasons(f: &mut fmt::Formatter<'_>, reasons: &[VerdictReason]) -> fmt::Result {
+    for (i, reason) in reasons.iter().enumerate() {
+        if i > 0 {
+            f.write_str("; ")?;
+        }
+        write!(f, "{reason}")?;
+    }
+    Ok(())
+}
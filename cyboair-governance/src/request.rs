@@ -0,0 +1,121 @@
+#![forbid(unsafe_code)]
+
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Identifies one inbound request so its decision can be matched across
+/// the HTTP layer, `GovernanceCore` and the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RequestContext {
+    pub request_id: Uuid,
+    pub received_at: DateTime<Utc>,
+    /// Span of the caller's trace this request belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+}
+
+impl RequestContext {
+    /// A fresh random request id.
+    pub fn new(received_at: DateTime<Utc>) -> Self {
+        Self::with_id(Uuid::new_v4(), received_at)
+    }
+
+    /// Keep an id assigned upstream, e.g. from an `X-Request-Id` header.
+    pub fn with_id(request_id: Uuid, received_at: DateTime<Utc>) -> Self {
+        Self {
+            request_id,
+            received_at,
+            parent_span_id: None,
+        }
+    }
+
+    pub fn with_parent_span(mut self, span_id: impl Into<String>) -> Self {
+        self.parent_span_id = Some(span_id.into());
+        self
+    }
+}
+
+/// How long one policy took to evaluate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyTiming {
+    pub policy: String,
+    pub elapsed: Duration,
+}
+
+/// Monotonic timer. `Instant::now` panics on wasm32-unknown-unknown, so
+/// the wasm build reads the JS host clock instead, at millisecond
+/// resolution.
+pub(crate) struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    start: std::time::Instant,
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    start_ms: f64,
+}
+
+impl Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    pub(crate) fn start() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    pub(crate) fn start() -> Self {
+        Self {
+            start_ms: js_sys::Date::now(),
+        }
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64((js_sys::Date::now() - self.start_ms).max(0.0) / 1e3)
+    }
+}
+
+/// Await one policy evaluation and time it. With the `tracing` feature it
+/// runs inside a `policy` span carrying the policy name and request id.
+pub(crate) async fn timed<F: Future>(
+    policy: String,
+    request: Option<&RequestContext>,
+    evaluation: F,
+) -> (F::Output, PolicyTiming) {
+    let stopwatch = Stopwatch::start();
+    #[cfg(feature = "tracing")]
+    let output = {
+        use tracing::Instrument;
+        let span = tracing::info_span!(
+            "policy",
+            policy = %policy,
+            request_id = tracing::field::Empty,
+            parent_span_id = tracing::field::Empty,
+        );
+        if let Some(request) = request {
+            span.record("request_id", tracing::field::display(request.request_id));
+            if let Some(parent) = &request.parent_span_id {
+                span.record("parent_span_id", parent.as_str());
+            }
+        }
+        evaluation.instrument(span).await
+    };
+    #[cfg(not(feature = "tracing"))]
+    let output = {
+        let _ = request;
+        evaluation.await
+    };
+    let timing = PolicyTiming {
+        policy,
+        elapsed: stopwatch.elapsed(),
+    };
+    (output, timing)
+}
@@ -0,0 +1,140 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+
+use cybo_corridor_core::aln::{
+    AlnDocument, AlnError, AlnTable, SignatureVerifier, ROH_STRESSOR_PREFIX,
+};
+use serde::{Deserialize, Serialize};
+
+/// Ceiling on RoH before any proposal may be applied.
+pub const ROH_CEILING: f64 = 0.3;
+
+/// Normalized stressor readings keyed by stressor name, each nominally in [0, 1].
+pub type RohInputs = HashMap<String, f64>;
+
+/// One weighted stressor in the RoH model.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stressor {
+    pub name: String,
+    pub weight: f64,
+    /// Value used when the inputs omit this stressor; 0.0 if not given.
+    #[serde(default)]
+    pub default: f64,
+}
+
+/// Risk-of-harm model: a weighted sum over normalized stressors.
+///
+/// JSON format:
+/// `{"stressors": [{"name": "pm25", "weight": 0.4}, {"name": "noise", "weight": 0.1, "default": 0.2}]}`
+///
+/// or, from a signed `.rohmodel.aln` table, one `roh.stressor.<name>`
+/// section per stressor with `weight` and optional `default` entries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RohModel {
+    #[serde(default)]
+    pub stressors: Vec<Stressor>,
+}
+
+impl RohModel {
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// Stressors from a verified table, in file order. Weights must be
+    /// finite and non-negative, defaults in [0, 1].
+    pub fn from_aln(table: &AlnTable) -> Result<Self, AlnError> {
+        let stressors = table
+            .sections_with_prefix(ROH_STRESSOR_PREFIX)
+            .map(|(name, section)| {
+                section.only_keys(&["weight", "default"])?;
+                let weight = section.f64("weight")?;
+                if weight < 0.0 {
+                    return Err(AlnError::OutOfRange {
+                        section: section.name.clone(),
+                        key: "weight".into(),
+                        value: weight,
+                    });
+                }
+                let default = section.opt_f64("default")?.unwrap_or(0.0);
+                if !(0.0..=1.0).contains(&default) {
+                    return Err(AlnError::OutOfRange {
+                        section: section.name.clone(),
+                        key: "default".into(),
+                        value: default,
+                    });
+                }
+                Ok(Stressor {
+                    name: name.to_string(),
+                    weight,
+                    default,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if stressors.is_empty() {
+            return Err(AlnError::MissingSection {
+                section: format!("{ROH_STRESSOR_PREFIX}*"),
+            });
+        }
+        Ok(RohModel { stressors })
+    }
+
+    /// [`from_aln`](Self::from_aln) over `text` once `verifier` accepts its
+    /// signature.
+    pub fn from_signed_aln(
+        text: &str,
+        verifier: &impl SignatureVerifier,
+    ) -> Result<Self, AlnError> {
+        Self::from_aln(&AlnDocument::parse(text)?.verify(verifier)?)
+    }
+
+    /// RoH = sum_i w_i * clamp(x_i, 0, 1), clamped to [0, 1]. Stressors missing
+    /// from `inputs` use their default. Non-finite inputs yield NaN.
+    pub fn compute_roh(&self, inputs: &RohInputs) -> f64 {
+        let roh: f64 = self
+            .stressors
+            .iter()
+            .map(|s| {
+                let x = inputs.get(&s.name).copied().unwrap_or(s.default);
+                if x.is_finite() {
+                    s.weight * x.clamp(0.0, 1.0)
+                } else {
+                    f64::NAN
+                }
+            })
+            .sum();
+        if roh.is_nan() {
+            roh
+        } else {
+            roh.clamp(0.0, 1.0)
+        }
+    }
+
+    /// Enforce RoH_after <= RoH_before <= ROH_CEILING, returning both values.
+    pub fn check_invariant(
+        &self,
+        before: &RohInputs,
+        after: &RohInputs,
+    ) -> Result<(f64, f64), String> {
+        let roh_before = self.compute_roh(before);
+        let roh_after = self.compute_roh(after);
+        if !(roh_before.is_finite() && roh_after.is_finite()) {
+            return Err(format!(
+                "RoH undefined (before={roh_before}, after={roh_after}): non-finite stressor input"
+            ));
+        }
+        if roh_before > ROH_CEILING {
+            return Err(format!(
+                "RoH_before {roh_before:.3} exceeds ceiling {ROH_CEILING:.3}"
+            ));
+        }
+        if roh_after > roh_before {
+            return Err(format!(
+                "RoH_after {roh_after:.3} exceeds RoH_before {roh_before:.3}"
+            ));
+        }
+        Ok((roh_before, roh_after))
+    }
+}
@@ -0,0 +1,173 @@
+#![forbid(unsafe_code)]
+
+//! JSON Schemas of the wire types partners integrate against (feature
+//! `schema`).
+//!
+//! [`write_schemas`] writes one `<name>.schema.json` per entry of
+//! [`SCHEMA_NAMES`] plus a `SHA256SUMS` file. Every schema carries
+//! [`SCHEMA_VERSION`] as `x-cyboair-schema-version`. The snapshots committed
+//! under `schemas/` are checked by a test, so any change to these types (or
+//! to schemars' output) fails CI until `SCHEMA_VERSION` is bumped and the
+//! snapshots are regenerated:
+//!
+//! ```text
+//! cargo run --example export_schemas --features schema -- schemas
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cybo_corridor_core::BeeEnvelope;
+use cyboair_corridor_safety::CorridorRow;
+use schemars::JsonSchema;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::audit::AuditEntry;
+use crate::guards::ControlProposal;
+use crate::pipeline::VerifierVerdict;
+use crate::Verdict;
+
+/// Bump whenever any exported schema changes.
+pub const SCHEMA_VERSION: u32 = 10;
+
+/// File stems of the exported schemas, in output order.
+pub const SCHEMA_NAMES: [&str; 6] = [
+    "audit_entry",
+    "bee_envelope",
+    "control_proposal",
+    "corridor_row",
+    "verdict",
+    "verifier_verdict",
+];
+
+fn versioned<T: JsonSchema>() -> Value {
+    let mut schema = schemars::schema_for!(T);
+    schema.insert("x-cyboair-schema-version".into(), SCHEMA_VERSION.into());
+    schema.to_value()
+}
+
+/// `(file name, contents)` of every schema, in [`SCHEMA_NAMES`] order.
+pub fn schema_files() -> Vec<(String, String)> {
+    let schemas = [
+        versioned::<AuditEntry>(),
+        versioned::<BeeEnvelope>(),
+        versioned::<ControlProposal>(),
+        versioned::<CorridorRow>(),
+        versioned::<Verdict>(),
+        versioned::<VerifierVerdict>(),
+    ];
+    SCHEMA_NAMES
+        .iter()
+        .zip(schemas)
+        .map(|(name, schema)| {
+            // serde_json maps are sorted, so the output is stable.
+            let mut text = serde_json::to_string_pretty(&schema).expect("schema is JSON");
+            text.push('\n');
+            (format!("{name}.schema.json"), text)
+        })
+        .collect()
+}
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+/// `sha256sum`-compatible listing of `files`.
+pub fn sha256sums(files: &[(String, String)]) -> String {
+    files
+        .iter()
+        .map(|(name, text)| format!("{}  {name}\n", sha256_hex(text.as_bytes())))
+        .collect()
+}
+
+/// Write every schema and `SHA256SUMS` into `dir`, creating it if needed.
+/// Returns the paths written.
+pub fn write_schemas(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let files = schema_files();
+    let mut written = Vec::with_capacity(files.len() + 1);
+    for (name, text) in &files {
+        let path = dir.join(name);
+        fs::write(&path, text)?;
+        written.push(path);
+    }
+    let path = dir.join("SHA256SUMS");
+    fs::write(&path, sha256sums(&files))?;
+    written.push(path);
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas")
+    }
+
+    #[test]
+    fn schemas_match_committed_snapshots() {
+        let dir = snapshot_dir();
+        let committed_version = fs::read_to_string(dir.join("control_proposal.schema.json"))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .and_then(|schema| schema["x-cyboair-schema-version"].as_u64());
+        let regenerate = "regenerate with `cargo run --example export_schemas \
+                          --features schema -- schemas`";
+
+        for (name, text) in schema_files() {
+            let committed = fs::read(dir.join(&name)).unwrap_or_default();
+            if sha256_hex(&committed) == sha256_hex(text.as_bytes()) {
+                continue;
+            }
+            if committed_version == Some(u64::from(SCHEMA_VERSION)) {
+                panic!("{name} changed without a SCHEMA_VERSION bump; bump it and {regenerate}");
+            }
+            panic!("{name} is stale for SCHEMA_VERSION {SCHEMA_VERSION}; {regenerate}");
+        }
+        let sums = fs::read_to_string(dir.join("SHA256SUMS")).unwrap_or_default();
+        assert_eq!(
+            sums,
+            sha256sums(&schema_files()),
+            "SHA256SUMS: {regenerate}"
+        );
+    }
+
+    #[test]
+    fn schemas_carry_the_version_and_describe_their_fields() {
+        let files = schema_files();
+        assert_eq!(files.len(), SCHEMA_NAMES.len());
+        for (name, text) in &files {
+            let schema: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(schema["x-cyboair-schema-version"], SCHEMA_VERSION, "{name}");
+        }
+        let proposal: Value = serde_json::from_str(&files[2].1).unwrap();
+        assert_eq!(proposal["title"], "ControlProposal");
+        assert!(proposal["anyOf"][0]["required"]
+            .as_array()
+            .unwrap()
+            .contains(&"directives".into()));
+        assert!(proposal["$defs"]["NodeDirective"]["required"]
+            .as_array()
+            .unwrap()
+            .contains(&"new_duty_cycle".into()));
+    }
+
+    #[test]
+    fn write_schemas_creates_the_directory() {
+        let dir = std::env::temp_dir().join(format!("cyboair-schemas-{}", std::process::id()));
+        let written = write_schemas(&dir).unwrap();
+        assert_eq!(written.len(), SCHEMA_NAMES.len() + 1);
+        assert!(written.iter().all(|p| p.exists()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,616 @@
+//! HTTP front end for policy authorization and proposal verification
+//! (feature `server`).
+//!
+//! * `POST /authorize`: [`AuthorizeRequest`] to [`AuthorizeResponse`],
+//!   answered with 403 and the same body when the decision is a denial.
+//!   Each request gets a [`RequestContext`], with the id from an
+//!   [`REQUEST_ID_HEADER`] UUID if the caller sent one; the id is returned
+//!   in that header and in the response body;
+//! * `POST /proposals`: [`ControlProposal`] to [`VerifierVerdict`]. The
+//!   principal's role must pass RBAC for `ExecuteControlProposal`, then the
+//!   proposal runs through the server's [`VerifierPipeline`]; anything not
+//!   approved is answered with 403 and the verdict.
+//!
+//! Bodies that do not parse get an [`ApiError`] with axum's rejection
+//! status (400, 415 or 422).
+//!
+//! Nothing the decision depends on is taken from the payload. The principal
+//! is whoever the request's credential proves to the [`Authenticator`];
+//! requests without one get 401. `EnvironmentCtx` is built server-side:
+//! `time_utc` from the server's [`Clock`], `ip_address` from the peer
+//! address and `is_encrypted_channel` from what the listener's [`PeerInfo`]
+//! reports. Plain TCP through [`serve`] is unencrypted; a TLS listener
+//! should implement `Connected` for `PeerInfo` with `encrypted: true` and,
+//! for mTLS, the verified client identity.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::connect_info::Connected;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::serve::IncomingStream;
+use axum::{Json, Router};
+use gatehouse::{AccessDecision, AccessEvaluation, Policy, PolicyEvalResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use crate::audit::PolicyTrailEntry;
+use crate::clock::{Clock, SystemClock};
+use crate::guards::ControlProposal;
+use crate::pipeline::{VerificationContext, VerifierPipeline, VerifierVerdict};
+use crate::policy::{GovernanceCore, RbacPolicy};
+use crate::reason::{ReasonCode, VerdictReason};
+use crate::request::RequestContext;
+use crate::types::{Action, EnvironmentCtx, Resource, ResourceType, User};
+
+/// Header carrying a request's id, in both directions.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Body of `POST /authorize`. The principal and environment are never
+/// part of it; see the module docs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuthorizeRequest {
+    pub resource: Resource,
+    pub action: Action,
+}
+
+/// Body of every `POST /authorize` decision, granted or not.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuthorizeResponse {
+    /// Id of the request this decision answers, as in its
+    /// [`REQUEST_ID_HEADER`].
+    pub request_id: Uuid,
+    pub granted: bool,
+    /// Every policy consulted, in evaluation order.
+    pub policy_trail: Vec<PolicyTrailEntry>,
+}
+
+impl AuthorizeResponse {
+    pub fn new(request: &RequestContext, eval: &AccessEvaluation) -> Self {
+        Self {
+            request_id: request.request_id,
+            granted: matches!(eval.decision, AccessDecision::Granted),
+            policy_trail: eval.results.iter().map(trail_entry).collect(),
+        }
+    }
+}
+
+fn trail_entry(result: &PolicyEvalResult) -> PolicyTrailEntry {
+    match result {
+        PolicyEvalResult::Granted {
+            policy_type,
+            reason,
+        } => PolicyTrailEntry {
+            policy: policy_type.clone(),
+            granted: true,
+            reason: reason.clone(),
+        },
+        PolicyEvalResult::Denied {
+            policy_type,
+            reason,
+        } => PolicyTrailEntry {
+            policy: policy_type.clone(),
+            granted: false,
+            reason: Some(reason.clone()),
+        },
+        #[allow(unreachable_patterns)]
+        other => PolicyTrailEntry {
+            policy: "unknown".into(),
+            granted: other.is_granted(),
+            reason: None,
+        },
+    }
+}
+
+/// Body of requests that never reached a decision.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiError {
+    pub error: String,
+}
+
+/// Connection facts the listener vouches for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub addr: SocketAddr,
+    pub encrypted: bool,
+    /// Client identity verified by an mTLS listener.
+    pub client_identity: Option<String>,
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerInfo {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        PeerInfo {
+            addr: *stream.remote_addr(),
+            encrypted: false,
+            client_identity: None,
+        }
+    }
+}
+
+/// Who a request's credentials prove the caller to be.
+pub trait Authenticator: Send + Sync {
+    /// The principal `headers` and `peer` authenticate, or `None`.
+    fn authenticate(&self, headers: &HeaderMap, peer: &PeerInfo) -> Option<User>;
+}
+
+/// `Authorization: Bearer` tokens issued out of band, each bound to one
+/// principal. Only SHA-256 digests of the tokens are kept.
+#[derive(Debug, Clone, Default)]
+pub struct BearerTokens {
+    principals: HashMap<[u8; 32], User>,
+}
+
+impl BearerTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind `token` to `user`, replacing any earlier binding.
+    pub fn insert(&mut self, token: &str, user: User) {
+        self.principals.insert(Sha256::digest(token).into(), user);
+    }
+
+    pub fn with(mut self, token: &str, user: User) -> Self {
+        self.insert(token, user);
+        self
+    }
+}
+
+impl Authenticator for BearerTokens {
+    fn authenticate(&self, headers: &HeaderMap, _peer: &PeerInfo) -> Option<User> {
+        let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+        let token = value.strip_prefix("Bearer ")?;
+        let digest: [u8; 32] = Sha256::digest(token).into();
+        self.principals.get(&digest).cloned()
+    }
+}
+
+/// Everything the routes share.
+#[derive(Clone)]
+pub struct ServerState {
+    core: Arc<GovernanceCore>,
+    auth: Arc<dyn Authenticator>,
+    clock: Arc<dyn Clock>,
+    pipeline: Arc<VerifierPipeline>,
+    verification: VerificationContext,
+}
+
+impl ServerState {
+    /// State over `core`, authenticating with `auth`, on the system clock,
+    /// verifying proposals with the structural stage only.
+    pub fn new(core: Arc<GovernanceCore>, auth: Arc<dyn Authenticator>) -> Self {
+        Self {
+            core,
+            auth,
+            clock: Arc::new(SystemClock),
+            pipeline: Arc::new(VerifierPipeline::new()),
+            verification: VerificationContext::default(),
+        }
+    }
+
+    /// Stamp `EnvironmentCtx::time_utc` from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Verify proposals with `pipeline` in `ctx`; the submitter is set per
+    /// request to the authenticated principal.
+    pub fn with_pipeline(
+        mut self,
+        pipeline: Arc<VerifierPipeline>,
+        ctx: VerificationContext,
+    ) -> Self {
+        self.pipeline = pipeline;
+        self.verification = ctx;
+        self
+    }
+}
+
+/// Routes over `state`. Must be served with `ConnectInfo<PeerInfo>`, as
+/// [`serve`] does.
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/authorize", post(authorize))
+        .route("/proposals", post(proposals))
+        .with_state(state)
+}
+
+/// Serve [`router`] on `listener` until the task is dropped.
+pub async fn serve(listener: TcpListener, state: ServerState) -> std::io::Result<()> {
+    let app = router(state).into_make_service_with_connect_info::<PeerInfo>();
+    axum::serve(listener, app).await
+}
+
+type Rejected = (StatusCode, Json<ApiError>);
+
+fn rejected(e: JsonRejection) -> Rejected {
+    (
+        e.status(),
+        Json(ApiError {
+            error: e.body_text(),
+        }),
+    )
+}
+
+/// Context for an inbound request received now, keeping a caller-assigned
+/// id when it is a UUID.
+fn request_context(headers: &HeaderMap, clock: &dyn Clock) -> RequestContext {
+    let received_at = clock.now();
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .map_or_else(
+            || RequestContext::new(received_at),
+            |id| RequestContext::with_id(id, received_at),
+        )
+}
+
+async fn authorize(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<PeerInfo>,
+    headers: HeaderMap,
+    body: Result<Json<AuthorizeRequest>, JsonRejection>,
+) -> Response {
+    let request = request_context(&headers, state.clock.as_ref());
+    let id_header = [(REQUEST_ID_HEADER, request.request_id.to_string())];
+    let Some(user) = state.auth.authenticate(&headers, &peer) else {
+        let error = ApiError {
+            error: "missing or unknown credentials".into(),
+        };
+        return (StatusCode::UNAUTHORIZED, id_header, Json(error)).into_response();
+    };
+    let req = match body {
+        Ok(Json(req)) => req,
+        Err(e) => {
+            let (status, error) = rejected(e);
+            return (status, id_header, error).into_response();
+        }
+    };
+    let env = EnvironmentCtx {
+        time_utc: request.received_at,
+        ip_address: peer.addr.ip().to_string(),
+        is_encrypted_channel: peer.encrypted,
+    };
+
+    let eval = state
+        .core
+        .authorize_request(&user, &req.resource, &req.action, &env, &request)
+        .await;
+    let status = match eval.decision {
+        AccessDecision::Granted => StatusCode::OK,
+        AccessDecision::Denied => StatusCode::FORBIDDEN,
+    };
+    let response = AuthorizeResponse::new(&request, &eval);
+    (status, id_header, Json(response)).into_response()
+}
+
+async fn proposals(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<PeerInfo>,
+    headers: HeaderMap,
+    body: Result<Json<ControlProposal>, JsonRejection>,
+) -> Response {
+    let request = request_context(&headers, state.clock.as_ref());
+    let id_header = [(REQUEST_ID_HEADER, request.request_id.to_string())];
+    let Some(user) = state.auth.authenticate(&headers, &peer) else {
+        let error = ApiError {
+            error: "missing or unknown credentials".into(),
+        };
+        return (StatusCode::UNAUTHORIZED, id_header, Json(error)).into_response();
+    };
+    let proposal = match body {
+        Ok(Json(proposal)) => proposal,
+        Err(e) => {
+            let (status, error) = rejected(e);
+            return (status, id_header, error).into_response();
+        }
+    };
+    let env = EnvironmentCtx {
+        time_utc: request.received_at,
+        ip_address: peer.addr.ip().to_string(),
+        is_encrypted_channel: peer.encrypted,
+    };
+
+    let resource = Resource {
+        resource_id: proposal.node_ids().collect::<Vec<_>>().join(","),
+        resource_type: ResourceType::ControlProposal,
+        properties: HashMap::new(),
+    };
+    let rbac = RbacPolicy
+        .evaluate_access(&user, &Action::ExecuteControlProposal, &resource, &env)
+        .await;
+    let verdict = if rbac.is_granted() {
+        let ctx = state.verification.clone().with_submitter(&user.user_id);
+        VerifierVerdict::from(state.pipeline.run(&proposal, &ctx).await.verdict)
+    } else {
+        VerifierVerdict::reject(VerdictReason::new(
+            ReasonCode::Unauthorized,
+            format!(
+                "{} role {:?} may not execute control proposals",
+                user.user_id, user.role
+            ),
+        ))
+    };
+    let status = if verdict.approved {
+        StatusCode::OK
+    } else {
+        StatusCode::FORBIDDEN
+    };
+    (status, id_header, Json(verdict)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::types::Role;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    const STAFF_TOKEN: &str = "staff-token";
+    const GUEST_TOKEN: &str = "guest-token";
+
+    fn user(id: &str, role: Role) -> User {
+        User {
+            user_id: id.into(),
+            role,
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Server whose clock reads `hour`:00 UTC on 2025-07-01.
+    async fn spawn_server_at(hour: u32) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tokens = BearerTokens::new()
+            .with(STAFF_TOKEN, user("ops@cyboair.org", Role::Staff))
+            .with(GUEST_TOKEN, user("visitor", Role::Guest));
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2025, 7, 1, hour, 0, 0).unwrap());
+        let state = ServerState::new(Arc::new(GovernanceCore::new()), Arc::new(tokens))
+            .with_clock(Arc::new(clock));
+        tokio::spawn(serve(listener, state));
+        addr
+    }
+
+    async fn spawn_server() -> SocketAddr {
+        spawn_server_at(16).await
+    }
+
+    async fn post_json(addr: SocketAddr, path: &str, body: &str) -> (u16, String) {
+        post_json_as(addr, path, Some(STAFF_TOKEN), body).await
+    }
+
+    /// One-shot HTTP/1.1 POST with `token` as the bearer credential;
+    /// returns the status code and body.
+    async fn post_json_as(
+        addr: SocketAddr,
+        path: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (u16, String) {
+        let auth = token
+            .map(|t| format!("Authorization: Bearer {t}\r\n"))
+            .unwrap_or_default();
+        let (status, _, body) = post_raw(addr, path, &auth, body).await;
+        (status, body)
+    }
+
+    /// One-shot HTTP/1.1 POST with `extra_headers` (each ending in CRLF);
+    /// returns the status code, lowercased response headers and body.
+    async fn post_raw(
+        addr: SocketAddr,
+        path: &str,
+        extra_headers: &str,
+        body: &str,
+    ) -> (u16, HashMap<String, String>, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+             {extra_headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        let status = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let headers = lines
+            .filter_map(|l| l.split_once(": "))
+            .map(|(k, v)| (k.to_ascii_lowercase(), v.to_string()))
+            .collect();
+        (status, headers, body.to_string())
+    }
+
+    fn authorize_body(action: &str) -> String {
+        json!({
+            "resource": {"resource_id": "node_07", "resource_type": "Node", "properties": {}},
+            "action": action,
+        })
+        .to_string()
+    }
+
+    /// A body that also claims a principal and environment of its own.
+    fn forged_body(action: &str) -> String {
+        json!({
+            "user": {"user_id": "chair@cyboair.org", "role": "Superchair", "attributes": {}},
+            "resource": {"resource_id": "node_07", "resource_type": "Node", "properties": {}},
+            "action": action,
+            "env": {
+                "time_utc": "2025-07-01T10:00:00Z",
+                "ip_address": "203.0.113.9",
+                "is_encrypted_channel": true
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn authorize_grants_read_and_denies_write_over_plain_tcp() {
+        let addr = spawn_server().await;
+
+        let (status, body) = post_json(addr, "/authorize", &authorize_body("Read")).await;
+        assert_eq!(status, 200, "{body}");
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        assert!(response.granted);
+        assert_eq!(response.policy_trail.len(), 4);
+
+        let (status, body) = post_json(addr, "/authorize", &authorize_body("Write")).await;
+        assert_eq!(status, 403, "{body}");
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        assert!(!response.granted);
+        let abac = &response.policy_trail[1];
+        assert_eq!(abac.policy, "AbacPolicy");
+        assert!(!abac.granted);
+        assert_eq!(
+            abac.reason.as_deref(),
+            Some("unencrypted channel not allowed for privileged actions")
+        );
+    }
+
+    #[tokio::test]
+    async fn principal_and_time_come_from_the_server_not_the_body() {
+        // 22:00 on the server clock, outside Export's default business hours.
+        let addr = spawn_server_at(22).await;
+
+        let (status, body) = post_json(addr, "/authorize", &forged_body("Export")).await;
+        assert_eq!(status, 403, "{body}");
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        let window = response
+            .policy_trail
+            .iter()
+            .find(|e| e.policy == "TimeWindowPolicy")
+            .unwrap();
+        assert_eq!(
+            window.reason.as_deref(),
+            Some("22:00 local violates business_hours")
+        );
+
+        // A guest claiming Superchair is still a guest.
+        let (status, body) =
+            post_json_as(addr, "/authorize", Some(GUEST_TOKEN), &forged_body("Write")).await;
+        assert_eq!(status, 403, "{body}");
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.policy_trail[0].policy, "RbacPolicy");
+        assert!(!response.policy_trail[0].granted);
+
+        for token in [None, Some("made-up")] {
+            let (status, body) =
+                post_json_as(addr, "/authorize", token, &authorize_body("Read")).await;
+            assert_eq!(status, 401, "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn every_authorize_response_names_its_request() {
+        let addr = spawn_server().await;
+        let auth = format!("Authorization: Bearer {STAFF_TOKEN}\r\n");
+
+        let (status, headers, body) =
+            post_raw(addr, "/authorize", &auth, &authorize_body("Read")).await;
+        assert_eq!(status, 200, "{body}");
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(headers[REQUEST_ID_HEADER], response.request_id.to_string());
+        let (_, _, again) = post_raw(addr, "/authorize", &auth, &authorize_body("Read")).await;
+        let again: AuthorizeResponse = serde_json::from_str(&again).unwrap();
+        assert_ne!(again.request_id, response.request_id);
+
+        // A caller's UUID is kept; anything else is replaced.
+        let id = Uuid::from_u128(0x2110);
+        let (_, headers, body) = post_raw(
+            addr,
+            "/authorize",
+            &format!("{auth}X-Request-Id: {id}\r\n"),
+            &authorize_body("Write"),
+        )
+        .await;
+        let response: AuthorizeResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.request_id, id);
+        assert_eq!(headers[REQUEST_ID_HEADER], id.to_string());
+        let (_, headers, _) = post_raw(
+            addr,
+            "/authorize",
+            &format!("{auth}X-Request-Id: not-a-uuid\r\n"),
+            &authorize_body("Read"),
+        )
+        .await;
+        assert!(Uuid::parse_str(&headers[REQUEST_ID_HEADER]).is_ok());
+
+        // Requests that never reach a decision are still identified.
+        let (status, headers, _) = post_raw(addr, "/authorize", "", &authorize_body("Read")).await;
+        assert_eq!(status, 401);
+        assert!(headers.contains_key(REQUEST_ID_HEADER));
+    }
+
+    #[tokio::test]
+    async fn proposals_return_the_verdict() {
+        let addr = spawn_server().await;
+
+        let ok = json!({"node_id": "node_07", "new_duty_cycle": 0.4, "horizon_seconds": 300});
+        let (status, body) = post_json(addr, "/proposals", &ok.to_string()).await;
+        assert_eq!(status, 200, "{body}");
+        let verdict: VerifierVerdict = serde_json::from_str(&body).unwrap();
+        assert!(verdict.approved);
+
+        let bad = json!({"node_id": "node_07", "new_duty_cycle": 1.5, "horizon_seconds": 300});
+        let (status, body) = post_json(addr, "/proposals", &bad.to_string()).await;
+        assert_eq!(status, 403, "{body}");
+        let verdict: VerifierVerdict = serde_json::from_str(&body).unwrap();
+        assert!(!verdict.approved);
+        assert_eq!(verdict.reasons[0].node_id.as_deref(), Some("node_07"));
+    }
+
+    #[tokio::test]
+    async fn proposals_need_a_principal_allowed_to_execute_them() {
+        let addr = spawn_server().await;
+        let ok = json!({"node_id": "node_07", "new_duty_cycle": 0.4, "horizon_seconds": 300});
+
+        let (status, body) =
+            post_json_as(addr, "/proposals", Some(GUEST_TOKEN), &ok.to_string()).await;
+        assert_eq!(status, 403, "{body}");
+        let verdict: VerifierVerdict = serde_json::from_str(&body).unwrap();
+        assert!(!verdict.approved);
+        assert!(verdict.has_code(ReasonCode::Unauthorized), "{verdict}");
+
+        for token in [None, Some("made-up")] {
+            let (status, body) = post_json_as(addr, "/proposals", token, &ok.to_string()).await;
+            assert_eq!(status, 401, "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_are_structured_errors() {
+        let addr = spawn_server().await;
+
+        let (status, body) = post_json(addr, "/authorize", r#"{"user":"#).await;
+        assert_eq!(status, 400, "{body}");
+        let error: ApiError = serde_json::from_str(&body).unwrap();
+        assert!(!error.error.is_empty());
+
+        let (status, body) = post_json(addr, "/proposals", r#"{"node_id":"node_07"}"#).await;
+        assert_eq!(status, 422, "{body}");
+        let error: ApiError = serde_json::from_str(&body).unwrap();
+        assert!(error.error.contains("new_duty_cycle"), "{}", error.error);
+    }
+}
@@ -0,0 +1,327 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use cybo_corridor_core::aln::SignatureVerifier;
+use ed25519_dalek::{Signature, Verifier as _, VerifyingKey};
+use gatehouse::Policy;
+use serde::{Deserialize, Serialize};
+
+use crate::guards::ControlProposal;
+use crate::pipeline::{Verifier, VerifierVerdict};
+use crate::policy::RbacPolicy;
+use crate::reason::{ReasonCode, VerdictReason};
+use crate::types::{Action, EnvironmentCtx, Resource, ResourceType, Role, User};
+
+/// A control proposal as it arrives at the governance boundary: raw JSON
+/// bytes plus the signer's per-signer sequence number and Ed25519 signature
+/// over `SignedProposal::signing_message`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SignedProposal {
+    pub proposal_bytes: Vec<u8>,
+    pub signer_id: String,
+    /// Must strictly increase per signer; replays are rejected.
+    pub sequence: u64,
+    pub signature: Vec<u8>,
+}
+
+impl SignedProposal {
+    /// Bytes covered by the signature: signer id, sequence and payload, so
+    /// none of them can be swapped without invalidating it.
+    pub fn signing_message(signer_id: &str, sequence: u64, proposal_bytes: &[u8]) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(signer_id.len() + 9 + proposal_bytes.len());
+        msg.extend_from_slice(signer_id.as_bytes());
+        msg.push(0);
+        msg.extend_from_slice(&sequence.to_be_bytes());
+        msg.extend_from_slice(proposal_bytes);
+        msg
+    }
+}
+
+/// Public key and governance role for a registered signer.
+#[derive(Debug, Clone)]
+pub struct RegisteredSigner {
+    pub key: VerifyingKey,
+    pub role: Role,
+}
+
+/// Known signers plus the last accepted sequence number of each.
+#[derive(Debug, Default)]
+pub struct KeyRegistry {
+    signers: HashMap<String, RegisteredSigner>,
+    last_sequence: Mutex<HashMap<String, u64>>,
+}
+
+impl KeyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, signer_id: &str, key: VerifyingKey, role: Role) {
+        self.signers
+            .insert(signer_id.to_string(), RegisteredSigner { key, role });
+    }
+
+    pub fn signer(&self, signer_id: &str) -> Option<&RegisteredSigner> {
+        self.signers.get(signer_id)
+    }
+
+    /// Accept `sequence` if it is newer than the last one seen for the signer.
+    fn advance_sequence(&self, signer_id: &str, sequence: u64) -> Result<(), String> {
+        let mut last = self.last_sequence.lock().unwrap_or_else(|e| e.into_inner());
+        match last.get(signer_id) {
+            Some(&prev) if sequence <= prev => Err(format!(
+                "replayed or stale sequence {sequence} for signer {signer_id} (last {prev})"
+            )),
+            _ => {
+                last.insert(signer_id.to_string(), sequence);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Signed ALN tables set ceilings, risk weights and RoH parameters, so they
+/// are accepted only from registered `Superchair` signers, under
+/// `alg = "ed25519"`. Keys registered to submit proposals cannot sign them.
+impl SignatureVerifier for KeyRegistry {
+    fn verify(&self, signer: &str, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+        let (Some(signer), Ok(signature)) = (self.signer(signer), Signature::from_slice(signature))
+        else {
+            return false;
+        };
+        alg == "ed25519"
+            && matches!(signer.role, Role::Superchair)
+            && signer.key.verify(message, &signature).is_ok()
+    }
+}
+
+fn reject(code: ReasonCode, message: String) -> VerifierVerdict {
+    VerifierVerdict::reject(VerdictReason::new(code, message))
+}
+
+/// Signed entry point to the pipeline: provenance first, then RBAC for the
+/// signer's role, then the usual structural and governance checks.
+pub async fn verify_signed(
+    registry: &KeyRegistry,
+    signed: &SignedProposal,
+    env: &EnvironmentCtx,
+) -> VerifierVerdict {
+    let Some(signer) = registry.signer(&signed.signer_id) else {
+        return reject(
+            ReasonCode::UnknownSigner,
+            format!("unknown signer {}", signed.signer_id),
+        );
+    };
+
+    let signature = match Signature::from_slice(&signed.signature) {
+        Ok(sig) => sig,
+        Err(_) => return reject(ReasonCode::BadSignature, "malformed signature".into()),
+    };
+    let msg =
+        SignedProposal::signing_message(&signed.signer_id, signed.sequence, &signed.proposal_bytes);
+    if signer.key.verify(&msg, &signature).is_err() {
+        return reject(
+            ReasonCode::BadSignature,
+            format!("bad signature from signer {}", signed.signer_id),
+        );
+    }
+
+    if let Err(e) = registry.advance_sequence(&signed.signer_id, signed.sequence) {
+        return reject(ReasonCode::Replay, e);
+    }
+
+    let proposal: ControlProposal = match serde_json::from_slice(&signed.proposal_bytes) {
+        Ok(p) => p,
+        Err(e) => {
+            return reject(
+                ReasonCode::InvalidProposal,
+                format!("invalid proposal: {e}"),
+            )
+        }
+    };
+
+    let user = User {
+        user_id: signed.signer_id.clone(),
+        role: signer.role.clone(),
+        attributes: HashMap::new(),
+    };
+    let resource = Resource {
+        resource_id: proposal.node_ids().collect::<Vec<_>>().join(","),
+        resource_type: ResourceType::ControlProposal,
+        properties: HashMap::new(),
+    };
+    let rbac = RbacPolicy
+        .evaluate_access(&user, &Action::ExecuteControlProposal, &resource, env)
+        .await;
+    if !rbac.is_granted() {
+        return reject(
+            ReasonCode::Unauthorized,
+            format!(
+                "signer {} role {:?} may not execute control proposals",
+                signed.signer_id, signer.role
+            ),
+        );
+    }
+
+    Verifier::verify(&proposal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn env() -> EnvironmentCtx {
+        EnvironmentCtx {
+            time_utc: Utc::now(),
+            ip_address: "10.0.0.5".into(),
+            is_encrypted_channel: true,
+        }
+    }
+
+    fn ops_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn registry() -> KeyRegistry {
+        let mut reg = KeyRegistry::new();
+        reg.register("ops@cyboair.org", ops_key().verifying_key(), Role::Staff);
+        let guest = SigningKey::from_bytes(&[9u8; 32]);
+        reg.register("guest", guest.verifying_key(), Role::Guest);
+        reg
+    }
+
+    fn sign(key: &SigningKey, signer_id: &str, sequence: u64, payload: &str) -> SignedProposal {
+        let bytes = payload.as_bytes().to_vec();
+        let msg = SignedProposal::signing_message(signer_id, sequence, &bytes);
+        SignedProposal {
+            proposal_bytes: bytes,
+            signer_id: signer_id.into(),
+            sequence,
+            signature: key.sign(&msg).to_bytes().to_vec(),
+        }
+    }
+
+    const PAYLOAD: &str =
+        r#"{"node_id":"CYB-AIR-CANOPY-01","new_duty_cycle":0.4,"horizon_seconds":300}"#;
+
+    #[tokio::test]
+    async fn valid_signature_reaches_verifier() {
+        let reg = registry();
+        let verdict = verify_signed(
+            &reg,
+            &sign(&ops_key(), "ops@cyboair.org", 1, PAYLOAD),
+            &env(),
+        )
+        .await;
+        assert!(verdict.approved, "{verdict}");
+    }
+
+    #[tokio::test]
+    async fn tampered_payload_is_rejected() {
+        let reg = registry();
+        let mut signed = sign(&ops_key(), "ops@cyboair.org", 1, PAYLOAD);
+        signed.proposal_bytes = PAYLOAD.replace("0.4", "1.0").into_bytes();
+        let verdict = verify_signed(&reg, &signed, &env()).await;
+        assert!(!verdict.approved);
+        assert!(verdict.to_string().contains("bad signature"), "{}", verdict);
+    }
+
+    #[tokio::test]
+    async fn unknown_signer_is_rejected() {
+        let reg = registry();
+        let signed = sign(&ops_key(), "mallory", 1, PAYLOAD);
+        let verdict = verify_signed(&reg, &signed, &env()).await;
+        assert!(!verdict.approved);
+        assert!(verdict.to_string().contains("unknown signer mallory"));
+        assert!(verdict.has_code(ReasonCode::UnknownSigner));
+    }
+
+    #[tokio::test]
+    async fn replayed_sequence_is_rejected() {
+        let reg = registry();
+        let signed = sign(&ops_key(), "ops@cyboair.org", 5, PAYLOAD);
+        assert!(verify_signed(&reg, &signed, &env()).await.approved);
+
+        let verdict = verify_signed(&reg, &signed, &env()).await;
+        assert!(!verdict.approved);
+        assert!(verdict.to_string().contains("replayed"), "{verdict}");
+        assert!(verdict.has_code(ReasonCode::Replay));
+
+        let older = sign(&ops_key(), "ops@cyboair.org", 4, PAYLOAD);
+        assert!(!verify_signed(&reg, &older, &env()).await.approved);
+        let newer = sign(&ops_key(), "ops@cyboair.org", 6, PAYLOAD);
+        assert!(verify_signed(&reg, &newer, &env()).await.approved);
+    }
+
+    #[tokio::test]
+    async fn signer_role_must_pass_rbac() {
+        let reg = registry();
+        let guest = SigningKey::from_bytes(&[9u8; 32]);
+        let verdict = verify_signed(&reg, &sign(&guest, "guest", 1, PAYLOAD), &env()).await;
+        assert!(!verdict.approved);
+        assert!(
+            verdict.to_string().contains("may not execute"),
+            "{}",
+            verdict
+        );
+    }
+
+    const ROH_TABLE: &str = include_str!("../fixtures/BeeNeuralCorridorPhoenix.rohmodel.aln");
+    const ROH_TABLE_TAMPERED: &str =
+        include_str!("../fixtures/BeeNeuralCorridorPhoenix.rohmodel.tampered.aln");
+
+    /// The fixture tables' signer, registered with `role`.
+    fn table_registry(role: Role) -> KeyRegistry {
+        let mut reg = KeyRegistry::new();
+        reg.register("ops@cyboair.org", ops_key().verifying_key(), role);
+        reg
+    }
+
+    #[test]
+    fn signed_roh_tables_load_only_with_a_valid_signature() {
+        use crate::roh::{RohModel, Stressor};
+        use cybo_corridor_core::aln::AlnError;
+
+        let model =
+            RohModel::from_signed_aln(ROH_TABLE, &table_registry(Role::Superchair)).unwrap();
+        let names: Vec<_> = model.stressors.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["pm25", "o3", "noise", "heat"]);
+        assert_eq!(
+            model.stressors[2],
+            Stressor {
+                name: "noise".into(),
+                weight: 0.2,
+                default: 0.1,
+            }
+        );
+
+        assert_eq!(
+            RohModel::from_signed_aln(ROH_TABLE_TAMPERED, &table_registry(Role::Superchair)),
+            Err(AlnError::SignatureMismatch {
+                signer: "ops@cyboair.org".into()
+            })
+        );
+        // A signer the registry does not know is a mismatch too.
+        assert!(RohModel::from_signed_aln(ROH_TABLE, &KeyRegistry::new()).is_err());
+    }
+
+    #[test]
+    fn only_superchair_signers_may_sign_tables() {
+        use crate::roh::RohModel;
+        use cybo_corridor_core::aln::AlnError;
+
+        for role in [Role::Guest, Role::Bot, Role::Staff] {
+            assert_eq!(
+                RohModel::from_signed_aln(ROH_TABLE, &table_registry(role)),
+                Err(AlnError::SignatureMismatch {
+                    signer: "ops@cyboair.org".into()
+                })
+            );
+        }
+    }
+}
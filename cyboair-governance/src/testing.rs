@@ -0,0 +1,88 @@
+//! Proptest strategies for control proposals.
+//!
+//! `any_*` strategies cover the whole value space: empty node ids, duty
+//! outside [0, 1] or NaN, zero horizons, duplicates and oversized
+//! proposals. `valid_*` strategies only produce what `InputGuard` accepts.
+
+use proptest::collection::{hash_set, vec};
+use proptest::num::f64::ANY as ANY_F64;
+use proptest::prelude::*;
+
+use crate::guards::{ControlProposal, NodeDirective, RampShape, MAX_DIRECTIVES};
+
+fn any_ramp_shape() -> impl Strategy<Value = Option<RampShape>> {
+    proptest::option::of(prop_oneof![
+        Just(RampShape::Step),
+        Just(RampShape::Linear),
+        Just(RampShape::SCurve),
+        (1.0e-3..1.0e6f64).prop_map(|tau_s| RampShape::ExpApproach { tau_s }),
+    ])
+}
+
+fn directive(
+    node_id: impl Strategy<Value = String>,
+    duty: impl Strategy<Value = f64>,
+    horizon: impl Strategy<Value = u64>,
+) -> impl Strategy<Value = NodeDirective> {
+    (node_id, duty, horizon, any_ramp_shape()).prop_map(
+        |(node_id, new_duty_cycle, horizon_seconds, ramp_shape)| NodeDirective {
+            node_id,
+            new_duty_cycle,
+            horizon_seconds,
+            ramp_shape,
+        },
+    )
+}
+
+/// Any directive, including ones `InputGuard` refuses.
+pub fn any_node_directive() -> impl Strategy<Value = NodeDirective> {
+    directive(any::<String>(), ANY_F64, any::<u64>())
+}
+
+/// A directive with a non-empty node id, duty in [0, 1] and a positive
+/// horizon of at most a day.
+pub fn valid_node_directive() -> impl Strategy<Value = NodeDirective> {
+    directive("CYB-AIR-[A-Z0-9-]{1,16}", 0.0..=1.0f64, 1..=86_400u64)
+}
+
+/// Any proposal, up to twice `MAX_DIRECTIVES` long.
+pub fn any_control_proposal() -> impl Strategy<Value = ControlProposal> {
+    vec(any_node_directive(), 0..=2 * MAX_DIRECTIVES)
+        .prop_map(|directives| ControlProposal { directives })
+}
+
+/// A proposal `InputGuard::validate_control_proposal` accepts: one to
+/// `MAX_DIRECTIVES` valid directives, one per node.
+pub fn valid_control_proposal() -> impl Strategy<Value = ControlProposal> {
+    hash_set("CYB-AIR-[A-Z0-9-]{1,16}", 1..=MAX_DIRECTIVES)
+        .prop_flat_map(|ids| {
+            ids.into_iter()
+                .map(|id| directive(Just(id), 0.0..=1.0f64, 1..=86_400u64))
+                .collect::<Vec<_>>()
+        })
+        .prop_map(|directives| ControlProposal { directives })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guards::InputGuard;
+
+    proptest! {
+        #[test]
+        fn valid_proposals_pass_the_input_guard(p in valid_control_proposal()) {
+            prop_assert_eq!(InputGuard::validate_control_proposal(&p), Ok(()));
+        }
+
+        #[test]
+        fn any_proposal_is_refused_or_in_range(p in any_control_proposal()) {
+            if InputGuard::validate_control_proposal(&p).is_ok() {
+                prop_assert!(p.directives.len() <= MAX_DIRECTIVES);
+                for d in &p.directives {
+                    prop_assert!((0.0..=1.0).contains(&d.new_duty_cycle));
+                    prop_assert!(d.horizon_seconds > 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! JS bindings for client-side verification (feature `wasm`).
+//!
+//! Dashboards call [`verify_proposal_json`] for instant feedback before
+//! submitting. Only the synchronous [`Verifier::verify`] checks run here:
+//! CEIM and pipeline stages need shard rows and an async executor, so the
+//! server's verdict remains the authoritative one.
+//!
+//! Headless tests: `wasm-pack test --node --features wasm`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Verifier;
+
+/// Verify a JSON-encoded `Proposal`, returning the `Verdict` as a plain JS
+/// object (`{ approved, state, reasons }`). Malformed JSON comes back as a
+/// rejection with an `InvalidProposal` reason rather than a thrown error.
+#[wasm_bindgen]
+pub fn verify_proposal_json(json: &str) -> JsValue {
+    Verifier::verify_json(json)
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .expect_throw("verdict serializes to a JS object")
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn verdict(json: &str) -> Value {
+        serde_wasm_bindgen::from_value(verify_proposal_json(json)).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn approves_in_range_proposal() {
+        let v = verdict(
+            r#"{"directives":[
+                {"node_id":"node_01","new_duty_cycle":0.4,"horizon_seconds":300},
+                {"node_id":"node_02","new_duty_cycle":0.9,"horizon_seconds":60,"ramp_shape":"s_curve"}
+            ]}"#,
+        );
+        assert_eq!(v["approved"], true);
+        assert_eq!(v["state"], "Approved");
+        assert_eq!(v["reasons"][0]["code"], "Passed");
+    }
+
+    #[wasm_bindgen_test]
+    fn rejects_out_of_range_duty() {
+        let v = verdict(r#"{"node_id":"node_01","new_duty_cycle":1.5,"horizon_seconds":300}"#);
+        assert_eq!(v["approved"], false);
+        assert_eq!(v["state"], "Rejected");
+        let reason = &v["reasons"][0];
+        assert_eq!(reason["code"], "DutyOutOfRange");
+        assert_eq!(reason["node_id"], "node_01");
+        assert_eq!(reason["observed"], 1.5);
+    }
+}
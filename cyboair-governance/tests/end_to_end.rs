@@ -0,0 +1,671 @@
+//! End-to-end scenarios across governance, the corridor controller, the
+//! bee kernel and escalation, built from the crates' public APIs only.
+//!
+//! Every scenario starts from the fixtures in `tests/fixtures`: the Phoenix
+//! shard, its corridor bundle and the site's bee-rights polytope. A signed
+//! proposal is checked at the boundary, authorized, run through the
+//! verifier pipeline and dry-run against the shard; approved duties are
+//! then ramped in over 5-minute controller steps, with the bee kernel as
+//! the controller's bee guard and an escalation bridge watching each step.
+//! Governance decisions and verdicts go onto one audit chain. Time only
+//! moves through a `ManualClock`, so every run is the same.
+//!
+//! Built with the `ed25519-dalek` and `bee-kernel` features, against
+//! `cybo-corridor-core` with `corridor-safety`.
+
+#![cfg(all(feature = "ed25519-dalek", feature = "bee-kernel"))]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use cybo_corridor_core::escalation::{
+    apply_mode_actions, run_steps_escalating, EscalationBridge, EscalationConfig, NodeEscalation,
+};
+use cybo_corridor_core::{EscalationAction, EscalationTrigger};
+use cyboair_bee_karma::{BeeEnvSample, BeerightsPolytope};
+use cyboair_corridor_safety::{
+    compute_karma_bytes, compute_mass_kg, loader, verify_chain, BandHistory, ChainAppender,
+    CorridorBundle, CorridorController, CorridorRow, DutyRamp, EcoBand, EventChain, MapAltitude,
+    NodeState, OperationalMode, RectSafetyEnvelope, SimpleDwCeiling, SimpleHostBudget, StepInput,
+    ThresholdEcoBand,
+};
+use ed25519_dalek::{Signer, SigningKey};
+use gatehouse::AccessDecision;
+
+use cyboair_governance::audit::{AuditDecision, AuditEntry, AuditKind};
+use cyboair_governance::bee_kernel::BeeKernel;
+use cyboair_governance::chain::{ChainAuditSink, VerdictEvent, AUDIT, VERDICT};
+use cyboair_governance::clock::{Clock, ManualClock};
+use cyboair_governance::dry_run::{ProposalDiff, ShardNode};
+use cyboair_governance::guards::{NodeDirective, RampShape};
+use cyboair_governance::pipeline::{
+    CeimStage, PipelineReport, VerificationContext, VerifierPipeline, VerifierVerdict,
+    DEFAULT_REPAIR_ITERATIONS,
+};
+use cyboair_governance::reason::{ReasonCode, VerdictReason};
+use cyboair_governance::signing::{verify_signed, KeyRegistry, SignedProposal};
+use cyboair_governance::types::{self, EnvironmentCtx};
+use cyboair_governance::{
+    Action, CorridorBudgets, GovContext, GovernanceCore, Principal, Proposal, Resource, Role,
+    VerdictState, Verifier,
+};
+
+const SHARD: &str = include_str!("fixtures/phoenix_shard.csv");
+const BUNDLE: &str = include_str!("fixtures/phoenix_bundle.toml");
+const POLYTOPE: &str = include_str!("fixtures/bee_polytope.json");
+
+const CANOPY: &str = "CYB-AIR-CANOPY-01";
+const SCHOOL: &str = "CYB-AIR-SCHOOL-05";
+const GARDEN: &str = "CYB-AIR-GARDEN-07";
+
+const OPS: &str = "ops@cyboair.org";
+const BOT: &str = "corridor-bot";
+
+const TEMPERATURE_K: f64 = 310.0;
+/// The bundle's `host_budget.step_dt_s`.
+const STEP_S: i64 = 300;
+/// Ramp horizon of every proposal here: three controller steps.
+const HORIZON_S: u64 = 900;
+const STARTING_DUTY: f64 = 0.2;
+const RATED_POWER_W: f64 = 100.0;
+
+type Controller = CorridorController<
+    RectSafetyEnvelope<MapAltitude>,
+    SimpleHostBudget,
+    ThresholdEcoBand,
+    SimpleDwCeiling,
+    BeeKernel,
+>;
+
+/// Everything one submission produced on its way through governance.
+struct Submission {
+    boundary: VerifierVerdict,
+    authorized: bool,
+    report: PipelineReport,
+    diff: ProposalDiff,
+}
+
+impl Submission {
+    fn accepted(&self) -> bool {
+        self.boundary.approved && self.authorized && self.report.verdict.approved
+    }
+}
+
+/// One corridor, its governance and its controller, stepped by hand.
+struct Corridor {
+    clock: Arc<ManualClock>,
+    audit: Arc<ChainAuditSink<EventChain>>,
+    governance: GovernanceCore,
+    registry: KeyRegistry,
+    ops_key: SigningKey,
+    sequence: u64,
+    bundle: CorridorBundle,
+    rows: HashMap<String, CorridorRow>,
+    kernel: BeeKernel,
+    controller: Controller,
+    nodes: Vec<NodeState>,
+    ramps: HashMap<usize, (DutyRamp, f64)>,
+    bridge: EscalationBridge,
+    bands: BandHistory,
+    escalations: Vec<NodeEscalation>,
+}
+
+impl Corridor {
+    /// The fixtures loaded, every node at `STARTING_DUTY`, and the clock
+    /// at 2026-06-01 15:00 UTC. NO2 spikes on the school node escalate
+    /// after two consecutive steps.
+    fn new() -> Self {
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2026, 6, 1, 15, 0, 0).unwrap(),
+        ));
+        let audit = Arc::new(ChainAuditSink::new(EventChain::new()));
+        let governance = GovernanceCore::new_with_sink(audit.clone()).with_clock(clock.clone());
+
+        let ops_key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut registry = KeyRegistry::new();
+        registry.register(OPS, ops_key.verifying_key(), types::Role::Staff);
+
+        let bundle = CorridorBundle::from_toml(BUNDLE).unwrap();
+        let shard = loader::from_named_reader(SHARD.as_bytes(), "phoenix_shard.csv").unwrap();
+        let microspace: Vec<_> = shard
+            .iter()
+            .filter(|s| s.bee.as_ref().is_some_and(|b| b.bee_flag == 1))
+            .map(|s| s.row.machine_id.clone())
+            .collect();
+        let polytope = BeerightsPolytope::from_json_reader(POLYTOPE.as_bytes()).unwrap();
+        let kernel = BeeKernel::from_bundle(polytope, &bundle, microspace, &ambient()).unwrap();
+        let controller = bundle
+            .build(altitude())
+            .unwrap()
+            .with_bee_guard(kernel.clone());
+
+        let nodes = shard
+            .iter()
+            .map(|s| {
+                let mass_kg =
+                    compute_mass_kg(&s.row, s.row.pollutant_kind().unwrap(), TEMPERATURE_K)
+                        .unwrap();
+                NodeState {
+                    karma_bytes: compute_karma_bytes(&s.row, mass_kg),
+                    row: s.row.clone(),
+                    mass_kg,
+                    duty_cycle: STARTING_DUTY,
+                    power_w: RATED_POWER_W * STARTING_DUTY,
+                    geo_weight: 1.0,
+                    noise_db: None,
+                    emf_vpm: None,
+                    suspicion: None,
+                    mode: OperationalMode::Active,
+                }
+            })
+            .collect();
+
+        let bridge = EscalationBridge::new(EscalationConfig {
+            repeat_threshold: 2,
+            actions: vec![(
+                EscalationTrigger::UrbanNOxSpike,
+                vec![
+                    EscalationAction::EnterSensingOnly,
+                    EscalationAction::TriggerAudit,
+                    EscalationAction::DisableActuation,
+                ],
+            )],
+            default_actions: vec![EscalationAction::TriggerAudit],
+        });
+
+        Corridor {
+            clock,
+            audit,
+            governance,
+            registry,
+            ops_key,
+            sequence: 0,
+            rows: shard
+                .iter()
+                .map(|s| (s.row.machine_id.clone(), s.row.clone()))
+                .collect(),
+            bundle,
+            kernel,
+            controller,
+            nodes,
+            ramps: HashMap::new(),
+            bridge,
+            bands: BandHistory::new(64),
+            escalations: Vec::new(),
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.clock.now_unix_ms()
+    }
+
+    fn index(&self, node_id: &str) -> usize {
+        self.nodes
+            .iter()
+            .position(|n| n.row.machine_id == node_id)
+            .unwrap()
+    }
+
+    fn duty(&self, node_id: &str) -> f64 {
+        self.nodes[self.index(node_id)].duty_cycle
+    }
+
+    /// Sign `duties` as the ops signer and take the proposal through the
+    /// boundary, authorization, the pipeline and the dry run. Only an
+    /// accepted proposal starts its ramps.
+    async fn submit(&mut self, duties: &[(&str, f64)]) -> Submission {
+        let proposal = Proposal {
+            directives: duties
+                .iter()
+                .map(|&(node_id, duty)| NodeDirective {
+                    node_id: node_id.into(),
+                    new_duty_cycle: duty,
+                    horizon_seconds: HORIZON_S,
+                    ramp_shape: Some(RampShape::Linear),
+                })
+                .collect(),
+        };
+
+        self.sequence += 1;
+        let bytes = serde_json::to_vec(&proposal).unwrap();
+        let message = SignedProposal::signing_message(OPS, self.sequence, &bytes);
+        let signed = SignedProposal {
+            signature: self.ops_key.sign(&message).to_bytes().to_vec(),
+            proposal_bytes: bytes,
+            signer_id: OPS.into(),
+            sequence: self.sequence,
+        };
+        let env = EnvironmentCtx {
+            time_utc: self.clock.now(),
+            ip_address: "10.0.0.5".into(),
+            is_encrypted_channel: true,
+        };
+        let boundary = verify_signed(&self.registry, &signed, &env).await;
+
+        let ops = Principal {
+            id: OPS.into(),
+            role: Role::Staff,
+            attributes: vec![],
+        };
+        let resource = Resource {
+            resource_id: proposal.node_ids().collect::<Vec<_>>().join(","),
+            owner: None,
+            attributes: vec![("corridor".into(), self.bundle.corridor.clone())],
+        };
+        let authorization = self
+            .governance
+            .authorize(
+                &ops,
+                &Action::ProposeControl,
+                &resource,
+                &GovContext::default(),
+            )
+            .await;
+        let authorized = matches!(authorization.decision, AccessDecision::Granted);
+
+        let ctx = VerificationContext::default()
+            .with_shard(Arc::new(self.rows.clone()))
+            .with_budgets(CorridorBudgets {
+                max_mass_kg: 5.0e-4,
+                max_karma_nb: 1.0e6,
+                temperature_k: TEMPERATURE_K,
+                airflow: Default::default(),
+            })
+            .with_submitter(OPS);
+        let report = VerifierPipeline::new()
+            .add_stage(CeimStage)
+            .add_stage(self.kernel.clone())
+            .with_repair(DEFAULT_REPAIR_ITERATIONS)
+            .run(&proposal, &ctx)
+            .await;
+        let event = VerdictEvent {
+            proposal: &proposal,
+            verdict: &report.verdict,
+            warnings: &report.warnings,
+        };
+        self.audit
+            .with_chain(|chain| chain.append(VERDICT, &event))
+            .unwrap();
+
+        let shard_nodes = self
+            .nodes
+            .iter()
+            .map(|n| {
+                let node = ShardNode {
+                    row: self.rows[&n.row.machine_id].clone(),
+                    duty_cycle: n.duty_cycle,
+                    rated_power_w: RATED_POWER_W,
+                    geo_weight: n.geo_weight,
+                };
+                (n.row.machine_id.clone(), node)
+            })
+            .collect();
+        let diff = Verifier::dry_run(
+            &proposal,
+            &shard_nodes,
+            &self.bundle.controller,
+            altitude(),
+            TEMPERATURE_K,
+        )
+        .unwrap();
+
+        let submission = Submission {
+            boundary,
+            authorized,
+            report,
+            diff,
+        };
+        if submission.accepted() {
+            for d in &proposal.directives {
+                let i = self.index(&d.node_id);
+                let ramp = DutyRamp {
+                    from: self.nodes[i].duty_cycle,
+                    to: d.new_duty_cycle,
+                    horizon_s: d.horizon_seconds as f64,
+                    shape: d.ramp_shape.unwrap_or(RampShape::Step),
+                };
+                self.ramps.insert(i, (ramp, 0.0));
+            }
+        }
+        submission
+    }
+
+    /// A new reading on `node_id`: its concentrations, mass and karma.
+    fn reading(&mut self, node_id: &str, cin: f64, cout: f64) {
+        let i = self.index(node_id);
+        let node = &mut self.nodes[i];
+        node.row.cin = cin;
+        node.row.cout = cout;
+        node.mass_kg =
+            compute_mass_kg(&node.row, node.row.pollutant_kind().unwrap(), TEMPERATURE_K).unwrap();
+        node.karma_bytes = compute_karma_bytes(&node.row, node.mass_kg);
+    }
+
+    /// Advance the clock and ramps by one step, run one controller step
+    /// under escalation, and carry out the escalation actions the
+    /// corridor bot is authorized for.
+    async fn step(&mut self) -> EcoBand {
+        self.clock.advance(Duration::seconds(STEP_S));
+        let nodes = &mut self.nodes;
+        self.ramps.retain(|&i, (ramp, elapsed_s)| {
+            *elapsed_s += STEP_S as f64;
+            nodes[i].duty_cycle = ramp.duty_at(*elapsed_s);
+            !ramp.is_done(*elapsed_s)
+        });
+
+        let input = StepInput {
+            phi_dw: self.controller.dw_flux_density(&self.nodes).unwrap(),
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        };
+        let step = run_steps_escalating(
+            &self.controller,
+            &mut self.bridge,
+            &mut self.nodes,
+            1,
+            |_| input,
+        )
+        .unwrap()
+        .pop()
+        .unwrap();
+        let band = step.record.band;
+        self.bands.record(band, self.now_ms());
+
+        let bot = Principal {
+            id: BOT.into(),
+            role: Role::Bot,
+            attributes: vec![],
+        };
+        let mut authorized = Vec::new();
+        for escalation in step.escalations {
+            let node = Resource {
+                resource_id: escalation.machine_id.clone(),
+                owner: None,
+                attributes: vec![],
+            };
+            let mut granted = Vec::new();
+            for action in &escalation.actions {
+                let decision = self
+                    .governance
+                    .authorize_escalation(&bot, action, &node)
+                    .await
+                    .decision;
+                if matches!(decision, AccessDecision::Granted) {
+                    granted.push(action.clone());
+                }
+            }
+            self.escalations.push(escalation.clone());
+            authorized.push(NodeEscalation {
+                actions: granted,
+                ..escalation
+            });
+        }
+        let now_ms = self.now_ms();
+        apply_mode_actions(&self.controller, &mut self.nodes, &authorized, now_ms);
+        band
+    }
+
+    async fn run(&mut self, steps: usize) -> Vec<EcoBand> {
+        let mut bands = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            bands.push(self.step().await);
+        }
+        bands
+    }
+
+    /// The audit chain, verified, one line per entry.
+    fn trail(&self) -> Vec<String> {
+        let entries = self.audit.with_chain(|chain| chain.entries().to_vec());
+        verify_chain(&entries).unwrap();
+        entries
+            .iter()
+            .map(|e| match e.kind.as_str() {
+                AUDIT => {
+                    let entry: AuditEntry = serde_json::from_value(e.payload.clone()).unwrap();
+                    let decision = match entry.decision {
+                        AuditDecision::Granted => "granted",
+                        AuditDecision::Denied => "denied",
+                    };
+                    let kind = match entry.kind {
+                        AuditKind::Access { action } => format!("{action:?}"),
+                        AuditKind::Escalation { action } => format!("escalate {action:?}"),
+                        AuditKind::SuppressedEscalation { action } => {
+                            format!("suppress {action:?}")
+                        }
+                        other => format!("{other:?}"),
+                    };
+                    format!(
+                        "{} {kind} on {} {decision}",
+                        entry.principal_id, entry.resource_id
+                    )
+                }
+                VERDICT => {
+                    let approved = e.payload["verdict"]["approved"].as_bool().unwrap();
+                    format!("verdict {}", if approved { "approved" } else { "rejected" })
+                }
+                other => other.to_string(),
+            })
+            .collect()
+    }
+
+    /// Audit entry timestamps, in chain order.
+    fn audit_times_ms(&self) -> Vec<u64> {
+        self.audit.with_chain(|chain| {
+            chain
+                .entries()
+                .iter()
+                .filter(|e| e.kind == AUDIT)
+                .map(|e| e.payload["timestamp_unix_ms"].as_u64().unwrap())
+                .collect()
+        })
+    }
+}
+
+/// Air at the microspace nodes on the scenario afternoon; the kernel adds
+/// each node's distance from the hive.
+fn ambient() -> BeeEnvSample {
+    BeeEnvSample {
+        distance_from_hive_m: 0.0,
+        o3_ugm3: 40.0,
+        aqhi: 4.0,
+        pm25_ugm3: 12.0,
+        emf_vpm: 0.2,
+        pesticide_index: 0.1,
+        ambient_temp_c: 31.0,
+    }
+}
+
+fn altitude() -> MapAltitude {
+    MapAltitude::new().with_site("Phoenix-Intersection-A", 331.0)
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+}
+
+fn codes(reasons: &[VerdictReason]) -> Vec<(ReasonCode, Option<&str>)> {
+    reasons
+        .iter()
+        .map(|r| (r.code, r.node_id.as_deref()))
+        .collect()
+}
+
+#[tokio::test]
+async fn approved_proposal_ramps_in_and_holds() {
+    let mut corridor = Corridor::new();
+    let t0 = corridor.now_ms();
+
+    let submission = corridor.submit(&[(CANOPY, 0.6), (SCHOOL, 0.5)]).await;
+    assert!(submission.boundary.approved);
+    assert!(submission.authorized);
+    let verdict = &submission.report.verdict;
+    assert_eq!(verdict.state, VerdictState::Approved);
+    assert_eq!(codes(&verdict.reasons), [(ReasonCode::Passed, None)]);
+    assert!(verdict.suggestions.is_empty());
+    assert!(submission.diff.failed_checks.is_empty());
+    assert_eq!(submission.diff.after.band, Some(EcoBand::Green));
+
+    // Linear over three steps, then held.
+    let mut duties = Vec::new();
+    for _ in 0..4 {
+        assert_eq!(corridor.step().await, EcoBand::Green);
+        duties.push((corridor.duty(CANOPY), corridor.duty(SCHOOL)));
+    }
+    let expected = [
+        (0.2 + 0.4 / 3.0, 0.3),
+        (0.2 + 0.8 / 3.0, 0.4),
+        (0.6, 0.5),
+        (0.6, 0.5),
+    ];
+    for (&(canopy, school), (c, s)) in duties.iter().zip(expected) {
+        assert_close(canopy, c);
+        assert_close(school, s);
+    }
+    assert_close(corridor.duty(GARDEN), STARTING_DUTY);
+
+    assert!(corridor.escalations.is_empty());
+    let transitions: Vec<_> = corridor.bands.transitions().collect();
+    assert_eq!(transitions.len(), 1);
+    assert_eq!(transitions[0].to, EcoBand::Green);
+    assert_eq!(transitions[0].at_ms, t0 + 300_000);
+    assert_eq!(
+        corridor.trail(),
+        [
+            "ops@cyboair.org ProposeControl on CYB-AIR-CANOPY-01,CYB-AIR-SCHOOL-05 granted",
+            "verdict approved",
+        ]
+    );
+    assert_eq!(corridor.audit_times_ms(), [t0]);
+}
+
+#[tokio::test]
+async fn bee_veto_rejects_then_suggested_duty_is_applied() {
+    let mut corridor = Corridor::new();
+
+    let vetoed = corridor.submit(&[(CANOPY, 0.6), (GARDEN, 0.6)]).await;
+    assert!(vetoed.boundary.approved);
+    assert!(vetoed.authorized);
+    let verdict = &vetoed.report.verdict;
+    assert_eq!(verdict.state, VerdictState::Rejected);
+    assert_eq!(
+        codes(&verdict.reasons),
+        [
+            (ReasonCode::Passed, None),
+            (ReasonCode::BeeVeto, Some(GARDEN))
+        ]
+    );
+    let veto = &verdict.reasons[1];
+    assert_eq!(veto.observed, Some(0.6));
+    let limit = veto.limit.unwrap();
+    assert_close(limit, 0.3);
+    assert_eq!(verdict.suggestions.len(), 1);
+    assert_eq!(verdict.suggestions[0].node_id, GARDEN);
+    assert_close(verdict.suggestions[0].suggested_duty.unwrap(), limit);
+
+    // Nothing moves on a rejected proposal.
+    corridor.run(2).await;
+    assert_close(corridor.duty(CANOPY), STARTING_DUTY);
+    assert_close(corridor.duty(GARDEN), STARTING_DUTY);
+
+    let suggested = verdict.suggestions[0].suggested_duty.unwrap();
+    let resubmitted = corridor.submit(&[(CANOPY, 0.6), (GARDEN, suggested)]).await;
+    assert_eq!(resubmitted.report.verdict.state, VerdictState::Approved);
+    assert!(resubmitted.diff.failed_checks.is_empty());
+
+    let bands = corridor.run(4).await;
+    assert!(bands.iter().all(|&b| b == EcoBand::Green));
+    assert_close(corridor.duty(CANOPY), 0.6);
+    assert_close(corridor.duty(GARDEN), 0.3);
+    assert_close(corridor.duty(SCHOOL), STARTING_DUTY);
+    assert!(corridor.escalations.is_empty());
+    assert_eq!(
+        corridor.trail(),
+        [
+            "ops@cyboair.org ProposeControl on CYB-AIR-CANOPY-01,CYB-AIR-GARDEN-07 granted",
+            "verdict rejected",
+            "ops@cyboair.org ProposeControl on CYB-AIR-CANOPY-01,CYB-AIR-GARDEN-07 granted",
+            "verdict approved",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn no2_spike_escalates_within_the_bot_role() {
+    let mut corridor = Corridor::new();
+    let t0 = corridor.now_ms();
+
+    let submission = corridor.submit(&[(CANOPY, 0.6), (SCHOOL, 0.5)]).await;
+    assert!(submission.accepted());
+    corridor.run(3).await;
+
+    // The school's NO2 reading pushes the corridor's DW flux past the
+    // ceiling.
+    corridor.reading(SCHOOL, 120.0, 30.0);
+    let phi_dw = corridor
+        .controller
+        .dw_flux_density(&corridor.nodes)
+        .unwrap();
+    assert!(phi_dw > 1.0e-8, "{phi_dw}");
+
+    // The first breached step only sheds duty; the second escalates.
+    assert_eq!(corridor.step().await, EcoBand::Amber);
+    assert!(corridor.escalations.is_empty());
+    assert_close(corridor.duty(SCHOOL), 0.412);
+    assert_eq!(corridor.step().await, EcoBand::Amber);
+
+    assert_eq!(corridor.escalations.len(), 1);
+    let escalation = &corridor.escalations[0];
+    assert_eq!(escalation.machine_id, SCHOOL);
+    assert_eq!(escalation.trigger, EscalationTrigger::UrbanNOxSpike);
+    assert_eq!(escalation.consecutive, 2);
+    assert_eq!(
+        escalation.actions,
+        [
+            EscalationAction::EnterSensingOnly,
+            EscalationAction::TriggerAudit,
+            EscalationAction::DisableActuation,
+        ]
+    );
+
+    // Sensing-only was granted to the bot, disabling actuation was not.
+    let escalated_at = t0 + 5 * 300_000;
+    let school = &corridor.nodes[corridor.index(SCHOOL)];
+    assert_eq!(school.duty_cycle, 0.0);
+    assert!(matches!(
+        school.mode,
+        OperationalMode::SensingOnly { since_ms, .. } if since_ms == escalated_at
+    ));
+    // Everyone else keeps shedding under the breach.
+    assert_close(corridor.duty(CANOPY), 0.424);
+    assert_close(corridor.duty(GARDEN), 0.024);
+
+    let transitions: Vec<_> = corridor
+        .bands
+        .transitions()
+        .map(|t| (t.from, t.to, t.at_ms))
+        .collect();
+    assert_eq!(
+        transitions,
+        [
+            (None, EcoBand::Green, t0 + 300_000),
+            (Some(EcoBand::Green), EcoBand::Amber, t0 + 4 * 300_000),
+        ]
+    );
+    assert_eq!(
+        corridor.trail(),
+        [
+            "ops@cyboair.org ProposeControl on CYB-AIR-CANOPY-01,CYB-AIR-SCHOOL-05 granted",
+            "verdict approved",
+            "corridor-bot escalate EnterSensingOnly on CYB-AIR-SCHOOL-05 granted",
+            "corridor-bot escalate TriggerAudit on CYB-AIR-SCHOOL-05 granted",
+            "corridor-bot suppress DisableActuation on CYB-AIR-SCHOOL-05 denied",
+        ]
+    );
+    assert_eq!(
+        corridor.audit_times_ms(),
+        [t0, escalated_at, escalated_at, escalated_at]
+    );
+}
@@ -2,6 +2,8 @@ use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use cyboair_corridor_safety::units::{unit_to_kg_factor_at_pressure, UnitError, P_STANDARD_PA};
+
 #[derive(Debug, Clone)]
 struct CyboAirRow {
     machineid: String,
@@ -29,19 +31,6 @@ struct NodeState {
     c_power: f64,
 }
 
-/// Eq. 1: unit operator u(unit, T, MW) -> kg/m^3 per unit
-fn unit_to_kg_factor(unit: &str, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    match unit {
-        "ugm3" => 1e-9,
-        "mgm3" => 1e-6,
-        "ppb" => {
-            let r = 8.3145_f64;
-            molar_mass_kg_per_mol / (r * temperature_k) * 1e-9
-        }
-        _ => 0.0,
-    }
-}
-
 /// Parse one CSV line from CyboAirTenMachinesPhoenix2026v1.csv
 fn parse_csv_row(line: &str) -> Result<CyboAirRow, Box<dyn Error>> {
     // Simple CSV split; assumes no embedded commas in unquoted fields
@@ -88,6 +77,7 @@ fn compute_w_geo(location: &str) -> f64 {
 fn update_node(
     node: &mut NodeState,
     temperature_k: f64,
+    pressure_pa: f64,
     molar_mass_kg_per_mol: f64,
     m_ref: f64,
     k_ref: f64,
@@ -95,12 +85,13 @@ fn update_node(
     eta2: f64,
     eta3: f64,
     eta4: f64,
-) {
+) -> Result<(), UnitError> {
     let r = &node.row;
 
     // Eq. 1: mass removed M_i
     let delta_c = (r.cin - r.cout).max(0.0);
-    let alpha = unit_to_kg_factor(&r.unit, temperature_k, molar_mass_kg_per_mol);
+    let alpha =
+        unit_to_kg_factor_at_pressure(&r.unit, temperature_k, pressure_pa, molar_mass_kg_per_mol)?;
     let c_u = alpha * delta_c;
     node.mass_kg = c_u * r.airflow_m3_per_s * r.period_s;
 
@@ -129,6 +120,7 @@ fn update_node(
         u_next = 1.0;
     }
     node.duty_cycle = u_next;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -161,6 +153,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Phoenix-like defaults; for real deployment, use pollutant-specific MW_x
     let temperature_k = 310.0_f64;
+    let pressure_pa = P_STANDARD_PA; // Phoenix is nearer 97 kPa; use station pressure
     let molar_mass_kg_per_mol = 0.048_f64; // e.g., O3 / VOC surrogate
 
     // Reference scales from shard order-of-magnitude
@@ -178,6 +171,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         update_node(
             node,
             temperature_k,
+            pressure_pa,
             molar_mass_kg_per_mol,
             m_ref,
             k_ref,
@@ -185,7 +179,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             eta2,
             eta3,
             eta4,
-        );
+        )?;
     }
 
     // Governance-grade log of mass, Karma, and duty cycle
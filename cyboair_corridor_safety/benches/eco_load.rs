@@ -0,0 +1,76 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use cyboair_corridor_safety::{
+    compute_karma_bytes, compute_mass_kg, ConstAltitude, ControllerConfig, CorridorRow, NodeState,
+    OperationalMode, Pollutant,
+};
+
+const NODES: usize = 1_000_000;
+
+/// Scenario-study corridor: nanogram-scale masses with a few large ones.
+fn synthetic_nodes(count: usize) -> Vec<NodeState> {
+    let row = CorridorRow {
+        machine_id: "CYB-AIR-SYN".to_string(),
+        r#type: "UrbanNanoswarmCanopy".to_string(),
+        location: "Phoenix-Scenario".to_string(),
+        pollutant: "PM2.5".to_string(),
+        cin: 30.0,
+        cout: 18.0,
+        unit: "ugm3".to_string(),
+        airflow_m3_per_s: 1.0,
+        period_s: 3600.0,
+        lambda_hazard: 3.0,
+        beta_nb_per_kg: 5.0e8,
+        ecoimpact_score: 0.92,
+        trace_id: None,
+        shard_version: None,
+    };
+    let mass_kg = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+    let template = NodeState {
+        karma_bytes: compute_karma_bytes(&row, mass_kg),
+        row,
+        mass_kg,
+        duty_cycle: 0.5,
+        power_w: 50.0,
+        geo_weight: 0.8,
+        noise_db: None,
+        emf_vpm: None,
+        suspicion: None,
+        mode: OperationalMode::Active,
+    };
+    (0..count)
+        .map(|i| {
+            let mut n = template.clone();
+            n.mass_kg = if i % 1000 == 0 {
+                1.0
+            } else {
+                1e-9 * (1.0 + (i % 7) as f64 / 7.0)
+            };
+            n
+        })
+        .collect()
+}
+
+fn bench_eco_load(c: &mut Criterion) {
+    let controller = ControllerConfig::from_toml(include_str!("../config/phoenix.toml"))
+        .unwrap()
+        .build(ConstAltitude(331.0))
+        .unwrap();
+    let nodes = synthetic_nodes(NODES);
+
+    let mut group = c.benchmark_group("corridor_1m");
+    group.sample_size(20);
+    group.bench_function("eco_load", |b| {
+        b.iter(|| controller.eco_load(black_box(&nodes), 0.5, 0.5))
+    });
+    group.bench_function("eco_load_weighted", |b| {
+        b.iter(|| controller.eco_load_weighted(black_box(&nodes), 0.5, 0.5))
+    });
+    group.bench_function("eco_load_every_other", |b| {
+        b.iter(|| controller.eco_load(black_box(&nodes).iter().step_by(2), 0.5, 0.5))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_eco_load);
+criterion_main!(benches);
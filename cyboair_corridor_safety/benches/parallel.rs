@@ -0,0 +1,140 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use cyboair_corridor_safety::{
+    compute_karma_bytes, compute_mass_kg, ConstAltitude, CorridorController, CorridorRow, EcoBand,
+    EcoLoadMode, GainSchedule, NoBeeGuard, NoMetrics, NodeState, OperationalMode, Pollutant,
+    RectSafetyEnvelope, SimpleDwCeiling, SimpleHostBudget, ThresholdEcoBand, ViolationPolicy,
+};
+
+const NODES: usize = 50_000;
+
+fn corridor() -> CorridorController<
+    RectSafetyEnvelope<ConstAltitude>,
+    SimpleHostBudget,
+    ThresholdEcoBand,
+    SimpleDwCeiling,
+> {
+    CorridorController {
+        envelope: RectSafetyEnvelope {
+            u_min: 0.0,
+            u_max: 1.0,
+            z_min_m: 5.0,
+            z_max_m: 600.0,
+            ecoimpact_min: 0.7,
+            ecoimpact_max: 1.0,
+            noise_max_db: None,
+            emf_max_vpm: None,
+            altitude: ConstAltitude(331.0),
+        },
+        host_budget: SimpleHostBudget {
+            p_max_w: 150.0,
+            e_step_max_j: 1.0e5,
+            step_dt_s: 300.0,
+        },
+        eco_band: ThresholdEcoBand {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+            gain_green: 0.0,
+            gain_amber: 0.2,
+            gain_red: 0.5,
+        },
+        dw_ceiling: SimpleDwCeiling { phi_dw_max: 1.0e-6 },
+        corridor_area_m2: 10.0,
+        m_ref_kg: 1.0e-6,
+        k_ref_nb: 1.0e10,
+        eta_m: 0.1,
+        eta_k: 0.1,
+        eta_w: 0.2,
+        eta_b: 0.2,
+        eta_p: 0.05,
+        eta_dw: 0.1,
+        eta_noise: 0.0,
+        eta_emf: 0.0,
+        noise_ref_db: 0.0,
+        emf_ref_vpm: 0.0,
+        gain_schedule: GainSchedule::default(),
+        duty_schedule: None,
+        geo_weighting: None,
+        airflow: None,
+        agent_gate: None,
+        corridor: None,
+        eco_load_mode: EcoLoadMode::Unweighted,
+        slew_limit: None,
+        violation_policy: ViolationPolicy::Error,
+        stability: None,
+        bee_guard: NoBeeGuard,
+        metrics: NoMetrics,
+    }
+}
+
+fn synthetic_nodes(count: usize) -> Vec<NodeState> {
+    (0..count)
+        .map(|i| {
+            let row = CorridorRow {
+                machine_id: format!("CYB-AIR-SYN-{i:05}"),
+                r#type: "UrbanNanoswarmCanopy".to_string(),
+                location: "Phoenix-Scenario".to_string(),
+                pollutant: "PM2.5".to_string(),
+                cin: 30.0 + (i % 20) as f64,
+                cout: 18.0,
+                unit: "ugm3".to_string(),
+                airflow_m3_per_s: 1.0 + (i % 5) as f64,
+                period_s: 3600.0,
+                lambda_hazard: 3.0,
+                beta_nb_per_kg: 5.0e8,
+                ecoimpact_score: 0.92,
+                trace_id: None,
+                shard_version: None,
+            };
+            let mass_kg = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+            let karma_bytes = compute_karma_bytes(&row, mass_kg);
+            NodeState {
+                row,
+                mass_kg,
+                karma_bytes,
+                duty_cycle: (i % 97) as f64 / 97.0,
+                power_w: 20.0 + (i % 100) as f64,
+                geo_weight: (i % 11) as f64 / 10.0,
+                noise_db: None,
+                emf_vpm: None,
+                suspicion: None,
+                mode: OperationalMode::Active,
+            }
+        })
+        .collect()
+}
+
+fn bench_updates(c: &mut Criterion) {
+    let controller = corridor();
+    let nodes = synthetic_nodes(NODES);
+
+    let mut group = c.benchmark_group("corridor_50k");
+    group.bench_function("eco_load_serial", |b| {
+        b.iter(|| controller.eco_load(black_box(&nodes), 0.5, 0.5))
+    });
+    group.bench_function("eco_load_parallel", |b| {
+        b.iter(|| controller.eco_load_parallel(black_box(&nodes), 0.5, 0.5))
+    });
+    group.bench_function("update_serial", |b| {
+        b.iter_batched_ref(
+            || nodes.clone(),
+            |ns| {
+                ns.iter_mut()
+                    .map(|n| controller.update_node_duty(n, EcoBand::Amber, 5.0e-7))
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("update_parallel", |b| {
+        b.iter_batched_ref(
+            || nodes.clone(),
+            |ns| controller.update_nodes_parallel(ns, EcoBand::Amber, 5.0e-7),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_updates);
+criterion_main!(benches);
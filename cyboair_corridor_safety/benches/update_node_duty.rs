@@ -0,0 +1,103 @@
+//! One `update_node_duty` pass and one `eco_load` over 1k, 10k and 100k
+//! nodes, with the Phoenix configuration.
+//!
+//! ```text
+//! cargo bench --bench update_node_duty -- --save-baseline main
+//! cargo bench --bench update_node_duty -- --baseline main
+//! ```
+//!
+//! The second run reports the change against the first. Duty outputs must
+//! stay bit-identical across optimizations; `duty_law_outputs_are_bit_stable`
+//! in the library tests pins them.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cyboair_corridor_safety::{
+    compute_karma_bytes, compute_mass_kg, ConstAltitude, ControllerConfig, CorridorRow, EcoBand,
+    NodeState, OperationalMode, Pollutant,
+};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Corridor of canopy nodes with spread duties, powers and pollutants.
+fn synthetic_nodes(count: usize) -> Vec<NodeState> {
+    let row = CorridorRow {
+        machine_id: "CYB-AIR-SYN".to_string(),
+        r#type: "UrbanNanoswarmCanopy".to_string(),
+        location: "Phoenix-Scenario".to_string(),
+        pollutant: "PM2.5".to_string(),
+        cin: 30.0,
+        cout: 18.0,
+        unit: "ugm3".to_string(),
+        airflow_m3_per_s: 1.0,
+        period_s: 3600.0,
+        lambda_hazard: 3.0,
+        beta_nb_per_kg: 5.0e8,
+        ecoimpact_score: 0.92,
+        trace_id: None,
+        shard_version: None,
+    };
+    let mass_kg = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+    let template = NodeState {
+        karma_bytes: compute_karma_bytes(&row, mass_kg),
+        row,
+        mass_kg,
+        duty_cycle: 0.5,
+        power_w: 50.0,
+        geo_weight: 0.8,
+        noise_db: None,
+        emf_vpm: None,
+        suspicion: None,
+        mode: OperationalMode::Active,
+    };
+    (0..count)
+        .map(|i| {
+            let mut n = template.clone();
+            n.row.pollutant = ["PM2.5", "O3", "NO2", "PM10"][i % 4].to_string();
+            n.duty_cycle = (i % 100) as f64 / 100.0;
+            n.power_w = 20.0 + (i % 13) as f64 * 9.0;
+            n.mass_kg *= 1.0 + (i % 7) as f64 / 7.0;
+            n
+        })
+        .collect()
+}
+
+fn bench_update_node_duty(c: &mut Criterion) {
+    let controller = ControllerConfig::from_toml(include_str!("../config/phoenix.toml"))
+        .unwrap()
+        .build(ConstAltitude(331.0))
+        .unwrap();
+
+    let mut group = c.benchmark_group("update_node_duty");
+    for size in SIZES {
+        let nodes = synthetic_nodes(size);
+        let phi_dw = controller.dw_flux_density(&nodes).unwrap();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &nodes, |b, nodes| {
+            let mut nodes = nodes.clone();
+            b.iter(|| {
+                for node in nodes.iter_mut() {
+                    let _ = black_box(controller.update_node_duty(
+                        node,
+                        EcoBand::Amber,
+                        black_box(phi_dw),
+                    ));
+                }
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("eco_load");
+    for size in SIZES {
+        let nodes = synthetic_nodes(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &nodes, |b, nodes| {
+            b.iter(|| controller.eco_load(black_box(nodes), 0.5, 0.5))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_update_node_duty);
+criterion_main!(benches);
@@ -0,0 +1,245 @@
+//! Airflow as a function of duty cycle.
+//!
+//! A row's `airflow_m3_per_s` is what the unit moved while measured, which
+//! [`compute_mass_kg`](crate::compute_mass_kg) keeps using for historical
+//! rows. To predict what a node removes at some other duty, treat it as
+//! the airflow at full duty, `Q_max`, and scale with an [`AirflowModel`]:
+//!
+//! - `Constant`: `Q_max` at any duty, the uncoupled behaviour;
+//! - `Linear`: `duty * Q_max`;
+//! - `Affine`: a bleed flow `Q_min` even when idle,
+//!   `Q_min + duty * (Q_max - Q_min)`.
+//!
+//! Mass is linear in airflow, so a prediction at duty `u` is the measured
+//! mass times [`AirflowModel::airflow_fraction`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::units::UnitError;
+use crate::{compute_mass_kg, CorridorRow, NodeState, Pollutant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum AirflowModel {
+    Constant,
+    #[default]
+    Linear,
+    Affine {
+        /// Bleed flow at zero duty, m^3/s; capped at the row's airflow.
+        min_airflow_m3_per_s: f64,
+    },
+}
+
+impl AirflowModel {
+    /// Airflow at `duty`, clamped to [0, 1], for a unit moving
+    /// `max_airflow_m3_per_s` at full duty.
+    pub fn effective_airflow(&self, max_airflow_m3_per_s: f64, duty: f64) -> f64 {
+        let duty = duty.clamp(0.0, 1.0);
+        match *self {
+            AirflowModel::Constant => max_airflow_m3_per_s,
+            AirflowModel::Linear => duty * max_airflow_m3_per_s,
+            AirflowModel::Affine {
+                min_airflow_m3_per_s,
+            } => {
+                let min = min_airflow_m3_per_s.min(max_airflow_m3_per_s);
+                min + duty * (max_airflow_m3_per_s - min)
+            }
+        }
+    }
+
+    /// `effective_airflow` as a share of `max_airflow_m3_per_s`; 0 for a
+    /// unit with no airflow.
+    pub fn airflow_fraction(&self, max_airflow_m3_per_s: f64, duty: f64) -> f64 {
+        if max_airflow_m3_per_s > 0.0 {
+            self.effective_airflow(max_airflow_m3_per_s, duty) / max_airflow_m3_per_s
+        } else {
+            0.0
+        }
+    }
+
+    fn validate(&self, entry: &str) -> Result<(), ConfigError> {
+        match *self {
+            AirflowModel::Affine {
+                min_airflow_m3_per_s: q,
+            } if !(q.is_finite() && q >= 0.0) => Err(ConfigError::Invalid {
+                field: "airflow",
+                reason: format!("{entry}.min_airflow_m3_per_s must be finite and >= 0, got {q}"),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Airflow models by node type (`CorridorRow::type`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AirflowModels {
+    #[serde(default)]
+    pub default: AirflowModel,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_type: BTreeMap<String, AirflowModel>,
+}
+
+impl AirflowModels {
+    pub fn uniform(model: AirflowModel) -> Self {
+        AirflowModels {
+            default: model,
+            by_type: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_type(mut self, node_type: impl Into<String>, model: AirflowModel) -> Self {
+        self.by_type.insert(node_type.into(), model);
+        self
+    }
+
+    pub fn for_row(&self, row: &CorridorRow) -> &AirflowModel {
+        self.by_type.get(&row.r#type).unwrap_or(&self.default)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.default.validate("default")?;
+        for (node_type, model) in &self.by_type {
+            model.validate(&format!("by_type.{node_type}"))?;
+        }
+        Ok(())
+    }
+
+    /// `node` with mass and karma scaled from its row's airflow to its
+    /// `duty_cycle`. A node out of `Active` mode is not driving its
+    /// airflow, so its measured mass and karma are kept.
+    pub fn project_node(&self, node: &NodeState) -> NodeState {
+        if !node.mode.is_active() {
+            return node.clone();
+        }
+        let fraction = self
+            .for_row(&node.row)
+            .airflow_fraction(node.row.airflow_m3_per_s, node.duty_cycle);
+        NodeState {
+            mass_kg: node.mass_kg * fraction,
+            karma_bytes: node.karma_bytes * fraction,
+            ..node.clone()
+        }
+    }
+}
+
+/// `compute_mass_kg` for `row` running at `duty`, its `airflow_m3_per_s`
+/// taken as the airflow at full duty.
+pub fn compute_mass_kg_at_duty(
+    row: &CorridorRow,
+    duty: f64,
+    model: &AirflowModel,
+    pollutant: Pollutant,
+    temperature_k: f64,
+) -> Result<f64, UnitError> {
+    let projected = CorridorRow {
+        airflow_m3_per_s: model.effective_airflow(row.airflow_m3_per_s, duty),
+        ..row.clone()
+    };
+    compute_mass_kg(&projected, pollutant, temperature_k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::StepInput;
+
+    fn mass_at(duty: f64, model: AirflowModel) -> f64 {
+        let row = &phoenix_nodes()[0].row;
+        compute_mass_kg_at_duty(row, duty, &model, Pollutant::PM25, 310.0).unwrap()
+    }
+
+    #[test]
+    fn linear_model_scales_mass_with_duty() {
+        let full = compute_mass_kg(&phoenix_nodes()[0].row, Pollutant::PM25, 310.0).unwrap();
+        assert_eq!(mass_at(1.0, AirflowModel::Linear), full);
+        for duty in [0.0, 0.1, 0.25, 0.5, 0.9] {
+            let m = mass_at(duty, AirflowModel::Linear);
+            assert!((m - duty * full).abs() <= 1e-12 * full, "{duty}: {m}");
+        }
+        assert_eq!(mass_at(1.7, AirflowModel::Linear), full);
+        assert_eq!(mass_at(0.1, AirflowModel::Constant), full);
+    }
+
+    #[test]
+    fn bleed_flow_sets_a_floor() {
+        // Phoenix canopy row: 3 m^3/s at full duty, 0.6 bled when idle.
+        let model = AirflowModel::Affine {
+            min_airflow_m3_per_s: 0.6,
+        };
+        let full = mass_at(1.0, model);
+        assert!((mass_at(0.0, model) - 0.2 * full).abs() < 1e-12 * full);
+        assert!((mass_at(0.5, model) - 0.6 * full).abs() < 1e-12 * full);
+        assert!(mass_at(0.0, model) > mass_at(0.0, AirflowModel::Linear));
+        assert_eq!(model.effective_airflow(0.4, 0.0), 0.4);
+        assert_eq!(model.airflow_fraction(0.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn simulator_evaluates_eco_load_at_each_duty() {
+        let models = AirflowModels::uniform(AirflowModel::Linear).with_type(
+            "SchoolZoneShield",
+            AirflowModel::Affine {
+                min_airflow_m3_per_s: 0.5,
+            },
+        );
+        let corridor = || {
+            let mut nodes = phoenix_nodes();
+            nodes[1].row.r#type = "SchoolZoneShield".into();
+            nodes
+        };
+        let nodes = corridor();
+        assert_eq!(models.for_row(&nodes[0].row), &AirflowModel::Linear);
+        assert_ne!(models.for_row(&nodes[1].row), &AirflowModel::Linear);
+
+        let input = |_| StepInput {
+            phi_dw: 0.0,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        };
+        let run = |airflow: Option<AirflowModels>, duty: f64| {
+            let mut controller = phoenix_controller();
+            controller.airflow = airflow;
+            let mut nodes = corridor();
+            for node in &mut nodes {
+                node.duty_cycle = duty;
+            }
+            controller
+                .run_steps(&mut nodes, 1, input)
+                .unwrap()
+                .remove(0)
+        };
+
+        let uncoupled = run(None, 0.1);
+        assert_eq!(uncoupled.eco_load, run(None, 0.9).eco_load);
+        let low = run(Some(models.clone()), 0.1);
+        let high = run(Some(models), 0.9);
+        assert!(low.eco_load < high.eco_load);
+        assert!(high.eco_load < uncoupled.eco_load);
+        // Records report mass at the duty the step settled on.
+        let (coupled, measured) = (&low.nodes[0], &uncoupled.nodes[0]);
+        assert!((coupled.mass_kg - coupled.duty_cycle * measured.mass_kg).abs() < 1e-15);
+    }
+
+    #[test]
+    fn negative_bleed_flow_is_rejected() {
+        let models = AirflowModels::default().with_type(
+            "UrbanNanoswarmCanopy",
+            AirflowModel::Affine {
+                min_airflow_m3_per_s: -1.0,
+            },
+        );
+        assert!(matches!(
+            models.validate(),
+            Err(ConfigError::Invalid { field: "airflow", reason })
+                if reason.starts_with("by_type.UrbanNanoswarmCanopy")
+        ));
+    }
+}
@@ -0,0 +1,300 @@
+//! Corridor-wide duty allocation under a shared cap.
+//!
+//! Each node can pass its own host budget while the corridor as a whole
+//! overdraws a shared feed. `DutyAllocator` runs after the update law and
+//! water-fills the desired duties under the cap: node i gets
+//! `min(desired_i, level * priority_i)`, with the level chosen so the
+//! aggregate meets the cap exactly. Priority is `geo_weight *
+//! ecoimpact_score`, so sensitive, high-impact nodes are cut least, and no
+//! node is ever raised above its desired duty.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::NodeState;
+
+/// What the corridor cap limits. The aggregate is linear in duty.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "cap", rename_all = "snake_case")]
+pub enum CorridorCap {
+    /// Total draw (W), reading each node's `power_w` as its draw at full
+    /// duty.
+    PowerW(f64),
+    /// Total `duty * airflow_m3_per_s` (m^3/s).
+    DutyAirflowM3PerS(f64),
+}
+
+impl CorridorCap {
+    fn limit(self) -> f64 {
+        match self {
+            CorridorCap::PowerW(cap) | CorridorCap::DutyAirflowM3PerS(cap) => cap,
+        }
+    }
+
+    /// The node's contribution to the aggregate per unit duty.
+    fn weight(self, node: &NodeState) -> (&'static str, f64) {
+        match self {
+            CorridorCap::PowerW(_) => ("power_w", node.power_w),
+            CorridorCap::DutyAirflowM3PerS(_) => ("airflow_m3_per_s", node.row.airflow_m3_per_s),
+        }
+    }
+}
+
+/// Why no allocation was made.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AllocationError {
+    #[error("corridor cap must be finite and non-negative, got {cap}")]
+    InvalidCap { cap: f64 },
+    #[error("{desired} desired duties for {nodes} nodes")]
+    LengthMismatch { nodes: usize, desired: usize },
+    #[error("{machine_id}: {field} must be finite and non-negative, got {value}")]
+    InvalidNodeValue {
+        machine_id: String,
+        field: &'static str,
+        value: f64,
+    },
+}
+
+/// One node's share of the cap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeAllocation {
+    pub machine_id: String,
+    pub desired_duty: f64,
+    pub allocated_duty: f64,
+    /// `allocated_duty / desired_duty`, in [0, 1]; 1 for a zero desired duty.
+    pub scale: f64,
+}
+
+/// Result of one allocation pass, in node order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DutyAllocation {
+    pub cap: CorridorCap,
+    /// Aggregate at the desired duties.
+    pub demand: f64,
+    /// Aggregate at the allocated duties; at most the cap.
+    pub allocated: f64,
+    pub nodes: Vec<NodeAllocation>,
+}
+
+impl DutyAllocation {
+    /// Whether the cap cut any node.
+    pub fn is_binding(&self) -> bool {
+        self.nodes.iter().any(|n| n.scale < 1.0)
+    }
+}
+
+/// Shares a corridor-wide cap among nodes; see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DutyAllocator {
+    pub cap: CorridorCap,
+}
+
+impl DutyAllocator {
+    pub fn new(cap: CorridorCap) -> Self {
+        DutyAllocator { cap }
+    }
+
+    /// Allocate `desired[i]` for `nodes[i]` without changing the nodes.
+    /// Desired duties are clamped to [0, 1].
+    pub fn allocate(
+        &self,
+        nodes: &[NodeState],
+        desired: &[f64],
+    ) -> Result<DutyAllocation, AllocationError> {
+        let cap = self.cap.limit();
+        if !(cap.is_finite() && cap >= 0.0) {
+            return Err(AllocationError::InvalidCap { cap });
+        }
+        if nodes.len() != desired.len() {
+            return Err(AllocationError::LengthMismatch {
+                nodes: nodes.len(),
+                desired: desired.len(),
+            });
+        }
+
+        // (desired, weight, priority) per node.
+        let mut terms = Vec::with_capacity(nodes.len());
+        for (node, &d) in nodes.iter().zip(desired) {
+            let (weight_field, weight) = self.cap.weight(node);
+            for (field, value) in [
+                ("desired_duty", d),
+                (weight_field, weight),
+                ("geo_weight", node.geo_weight),
+                ("ecoimpact_score", node.row.ecoimpact_score),
+            ] {
+                if !(value.is_finite() && value >= 0.0) {
+                    return Err(AllocationError::InvalidNodeValue {
+                        machine_id: node.row.machine_id.clone(),
+                        field,
+                        value,
+                    });
+                }
+            }
+            let priority = node.geo_weight * node.row.ecoimpact_score;
+            terms.push((d.min(1.0), weight, priority));
+        }
+
+        let demand: f64 = terms.iter().map(|&(d, w, _)| w * d).sum();
+        let level = if demand > cap {
+            Some(water_level(&terms, cap))
+        } else {
+            None
+        };
+
+        let mut allocated = 0.0;
+        let nodes = nodes
+            .iter()
+            .zip(&terms)
+            .map(|(node, &(d, w, p))| {
+                let u = match level {
+                    Some(level) if w > 0.0 => d.min(level * p),
+                    _ => d,
+                };
+                allocated += w * u;
+                NodeAllocation {
+                    machine_id: node.row.machine_id.clone(),
+                    desired_duty: d,
+                    allocated_duty: u,
+                    scale: if d > 0.0 { u / d } else { 1.0 },
+                }
+            })
+            .collect();
+        Ok(DutyAllocation {
+            cap: self.cap,
+            demand,
+            allocated,
+            nodes,
+        })
+    }
+
+    /// Allocate with each node's current duty, e.g. just after an update
+    /// pass, as its desired duty, and write the allocated duties back.
+    pub fn apply(&self, nodes: &mut [NodeState]) -> Result<DutyAllocation, AllocationError> {
+        let desired: Vec<f64> = nodes.iter().map(|n| n.duty_cycle).collect();
+        let allocation = self.allocate(nodes, &desired)?;
+        for (node, a) in nodes.iter_mut().zip(&allocation.nodes) {
+            node.duty_cycle = a.allocated_duty;
+        }
+        Ok(allocation)
+    }
+}
+
+/// The level at which `sum w * min(d, level * p)` over nodes with `w > 0`
+/// equals `cap`. Only called when the demand exceeds the cap, so the level
+/// lies below the last breakpoint `d / p`.
+fn water_level(terms: &[(f64, f64, f64)], cap: f64) -> f64 {
+    let mut active: Vec<(f64, f64, f64)> = terms
+        .iter()
+        .filter(|&&(d, w, p)| w > 0.0 && d > 0.0 && p > 0.0)
+        .map(|&(d, w, p)| (d / p, w * d, w * p))
+        .collect();
+    active.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Below the next breakpoint the aggregate is `saturated + level * slope`.
+    let mut saturated = 0.0;
+    let mut slope: f64 = active.iter().map(|&(_, _, wp)| wp).sum();
+    for (breakpoint, wd, wp) in active {
+        if saturated + breakpoint * slope >= cap {
+            break;
+        }
+        saturated += wd;
+        slope -= wp;
+    }
+    if slope > 0.0 {
+        ((cap - saturated) / slope).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+
+    /// Canopy, school zone and an industrial-edge node, each wanting 0.8
+    /// duty at 100 W full draw: 240 W against a 150 W feed.
+    fn corridor() -> Vec<NodeState> {
+        let base = phoenix_nodes();
+        let mut canopy = base[0].clone();
+        let mut school = base[1].clone();
+        let mut edge = base[0].clone();
+        edge.row.machine_id = "CYB-AIR-EDGE-09".to_string();
+        canopy.geo_weight = 1.0;
+        school.geo_weight = 2.0;
+        edge.geo_weight = 0.5;
+        edge.row.ecoimpact_score = 0.75;
+        let mut nodes = vec![canopy, school, edge];
+        for node in &mut nodes {
+            node.power_w = 100.0;
+            node.duty_cycle = 0.8;
+        }
+        nodes
+    }
+
+    #[test]
+    fn school_zone_is_cut_least() {
+        let mut nodes = corridor();
+        let allocation = DutyAllocator::new(CorridorCap::PowerW(150.0))
+            .apply(&mut nodes)
+            .unwrap();
+        assert!((allocation.demand - 240.0).abs() < 1e-9);
+        assert!((allocation.allocated - 150.0).abs() < 1e-9);
+        assert!(allocation.is_binding());
+
+        let scale: Vec<f64> = allocation.nodes.iter().map(|n| n.scale).collect();
+        assert!(scale[1] > scale[0] && scale[0] > scale[2], "{scale:?}");
+        for (node, a) in nodes.iter().zip(&allocation.nodes) {
+            assert!(a.allocated_duty <= a.desired_duty);
+            assert_eq!(node.duty_cycle, a.allocated_duty);
+        }
+        // The school node keeps its full desired duty here.
+        assert_eq!(allocation.nodes[1].allocated_duty, 0.8);
+    }
+
+    #[test]
+    fn demand_under_the_cap_is_untouched() {
+        let nodes = corridor();
+        let desired = [0.2, 0.3, 0.4];
+        let allocation = DutyAllocator::new(CorridorCap::PowerW(150.0))
+            .allocate(&nodes, &desired)
+            .unwrap();
+        assert!(!allocation.is_binding());
+        for (a, d) in allocation.nodes.iter().zip(desired) {
+            assert_eq!(a.allocated_duty, d);
+            assert_eq!(a.scale, 1.0);
+        }
+    }
+
+    #[test]
+    fn airflow_cap_and_bad_input() {
+        let nodes = corridor();
+        let airflow: f64 = nodes.iter().map(|n| n.row.airflow_m3_per_s).sum();
+        let allocator = DutyAllocator::new(CorridorCap::DutyAirflowM3PerS(0.4 * airflow));
+        let allocation = allocator.allocate(&nodes, &[0.8; 3]).unwrap();
+        assert!((allocation.allocated - 0.4 * airflow).abs() < 1e-9);
+
+        assert_eq!(
+            allocator.allocate(&nodes, &[0.8; 2]),
+            Err(AllocationError::LengthMismatch {
+                nodes: 3,
+                desired: 2
+            })
+        );
+        assert!(matches!(
+            allocator.allocate(&nodes, &[0.8, f64::NAN, 0.8]),
+            Err(AllocationError::InvalidNodeValue {
+                field: "desired_duty",
+                ..
+            })
+        ));
+        assert_eq!(
+            DutyAllocator::new(CorridorCap::PowerW(-1.0)).allocate(&nodes, &[0.8; 3]),
+            Err(AllocationError::InvalidCap { cap: -1.0 })
+        );
+    }
+}
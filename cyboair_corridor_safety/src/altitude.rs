@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+/// Source of site altitude for the safety envelope.
+pub trait AltitudeProvider {
+    /// Altitude of `location` in metres, or `None` if the location is unknown.
+    fn altitude_m(&self, location: &str) -> Option<f64>;
+}
+
+/// Closures can capture a DEM raster or any other state.
+impl<F> AltitudeProvider for F
+where
+    F: Fn(&str) -> Option<f64>,
+{
+    fn altitude_m(&self, location: &str) -> Option<f64> {
+        self(location)
+    }
+}
+
+/// Same altitude for every location, e.g. a city-wide mean elevation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstAltitude(pub f64);
+
+impl AltitudeProvider for ConstAltitude {
+    fn altitude_m(&self, _location: &str) -> Option<f64> {
+        Some(self.0)
+    }
+}
+
+/// Surveyed site elevations keyed by location.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapAltitude {
+    pub sites: HashMap<String, f64>,
+}
+
+impl MapAltitude {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_site(mut self, location: &str, altitude_m: f64) -> Self {
+        self.sites.insert(location.to_string(), altitude_m);
+        self
+    }
+}
+
+impl AltitudeProvider for MapAltitude {
+    fn altitude_m(&self, location: &str) -> Option<f64> {
+        self.sites.get(location).copied()
+    }
+}
+
+/// Adapter for the old `fn(&str) -> f64` altitude maps, which know every location.
+#[derive(Debug, Clone, Copy)]
+pub struct FnAltitude(pub fn(&str) -> f64);
+
+impl From<fn(&str) -> f64> for FnAltitude {
+    fn from(f: fn(&str) -> f64) -> Self {
+        FnAltitude(f)
+    }
+}
+
+impl AltitudeProvider for FnAltitude {
+    fn altitude_m(&self, location: &str) -> Option<f64> {
+        Some((self.0)(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(_loc: &str) -> f64 {
+        331.0
+    }
+
+    #[test]
+    fn providers_resolve_locations() {
+        let map = MapAltitude::new().with_site("Elementary-North", 340.0);
+        assert_eq!(map.altitude_m("Elementary-North"), Some(340.0));
+        assert_eq!(map.altitude_m("Unknown-Site"), None);
+
+        assert_eq!(ConstAltitude(331.0).altitude_m("anywhere"), Some(331.0));
+        let legacy = FnAltitude::from(flat as fn(&str) -> f64);
+        assert_eq!(legacy.altitude_m("anywhere"), Some(331.0));
+
+        let dem = HashMap::from([("Ridge-1", 520.0)]);
+        let lookup = move |loc: &str| dem.get(loc).copied();
+        assert_eq!(lookup.altitude_m("Ridge-1"), Some(520.0));
+    }
+}
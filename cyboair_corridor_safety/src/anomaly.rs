@@ -0,0 +1,449 @@
+//! Suspicion scoring for physically implausible rows.
+//!
+//! Some rows parse and pass every envelope but cannot be right: `cout` at
+//! exactly 0.0 for hours, a removal efficiency above 99.9 %, airflow ten
+//! times the machine type's spec. An [`AnomalyScorer`] runs three
+//! heuristics over each row, in time order per machine:
+//!
+//! * removal efficiency `(cin - cout) / cin` above the type's cap;
+//! * `cout` flat (within a tolerance) for at least `flat_line_min_s` of
+//!   consecutive periods;
+//! * airflow outside the type's plausible range, scored by how many
+//!   decades outside, up to 1 at one decade.
+//!
+//! Each heuristic scores the row in [0, 1]; the row's suspicion is the
+//! weighted sum, capped at 1. Rows scoring above `threshold` are flagged,
+//! not rejected: [`AnomalyScorer::flag_nodes`] sets
+//! [`NodeState::suspicion`], and a [`KarmaLedger`](crate::KarmaLedger)
+//! with a suspicion discount credits less karma for flagged nodes.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::{CorridorRow, NodeState, ShardRow};
+
+/// Heuristic that contributed to a row's suspicion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Heuristic {
+    EfficiencyAboveCap,
+    FlatLine,
+    AirflowOutOfRange,
+}
+
+/// Plausible airflow for a machine type, m^3/s.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AirflowRange {
+    pub min_m3_per_s: f64,
+    pub max_m3_per_s: f64,
+}
+
+/// Weight of each heuristic in the suspicion score.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HeuristicWeights {
+    pub efficiency: f64,
+    pub flat_line: f64,
+    pub airflow: f64,
+}
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        HeuristicWeights {
+            efficiency: 1.0,
+            flat_line: 1.0,
+            airflow: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct AnomalyConfig {
+    /// Rows scoring above this are flagged.
+    pub threshold: f64,
+    pub weights: HeuristicWeights,
+    /// Highest plausible removal efficiency, as a fraction.
+    pub efficiency_cap: f64,
+    /// Per-type overrides of `efficiency_cap`, by `CorridorRow::type`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub efficiency_caps_by_type: BTreeMap<String, f64>,
+    /// Seconds of unchanged `cout` that count as a flat line.
+    pub flat_line_min_s: f64,
+    /// Largest `cout` change still taken as unchanged; 0 for exact.
+    pub flat_line_tolerance: f64,
+    /// Plausible airflow by `CorridorRow::type`; types not listed are not
+    /// checked.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub airflow_ranges: BTreeMap<String, AirflowRange>,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        AnomalyConfig {
+            threshold: 0.5,
+            weights: HeuristicWeights::default(),
+            efficiency_cap: 0.999,
+            efficiency_caps_by_type: BTreeMap::new(),
+            flat_line_min_s: 4.0 * 3600.0,
+            flat_line_tolerance: 0.0,
+            airflow_ranges: BTreeMap::new(),
+        }
+    }
+}
+
+impl AnomalyConfig {
+    pub fn with_efficiency_cap(mut self, node_type: impl Into<String>, cap: f64) -> Self {
+        self.efficiency_caps_by_type.insert(node_type.into(), cap);
+        self
+    }
+
+    pub fn with_airflow_range(
+        mut self,
+        node_type: impl Into<String>,
+        min_m3_per_s: f64,
+        max_m3_per_s: f64,
+    ) -> Self {
+        self.airflow_ranges.insert(
+            node_type.into(),
+            AirflowRange {
+                min_m3_per_s,
+                max_m3_per_s,
+            },
+        );
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field, reason: String| Err(ConfigError::Invalid { field, reason });
+        if !(0.0..1.0).contains(&self.threshold) {
+            return invalid(
+                "anomaly.threshold",
+                format!("must be in [0, 1), got {}", self.threshold),
+            );
+        }
+        let w = &self.weights;
+        for (name, v) in [
+            ("efficiency", w.efficiency),
+            ("flat_line", w.flat_line),
+            ("airflow", w.airflow),
+        ] {
+            if !(0.0..=1.0).contains(&v) {
+                return invalid(
+                    "anomaly.weights",
+                    format!("{name} must be in [0, 1], got {v}"),
+                );
+            }
+        }
+        for (entry, cap) in std::iter::once(("default", &self.efficiency_cap)).chain(
+            self.efficiency_caps_by_type
+                .iter()
+                .map(|(t, c)| (t.as_str(), c)),
+        ) {
+            if !(*cap > 0.0 && *cap <= 1.0) {
+                return invalid(
+                    "anomaly.efficiency_cap",
+                    format!("{entry} must be in (0, 1], got {cap}"),
+                );
+            }
+        }
+        if !(self.flat_line_min_s.is_finite() && self.flat_line_min_s > 0.0) {
+            return invalid(
+                "anomaly.flat_line_min_s",
+                format!("must be finite and positive, got {}", self.flat_line_min_s),
+            );
+        }
+        if !(self.flat_line_tolerance.is_finite() && self.flat_line_tolerance >= 0.0) {
+            return invalid(
+                "anomaly.flat_line_tolerance",
+                format!("must be finite and >= 0, got {}", self.flat_line_tolerance),
+            );
+        }
+        for (node_type, r) in &self.airflow_ranges {
+            if !(r.min_m3_per_s > 0.0
+                && r.min_m3_per_s <= r.max_m3_per_s
+                && r.max_m3_per_s.is_finite())
+            {
+                return invalid(
+                    "anomaly.airflow_ranges",
+                    format!(
+                        "{node_type} needs 0 < min <= max, got [{}, {}]",
+                        r.min_m3_per_s, r.max_m3_per_s
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn efficiency_cap_for(&self, row: &CorridorRow) -> f64 {
+        self.efficiency_caps_by_type
+            .get(&row.r#type)
+            .copied()
+            .unwrap_or(self.efficiency_cap)
+    }
+}
+
+/// A row's suspicion score and the heuristics that contributed to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Suspicion {
+    /// In [0, 1].
+    pub score: f64,
+    pub heuristics: Vec<Heuristic>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FlatRun {
+    cout: f64,
+    /// Seconds `cout` has held, counting the periods after the first.
+    held_s: f64,
+}
+
+/// Scores rows against an [`AnomalyConfig`], tracking each machine's `cout`
+/// between rows. Feed each machine's rows in time order.
+#[derive(Debug, Clone)]
+pub struct AnomalyScorer {
+    config: AnomalyConfig,
+    runs: BTreeMap<String, FlatRun>,
+}
+
+impl AnomalyScorer {
+    pub fn new(config: AnomalyConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(AnomalyScorer {
+            config,
+            runs: BTreeMap::new(),
+        })
+    }
+
+    pub fn config(&self) -> &AnomalyConfig {
+        &self.config
+    }
+
+    /// Score the next row of its machine.
+    pub fn score(&mut self, row: &CorridorRow) -> Suspicion {
+        let config = &self.config;
+        let mut score = 0.0;
+        let mut heuristics = Vec::new();
+        let mut add = |heuristic, weight: f64, s: f64| {
+            if s > 0.0 {
+                score += weight * s;
+                heuristics.push(heuristic);
+            }
+        };
+
+        let efficiency = (row.cin - row.cout) / row.cin;
+        let efficiency_hit = row.cin > 0.0 && efficiency > config.efficiency_cap_for(row);
+        add(
+            Heuristic::EfficiencyAboveCap,
+            config.weights.efficiency,
+            if efficiency_hit { 1.0 } else { 0.0 },
+        );
+
+        let run = match self.runs.get(&row.machine_id) {
+            Some(run) if (row.cout - run.cout).abs() <= config.flat_line_tolerance => FlatRun {
+                cout: run.cout,
+                held_s: run.held_s + row.period_s,
+            },
+            _ => FlatRun {
+                cout: row.cout,
+                held_s: 0.0,
+            },
+        };
+        self.runs.insert(row.machine_id.clone(), run);
+        add(
+            Heuristic::FlatLine,
+            config.weights.flat_line,
+            if run.held_s >= config.flat_line_min_s {
+                1.0
+            } else {
+                0.0
+            },
+        );
+
+        if let Some(range) = config.airflow_ranges.get(&row.r#type) {
+            let q = row.airflow_m3_per_s;
+            let decades = if q < range.min_m3_per_s {
+                (range.min_m3_per_s / q.max(f64::MIN_POSITIVE)).log10()
+            } else if q > range.max_m3_per_s {
+                (q / range.max_m3_per_s).log10()
+            } else {
+                0.0
+            };
+            add(
+                Heuristic::AirflowOutOfRange,
+                config.weights.airflow,
+                decades.min(1.0),
+            );
+        }
+
+        Suspicion {
+            score: score.min(1.0),
+            heuristics,
+        }
+    }
+
+    /// [`score`](Self::score) the row, keeping it only if it is flagged.
+    pub fn flag(&mut self, row: &CorridorRow) -> Option<Suspicion> {
+        let suspicion = self.score(row);
+        (suspicion.score > self.config.threshold).then_some(suspicion)
+    }
+
+    /// [`flag`](Self::flag) each row of a loaded shard, in order.
+    pub fn flag_shard(&mut self, rows: &[ShardRow]) -> Vec<Option<Suspicion>> {
+        rows.iter().map(|r| self.flag(&r.row)).collect()
+    }
+
+    /// Set every node's `suspicion` from its current row, returning how
+    /// many were flagged. Call once per step.
+    pub fn flag_nodes(&mut self, nodes: &mut [NodeState]) -> usize {
+        let mut flagged = 0;
+        for node in nodes {
+            node.suspicion = self.flag(&node.row);
+            flagged += usize::from(node.suspicion.is_some());
+        }
+        flagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+
+    fn row() -> CorridorRow {
+        phoenix_nodes()[0].row.clone()
+    }
+
+    #[test]
+    fn efficiency_above_the_type_cap_is_flagged() {
+        let mut scorer =
+            AnomalyScorer::new(AnomalyConfig::default().with_efficiency_cap("Scrubber", 0.9999))
+                .unwrap();
+        let mut r = row();
+        r.cin = 40.0;
+        r.cout = 0.02; // 99.95 %
+        let suspicion = scorer.flag(&r).unwrap();
+        assert_eq!(suspicion.score, 1.0);
+        assert_eq!(suspicion.heuristics, [Heuristic::EfficiencyAboveCap]);
+
+        r.machine_id = "SCRUBBER-01".into();
+        r.r#type = "Scrubber".into();
+        assert!(scorer.flag(&r).is_none());
+    }
+
+    #[test]
+    fn flat_lines_are_flagged_once_they_last() {
+        let mut scorer = AnomalyScorer::new(AnomalyConfig {
+            flat_line_min_s: 3.0 * 3600.0,
+            ..AnomalyConfig::default()
+        })
+        .unwrap();
+        let mut r = row();
+        r.period_s = 3600.0;
+        r.cout = 12.5;
+        let flagged: Vec<bool> = (0..5).map(|_| scorer.flag(&r).is_some()).collect();
+        assert_eq!(flagged, [false, false, false, true, true]);
+
+        // Any change restarts the run; other machines have their own.
+        r.cout = 12.6;
+        assert!(scorer.flag(&r).is_none());
+        let mut other = row();
+        other.machine_id = "CYB-AIR-CANOPY-02".into();
+        other.cout = 12.6;
+        assert!(scorer.flag(&other).is_none());
+    }
+
+    #[test]
+    fn airflow_is_scored_by_decades_outside_the_type_range() {
+        let config = AnomalyConfig {
+            weights: HeuristicWeights {
+                airflow: 0.8,
+                ..HeuristicWeights::default()
+            },
+            ..AnomalyConfig::default()
+        }
+        .with_airflow_range(row().r#type, 1.0, 4.0);
+        let mut scorer = AnomalyScorer::new(config).unwrap();
+        let mut r = row();
+        for (airflow, score) in [
+            (2.0, 0.0),
+            (40.0, 0.8),
+            (400.0, 0.8),
+            (0.5, 0.8 * 2f64.log10()),
+        ] {
+            r.airflow_m3_per_s = airflow;
+            r.cout = airflow; // keep the flat line out of it
+            let s = scorer.score(&r).score;
+            assert!((s - score).abs() < 1e-12, "{airflow}: {s}");
+        }
+        r.airflow_m3_per_s = 0.5;
+        r.cout = 1.0;
+        assert!(scorer.flag(&r).is_none(), "0.24 is under the threshold");
+    }
+
+    #[test]
+    fn weighted_scores_are_summed_and_capped() {
+        let weights = HeuristicWeights {
+            efficiency: 0.3,
+            flat_line: 0.3,
+            airflow: 0.3,
+        };
+        let config = AnomalyConfig {
+            threshold: 0.7,
+            weights,
+            flat_line_min_s: 1.0,
+            ..AnomalyConfig::default()
+        }
+        .with_airflow_range(row().r#type, 1.0, 4.0);
+        let mut scorer = AnomalyScorer::new(config.clone()).unwrap();
+        let mut r = row();
+        r.cout = 0.0;
+        r.airflow_m3_per_s = 40.0;
+        assert!(scorer.flag(&r).is_none(), "0.6 is two hits");
+        let suspicion = scorer.flag(&r).unwrap();
+        assert!((suspicion.score - 0.9).abs() < 1e-12);
+        assert_eq!(suspicion.heuristics.len(), 3);
+
+        let mut heavy = AnomalyScorer::new(AnomalyConfig {
+            weights: HeuristicWeights::default(),
+            ..config
+        })
+        .unwrap();
+        heavy.score(&r);
+        assert_eq!(heavy.score(&r).score, 1.0);
+    }
+
+    #[test]
+    fn nodes_carry_the_flag_and_bad_configs_are_refused() {
+        let mut scorer = AnomalyScorer::new(AnomalyConfig::default()).unwrap();
+        let mut nodes = phoenix_nodes();
+        nodes[1].row.cout = 0.0;
+        assert_eq!(scorer.flag_nodes(&mut nodes), 1);
+        assert!(nodes[0].suspicion.is_none());
+        assert_eq!(
+            nodes[1].suspicion.as_ref().unwrap().heuristics,
+            [Heuristic::EfficiencyAboveCap]
+        );
+
+        for config in [
+            AnomalyConfig {
+                threshold: 1.0,
+                ..AnomalyConfig::default()
+            },
+            AnomalyConfig::default().with_efficiency_cap("Scrubber", 1.5),
+            AnomalyConfig::default().with_airflow_range("Scrubber", 4.0, 1.0),
+        ] {
+            assert!(matches!(
+                AnomalyScorer::new(config),
+                Err(ConfigError::Invalid { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+
+use crate::snapshot::{BandState, SnapshotError};
+use crate::{EcoBand, EcoBandClassifier};
+
+/// Reference statistics of corridor eco-load for one calendar month.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MonthBaseline {
+    /// Most recent observations, oldest first (bounded by `BaselineModel::max_samples`).
+    pub samples: Vec<f64>,
+}
+
+impl MonthBaseline {
+    /// Median of the stored samples, or `None` if the month has no data yet.
+    pub fn median(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            Some(0.5 * (sorted[mid - 1] + sorted[mid]))
+        } else {
+            Some(sorted[mid])
+        }
+    }
+}
+
+/// Per-month seasonal baseline of corridor eco-load.
+///
+/// Serializable so the learned baseline can be persisted alongside the rest
+/// of the controller state and restored after a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BaselineModel {
+    pub months: [MonthBaseline; 12],
+    /// Window size per month; older samples are dropped first.
+    pub max_samples: usize,
+}
+
+impl BaselineModel {
+    pub fn new(max_samples: usize) -> Self {
+        BaselineModel {
+            months: Default::default(),
+            max_samples,
+        }
+    }
+
+    fn slot(month: u8) -> usize {
+        usize::from(month.clamp(1, 12) - 1)
+    }
+
+    /// Record an eco-load observation for `month` (1 = January .. 12 = December).
+    pub fn observe(&mut self, month: u8, eco_load: f64) {
+        if !eco_load.is_finite() {
+            return;
+        }
+        let m = &mut self.months[Self::slot(month)];
+        m.samples.push(eco_load);
+        if m.samples.len() > self.max_samples {
+            let excess = m.samples.len() - self.max_samples;
+            m.samples.drain(..excess);
+        }
+    }
+
+    /// Seasonal median for `month`, if any data has been observed.
+    pub fn median(&self, month: u8) -> Option<f64> {
+        self.months[Self::slot(month)].median()
+    }
+
+    pub fn sample_count(&self, month: u8) -> usize {
+        self.months[Self::slot(month)].samples.len()
+    }
+}
+
+/// Whether the band thresholds apply to absolute or baseline-relative load.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LoadScaling {
+    Absolute,
+    /// Classify on `eco_load / seasonal median`.
+    BaselineRelative,
+}
+
+/// Provenance of one seasonal band decision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonalBandDecision {
+    pub band: EcoBand,
+    pub month: u8,
+    pub absolute_load: f64,
+    /// Load actually fed to the inner classifier.
+    pub classified_load: f64,
+    /// Seasonal median used for scaling; `None` means absolute fallback.
+    pub baseline_median: Option<f64>,
+    pub baseline_samples: usize,
+    /// True if the absolute ceiling forced the band regardless of scaling.
+    pub ceiling_breached: bool,
+}
+
+/// Eco-band classifier that can operate on baseline-relative load while
+/// still enforcing an absolute hard ceiling.
+///
+/// The inner classifier's thresholds are in whatever units `scaling` selects
+/// (ratio to seasonal median for `BaselineRelative`). Any absolute load at or
+/// above `absolute_red_ceiling` is `Red`, and any at or above
+/// `absolute_amber_ceiling` is at least `Amber`, so relative scaling can never
+/// hide a dangerous absolute level. Months with no baseline fall back to
+/// absolute load.
+#[derive(Debug, Clone)]
+pub struct SeasonalEcoBand<B: EcoBandClassifier> {
+    pub inner: B,
+    pub baseline: BaselineModel,
+    pub scaling: LoadScaling,
+    pub absolute_amber_ceiling: f64,
+    pub absolute_red_ceiling: f64,
+    /// Calendar month (1..=12) used by `EcoBandClassifier::classify`.
+    pub month: u8,
+}
+
+impl<B: EcoBandClassifier> SeasonalEcoBand<B> {
+    /// Classify `eco_load` for `month` and report how the decision was made.
+    pub fn classify_seasonal(&self, eco_load: f64, month: u8) -> SeasonalBandDecision {
+        let median = match self.scaling {
+            LoadScaling::Absolute => None,
+            LoadScaling::BaselineRelative => self.baseline.median(month).filter(|m| *m > 0.0),
+        };
+        let classified_load = match median {
+            Some(m) => eco_load / m,
+            None => eco_load,
+        };
+        let scaled_band = self.inner.classify(classified_load);
+
+        let floor = if eco_load >= self.absolute_red_ceiling {
+            EcoBand::Red
+        } else if eco_load >= self.absolute_amber_ceiling {
+            EcoBand::Amber
+        } else {
+            EcoBand::Green
+        };
+
+        SeasonalBandDecision {
+            band: scaled_band.max(floor),
+            month,
+            absolute_load: eco_load,
+            classified_load,
+            baseline_median: median,
+            baseline_samples: self.baseline.sample_count(month),
+            ceiling_breached: floor > scaled_band,
+        }
+    }
+
+    /// Record `eco_load` into the baseline for `month`.
+    pub fn observe(&mut self, month: u8, eco_load: f64) {
+        self.baseline.observe(month, eco_load);
+    }
+}
+
+impl<B: EcoBandClassifier> EcoBandClassifier for SeasonalEcoBand<B> {
+    fn classify(&self, eco_load: f64) -> EcoBand {
+        self.classify_seasonal(eco_load, self.month).band
+    }
+
+    fn band_gain(&self, band: EcoBand) -> f64 {
+        self.inner.band_gain(band)
+    }
+
+    fn band_state(&self) -> BandState {
+        BandState {
+            baseline: Some(self.baseline.clone()),
+            ..self.inner.band_state()
+        }
+    }
+
+    fn restore_band_state(&mut self, state: &BandState) -> Result<(), SnapshotError> {
+        let baseline = state.baseline.clone().ok_or(SnapshotError::Missing {
+            component: "seasonal baseline",
+        })?;
+        self.inner.restore_band_state(state)?;
+        self.baseline = baseline;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThresholdEcoBand;
+
+    const JANUARY: u8 = 1;
+    const JULY: u8 = 7;
+
+    /// Deterministic day-to-day wiggle of +/- 10% around `level`.
+    fn season(level: f64, days: usize) -> Vec<f64> {
+        (0..days)
+            .map(|d| level * (1.0 + 0.1 * ((d as f64) * 0.7).sin()))
+            .collect()
+    }
+
+    fn absolute_thresholds() -> ThresholdEcoBand {
+        ThresholdEcoBand {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+            gain_green: 0.0,
+            gain_amber: 0.2,
+            gain_red: 0.5,
+        }
+    }
+
+    fn relative_thresholds() -> ThresholdEcoBand {
+        ThresholdEcoBand {
+            theta_green_amber: 1.5,
+            theta_amber_red: 2.5,
+            gain_green: 0.0,
+            gain_amber: 0.2,
+            gain_red: 0.5,
+        }
+    }
+
+    fn seasonal(
+        inner: ThresholdEcoBand,
+        scaling: LoadScaling,
+    ) -> SeasonalEcoBand<ThresholdEcoBand> {
+        SeasonalEcoBand {
+            inner,
+            baseline: BaselineModel::new(60),
+            scaling,
+            absolute_amber_ceiling: 1.2,
+            absolute_red_ceiling: 2.0,
+            month: JANUARY,
+        }
+    }
+
+    fn escalations(c: &SeasonalEcoBand<ThresholdEcoBand>, loads: &[f64], month: u8) -> usize {
+        loads
+            .iter()
+            .filter(|&&l| c.classify_seasonal(l, month).band != EcoBand::Green)
+            .count()
+    }
+
+    #[test]
+    fn relative_scaling_cuts_winter_false_positives() {
+        let winter = season(0.7, 30);
+        let summer = season(0.35, 30);
+
+        let absolute = seasonal(absolute_thresholds(), LoadScaling::Absolute);
+        let mut relative = seasonal(relative_thresholds(), LoadScaling::BaselineRelative);
+        for (&w, &s) in winter.iter().zip(summer.iter()) {
+            relative.observe(JANUARY, w);
+            relative.observe(JULY, s);
+        }
+
+        let abs_winter = escalations(&absolute, &winter, JANUARY);
+        let rel_winter = escalations(&relative, &winter, JANUARY);
+        assert!(abs_winter > 20);
+        assert_eq!(rel_winter, 0);
+        assert_eq!(escalations(&relative, &summer, JULY), 0);
+    }
+
+    #[test]
+    fn absolute_ceiling_escalates_in_both_seasons() {
+        let mut relative = seasonal(relative_thresholds(), LoadScaling::BaselineRelative);
+        for &l in &season(1.5, 30) {
+            relative.observe(JANUARY, l);
+        }
+        for &l in &season(0.35, 30) {
+            relative.observe(JULY, l);
+        }
+
+        // 2.5 is only ~1.7x the winter median, but above the absolute ceiling.
+        let winter = relative.classify_seasonal(2.5, JANUARY);
+        assert_eq!(winter.band, EcoBand::Red);
+        assert!(winter.ceiling_breached);
+        assert!(winter.baseline_median.is_some());
+
+        let summer = relative.classify_seasonal(2.5, JULY);
+        assert_eq!(summer.band, EcoBand::Red);
+    }
+
+    #[test]
+    fn missing_baseline_falls_back_to_absolute() {
+        let relative = seasonal(relative_thresholds(), LoadScaling::BaselineRelative);
+        let d = relative.classify_seasonal(0.8, JULY);
+        assert_eq!(d.baseline_median, None);
+        assert_eq!(d.classified_load, 0.8);
+    }
+
+    #[test]
+    fn baseline_round_trips_through_serde() {
+        let mut model = BaselineModel::new(3);
+        for l in [0.1, 0.2, 0.3, 0.4] {
+            model.observe(JULY, l);
+        }
+        assert_eq!(model.sample_count(JULY), 3);
+        assert_eq!(model.median(JULY), Some(0.3));
+
+        let restored: BaselineModel =
+            serde_json::from_str(&serde_json::to_string(&model).unwrap()).unwrap();
+        assert_eq!(restored.median(JULY), Some(0.3));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::snapshot::{BudgetState, SnapshotError};
+use crate::{HostBudget, NodeState, SafetyError};
+
+/// Smallest charge headroom used when scaling the power fraction, so a node
+/// sitting exactly at its reserve gets a large but finite penalty.
+const MIN_HEADROOM: f64 = 1e-3;
+
+/// Battery and power limits shared by every node in a corridor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BatteryConfig {
+    /// Usable battery capacity per node (J).
+    pub capacity_j: f64,
+    /// State of charge assumed for nodes not yet seen, in [0, 1].
+    pub initial_soc: f64,
+    /// Actuation is refused at or below this state of charge.
+    pub reserve_soc: f64,
+    /// Instantaneous power limit (W).
+    pub p_max_w: f64,
+    /// Step duration used to integrate charge (s).
+    pub step_dt_s: f64,
+}
+
+/// Host budget for battery + solar field nodes.
+///
+/// Tracks state of charge per `machine_id`. The caller advances it once per
+/// step with `update`; budget checks read the last integrated state.
+#[derive(Debug, Clone)]
+pub struct BatteryHostBudget {
+    pub config: BatteryConfig,
+    soc: HashMap<String, f64>,
+}
+
+impl BatteryHostBudget {
+    pub fn new(config: BatteryConfig) -> Self {
+        BatteryHostBudget {
+            config,
+            soc: HashMap::new(),
+        }
+    }
+
+    /// Current state of charge for `machine_id`.
+    pub fn soc(&self, machine_id: &str) -> f64 {
+        self.soc
+            .get(machine_id)
+            .copied()
+            .unwrap_or(self.config.initial_soc)
+    }
+
+    /// Override the state of charge, e.g. from a telemetry reading.
+    pub fn set_soc(&mut self, machine_id: &str, soc: f64) {
+        self.soc.insert(machine_id.to_string(), soc.clamp(0.0, 1.0));
+    }
+
+    /// Integrate one step: each node discharges at `power_w` and charges at
+    /// `charge_w(machine_id)` (solar input, W).
+    pub fn update<F>(&mut self, nodes: &[NodeState], charge_w: F)
+    where
+        F: Fn(&str) -> f64,
+    {
+        for node in nodes {
+            let id = node.row.machine_id.as_str();
+            let net_j = (charge_w(id).max(0.0) - node.power_w) * self.config.step_dt_s;
+            let soc = if self.config.capacity_j > 0.0 {
+                self.soc(id) + net_j / self.config.capacity_j
+            } else {
+                0.0
+            };
+            self.set_soc(id, soc);
+        }
+    }
+
+    /// Charge above the reserve, normalized to [0, 1].
+    fn headroom(&self, machine_id: &str) -> f64 {
+        let span = 1.0 - self.config.reserve_soc;
+        if span <= 0.0 {
+            return 0.0;
+        }
+        ((self.soc(machine_id) - self.config.reserve_soc) / span).clamp(0.0, 1.0)
+    }
+}
+
+impl HostBudget for BatteryHostBudget {
+    fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError> {
+        match self.host_budget_violations(node).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn host_budget_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        let id = &node.row.machine_id;
+        let mut violations = Vec::new();
+        if node.power_w > self.config.p_max_w {
+            violations.push(SafetyError::HostBudgetExceeded {
+                machine_id: id.clone(),
+                power_w: node.power_w,
+                p_max_w: self.config.p_max_w,
+            });
+        }
+        let soc = self.soc(id);
+        if soc <= self.config.reserve_soc {
+            violations.push(SafetyError::BatteryReserve {
+                machine_id: id.clone(),
+                soc,
+                reserve_soc: self.config.reserve_soc,
+            });
+        }
+        violations
+    }
+
+    /// P/P_max divided by the charge headroom, so low-battery nodes are
+    /// throttled harder through the controller's eta_p term.
+    fn power_fraction(&self, node: &NodeState) -> f64 {
+        if self.config.p_max_w <= 0.0 {
+            return 0.0;
+        }
+        let base = (node.power_w / self.config.p_max_w).max(0.0);
+        base / self.headroom(&node.row.machine_id).max(MIN_HEADROOM)
+    }
+
+    fn budget_state(&self) -> BudgetState {
+        BudgetState {
+            battery_soc: Some(
+                self.soc
+                    .iter()
+                    .map(|(id, &soc)| (id.clone(), soc))
+                    .collect(),
+            ),
+            ..BudgetState::default()
+        }
+    }
+
+    fn restore_budget_state(&mut self, state: &BudgetState) -> Result<(), SnapshotError> {
+        let soc = state.battery_soc.as_ref().ok_or(SnapshotError::Missing {
+            component: "battery state of charge",
+        })?;
+        self.soc = soc.iter().map(|(id, &soc)| (id.clone(), soc)).collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+
+    fn field_config() -> BatteryConfig {
+        BatteryConfig {
+            // 100 Wh pack.
+            capacity_j: 360_000.0,
+            initial_soc: 0.5,
+            reserve_soc: 0.2,
+            p_max_w: 150.0,
+            step_dt_s: 300.0,
+        }
+    }
+
+    #[test]
+    fn draining_battery_trips_at_reserve() {
+        let mut budget = BatteryHostBudget::new(field_config());
+        let nodes = phoenix_nodes();
+        let node = &nodes[0];
+
+        // 50 W for 300 s drains 15 kJ, about 0.0417 SoC per step.
+        let mut steps = 0;
+        let mut last_fraction = budget.power_fraction(node);
+        while budget.check_host_budget(node).is_ok() {
+            budget.update(&nodes, |_| 0.0);
+            steps += 1;
+            let fraction = budget.power_fraction(node);
+            assert!(fraction >= last_fraction);
+            last_fraction = fraction;
+            assert!(steps < 100, "battery never reached reserve");
+        }
+        // 0.5 -> 0.2 takes 7.2 steps of drain.
+        assert_eq!(steps, 8);
+        match budget.check_host_budget(node) {
+            Err(SafetyError::BatteryReserve {
+                machine_id,
+                soc,
+                reserve_soc,
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-CANOPY-01");
+                assert!(soc <= 0.2);
+                assert_eq!(reserve_soc, 0.2);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // Solar input above the draw recharges past the reserve.
+        budget.update(&nodes, |_| 200.0);
+        assert!(budget.check_host_budget(node).is_ok());
+    }
+
+    #[test]
+    fn low_charge_scales_power_fraction() {
+        let mut budget = BatteryHostBudget::new(field_config());
+        let node = &phoenix_nodes()[0];
+        budget.set_soc("CYB-AIR-CANOPY-01", 1.0);
+        let full = budget.power_fraction(node);
+        assert!((full - 50.0 / 150.0).abs() < 1e-12);
+
+        budget.set_soc("CYB-AIR-CANOPY-01", 0.28);
+        assert!((budget.power_fraction(node) - 10.0 * full).abs() < 1e-9);
+    }
+
+    #[test]
+    fn config_round_trips_through_json() {
+        let cfg = field_config();
+        let json = serde_json::to_string(&cfg).unwrap();
+        let back: BatteryConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, cfg);
+    }
+}
@@ -0,0 +1,324 @@
+//! Everything one corridor is configured with, in one document.
+//!
+//! A [`CorridorBundle`] holds the controller config (with its eco-band
+//! thresholds, gain schedule and duty schedule), the machine topology, the
+//! bee-rights polytopes and the hive sites of one corridor, as TOML or
+//! JSON. Loading validates the parts against each other as well as on
+//! their own, so a bundle that loads is one the corridor can run with.
+//!
+//! Polytopes and hives are kept as plain data here; the bee crates build
+//! their own types from them.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    AltitudeProvider, BeeGuard, ConfigError, ControllerConfig, Coordinates, CorridorController,
+    CorridorMembership, CorridorTopology, MetricsRecorder, RectSafetyEnvelope, SimpleDwCeiling,
+    SimpleHostBudget, ThresholdEcoBand,
+};
+
+/// One half-space a·x + b <= 0 over a polytope's axes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConstraintRow {
+    pub a: Vec<f64>,
+    pub b: f64,
+}
+
+/// A bee-rights polytope over named axes, e.g. distance from hive, O3,
+/// EMF and duty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolytopeConfig {
+    pub name: String,
+    pub axes: Vec<String>,
+    pub constraints: Vec<ConstraintRow>,
+}
+
+/// A known hive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HiveSite {
+    pub id: String,
+    #[serde(flatten)]
+    pub coordinates: Coordinates,
+}
+
+/// Per-corridor configuration; see the module docs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CorridorBundle {
+    /// The corridor in `topology` this bundle configures.
+    pub corridor: String,
+    pub controller: ControllerConfig,
+    pub topology: CorridorTopology,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bee_polytopes: Vec<PolytopeConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hives: Vec<HiveSite>,
+}
+
+/// A bundle swapped in, and the fields that changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub corridor: String,
+    /// Dotted paths of the fields that differ from the previous bundle,
+    /// e.g. `controller.gains.eta_p`; `[""]`, the whole document, if
+    /// there was none.
+    pub changed: Vec<String>,
+}
+
+impl CorridorBundle {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let bundle: Self = toml::from_str(s).map_err(|e| ConfigError::Toml(e.to_string()))?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, ConfigError> {
+        let bundle: Self = serde_json::from_str(s).map_err(|e| ConfigError::Json(e.to_string()))?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// Load `path`: JSON if it ends in `.json`, TOML otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {e}", path.display())))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// The controller config's own checks, then the cross-references: the
+    /// corridor has machines, every node the gain and duty schedules name
+    /// is one of them, polytope rows have one coefficient per axis, and
+    /// hive ids are unique.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.controller.validate()?;
+
+        let membership = self.membership();
+        if membership.machines.is_empty() {
+            return Err(invalid(
+                "topology",
+                format!("no machines in corridor {}", self.corridor),
+            ));
+        }
+        let scheduled = self
+            .controller
+            .gain_schedule
+            .nodes
+            .keys()
+            .map(|id| ("gain_schedule", id))
+            .chain(
+                self.controller
+                    .duty_schedule
+                    .iter()
+                    .flat_map(|s| s.utc_offset_minutes.keys())
+                    .map(|id| ("duty_schedule", id)),
+            );
+        for (field, id) in scheduled {
+            if !membership.contains(id) {
+                return Err(invalid(
+                    field,
+                    format!("{id} is not in corridor {}", self.corridor),
+                ));
+            }
+        }
+
+        for polytope in &self.bee_polytopes {
+            if polytope.axes.is_empty() {
+                return Err(invalid(
+                    "bee_polytopes",
+                    format!("{} has no axes", polytope.name),
+                ));
+            }
+            for (i, row) in polytope.constraints.iter().enumerate() {
+                if row.a.len() != polytope.axes.len() {
+                    return Err(invalid(
+                        "bee_polytopes",
+                        format!(
+                            "{} constraint {i} has {} coefficients for {} axes",
+                            polytope.name,
+                            row.a.len(),
+                            polytope.axes.len()
+                        ),
+                    ));
+                }
+                if !row.a.iter().chain([&row.b]).all(|v| v.is_finite()) {
+                    return Err(invalid(
+                        "bee_polytopes",
+                        format!("{} constraint {i} is not finite", polytope.name),
+                    ));
+                }
+            }
+        }
+
+        let mut ids = BTreeSet::new();
+        for hive in &self.hives {
+            let Coordinates { lat_deg, lon_deg } = hive.coordinates;
+            if !((-90.0..=90.0).contains(&lat_deg) && (-180.0..=180.0).contains(&lon_deg)) {
+                return Err(invalid(
+                    "hives",
+                    format!("{} is at ({lat_deg}, {lon_deg})", hive.id),
+                ));
+            }
+            if !ids.insert(&hive.id) {
+                return Err(invalid("hives", format!("{} is listed twice", hive.id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Machines `topology` places in `corridor`.
+    pub fn membership(&self) -> CorridorMembership {
+        self.topology.membership(&self.corridor)
+    }
+
+    /// Validate and build a controller restricted to the corridor.
+    pub fn build<A: AltitudeProvider>(
+        &self,
+        altitude: A,
+    ) -> Result<
+        CorridorController<
+            RectSafetyEnvelope<A>,
+            SimpleHostBudget,
+            ThresholdEcoBand,
+            SimpleDwCeiling,
+        >,
+        ConfigError,
+    > {
+        self.validate()?;
+        let mut controller = self.controller.build(altitude)?;
+        controller.corridor = Some(self.membership());
+        Ok(controller)
+    }
+
+    /// Validate, then reconfigure `controller` as `build` would and
+    /// restrict it to the corridor; see [`ControllerConfig::apply`]. On
+    /// error the controller is unchanged.
+    pub fn apply<A, G, R>(
+        &self,
+        controller: &mut CorridorController<
+            RectSafetyEnvelope<A>,
+            SimpleHostBudget,
+            ThresholdEcoBand,
+            SimpleDwCeiling,
+            G,
+            R,
+        >,
+    ) -> Result<(), ConfigError>
+    where
+        A: AltitudeProvider,
+        G: BeeGuard,
+        R: MetricsRecorder,
+    {
+        self.validate()?;
+        self.controller.apply(controller)?;
+        controller.corridor = Some(self.membership());
+        Ok(())
+    }
+
+    /// Dotted paths of the fields that differ between `self` and `next`.
+    /// Objects are compared field by field; arrays and values whole.
+    pub fn diff(&self, next: &CorridorBundle) -> Vec<String> {
+        let value = |b: &CorridorBundle| serde_json::to_value(b).unwrap_or(Value::Null);
+        let mut changed = Vec::new();
+        diff_values("", &value(self), &value(next), &mut changed);
+        changed
+    }
+}
+
+fn invalid(field: &'static str, reason: String) -> ConfigError {
+    ConfigError::Invalid { field, reason }
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changed: &mut Vec<String>) {
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        if old != new {
+            changed.push(path.to_string());
+        }
+        return;
+    };
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for key in keys {
+        let child = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        match (old.get(key), new.get(key)) {
+            (Some(a), Some(b)) => diff_values(&child, a, b, changed),
+            _ => changed.push(child),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConstAltitude;
+
+    const PHOENIX: &str = include_str!("../config/phoenix_bundle.toml");
+
+    #[test]
+    fn phoenix_bundle_loads_and_builds_a_corridor_controller() {
+        let bundle = CorridorBundle::from_toml(PHOENIX).unwrap();
+        assert_eq!(bundle.membership().machines.len(), 2);
+        assert_eq!(bundle.bee_polytopes[0].axes.len(), 4);
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert_eq!(CorridorBundle::from_json(&json).unwrap(), bundle);
+
+        let controller = bundle.build(ConstAltitude(331.0)).unwrap();
+        assert!(controller
+            .corridor
+            .as_ref()
+            .unwrap()
+            .contains("CYB-AIR-SCHOOL-05"));
+        assert_eq!(bundle.diff(&bundle), Vec::<String>::new());
+    }
+
+    #[test]
+    fn cross_references_are_checked() {
+        let bundle = CorridorBundle::from_toml(PHOENIX).unwrap();
+        let field = |b: &CorridorBundle| match b.validate() {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            other => panic!("{other:?}"),
+        };
+
+        let mut b = bundle.clone();
+        b.controller
+            .gain_schedule
+            .nodes
+            .insert("CYB-AIR-GHOST-99".into(), b.controller.gains.clone());
+        assert_eq!(field(&b), "gain_schedule");
+
+        let mut b = bundle.clone();
+        b.bee_polytopes[0].constraints[1].a.pop();
+        assert_eq!(field(&b), "bee_polytopes");
+
+        let mut b = bundle.clone();
+        b.hives.push(b.hives[0].clone());
+        assert_eq!(field(&b), "hives");
+
+        let mut b = bundle.clone();
+        b.corridor = "tucson-b".into();
+        assert_eq!(field(&b), "topology");
+
+        let mut b = bundle.clone();
+        b.controller.eco_band.theta_amber_red = 0.1;
+        assert_eq!(field(&b), "eco_band.theta_amber_red");
+
+        let mut next = bundle.clone();
+        next.controller.gains.eta_p = 0.2;
+        next.hives.clear();
+        assert_eq!(bundle.diff(&next), ["controller.gains.eta_p", "hives"]);
+    }
+}
@@ -0,0 +1,304 @@
+//! Sensor calibration for `cin`/`cout` before CEIM mass.
+//!
+//! Low-cost sensors read `raw = true / span + offset`: a zero offset plus a
+//! span that drifts over time. [`CalibrationParams::correct`] inverts that,
+//! `true = span * (raw - offset)`, and [`DriftEstimator`] re-fits the span
+//! against a co-located reference instrument, moving it by at most a fixed
+//! fraction per day since the last calibration so one noisy comparison
+//! cannot swing the mass and karma of a whole corridor.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::CorridorRow;
+
+const DAY_MS: f64 = 86_400_000.0;
+
+/// Why a calibration table or drift update could not be made.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CalibrationError {
+    #[error("calibration json error: {0}")]
+    Json(String),
+    #[error("{pairs} reference pairs, need at least {min}")]
+    TooFewPairs { pairs: usize, min: usize },
+    /// Offset-corrected readings are all zero, or a value is not finite.
+    #[error("reference pairs do not determine a span")]
+    Degenerate,
+}
+
+/// Zero offset and span for one machine's concentration sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CalibrationParams {
+    /// Reading at zero concentration, in the row's unit.
+    pub offset: f64,
+    /// True concentration per unit of offset-corrected reading.
+    pub span: f64,
+    /// Unix milliseconds of the last calibration or span update.
+    pub last_calibrated_unix_ms: u64,
+}
+
+impl CalibrationParams {
+    /// Corrected concentration for `raw`; never negative.
+    pub fn correct(&self, raw: f64) -> f64 {
+        (self.span * (raw - self.offset)).max(0.0)
+    }
+
+    /// Time since the last calibration, 0 if it lies in the future.
+    pub fn age_ms(&self, now_unix_ms: u64) -> u64 {
+        now_unix_ms.saturating_sub(self.last_calibrated_unix_ms)
+    }
+}
+
+/// `row` with `cin` and `cout` corrected by `params`.
+pub fn apply_calibration(row: &CorridorRow, params: &CalibrationParams) -> CorridorRow {
+    CorridorRow {
+        cin: params.correct(row.cin),
+        cout: params.correct(row.cout),
+        ..row.clone()
+    }
+}
+
+/// A machine whose calibration is older than the table allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleCalibration {
+    pub machine_id: String,
+    pub age_ms: u64,
+}
+
+/// Calibration per `machine_id`, e.g. from a JSON file:
+///
+/// ```json
+/// {"max_age_ms": 2592000000,
+///  "machines": {"CYB-AIR-CANOPY-01":
+///    {"offset": 1.5, "span": 1.04, "last_calibrated_unix_ms": 1760000000000}}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CalibrationTable {
+    pub machines: BTreeMap<String, CalibrationParams>,
+    /// Calibrations older than this are reported by `stale`; `None` never.
+    #[serde(default)]
+    pub max_age_ms: Option<u64>,
+}
+
+impl CalibrationTable {
+    pub fn from_json(s: &str) -> Result<Self, CalibrationError> {
+        serde_json::from_str(s).map_err(|e| CalibrationError::Json(e.to_string()))
+    }
+
+    pub fn get(&self, machine_id: &str) -> Option<&CalibrationParams> {
+        self.machines.get(machine_id)
+    }
+
+    /// `row` calibrated with its machine's parameters, or `None` if the
+    /// machine has none.
+    pub fn calibrate(&self, row: &CorridorRow) -> Option<CorridorRow> {
+        self.get(&row.machine_id)
+            .map(|params| apply_calibration(row, params))
+    }
+
+    /// Machines calibrated more than `max_age_ms` before `now_unix_ms`.
+    pub fn stale(&self, now_unix_ms: u64) -> Vec<StaleCalibration> {
+        let Some(max_age_ms) = self.max_age_ms else {
+            return Vec::new();
+        };
+        self.machines
+            .iter()
+            .map(|(id, params)| (id, params.age_ms(now_unix_ms)))
+            .filter(|&(_, age_ms)| age_ms > max_age_ms)
+            .map(|(id, age_ms)| StaleCalibration {
+                machine_id: id.clone(),
+                age_ms,
+            })
+            .collect()
+    }
+}
+
+/// Outcome of one `DriftEstimator::update`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftUpdate {
+    /// Least-squares span from the reference pairs.
+    pub estimated_span: f64,
+    /// Span now in the parameters.
+    pub applied_span: f64,
+    /// True if the per-day bound held the span back from the estimate.
+    pub limited: bool,
+}
+
+/// Re-fits a sensor's span against a co-located reference instrument.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DriftEstimator {
+    /// Largest relative span change per day since the last calibration,
+    /// e.g. 0.02 for 2 %.
+    pub max_span_change_per_day: f64,
+    /// Fewest overlapping readings an update needs.
+    pub min_pairs: usize,
+}
+
+impl DriftEstimator {
+    /// Fit `span` to `pairs` of (raw reading, reference concentration)
+    /// taken together, keeping the offset, and move `params.span` toward
+    /// it by at most `max_span_change_per_day` per elapsed day. On success
+    /// the parameters count as calibrated at `now_unix_ms`.
+    pub fn update(
+        &self,
+        params: &mut CalibrationParams,
+        pairs: &[(f64, f64)],
+        now_unix_ms: u64,
+    ) -> Result<DriftUpdate, CalibrationError> {
+        if pairs.len() < self.min_pairs.max(1) {
+            return Err(CalibrationError::TooFewPairs {
+                pairs: pairs.len(),
+                min: self.min_pairs.max(1),
+            });
+        }
+        // Least squares through the origin: reference = span * (raw - offset).
+        let (mut xy, mut xx) = (0.0, 0.0);
+        for &(raw, reference) in pairs {
+            let x = raw - params.offset;
+            xy += x * reference;
+            xx += x * x;
+        }
+        let estimated_span = xy / xx;
+        if !(estimated_span.is_finite() && xx > 0.0) {
+            return Err(CalibrationError::Degenerate);
+        }
+
+        let days = params.age_ms(now_unix_ms) as f64 / DAY_MS;
+        let bound = self.max_span_change_per_day.max(0.0) * days * params.span.abs();
+        let applied_span = estimated_span.clamp(params.span - bound, params.span + bound);
+        params.span = applied_span;
+        params.last_calibrated_unix_ms = now_unix_ms;
+        Ok(DriftUpdate {
+            estimated_span,
+            applied_span,
+            limited: applied_span != estimated_span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+    use crate::{compute_mass_kg, Pollutant};
+
+    const T0: u64 = 1_760_000_000_000;
+
+    /// What a sensor with `params` reads at true concentration `c`.
+    fn reading(params: &CalibrationParams, c: f64) -> f64 {
+        c / params.span + params.offset
+    }
+
+    #[test]
+    fn calibration_removes_sensor_bias() {
+        let truth = phoenix_nodes()[0].row.clone();
+        let sensor = CalibrationParams {
+            offset: 2.5,
+            span: 1.25,
+            last_calibrated_unix_ms: T0,
+        };
+        let mut raw = truth.clone();
+        raw.cin = reading(&sensor, truth.cin);
+        raw.cout = reading(&sensor, truth.cout);
+
+        let table = CalibrationTable::from_json(&format!(
+            r#"{{"machines": {{"{}": {}}}}}"#,
+            truth.machine_id,
+            serde_json::to_string(&sensor).unwrap()
+        ))
+        .unwrap();
+        let fixed = table.calibrate(&raw).unwrap();
+        assert!((fixed.cin - truth.cin).abs() < 1e-9);
+        assert!((fixed.cout - truth.cout).abs() < 1e-9);
+
+        let mass = |row: &CorridorRow| compute_mass_kg(row, Pollutant::PM25, 310.0).unwrap();
+        let true_mass = mass(&truth);
+        assert!((mass(&raw) - true_mass).abs() / true_mass > 0.1);
+        assert!((mass(&fixed) - true_mass).abs() / true_mass < 1e-9);
+
+        let mut other = raw.clone();
+        other.machine_id = "CYB-AIR-UNKNOWN".into();
+        assert!(table.calibrate(&other).is_none());
+    }
+
+    #[test]
+    fn span_update_is_bounded_per_day() {
+        // The sensor's true span drifted from 1.0 to 1.1.
+        let drifted = CalibrationParams {
+            offset: 1.0,
+            span: 1.1,
+            last_calibrated_unix_ms: T0,
+        };
+        let pairs: Vec<(f64, f64)> = [10.0, 20.0, 35.0, 50.0]
+            .iter()
+            .map(|&c| (reading(&drifted, c), c))
+            .collect();
+        let estimator = DriftEstimator {
+            max_span_change_per_day: 0.02,
+            min_pairs: 3,
+        };
+        let mut params = CalibrationParams {
+            span: 1.0,
+            ..drifted
+        };
+
+        // One day in: the 10 % drift is cut to 2 %.
+        let day = DAY_MS as u64;
+        let update = estimator.update(&mut params, &pairs, T0 + day).unwrap();
+        assert!((update.estimated_span - 1.1).abs() < 1e-12);
+        assert!(update.limited);
+        assert!((params.span - 1.02).abs() < 1e-12);
+        assert_eq!(params.last_calibrated_unix_ms, T0 + day);
+
+        // Ten more days allow the rest.
+        let update = estimator
+            .update(&mut params, &pairs, T0 + 11 * day)
+            .unwrap();
+        assert!(!update.limited);
+        assert!((params.span - 1.1).abs() < 1e-12);
+
+        assert_eq!(
+            estimator.update(&mut params, &pairs[..2], T0 + 12 * day),
+            Err(CalibrationError::TooFewPairs { pairs: 2, min: 3 })
+        );
+        assert_eq!(
+            estimator.update(&mut params, &[(1.0, 0.0); 3], T0 + 12 * day),
+            Err(CalibrationError::Degenerate)
+        );
+    }
+
+    #[test]
+    fn stale_calibrations_are_reported() {
+        let params = |last_calibrated_unix_ms| CalibrationParams {
+            offset: 0.0,
+            span: 1.0,
+            last_calibrated_unix_ms,
+        };
+        let day = DAY_MS as u64;
+        let table = CalibrationTable {
+            machines: [
+                ("CYB-AIR-FRESH".to_string(), params(T0 - day)),
+                ("CYB-AIR-OLD".to_string(), params(T0 - 40 * day)),
+            ]
+            .into(),
+            max_age_ms: Some(30 * day),
+        };
+        assert_eq!(
+            table.stale(T0),
+            [StaleCalibration {
+                machine_id: "CYB-AIR-OLD".into(),
+                age_ms: 40 * day
+            }]
+        );
+        let unbounded = CalibrationTable {
+            max_age_ms: None,
+            ..table
+        };
+        assert!(unbounded.stale(T0).is_empty());
+    }
+}
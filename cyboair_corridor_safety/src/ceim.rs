@@ -0,0 +1,392 @@
+//! Reference CEIM math.
+//!
+//! The one implementation of mass, karma, S_bee and ecoimpact. Every crate
+//! that computes these quantities delegates here, and checks its public
+//! entry points against the reference tables below with [`assert_agree`],
+//! so two call sites can only drift apart by failing a test.
+//!
+//! | Quantity | Formula |
+//! |---|---|
+//! | Mass, kg | `M = alpha * max(cin - cout, 0) * Q * t` |
+//! | Karma, NanoKarmaBytes | `K = lambda * beta * M` |
+//! | Bee karma | `K_bee = w * lambda_bee * beta_bee * M` |
+//! | S_bee | `1 - exp(-alpha * K_bee / max(K_ref, 1))`, exponent clamped to ±50 |
+//! | Ecoimpact | `S = 1 - exp(-alpha * (w_m * M / M0 + w_k * K / K0))`, clamped to [0, 1] |
+//!
+//! `alpha` in the mass row is the unit factor to kg/m^3 from
+//! [`units`](crate::units): exact for mass units, `p * MW / (R * T) * scale`
+//! for mixing ratios, with [`R_GAS`] and, unless a pressure is given,
+//! [`P_STANDARD_PA`]. Products are evaluated left to right in the order
+//! written, and callers must not reorder them: agreement is checked bit
+//! for bit.
+
+pub use crate::units::{P_STANDARD_PA, R_GAS};
+
+use crate::pollutant::Pollutant;
+use crate::units::ConcentrationUnit;
+use crate::CorridorRow;
+
+/// Mass removed, kg, from a concentration drop in units whose kg/m^3
+/// factor is `kg_per_m3`. A node that adds pollutant removes nothing.
+pub fn mass_kg(cin: f64, cout: f64, kg_per_m3: f64, airflow_m3_per_s: f64, period_s: f64) -> f64 {
+    kg_per_m3 * (cin - cout).max(0.0) * airflow_m3_per_s * period_s
+}
+
+/// Hazard-weighted NanoKarmaBytes.
+pub fn karma_bytes(lambda_hazard: f64, beta_nb_per_kg: f64, mass_kg: f64) -> f64 {
+    lambda_hazard * beta_nb_per_kg * mass_kg
+}
+
+/// Bee NanoKarmaBytes, with the microspace weight `weight` (1 outside).
+pub fn bee_karma_bytes(weight: f64, lambda_bee: f64, beta_bee: f64, mass_kg: f64) -> f64 {
+    weight * lambda_bee * beta_bee * mass_kg
+}
+
+/// Normalized bee stress S_bee in [0, 1). References below 1 count as 1.
+pub fn s_bee(bee_karma_bytes: f64, kref_bee: f64, alpha: f64) -> f64 {
+    let x = -alpha * (bee_karma_bytes / kref_bee.max(1.0));
+    1.0 - x.clamp(-50.0, 50.0).exp()
+}
+
+/// Weighted, normalized load fed to [`ecoimpact`].
+pub fn ecoimpact_load(
+    mass_kg: f64,
+    mass_ref_kg: f64,
+    w_mass: f64,
+    karma_bytes: f64,
+    karma_ref: f64,
+    w_karma: f64,
+) -> f64 {
+    w_mass * mass_kg / mass_ref_kg + w_karma * karma_bytes / karma_ref
+}
+
+/// Ecoimpact index for `load`, clamped to [0, 1].
+pub fn ecoimpact(load: f64, alpha: f64) -> f64 {
+    (1.0 - (-alpha * load).exp()).clamp(0.0, 1.0)
+}
+
+/// One reference shard row, with its air conditions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceRow {
+    pub name: &'static str,
+    pub pollutant: Pollutant,
+    pub unit: ConcentrationUnit,
+    pub cin: f64,
+    pub cout: f64,
+    pub airflow_m3_per_s: f64,
+    pub period_s: f64,
+    pub temperature_k: f64,
+    pub pressure_pa: f64,
+    pub lambda_hazard: f64,
+    pub beta_nb_per_kg: f64,
+    /// Bee microspace weight; 1 outside a microspace.
+    pub bee_weight: f64,
+}
+
+/// Rows covering every unit family, a non-standard pressure and an
+/// outlet above the inlet.
+pub const REFERENCE_ROWS: &[ReferenceRow] = &[
+    ReferenceRow {
+        name: "pm25-canopy",
+        pollutant: Pollutant::PM25,
+        unit: ConcentrationUnit::UgPerM3,
+        cin: 40.0,
+        cout: 28.0,
+        airflow_m3_per_s: 3.0,
+        period_s: 3600.0,
+        temperature_k: 310.0,
+        pressure_pa: P_STANDARD_PA,
+        lambda_hazard: 3.0,
+        beta_nb_per_kg: 5.0e8,
+        bee_weight: 1.0,
+    },
+    ReferenceRow {
+        name: "o3-ppb-at-altitude",
+        pollutant: Pollutant::O3,
+        unit: ConcentrationUnit::Ppb,
+        cin: 62.0,
+        cout: 41.5,
+        airflow_m3_per_s: 2.5,
+        period_s: 1800.0,
+        temperature_k: 305.15,
+        pressure_pa: 97_400.0,
+        lambda_hazard: 2.0,
+        beta_nb_per_kg: 1.0e9,
+        bee_weight: 1.7,
+    },
+    ReferenceRow {
+        name: "no2-ppm",
+        pollutant: Pollutant::NO2,
+        unit: ConcentrationUnit::Ppm,
+        cin: 0.12,
+        cout: 0.07,
+        airflow_m3_per_s: 1.2,
+        period_s: 900.0,
+        temperature_k: 298.15,
+        pressure_pa: P_STANDARD_PA,
+        lambda_hazard: 1.5,
+        beta_nb_per_kg: 2.0e9,
+        bee_weight: 1.0,
+    },
+    ReferenceRow {
+        name: "voc-ng-microspace",
+        pollutant: Pollutant::VOC,
+        unit: ConcentrationUnit::NgPerM3,
+        cin: 850.0,
+        cout: 310.0,
+        airflow_m3_per_s: 0.4,
+        period_s: 600.0,
+        temperature_k: 301.0,
+        pressure_pa: P_STANDARD_PA,
+        lambda_hazard: 4.0,
+        beta_nb_per_kg: 7.5e8,
+        bee_weight: 2.5,
+    },
+    ReferenceRow {
+        name: "pm10-inverted-outlet",
+        pollutant: Pollutant::PM10,
+        unit: ConcentrationUnit::MgPerM3,
+        cin: 0.05,
+        cout: 0.06,
+        airflow_m3_per_s: 5.0,
+        period_s: 3600.0,
+        temperature_k: 315.0,
+        pressure_pa: P_STANDARD_PA,
+        lambda_hazard: 1.0,
+        beta_nb_per_kg: 3.0e8,
+        bee_weight: 1.0,
+    },
+];
+
+impl ReferenceRow {
+    /// Molar mass for the row's unit conversion; particulates, which never
+    /// need one, get 1 kg/mol.
+    pub fn molar_mass(&self) -> f64 {
+        self.pollutant.molar_mass().unwrap_or(1.0)
+    }
+
+    pub fn kg_per_m3(&self) -> f64 {
+        self.unit
+            .kg_per_m3_factor_at_pressure(self.temperature_k, self.pressure_pa, self.molar_mass())
+            .expect("reference rows convert")
+    }
+
+    pub fn mass_kg(&self) -> f64 {
+        mass_kg(
+            self.cin,
+            self.cout,
+            self.kg_per_m3(),
+            self.airflow_m3_per_s,
+            self.period_s,
+        )
+    }
+
+    pub fn karma_bytes(&self) -> f64 {
+        karma_bytes(self.lambda_hazard, self.beta_nb_per_kg, self.mass_kg())
+    }
+
+    /// Bee karma from the pollutant's bee hazard table.
+    pub fn bee_karma_bytes(&self) -> f64 {
+        bee_karma_bytes(
+            self.bee_weight,
+            self.pollutant.bee_lambda(),
+            self.pollutant.bee_beta_nb_per_kg(),
+            self.mass_kg(),
+        )
+    }
+
+    /// The row as the corridor controller loads it.
+    pub fn corridor_row(&self) -> CorridorRow {
+        CorridorRow {
+            machine_id: self.name.into(),
+            r#type: "Reference".into(),
+            location: "Reference".into(),
+            pollutant: self.pollutant.as_str().into(),
+            cin: self.cin,
+            cout: self.cout,
+            unit: self.unit.as_str().into(),
+            airflow_m3_per_s: self.airflow_m3_per_s,
+            period_s: self.period_s,
+            lambda_hazard: self.lambda_hazard,
+            beta_nb_per_kg: self.beta_nb_per_kg,
+            ecoimpact_score: 0.9,
+            trace_id: None,
+            shard_version: None,
+        }
+    }
+}
+
+/// `(bee_karma_bytes, kref_bee, alpha)` for S_bee, including a reference
+/// below 1 and an exponent past the clamp.
+pub const REFERENCE_S_BEE: &[(f64, f64, f64)] = &[
+    (0.0, 1.0e12, 1.0),
+    (4.2e11, 1.0e12, 1.0),
+    (3.0e12, 1.0e12, 0.5),
+    (10.0, 0.5, 2.0),
+    (1.0e20, 1.0, 1.0),
+];
+
+/// Inputs and weights for the ecoimpact index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceEcoimpact {
+    pub mass_kg: f64,
+    pub karma_bytes: f64,
+    pub alpha: f64,
+    pub mass_ref_kg: f64,
+    pub karma_ref: f64,
+    pub w_mass: f64,
+    pub w_karma: f64,
+}
+
+impl ReferenceEcoimpact {
+    pub fn ecoimpact(&self) -> f64 {
+        let load = ecoimpact_load(
+            self.mass_kg,
+            self.mass_ref_kg,
+            self.w_mass,
+            self.karma_bytes,
+            self.karma_ref,
+            self.w_karma,
+        );
+        ecoimpact(load, self.alpha)
+    }
+}
+
+/// Karma-only, mixed and saturated ecoimpact inputs.
+pub const REFERENCE_ECOIMPACT: &[ReferenceEcoimpact] = &[
+    ReferenceEcoimpact {
+        mass_kg: 0.0,
+        karma_bytes: 2.4e5,
+        alpha: 1.0,
+        mass_ref_kg: 1.0,
+        karma_ref: 1.0e6,
+        w_mass: 0.0,
+        w_karma: 1.0,
+    },
+    ReferenceEcoimpact {
+        mass_kg: 3.24e-5,
+        karma_bytes: 4.86e4,
+        alpha: 0.8,
+        mass_ref_kg: 1.0e-4,
+        karma_ref: 1.0e5,
+        w_mass: 0.5,
+        w_karma: 0.5,
+    },
+    ReferenceEcoimpact {
+        mass_kg: 2.0,
+        karma_bytes: 1.0e12,
+        alpha: 3.0,
+        mass_ref_kg: 1.0e-3,
+        karma_ref: 1.0e6,
+        w_mass: 0.3,
+        w_karma: 0.7,
+    },
+];
+
+/// Panic unless every `(call_site, value)` agrees bit for bit, naming the
+/// first two call sites that disagree.
+pub fn assert_agree(quantity: &str, case: &str, sites: &[(&str, f64)]) {
+    let Some(&(first, expected)) = sites.first() else {
+        return;
+    };
+    for &(site, value) in &sites[1..] {
+        assert!(
+            value.to_bits() == expected.to_bits(),
+            "{quantity} for {case}: {site} = {value:e} but {first} = {expected:e}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::airflow::{compute_mass_kg_at_duty, AirflowModel};
+    use crate::removal::{net_mass_removed_at_pressure, ZeroBaseline};
+    use crate::{
+        checked_karma_bytes, checked_mass_kg, checked_mass_kg_at_pressure, compute_karma_bytes,
+        compute_mass_kg, compute_mass_kg_at_pressure, units,
+    };
+
+    #[test]
+    fn corridor_entry_points_match_the_reference() {
+        for case in REFERENCE_ROWS {
+            let row = case.corridor_row();
+            let (p, t, pa) = (case.pollutant, case.temperature_k, case.pressure_pa);
+            let mut mass = vec![
+                ("ceim::mass_kg", case.mass_kg()),
+                (
+                    "compute_mass_kg_at_pressure",
+                    compute_mass_kg_at_pressure(&row, p, t, pa).unwrap(),
+                ),
+                (
+                    "checked_mass_kg_at_pressure",
+                    checked_mass_kg_at_pressure(&row, p, t, pa).unwrap(),
+                ),
+                (
+                    "net_mass_removed_at_pressure",
+                    net_mass_removed_at_pressure(&row, p, t, pa, &ZeroBaseline)
+                        .unwrap()
+                        .gross_kg,
+                ),
+                (
+                    "units::unit_to_kg_factor_at_pressure",
+                    mass_kg(
+                        case.cin,
+                        case.cout,
+                        units::unit_to_kg_factor_at_pressure(
+                            case.unit.as_str(),
+                            t,
+                            pa,
+                            case.molar_mass(),
+                        )
+                        .unwrap(),
+                        case.airflow_m3_per_s,
+                        case.period_s,
+                    ),
+                ),
+            ];
+            if pa == P_STANDARD_PA {
+                mass.extend([
+                    ("compute_mass_kg", compute_mass_kg(&row, p, t).unwrap()),
+                    ("checked_mass_kg", checked_mass_kg(&row, p, t).unwrap()),
+                    (
+                        "compute_mass_kg_at_duty",
+                        compute_mass_kg_at_duty(&row, 1.0, &AirflowModel::Linear, p, t).unwrap(),
+                    ),
+                ]);
+            }
+            assert_agree("mass_kg", case.name, &mass);
+
+            let m = case.mass_kg();
+            assert_agree(
+                "karma_bytes",
+                case.name,
+                &[
+                    ("ceim::karma_bytes", case.karma_bytes()),
+                    ("compute_karma_bytes", compute_karma_bytes(&row, m)),
+                    ("checked_karma_bytes", checked_karma_bytes(&row, m).unwrap()),
+                ],
+            );
+        }
+        assert_eq!(REFERENCE_ROWS[4].mass_kg(), 0.0);
+    }
+
+    #[test]
+    fn reference_formulas_hold() {
+        // 12 ug/m3 over 3 m3/s for an hour.
+        let canopy = REFERENCE_ROWS[0];
+        assert!((canopy.mass_kg() - 1.296e-4).abs() < 1e-18);
+        assert!((canopy.karma_bytes() - 1.944e5).abs() < 1e-6);
+        assert_eq!(s_bee(0.0, 1.0e12, 1.0), 0.0);
+        assert_eq!(s_bee(10.0, 0.5, 2.0), s_bee(10.0, 1.0, 2.0));
+        assert_eq!(s_bee(1.0e20, 1.0, 1.0), 1.0 - (-50.0f64).exp());
+        assert_eq!(ecoimpact(0.0, 1.0), 0.0);
+        assert!((REFERENCE_ECOIMPACT[0].ecoimpact() - (1.0 - (-0.24f64).exp())).abs() < 1e-15);
+    }
+
+    #[test]
+    #[should_panic(expected = "mass_kg for r: b = 2e0 but a = 1e0")]
+    fn disagreement_names_both_call_sites() {
+        assert_agree("mass_kg", "r", &[("a", 1.0), ("b", 2.0)]);
+    }
+}
@@ -0,0 +1,459 @@
+//! Append-only, hash-chained log of control decisions.
+//!
+//! Each [`ChainEntry`] carries the SHA-256 of its predecessor, so editing,
+//! dropping or reordering any entry breaks every hash after it and
+//! [`verify_chain`] names the first index that no longer fits.
+//!
+//! An entry's hash is the lowercase hex SHA-256 of the canonical JSON of
+//! `{"index", "kind", "payload", "prev_hash"}`: no whitespace, object keys
+//! sorted by byte order at every level, numbers as `serde_json` writes
+//! them. The first entry's `prev_hash` is [`GENESIS_HASH`]. Publishing
+//! [`ChainHead`] somewhere outside the operator's control anchors the whole
+//! history up to it.
+//!
+//! [`FileEventChain`] keeps the chain as JSON lines, one entry per line,
+//! synced before an append returns. A line torn by a crash mid-append is
+//! dropped when the file is reopened; anything else that fails to parse or
+//! verify refuses the open.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::StepRecord;
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Kind of the entries [`ChainAppender::append_step`] writes.
+pub const DUTY_CHANGE: &str = "duty_change";
+
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("chain I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("chain event could not be serialized: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("chain line {line} is not an entry: {reason}")]
+    Corrupt { line: usize, reason: String },
+    /// The first entry that does not follow from the ones before it.
+    #[error("chain diverges at entry {index}: {reason}")]
+    Diverged { index: u64, reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainEntry {
+    pub index: u64,
+    pub kind: String,
+    pub payload: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl ChainEntry {
+    fn new(index: u64, kind: &str, payload: Value, prev_hash: String) -> Self {
+        let hash = entry_hash(index, kind, &payload, &prev_hash);
+        ChainEntry {
+            index,
+            kind: kind.to_string(),
+            payload,
+            prev_hash,
+            hash,
+        }
+    }
+
+    /// The hash this entry's contents call for.
+    pub fn expected_hash(&self) -> String {
+        entry_hash(self.index, &self.kind, &self.payload, &self.prev_hash)
+    }
+}
+
+/// Length and last hash of a chain, for external anchoring.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainHead {
+    pub len: u64,
+    /// [`GENESIS_HASH`] for an empty chain.
+    pub hash: String,
+}
+
+/// One duty change from a simulator step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DutyChangeEvent {
+    pub step: usize,
+    pub machine_id: String,
+    pub duty_before: f64,
+    pub duty_after: f64,
+    pub band: crate::EcoBand,
+}
+
+/// `value` as canonical JSON; see the module docs.
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn entry_hash(index: u64, kind: &str, payload: &Value, prev_hash: &str) -> String {
+    let body = json!({
+        "index": index,
+        "kind": kind,
+        "payload": payload,
+        "prev_hash": prev_hash,
+    });
+    Sha256::digest(canonical_json(&body).as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Check that `entries` form a chain from genesis and return its head.
+pub fn verify_chain(entries: &[ChainEntry]) -> Result<ChainHead, ChainError> {
+    let mut prev = GENESIS_HASH.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        let index = i as u64;
+        let diverged = |reason: String| Err(ChainError::Diverged { index, reason });
+        if entry.index != index {
+            return diverged(format!("entry claims index {}", entry.index));
+        }
+        if entry.prev_hash != prev {
+            return diverged(format!(
+                "prev_hash {} does not match the previous entry's {prev}",
+                entry.prev_hash
+            ));
+        }
+        let expected = entry.expected_hash();
+        if entry.hash != expected {
+            return diverged(format!(
+                "hash {} does not match its contents ({expected})",
+                entry.hash
+            ));
+        }
+        prev = expected;
+    }
+    Ok(ChainHead {
+        len: entries.len() as u64,
+        hash: prev,
+    })
+}
+
+/// Somewhere control decisions can be chained.
+pub trait ChainAppender {
+    /// Append `payload` as an entry of `kind` and return the new head.
+    fn append<T: Serialize + ?Sized>(
+        &mut self,
+        kind: &str,
+        payload: &T,
+    ) -> Result<ChainHead, ChainError>;
+
+    /// Append a [`DUTY_CHANGE`] entry for each node whose duty `record`
+    /// changed, in node order; the head after the last one.
+    fn append_step(&mut self, record: &StepRecord) -> Result<Option<ChainHead>, ChainError> {
+        let mut head = None;
+        for node in &record.nodes {
+            if node.duty_before == node.duty_cycle {
+                continue;
+            }
+            let event = DutyChangeEvent {
+                step: record.step,
+                machine_id: node.machine_id.clone(),
+                duty_before: node.duty_before,
+                duty_after: node.duty_cycle,
+                band: record.band,
+            };
+            head = Some(self.append(DUTY_CHANGE, &event)?);
+        }
+        Ok(head)
+    }
+}
+
+/// In-memory chain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventChain {
+    entries: Vec<ChainEntry>,
+}
+
+impl EventChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A chain over `entries`, if they verify.
+    pub fn from_entries(entries: Vec<ChainEntry>) -> Result<Self, ChainError> {
+        verify_chain(&entries)?;
+        Ok(EventChain { entries })
+    }
+
+    pub fn entries(&self) -> &[ChainEntry] {
+        &self.entries
+    }
+
+    pub fn head(&self) -> ChainHead {
+        ChainHead {
+            len: self.entries.len() as u64,
+            hash: self
+                .entries
+                .last()
+                .map_or_else(|| GENESIS_HASH.to_string(), |e| e.hash.clone()),
+        }
+    }
+
+    pub fn verify(&self) -> Result<ChainHead, ChainError> {
+        verify_chain(&self.entries)
+    }
+
+    fn next_entry<T: Serialize + ?Sized>(
+        &self,
+        kind: &str,
+        payload: &T,
+    ) -> Result<ChainEntry, ChainError> {
+        let head = self.head();
+        Ok(ChainEntry::new(
+            head.len,
+            kind,
+            serde_json::to_value(payload)?,
+            head.hash,
+        ))
+    }
+}
+
+impl ChainAppender for EventChain {
+    fn append<T: Serialize + ?Sized>(
+        &mut self,
+        kind: &str,
+        payload: &T,
+    ) -> Result<ChainHead, ChainError> {
+        let entry = self.next_entry(kind, payload)?;
+        self.entries.push(entry);
+        Ok(self.head())
+    }
+}
+
+/// Chain persisted as JSON lines; see the module docs.
+#[derive(Debug)]
+pub struct FileEventChain {
+    path: PathBuf,
+    file: File,
+    chain: EventChain,
+}
+
+impl FileEventChain {
+    /// Open or create the chain at `path`, verifying what is there.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChainError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+
+        // Everything after the last newline is an append that never finished.
+        let complete = text.rfind('\n').map_or(0, |i| i + 1);
+        if complete < text.len() {
+            file.set_len(complete as u64)?;
+            file.sync_data()?;
+        }
+        let entries = text[..complete]
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| ChainError::Corrupt {
+                    line: i + 1,
+                    reason: e.to_string(),
+                })
+            })
+            .collect::<Result<Vec<ChainEntry>, _>>()?;
+        let chain = EventChain::from_entries(entries)?;
+        Ok(FileEventChain { path, file, chain })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn chain(&self) -> &EventChain {
+        &self.chain
+    }
+
+    pub fn head(&self) -> ChainHead {
+        self.chain.head()
+    }
+}
+
+impl ChainAppender for FileEventChain {
+    fn append<T: Serialize + ?Sized>(
+        &mut self,
+        kind: &str,
+        payload: &T,
+    ) -> Result<ChainHead, ChainError> {
+        let entry = self.chain.next_entry(kind, payload)?;
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.chain.entries.push(entry);
+        Ok(self.chain.head())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::StepInput;
+
+    fn decisions() -> EventChain {
+        let mut chain = EventChain::new();
+        let mut controller = phoenix_controller();
+        // Power penalty only, so duties move a little every step.
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        controller.eta_w = 0.0;
+        controller.eta_b = 0.0;
+        controller.eta_p = 0.1;
+        let mut nodes = phoenix_nodes();
+        let log = controller
+            .run_steps(&mut nodes, 3, |_| StepInput {
+                phi_dw: 5.0e-7,
+                alpha_m: 0.5,
+                alpha_k: 0.5,
+                eco_offset: 0.0,
+                wind: None,
+            })
+            .unwrap();
+        for record in &log {
+            chain.append_step(record).unwrap();
+        }
+        chain
+            .append(
+                "escalation",
+                &json!({"action": "reduce_duty", "node_id": "CYB-AIR-SCHOOL-05"}),
+            )
+            .unwrap();
+        chain
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cyboair-chain-{}-{name}.jsonl", std::process::id()))
+    }
+
+    #[test]
+    fn hashing_is_deterministic_and_key_order_free() {
+        let (a, b) = (decisions(), decisions());
+        assert_eq!(a.entries().len(), 7);
+        assert_eq!(a.head(), b.head());
+        assert_eq!(a.verify().unwrap(), a.head());
+        assert_eq!(a.entries()[0].prev_hash, GENESIS_HASH);
+        assert_eq!(a.entries()[0].kind, DUTY_CHANGE);
+
+        let x: Value = serde_json::from_str(r#"{"b": [1, {"d": 2, "c": 3}], "a": "x"}"#).unwrap();
+        assert_eq!(canonical_json(&x), r#"{"a":"x","b":[1,{"c":3,"d":2}]}"#);
+        // Pinned so a change to the canonical form cannot go unnoticed:
+        // sha256 of {"index":0,"kind":"test","payload":{"a":"x",...},"prev_hash":"00..."}.
+        assert_eq!(
+            ChainEntry::new(0, "test", x, GENESIS_HASH.into()).hash,
+            "0efad999ac580d4d56ea7d3fb7d0d7a8016183c94d38eb0e36baa80bb95251b9"
+        );
+        assert_eq!(EventChain::new().head().hash, GENESIS_HASH);
+    }
+
+    #[test]
+    fn tampering_with_a_middle_entry_is_located() {
+        let chain = decisions();
+        let middle = chain.entries().len() / 2;
+
+        let mut edited = chain.entries().to_vec();
+        edited[middle].payload["duty_after"] = json!(0.0);
+        assert!(matches!(
+            verify_chain(&edited),
+            Err(ChainError::Diverged { index, .. }) if index == middle as u64
+        ));
+
+        // Rehashing the edit moves the break to the next entry.
+        edited[middle].hash = edited[middle].expected_hash();
+        assert!(matches!(
+            verify_chain(&edited),
+            Err(ChainError::Diverged { index, .. }) if index == middle as u64 + 1
+        ));
+
+        let mut dropped = chain.entries().to_vec();
+        dropped.remove(middle);
+        assert!(matches!(
+            EventChain::from_entries(dropped),
+            Err(ChainError::Diverged { index, .. }) if index == middle as u64
+        ));
+    }
+
+    #[test]
+    fn file_chain_survives_reopen_and_a_torn_append() {
+        let path = temp_path("reopen");
+        let _ = std::fs::remove_file(&path);
+        let expected = decisions();
+        {
+            let mut file = FileEventChain::open(&path).unwrap();
+            for entry in expected.entries() {
+                file.append(&entry.kind, &entry.payload).unwrap();
+            }
+            assert_eq!(file.head(), expected.head());
+        }
+        // A crash partway through the next append.
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(br#"{"index":9,"kind":"duty"#)
+            .unwrap();
+
+        let mut reopened = FileEventChain::open(&path).unwrap();
+        assert_eq!(reopened.chain(), &expected);
+        let head = reopened.append("note", "after the crash").unwrap();
+        assert_eq!(head.len, expected.head().len + 1);
+        drop(reopened);
+        assert_eq!(FileEventChain::open(&path).unwrap().head(), head);
+
+        // A rewritten middle line refuses the open.
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.replacen("\"step\":1", "\"step\":7", 1)).unwrap();
+        assert!(matches!(
+            FileEventChain::open(&path),
+            Err(ChainError::Diverged { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
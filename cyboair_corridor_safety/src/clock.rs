@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+/// Source of "now" for time-stamped records, so tests can step time.
+pub trait Clock {
+    /// Milliseconds since the Unix epoch.
+    fn now_unix_ms(&self) -> u64;
+}
+
+/// Wall-clock time; 0 before the Unix epoch.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time, so a
+/// test can keep one handle and give another to the code under test.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now_ms: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(start_unix_ms: u64) -> Self {
+        ManualClock {
+            now_ms: Arc::new(AtomicU64::new(start_unix_ms)),
+        }
+    }
+
+    pub fn set(&self, now_unix_ms: u64) {
+        self.now_ms.store(now_unix_ms, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by_ms: u64) {
+        self.now_ms.fetch_add(by_ms, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_unix_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+/// A sample older than a time-windowed component accepts: more than
+/// `max_lateness_ms` behind the newest sample it has seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("sample at {sample_ms} ms is more than {max_lateness_ms} ms behind the newest at {newest_ms} ms")]
+pub struct LateData {
+    pub sample_ms: u64,
+    pub newest_ms: u64,
+    pub max_lateness_ms: u64,
+}
+
+impl LateData {
+    /// `Err` if `sample_ms` is further behind `newest_ms` than allowed.
+    pub fn check(sample_ms: u64, newest_ms: u64, max_lateness_ms: u64) -> Result<(), LateData> {
+        if newest_ms.saturating_sub(sample_ms) > max_lateness_ms {
+            return Err(LateData {
+                sample_ms,
+                newest_ms,
+                max_lateness_ms,
+            });
+        }
+        Ok(())
+    }
+}
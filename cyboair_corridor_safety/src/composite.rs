@@ -0,0 +1,537 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::snapshot::{BudgetState, SnapshotError};
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope, SafetyError,
+};
+
+/// A member of a `CompositeSafetyEnvelope`.
+pub type BoxedSafetyEnvelope = Box<dyn SafetyEnvelope + Send + Sync>;
+
+/// A composite safety envelope was built with no members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("composite safety envelope needs at least one member")]
+pub struct EmptyCompositeEnvelope;
+
+/// Several safety envelopes enforced together, e.g. the rectangular
+/// operating bounds, a bee polytope and hive exclusion zones. A node is
+/// inside only if it is inside every member.
+///
+/// Members are checked in registration order. `check_envelope` stops at
+/// the first failing member and returns its first error wrapped in
+/// [`SafetyError::EnvelopeMember`] with the member's name; later members
+/// are not evaluated. `check_all`, and so `envelope_violations` and dry
+/// runs, evaluates every member and returns every failure, wrapped the
+/// same way and in the same order.
+pub struct CompositeSafetyEnvelope {
+    members: Vec<(String, BoxedSafetyEnvelope)>,
+}
+
+impl CompositeSafetyEnvelope {
+    /// Refuses an empty member list, which would accept every node.
+    pub fn new(
+        members: Vec<(String, BoxedSafetyEnvelope)>,
+    ) -> Result<Self, EmptyCompositeEnvelope> {
+        if members.is_empty() {
+            return Err(EmptyCompositeEnvelope);
+        }
+        Ok(CompositeSafetyEnvelope { members })
+    }
+
+    pub fn builder() -> CompositeSafetyEnvelopeBuilder {
+        CompositeSafetyEnvelopeBuilder::default()
+    }
+
+    /// Member names, in check order.
+    pub fn member_names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Every violation, each with the name of the member that raised it.
+    pub fn member_violations(&self, node: &NodeState) -> Vec<(&str, SafetyError)> {
+        self.members
+            .iter()
+            .flat_map(|(name, envelope)| {
+                envelope
+                    .envelope_violations(node)
+                    .into_iter()
+                    .map(move |e| (name.as_str(), e))
+            })
+            .collect()
+    }
+
+    /// Evaluate every member, failing with all their violations.
+    pub fn check_all(&self, node: &NodeState) -> Result<(), Vec<SafetyError>> {
+        let violations: Vec<SafetyError> = self
+            .member_violations(node)
+            .into_iter()
+            .map(|(name, e)| attribute(name, e))
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn attribute(member: &str, error: SafetyError) -> SafetyError {
+    SafetyError::EnvelopeMember {
+        member: member.to_string(),
+        error: Box::new(error),
+    }
+}
+
+impl fmt::Debug for CompositeSafetyEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeSafetyEnvelope")
+            .field("members", &self.member_names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl SafetyEnvelope for CompositeSafetyEnvelope {
+    fn check_envelope(&self, node: &NodeState) -> Result<(), SafetyError> {
+        self.members.iter().try_for_each(|(name, envelope)| {
+            envelope
+                .check_envelope(node)
+                .map_err(|e| attribute(name, e))
+        })
+    }
+
+    fn envelope_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        self.check_all(node).err().unwrap_or_default()
+    }
+
+    /// The lowest member safe duty; fully off if any member says so.
+    fn safe_duty(&self) -> Option<f64> {
+        self.members
+            .iter()
+            .map(|(_, envelope)| envelope.safe_duty())
+            .try_fold(f64::INFINITY, |lowest, u| u.map(|u| lowest.min(u)))
+    }
+}
+
+/// Collects named members for a `CompositeSafetyEnvelope`.
+#[derive(Default)]
+pub struct CompositeSafetyEnvelopeBuilder {
+    members: Vec<(String, BoxedSafetyEnvelope)>,
+}
+
+impl CompositeSafetyEnvelopeBuilder {
+    pub fn member<E>(mut self, name: impl Into<String>, envelope: E) -> Self
+    where
+        E: SafetyEnvelope + Send + Sync + 'static,
+    {
+        self.members.push((name.into(), Box::new(envelope)));
+        self
+    }
+
+    pub fn build(self) -> Result<CompositeSafetyEnvelope, EmptyCompositeEnvelope> {
+        CompositeSafetyEnvelope::new(self.members)
+    }
+}
+
+/// A member of a `CompositeHostBudget`.
+pub type BoxedHostBudget = Box<dyn HostBudget + Send + Sync>;
+
+/// A composite host budget was built with no members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("composite host budget needs at least one member")]
+pub struct EmptyCompositeBudget;
+
+/// Several host budgets enforced together, e.g. power, battery and karma
+/// liability. A node passes only if every member passes.
+///
+/// Members are named so a failure can be attributed with
+/// `member_violations`; `check_host_budget` returns the member's own
+/// error unchanged.
+pub struct CompositeHostBudget {
+    members: Vec<(String, BoxedHostBudget)>,
+}
+
+impl CompositeHostBudget {
+    /// Refuses an empty member list, which would accept every node.
+    pub fn new(members: Vec<(String, BoxedHostBudget)>) -> Result<Self, EmptyCompositeBudget> {
+        if members.is_empty() {
+            return Err(EmptyCompositeBudget);
+        }
+        Ok(CompositeHostBudget { members })
+    }
+
+    pub fn builder() -> CompositeHostBudgetBuilder {
+        CompositeHostBudgetBuilder::default()
+    }
+
+    /// Member names, in check order.
+    pub fn member_names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Every violation, each with the name of the member that raised it.
+    pub fn member_violations(&self, node: &NodeState) -> Vec<(&str, SafetyError)> {
+        self.members
+            .iter()
+            .flat_map(|(name, budget)| {
+                budget
+                    .host_budget_violations(node)
+                    .into_iter()
+                    .map(move |e| (name.as_str(), e))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for CompositeHostBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeHostBudget")
+            .field("members", &self.member_names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl HostBudget for CompositeHostBudget {
+    /// The first failing member's error, in member order.
+    fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError> {
+        self.members
+            .iter()
+            .try_for_each(|(_, budget)| budget.check_host_budget(node))
+    }
+
+    /// The largest member fraction, so the most constrained budget drives
+    /// the controller's eta_p term.
+    fn power_fraction(&self, node: &NodeState) -> f64 {
+        self.members
+            .iter()
+            .map(|(_, budget)| budget.power_fraction(node))
+            .fold(0.0, f64::max)
+    }
+
+    fn host_budget_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        self.member_violations(node)
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect()
+    }
+
+    fn budget_state(&self) -> BudgetState {
+        BudgetState {
+            members: self
+                .members
+                .iter()
+                .map(|(name, budget)| (name.clone(), budget.budget_state()))
+                .collect(),
+            ..BudgetState::default()
+        }
+    }
+
+    /// Each member from the state saved under its name; a member with
+    /// none is restored from an empty state.
+    fn restore_budget_state(&mut self, state: &BudgetState) -> Result<(), SnapshotError> {
+        let empty = BudgetState::default();
+        self.members.iter_mut().try_for_each(|(name, budget)| {
+            budget.restore_budget_state(state.members.get(name).unwrap_or(&empty))
+        })
+    }
+}
+
+/// Collects named members for a `CompositeHostBudget`.
+#[derive(Default)]
+pub struct CompositeHostBudgetBuilder {
+    members: Vec<(String, BoxedHostBudget)>,
+}
+
+impl CompositeHostBudgetBuilder {
+    pub fn member<H>(mut self, name: impl Into<String>, budget: H) -> Self
+    where
+        H: HostBudget + Send + Sync + 'static,
+    {
+        self.members.push((name.into(), Box::new(budget)));
+        self
+    }
+
+    pub fn build(self) -> Result<CompositeHostBudget, EmptyCompositeBudget> {
+        CompositeHostBudget::new(self.members)
+    }
+}
+
+/// Assembles a `CompositeHostBudget` for a controller; see
+/// `CorridorController::with_host_budgets`.
+pub struct ControllerBudgetBuilder<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    controller: CorridorController<E, H, B, D, G, R>,
+    budgets: CompositeHostBudgetBuilder,
+}
+
+impl<E, H, B, D, G, R> ControllerBudgetBuilder<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    pub(crate) fn new(controller: CorridorController<E, H, B, D, G, R>) -> Self {
+        ControllerBudgetBuilder {
+            controller,
+            budgets: CompositeHostBudgetBuilder::default(),
+        }
+    }
+
+    pub fn member<M>(mut self, name: impl Into<String>, budget: M) -> Self
+    where
+        M: HostBudget + Send + Sync + 'static,
+    {
+        self.budgets = self.budgets.member(name, budget);
+        self
+    }
+
+    /// The controller with the composite in place of its host budget.
+    pub fn build(
+        self,
+    ) -> Result<CorridorController<E, CompositeHostBudget, B, D, G, R>, EmptyCompositeBudget> {
+        Ok(self.controller.with_host_budget(self.budgets.build()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{EcoBand, EnvelopeField, RectSafetyEnvelope, SimpleHostBudget};
+
+    /// Fails every node with a battery-reserve error.
+    struct FlatBattery {
+        fraction: f64,
+    }
+
+    impl HostBudget for FlatBattery {
+        fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError> {
+            Err(SafetyError::BatteryReserve {
+                machine_id: node.row.machine_id.clone(),
+                soc: 0.1,
+                reserve_soc: 0.2,
+            })
+        }
+
+        fn power_fraction(&self, _node: &NodeState) -> f64 {
+            self.fraction
+        }
+    }
+
+    fn power() -> SimpleHostBudget {
+        SimpleHostBudget {
+            p_max_w: 100.0,
+            e_step_max_j: 1.0e9,
+            step_dt_s: 60.0,
+        }
+    }
+
+    #[test]
+    fn empty_composite_is_refused() {
+        assert_eq!(
+            CompositeHostBudget::builder().build().unwrap_err(),
+            EmptyCompositeBudget
+        );
+        assert!(phoenix_controller().with_host_budgets().build().is_err());
+    }
+
+    #[test]
+    fn failures_are_attributed_to_their_member() {
+        let node = &phoenix_nodes()[0];
+        let composite = CompositeHostBudget::builder()
+            .member("power", power())
+            .member("battery", FlatBattery { fraction: 0.1 })
+            .build()
+            .unwrap();
+        assert!(matches!(
+            composite.check_host_budget(node),
+            Err(SafetyError::BatteryReserve { .. })
+        ));
+        let violations = composite.member_violations(node);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].0, "battery");
+
+        // Over P_max too: both members report, power first.
+        let mut hot = node.clone();
+        hot.power_w = 150.0;
+        let names: Vec<_> = composite
+            .member_violations(&hot)
+            .into_iter()
+            .map(|(name, e)| (name, e.kind()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("power", "host_budget_exceeded"),
+                ("battery", "battery_reserve")
+            ]
+        );
+        assert!(matches!(
+            composite.check_host_budget(&hot),
+            Err(SafetyError::HostBudgetExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn power_fraction_is_the_largest_member_fraction() {
+        let node = &phoenix_nodes()[0];
+        let p_frac = node.power_w / 100.0;
+        for fraction in [0.1 * p_frac, 10.0 * p_frac] {
+            let composite = CompositeHostBudget::builder()
+                .member("power", power())
+                .member("battery", FlatBattery { fraction })
+                .build()
+                .unwrap();
+            assert_eq!(composite.power_fraction(node), p_frac.max(fraction));
+        }
+    }
+
+    /// Fails every node whose duty exceeds `max`, with a duty-cycle
+    /// violation.
+    struct DutyCap {
+        max: f64,
+    }
+
+    impl SafetyEnvelope for DutyCap {
+        fn check_envelope(&self, node: &NodeState) -> Result<(), SafetyError> {
+            if node.duty_cycle <= self.max {
+                return Ok(());
+            }
+            Err(SafetyError::EnvelopeViolation {
+                machine_id: node.row.machine_id.clone(),
+                field: EnvelopeField::DutyCycle,
+                value: node.duty_cycle,
+                min: 0.0,
+                max: self.max,
+            })
+        }
+    }
+
+    /// rect (u_max 1.0), then caps at 0.6 and 0.3, with rect's altitude
+    /// bounds the only ones a node can fail besides duty.
+    fn three_envelopes() -> CompositeSafetyEnvelope {
+        CompositeSafetyEnvelope::builder()
+            .member("rect", phoenix_controller().envelope)
+            .member("cap_0.6", DutyCap { max: 0.6 })
+            .member("cap_0.3", DutyCap { max: 0.3 })
+            .build()
+            .unwrap()
+    }
+
+    fn failing_member(e: &SafetyError) -> &str {
+        match e {
+            SafetyError::EnvelopeMember { member, .. } => member,
+            other => panic!("unattributed error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn empty_envelope_is_refused() {
+        assert_eq!(
+            CompositeSafetyEnvelope::builder().build().unwrap_err(),
+            EmptyCompositeEnvelope
+        );
+        assert!(CompositeSafetyEnvelope::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn first_failing_member_is_named() {
+        let composite = three_envelopes();
+        let mut node = phoenix_nodes()[0].clone();
+
+        node.duty_cycle = 0.2;
+        assert_eq!(composite.check_envelope(&node), Ok(()));
+
+        // Only the last member fails.
+        node.duty_cycle = 0.5;
+        let err = composite.check_envelope(&node).unwrap_err();
+        assert_eq!(failing_member(&err), "cap_0.3");
+        assert!(matches!(
+            err.unattributed(),
+            SafetyError::EnvelopeViolation { max, .. } if *max == 0.3
+        ));
+        assert_eq!(err.kind(), "envelope_violation");
+
+        // Both caps fail; the middle one is reported.
+        node.duty_cycle = 0.8;
+        let err = composite.check_envelope(&node).unwrap_err();
+        assert_eq!(failing_member(&err), "cap_0.6");
+        assert!(err.to_string().starts_with("envelope member cap_0.6: "));
+
+        // Out of rect's altitude band: the first member short-circuits.
+        let composite = CompositeSafetyEnvelope::builder()
+            .member(
+                "rect",
+                RectSafetyEnvelope {
+                    z_min_m: 400.0,
+                    ..phoenix_controller().envelope
+                },
+            )
+            .member("cap_0.6", DutyCap { max: 0.6 })
+            .member("cap_0.3", DutyCap { max: 0.3 })
+            .build()
+            .unwrap();
+        let err = composite.check_envelope(&node).unwrap_err();
+        assert_eq!(failing_member(&err), "rect");
+        assert!(matches!(
+            err.unattributed(),
+            SafetyError::EnvelopeViolation {
+                field: EnvelopeField::Altitude,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn aggregate_mode_reports_every_member() {
+        let composite = three_envelopes();
+        let mut node = phoenix_nodes()[0].clone();
+        node.duty_cycle = 0.8;
+        let all = composite.check_all(&node).unwrap_err();
+        assert_eq!(
+            all.iter().map(failing_member).collect::<Vec<_>>(),
+            ["cap_0.6", "cap_0.3"]
+        );
+        assert_eq!(composite.envelope_violations(&node), all);
+
+        // A dry run sees them all, attributed.
+        let controller = phoenix_controller().with_envelope(composite);
+        let assessment = controller.dry_run_node(&node, EcoBand::Green, 0.0);
+        assert_eq!(assessment.violations, all);
+        assert_eq!(controller.envelope.safe_duty(), None);
+
+        node.duty_cycle = 0.1;
+        assert_eq!(controller.envelope.check_all(&node), Ok(()));
+    }
+
+    #[test]
+    fn controller_builder_installs_the_composite() {
+        let controller = phoenix_controller()
+            .with_host_budgets()
+            .member("power", power())
+            .member("battery", FlatBattery { fraction: 0.0 })
+            .build()
+            .unwrap();
+        assert_eq!(
+            controller.host_budget.member_names().collect::<Vec<_>>(),
+            ["power", "battery"]
+        );
+        let mut node = phoenix_nodes()[0].clone();
+        assert!(matches!(
+            controller.update_node_duty(&mut node, EcoBand::Green, 0.0),
+            Err(SafetyError::BatteryReserve { .. })
+        ));
+    }
+}
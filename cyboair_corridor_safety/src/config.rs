@@ -0,0 +1,479 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    AirflowModels, AltitudeProvider, BeeGuard, ClockedSchedule, CorridorController, DutySchedule,
+    DynamicGeoWeight, EcoLoadMode, GainSchedule, KarmaBasis, MassBaseline, MetricsRecorder,
+    NoBeeGuard, NoMetrics, RectSafetyEnvelope, SimpleDwCeiling, SimpleHostBudget, SlewLimit,
+    SystemClock, ThresholdEcoBand, ViolationPolicy,
+};
+
+/// Errors from parsing or validating a controller configuration.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConfigError {
+    #[error("toml config error: {0}")]
+    Toml(String),
+    #[error("json config error: {0}")]
+    Json(String),
+    #[error("config file error: {0}")]
+    Io(String),
+    #[error("invalid config: {field}: {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+/// Equation 5 gains.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GainConfig {
+    pub eta_m: f64,
+    pub eta_k: f64,
+    pub eta_w: f64,
+    pub eta_b: f64,
+    pub eta_p: f64,
+    pub eta_dw: f64,
+    #[serde(default)]
+    pub eta_noise: f64,
+    #[serde(default)]
+    pub eta_emf: f64,
+}
+
+/// `RectSafetyEnvelope` bounds; the altitude provider is supplied at build time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EnvelopeConfig {
+    pub u_min: f64,
+    pub u_max: f64,
+    pub z_min_m: f64,
+    pub z_max_m: f64,
+    pub ecoimpact_min: f64,
+    pub ecoimpact_max: f64,
+    #[serde(default)]
+    pub noise_max_db: Option<f64>,
+    #[serde(default)]
+    pub emf_max_vpm: Option<f64>,
+}
+
+/// Deployment tuning for a `CorridorController`, loadable from TOML or JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ControllerConfig {
+    pub corridor_area_m2: f64,
+    pub m_ref_kg: f64,
+    pub k_ref_nb: f64,
+    /// Exposure reference scales; required when the matching gain is set.
+    #[serde(default)]
+    pub noise_ref_db: f64,
+    #[serde(default)]
+    pub emf_ref_vpm: f64,
+    pub gains: GainConfig,
+    /// Per-node and per-band gains in place of `gains`.
+    #[serde(default, skip_serializing_if = "GainSchedule::is_empty")]
+    pub gain_schedule: GainSchedule,
+    /// Time-of-week duty caps, read against the system clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duty_schedule: Option<DutySchedule>,
+    pub envelope: EnvelopeConfig,
+    pub host_budget: SimpleHostBudget,
+    pub eco_band: ThresholdEcoBand,
+    pub dw_ceiling: SimpleDwCeiling,
+    #[serde(default)]
+    pub eco_load_mode: EcoLoadMode,
+    #[serde(default)]
+    pub slew_limit: Option<SlewLimit>,
+    #[serde(default)]
+    pub violation_policy: ViolationPolicy,
+    /// Wind-dependent geo weighting toward sensitive receptors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo_weighting: Option<DynamicGeoWeight>,
+    /// Airflow at duty for the simulator's eco-load, by node type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub airflow: Option<AirflowModels>,
+    /// Removal expected without actuation; see [`removal`](crate::removal).
+    #[serde(default)]
+    pub mass_baseline: MassBaseline,
+    /// Whether karma is credited for gross or net removal.
+    #[serde(default)]
+    pub karma_basis: KarmaBasis,
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+fn non_negative(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() && v >= 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite and >= 0, got {v}")))
+    }
+}
+
+fn positive(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() && v > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite and > 0, got {v}")))
+    }
+}
+
+fn finite(field: &'static str, v: f64) -> Result<(), ConfigError> {
+    if v.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(field, format!("must be finite, got {v}")))
+    }
+}
+
+fn ordered(field: &'static str, lo: f64, hi: f64) -> Result<(), ConfigError> {
+    if lo <= hi {
+        Ok(())
+    } else {
+        Err(invalid(
+            field,
+            format!("lower bound {lo} exceeds upper bound {hi}"),
+        ))
+    }
+}
+
+impl ControllerConfig {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let cfg: Self = toml::from_str(s).map_err(|e| ConfigError::Toml(e.to_string()))?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, ConfigError> {
+        let cfg: Self = serde_json::from_str(s).map_err(|e| ConfigError::Json(e.to_string()))?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// Reject values the controller cannot run with; the first problem wins.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        positive("corridor_area_m2", self.corridor_area_m2)?;
+        positive("m_ref_kg", self.m_ref_kg)?;
+        positive("k_ref_nb", self.k_ref_nb)?;
+
+        let g = &self.gains;
+        non_negative("gains.eta_m", g.eta_m)?;
+        non_negative("gains.eta_k", g.eta_k)?;
+        non_negative("gains.eta_w", g.eta_w)?;
+        non_negative("gains.eta_b", g.eta_b)?;
+        non_negative("gains.eta_p", g.eta_p)?;
+        non_negative("gains.eta_dw", g.eta_dw)?;
+        non_negative("gains.eta_noise", g.eta_noise)?;
+        non_negative("gains.eta_emf", g.eta_emf)?;
+        for (entry, g) in self.gain_schedule.entries() {
+            for (name, v) in [
+                ("eta_m", g.eta_m),
+                ("eta_k", g.eta_k),
+                ("eta_w", g.eta_w),
+                ("eta_b", g.eta_b),
+                ("eta_p", g.eta_p),
+                ("eta_dw", g.eta_dw),
+                ("eta_noise", g.eta_noise),
+                ("eta_emf", g.eta_emf),
+            ] {
+                if !(v.is_finite() && v >= 0.0) {
+                    return Err(invalid(
+                        "gain_schedule",
+                        format!("{entry}.{name} must be finite and >= 0, got {v}"),
+                    ));
+                }
+            }
+        }
+        let all_gains = || std::iter::once(g).chain(self.gain_schedule.entries().map(|(_, g)| g));
+        if all_gains().any(|g| g.eta_noise > 0.0) {
+            positive("noise_ref_db", self.noise_ref_db)?;
+        }
+        if all_gains().any(|g| g.eta_emf > 0.0) {
+            positive("emf_ref_vpm", self.emf_ref_vpm)?;
+        }
+
+        if let Some(schedule) = &self.duty_schedule {
+            schedule.validate()?;
+        }
+        if let Some(geo) = &self.geo_weighting {
+            geo.validate()?;
+        }
+        if let Some(airflow) = &self.airflow {
+            airflow.validate()?;
+        }
+        self.mass_baseline.validate()?;
+
+        let e = &self.envelope;
+        ordered("envelope.u_min", e.u_min, e.u_max)?;
+        ordered("envelope.z_min_m", e.z_min_m, e.z_max_m)?;
+        ordered("envelope.ecoimpact_min", e.ecoimpact_min, e.ecoimpact_max)?;
+        if let Some(max) = e.noise_max_db {
+            finite("envelope.noise_max_db", max)?;
+        }
+        if let Some(max) = e.emf_max_vpm {
+            non_negative("envelope.emf_max_vpm", max)?;
+        }
+
+        let h = &self.host_budget;
+        positive("host_budget.p_max_w", h.p_max_w)?;
+        positive("host_budget.e_step_max_j", h.e_step_max_j)?;
+        positive("host_budget.step_dt_s", h.step_dt_s)?;
+
+        let b = &self.eco_band;
+        ordered(
+            "eco_band.theta_amber_red",
+            b.theta_green_amber,
+            b.theta_amber_red,
+        )?;
+        non_negative("eco_band.gain_green", b.gain_green)?;
+        non_negative("eco_band.gain_amber", b.gain_amber)?;
+        non_negative("eco_band.gain_red", b.gain_red)?;
+
+        positive("dw_ceiling.phi_dw_max", self.dw_ceiling.phi_dw_max)?;
+
+        if let Some(limit) = &self.slew_limit {
+            non_negative("slew_limit.max_up", limit.max_up)?;
+            non_negative("slew_limit.max_down", limit.max_down)?;
+        }
+        if let ViolationPolicy::ClampToSafe { safe_duty: Some(u) } = self.violation_policy {
+            if !(0.0..=1.0).contains(&u) {
+                return Err(invalid(
+                    "violation_policy.safe_duty",
+                    format!("must be in [0, 1], got {u}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate and build a controller using `altitude` for the envelope.
+    pub fn build<A: AltitudeProvider>(
+        &self,
+        altitude: A,
+    ) -> Result<
+        CorridorController<
+            RectSafetyEnvelope<A>,
+            SimpleHostBudget,
+            ThresholdEcoBand,
+            SimpleDwCeiling,
+        >,
+        ConfigError,
+    > {
+        self.validate()?;
+        let e = &self.envelope;
+        Ok(CorridorController {
+            envelope: RectSafetyEnvelope {
+                u_min: e.u_min,
+                u_max: e.u_max,
+                z_min_m: e.z_min_m,
+                z_max_m: e.z_max_m,
+                ecoimpact_min: e.ecoimpact_min,
+                ecoimpact_max: e.ecoimpact_max,
+                noise_max_db: e.noise_max_db,
+                emf_max_vpm: e.emf_max_vpm,
+                altitude,
+            },
+            host_budget: self.host_budget.clone(),
+            eco_band: self.eco_band.clone(),
+            dw_ceiling: self.dw_ceiling.clone(),
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.gains.eta_m,
+            eta_k: self.gains.eta_k,
+            eta_w: self.gains.eta_w,
+            eta_b: self.gains.eta_b,
+            eta_p: self.gains.eta_p,
+            eta_dw: self.gains.eta_dw,
+            eta_noise: self.gains.eta_noise,
+            eta_emf: self.gains.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule.clone(),
+            duty_schedule: self
+                .duty_schedule
+                .clone()
+                .map(|s| ClockedSchedule::new(s, SystemClock)),
+            geo_weighting: self.geo_weighting.clone(),
+            airflow: self.airflow.clone(),
+            agent_gate: None,
+            corridor: None,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: None,
+            bee_guard: NoBeeGuard,
+            metrics: NoMetrics,
+        })
+    }
+
+    /// Validate and overwrite everything `build` sets from the config on
+    /// an existing controller. Its altitude provider, agent gate, corridor
+    /// membership, stability monitor, bee guard and metrics are kept. On
+    /// error the controller is unchanged.
+    pub fn apply<A, G, R>(
+        &self,
+        controller: &mut CorridorController<
+            RectSafetyEnvelope<A>,
+            SimpleHostBudget,
+            ThresholdEcoBand,
+            SimpleDwCeiling,
+            G,
+            R,
+        >,
+    ) -> Result<(), ConfigError>
+    where
+        A: AltitudeProvider,
+        G: BeeGuard,
+        R: MetricsRecorder,
+    {
+        self.validate()?;
+        let e = &self.envelope;
+        let envelope = &mut controller.envelope;
+        envelope.u_min = e.u_min;
+        envelope.u_max = e.u_max;
+        envelope.z_min_m = e.z_min_m;
+        envelope.z_max_m = e.z_max_m;
+        envelope.ecoimpact_min = e.ecoimpact_min;
+        envelope.ecoimpact_max = e.ecoimpact_max;
+        envelope.noise_max_db = e.noise_max_db;
+        envelope.emf_max_vpm = e.emf_max_vpm;
+        controller.host_budget = self.host_budget.clone();
+        controller.eco_band = self.eco_band.clone();
+        controller.dw_ceiling = self.dw_ceiling.clone();
+        controller.corridor_area_m2 = self.corridor_area_m2;
+        controller.m_ref_kg = self.m_ref_kg;
+        controller.k_ref_nb = self.k_ref_nb;
+        controller.eta_m = self.gains.eta_m;
+        controller.eta_k = self.gains.eta_k;
+        controller.eta_w = self.gains.eta_w;
+        controller.eta_b = self.gains.eta_b;
+        controller.eta_p = self.gains.eta_p;
+        controller.eta_dw = self.gains.eta_dw;
+        controller.eta_noise = self.gains.eta_noise;
+        controller.eta_emf = self.gains.eta_emf;
+        controller.noise_ref_db = self.noise_ref_db;
+        controller.emf_ref_vpm = self.emf_ref_vpm;
+        controller.gain_schedule = self.gain_schedule.clone();
+        controller.duty_schedule = self
+            .duty_schedule
+            .clone()
+            .map(|s| ClockedSchedule::new(s, SystemClock));
+        controller.geo_weighting = self.geo_weighting.clone();
+        controller.airflow = self.airflow.clone();
+        controller.eco_load_mode = self.eco_load_mode;
+        controller.slew_limit = self.slew_limit;
+        controller.violation_policy = self.violation_policy;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConstAltitude;
+
+    const PHOENIX: &str = include_str!("../config/phoenix.toml");
+
+    #[test]
+    fn phoenix_config_round_trips() {
+        let cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        assert_eq!(cfg.gains.eta_p, 0.05);
+        assert_eq!(cfg.slew_limit, Some(SlewLimit::symmetric(0.25)));
+
+        let json = serde_json::to_string(&cfg).unwrap();
+        assert_eq!(ControllerConfig::from_json(&json).unwrap(), cfg);
+        let toml = toml::to_string(&cfg).unwrap();
+        assert_eq!(ControllerConfig::from_toml(&toml).unwrap(), cfg);
+
+        let controller = cfg.build(ConstAltitude(331.0)).unwrap();
+        assert_eq!(controller.eta_dw, 0.1);
+        assert_eq!(controller.envelope.z_max_m, 600.0);
+    }
+
+    #[test]
+    fn inverted_band_thresholds_are_rejected() {
+        let mut cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        cfg.eco_band.theta_amber_red = 0.4;
+        let err = cfg.build(ConstAltitude(331.0)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                field: "eco_band.theta_amber_red",
+                ..
+            }
+        ));
+
+        let toml = PHOENIX.replace("theta_amber_red = 1.0", "theta_amber_red = 0.4");
+        assert_eq!(ControllerConfig::from_toml(&toml), Err(err));
+    }
+
+    #[test]
+    fn negative_gain_and_inverted_envelope_are_rejected() {
+        let mut cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        cfg.gains.eta_b = -0.2;
+        assert!(matches!(
+            cfg.validate(),
+            Err(ConfigError::Invalid {
+                field: "gains.eta_b",
+                ..
+            })
+        ));
+
+        let mut cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        cfg.gains.eta_noise = 0.1;
+        assert!(matches!(
+            cfg.validate(),
+            Err(ConfigError::Invalid {
+                field: "noise_ref_db",
+                ..
+            })
+        ));
+        cfg.noise_ref_db = 60.0;
+        assert_eq!(cfg.validate(), Ok(()));
+
+        let mut cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        cfg.envelope.u_min = 0.9;
+        cfg.envelope.u_max = 0.1;
+        assert!(matches!(
+            cfg.validate(),
+            Err(ConfigError::Invalid {
+                field: "envelope.u_min",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn violation_policy_defaults_to_error_and_validates_safe_duty() {
+        let mut cfg = ControllerConfig::from_toml(PHOENIX).unwrap();
+        assert_eq!(cfg.violation_policy, ViolationPolicy::Error);
+
+        let toml =
+            format!("violation_policy = {{ ClampToSafe = {{ safe_duty = 1.5 }} }}\n{PHOENIX}");
+        assert!(matches!(
+            ControllerConfig::from_toml(&toml),
+            Err(ConfigError::Invalid {
+                field: "violation_policy.safe_duty",
+                ..
+            })
+        ));
+
+        cfg.violation_policy = ViolationPolicy::ClampToSafe { safe_duty: None };
+        let json = serde_json::to_string(&cfg).unwrap();
+        let controller = ControllerConfig::from_json(&json)
+            .unwrap()
+            .build(ConstAltitude(331.0))
+            .unwrap();
+        assert_eq!(controller.violation_policy, cfg.violation_policy);
+    }
+
+    #[test]
+    fn malformed_toml_is_a_parse_error() {
+        assert!(matches!(
+            ControllerConfig::from_toml("corridor_area_m2 = \"wide\""),
+            Err(ConfigError::Toml(_))
+        ));
+    }
+}
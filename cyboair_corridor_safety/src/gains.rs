@@ -0,0 +1,247 @@
+//! Per-node gain overrides and gain scheduling by eco band.
+//!
+//! By default every node runs Equation 5 with the controller's `eta_*`
+//! fields. A [`GainSchedule`] replaces them, as a complete [`GainConfig`],
+//! in this order:
+//!
+//! 1. the node's entry in `nodes`, keyed by `machine_id`;
+//! 2. the entry in `bands` for the band of the update;
+//! 3. the controller's own gains.
+//!
+//! So a school-zone node can respond harder to mass in every band, while
+//! the rest of the corridor is damped in Red to avoid overshoot. Each
+//! `UpdateReport` names the [`GainSource`] the law ran with.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::GainConfig;
+use crate::EcoBand;
+
+/// Where the gains of one duty update came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GainSource {
+    /// The node's entry in `GainSchedule::nodes`.
+    NodeOverride,
+    /// The `GainSchedule::bands` entry for this band.
+    BandSchedule(EcoBand),
+    /// The controller's `eta_*` fields.
+    ControllerDefault,
+}
+
+/// Gains for each eco band; a missing band uses the controller's.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BandGains {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub green: Option<GainConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amber: Option<GainConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub red: Option<GainConfig>,
+}
+
+impl BandGains {
+    pub fn get(&self, band: EcoBand) -> Option<&GainConfig> {
+        match band {
+            EcoBand::Green => self.green.as_ref(),
+            EcoBand::Amber => self.amber.as_ref(),
+            EcoBand::Red => self.red.as_ref(),
+        }
+    }
+
+    /// Set entries, as (band, gains).
+    pub fn iter(&self) -> impl Iterator<Item = (EcoBand, &GainConfig)> {
+        [EcoBand::Green, EcoBand::Amber, EcoBand::Red]
+            .into_iter()
+            .filter_map(|band| self.get(band).map(|g| (band, g)))
+    }
+}
+
+/// Gain overrides by node and by band; see the module docs for the order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GainSchedule {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub nodes: BTreeMap<String, GainConfig>,
+    #[serde(default)]
+    pub bands: BandGains,
+}
+
+impl GainSchedule {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.bands.iter().next().is_none()
+    }
+
+    /// Gains for `machine_id` in `band`, or `None` if the controller's
+    /// own apply.
+    pub fn resolve(&self, machine_id: &str, band: EcoBand) -> Option<(&GainConfig, GainSource)> {
+        if let Some(gains) = self.nodes.get(machine_id) {
+            return Some((gains, GainSource::NodeOverride));
+        }
+        self.bands
+            .get(band)
+            .map(|gains| (gains, GainSource::BandSchedule(band)))
+    }
+
+    /// Every gain set with a label for error messages, nodes first.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (String, &GainConfig)> {
+        self.nodes
+            .iter()
+            .map(|(id, g)| (format!("nodes.{id}"), g))
+            .chain(
+                self.bands
+                    .iter()
+                    .map(|(band, g)| (format!("bands.{}", band_key(band)), g)),
+            )
+    }
+}
+
+fn band_key(band: EcoBand) -> &'static str {
+    match band {
+        EcoBand::Green => "green",
+        EcoBand::Amber => "amber",
+        EcoBand::Red => "red",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{
+        ConstAltitude, ControllerConfig, CorridorController, RectSafetyEnvelope, SimpleDwCeiling,
+        SimpleHostBudget, ThresholdEcoBand,
+    };
+
+    type Controller = CorridorController<
+        RectSafetyEnvelope<ConstAltitude>,
+        SimpleHostBudget,
+        ThresholdEcoBand,
+        SimpleDwCeiling,
+    >;
+
+    /// Duty after each of five Amber steps, per Phoenix node.
+    fn trajectories(controller: &Controller) -> Vec<Vec<f64>> {
+        let mut nodes = phoenix_nodes();
+        let mut duties = vec![Vec::new(); nodes.len()];
+        for _ in 0..5 {
+            for (node, duty) in nodes.iter_mut().zip(&mut duties) {
+                controller
+                    .update_node_duty(node, EcoBand::Amber, 0.0)
+                    .unwrap();
+                duty.push(node.duty_cycle);
+            }
+        }
+        duties
+    }
+
+    fn scaled(base: &GainConfig, mass: f64) -> GainConfig {
+        GainConfig {
+            eta_m: base.eta_m * mass,
+            eta_k: base.eta_k * mass,
+            ..base.clone()
+        }
+    }
+
+    #[test]
+    fn node_override_beats_band_schedule_beats_defaults() {
+        let mut controller = phoenix_controller();
+        let defaults = controller.default_gains();
+        let school = scaled(&defaults, 3.0);
+        let red = scaled(&defaults, 0.5);
+        controller
+            .gain_schedule
+            .nodes
+            .insert("CYB-AIR-SCHOOL-05".into(), school.clone());
+        controller.gain_schedule.bands.red = Some(red.clone());
+
+        let resolve = |id, band| controller.effective_gains(id, band);
+        assert_eq!(
+            resolve("CYB-AIR-SCHOOL-05", EcoBand::Red),
+            (school.clone(), GainSource::NodeOverride)
+        );
+        assert_eq!(
+            resolve("CYB-AIR-CANOPY-01", EcoBand::Red),
+            (red, GainSource::BandSchedule(EcoBand::Red))
+        );
+        assert_eq!(
+            resolve("CYB-AIR-CANOPY-01", EcoBand::Amber),
+            (defaults, GainSource::ControllerDefault)
+        );
+
+        let mut node = phoenix_nodes()[1].clone();
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+        assert_eq!(report.gain_source, Some(GainSource::NodeOverride));
+    }
+
+    #[test]
+    fn override_changes_only_its_node() {
+        let base = phoenix_controller();
+        let mut tuned = phoenix_controller();
+        // An industrial node saving power: only the power term acts.
+        let gains = GainConfig {
+            eta_m: 0.0,
+            eta_k: 0.0,
+            eta_w: 0.0,
+            eta_b: 0.0,
+            eta_p: 0.5,
+            ..tuned.default_gains()
+        };
+        tuned
+            .gain_schedule
+            .nodes
+            .insert("CYB-AIR-CANOPY-01".into(), gains);
+
+        let (before, after) = (trajectories(&base), trajectories(&tuned));
+        assert_ne!(before[0], after[0]);
+        assert!(after[0].windows(2).all(|w| w[1] <= w[0]), "{after:?}");
+        assert_eq!(before[1], after[1]);
+    }
+
+    #[test]
+    fn schedule_loads_from_toml() {
+        let toml = format!(
+            "{}\n{}",
+            include_str!("../config/phoenix.toml"),
+            r#"
+[gain_schedule.nodes.CYB-AIR-SCHOOL-05]
+eta_m = 0.3
+eta_k = 0.3
+eta_w = 0.2
+eta_b = 0.2
+eta_p = 0.05
+eta_dw = 0.1
+
+[gain_schedule.bands.red]
+eta_m = 0.05
+eta_k = 0.05
+eta_w = 0.1
+eta_b = 0.1
+eta_p = 0.05
+eta_dw = 0.1
+"#
+        );
+        let cfg = ControllerConfig::from_toml(&toml).unwrap();
+        assert_eq!(cfg.gain_schedule.nodes["CYB-AIR-SCHOOL-05"].eta_m, 0.3);
+        assert_eq!(cfg.gain_schedule.bands.red.as_ref().unwrap().eta_m, 0.05);
+        assert_eq!(
+            ControllerConfig::from_toml(&toml::to_string(&cfg).unwrap()).unwrap(),
+            cfg
+        );
+
+        let bad = toml.replace("eta_m = 0.05", "eta_m = -0.05");
+        assert!(matches!(
+            ControllerConfig::from_toml(&bad),
+            Err(crate::ConfigError::Invalid {
+                field: "gain_schedule",
+                reason,
+            }) if reason.starts_with("bands.red.eta_m")
+        ));
+    }
+}
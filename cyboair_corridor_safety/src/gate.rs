@@ -0,0 +1,223 @@
+//! Duty ceilings from the operating agent's blood-gate level.
+//!
+//! Cybernet agents carry a `blood_gate_level` from 0 (revoked) through 1
+//! (read-only), 2 (limited write) to 3 (full). A [`GateDutyPolicy`] maps
+//! each level to the highest duty the agent may actuate; a
+//! [`GateResolver`] finds the level for a machine or agent. An agent the
+//! resolver does not know gets the most restrictive cap in the policy.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+
+/// Highest blood-gate level; anything above counts as this.
+pub const MAX_GATE_LEVEL: u8 = 3;
+
+/// Maximum duty per blood-gate level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GateDutyPolicy {
+    /// Indexed by level, 0 to [`MAX_GATE_LEVEL`].
+    pub max_duty: [f64; 4],
+}
+
+impl Default for GateDutyPolicy {
+    /// Revoked and read-only agents only sense; limited write runs to 0.4.
+    fn default() -> Self {
+        GateDutyPolicy {
+            max_duty: [0.0, 0.0, 0.4, 1.0],
+        }
+    }
+}
+
+impl GateDutyPolicy {
+    /// Cap for `level`; the lowest cap if the level is unknown.
+    pub fn cap(&self, level: Option<u8>) -> f64 {
+        match level {
+            Some(level) => self.max_duty[usize::from(level.min(MAX_GATE_LEVEL))],
+            None => self.max_duty.iter().copied().fold(f64::INFINITY, f64::min),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (level, &cap) in self.max_duty.iter().enumerate() {
+            if !(0.0..=1.0).contains(&cap) {
+                return Err(ConfigError::Invalid {
+                    field: "gate_policy",
+                    reason: format!("max_duty[{level}] must be in [0, 1], got {cap}"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the blood-gate level behind a machine id or agent id.
+pub trait GateResolver {
+    /// `None` if the key has no known agent.
+    fn gate_level(&self, key: &str) -> Option<u8>;
+}
+
+/// Gate levels keyed by machine or agent id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapGateResolver {
+    pub levels: HashMap<String, u8>,
+}
+
+impl MapGateResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_level(mut self, key: impl Into<String>, level: u8) -> Self {
+        self.levels.insert(key.into(), level);
+        self
+    }
+}
+
+impl GateResolver for MapGateResolver {
+    fn gate_level(&self, key: &str) -> Option<u8> {
+        self.levels.get(key).copied()
+    }
+}
+
+/// A policy and the resolver it reads levels from.
+#[derive(Clone)]
+pub struct AgentGate {
+    pub policy: GateDutyPolicy,
+    pub resolver: Arc<dyn GateResolver + Send + Sync>,
+}
+
+impl AgentGate {
+    pub fn new(
+        policy: GateDutyPolicy,
+        resolver: impl GateResolver + Send + Sync + 'static,
+    ) -> Self {
+        AgentGate {
+            policy,
+            resolver: Arc::new(resolver),
+        }
+    }
+
+    /// Level and cap for `key`.
+    pub fn lookup(&self, key: &str) -> (Option<u8>, f64) {
+        let level = self.resolver.gate_level(key);
+        (level, self.policy.cap(level))
+    }
+
+    /// `proposed` capped for `key`.
+    pub fn cap(&self, key: &str, proposed: f64) -> GateCap {
+        let (level, max_duty) = self.lookup(key);
+        GateCap {
+            level,
+            max_duty,
+            sensing_only: max_duty == 0.0,
+            proposed,
+            applied: proposed.min(max_duty),
+        }
+    }
+}
+
+impl fmt::Debug for AgentGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentGate")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The gate cap checked during a duty update.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GateCap {
+    /// `None` if the resolver did not know the agent.
+    pub level: Option<u8>,
+    pub max_duty: f64,
+    pub sensing_only: bool,
+    /// Duty before the cap.
+    pub proposed: f64,
+    /// Duty after it; below `proposed` only if the cap bound.
+    pub applied: f64,
+}
+
+impl GateCap {
+    pub fn bound(&self) -> bool {
+        self.applied < self.proposed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::EcoBand;
+
+    fn gate() -> AgentGate {
+        AgentGate::new(
+            GateDutyPolicy::default(),
+            MapGateResolver::new()
+                .with_level("CYB-AIR-CANOPY-00", 0)
+                .with_level("CYB-AIR-CANOPY-01", 1)
+                .with_level("CYB-AIR-CANOPY-02", 2)
+                .with_level("CYB-AIR-CANOPY-03", 3)
+                .with_level("CYB-AIR-CANOPY-09", 9),
+        )
+    }
+
+    #[test]
+    fn every_gate_level_caps_the_updated_duty() {
+        let mut controller = phoenix_controller();
+        controller.agent_gate = Some(gate());
+        let update = |machine_id: &str| {
+            let mut node = phoenix_nodes()[0].clone();
+            node.row.machine_id = machine_id.into();
+            node.duty_cycle = 0.9;
+            controller
+                .update_node_duty(&mut node, EcoBand::Red, 0.0)
+                .unwrap()
+        };
+
+        for (machine_id, level, max_duty) in [
+            ("CYB-AIR-CANOPY-00", Some(0), 0.0),
+            ("CYB-AIR-CANOPY-01", Some(1), 0.0),
+            ("CYB-AIR-CANOPY-02", Some(2), 0.4),
+            ("CYB-AIR-CANOPY-03", Some(3), 1.0),
+            ("CYB-AIR-CANOPY-09", Some(9), 1.0),
+            // Unknown agents get the most restrictive cap.
+            ("CYB-AIR-GHOST-99", None, 0.0),
+        ] {
+            let report = update(machine_id);
+            let cap = report.gate_cap.unwrap();
+            assert_eq!((cap.level, cap.max_duty), (level, max_duty), "{machine_id}");
+            assert_eq!(cap.sensing_only, max_duty == 0.0);
+            assert_eq!(report.duty_after, cap.proposed.min(max_duty));
+            // A gate cap is not an envelope clamp.
+            assert!(report.violation.is_none() && report.bee_rights_clamped.is_none());
+        }
+        assert!(update("CYB-AIR-CANOPY-02").gate_cap.unwrap().bound());
+        assert!(!update("CYB-AIR-CANOPY-03").gate_cap.unwrap().bound());
+
+        let mut node = phoenix_nodes()[0].clone();
+        let ungated = phoenix_controller()
+            .update_node_duty(&mut node, EcoBand::Red, 0.0)
+            .unwrap();
+        assert!(ungated.gate_cap.is_none());
+    }
+
+    #[test]
+    fn caps_outside_the_unit_interval_are_rejected() {
+        assert_eq!(GateDutyPolicy::default().validate(), Ok(()));
+        let policy = GateDutyPolicy {
+            max_duty: [0.0, 0.0, 1.4, 1.0],
+        };
+        assert!(matches!(
+            policy.validate(),
+            Err(ConfigError::Invalid { field: "gate_policy", reason }) if reason.contains("max_duty[2]")
+        ));
+        assert_eq!(policy.cap(None), 0.0);
+    }
+}
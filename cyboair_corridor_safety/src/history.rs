@@ -0,0 +1,470 @@
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, LateData, SystemClock};
+use crate::snapshot::{BandState, SnapshotError};
+use crate::{EcoBand, EcoBandClassifier};
+
+/// Change of corridor eco-band at `at_ms` (Unix milliseconds).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BandTransition {
+    /// `None` for the first band ever observed.
+    pub from: Option<EcoBand>,
+    pub to: EcoBand,
+    pub at_ms: u64,
+}
+
+/// Eco-band transitions of one corridor over time.
+///
+/// Each transition's band holds until the next transition, and the latest
+/// one holds until the last observation; time after that is unknown and
+/// never counted. Retention drops the oldest transitions first, so the
+/// history effectively starts at the oldest one kept. Serializable so it
+/// can be exported for reporting or persisted across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BandHistory {
+    /// Most transitions kept (at least 1).
+    pub max_transitions: usize,
+    /// Transitions whose band ended more than this long before the last
+    /// observation are dropped; `None` keeps them up to `max_transitions`.
+    pub max_age_ms: Option<u64>,
+    /// How far an observation may trail the last one and still be placed
+    /// at its own time by `record_sample`; `None` refuses any that trail.
+    pub max_lateness_ms: Option<u64>,
+    transitions: VecDeque<BandTransition>,
+    last_observed_ms: Option<u64>,
+    /// Observations within `max_lateness_ms` of the last, oldest first;
+    /// the transitions from the first of them on are rebuilt from these
+    /// when a late one arrives.
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    recent: VecDeque<(u64, EcoBand)>,
+}
+
+impl BandHistory {
+    pub fn new(max_transitions: usize) -> Self {
+        BandHistory {
+            max_transitions,
+            max_age_ms: None,
+            max_lateness_ms: None,
+            transitions: VecDeque::new(),
+            last_observed_ms: None,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Also drop transitions older than `max_age_ms`.
+    pub fn with_max_age_ms(mut self, max_age_ms: u64) -> Self {
+        self.max_age_ms = Some(max_age_ms);
+        self
+    }
+
+    /// Accept observations up to `max_lateness_ms` behind the last one.
+    pub fn with_max_lateness_ms(mut self, max_lateness_ms: u64) -> Self {
+        self.max_lateness_ms = Some(max_lateness_ms);
+        self
+    }
+
+    /// Record that the corridor was in `band` at `at_ms`, returning the
+    /// transition if the band changed. An observation too late for
+    /// `record_sample` is taken as the last observation, so time never
+    /// runs backwards within the history.
+    pub fn record(&mut self, band: EcoBand, at_ms: u64) -> Option<BandTransition> {
+        match self.record_sample(band, at_ms) {
+            Ok(transition) => transition,
+            Err(late) => self.record_sample(band, late.newest_ms).ok().flatten(),
+        }
+    }
+
+    /// Record that the corridor was in `band` at `at_ms`, which may be
+    /// before the last observation. A late band holds from `at_ms` until
+    /// the next later observation, and the transitions after it are
+    /// rebuilt to match. Returns the transition at `at_ms`, if any.
+    pub fn record_sample(
+        &mut self,
+        band: EcoBand,
+        at_ms: u64,
+    ) -> Result<Option<BandTransition>, LateData> {
+        let last = match self.last_observed_ms {
+            Some(last) if at_ms < last => last,
+            _ => {
+                self.remember(band, at_ms);
+                return Ok(self.push(band, at_ms));
+            }
+        };
+        LateData::check(at_ms, last, self.max_lateness_ms.unwrap_or(0))?;
+        self.remember(band, at_ms);
+        let replay_from = self.recent.front().map_or(at_ms, |&(t, _)| t);
+        while self
+            .transitions
+            .back()
+            .is_some_and(|t| t.at_ms >= replay_from)
+        {
+            self.transitions.pop_back();
+        }
+        for (t, b) in self.recent.clone() {
+            self.push(b, t);
+        }
+        Ok(self.transitions.iter().find(|t| t.at_ms == at_ms).copied())
+    }
+
+    /// Keep `band` at `at_ms` in `recent`, in time order, and forget
+    /// observations no late one can precede any more.
+    fn remember(&mut self, band: EcoBand, at_ms: u64) {
+        let Some(max_lateness) = self.max_lateness_ms else {
+            return;
+        };
+        let i = self.recent.partition_point(|&(t, _)| t <= at_ms);
+        self.recent.insert(i, (at_ms, band));
+        let newest = self.last_observed_ms.unwrap_or(at_ms).max(at_ms);
+        let cutoff = newest.saturating_sub(max_lateness);
+        while self.recent.front().is_some_and(|&(t, _)| t < cutoff) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn push(&mut self, band: EcoBand, at_ms: u64) -> Option<BandTransition> {
+        self.last_observed_ms = Some(self.last_observed_ms.map_or(at_ms, |last| last.max(at_ms)));
+        let from = self.current();
+        let transition = (from != Some(band)).then(|| {
+            let t = BandTransition {
+                from,
+                to: band,
+                at_ms,
+            };
+            self.transitions.push_back(t);
+            t
+        });
+        self.evict();
+        transition
+    }
+
+    fn evict(&mut self) {
+        while self.transitions.len() > self.max_transitions.max(1) {
+            self.transitions.pop_front();
+        }
+        if let (Some(max_age), Some(last)) = (self.max_age_ms, self.last_observed_ms) {
+            let cutoff = last.saturating_sub(max_age);
+            // Keep the transition whose band spans the cutoff.
+            while self.transitions.len() > 1 && self.transitions[1].at_ms <= cutoff {
+                self.transitions.pop_front();
+            }
+        }
+    }
+
+    /// Band at the last observation.
+    pub fn current(&self) -> Option<EcoBand> {
+        self.transitions.back().map(|t| t.to)
+    }
+
+    pub fn last_observed_ms(&self) -> Option<u64> {
+        self.last_observed_ms
+    }
+
+    /// Retained transitions, oldest first.
+    pub fn transitions(&self) -> impl Iterator<Item = &BandTransition> {
+        self.transitions.iter()
+    }
+
+    /// Retained transitions that happened within `range`.
+    pub fn transitions_in(&self, range: Range<u64>) -> impl Iterator<Item = &BandTransition> {
+        self.transitions
+            .iter()
+            .filter(move |t| range.contains(&t.at_ms))
+    }
+
+    /// Milliseconds of `range` the corridor is known to have spent in `band`.
+    pub fn total_time_in(&self, band: EcoBand, range: Range<u64>) -> u64 {
+        let Some(last) = self.last_observed_ms else {
+            return 0;
+        };
+        let ends = self
+            .transitions
+            .iter()
+            .skip(1)
+            .map(|t| t.at_ms)
+            .chain([last]);
+        self.transitions
+            .iter()
+            .zip(ends)
+            .filter(|(t, _)| t.to == band)
+            .map(|(t, end)| end.min(range.end).saturating_sub(t.at_ms.max(range.start)))
+            .sum()
+    }
+}
+
+/// Classifier that records every outcome of `inner` in a [`BandHistory`],
+/// stamped by `clock`.
+///
+/// Like `HystereticEcoBand`, the history sits behind interior mutability
+/// so the wrapper can be a `CorridorController`'s `eco_band` and be fed by
+/// each `run_steps` tick; it is `!Sync`, so use one per control loop.
+#[derive(Debug, Clone)]
+pub struct RecordingEcoBand<B, C = SystemClock> {
+    pub inner: B,
+    pub clock: C,
+    history: RefCell<BandHistory>,
+}
+
+impl<B: EcoBandClassifier, C: Clock> RecordingEcoBand<B, C> {
+    pub fn new(inner: B, clock: C, history: BandHistory) -> Self {
+        RecordingEcoBand {
+            inner,
+            clock,
+            history: RefCell::new(history),
+        }
+    }
+
+    /// History so far; do not hold it across a `classify` call.
+    pub fn history(&self) -> Ref<'_, BandHistory> {
+        self.history.borrow()
+    }
+
+    /// Unwrap into the classifier and the recorded history.
+    pub fn into_parts(self) -> (B, BandHistory) {
+        (self.inner, self.history.into_inner())
+    }
+}
+
+impl<B: EcoBandClassifier, C: Clock> EcoBandClassifier for RecordingEcoBand<B, C> {
+    fn classify(&self, eco_load: f64) -> EcoBand {
+        let band = self.inner.classify(eco_load);
+        self.history
+            .borrow_mut()
+            .record(band, self.clock.now_unix_ms());
+        band
+    }
+
+    fn band_gain(&self, band: EcoBand) -> f64 {
+        self.inner.band_gain(band)
+    }
+
+    fn band_state(&self) -> BandState {
+        BandState {
+            history: Some(self.history().clone()),
+            ..self.inner.band_state()
+        }
+    }
+
+    fn restore_band_state(&mut self, state: &BandState) -> Result<(), SnapshotError> {
+        let history = state.history.clone().ok_or(SnapshotError::Missing {
+            component: "band history",
+        })?;
+        self.inner.restore_band_state(state)?;
+        *self.history.get_mut() = history;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{StepInput, ThresholdEcoBand};
+
+    const MINUTE_MS: u64 = 60_000;
+    const HOUR_MS: u64 = 60 * MINUTE_MS;
+    const DAY_START_MS: u64 = 1_750_000_000_000;
+
+    fn thresholds() -> ThresholdEcoBand {
+        ThresholdEcoBand {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+            gain_green: 0.0,
+            gain_amber: 0.2,
+            gain_red: 0.5,
+        }
+    }
+
+    /// Green overnight, amber and red peaks through the day.
+    fn load_at(minute: u64) -> f64 {
+        match minute / 60 {
+            0..=5 | 21.. => 0.2,
+            6..=8 | 12..=17 => 0.7,
+            _ => 1.3,
+        }
+    }
+
+    /// Classify every five minutes from midnight to the next midnight.
+    fn simulate_day(history: BandHistory) -> BandHistory {
+        let clock = ManualClock::new(DAY_START_MS);
+        let classifier = RecordingEcoBand::new(thresholds(), clock.clone(), history);
+        for minute in (0..=24 * 60).step_by(5) {
+            clock.set(DAY_START_MS + minute * MINUTE_MS);
+            classifier.classify(load_at(minute));
+        }
+        classifier.into_parts().1
+    }
+
+    #[test]
+    fn day_of_alternating_loads_adds_up_per_band() {
+        let history = simulate_day(BandHistory::new(64));
+        let day = DAY_START_MS..DAY_START_MS + 24 * HOUR_MS;
+
+        assert_eq!(
+            history.total_time_in(EcoBand::Green, day.clone()),
+            9 * HOUR_MS
+        );
+        assert_eq!(
+            history.total_time_in(EcoBand::Amber, day.clone()),
+            9 * HOUR_MS
+        );
+        assert_eq!(
+            history.total_time_in(EcoBand::Red, day.clone()),
+            6 * HOUR_MS
+        );
+
+        // 10:00-19:00 overlaps the 09:00-12:00 and 18:00-21:00 red peaks.
+        let afternoon = DAY_START_MS + 10 * HOUR_MS..DAY_START_MS + 19 * HOUR_MS;
+        assert_eq!(history.total_time_in(EcoBand::Red, afternoon), 3 * HOUR_MS);
+
+        let bands: Vec<_> = history
+            .transitions_in(day)
+            .map(|t| (t.from, t.to))
+            .collect();
+        assert_eq!(
+            bands,
+            [
+                (None, EcoBand::Green),
+                (Some(EcoBand::Green), EcoBand::Amber),
+                (Some(EcoBand::Amber), EcoBand::Red),
+                (Some(EcoBand::Red), EcoBand::Amber),
+                (Some(EcoBand::Amber), EcoBand::Red),
+                (Some(EcoBand::Red), EcoBand::Green),
+            ]
+        );
+        assert_eq!(history.current(), Some(EcoBand::Green));
+
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(serde_json::from_str::<BandHistory>(&json).unwrap(), history);
+    }
+
+    #[test]
+    fn retention_bounds_the_history() {
+        let history = simulate_day(BandHistory::new(2));
+        let day = DAY_START_MS..DAY_START_MS + 24 * HOUR_MS;
+        assert_eq!(history.transitions().count(), 2);
+        // Only the 18:00 red peak and the 21:00 return to green remain.
+        assert_eq!(
+            history.total_time_in(EcoBand::Red, day.clone()),
+            3 * HOUR_MS
+        );
+        assert_eq!(history.total_time_in(EcoBand::Amber, day), 0);
+
+        let history = simulate_day(BandHistory::new(64).with_max_age_ms(4 * HOUR_MS));
+        let first = history.transitions().next().unwrap();
+        // 20:00 cutoff: the red band that spans it is kept.
+        assert_eq!(first.at_ms, DAY_START_MS + 18 * HOUR_MS);
+        assert_eq!(first.to, EcoBand::Red);
+    }
+
+    #[test]
+    fn late_timestamps_do_not_run_time_backwards() {
+        let mut history = BandHistory::new(8);
+        history.record(EcoBand::Red, 1_000);
+        let t = history.record(EcoBand::Green, 500).unwrap();
+        assert_eq!(t.at_ms, 1_000);
+        assert_eq!(history.total_time_in(EcoBand::Red, 0..u64::MAX), 0);
+        assert_eq!(history.record(EcoBand::Green, 4_000), None);
+        assert_eq!(history.total_time_in(EcoBand::Green, 0..u64::MAX), 3_000);
+    }
+
+    #[test]
+    fn late_observations_are_placed_at_their_own_time() {
+        // Green at 0, 10 and 50 minutes; the 20-40 minute batch is delayed.
+        let mut history = BandHistory::new(16).with_max_lateness_ms(40 * MINUTE_MS);
+        for minute in [0, 10, 50] {
+            history.record(EcoBand::Green, minute * MINUTE_MS);
+        }
+        for (minute, band) in [(20, EcoBand::Red), (30, EcoBand::Red), (40, EcoBand::Green)] {
+            history.record_sample(band, minute * MINUTE_MS).unwrap();
+        }
+        let at = |history: &BandHistory| -> Vec<_> {
+            history
+                .transitions()
+                .map(|t| (t.to, t.at_ms / MINUTE_MS))
+                .collect()
+        };
+        assert_eq!(
+            at(&history),
+            [
+                (EcoBand::Green, 0),
+                (EcoBand::Red, 20),
+                (EcoBand::Green, 40)
+            ]
+        );
+        assert_eq!(history.last_observed_ms(), Some(50 * MINUTE_MS));
+        assert_eq!(
+            history.total_time_in(EcoBand::Red, 0..u64::MAX),
+            20 * MINUTE_MS
+        );
+
+        // A late band between two greens splits the span.
+        let t = history
+            .record_sample(EcoBand::Amber, 45 * MINUTE_MS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(t.from, Some(EcoBand::Green));
+        assert_eq!(
+            at(&history)[2..],
+            [
+                (EcoBand::Green, 40),
+                (EcoBand::Amber, 45),
+                (EcoBand::Green, 50)
+            ]
+        );
+        assert_eq!(history.current(), Some(EcoBand::Green));
+
+        let before = history.clone();
+        assert_eq!(
+            history.record_sample(EcoBand::Red, 5 * MINUTE_MS),
+            Err(LateData {
+                sample_ms: 5 * MINUTE_MS,
+                newest_ms: 50 * MINUTE_MS,
+                max_lateness_ms: 40 * MINUTE_MS,
+            })
+        );
+        assert_eq!(history, before);
+    }
+
+    #[test]
+    fn controller_run_populates_history_each_step() {
+        let clock = ManualClock::new(DAY_START_MS);
+        let base = phoenix_controller();
+        let recording =
+            RecordingEcoBand::new(base.eco_band.clone(), clock.clone(), BandHistory::new(64));
+        let controller = base.with_eco_band(recording);
+        let mut nodes = phoenix_nodes();
+
+        let log = controller
+            .run_steps(&mut nodes, 12, |step| {
+                clock.set(DAY_START_MS + step as u64 * 5 * MINUTE_MS);
+                StepInput {
+                    phi_dw: 5.0e-7,
+                    alpha_m: 0.5,
+                    alpha_k: 0.5,
+                    // Alternate between the raw load and most of it offset.
+                    eco_offset: if step / 3 % 2 == 0 { 0.0 } else { 1.0e3 },
+                    wind: None,
+                }
+            })
+            .unwrap();
+
+        let history = controller.eco_band.history();
+        assert_eq!(history.current(), Some(log[11].band));
+        for band in [EcoBand::Green, EcoBand::Amber, EcoBand::Red] {
+            let steps = log[..11].iter().filter(|r| r.band == band).count() as u64;
+            assert_eq!(
+                history.total_time_in(band, 0..u64::MAX),
+                steps * 5 * MINUTE_MS,
+                "{band:?}"
+            );
+        }
+        assert!(history.transitions().count() > 1);
+    }
+}
@@ -0,0 +1,706 @@
+//! Per-machine, per-pollutant karma totals for stakeholder settlement.
+//!
+//! A [`KarmaLedger`] is fed each step's mass and karma and keeps one bucket
+//! per machine, pollutant and period ([`LedgerPeriod`], UTC calendar
+//! hours, days or months). Exports list buckets by machine, then period,
+//! then pollutant, with the columns of [`LedgerRow`] in declaration order.
+//!
+//! Precision: totals are summed with compensated summation, so they stay
+//! within a few ulps of the exact sum whatever the number or order of
+//! steps. Nothing is rounded on export; CSV and JSON write each total as
+//! the shortest decimal that parses back to the same `f64`. Rounding to
+//! settlement precision is left to the consumer.
+//!
+//! Karma from nodes flagged as suspicious (see [`anomaly`](crate::anomaly))
+//! is credited at [`KarmaLedger::with_suspicion_discount`]'s factor, and
+//! counted in the bucket's `flagged_steps`.
+//!
+//! Ledgers from several gateways are combined with [`KarmaLedger::merge`].
+//! Two ledgers both holding a bucket for the same machine and period, for
+//! any pollutant, means two gateways claim the same machine-time; that is
+//! a conflict and nothing is merged.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::locale::{LocaleError, ReportLocale};
+use crate::summation::KahanSum;
+use crate::{NodeState, Pollutant, UnknownPollutant};
+
+const HOUR_MS: u64 = 3_600_000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+
+/// Width of a ledger bucket. Buckets start on UTC boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LedgerPeriod {
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+impl LedgerPeriod {
+    /// Start and end (exclusive) of the bucket holding `unix_ms`.
+    pub fn bounds(self, unix_ms: u64) -> (u64, u64) {
+        match self {
+            LedgerPeriod::Hourly => {
+                let start = unix_ms - unix_ms % HOUR_MS;
+                (start, start + HOUR_MS)
+            }
+            LedgerPeriod::Daily => {
+                let start = unix_ms - unix_ms % DAY_MS;
+                (start, start + DAY_MS)
+            }
+            LedgerPeriod::Monthly => {
+                let (year, month, _) = civil_from_days(unix_ms / DAY_MS);
+                let (next_year, next_month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                (
+                    days_from_civil(year, month, 1) * DAY_MS,
+                    days_from_civil(next_year, next_month, 1) * DAY_MS,
+                )
+            }
+        }
+    }
+
+    /// UTC label of the bucket starting at `start_unix_ms`: "2026-07",
+    /// "2026-07-01" or "2026-07-01T13".
+    pub fn label(self, start_unix_ms: u64) -> String {
+        let (year, month, day) = civil_from_days(start_unix_ms / DAY_MS);
+        match self {
+            LedgerPeriod::Monthly => format!("{year:04}-{month:02}"),
+            LedgerPeriod::Daily => format!("{year:04}-{month:02}-{day:02}"),
+            LedgerPeriod::Hourly => {
+                let hour = start_unix_ms % DAY_MS / HOUR_MS;
+                format!("{year:04}-{month:02}-{day:02}T{hour:02}")
+            }
+        }
+    }
+}
+
+/// Proleptic Gregorian (year, month, day) of a day count since 1970-01-01.
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01 so leap days end each 400-year era.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Inverse of `civil_from_days`, for dates from 1970 on.
+pub(crate) fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    #[error("non-finite mass or karma for {machine_id}")]
+    NonFinite { machine_id: String },
+    #[error("suspicion discount must be in [0, 1], got {factor}")]
+    InvalidDiscount { factor: f64 },
+    #[error(transparent)]
+    UnknownPollutant(#[from] UnknownPollutant),
+    #[error("cannot merge a {theirs:?} ledger into a {ours:?} one")]
+    PeriodMismatch {
+        ours: LedgerPeriod,
+        theirs: LedgerPeriod,
+    },
+    #[error("{} machine periods are claimed by both ledgers", .0.len())]
+    Conflicts(Vec<LedgerConflict>),
+    #[error("ledger csv error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("ledger json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Locale(#[from] LocaleError),
+}
+
+/// A machine and period both merged ledgers hold buckets for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerConflict {
+    pub machine_id: String,
+    pub period_start_unix_ms: u64,
+}
+
+/// One exported bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerRow {
+    pub machine_id: String,
+    /// See [`LedgerPeriod::label`].
+    pub period: String,
+    pub period_start_unix_ms: u64,
+    /// Exclusive.
+    pub period_end_unix_ms: u64,
+    /// Canonical shard spelling, e.g. "PM2.5".
+    pub pollutant: String,
+    pub mass_kg: f64,
+    pub karma_bytes: f64,
+    /// Records summed into the bucket.
+    pub steps: u64,
+    /// Of `steps`, those from suspicious nodes, whose karma was discounted.
+    #[serde(default)]
+    pub flagged_steps: u64,
+}
+
+/// JSON form of a ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerExport {
+    pub period: LedgerPeriod,
+    pub rows: Vec<LedgerRow>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    mass_kg: KahanSum,
+    karma_bytes: KahanSum,
+    steps: u64,
+    flagged_steps: u64,
+}
+
+/// Header of localized CSV exports.
+const LOCALIZED_COLUMNS: [&str; 9] = [
+    "machine_id",
+    "period",
+    "period_start",
+    "period_end",
+    "pollutant",
+    "mass_kg",
+    "karma_bytes",
+    "steps",
+    "flagged_steps",
+];
+
+/// (machine_id, period start, pollutant): the export order.
+type BucketKey = (String, u64, Pollutant);
+
+/// Karma and mass totals bucketed by machine, period and pollutant.
+#[derive(Debug, Clone)]
+pub struct KarmaLedger {
+    period: LedgerPeriod,
+    buckets: BTreeMap<BucketKey, Bucket>,
+    suspicion_discount: f64,
+}
+
+impl KarmaLedger {
+    pub fn new(period: LedgerPeriod) -> Self {
+        KarmaLedger {
+            period,
+            buckets: BTreeMap::new(),
+            suspicion_discount: 1.0,
+        }
+    }
+
+    /// Credit suspicious nodes' karma times `factor`, in [0, 1]; 1, the
+    /// default, credits it in full and 0 withholds it.
+    pub fn with_suspicion_discount(mut self, factor: f64) -> Result<Self, LedgerError> {
+        if !(0.0..=1.0).contains(&factor) {
+            return Err(LedgerError::InvalidDiscount { factor });
+        }
+        self.suspicion_discount = factor;
+        Ok(self)
+    }
+
+    pub fn period(&self) -> LedgerPeriod {
+        self.period
+    }
+
+    pub fn suspicion_discount(&self) -> f64 {
+        self.suspicion_discount
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Add one step's mass and karma for `machine_id` at `unix_ms`.
+    pub fn record(
+        &mut self,
+        machine_id: &str,
+        pollutant: Pollutant,
+        mass_kg: f64,
+        karma_bytes: f64,
+        unix_ms: u64,
+    ) -> Result<(), LedgerError> {
+        self.record_step(machine_id, pollutant, mass_kg, karma_bytes, unix_ms, false)
+    }
+
+    /// [`record`](Self::record) for a step from a suspicious node: karma
+    /// is credited at the suspicion discount.
+    pub fn record_flagged(
+        &mut self,
+        machine_id: &str,
+        pollutant: Pollutant,
+        mass_kg: f64,
+        karma_bytes: f64,
+        unix_ms: u64,
+    ) -> Result<(), LedgerError> {
+        self.record_step(machine_id, pollutant, mass_kg, karma_bytes, unix_ms, true)
+    }
+
+    fn record_step(
+        &mut self,
+        machine_id: &str,
+        pollutant: Pollutant,
+        mass_kg: f64,
+        karma_bytes: f64,
+        unix_ms: u64,
+        flagged: bool,
+    ) -> Result<(), LedgerError> {
+        if !(mass_kg.is_finite() && karma_bytes.is_finite()) {
+            return Err(LedgerError::NonFinite {
+                machine_id: machine_id.to_string(),
+            });
+        }
+        let (start, _) = self.period.bounds(unix_ms);
+        let bucket = self
+            .buckets
+            .entry((machine_id.to_string(), start, pollutant))
+            .or_default();
+        bucket.mass_kg.add(mass_kg);
+        if flagged {
+            bucket
+                .karma_bytes
+                .add(karma_bytes * self.suspicion_discount);
+            bucket.flagged_steps += 1;
+        } else {
+            bucket.karma_bytes.add(karma_bytes);
+        }
+        bucket.steps += 1;
+        Ok(())
+    }
+
+    /// `record` every node's mass and karma, e.g. after a controller step;
+    /// nodes with a `suspicion` are recorded as flagged. Stops at the first
+    /// node that cannot be recorded.
+    pub fn record_nodes(&mut self, nodes: &[NodeState], unix_ms: u64) -> Result<(), LedgerError> {
+        for node in nodes {
+            let pollutant = node.row.pollutant_kind()?;
+            self.record_step(
+                &node.row.machine_id,
+                pollutant,
+                node.mass_kg,
+                node.karma_bytes,
+                unix_ms,
+                node.suspicion.is_some(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Buckets in export order.
+    pub fn rows(&self) -> Vec<LedgerRow> {
+        self.buckets
+            .iter()
+            .map(|((machine_id, start, pollutant), bucket)| LedgerRow {
+                machine_id: machine_id.clone(),
+                period: self.period.label(*start),
+                period_start_unix_ms: *start,
+                period_end_unix_ms: self.period.bounds(*start).1,
+                pollutant: pollutant.as_str().to_string(),
+                mass_kg: bucket.mass_kg.total(),
+                karma_bytes: bucket.karma_bytes.total(),
+                steps: bucket.steps,
+                flagged_steps: bucket.flagged_steps,
+            })
+            .collect()
+    }
+
+    pub fn to_export(&self) -> LedgerExport {
+        LedgerExport {
+            period: self.period,
+            rows: self.rows(),
+        }
+    }
+
+    /// Rebuild a ledger from an export, e.g. one received from a gateway.
+    pub fn from_export(export: &LedgerExport) -> Result<Self, LedgerError> {
+        let mut ledger = KarmaLedger::new(export.period);
+        for row in &export.rows {
+            if !(row.mass_kg.is_finite() && row.karma_bytes.is_finite()) {
+                return Err(LedgerError::NonFinite {
+                    machine_id: row.machine_id.clone(),
+                });
+            }
+            let pollutant: Pollutant = row.pollutant.parse()?;
+            let (start, _) = export.period.bounds(row.period_start_unix_ms);
+            let bucket = ledger
+                .buckets
+                .entry((row.machine_id.clone(), start, pollutant))
+                .or_default();
+            bucket.mass_kg.add(row.mass_kg);
+            bucket.karma_bytes.add(row.karma_bytes);
+            bucket.steps += row.steps;
+            bucket.flagged_steps += row.flagged_steps;
+        }
+        Ok(ledger)
+    }
+
+    pub fn to_json(&self) -> Result<String, LedgerError> {
+        Ok(serde_json::to_string_pretty(&self.to_export())?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, LedgerError> {
+        Self::from_export(&serde_json::from_str(s)?)
+    }
+
+    /// One header line, then one line per bucket.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<(), LedgerError> {
+        let mut csv = csv::Writer::from_writer(writer);
+        for row in self.rows() {
+            csv.serialize(row)?;
+        }
+        csv.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+
+    /// [`write_csv`](Self::write_csv) in `locale`'s conventions, with the
+    /// period bounds as timestamps in `period_start` and `period_end`.
+    pub fn write_csv_localized<W: io::Write>(
+        &self,
+        writer: W,
+        locale: &ReportLocale,
+    ) -> Result<(), LedgerError> {
+        locale.validate()?;
+        let mut csv = locale.csv_writer(writer);
+        csv.write_record(LOCALIZED_COLUMNS)?;
+        for row in self.rows() {
+            csv.write_record([
+                row.machine_id,
+                row.period,
+                locale.format_timestamp(row.period_start_unix_ms),
+                locale.format_timestamp(row.period_end_unix_ms),
+                row.pollutant,
+                locale.format_number(row.mass_kg),
+                locale.format_number(row.karma_bytes),
+                locale.format_count(row.steps),
+                locale.format_count(row.flagged_steps),
+            ])?;
+        }
+        csv.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+
+    /// Rows of a [`write_csv_localized`](Self::write_csv_localized) export
+    /// in the same `locale`, exactly as exported. Rebuild the ledger with
+    /// [`from_export`](Self::from_export) and the ledger's period.
+    pub fn read_csv_localized<R: io::Read>(
+        reader: R,
+        locale: &ReportLocale,
+    ) -> Result<Vec<LedgerRow>, LedgerError> {
+        locale.validate()?;
+        let mut rows = Vec::new();
+        for record in locale.csv_reader(reader).records() {
+            let record = record?;
+            let field = |i| record.get(i).unwrap_or_default();
+            rows.push(LedgerRow {
+                machine_id: field(0).to_string(),
+                period: field(1).to_string(),
+                period_start_unix_ms: locale.parse_timestamp(field(2))?,
+                period_end_unix_ms: locale.parse_timestamp(field(3))?,
+                pollutant: field(4).to_string(),
+                mass_kg: locale.parse_number(field(5))?,
+                karma_bytes: locale.parse_number(field(6))?,
+                steps: locale.parse_count(field(7))?,
+                flagged_steps: locale.parse_count(field(8))?,
+            });
+        }
+        Ok(rows)
+    }
+
+    /// Machine periods held by both ledgers, whatever the pollutant.
+    pub fn conflicts(&self, other: &KarmaLedger) -> Vec<LedgerConflict> {
+        let mut conflicts: Vec<LedgerConflict> = Vec::new();
+        for (machine_id, start, _) in other.buckets.keys() {
+            // PM25 is the lowest pollutant, so this starts at the period.
+            let claimed = self
+                .buckets
+                .range((machine_id.clone(), *start, Pollutant::PM25)..)
+                .next()
+                .is_some_and(|((m, s, _), _)| m == machine_id && s == start);
+            let seen = conflicts
+                .last()
+                .is_some_and(|c| &c.machine_id == machine_id && c.period_start_unix_ms == *start);
+            if claimed && !seen {
+                conflicts.push(LedgerConflict {
+                    machine_id: machine_id.clone(),
+                    period_start_unix_ms: *start,
+                });
+            }
+        }
+        conflicts
+    }
+
+    /// Add `other`'s buckets. Refused, with nothing merged, if the periods
+    /// differ or any machine period is in both.
+    pub fn merge(&mut self, other: &KarmaLedger) -> Result<(), LedgerError> {
+        if self.period != other.period {
+            return Err(LedgerError::PeriodMismatch {
+                ours: self.period,
+                theirs: other.period,
+            });
+        }
+        let conflicts = self.conflicts(other);
+        if !conflicts.is_empty() {
+            return Err(LedgerError::Conflicts(conflicts));
+        }
+        self.buckets
+            .extend(other.buckets.iter().map(|(k, v)| (k.clone(), *v)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-07-01T00:00:00Z.
+    const JULY_1: u64 = 1_782_864_000_000;
+    /// 2026-08-01T00:00:00Z.
+    const AUGUST_1: u64 = JULY_1 + 31 * DAY_MS;
+
+    /// Every 15 minutes of July 2026 for two machines, plus one reading
+    /// either side of the month.
+    fn july(period: LedgerPeriod) -> KarmaLedger {
+        let mut ledger = KarmaLedger::new(period);
+        let mut t = JULY_1 - 1;
+        ledger
+            .record("CANOPY-01", Pollutant::PM25, 1.0, 10.0, t)
+            .unwrap();
+        t += 1;
+        while t < AUGUST_1 {
+            ledger
+                .record("CANOPY-01", Pollutant::PM25, 1e-3, 5e5, t)
+                .unwrap();
+            ledger
+                .record("CANOPY-01", Pollutant::O3, 2e-3, 1e5, t)
+                .unwrap();
+            ledger
+                .record("SCHOOL-05", Pollutant::PM25, 5e-4, 2e5, t)
+                .unwrap();
+            t += 15 * 60_000;
+        }
+        ledger
+            .record("CANOPY-01", Pollutant::PM25, 1.0, 10.0, AUGUST_1)
+            .unwrap();
+        ledger
+    }
+
+    #[test]
+    fn calendar_buckets_split_at_period_edges() {
+        assert_eq!(LedgerPeriod::Monthly.bounds(JULY_1), (JULY_1, AUGUST_1));
+        assert_eq!(
+            LedgerPeriod::Monthly.bounds(AUGUST_1 - 1),
+            (JULY_1, AUGUST_1)
+        );
+        assert_eq!(LedgerPeriod::Monthly.label(JULY_1 - 1), "2026-06");
+        // February of a leap year, and a December rolling into January.
+        let feb_2028 = days_from_civil(2028, 2, 1) * DAY_MS;
+        assert_eq!(
+            LedgerPeriod::Monthly.bounds(feb_2028 + 28 * DAY_MS),
+            (feb_2028, feb_2028 + 29 * DAY_MS)
+        );
+        let dec_2026 = days_from_civil(2026, 12, 31) * DAY_MS;
+        assert_eq!(
+            LedgerPeriod::Monthly.label(LedgerPeriod::Monthly.bounds(dec_2026).1),
+            "2027-01"
+        );
+        assert_eq!(
+            LedgerPeriod::Hourly.label(JULY_1 + 13 * HOUR_MS + 1),
+            "2026-07-01T13"
+        );
+
+        let rows = july(LedgerPeriod::Monthly).rows();
+        let keys: Vec<_> = rows
+            .iter()
+            .map(|r| {
+                (
+                    r.machine_id.as_str(),
+                    r.period.as_str(),
+                    r.pollutant.as_str(),
+                    r.steps,
+                )
+            })
+            .collect();
+        let steps = 31 * 24 * 4;
+        assert_eq!(
+            keys,
+            [
+                ("CANOPY-01", "2026-06", "PM2.5", 1),
+                ("CANOPY-01", "2026-07", "PM2.5", steps),
+                ("CANOPY-01", "2026-07", "O3", steps),
+                ("CANOPY-01", "2026-08", "PM2.5", 1),
+                ("SCHOOL-05", "2026-07", "PM2.5", steps),
+            ]
+        );
+        let july_pm = &rows[1];
+        assert_eq!(july_pm.period_end_unix_ms, AUGUST_1);
+        assert!((july_pm.mass_kg - steps as f64 * 1e-3).abs() < 1e-12);
+        assert_eq!(july_pm.karma_bytes, steps as f64 * 5e5);
+
+        // Daily buckets of the month add up to the monthly one.
+        let daily = july(LedgerPeriod::Daily).rows();
+        let july_days: Vec<_> = daily
+            .iter()
+            .filter(|r| r.machine_id == "CANOPY-01" && r.pollutant == "PM2.5")
+            .filter(|r| r.period.starts_with("2026-07"))
+            .collect();
+        assert_eq!(july_days.len(), 31);
+        assert!(july_days.iter().all(|r| r.steps == 96));
+        let total: f64 = july_days.iter().map(|r| r.karma_bytes).sum();
+        assert_eq!(total, july_pm.karma_bytes);
+    }
+
+    #[test]
+    fn exports_round_trip_in_a_stable_order() {
+        let ledger = july(LedgerPeriod::Monthly);
+        let mut csv = Vec::new();
+        ledger.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("machine_id,period,period_start_unix_ms,period_end_unix_ms,pollutant,mass_kg,karma_bytes,steps,flagged_steps")
+        );
+        assert!(lines.next().unwrap().starts_with("CANOPY-01,2026-06,"));
+
+        let back = KarmaLedger::from_json(&ledger.to_json().unwrap()).unwrap();
+        assert_eq!(back.rows(), ledger.rows());
+    }
+
+    #[test]
+    fn comma_decimal_csv_recovers_the_exact_totals() {
+        let mut ledger = july(LedgerPeriod::Daily)
+            .with_suspicion_discount(0.3)
+            .unwrap();
+        ledger
+            .record_flagged("SCHOOL-05", Pollutant::O3, 1.0 / 3.0, 1234567.891, JULY_1)
+            .unwrap();
+        let locale = ReportLocale {
+            decimal_separator: ',',
+            digit_grouping: Some('.'),
+            ..ReportLocale::default()
+        };
+        let mut csv = Vec::new();
+        ledger.write_csv_localized(&mut csv, &locale).unwrap();
+        let text = String::from_utf8(csv.clone()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some("machine_id;period;period_start;period_end;pollutant;mass_kg;karma_bytes;steps;flagged_steps")
+        );
+        assert!(lines.next().unwrap().starts_with(
+            "CANOPY-01;2026-06-30;2026-06-30T00:00:00.000Z;2026-07-01T00:00:00.000Z;PM2.5;1;10;1;0"
+        ));
+        assert!(text.contains("SCHOOL-05;2026-07-01;2026-07-01T00:00:00.000Z;2026-07-02T00:00:00.000Z;O3;0,3333333333333333;370.370,3673;1;1"), "{text}");
+
+        let rows = KarmaLedger::read_csv_localized(csv.as_slice(), &locale).unwrap();
+        assert_eq!(rows, ledger.rows());
+        let back = KarmaLedger::from_export(&LedgerExport {
+            period: LedgerPeriod::Daily,
+            rows,
+        })
+        .unwrap();
+        assert_eq!(back.rows(), ledger.rows());
+
+        // Read in the wrong locale, the numbers are refused, not misread.
+        assert!(matches!(
+            KarmaLedger::read_csv_localized(csv.as_slice(), &ReportLocale::default()),
+            Err(LedgerError::Csv(_) | LedgerError::Locale(_))
+        ));
+    }
+
+    #[test]
+    fn gateways_claiming_the_same_machine_period_conflict() {
+        let mut east = KarmaLedger::new(LedgerPeriod::Daily);
+        east.record("CANOPY-01", Pollutant::PM25, 1e-3, 5e5, JULY_1)
+            .unwrap();
+        let mut west = KarmaLedger::new(LedgerPeriod::Daily);
+        west.record("SCHOOL-05", Pollutant::PM25, 1e-3, 5e5, JULY_1)
+            .unwrap();
+        west.record("CANOPY-01", Pollutant::PM25, 1e-3, 5e5, JULY_1 + DAY_MS)
+            .unwrap();
+
+        let mut merged = east.clone();
+        merged.merge(&west).unwrap();
+        assert_eq!(merged.rows().len(), 3);
+
+        // Another gateway reporting CANOPY-01 on July 1, even for another
+        // pollutant, overlaps east.
+        let mut rogue = KarmaLedger::new(LedgerPeriod::Daily);
+        rogue
+            .record("CANOPY-01", Pollutant::O3, 1e-3, 5e5, JULY_1 + HOUR_MS)
+            .unwrap();
+        rogue
+            .record("CANOPY-01", Pollutant::PM25, 1e-3, 5e5, JULY_1 + HOUR_MS)
+            .unwrap();
+        let before = merged.rows();
+        match merged.merge(&rogue) {
+            Err(LedgerError::Conflicts(conflicts)) => assert_eq!(
+                conflicts,
+                [LedgerConflict {
+                    machine_id: "CANOPY-01".into(),
+                    period_start_unix_ms: JULY_1,
+                }]
+            ),
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+        assert_eq!(merged.rows(), before);
+
+        assert!(matches!(
+            merged.merge(&KarmaLedger::new(LedgerPeriod::Monthly)),
+            Err(LedgerError::PeriodMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn suspicious_nodes_are_credited_at_the_discount() {
+        assert!(matches!(
+            KarmaLedger::new(LedgerPeriod::Daily).with_suspicion_discount(1.5),
+            Err(LedgerError::InvalidDiscount { .. })
+        ));
+        let mut ledger = KarmaLedger::new(LedgerPeriod::Daily)
+            .with_suspicion_discount(0.25)
+            .unwrap();
+        let mut nodes = crate::tests::phoenix_nodes();
+        nodes.truncate(1);
+        nodes[0].mass_kg = 2e-3;
+        nodes[0].karma_bytes = 8e5;
+        ledger.record_nodes(&nodes, JULY_1).unwrap();
+        nodes[0].suspicion = Some(crate::Suspicion {
+            score: 1.0,
+            heuristics: vec![crate::Heuristic::FlatLine],
+        });
+        ledger.record_nodes(&nodes, JULY_1 + HOUR_MS).unwrap();
+        ledger.record_nodes(&nodes, JULY_1 + 2 * HOUR_MS).unwrap();
+
+        let [row] = ledger.rows().try_into().unwrap();
+        assert_eq!((row.steps, row.flagged_steps), (3, 2));
+        // Mass is measured either way; only the credit is discounted.
+        assert!((row.mass_kg - 6e-3).abs() < 1e-15);
+        assert_eq!(row.karma_bytes, 8e5 + 2.0 * 0.25 * 8e5);
+
+        let back = KarmaLedger::from_json(&ledger.to_json().unwrap()).unwrap();
+        assert_eq!(back.rows()[0].flagged_steps, 2);
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::LateData;
+use crate::snapshot::{BudgetState, SnapshotError};
+use crate::{HostBudget, NodeState, SafetyError, SimpleHostBudget};
+
+/// Liability cap and the window it is summed over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LiabilityConfig {
+    /// Most karma a node may accumulate within the window (NanoKarmaBytes).
+    pub cap_karma_bytes: f64,
+    /// Length of the rolling window (ms).
+    pub window_ms: u64,
+    /// Width of one accumulation bucket (ms); the window slides by whole
+    /// buckets. 0 is treated as 1.
+    pub bucket_ms: u64,
+    /// How far a sample may trail the newest one (ms) and still be placed
+    /// in its own bucket; older samples are refused. Buckets are kept this
+    /// much longer than the window so late samples have somewhere to land.
+    #[serde(default)]
+    pub max_lateness_ms: u64,
+}
+
+impl LiabilityConfig {
+    fn bucket_width(&self) -> u64 {
+        self.bucket_ms.max(1)
+    }
+
+    fn bucket_start(&self, unix_ms: u64) -> u64 {
+        unix_ms - unix_ms % self.bucket_width()
+    }
+
+    /// Start of the oldest bucket in the window whose last bucket starts
+    /// at `end_bucket`.
+    fn window_start(&self, end_bucket: u64) -> u64 {
+        (end_bucket + self.bucket_width()).saturating_sub(self.window_ms)
+    }
+
+    fn window_sum(&self, buckets: &VecDeque<LiabilityBucket>, end_bucket: u64) -> f64 {
+        let range = self.window_start(end_bucket)..=end_bucket;
+        buckets
+            .iter()
+            .filter(|b| range.contains(&b.start_unix_ms))
+            .map(|b| b.karma_bytes)
+            .sum()
+    }
+}
+
+/// Karma summed into one bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LiabilityBucket {
+    pub start_unix_ms: u64,
+    pub karma_bytes: f64,
+}
+
+/// A window that was within the cap when it was current but exceeds it
+/// once a late sample is counted. Decisions taken while it was current
+/// let the node run when it should have been throttled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LiabilityCorrection {
+    pub machine_id: String,
+    /// Start of the window's last bucket, i.e. when it was current.
+    pub window_end_bucket_unix_ms: u64,
+    /// Window karma as it was judged.
+    pub previous_karma_bytes: f64,
+    pub karma_bytes: f64,
+    pub cap_karma_bytes: f64,
+}
+
+/// Per-node buckets of a `KarmaLiabilityBudget`, oldest first. Save it
+/// with `KarmaLiabilityBudget::window` and restore it with
+/// `KarmaLiabilityBudget::restore_window`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LiabilityWindow {
+    /// Time of the last `record`; buckets are expired relative to it.
+    pub now_unix_ms: u64,
+    pub buckets: BTreeMap<String, VecDeque<LiabilityBucket>>,
+}
+
+/// Host budget that throttles nodes whose cumulative karma over a rolling
+/// window passes a liability cap, on top of an inner power budget.
+///
+/// The caller records each step's karma with `record`; budget checks read
+/// the window as of the last record. A node over the cap is refused
+/// whatever its power headroom.
+#[derive(Debug, Clone)]
+pub struct KarmaLiabilityBudget<H = SimpleHostBudget> {
+    pub config: LiabilityConfig,
+    pub inner: H,
+    window: LiabilityWindow,
+}
+
+impl<H: HostBudget> KarmaLiabilityBudget<H> {
+    pub fn new(config: LiabilityConfig, inner: H) -> Self {
+        KarmaLiabilityBudget {
+            config,
+            inner,
+            window: LiabilityWindow::default(),
+        }
+    }
+
+    /// Add each node's `karma_bytes` to its bucket for `now_unix_ms`, then
+    /// drop buckets that have slid out of the window. Non-finite or
+    /// negative karma adds nothing, and so does a sample more than
+    /// `max_lateness_ms` late; `record_sample` reports both the refusal
+    /// and what a late sample changed.
+    pub fn record(&mut self, nodes: &[NodeState], now_unix_ms: u64) {
+        let _ = self.record_sample(nodes, now_unix_ms);
+    }
+
+    /// `record` for a sample taken at `sample_unix_ms`, which may be older
+    /// than samples already recorded. Its karma goes into the bucket of
+    /// its own time, and every window containing that bucket that was
+    /// already judged is judged again: one pushed over the cap by the late
+    /// karma is returned as a correction.
+    pub fn record_sample(
+        &mut self,
+        nodes: &[NodeState],
+        sample_unix_ms: u64,
+    ) -> Result<Vec<LiabilityCorrection>, LateData> {
+        let newest = self.window.now_unix_ms;
+        LateData::check(sample_unix_ms, newest, self.config.max_lateness_ms)?;
+        let start = self.config.bucket_start(sample_unix_ms);
+        // Windows ending in [start, newest's bucket] were current before.
+        let judged: Vec<u64> = if sample_unix_ms < newest {
+            (start..=self.config.bucket_start(newest))
+                .step_by(self.config.bucket_width() as usize)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let cap = self.config.cap_karma_bytes;
+        let mut corrections = Vec::new();
+        for node in nodes {
+            let karma = node.karma_bytes;
+            if !(karma.is_finite() && karma > 0.0) {
+                continue;
+            }
+            let buckets = self
+                .window
+                .buckets
+                .entry(node.row.machine_id.clone())
+                .or_default();
+            let before: Vec<f64> = judged
+                .iter()
+                .map(|&end| self.config.window_sum(buckets, end))
+                .collect();
+            let i = buckets.partition_point(|b| b.start_unix_ms < start);
+            match buckets.get_mut(i) {
+                Some(b) if b.start_unix_ms == start => b.karma_bytes += karma,
+                _ => buckets.insert(
+                    i,
+                    LiabilityBucket {
+                        start_unix_ms: start,
+                        karma_bytes: karma,
+                    },
+                ),
+            }
+            for (&end, previous) in judged.iter().zip(before) {
+                let karma_bytes = self.config.window_sum(buckets, end);
+                if previous <= cap && karma_bytes > cap {
+                    corrections.push(LiabilityCorrection {
+                        machine_id: node.row.machine_id.clone(),
+                        window_end_bucket_unix_ms: end,
+                        previous_karma_bytes: previous,
+                        karma_bytes,
+                        cap_karma_bytes: cap,
+                    });
+                }
+            }
+        }
+        self.expire(sample_unix_ms);
+        Ok(corrections)
+    }
+
+    /// Slide the window to `now_unix_ms` without recording anything.
+    /// Time never moves backwards; an earlier `now_unix_ms` is ignored.
+    pub fn expire(&mut self, now_unix_ms: u64) {
+        let now = now_unix_ms.max(self.window.now_unix_ms);
+        self.window.now_unix_ms = now;
+        // Keep every bucket of the windows a late sample may still reach.
+        let oldest = self.config.window_start(
+            self.config
+                .bucket_start(now.saturating_sub(self.config.max_lateness_ms)),
+        );
+        self.window.buckets.retain(|_, buckets| {
+            while buckets.front().is_some_and(|b| b.start_unix_ms < oldest) {
+                buckets.pop_front();
+            }
+            !buckets.is_empty()
+        });
+    }
+
+    /// Karma `machine_id` accumulated within the window.
+    pub fn window_karma_bytes(&self, machine_id: &str) -> f64 {
+        // The window ends with the current bucket.
+        let current = self.config.bucket_start(self.window.now_unix_ms);
+        self.window
+            .buckets
+            .get(machine_id)
+            .map_or(0.0, |buckets| self.config.window_sum(buckets, current))
+    }
+
+    /// Window karma over the cap, in [0, +inf); 1 is the trip point.
+    pub fn liability_fraction(&self, machine_id: &str) -> f64 {
+        if self.config.cap_karma_bytes <= 0.0 {
+            return 0.0;
+        }
+        self.window_karma_bytes(machine_id) / self.config.cap_karma_bytes
+    }
+
+    /// Forget every node's accumulated karma.
+    pub fn reset(&mut self) {
+        self.window = LiabilityWindow::default();
+    }
+
+    /// Forget one node's accumulated karma, e.g. after remediation.
+    pub fn reset_node(&mut self, machine_id: &str) {
+        self.window.buckets.remove(machine_id);
+    }
+
+    pub fn window(&self) -> &LiabilityWindow {
+        &self.window
+    }
+
+    /// Replace the window, e.g. with one saved before a restart. Buckets
+    /// already out of the window are dropped.
+    pub fn restore_window(&mut self, window: LiabilityWindow) {
+        let now = window.now_unix_ms;
+        self.window = window;
+        self.expire(now);
+    }
+}
+
+impl<H: HostBudget> HostBudget for KarmaLiabilityBudget<H> {
+    fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError> {
+        match self.host_budget_violations(node).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn host_budget_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        let mut violations = self.inner.host_budget_violations(node);
+        let id = &node.row.machine_id;
+        let karma_bytes = self.window_karma_bytes(id);
+        if karma_bytes > self.config.cap_karma_bytes {
+            violations.push(SafetyError::LiabilityCapExceeded {
+                machine_id: id.clone(),
+                karma_bytes,
+                cap_karma_bytes: self.config.cap_karma_bytes,
+            });
+        }
+        violations
+    }
+
+    /// The larger of the inner power fraction and the liability fraction,
+    /// so nodes nearing their cap are throttled through the controller's
+    /// eta_p term as if nearing P_max.
+    fn power_fraction(&self, node: &NodeState) -> f64 {
+        self.inner
+            .power_fraction(node)
+            .max(self.liability_fraction(&node.row.machine_id))
+    }
+
+    fn budget_state(&self) -> BudgetState {
+        BudgetState {
+            liability: Some(self.window.clone()),
+            ..self.inner.budget_state()
+        }
+    }
+
+    fn restore_budget_state(&mut self, state: &BudgetState) -> Result<(), SnapshotError> {
+        let window = state.liability.clone().ok_or(SnapshotError::Missing {
+            component: "liability window",
+        })?;
+        self.inner.restore_budget_state(state)?;
+        self.restore_window(window);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+
+    const MINUTE_MS: u64 = 60_000;
+
+    fn budget(cap_karma_bytes: f64) -> KarmaLiabilityBudget {
+        KarmaLiabilityBudget::new(
+            LiabilityConfig {
+                cap_karma_bytes,
+                window_ms: 60 * MINUTE_MS,
+                bucket_ms: 10 * MINUTE_MS,
+                max_lateness_ms: 0,
+            },
+            SimpleHostBudget {
+                p_max_w: 150.0,
+                e_step_max_j: 1.0e9,
+                step_dt_s: 60.0,
+            },
+        )
+    }
+
+    #[test]
+    fn filling_the_window_trips_at_the_cap() {
+        let nodes = phoenix_nodes();
+        let node = &nodes[0];
+        let per_step = node.karma_bytes;
+        // The cap is crossed on the fourth 10-minute step.
+        let mut budget = budget(3.5 * per_step);
+
+        let mut steps = 0;
+        while budget.check_host_budget(node).is_ok() {
+            budget.record(&nodes, steps * 10 * MINUTE_MS);
+            steps += 1;
+            assert!(steps < 100, "liability never reached the cap");
+        }
+        assert_eq!(steps, 4);
+        assert!(budget.liability_fraction(&node.row.machine_id) > 1.0);
+        match budget.check_host_budget(node) {
+            Err(SafetyError::LiabilityCapExceeded {
+                machine_id,
+                karma_bytes,
+                cap_karma_bytes,
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-CANOPY-01");
+                assert!((karma_bytes - 4.0 * per_step).abs() <= 1e-9 * karma_bytes);
+                assert_eq!(cap_karma_bytes, 3.5 * per_step);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        // Power is well within P_max; the liability alone throttles.
+        assert!(budget.inner.check_host_budget(node).is_ok());
+        assert!(budget.power_fraction(node) > 1.0);
+    }
+
+    #[test]
+    fn old_buckets_expire_and_the_node_recovers() {
+        let nodes = phoenix_nodes();
+        let node = &nodes[0];
+        let id = node.row.machine_id.as_str();
+        let mut budget = budget(3.5 * node.karma_bytes);
+        for step in 0..6 {
+            budget.record(&nodes, step * 10 * MINUTE_MS);
+        }
+        assert!(budget.check_host_budget(node).is_err());
+        assert_eq!(budget.window().buckets[id].len(), 6);
+
+        // Quiet steps: one bucket slides out every ten minutes.
+        let mut fractions = vec![budget.liability_fraction(id)];
+        for step in 6..12 {
+            budget.expire(step * 10 * MINUTE_MS);
+            fractions.push(budget.liability_fraction(id));
+        }
+        assert!(fractions.windows(2).all(|w| w[1] < w[0]));
+        assert!(budget.check_host_budget(node).is_ok());
+        assert_eq!(budget.window_karma_bytes(id), 0.0);
+        assert!(!budget.window().buckets.contains_key(id));
+    }
+
+    #[test]
+    fn records_within_a_bucket_share_it() {
+        let nodes = phoenix_nodes();
+        let mut budget = budget(f64::MAX);
+        budget.record(&nodes, 0);
+        budget.record(&nodes, 9 * MINUTE_MS);
+        budget.record(&nodes, 10 * MINUTE_MS);
+        let buckets = &budget.window().buckets[nodes[0].row.machine_id.as_str()];
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].karma_bytes, 2.0 * nodes[0].karma_bytes);
+    }
+
+    #[test]
+    fn late_samples_land_in_their_bucket_and_correct_passed_windows() {
+        let nodes = phoenix_nodes();
+        let node = &nodes[..1];
+        let id = node[0].row.machine_id.as_str();
+        let mut budget = budget(4.5 * node[0].karma_bytes);
+        budget.config.max_lateness_ms = 40 * MINUTE_MS;
+        // The 10-minute sample is held up on a store-and-forward link.
+        for minute in [0, 20, 30, 50] {
+            assert!(budget
+                .record_sample(node, minute * MINUTE_MS)
+                .unwrap()
+                .is_empty());
+        }
+        assert!(budget.check_host_budget(&node[0]).is_ok());
+
+        let corrections = budget.record_sample(node, 15 * MINUTE_MS).unwrap();
+        let starts: Vec<u64> = budget.window().buckets[id]
+            .iter()
+            .map(|b| b.start_unix_ms / MINUTE_MS)
+            .collect();
+        assert_eq!(starts, [0, 10, 20, 30, 50]);
+        assert_eq!(budget.window().now_unix_ms, 50 * MINUTE_MS);
+        // Only the window judged at 50 minutes held four samples; it now
+        // holds five.
+        assert_eq!(corrections.len(), 1);
+        let c = &corrections[0];
+        assert_eq!(c.machine_id, id);
+        assert_eq!(c.window_end_bucket_unix_ms, 50 * MINUTE_MS);
+        assert!(c.previous_karma_bytes <= c.cap_karma_bytes);
+        assert!(c.karma_bytes > c.cap_karma_bytes);
+        assert!(budget.check_host_budget(&node[0]).is_err());
+
+        let before = budget.window().clone();
+        assert_eq!(
+            budget.record_sample(node, 5 * MINUTE_MS),
+            Err(LateData {
+                sample_ms: 5 * MINUTE_MS,
+                newest_ms: 50 * MINUTE_MS,
+                max_lateness_ms: 40 * MINUTE_MS,
+            })
+        );
+        budget.record(node, 5 * MINUTE_MS);
+        assert_eq!(budget.window(), &before);
+    }
+
+    #[test]
+    fn window_survives_a_json_round_trip_and_resets() {
+        let nodes = phoenix_nodes();
+        let id = nodes[0].row.machine_id.as_str();
+        let mut budget = budget(3.5 * nodes[0].karma_bytes);
+        for step in 0..4 {
+            budget.record(&nodes, step * 10 * MINUTE_MS);
+        }
+        let json = serde_json::to_string(budget.window()).unwrap();
+
+        let mut restored = self::budget(3.5 * nodes[0].karma_bytes);
+        restored.restore_window(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.window(), budget.window());
+        assert!(restored.check_host_budget(&nodes[0]).is_err());
+
+        restored.reset_node(id);
+        assert!(restored.check_host_budget(&nodes[0]).is_ok());
+        assert!(restored.window_karma_bytes(nodes[1].row.machine_id.as_str()) > 0.0);
+        restored.reset();
+        assert_eq!(restored.window(), &LiabilityWindow::default());
+    }
+}
@@ -1,9 +1,128 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use summation::KahanSum;
+use uuid::Uuid;
+
+pub mod airflow;
+pub mod allocation;
+pub mod altitude;
+pub mod anomaly;
+pub mod baseline;
+pub mod battery;
+pub mod bundle;
+pub mod calibration;
+pub mod ceim;
+pub mod chain;
+pub mod clock;
+pub mod composite;
+pub mod config;
+pub mod gains;
+pub mod gate;
+pub mod history;
+pub mod ledger;
+pub mod liability;
+pub mod loader;
+pub mod locale;
+pub mod merge;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod mode;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+pub mod pollutant;
+pub mod quarantine;
+pub mod ramp;
+pub mod removal;
+pub mod replay;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod schedule;
+pub mod simulation;
+pub mod smoothing;
+pub mod snapshot;
+pub mod stability;
+mod summation;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod topology;
+pub mod units;
+pub mod wind;
+
+pub use airflow::{compute_mass_kg_at_duty, AirflowModel, AirflowModels};
+pub use allocation::{AllocationError, CorridorCap, DutyAllocation, DutyAllocator, NodeAllocation};
+pub use altitude::{AltitudeProvider, ConstAltitude, FnAltitude, MapAltitude};
+pub use anomaly::{
+    AirflowRange, AnomalyConfig, AnomalyScorer, Heuristic, HeuristicWeights, Suspicion,
+};
+pub use baseline::{BaselineModel, LoadScaling, SeasonalBandDecision, SeasonalEcoBand};
+pub use battery::{BatteryConfig, BatteryHostBudget};
+pub use bundle::{ConfigChange, ConstraintRow, CorridorBundle, HiveSite, PolytopeConfig};
+pub use calibration::{
+    apply_calibration, CalibrationError, CalibrationParams, CalibrationTable, DriftEstimator,
+    DriftUpdate, StaleCalibration,
+};
+pub use chain::{
+    verify_chain, ChainAppender, ChainEntry, ChainError, ChainHead, EventChain, FileEventChain,
+};
+pub use clock::{Clock, LateData, ManualClock, SystemClock};
+pub use composite::{
+    BoxedHostBudget, BoxedSafetyEnvelope, CompositeHostBudget, CompositeHostBudgetBuilder,
+    CompositeSafetyEnvelope, CompositeSafetyEnvelopeBuilder, ControllerBudgetBuilder,
+    EmptyCompositeBudget, EmptyCompositeEnvelope,
+};
+pub use config::{ConfigError, ControllerConfig, EnvelopeConfig, GainConfig};
+pub use gains::{BandGains, GainSchedule, GainSource};
+pub use gate::{AgentGate, GateCap, GateDutyPolicy, GateResolver, MapGateResolver};
+pub use history::{BandHistory, BandTransition, RecordingEcoBand};
+pub use ledger::{KarmaLedger, LedgerConflict, LedgerError, LedgerExport, LedgerPeriod, LedgerRow};
+pub use liability::{
+    KarmaLiabilityBudget, LiabilityBucket, LiabilityConfig, LiabilityCorrection, LiabilityWindow,
+};
+pub use loader::{BeeExtension, LoadError, ShardRow};
+pub use locale::{DateOrder, LocaleError, ReportLocale, TimestampStyle, UnitStyle};
+pub use merge::{
+    merge_colocated, MergeConflict, MergeError, MergeFields, MergeOptions, MergeReport, MergeRow,
+    MergedGroup,
+};
+pub use mode::{ExitAuthorizer, OperationalMode};
+pub use pollutant::{Pollutant, UnknownPollutant};
+pub use quarantine::{
+    FailureRecord, Quarantine, QuarantineEvent, QuarantinePolicy, QuarantinedNode, QUARANTINE,
+};
+pub use ramp::{duty_at, DutyRamp, RampShape};
+pub use removal::{
+    net_mass_removed, net_mass_removed_at_pressure, DecayBaseline, KarmaBasis, MassBaseline,
+    MassRemoval, RemovalBaseline, ZeroBaseline,
+};
+pub use schedule::{ClockedSchedule, DutySchedule, ScheduleCap, ScheduleWindow, Weekday};
+pub use simulation::{NodeStepRecord, StepInput, StepRecord};
+pub use smoothing::{
+    smooth_nodes, NodeSmoothing, SeriesSmoothing, SmoothedValue, SmoothingConfig, SmoothingReport,
+};
+pub use snapshot::{BandState, BudgetState, ControllerSnapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use stability::{
+    load_potential, residual_potential, PassReport, Potential, StabilityMonitor, StabilityPolicy,
+    StabilityRecord,
+};
+pub use topology::{
+    Admission, Coordinates, CorridorMembership, CorridorTopology, MachineRecord, Registration,
+    TopologyError, UnknownMachinePolicy,
+};
+pub use units::{unit_to_kg_factor, unit_to_kg_factor_at_pressure, ConcentrationUnit, UnitError};
+pub use wind::{DynamicGeoWeight, GeoWeighting, Receptor, SitePosition, Wind};
+
 /// Core row schema, aligned with Cybo-Air qpudatashards for Phoenix and similar.
 /// This is intentionally close to the types you already use in cybo-air control crates.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CorridorRow {
     pub machine_id: String,
     pub r#type: String,
@@ -17,10 +136,18 @@ pub struct CorridorRow {
     pub lambda_hazard: f64,
     pub beta_nb_per_kg: f64,
     pub ecoimpact_score: f64,
+    /// Identifies the shard row (or telemetry frame) this row came from,
+    /// so a duty change can be traced back to it; see
+    /// [`loader::row_trace_id`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<Uuid>,
+    /// Version of the shard the row was loaded from, if it declares one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard_version: Option<String>,
 }
 
 /// Minimal node state needed for corridor control.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeState {
     pub row: CorridorRow,
     pub mass_kg: f64,
@@ -28,59 +155,368 @@ pub struct NodeState {
     pub duty_cycle: f64, // u in [0,1]
     pub power_w: f64,
     pub geo_weight: f64,
+    /// Acoustic level at the node, dB(A); `None` if not measured.
+    pub noise_db: Option<f64>,
+    /// RF electric field strength at the node, V/m; `None` if not measured.
+    pub emf_vpm: Option<f64>,
+    /// Set when the current row scored as implausible; see [`anomaly`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspicion: Option<Suspicion>,
+    /// Whether the controller may actuate the node; see [`mode`].
+    #[serde(default, skip_serializing_if = "OperationalMode::is_active")]
+    pub mode: OperationalMode,
 }
 
-/// Eco-band classification.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Eco-band classification, ordered by severity (Green < Amber < Red).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum EcoBand {
     Green,
     Amber,
     Red,
 }
 
-/// Errors for invariants and envelopes.
-#[derive(Debug, Error)]
+/// Envelope dimension that a node violated.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EnvelopeField {
+    DutyCycle,
+    Altitude,
+    EcoimpactScore,
+    NoiseDb,
+    EmfVpm,
+}
+
+impl std::fmt::Display for EnvelopeField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EnvelopeField::DutyCycle => "duty_cycle",
+            EnvelopeField::Altitude => "altitude_m",
+            EnvelopeField::EcoimpactScore => "ecoimpact_score",
+            EnvelopeField::NoiseDb => "noise_db",
+            EnvelopeField::EmfVpm => "emf_vpm",
+        })
+    }
+}
+
+/// Errors for invariants and envelopes, carrying the offending node and values.
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum SafetyError {
-    #[error("safety envelope violated: {0}")]
-    EnvelopeViolation(&'static str),
-    #[error("host budget exceeded: {0}")]
-    HostBudgetExceeded(&'static str),
-    #[error("dw ceiling exceeded: {0}")]
-    DwCeilingExceeded(&'static str),
-}
-
-/// Conversion from shard concentration units to kg/m^3.
-/// This is the same operator you already use (ug/m3, mg/m3, ppb via MW,R,T).
-pub fn unit_to_kg_factor(unit: &str, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    match unit {
-        "ugm3" => 1e-9,
-        "mgm3" => 1e-6,
-        "ppb" => {
-            let r = 8.3145_f64;
-            // C(ppb) * MW / (R*T) * 1e-9 -> kg/m^3
-            molar_mass_kg_per_mol / (r * temperature_k) * 1e-9
+    #[error("{machine_id}: safety envelope violated: {field}={value} outside [{min}, {max}]")]
+    EnvelopeViolation {
+        machine_id: String,
+        field: EnvelopeField,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    #[error("{machine_id}: host budget exceeded: power {power_w} W > {p_max_w} W")]
+    HostBudgetExceeded {
+        machine_id: String,
+        power_w: f64,
+        p_max_w: f64,
+    },
+    #[error("{machine_id}: host budget exceeded: step energy {energy_j} J > {e_max_j} J")]
+    EnergyBudgetExceeded {
+        machine_id: String,
+        energy_j: f64,
+        e_max_j: f64,
+    },
+    #[error("dw ceiling exceeded: phi_dw {phi_dw} > {phi_dw_max}")]
+    DwCeilingExceeded { phi_dw: f64, phi_dw_max: f64 },
+    #[error("{pollutant} dw ceiling exceeded: phi_dw {phi_dw} > {phi_dw_max}")]
+    PollutantDwCeilingExceeded {
+        pollutant: Pollutant,
+        phi_dw: f64,
+        phi_dw_max: f64,
+    },
+    #[error("dw flux undefined: corridor area {area_m2} m^2 must be positive")]
+    InvalidCorridorArea { area_m2: f64 },
+    #[error("{machine_id}: dw flux undefined: period {period_s} s must be positive")]
+    InvalidPeriod { machine_id: String, period_s: f64 },
+    #[error("reference scale {name} must be finite and positive, got {value}")]
+    InvalidReference { name: &'static str, value: f64 },
+    #[error("eco-load weights must be finite, non-negative and not both zero (alpha_m={alpha_m}, alpha_k={alpha_k})")]
+    InvalidLoadWeights { alpha_m: f64, alpha_k: f64 },
+    #[error("eco-load offset must be finite and non-negative, got {offset}")]
+    InvalidEcoOffset { offset: f64 },
+    #[error("{machine_id}: geo_weight must be finite and non-negative, got {geo_weight}")]
+    InvalidGeoWeight { machine_id: String, geo_weight: f64 },
+    #[error("geo-weighted eco-load undefined: every node has geo_weight 0")]
+    ZeroGeoWeights,
+    #[error("{machine_id}: {field} is not finite ({value})")]
+    NonFiniteNode {
+        machine_id: String,
+        field: &'static str,
+        value: f64,
+    },
+    /// A value computed from the node came out NaN or infinite, e.g. by
+    /// overflow or a non-finite gain.
+    #[error("{machine_id}: computed {field} is not finite ({value})")]
+    NonFiniteValue {
+        machine_id: String,
+        field: &'static str,
+        value: f64,
+    },
+    #[error("corridor potential increased: V {v_before} -> {v_after} (tolerance {tolerance})")]
+    LyapunovIncrease {
+        v_before: f64,
+        v_after: f64,
+        tolerance: f64,
+    },
+    #[error("{machine_id}: battery at reserve: soc {soc} <= {reserve_soc}")]
+    BatteryReserve {
+        machine_id: String,
+        soc: f64,
+        reserve_soc: f64,
+    },
+    #[error("{machine_id}: host budget exceeded: window karma {karma_bytes} > liability cap {cap_karma_bytes}")]
+    LiabilityCapExceeded {
+        machine_id: String,
+        karma_bytes: f64,
+        cap_karma_bytes: f64,
+    },
+    #[error(
+        "{machine_id}: inside hive exclusion zone of {hive_id}: {distance_m} m < {radius_m} m"
+    )]
+    HiveExclusion {
+        machine_id: String,
+        hive_id: String,
+        distance_m: f64,
+        radius_m: f64,
+    },
+    /// The node is not a member of the controller's corridor; see
+    /// [`topology`].
+    #[error("{machine_id} is not in corridor {corridor}")]
+    OutsideCorridor {
+        machine_id: String,
+        corridor: String,
+    },
+    /// [`CorridorController::exit_mode`] was refused.
+    #[error("{machine_id} may not leave {} mode without authorization", mode.as_str())]
+    UnauthorizedModeExit {
+        machine_id: String,
+        mode: OperationalMode,
+    },
+    /// A `CompositeSafetyEnvelope` member's error, labelled with the name
+    /// it was registered under. Its `kind` is the inner error's.
+    #[error("envelope member {member}: {error}")]
+    EnvelopeMember {
+        member: String,
+        error: Box<SafetyError>,
+    },
+}
+
+impl SafetyError {
+    /// Every [`SafetyError::kind`], in declaration order.
+    pub const KINDS: &'static [&'static str] = &[
+        "envelope_violation",
+        "host_budget_exceeded",
+        "energy_budget_exceeded",
+        "dw_ceiling_exceeded",
+        "pollutant_dw_ceiling_exceeded",
+        "invalid_corridor_area",
+        "invalid_period",
+        "invalid_reference",
+        "invalid_load_weights",
+        "invalid_eco_offset",
+        "invalid_geo_weight",
+        "zero_geo_weights",
+        "non_finite_node",
+        "non_finite_value",
+        "lyapunov_increase",
+        "battery_reserve",
+        "liability_cap_exceeded",
+        "hive_exclusion",
+        "outside_corridor",
+        "unauthorized_mode_exit",
+    ];
+
+    /// Stable snake_case name of the variant, e.g. for metric labels;
+    /// envelope-member attribution is looked through.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SafetyError::EnvelopeMember { error, .. } => error.kind(),
+            SafetyError::EnvelopeViolation { .. } => "envelope_violation",
+            SafetyError::HostBudgetExceeded { .. } => "host_budget_exceeded",
+            SafetyError::EnergyBudgetExceeded { .. } => "energy_budget_exceeded",
+            SafetyError::DwCeilingExceeded { .. } => "dw_ceiling_exceeded",
+            SafetyError::PollutantDwCeilingExceeded { .. } => "pollutant_dw_ceiling_exceeded",
+            SafetyError::InvalidCorridorArea { .. } => "invalid_corridor_area",
+            SafetyError::InvalidPeriod { .. } => "invalid_period",
+            SafetyError::InvalidReference { .. } => "invalid_reference",
+            SafetyError::InvalidLoadWeights { .. } => "invalid_load_weights",
+            SafetyError::InvalidEcoOffset { .. } => "invalid_eco_offset",
+            SafetyError::InvalidGeoWeight { .. } => "invalid_geo_weight",
+            SafetyError::ZeroGeoWeights => "zero_geo_weights",
+            SafetyError::NonFiniteNode { .. } => "non_finite_node",
+            SafetyError::NonFiniteValue { .. } => "non_finite_value",
+            SafetyError::LyapunovIncrease { .. } => "lyapunov_increase",
+            SafetyError::BatteryReserve { .. } => "battery_reserve",
+            SafetyError::LiabilityCapExceeded { .. } => "liability_cap_exceeded",
+            SafetyError::HiveExclusion { .. } => "hive_exclusion",
+            SafetyError::OutsideCorridor { .. } => "outside_corridor",
+            SafetyError::UnauthorizedModeExit { .. } => "unauthorized_mode_exit",
         }
-        _ => 0.0,
+    }
+
+    /// The error without any envelope-member attribution.
+    pub fn unattributed(&self) -> &SafetyError {
+        match self {
+            SafetyError::EnvelopeMember { error, .. } => error.unattributed(),
+            e => e,
+        }
+    }
+}
+
+impl CorridorRow {
+    /// Parsed concentration unit of this row.
+    pub fn concentration_unit(&self) -> Result<ConcentrationUnit, UnitError> {
+        self.unit.parse()
+    }
+
+    /// Parsed pollutant species of this row.
+    pub fn pollutant_kind(&self) -> Result<Pollutant, UnknownPollutant> {
+        self.pollutant.parse()
     }
 }
 
-/// CEIM-style mass operator M = C_u * Q * t.
-pub fn compute_mass_kg(row: &CorridorRow, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    let alpha = unit_to_kg_factor(&row.unit, temperature_k, molar_mass_kg_per_mol);
-    let delta_c = (row.cin - row.cout).max(0.0);
-    let c_u = alpha * delta_c;
-    c_u * row.airflow_m3_per_s * row.period_s
+/// CEIM-style mass operator M = C_u * Q * t, at standard pressure.
+/// Unknown units are an error rather than silently zero mass; the molar
+/// mass comes from `pollutant` and is only used for mixing-ratio units.
+pub fn compute_mass_kg(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+) -> Result<f64, UnitError> {
+    compute_mass_kg_at_pressure(row, pollutant, temperature_k, units::P_STANDARD_PA)
+}
+
+/// `compute_mass_kg` at an explicit ambient pressure, Pa.
+pub fn compute_mass_kg_at_pressure(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    pressure_pa: f64,
+) -> Result<f64, UnitError> {
+    let alpha = row.concentration_unit()?.kg_per_m3_factor_for_at_pressure(
+        pollutant,
+        temperature_k,
+        pressure_pa,
+    )?;
+    Ok(ceim::mass_kg(
+        row.cin,
+        row.cout,
+        alpha,
+        row.airflow_m3_per_s,
+        row.period_s,
+    ))
 }
 
 /// Hazard-weighted NanoKarmaBytes, K = lambda * beta * M.
 pub fn compute_karma_bytes(row: &CorridorRow, mass_kg: f64) -> f64 {
-    row.lambda_hazard * row.beta_nb_per_kg * mass_kg
+    ceim::karma_bytes(row.lambda_hazard, row.beta_nb_per_kg, mass_kg)
+}
+
+/// Why [`checked_mass_kg`] produced no mass.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MassError {
+    #[error(transparent)]
+    Unit(#[from] UnitError),
+    #[error(transparent)]
+    NonFinite(SafetyError),
+}
+
+/// Reject rows with a NaN or infinite reading or coefficient. Left in,
+/// they give NaN mass, or zero mass for a NaN `cin`, since the
+/// concentration drop is floored with `max`.
+pub fn check_row_finite(row: &CorridorRow) -> Result<(), SafetyError> {
+    for (field, value) in [
+        ("cin", row.cin),
+        ("cout", row.cout),
+        ("airflow_m3_per_s", row.airflow_m3_per_s),
+        ("period_s", row.period_s),
+        ("lambda_hazard", row.lambda_hazard),
+        ("beta_nb_per_kg", row.beta_nb_per_kg),
+        ("ecoimpact_score", row.ecoimpact_score),
+    ] {
+        if !value.is_finite() {
+            return Err(SafetyError::NonFiniteNode {
+                machine_id: row.machine_id.clone(),
+                field,
+                value,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `compute_mass_kg` for rows that passed [`check_row_finite`], with an
+/// overflowing result reported as `NonFiniteValue`.
+pub fn checked_mass_kg(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+) -> Result<f64, MassError> {
+    checked_mass_kg_at_pressure(row, pollutant, temperature_k, units::P_STANDARD_PA)
+}
+
+/// `checked_mass_kg` at an explicit ambient pressure, Pa.
+pub fn checked_mass_kg_at_pressure(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    pressure_pa: f64,
+) -> Result<f64, MassError> {
+    check_row_finite(row).map_err(MassError::NonFinite)?;
+    let mass_kg = compute_mass_kg_at_pressure(row, pollutant, temperature_k, pressure_pa)?;
+    finite_value(&row.machine_id, "mass_kg", mass_kg).map_err(MassError::NonFinite)
+}
+
+/// `compute_karma_bytes`, rejecting a non-finite row or mass and an
+/// overflowing result.
+pub fn checked_karma_bytes(row: &CorridorRow, mass_kg: f64) -> Result<f64, SafetyError> {
+    check_row_finite(row)?;
+    if !mass_kg.is_finite() {
+        return Err(SafetyError::NonFiniteNode {
+            machine_id: row.machine_id.clone(),
+            field: "mass_kg",
+            value: mass_kg,
+        });
+    }
+    finite_value(
+        &row.machine_id,
+        "karma_bytes",
+        compute_karma_bytes(row, mass_kg),
+    )
+}
+
+/// `value`, or `NonFiniteValue` naming `field` if it is NaN or infinite.
+fn finite_value(machine_id: &str, field: &'static str, value: f64) -> Result<f64, SafetyError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(SafetyError::NonFiniteValue {
+            machine_id: machine_id.to_string(),
+            field,
+            value,
+        })
+    }
 }
 
 /// Trait for safety envelope semantics.
 pub trait SafetyEnvelope {
     /// Returns Ok(()) if the node state is inside its safety envelope, Err otherwise.
     fn check_envelope(&self, node: &NodeState) -> Result<(), SafetyError>;
+    /// Every envelope violation, for dry runs; defaults to the first one only.
+    fn envelope_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        self.check_envelope(node).err().into_iter().collect()
+    }
+    /// Duty `ViolationPolicy::ClampToSafe` falls back to when the policy
+    /// names none; `None` means fully off.
+    fn safe_duty(&self) -> Option<f64> {
+        None
+    }
 }
 
 /// Trait for host-budget semantics (energy, power, liability caps).
@@ -89,6 +525,18 @@ pub trait HostBudget {
     fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError>;
     /// Return normalized power fraction P/P_max in [0, +inf).
     fn power_fraction(&self, node: &NodeState) -> f64;
+    /// Every host-budget violation, for dry runs; defaults to the first one only.
+    fn host_budget_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        self.check_host_budget(node).err().into_iter().collect()
+    }
+    /// Mutable state for a [`ControllerSnapshot`]; none by default.
+    fn budget_state(&self) -> BudgetState {
+        BudgetState::default()
+    }
+    /// Restore what `budget_state` saved.
+    fn restore_budget_state(&mut self, _state: &BudgetState) -> Result<(), SnapshotError> {
+        Ok(())
+    }
 }
 
 /// Trait for eco-band classification at corridor scope.
@@ -97,6 +545,14 @@ pub trait EcoBandClassifier {
     fn classify(&self, eco_load: f64) -> EcoBand;
     /// Optional band gain used in the duty update law.
     fn band_gain(&self, band: EcoBand) -> f64;
+    /// Mutable state for a [`ControllerSnapshot`]; none by default.
+    fn band_state(&self) -> BandState {
+        BandState::default()
+    }
+    /// Restore what `band_state` saved.
+    fn restore_band_state(&mut self, _state: &BandState) -> Result<(), SnapshotError> {
+        Ok(())
+    }
 }
 
 /// Trait for DW ceiling invariants over corridors.
@@ -105,43 +561,160 @@ pub trait DwCeilingInvariant {
     fn check_dw_ceiling(&self, phi_dw: f64) -> Result<(), SafetyError>;
     /// Returns normalized DW violation \delta_DW (>= 0 if violated, 0 otherwise).
     fn dw_violation(&self, phi_dw: f64) -> f64;
+    /// Pollutant-aware check; defaults to the corridor-wide ceiling.
+    fn check_dw_ceiling_for(&self, _pollutant: Pollutant, phi_dw: f64) -> Result<(), SafetyError> {
+        self.check_dw_ceiling(phi_dw)
+    }
+    /// Pollutant-aware violation; defaults to the corridor-wide ceiling.
+    fn dw_violation_for(&self, _pollutant: Pollutant, phi_dw: f64) -> f64 {
+        self.dw_violation(phi_dw)
+    }
+}
+
+/// Trait for bee-rights guards applied to a node's proposed duty cycle.
+pub trait BeeGuard {
+    /// Returns `Some(safe_duty)` if `u_new` would violate bee rights for
+    /// this node, `None` if it is admissible.
+    fn check_bee_rights(&self, node: &NodeState, u_new: f64) -> Option<f64>;
+}
+
+/// Guard that admits every duty cycle; the controller default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoBeeGuard;
+
+impl BeeGuard for NoBeeGuard {
+    fn check_bee_rights(&self, _node: &NodeState, _u_new: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Observer of controller activity, e.g. for exporting metrics.
+///
+/// Called from `eco_load` and `update_node_duty`; every method defaults to
+/// a no-op. Takes `&self` like the controller, so implementations that
+/// keep state need interior mutability.
+pub trait MetricsRecorder {
+    /// A corridor eco-load was computed.
+    fn record_eco_load(&self, _eco_load: f64) {}
+    /// `node` was updated as described by `report`.
+    fn record_update(&self, _node: &NodeState, _report: &UpdateReport) {}
+    /// A check failed with `error`.
+    fn record_error(&self, _error: &SafetyError) {}
 }
 
+/// Recorder that ignores everything; the controller default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoMetrics;
+
+impl MetricsRecorder for NoMetrics {}
+
 /// Simple rectangular envelope over duty, altitude, and ecoimpact.
 /// This is intentionally conservative; more complex A x <= b polytopes can be swapped in.
 #[derive(Debug, Clone)]
-pub struct RectSafetyEnvelope {
+pub struct RectSafetyEnvelope<A = FnAltitude> {
     pub u_min: f64,
     pub u_max: f64,
+    /// Altitude bounds; `-inf` and `+inf` together disable the altitude
+    /// check, and the altitude map is then never consulted.
     pub z_min_m: f64,
     pub z_max_m: f64,
     pub ecoimpact_min: f64,
     pub ecoimpact_max: f64,
-    /// Altitude map, provided externally.
-    pub altitude_m: fn(&str) -> f64,
+    /// Ceiling on `NodeState::noise_db`; `None` disables the check, and nodes
+    /// without a reading always pass it.
+    pub noise_max_db: Option<f64>,
+    /// Ceiling on `NodeState::emf_vpm`, with the same rules as `noise_max_db`.
+    pub emf_max_vpm: Option<f64>,
+    /// Altitude map, provided externally; unknown locations violate the
+    /// envelope unless the altitude check is disabled.
+    pub altitude: A,
 }
 
-impl SafetyEnvelope for RectSafetyEnvelope {
+impl<A: AltitudeProvider> SafetyEnvelope for RectSafetyEnvelope<A> {
     fn check_envelope(&self, node: &NodeState) -> Result<(), SafetyError> {
-        let u = node.duty_cycle;
-        if u < self.u_min || u > self.u_max {
-            return Err(SafetyError::EnvelopeViolation("duty_cycle out of bounds"));
-        }
-        let z = (self.altitude_m)(&node.row.location);
-        if z < self.z_min_m || z > self.z_max_m {
-            return Err(SafetyError::EnvelopeViolation("altitude out of bounds"));
+        match self.envelope_violations(node).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
+    }
+
+    fn envelope_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        let z_unbounded = self.z_min_m == f64::NEG_INFINITY && self.z_max_m == f64::INFINITY;
+        let z = if z_unbounded {
+            0.0
+        } else {
+            self.altitude
+                .altitude_m(&node.row.location)
+                .unwrap_or(f64::NAN)
+        };
+        let u = node.duty_cycle;
         let s = node.row.ecoimpact_score;
-        if s < self.ecoimpact_min || s > self.ecoimpact_max {
-            return Err(SafetyError::EnvelopeViolation("ecoimpact score outside envelope"));
-        }
-        Ok(())
+        let ceiling = |value: Option<f64>, max: Option<f64>| match (value, max) {
+            (Some(v), Some(max)) => (v, max, v > max),
+            _ => (f64::NAN, f64::INFINITY, false),
+        };
+        let (noise, noise_max, noise_over) = ceiling(node.noise_db, self.noise_max_db);
+        let (emf, emf_max, emf_over) = ceiling(node.emf_vpm, self.emf_max_vpm);
+        [
+            (
+                EnvelopeField::DutyCycle,
+                u,
+                self.u_min,
+                self.u_max,
+                u < self.u_min || u > self.u_max,
+            ),
+            (
+                EnvelopeField::Altitude,
+                z,
+                self.z_min_m,
+                self.z_max_m,
+                !z_unbounded && !(self.z_min_m..=self.z_max_m).contains(&z),
+            ),
+            (
+                EnvelopeField::EcoimpactScore,
+                s,
+                self.ecoimpact_min,
+                self.ecoimpact_max,
+                s < self.ecoimpact_min || s > self.ecoimpact_max,
+            ),
+            (
+                EnvelopeField::NoiseDb,
+                noise,
+                f64::NEG_INFINITY,
+                noise_max,
+                noise_over,
+            ),
+            (
+                EnvelopeField::EmfVpm,
+                emf,
+                f64::NEG_INFINITY,
+                emf_max,
+                emf_over,
+            ),
+        ]
+        .into_iter()
+        .filter(|check| check.4)
+        .map(
+            |(field, value, min, max, _)| SafetyError::EnvelopeViolation {
+                machine_id: node.row.machine_id.clone(),
+                field,
+                value,
+                min,
+                max,
+            },
+        )
+        .collect()
+    }
+
+    fn safe_duty(&self) -> Option<f64> {
+        Some(self.u_min)
     }
 }
 
 /// Simple host budget over instantaneous power and per-step energy.
 /// For full horizons, you can integrate externally and feed cumulative metrics here.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SimpleHostBudget {
     pub p_max_w: f64,
     pub e_step_max_j: f64,
@@ -151,14 +724,30 @@ pub struct SimpleHostBudget {
 
 impl HostBudget for SimpleHostBudget {
     fn check_host_budget(&self, node: &NodeState) -> Result<(), SafetyError> {
+        match self.host_budget_violations(node).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn host_budget_violations(&self, node: &NodeState) -> Vec<SafetyError> {
+        let mut violations = Vec::new();
         if node.power_w > self.p_max_w {
-            return Err(SafetyError::HostBudgetExceeded("instantaneous power exceeded"));
+            violations.push(SafetyError::HostBudgetExceeded {
+                machine_id: node.row.machine_id.clone(),
+                power_w: node.power_w,
+                p_max_w: self.p_max_w,
+            });
         }
         let e_step = node.power_w * self.step_dt_s;
         if e_step > self.e_step_max_j {
-            return Err(SafetyError::HostBudgetExceeded("per-step energy exceeded"));
+            violations.push(SafetyError::EnergyBudgetExceeded {
+                machine_id: node.row.machine_id.clone(),
+                energy_j: e_step,
+                e_max_j: self.e_step_max_j,
+            });
         }
-        Ok(())
+        violations
     }
 
     fn power_fraction(&self, node: &NodeState) -> f64 {
@@ -171,7 +760,8 @@ impl HostBudget for SimpleHostBudget {
 }
 
 /// Linear eco-band classifier based on corridor eco-load.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ThresholdEcoBand {
     pub theta_green_amber: f64,
     pub theta_amber_red: f64,
@@ -200,8 +790,134 @@ impl EcoBandClassifier for ThresholdEcoBand {
     }
 }
 
-/// DW ceiling invariant over mass flux density.
+/// Eco-band classifier with per-boundary hysteresis.
+///
+/// Escalation across a boundary happens when the load reaches its threshold
+/// (`theta_*`); de-escalation only once the load falls below `theta_* - gap_*`.
+/// The previous band is kept in a `Cell` so the classifier still implements
+/// `EcoBandClassifier::classify(&self, ..)` and can sit inside a shared
+/// `CorridorController`; the price is that it is `!Sync`, so use one
+/// instance per control loop.
 #[derive(Debug, Clone)]
+pub struct HystereticEcoBand {
+    pub theta_green_amber: f64,
+    pub theta_amber_red: f64,
+    /// De-escalation gap below `theta_green_amber` (must be > 0).
+    pub gap_green_amber: f64,
+    /// De-escalation gap below `theta_amber_red` (must be > 0).
+    pub gap_amber_red: f64,
+    pub gain_green: f64,
+    pub gain_amber: f64,
+    pub gain_red: f64,
+    state: Cell<EcoBand>,
+}
+
+impl HystereticEcoBand {
+    /// Build a hysteretic classifier starting in `initial`.
+    ///
+    /// Panics if a gap is not strictly positive, since the de-escalation
+    /// threshold must be strictly below the escalation threshold.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        theta_green_amber: f64,
+        theta_amber_red: f64,
+        gap_green_amber: f64,
+        gap_amber_red: f64,
+        gain_green: f64,
+        gain_amber: f64,
+        gain_red: f64,
+        initial: EcoBand,
+    ) -> Self {
+        assert!(gap_green_amber > 0.0, "gap_green_amber must be > 0");
+        assert!(gap_amber_red > 0.0, "gap_amber_red must be > 0");
+        HystereticEcoBand {
+            theta_green_amber,
+            theta_amber_red,
+            gap_green_amber,
+            gap_amber_red,
+            gain_green,
+            gain_amber,
+            gain_red,
+            state: Cell::new(initial),
+        }
+    }
+
+    /// Band remembered from the last `classify` call.
+    pub fn current_band(&self) -> EcoBand {
+        self.state.get()
+    }
+
+    /// Force the remembered band, e.g. after a controller restart.
+    pub fn reset(&self, band: EcoBand) {
+        self.state.set(band);
+    }
+}
+
+impl EcoBandClassifier for HystereticEcoBand {
+    fn classify(&self, eco_load: f64) -> EcoBand {
+        let down_green = self.theta_green_amber - self.gap_green_amber;
+        let down_amber = self.theta_amber_red - self.gap_amber_red;
+
+        let next = match self.state.get() {
+            EcoBand::Green => {
+                if eco_load >= self.theta_amber_red {
+                    EcoBand::Red
+                } else if eco_load >= self.theta_green_amber {
+                    EcoBand::Amber
+                } else {
+                    EcoBand::Green
+                }
+            }
+            EcoBand::Amber => {
+                if eco_load >= self.theta_amber_red {
+                    EcoBand::Red
+                } else if eco_load < down_green {
+                    EcoBand::Green
+                } else {
+                    EcoBand::Amber
+                }
+            }
+            EcoBand::Red => {
+                if eco_load >= down_amber {
+                    EcoBand::Red
+                } else if eco_load < down_green {
+                    EcoBand::Green
+                } else {
+                    EcoBand::Amber
+                }
+            }
+        };
+        self.state.set(next);
+        next
+    }
+
+    fn band_gain(&self, band: EcoBand) -> f64 {
+        match band {
+            EcoBand::Green => self.gain_green,
+            EcoBand::Amber => self.gain_amber,
+            EcoBand::Red => self.gain_red,
+        }
+    }
+
+    fn band_state(&self) -> BandState {
+        BandState {
+            current: Some(self.current_band()),
+            ..BandState::default()
+        }
+    }
+
+    fn restore_band_state(&mut self, state: &BandState) -> Result<(), SnapshotError> {
+        let band = state.current.ok_or(SnapshotError::Missing {
+            component: "hysteretic band",
+        })?;
+        self.reset(band);
+        Ok(())
+    }
+}
+
+/// DW ceiling invariant over mass flux density.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SimpleDwCeiling {
     pub phi_dw_max: f64,
 }
@@ -209,34 +925,155 @@ pub struct SimpleDwCeiling {
 impl DwCeilingInvariant for SimpleDwCeiling {
     fn check_dw_ceiling(&self, phi_dw: f64) -> Result<(), SafetyError> {
         if phi_dw > self.phi_dw_max {
-            Err(SafetyError::DwCeilingExceeded("dw ceiling violated"))
+            Err(SafetyError::DwCeilingExceeded {
+                phi_dw,
+                phi_dw_max: self.phi_dw_max,
+            })
         } else {
             Ok(())
         }
     }
 
     fn dw_violation(&self, phi_dw: f64) -> f64 {
-        if self.phi_dw_max <= 0.0 {
-            0.0
+        relative_dw_violation(phi_dw, self.phi_dw_max)
+    }
+}
+
+/// (Phi_dw - Phi_max) / Phi_max, floored at 0. Most nodes sit under the
+/// ceiling, where the quotient is never positive and the floor gives the
+/// same 0 without dividing.
+fn relative_dw_violation(phi_dw: f64, phi_dw_max: f64) -> f64 {
+    if phi_dw_max <= 0.0 || phi_dw <= phi_dw_max {
+        0.0
+    } else {
+        ((phi_dw - phi_dw_max) / phi_dw_max).max(0.0)
+    }
+}
+
+/// DW ceilings keyed by pollutant; pollutants without an entry use `default_phi_dw_max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MapDwCeiling {
+    pub ceilings: HashMap<Pollutant, f64>,
+    pub default_phi_dw_max: f64,
+}
+
+impl MapDwCeiling {
+    pub fn new(default_phi_dw_max: f64) -> Self {
+        MapDwCeiling {
+            ceilings: HashMap::new(),
+            default_phi_dw_max,
+        }
+    }
+
+    pub fn with_ceiling(mut self, pollutant: Pollutant, phi_dw_max: f64) -> Self {
+        self.ceilings.insert(pollutant, phi_dw_max);
+        self
+    }
+
+    pub fn phi_dw_max(&self, pollutant: Pollutant) -> f64 {
+        self.ceilings
+            .get(&pollutant)
+            .copied()
+            .unwrap_or(self.default_phi_dw_max)
+    }
+}
+
+impl DwCeilingInvariant for MapDwCeiling {
+    fn check_dw_ceiling(&self, phi_dw: f64) -> Result<(), SafetyError> {
+        if phi_dw > self.default_phi_dw_max {
+            Err(SafetyError::DwCeilingExceeded {
+                phi_dw,
+                phi_dw_max: self.default_phi_dw_max,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn dw_violation(&self, phi_dw: f64) -> f64 {
+        relative_dw_violation(phi_dw, self.default_phi_dw_max)
+    }
+
+    fn check_dw_ceiling_for(&self, pollutant: Pollutant, phi_dw: f64) -> Result<(), SafetyError> {
+        let phi_dw_max = self.phi_dw_max(pollutant);
+        if phi_dw > phi_dw_max {
+            Err(SafetyError::PollutantDwCeilingExceeded {
+                pollutant,
+                phi_dw,
+                phi_dw_max,
+            })
         } else {
-            ((phi_dw - self.phi_dw_max) / self.phi_dw_max).max(0.0)
+            Ok(())
+        }
+    }
+
+    fn dw_violation_for(&self, pollutant: Pollutant, phi_dw: f64) -> f64 {
+        relative_dw_violation(phi_dw, self.phi_dw_max(pollutant))
+    }
+}
+
+/// Reject nodes whose mass or karma would poison corridor sums.
+pub(crate) fn check_node_finite(node: &NodeState) -> Result<(), SafetyError> {
+    for (field, value) in [("mass_kg", node.mass_kg), ("karma_bytes", node.karma_bytes)] {
+        if !value.is_finite() {
+            return Err(SafetyError::NonFiniteNode {
+                machine_id: node.row.machine_id.clone(),
+                field,
+                value,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reading over its reference scale in [0, +inf), like
+/// [`HostBudget::power_fraction`]; 0 without a reading or a positive scale.
+fn exposure_fraction(reading: Option<f64>, reference: f64) -> f64 {
+    match reading {
+        Some(v) if reference > 0.0 => (v / reference).max(0.0),
+        _ => 0.0,
+    }
+}
+
+/// Reject duty, power, weight, noise or EMF values that would poison the
+/// duty law.
+pub(crate) fn check_duty_inputs_finite(node: &NodeState) -> Result<(), SafetyError> {
+    for (field, value) in [
+        ("duty_cycle", Some(node.duty_cycle)),
+        ("power_w", Some(node.power_w)),
+        ("geo_weight", Some(node.geo_weight)),
+        ("noise_db", node.noise_db),
+        ("emf_vpm", node.emf_vpm),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_finite()) {
+            return Err(SafetyError::NonFiniteNode {
+                machine_id: node.row.machine_id.clone(),
+                field,
+                value,
+            });
         }
     }
+    Ok(())
 }
 
 /// Unified corridor controller composing the four semantics into a duty update.
 #[derive(Debug, Clone)]
-pub struct CorridorController<E, H, B, D>
+pub struct CorridorController<E, H, B, D, G = NoBeeGuard, R = NoMetrics>
 where
     E: SafetyEnvelope,
     H: HostBudget,
     B: EcoBandClassifier,
     D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
 {
     pub envelope: E,
     pub host_budget: H,
     pub eco_band: B,
     pub dw_ceiling: D,
+    /// Corridor cross-section area used for DW flux density (m^2).
+    pub corridor_area_m2: f64,
     // Reference scales from shard
     pub m_ref_kg: f64,
     pub k_ref_nb: f64,
@@ -247,82 +1084,2454 @@ where
     pub eta_b: f64,
     pub eta_p: f64,
     pub eta_dw: f64,
+    /// Noise and EMF exposure gains; nodes without a reading are not
+    /// penalized.
+    pub eta_noise: f64,
+    pub eta_emf: f64,
+    /// Reference scales for the exposure terms; non-positive disables them.
+    pub noise_ref_db: f64,
+    pub emf_ref_vpm: f64,
+    /// Per-node and per-band replacements for the gains above; see
+    /// [`gains`]. Empty runs every node on the `eta_*` fields.
+    pub gain_schedule: GainSchedule,
+    /// Time-of-week duty caps applied after everything else; see
+    /// [`schedule`]. `None` disables them.
+    pub duty_schedule: Option<ClockedSchedule>,
+    /// Wind-dependent geo weighting toward receptors; see [`wind`]. `None`,
+    /// or an update without wind, uses `NodeState::geo_weight`.
+    pub geo_weighting: Option<DynamicGeoWeight>,
+    /// Airflow at duty for `run_steps`' eco-load; see [`airflow`]. `None`
+    /// takes mass and karma as measured, whatever the duty.
+    pub airflow: Option<AirflowModels>,
+    /// Duty ceilings from the operating agent's blood-gate level; see
+    /// [`gate`]. `None` disables them.
+    pub agent_gate: Option<AgentGate>,
+    /// Machines this controller may update; see [`topology`]. `None`
+    /// updates any node it is given.
+    pub corridor: Option<CorridorMembership>,
+    /// Aggregation `eco_load_with_offset`, and so `run_steps`, uses.
+    pub eco_load_mode: EcoLoadMode,
+    /// Optional per-step slew limit on duty-cycle changes; `None` disables it.
+    pub slew_limit: Option<SlewLimit>,
+    /// What `update_node_duty` does when a node fails its checks.
+    pub violation_policy: ViolationPolicy,
+    /// Optional Lyapunov monitor over whole update passes; `None` disables it.
+    pub stability: Option<StabilityMonitor>,
+    /// Bee-rights guard applied to the projected duty before assignment.
+    pub bee_guard: G,
+    /// Told about every eco-load, duty update and failed check.
+    pub metrics: R,
+}
+
+/// How `update_node_duty` treats a node that fails its envelope,
+/// host-budget or DW-ceiling check. Invalid references and non-finite
+/// telemetry are errors under every policy, since the law cannot run.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ViolationPolicy {
+    /// Return the first envelope or host-budget failure and leave the duty
+    /// as it was; a DW-ceiling breach is only penalized by the law.
+    #[default]
+    Error,
+    /// Drive the duty toward `safe_duty`, else the envelope's
+    /// [`SafetyEnvelope::safe_duty`], else 0, through the slew limit and
+    /// the bee guard.
+    ClampToSafe { safe_duty: Option<f64> },
+    /// Leave the duty unchanged and report the failures.
+    SkipNode,
+}
+
+/// What a non-`Error` [`ViolationPolicy`] did with a failing node, or
+/// that a non-finite duty was refused under any policy.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ViolationAction {
+    /// The duty was driven toward `target`.
+    ClampedToSafe {
+        target: f64,
+    },
+    Skipped,
+    /// The law produced a NaN or infinite duty; the previous one was kept.
+    HeldPrevious,
+}
+
+/// Failed checks handled instead of erroring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ViolationHandling {
+    pub action: ViolationAction,
+    /// Each failure's message, in check order.
+    pub violations: Vec<String>,
+}
+
+/// Maximum duty-cycle change per controller step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SlewLimit {
+    pub max_up: f64,
+    pub max_down: f64,
+}
+
+impl SlewLimit {
+    /// Same limit in both directions (`max_delta_per_step`).
+    pub fn symmetric(max_delta_per_step: f64) -> Self {
+        SlewLimit {
+            max_up: max_delta_per_step,
+            max_down: max_delta_per_step,
+        }
+    }
+
+    /// Limit `u_new` to `[u_old - max_down, u_old + max_up]`.
+    pub fn apply(&self, u_old: f64, u_new: f64) -> f64 {
+        u_new.clamp(u_old - self.max_down.max(0.0), u_old + self.max_up.max(0.0))
+    }
+}
+
+/// Signed contribution of each Equation 5 term to a duty update.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DutyContributions {
+    pub mass: f64,
+    pub karma: f64,
+    pub geo_weight: f64,
+    pub band_gain: f64,
+    /// Power penalty (<= 0 for non-negative gains).
+    pub power: f64,
+    /// DW violation penalty (<= 0 for non-negative gains).
+    pub dw_violation: f64,
+    /// Noise exposure penalty (<= 0 for non-negative gains).
+    #[serde(default)]
+    pub noise: f64,
+    /// EMF exposure penalty (<= 0 for non-negative gains).
+    #[serde(default)]
+    pub emf: f64,
+}
+
+impl DutyContributions {
+    /// Sum of all terms, i.e. the unclamped duty delta.
+    pub fn total(&self) -> f64 {
+        self.mass
+            + self.karma
+            + self.geo_weight
+            + self.band_gain
+            + self.power
+            + self.dw_violation
+            + self.noise
+            + self.emf
+    }
+}
+
+/// Bee-rights guard override of a proposed duty cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BeeRightsClamped {
+    /// Duty the update law proposed after slew limiting and projection.
+    pub proposed: f64,
+    /// Safe duty returned by the guard and applied instead.
+    pub applied: f64,
+}
+
+/// Corridor eco-load before and after an external credit, e.g. carbonation
+/// uptake from mineral sheets deployed alongside the nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EcoLoadBreakdown {
+    /// Equation 3 load from the nodes alone.
+    pub gross: f64,
+    /// Credit offered, in eco-load units.
+    pub offset_requested: f64,
+    /// Credit actually subtracted; never more than `gross`.
+    pub offset_applied: f64,
+    /// `gross - offset_applied`, never below zero.
+    pub net: f64,
+}
+
+/// How corridor nodes are aggregated into the eco-load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EcoLoadMode {
+    /// Every node counts equally (`CorridorController::eco_load`).
+    #[default]
+    Unweighted,
+    /// Nodes count by `geo_weight` (`CorridorController::eco_load_weighted`).
+    GeoWeighted,
+}
+
+/// Audit record of a successful duty update, explaining why the duty moved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UpdateReport {
+    pub machine_id: String,
+    pub eco_band: EcoBand,
+    pub duty_before: f64,
+    pub duty_after: f64,
+    pub contributions: DutyContributions,
+    /// True if the [0,1] projection changed the (slew-limited) duty.
+    pub clipped: bool,
+    slew_limited: bool,
+    /// Set if the bee guard rejected the proposed duty.
+    pub bee_rights_clamped: Option<BeeRightsClamped>,
+    /// Set if the node failed its checks and the controller's
+    /// [`ViolationPolicy`] clamped or skipped it; the duty law did not run,
+    /// so `contributions` are all zero.
+    #[serde(default)]
+    pub violation: Option<ViolationHandling>,
+    /// The node row's `trace_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<Uuid>,
+    /// Which gains Equation 5 ran with; `None` if it did not run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain_source: Option<GainSource>,
+    /// The duty schedule window open at the update, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_cap: Option<ScheduleCap>,
+    /// Static and effective geo weight; `None` if the law did not run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo_weight: Option<GeoWeighting>,
+    /// The operating agent's gate cap, if the controller has an
+    /// `agent_gate`; distinct from envelope clamps in `violation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gate_cap: Option<GateCap>,
+}
+
+impl UpdateReport {
+    /// True if the slew limit changed the applied duty cycle.
+    pub fn slew_limited(&self) -> bool {
+        self.slew_limited
+    }
+
+    /// Duty delta before slew limiting and projection.
+    pub fn unclamped_delta(&self) -> f64 {
+        self.contributions.total()
+    }
+}
+
+/// Pre-flight view of one node: every failed check, and the update the
+/// duty law would make regardless.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeAssessment {
+    pub machine_id: String,
+    /// Envelope, host-budget, reference, finiteness and DW-ceiling
+    /// failures, in that order. Under `ViolationPolicy::Error`,
+    /// `update_node_duty` refuses on all but the DW ceiling, which it only
+    /// penalizes.
+    pub violations: Vec<SafetyError>,
+    /// Report `update_node_duty` would return if the checks passed; its
+    /// `duty_after`, `clipped`, `slew_limited()` and `bee_rights_clamped`
+    /// say where the duty would land and what would bind. `None` when the
+    /// references or the node's mass, karma or exposure readings make the
+    /// law incomputable.
+    pub projected: Option<UpdateReport>,
+}
+
+impl NodeAssessment {
+    /// True if `update_node_duty` would succeed and no ceiling is exceeded.
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// [`NodeAssessment`]s for a corridor, in node order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorridorAssessment {
+    pub nodes: Vec<NodeAssessment>,
+}
+
+impl CorridorAssessment {
+    pub fn is_clean(&self) -> bool {
+        self.nodes.iter().all(NodeAssessment::is_clean)
+    }
+
+    /// All violations across nodes, in node order.
+    pub fn violations(&self) -> impl Iterator<Item = &SafetyError> {
+        self.nodes.iter().flat_map(|n| &n.violations)
+    }
+
+    /// Violation counts by [`SafetyError::kind`].
+    pub fn counts_by_kind(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for e in self.violations() {
+            *counts.entry(e.kind()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Nodes whose projected duty would be changed by the slew limit.
+    pub fn slew_limited(&self) -> impl Iterator<Item = &NodeAssessment> {
+        self.nodes
+            .iter()
+            .filter(|n| n.projected.as_ref().is_some_and(UpdateReport::slew_limited))
+    }
 }
 
-impl<E, H, B, D> CorridorController<E, H, B, D>
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
 where
     E: SafetyEnvelope,
     H: HostBudget,
     B: EcoBandClassifier,
     D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
 {
-    /// Compute corridor-wide eco-load from nodes.
-    /// This is Equation 3: E_corr = a_M M_corr/M_ref + a_K K_corr/K_ref.
-    pub fn eco_load(&self, nodes: &[NodeState], alpha_m: f64, alpha_k: f64) -> f64 {
-        let m_sum: f64 = nodes.iter().map(|n| n.mass_kg).sum();
-        let k_sum: f64 = nodes.iter().map(|n| n.karma_bytes).sum();
-        let m_norm = if self.m_ref_kg > 0.0 {
-            m_sum / self.m_ref_kg
-        } else {
-            0.0
-        };
-        let k_norm = if self.k_ref_nb > 0.0 {
-            k_sum / self.k_ref_nb
-        } else {
-            0.0
-        };
-        alpha_m * m_norm + alpha_k * k_norm
+    /// Replace the safety envelope, keeping the rest of the configuration.
+    pub fn with_envelope<E2: SafetyEnvelope>(
+        self,
+        envelope: E2,
+    ) -> CorridorController<E2, H, B, D, G, R> {
+        CorridorController {
+            envelope,
+            host_budget: self.host_budget,
+            eco_band: self.eco_band,
+            dw_ceiling: self.dw_ceiling,
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule,
+            duty_schedule: self.duty_schedule,
+            geo_weighting: self.geo_weighting,
+            airflow: self.airflow,
+            agent_gate: self.agent_gate,
+            corridor: self.corridor,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: self.stability,
+            bee_guard: self.bee_guard,
+            metrics: self.metrics,
+        }
     }
 
-    /// Compute DW flux density for the corridor from raw in/out flows.
-    /// Here flux is supplied externally (already aggregated).
-    pub fn dw_flux_density(&self, phi_dw_raw: f64) -> f64 {
-        phi_dw_raw
+    /// Replace the host budget, keeping the rest of the configuration.
+    pub fn with_host_budget<H2: HostBudget>(
+        self,
+        host_budget: H2,
+    ) -> CorridorController<E, H2, B, D, G, R> {
+        CorridorController {
+            envelope: self.envelope,
+            host_budget,
+            eco_band: self.eco_band,
+            dw_ceiling: self.dw_ceiling,
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule,
+            duty_schedule: self.duty_schedule,
+            geo_weighting: self.geo_weighting,
+            airflow: self.airflow,
+            agent_gate: self.agent_gate,
+            corridor: self.corridor,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: self.stability,
+            bee_guard: self.bee_guard,
+            metrics: self.metrics,
+        }
     }
 
-    /// Update a single node's duty-cycle using Equation 5, after all checks.
-    pub fn update_node_duty(
-        &self,
-        node: &mut NodeState,
-        eco_band: EcoBand,
-        phi_dw: f64,
-    ) -> Result<(), SafetyError> {
-        // Envelope and host-budget checks first.
-        self.envelope.check_envelope(node)?;
-        self.host_budget.check_host_budget(node)?;
+    /// Start replacing the host budget with a `CompositeHostBudget`:
+    /// add members with `member`, then `build`.
+    pub fn with_host_budgets(self) -> ControllerBudgetBuilder<E, H, B, D, G, R> {
+        ControllerBudgetBuilder::new(self)
+    }
 
-        // Compute normalized components.
-        let m_norm = if self.m_ref_kg > 0.0 {
-            node.mass_kg / self.m_ref_kg
-        } else {
-            0.0
-        };
-        let k_norm = if self.k_ref_nb > 0.0 {
-            node.karma_bytes / self.k_ref_nb
-        } else {
-            0.0
-        };
-        let w = node.geo_weight;
-        let band_gain = self.eco_band.band_gain(eco_band);
-        let p_frac = self.host_budget.power_fraction(node);
-        let dw_violation = self.dw_ceiling.dw_violation(phi_dw);
+    /// Replace the eco-band classifier, keeping the rest of the configuration.
+    pub fn with_eco_band<B2: EcoBandClassifier>(
+        self,
+        eco_band: B2,
+    ) -> CorridorController<E, H, B2, D, G, R> {
+        CorridorController {
+            envelope: self.envelope,
+            host_budget: self.host_budget,
+            eco_band,
+            dw_ceiling: self.dw_ceiling,
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule,
+            duty_schedule: self.duty_schedule,
+            geo_weighting: self.geo_weighting,
+            airflow: self.airflow,
+            agent_gate: self.agent_gate,
+            corridor: self.corridor,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: self.stability,
+            bee_guard: self.bee_guard,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Replace the bee guard, keeping the rest of the configuration.
+    pub fn with_bee_guard<G2: BeeGuard>(
+        self,
+        bee_guard: G2,
+    ) -> CorridorController<E, H, B, D, G2, R> {
+        CorridorController {
+            envelope: self.envelope,
+            host_budget: self.host_budget,
+            eco_band: self.eco_band,
+            dw_ceiling: self.dw_ceiling,
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule,
+            duty_schedule: self.duty_schedule,
+            geo_weighting: self.geo_weighting,
+            airflow: self.airflow,
+            agent_gate: self.agent_gate,
+            corridor: self.corridor,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: self.stability,
+            bee_guard,
+            metrics: self.metrics,
+        }
+    }
 
-        let mut u_new = node.duty_cycle
-            + self.eta_m * m_norm
-            + self.eta_k * k_norm
-            + self.eta_w * w
-            + self.eta_b * band_gain
-            - self.eta_p * p_frac
-            - self.eta_dw * dw_violation;
+    /// Replace the metrics recorder, keeping the rest of the configuration.
+    pub fn with_metrics<R2: MetricsRecorder>(
+        self,
+        metrics: R2,
+    ) -> CorridorController<E, H, B, D, G, R2> {
+        CorridorController {
+            envelope: self.envelope,
+            host_budget: self.host_budget,
+            eco_band: self.eco_band,
+            dw_ceiling: self.dw_ceiling,
+            corridor_area_m2: self.corridor_area_m2,
+            m_ref_kg: self.m_ref_kg,
+            k_ref_nb: self.k_ref_nb,
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+            noise_ref_db: self.noise_ref_db,
+            emf_ref_vpm: self.emf_ref_vpm,
+            gain_schedule: self.gain_schedule,
+            duty_schedule: self.duty_schedule,
+            geo_weighting: self.geo_weighting,
+            airflow: self.airflow,
+            agent_gate: self.agent_gate,
+            corridor: self.corridor,
+            eco_load_mode: self.eco_load_mode,
+            slew_limit: self.slew_limit,
+            violation_policy: self.violation_policy,
+            stability: self.stability,
+            bee_guard: self.bee_guard,
+            metrics,
+        }
+    }
+
+    /// The `eta_*` fields as a gain set.
+    pub fn default_gains(&self) -> GainConfig {
+        GainConfig {
+            eta_m: self.eta_m,
+            eta_k: self.eta_k,
+            eta_w: self.eta_w,
+            eta_b: self.eta_b,
+            eta_p: self.eta_p,
+            eta_dw: self.eta_dw,
+            eta_noise: self.eta_noise,
+            eta_emf: self.eta_emf,
+        }
+    }
 
-        // Project onto [0,1].
-        if u_new < 0.0 {
-            u_new = 0.0;
-        } else if u_new > 1.0 {
-            u_new = 1.0;
+    /// Gains `update_node_duty` uses for `machine_id` in `eco_band`: the
+    /// node override, else the band schedule, else `default_gains`.
+    pub fn effective_gains(&self, machine_id: &str, eco_band: EcoBand) -> (GainConfig, GainSource) {
+        match self.gain_schedule.resolve(machine_id, eco_band) {
+            Some((gains, source)) => (gains.clone(), source),
+            None => (self.default_gains(), GainSource::ControllerDefault),
         }
+    }
 
-        node.duty_cycle = u_new;
+    /// Ensure M_ref and K_ref can normalize loads.
+    pub(crate) fn check_references(&self) -> Result<(), SafetyError> {
+        for (name, value) in [("m_ref_kg", self.m_ref_kg), ("k_ref_nb", self.k_ref_nb)] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(SafetyError::InvalidReference { name, value });
+            }
+        }
         Ok(())
     }
+
+    /// Compute corridor-wide eco-load from nodes.
+    /// This is Equation 3: E_corr = a_M M_corr/M_ref + a_K K_corr/K_ref.
+    ///
+    /// The weights are normalized to a_M + a_K = 1, so only their ratio
+    /// matters. Errors on non-positive references, negative or all-zero
+    /// weights, and nodes with non-finite mass or karma.
+    ///
+    /// One pass over `nodes` with compensated sums, so any iterator of node
+    /// references works without collecting, and rounding does not grow with
+    /// corridor size or depend on node order.
+    ///
+    /// Quarantined nodes are left out; see [`OperationalMode::Quarantined`].
+    pub fn eco_load<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a NodeState>,
+        alpha_m: f64,
+        alpha_k: f64,
+    ) -> Result<f64, SafetyError> {
+        let result = (|| {
+            let (a_m, a_k) = self.load_weights(alpha_m, alpha_k)?;
+            let mut m_sum = KahanSum::default();
+            let mut k_sum = KahanSum::default();
+            for n in nodes.into_iter().filter(|n| n.mode.counts_toward_load()) {
+                check_node_finite(n)?;
+                m_sum.add(n.mass_kg);
+                k_sum.add(n.karma_bytes);
+            }
+            Ok(a_m * m_sum.total() / self.m_ref_kg + a_k * k_sum.total() / self.k_ref_nb)
+        })();
+        match &result {
+            Ok(load) => self.metrics.record_eco_load(*load),
+            Err(e) => self.metrics.record_error(e),
+        }
+        result
+    }
+
+    /// Equation 3 with each node's normalized mass and karma scaled by its
+    /// `geo_weight`, so sensitive locations weigh more:
+    /// E_corr = N / sum_i w_i * sum_i w_i (a_M M_i/M_ref + a_K K_i/K_ref).
+    ///
+    /// The N / sum_i w_i factor keeps the scale of `eco_load`: equal weights
+    /// give the same load. An empty corridor has load 0; otherwise errors as
+    /// `eco_load` does, and on negative or non-finite weights or weights that
+    /// are all zero. Sums, and skips quarantined nodes, like `eco_load`.
+    pub fn eco_load_weighted<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a NodeState>,
+        alpha_m: f64,
+        alpha_k: f64,
+    ) -> Result<f64, SafetyError> {
+        let result = (|| {
+            let (a_m, a_k) = self.load_weights(alpha_m, alpha_k)?;
+            let mut weighted = KahanSum::default();
+            let mut w_sum = KahanSum::default();
+            let mut count = 0usize;
+            for n in nodes.into_iter().filter(|n| n.mode.counts_toward_load()) {
+                check_node_finite(n)?;
+                let w = n.geo_weight;
+                if !(w.is_finite() && w >= 0.0) {
+                    return Err(SafetyError::InvalidGeoWeight {
+                        machine_id: n.row.machine_id.clone(),
+                        geo_weight: w,
+                    });
+                }
+                weighted.add(
+                    w * (a_m * n.mass_kg / self.m_ref_kg + a_k * n.karma_bytes / self.k_ref_nb),
+                );
+                w_sum.add(w);
+                count += 1;
+            }
+            let w_sum = w_sum.total();
+            if count == 0 {
+                Ok(0.0)
+            } else if w_sum > 0.0 {
+                Ok(count as f64 * weighted.total() / w_sum)
+            } else {
+                Err(SafetyError::ZeroGeoWeights)
+            }
+        })();
+        match &result {
+            Ok(load) => self.metrics.record_eco_load(*load),
+            Err(e) => self.metrics.record_error(e),
+        }
+        result
+    }
+
+    /// Eco-load under the controller's `eco_load_mode`, less a non-negative
+    /// `offset`, floored at zero so a credit can cancel the corridor's load
+    /// but never bank negative load.
+    pub fn eco_load_with_offset<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = &'a NodeState>,
+        alpha_m: f64,
+        alpha_k: f64,
+        offset: f64,
+    ) -> Result<EcoLoadBreakdown, SafetyError> {
+        if !(offset.is_finite() && offset >= 0.0) {
+            return Err(SafetyError::InvalidEcoOffset { offset });
+        }
+        let gross = match self.eco_load_mode {
+            EcoLoadMode::Unweighted => self.eco_load(nodes, alpha_m, alpha_k)?,
+            EcoLoadMode::GeoWeighted => self.eco_load_weighted(nodes, alpha_m, alpha_k)?,
+        };
+        let offset_applied = offset.min(gross);
+        Ok(EcoLoadBreakdown {
+            gross,
+            offset_requested: offset,
+            offset_applied,
+            net: gross - offset_applied,
+        })
+    }
+
+    /// Validate references and return the normalized eco-load weights.
+    pub(crate) fn load_weights(
+        &self,
+        alpha_m: f64,
+        alpha_k: f64,
+    ) -> Result<(f64, f64), SafetyError> {
+        self.check_references()?;
+        let valid = |a: f64| a.is_finite() && a >= 0.0;
+        let total = alpha_m + alpha_k;
+        if !(valid(alpha_m) && valid(alpha_k) && total > 0.0) {
+            return Err(SafetyError::InvalidLoadWeights { alpha_m, alpha_k });
+        }
+        Ok((alpha_m / total, alpha_k / total))
+    }
+
+    /// Compute DW flux density for the corridor from the CEIM mass of each node.
+    /// Phi_dw = sum_i M_i / (A * T_i), in kg m^-2 s^-1.
+    pub fn dw_flux_density(&self, nodes: &[NodeState]) -> Result<f64, SafetyError> {
+        if self.corridor_area_m2 <= 0.0 {
+            return Err(SafetyError::InvalidCorridorArea {
+                area_m2: self.corridor_area_m2,
+            });
+        }
+        let mut phi = 0.0;
+        for n in nodes {
+            if n.row.period_s <= 0.0 {
+                return Err(SafetyError::InvalidPeriod {
+                    machine_id: n.row.machine_id.clone(),
+                    period_s: n.row.period_s,
+                });
+            }
+            phi += n.mass_kg / (self.corridor_area_m2 * n.row.period_s);
+        }
+        Ok(phi)
+    }
+
+    /// Same as `update_node_duty`, but derives Phi_dw from the corridor nodes.
+    pub fn update_node_duty_from_nodes(
+        &self,
+        node: &mut NodeState,
+        eco_band: EcoBand,
+        nodes: &[NodeState],
+    ) -> Result<UpdateReport, SafetyError> {
+        let phi_dw = self.dw_flux_density(nodes)?;
+        self.update_node_duty(node, eco_band, phi_dw)
+    }
+
+    /// Update a single node's duty-cycle using Equation 5, after all checks.
+    pub fn update_node_duty(
+        &self,
+        node: &mut NodeState,
+        eco_band: EcoBand,
+        phi_dw: f64,
+    ) -> Result<UpdateReport, SafetyError> {
+        self.update_node_duty_in_wind(node, eco_band, phi_dw, None)
+    }
+
+    /// `update_node_duty` with this step's wind, which `geo_weighting`, if
+    /// set, turns into the node's effective geo weight.
+    pub fn update_node_duty_in_wind(
+        &self,
+        node: &mut NodeState,
+        eco_band: EcoBand,
+        phi_dw: f64,
+        wind: Option<Wind>,
+    ) -> Result<UpdateReport, SafetyError> {
+        let result = self.apply_duty_law(node, eco_band, phi_dw, wind);
+        match &result {
+            Ok(report) => self.metrics.record_update(node, report),
+            Err(e) => self.metrics.record_error(e),
+        }
+        result
+    }
+
+    /// Every check `update_node_duty` runs, plus the DW ceiling for the
+    /// node's pollutant, without stopping at the first failure or touching
+    /// `node`. Nothing is sent to the metrics recorder.
+    pub fn dry_run_node(&self, node: &NodeState, eco_band: EcoBand, phi_dw: f64) -> NodeAssessment {
+        if !node.mode.is_active() {
+            let violations: Vec<_> = [self.check_membership(node), check_node_finite(node)]
+                .into_iter()
+                .filter_map(Result::err)
+                .collect();
+            return NodeAssessment {
+                machine_id: node.row.machine_id.clone(),
+                projected: violations.is_empty().then(|| pinned_report(node, eco_band)),
+                violations,
+            };
+        }
+        let mut violations = self.envelope.envelope_violations(node);
+        violations.extend(self.host_budget.host_budget_violations(node));
+        let mut computable = true;
+        for e in [
+            self.check_membership(node),
+            self.check_references(),
+            check_node_finite(node),
+            check_duty_inputs_finite(node),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        {
+            violations.push(e);
+            computable = false;
+        }
+        violations.extend(self.check_node_dw_ceiling(node, phi_dw).err());
+        let projected = computable.then(|| {
+            let (report, held) = self.project_duty(node, eco_band, phi_dw, None);
+            violations.extend(held);
+            report
+        });
+        NodeAssessment {
+            machine_id: node.row.machine_id.clone(),
+            violations,
+            projected,
+        }
+    }
+
+    /// [`dry_run_node`](Self::dry_run_node) over every node.
+    pub fn dry_run(
+        &self,
+        nodes: &[NodeState],
+        eco_band: EcoBand,
+        phi_dw: f64,
+    ) -> CorridorAssessment {
+        CorridorAssessment {
+            nodes: nodes
+                .iter()
+                .map(|n| self.dry_run_node(n, eco_band, phi_dw))
+                .collect(),
+        }
+    }
+
+    fn check_membership(&self, node: &NodeState) -> Result<(), SafetyError> {
+        match &self.corridor {
+            Some(m) if !m.contains(&node.row.machine_id) => Err(SafetyError::OutsideCorridor {
+                machine_id: node.row.machine_id.clone(),
+                corridor: m.corridor.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// DW ceiling for the node's pollutant; unrecognised labels fall back to
+    /// the corridor-wide ceiling.
+    fn check_node_dw_ceiling(&self, node: &NodeState, phi_dw: f64) -> Result<(), SafetyError> {
+        match node.row.pollutant_kind() {
+            Ok(pollutant) => self.dw_ceiling.check_dw_ceiling_for(pollutant, phi_dw),
+            Err(_) => self.dw_ceiling.check_dw_ceiling(phi_dw),
+        }
+    }
+
+    fn apply_duty_law(
+        &self,
+        node: &mut NodeState,
+        eco_band: EcoBand,
+        phi_dw: f64,
+        wind: Option<Wind>,
+    ) -> Result<UpdateReport, SafetyError> {
+        // Whatever the policy: another corridor's machine is not ours to hold.
+        self.check_membership(node)?;
+        if !node.mode.is_active() {
+            // Telemetry is still checked; actuation checks do not apply.
+            check_node_finite(node)?;
+            let report = pinned_report(node, eco_band);
+            node.duty_cycle = report.duty_after;
+            return Ok(report);
+        }
+        let (report, held) = if self.violation_policy == ViolationPolicy::Error {
+            // Envelope and host-budget checks first.
+            self.envelope.check_envelope(node)?;
+            self.host_budget.check_host_budget(node)?;
+            self.check_references()?;
+            check_node_finite(node)?;
+            check_duty_inputs_finite(node)?;
+            self.project_duty(node, eco_band, phi_dw, wind)
+        } else {
+            let mut violations = self.envelope.envelope_violations(node);
+            violations.extend(self.host_budget.host_budget_violations(node));
+            self.check_references()?;
+            check_node_finite(node)?;
+            check_duty_inputs_finite(node)?;
+            violations.extend(self.check_node_dw_ceiling(node, phi_dw).err());
+            if violations.is_empty() {
+                self.project_duty(node, eco_band, phi_dw, wind)
+            } else {
+                for e in &violations {
+                    self.metrics.record_error(e);
+                }
+                self.handle_violations(node, eco_band, &violations)
+            }
+        };
+        if let Some(e) = &held {
+            self.metrics.record_error(e);
+        }
+        node.duty_cycle = report.duty_after;
+        Ok(report)
+    }
+
+    /// Clamp or skip a failing node according to the (non-`Error`) policy.
+    fn handle_violations(
+        &self,
+        node: &NodeState,
+        eco_band: EcoBand,
+        violations: &[SafetyError],
+    ) -> (UpdateReport, Option<SafetyError>) {
+        let handling = |action| {
+            Some(ViolationHandling {
+                action,
+                violations: violations.iter().map(ToString::to_string).collect(),
+            })
+        };
+        match self.violation_policy {
+            ViolationPolicy::ClampToSafe { safe_duty } => {
+                let target = safe_duty.or(self.envelope.safe_duty()).unwrap_or(0.0);
+                let (mut report, held) =
+                    self.settle_duty(node, eco_band, DutyContributions::default(), target);
+                if held.is_none() {
+                    report.violation = handling(ViolationAction::ClampedToSafe { target });
+                }
+                (report, held)
+            }
+            _ => (
+                UpdateReport {
+                    machine_id: node.row.machine_id.clone(),
+                    eco_band,
+                    duty_before: node.duty_cycle,
+                    duty_after: node.duty_cycle,
+                    contributions: DutyContributions::default(),
+                    clipped: false,
+                    slew_limited: false,
+                    bee_rights_clamped: None,
+                    violation: handling(ViolationAction::Skipped),
+                    trace_id: node.row.trace_id,
+                    gain_source: None,
+                    schedule_cap: None,
+                    geo_weight: None,
+                    gate_cap: None,
+                },
+                None,
+            ),
+        }
+    }
+
+    /// Equation 5 for `node`, assuming valid references and a finite node.
+    /// See `settle_duty` for the second value.
+    fn project_duty(
+        &self,
+        node: &NodeState,
+        eco_band: EcoBand,
+        phi_dw: f64,
+        wind: Option<Wind>,
+    ) -> (UpdateReport, Option<SafetyError>) {
+        // Compute normalized components.
+        let m_norm = node.mass_kg / self.m_ref_kg;
+        let k_norm = node.karma_bytes / self.k_ref_nb;
+        let geo_weight = match &self.geo_weighting {
+            Some(dynamic) => dynamic.weighting(node, wind),
+            None => GeoWeighting {
+                static_weight: node.geo_weight,
+                effective: node.geo_weight,
+            },
+        };
+        let w = geo_weight.effective;
+        let band_gain = self.eco_band.band_gain(eco_band);
+        let p_frac = self.host_budget.power_fraction(node);
+        // Unrecognised pollutant labels fall back to the corridor-wide ceiling.
+        let dw_violation = match node.row.pollutant_kind() {
+            Ok(pollutant) => self.dw_ceiling.dw_violation_for(pollutant, phi_dw),
+            Err(_) => self.dw_ceiling.dw_violation(phi_dw),
+        };
+
+        let (g, gain_source) = self.effective_gains(&node.row.machine_id, eco_band);
+
+        let contributions = DutyContributions {
+            mass: g.eta_m * m_norm,
+            karma: g.eta_k * k_norm,
+            geo_weight: g.eta_w * w,
+            band_gain: g.eta_b * band_gain,
+            power: -g.eta_p * p_frac,
+            dw_violation: -g.eta_dw * dw_violation,
+            noise: -g.eta_noise * exposure_fraction(node.noise_db, self.noise_ref_db),
+            emf: -g.eta_emf * exposure_fraction(node.emf_vpm, self.emf_ref_vpm),
+        };
+        let u_raw = node.duty_cycle
+            + contributions.mass
+            + contributions.karma
+            + contributions.geo_weight
+            + contributions.band_gain
+            + contributions.power
+            + contributions.dw_violation
+            + contributions.noise
+            + contributions.emf;
+        let (mut report, held) = self.settle_duty(node, eco_band, contributions, u_raw);
+        report.gain_source = Some(gain_source);
+        report.geo_weight = Some(geo_weight);
+        (report, held)
+    }
+
+    /// Slew-limit, project, bee-guard and schedule-cap `u_raw` into the
+    /// report for `node`.
+    ///
+    /// A NaN or infinite `u_raw`, or a non-finite duty from the bee guard,
+    /// keeps the node's previous duty instead; the report says so and the
+    /// `NonFiniteValue` error is returned alongside it.
+    fn settle_duty(
+        &self,
+        node: &NodeState,
+        eco_band: EcoBand,
+        contributions: DutyContributions,
+        u_raw: f64,
+    ) -> (UpdateReport, Option<SafetyError>) {
+        let u_old = node.duty_cycle;
+        if !u_raw.is_finite() {
+            return hold_duty(node, eco_band, contributions, "duty_raw", u_raw);
+        }
+        // Slew limit, then project onto [0,1].
+        let u_limited = match &self.slew_limit {
+            Some(limit) => limit.apply(u_old, u_raw),
+            None => u_raw,
+        };
+        let u_proj = u_limited.clamp(0.0, 1.0);
+
+        // Bee rights override everything else.
+        let bee_rights_clamped =
+            self.bee_guard
+                .check_bee_rights(node, u_proj)
+                .map(|safe| BeeRightsClamped {
+                    proposed: u_proj,
+                    applied: safe.clamp(0.0, 1.0),
+                });
+        let u_guarded = bee_rights_clamped.map_or(u_proj, |c| c.applied);
+        if !u_guarded.is_finite() {
+            return hold_duty(node, eco_band, contributions, "duty_cycle", u_guarded);
+        }
+
+        // Then the operating agent's gate.
+        let gate_cap = self
+            .agent_gate
+            .as_ref()
+            .map(|gate| gate.cap(&node.row.machine_id, u_guarded));
+        let u_gated = gate_cap.map_or(u_guarded, |c| c.applied);
+
+        // Schedule caps are applied last.
+        let schedule_cap = self
+            .duty_schedule
+            .as_ref()
+            .and_then(|s| s.binding_window(&node.row.machine_id))
+            .map(|window| ScheduleCap {
+                window: window.name.clone(),
+                max_duty: window.cap(),
+                sensing_only: window.sensing_only,
+                proposed: u_gated,
+                applied: u_gated.min(window.cap()),
+            });
+        let u_new = schedule_cap.as_ref().map_or(u_gated, |c| c.applied);
+
+        let report = UpdateReport {
+            machine_id: node.row.machine_id.clone(),
+            eco_band,
+            duty_before: u_old,
+            duty_after: u_new,
+            contributions,
+            clipped: u_proj != u_limited,
+            slew_limited: u_proj != u_raw.clamp(0.0, 1.0),
+            bee_rights_clamped,
+            violation: None,
+            trace_id: node.row.trace_id,
+            gain_source: None,
+            schedule_cap,
+            geo_weight: None,
+            gate_cap,
+        };
+        (report, None)
+    }
+}
+
+/// Report for a node whose mode pins its duty to 0; the law did not run.
+fn pinned_report(node: &NodeState, eco_band: EcoBand) -> UpdateReport {
+    UpdateReport {
+        machine_id: node.row.machine_id.clone(),
+        eco_band,
+        duty_before: node.duty_cycle,
+        duty_after: 0.0,
+        contributions: DutyContributions::default(),
+        clipped: false,
+        slew_limited: false,
+        bee_rights_clamped: None,
+        violation: None,
+        trace_id: node.row.trace_id,
+        gain_source: None,
+        schedule_cap: None,
+        geo_weight: None,
+        gate_cap: None,
+    }
+}
+
+/// Report keeping `node`'s duty after the law computed a non-finite `field`.
+fn hold_duty(
+    node: &NodeState,
+    eco_band: EcoBand,
+    contributions: DutyContributions,
+    field: &'static str,
+    value: f64,
+) -> (UpdateReport, Option<SafetyError>) {
+    let error = SafetyError::NonFiniteValue {
+        machine_id: node.row.machine_id.clone(),
+        field,
+        value,
+    };
+    let report = UpdateReport {
+        machine_id: node.row.machine_id.clone(),
+        eco_band,
+        duty_before: node.duty_cycle,
+        duty_after: node.duty_cycle,
+        contributions,
+        clipped: false,
+        slew_limited: false,
+        bee_rights_clamped: None,
+        violation: Some(ViolationHandling {
+            action: ViolationAction::HeldPrevious,
+            violations: vec![error.to_string()],
+        }),
+        trace_id: node.row.trace_id,
+        gain_source: None,
+        schedule_cap: None,
+        geo_weight: None,
+        gate_cap: None,
+    };
+    (report, Some(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{P_STANDARD_PA, R_GAS};
+
+    fn phoenix_row(
+        machine_id: &str,
+        cin: f64,
+        cout: f64,
+        airflow_m3_per_s: f64,
+        period_s: f64,
+    ) -> CorridorRow {
+        CorridorRow {
+            machine_id: machine_id.to_string(),
+            r#type: "UrbanNanoswarmCanopy".to_string(),
+            location: "Phoenix-Intersection-A".to_string(),
+            pollutant: "PM2.5".to_string(),
+            cin,
+            cout,
+            unit: "ugm3".to_string(),
+            airflow_m3_per_s,
+            period_s,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.92,
+            trace_id: None,
+            shard_version: None,
+        }
+    }
+
+    pub(crate) fn phoenix_nodes() -> Vec<NodeState> {
+        let rows = [
+            phoenix_row("CYB-AIR-CANOPY-01", 40.0, 28.0, 3.0, 3600.0),
+            phoenix_row("CYB-AIR-SCHOOL-05", 30.0, 18.0, 1.0, 2700.0),
+        ];
+        rows.into_iter()
+            .map(|row| {
+                let mass_kg = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+                let karma_bytes = compute_karma_bytes(&row, mass_kg);
+                NodeState {
+                    row,
+                    mass_kg,
+                    karma_bytes,
+                    duty_cycle: 0.5,
+                    power_w: 50.0,
+                    geo_weight: 0.8,
+                    noise_db: None,
+                    emf_vpm: None,
+                    suspicion: None,
+                    mode: OperationalMode::Active,
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn phoenix_controller() -> CorridorController<
+        RectSafetyEnvelope<ConstAltitude>,
+        SimpleHostBudget,
+        ThresholdEcoBand,
+        SimpleDwCeiling,
+    > {
+        CorridorController {
+            envelope: RectSafetyEnvelope {
+                u_min: 0.0,
+                u_max: 1.0,
+                z_min_m: 5.0,
+                z_max_m: 600.0,
+                ecoimpact_min: 0.7,
+                ecoimpact_max: 1.0,
+                noise_max_db: None,
+                emf_max_vpm: None,
+                altitude: ConstAltitude(331.0),
+            },
+            host_budget: SimpleHostBudget {
+                p_max_w: 150.0,
+                e_step_max_j: 1.0e5,
+                step_dt_s: 300.0,
+            },
+            eco_band: ThresholdEcoBand {
+                theta_green_amber: 0.5,
+                theta_amber_red: 1.0,
+                gain_green: 0.0,
+                gain_amber: 0.2,
+                gain_red: 0.5,
+            },
+            dw_ceiling: SimpleDwCeiling { phi_dw_max: 1.0e-6 },
+            corridor_area_m2: 10.0,
+            m_ref_kg: 1.0e-6,
+            k_ref_nb: 1.0e10,
+            eta_m: 0.1,
+            eta_k: 0.1,
+            eta_w: 0.2,
+            eta_b: 0.2,
+            eta_p: 0.05,
+            eta_dw: 0.1,
+            eta_noise: 0.0,
+            eta_emf: 0.0,
+            noise_ref_db: 0.0,
+            emf_ref_vpm: 0.0,
+            gain_schedule: GainSchedule::default(),
+            duty_schedule: None,
+            geo_weighting: None,
+            airflow: None,
+            agent_gate: None,
+            corridor: None,
+            eco_load_mode: EcoLoadMode::Unweighted,
+            slew_limit: None,
+            violation_policy: ViolationPolicy::Error,
+            stability: None,
+            bee_guard: NoBeeGuard,
+            metrics: NoMetrics,
+        }
+    }
+
+    /// Test guard: nodes within `min_distance_m` of a hive are capped.
+    struct HiveGuard {
+        hive_distance_m: fn(&str) -> f64,
+        min_distance_m: f64,
+        max_duty_near_hive: f64,
+    }
+
+    impl BeeGuard for HiveGuard {
+        fn check_bee_rights(&self, node: &NodeState, u_new: f64) -> Option<f64> {
+            let near = (self.hive_distance_m)(&node.row.location) < self.min_distance_m;
+            (near && u_new > self.max_duty_near_hive).then_some(self.max_duty_near_hive)
+        }
+    }
+
+    fn apiary_distance_m(loc: &str) -> f64 {
+        if loc == "Phoenix-Apiary-1" {
+            20.0
+        } else {
+            500.0
+        }
+    }
+
+    #[test]
+    fn bee_guard_clamps_node_near_hive() {
+        let mut controller = phoenix_controller();
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        controller.eta_w = 0.0;
+        controller.eta_p = 0.0;
+        controller.eta_dw = 0.0;
+        controller.eta_b = 1.0;
+        let controller = controller.with_bee_guard(HiveGuard {
+            hive_distance_m: apiary_distance_m,
+            min_distance_m: 50.0,
+            max_duty_near_hive: 0.3,
+        });
+        let mut nodes = phoenix_nodes();
+        nodes[0].row.location = "Phoenix-Apiary-1".to_string();
+
+        // Red band gain 0.5 pushes 0.3 -> 0.8 for both nodes.
+        for n in nodes.iter_mut() {
+            n.duty_cycle = 0.3;
+        }
+        let near = controller
+            .update_node_duty(&mut nodes[0], EcoBand::Red, 0.0)
+            .unwrap();
+        let far = controller
+            .update_node_duty(&mut nodes[1], EcoBand::Red, 0.0)
+            .unwrap();
+
+        assert!((far.duty_after - 0.8).abs() < 1e-12);
+        assert!(far.bee_rights_clamped.is_none());
+
+        let clamp = near.bee_rights_clamped.expect("near-hive node clamped");
+        assert!((clamp.proposed - 0.8).abs() < 1e-12);
+        assert_eq!(clamp.applied, 0.3);
+        assert_eq!(nodes[0].duty_cycle, 0.3);
+        assert_eq!(near.duty_after, 0.3);
+    }
+
+    #[test]
+    fn dry_run_reports_every_violation_without_mutating() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        nodes[1].row.ecoimpact_score = 0.1;
+        nodes[1].power_w = 200.0;
+
+        let bad = controller.dry_run_node(&nodes[1], EcoBand::Amber, 2.0e-6);
+        let kinds: Vec<_> = bad.violations.iter().map(SafetyError::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                "envelope_violation",
+                "host_budget_exceeded",
+                "dw_ceiling_exceeded"
+            ]
+        );
+        assert!(!bad.is_clean());
+        // The law is still computable, so the would-be duty is reported.
+        let projected = bad.projected.unwrap();
+        assert_ne!(projected.duty_after, 0.5);
+        assert_eq!(nodes[1].duty_cycle, 0.5);
+
+        // A clean node projects exactly what the real update does.
+        let clean = controller.dry_run_node(&nodes[0], EcoBand::Amber, 5.0e-7);
+        assert!(clean.is_clean());
+        let report = controller
+            .update_node_duty(&mut nodes[0], EcoBand::Amber, 5.0e-7)
+            .unwrap();
+        assert_eq!(clean.projected, Some(report));
+    }
+
+    #[test]
+    fn corridor_dry_run_aggregates_and_flags_binding_limits() {
+        let mut controller = phoenix_controller();
+        controller.slew_limit = Some(SlewLimit::symmetric(0.01));
+        controller.m_ref_kg = 0.0;
+        let mut nodes = phoenix_nodes();
+        nodes[0].row.ecoimpact_score = 0.1;
+
+        let assessment = controller.dry_run(&nodes, EcoBand::Red, 5.0e-7);
+        assert!(!assessment.is_clean());
+        // Invalid references are reported per node and leave nothing to project.
+        assert_eq!(assessment.counts_by_kind()["invalid_reference"], 2);
+        assert_eq!(assessment.counts_by_kind()["envelope_violation"], 1);
+        assert!(assessment.nodes.iter().all(|n| n.projected.is_none()));
+
+        controller.m_ref_kg = 1.0e-6;
+        let assessment = controller.dry_run(&nodes, EcoBand::Red, 5.0e-7);
+        assert_eq!(assessment.violations().count(), 1);
+        assert_eq!(assessment.slew_limited().count(), 2);
+        let projected = assessment.nodes[1].projected.as_ref().unwrap();
+        assert!((projected.duty_after - 0.51).abs() < 1e-12);
+        assert!(!projected.clipped);
+    }
+
+    #[test]
+    fn slew_limit_caps_large_positive_gradient() {
+        let mut controller = phoenix_controller();
+        let mut node = phoenix_nodes().remove(0);
+        node.duty_cycle = 0.0;
+
+        // Unlimited: the mass term alone saturates the duty cycle.
+        let mut free = node.clone();
+        let out = controller
+            .update_node_duty(&mut free, EcoBand::Red, 0.0)
+            .unwrap();
+        assert_eq!(free.duty_cycle, 1.0);
+        assert!(!out.slew_limited());
+
+        controller.slew_limit = Some(SlewLimit::symmetric(0.1));
+        let out = controller
+            .update_node_duty(&mut node, EcoBand::Red, 0.0)
+            .unwrap();
+        assert!((node.duty_cycle - 0.1).abs() < 1e-12);
+        assert!(out.slew_limited());
+        assert_eq!(out.duty_before, 0.0);
+    }
+
+    #[test]
+    fn update_report_contributions_sum_to_unclamped_delta() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        nodes[1].duty_cycle = 0.2;
+
+        let report = controller
+            .update_node_duty(&mut nodes[1], EcoBand::Amber, 2.0e-6)
+            .unwrap();
+        let c = &report.contributions;
+        assert!(c.power < 0.0);
+        assert!(c.dw_violation < 0.0);
+
+        // Reconstruct the unclamped target from the raw law.
+        let expected_delta = 0.1 * (3.24e-5 / 1.0e-6)
+            + 0.1 * (nodes[1].karma_bytes / 1.0e10)
+            + 0.2 * 0.8
+            + 0.2 * 0.2
+            - 0.05 * (50.0 / 150.0)
+            - 0.1 * 1.0;
+        assert!((report.unclamped_delta() - expected_delta).abs() < 1e-12);
+        assert!(report.clipped);
+        assert_eq!(report.duty_after, 1.0);
+        assert_eq!(report.eco_band, EcoBand::Amber);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"contributions\""));
+    }
+
+    #[test]
+    fn update_report_matches_applied_duty_when_unclipped() {
+        let mut controller = phoenix_controller();
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        let mut node = phoenix_nodes().remove(0);
+        node.duty_cycle = 0.3;
+
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+        assert!(!report.clipped);
+        assert!((report.duty_after - report.duty_before - report.unclamped_delta()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn slew_limit_respects_separate_down_rate() {
+        let limit = SlewLimit {
+            max_up: 0.05,
+            max_down: 0.2,
+        };
+        assert!((limit.apply(0.5, 0.0) - 0.3).abs() < 1e-12);
+        assert!((limit.apply(0.5, 1.0) - 0.55).abs() < 1e-12);
+        assert_eq!(limit.apply(0.5, 0.52), 0.52);
+    }
+
+    #[test]
+    fn compute_mass_kg_propagates_unknown_unit() {
+        let mut row = phoenix_row("CYB-AIR-CANOPY-01", 40.0, 28.0, 3.0, 3600.0);
+        assert!(compute_mass_kg(&row, Pollutant::PM25, 310.0).is_ok());
+        row.unit = "grains/ft3".to_string();
+        assert_eq!(
+            compute_mass_kg(&row, Pollutant::PM25, 310.0),
+            Err(UnitError::Unknown("grains/ft3".to_string()))
+        );
+    }
+
+    #[test]
+    fn compute_mass_kg_converts_ozone_ppb() {
+        let mut row = phoenix_row("CYB-AIR-O3-01", 50.0, 30.0, 1.0, 1.0);
+        row.pollutant = "O3".to_string();
+        row.unit = "ppb".to_string();
+        let m = compute_mass_kg(&row, row.pollutant_kind().unwrap(), 298.15).unwrap();
+        // 20 ppb O3 at 25 C ~ 39.2 ug/m3, over 1 m3.
+        let expected = 20.0 * P_STANDARD_PA * 0.048 / (R_GAS * 298.15) * 1e-9;
+        assert!((m - expected).abs() < 1e-18);
+        assert!((m - 39.25e-9).abs() < 0.1e-9);
+    }
+
+    #[test]
+    fn compute_mass_kg_pm25_ignores_molar_mass() {
+        let row = phoenix_row("CYB-AIR-CANOPY-01", 40.0, 28.0, 3.0, 3600.0);
+        // Temperature is irrelevant for mass concentrations.
+        let a = compute_mass_kg(&row, Pollutant::PM25, 310.0).unwrap();
+        let b = compute_mass_kg(&row, Pollutant::PM25, 0.0).unwrap();
+        assert_eq!(a, b);
+        assert!((a - 1.296e-4).abs() < 1e-15);
+
+        let mut ppb_row = row.clone();
+        ppb_row.unit = "ppb".to_string();
+        assert!(matches!(
+            compute_mass_kg(&ppb_row, Pollutant::PM25, 310.0),
+            Err(UnitError::NoMolarMass { .. })
+        ));
+    }
+
+    #[test]
+    fn dw_flux_density_matches_hand_computation() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+
+        // Canopy: 12 ug/m3 * 1e-9 * 3 m3/s * 3600 s = 1.296e-4 kg over 10 m2 * 3600 s.
+        // School: 12 ug/m3 * 1e-9 * 1 m3/s * 2700 s = 3.24e-5 kg over 10 m2 * 2700 s.
+        let expected = 1.296e-4 / (10.0 * 3600.0) + 3.24e-5 / (10.0 * 2700.0);
+        let phi = controller.dw_flux_density(&nodes).unwrap();
+        assert!((phi - expected).abs() < 1e-15);
+        assert!((phi - 4.8e-9).abs() < 1e-15);
+    }
+
+    #[test]
+    fn dw_flux_density_rejects_degenerate_geometry() {
+        let mut controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+
+        controller.corridor_area_m2 = 0.0;
+        assert_eq!(
+            controller.dw_flux_density(&nodes),
+            Err(SafetyError::InvalidCorridorArea { area_m2: 0.0 })
+        );
+
+        controller.corridor_area_m2 = 10.0;
+        nodes[1].row.period_s = -1.0;
+        assert_eq!(
+            controller.dw_flux_density(&nodes),
+            Err(SafetyError::InvalidPeriod {
+                machine_id: "CYB-AIR-SCHOOL-05".to_string(),
+                period_s: -1.0,
+            })
+        );
+    }
+
+    #[test]
+    fn envelope_violation_names_node_and_field() {
+        let controller = phoenix_controller();
+        let mut node = phoenix_nodes().remove(1);
+        node.row.ecoimpact_score = 0.4;
+
+        let err = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            SafetyError::EnvelopeViolation {
+                machine_id: "CYB-AIR-SCHOOL-05".to_string(),
+                field: EnvelopeField::EcoimpactScore,
+                value: 0.4,
+                min: 0.7,
+                max: 1.0,
+            }
+        );
+        assert!(err.to_string().contains("CYB-AIR-SCHOOL-05"));
+        assert!(err.to_string().contains("ecoimpact_score=0.4"));
+    }
+
+    #[test]
+    fn exposure_ceilings_reject_loud_or_hot_nodes() {
+        let mut controller = phoenix_controller();
+        controller.envelope.noise_max_db = Some(65.0);
+        controller.envelope.emf_max_vpm = Some(6.0);
+        let mut nodes = phoenix_nodes();
+
+        // Unmeasured nodes pass whatever the ceilings.
+        assert!(controller
+            .update_node_duty(&mut nodes[0].clone(), EcoBand::Green, 0.0)
+            .is_ok());
+        nodes[0].noise_db = Some(64.0);
+        nodes[0].emf_vpm = Some(6.0);
+        assert!(controller
+            .update_node_duty(&mut nodes[0].clone(), EcoBand::Green, 0.0)
+            .is_ok());
+
+        nodes[1].noise_db = Some(72.0);
+        let err = controller
+            .update_node_duty(&mut nodes[1], EcoBand::Green, 0.0)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            SafetyError::EnvelopeViolation {
+                machine_id: "CYB-AIR-SCHOOL-05".to_string(),
+                field: EnvelopeField::NoiseDb,
+                value: 72.0,
+                min: f64::NEG_INFINITY,
+                max: 65.0,
+            }
+        );
+        assert!(err.to_string().contains("noise_db=72"));
+
+        nodes[1].emf_vpm = Some(9.5);
+        let fields: Vec<_> = controller
+            .dry_run_node(&nodes[1], EcoBand::Green, 0.0)
+            .violations
+            .into_iter()
+            .map(|e| match e {
+                SafetyError::EnvelopeViolation { field, .. } => field,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(fields, [EnvelopeField::NoiseDb, EnvelopeField::EmfVpm]);
+
+        nodes[1].noise_db = Some(f64::NAN);
+        nodes[1].emf_vpm = None;
+        assert!(matches!(
+            controller.update_node_duty(&mut nodes[1], EcoBand::Green, 0.0),
+            Err(SafetyError::NonFiniteNode {
+                field: "noise_db",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn exposure_penalties_throttle_louder_nodes() {
+        let mut baseline = phoenix_controller();
+        baseline.eta_m = 0.0;
+        baseline.eta_k = 0.0;
+        baseline.eta_w = 0.0;
+        let mut controller = baseline.clone();
+        controller.eta_noise = 0.1;
+        controller.noise_ref_db = 60.0;
+        controller.eta_emf = 0.05;
+        controller.emf_ref_vpm = 3.0;
+        let mut quiet = phoenix_nodes().remove(1);
+        quiet.duty_cycle = 0.5;
+        let mut loud = quiet.clone();
+        loud.noise_db = Some(75.0);
+        loud.emf_vpm = Some(6.0);
+
+        // Without readings the new gains change nothing.
+        let before = baseline
+            .update_node_duty(&mut quiet.clone(), EcoBand::Amber, 0.0)
+            .unwrap();
+        let quiet_report = controller
+            .update_node_duty(&mut quiet, EcoBand::Amber, 0.0)
+            .unwrap();
+        assert_eq!(quiet_report, before);
+
+        let loud_report = controller
+            .update_node_duty(&mut loud, EcoBand::Amber, 0.0)
+            .unwrap();
+        let c = loud_report.contributions;
+        // 0.1 * 75 / 60 and 0.05 * 6 / 3.
+        assert!((c.noise + 0.125).abs() < 1e-12);
+        assert!((c.emf + 0.1).abs() < 1e-12);
+        assert!(
+            (quiet_report.unclamped_delta() - loud_report.unclamped_delta() - 0.225).abs() < 1e-12
+        );
+        assert!(!loud_report.clipped && !quiet_report.clipped);
+        assert!(loud.duty_cycle < quiet.duty_cycle);
+    }
+
+    #[test]
+    fn unknown_location_violates_altitude_envelope() {
+        let controller = phoenix_controller();
+        let envelope = RectSafetyEnvelope {
+            u_min: 0.0,
+            u_max: 1.0,
+            z_min_m: 5.0,
+            z_max_m: 600.0,
+            ecoimpact_min: 0.7,
+            ecoimpact_max: 1.0,
+            noise_max_db: None,
+            emf_max_vpm: None,
+            altitude: MapAltitude::new().with_site("Phoenix-Intersection-A", 331.0),
+        };
+        let controller = CorridorController {
+            envelope,
+            host_budget: controller.host_budget,
+            eco_band: controller.eco_band,
+            dw_ceiling: controller.dw_ceiling,
+            corridor_area_m2: controller.corridor_area_m2,
+            m_ref_kg: controller.m_ref_kg,
+            k_ref_nb: controller.k_ref_nb,
+            eta_m: controller.eta_m,
+            eta_k: controller.eta_k,
+            eta_w: controller.eta_w,
+            eta_b: controller.eta_b,
+            eta_p: controller.eta_p,
+            eta_dw: controller.eta_dw,
+            eta_noise: controller.eta_noise,
+            eta_emf: controller.eta_emf,
+            noise_ref_db: controller.noise_ref_db,
+            emf_ref_vpm: controller.emf_ref_vpm,
+            gain_schedule: controller.gain_schedule,
+            duty_schedule: controller.duty_schedule,
+            geo_weighting: controller.geo_weighting,
+            airflow: controller.airflow,
+            agent_gate: controller.agent_gate,
+            corridor: controller.corridor,
+            eco_load_mode: controller.eco_load_mode,
+            slew_limit: controller.slew_limit,
+            violation_policy: controller.violation_policy,
+            stability: controller.stability,
+            bee_guard: controller.bee_guard,
+            metrics: controller.metrics,
+        };
+        let mut nodes = phoenix_nodes();
+        assert!(controller
+            .update_node_duty(&mut nodes[0], EcoBand::Green, 0.0)
+            .is_ok());
+
+        nodes[1].row.location = "Unmapped-Rooftop".to_string();
+        let duty = nodes[1].duty_cycle;
+        match controller.update_node_duty(&mut nodes[1], EcoBand::Green, 0.0) {
+            Err(SafetyError::EnvelopeViolation {
+                machine_id,
+                field: EnvelopeField::Altitude,
+                value,
+                ..
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-SCHOOL-05");
+                assert!(value.is_nan());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert_eq!(nodes[1].duty_cycle, duty);
+    }
+
+    #[test]
+    fn unbounded_altitude_skips_the_lookup() {
+        let lookups = std::cell::Cell::new(0);
+        let mut envelope = RectSafetyEnvelope {
+            u_min: 0.0,
+            u_max: 1.0,
+            z_min_m: f64::NEG_INFINITY,
+            z_max_m: 600.0,
+            ecoimpact_min: 0.7,
+            ecoimpact_max: 1.0,
+            noise_max_db: None,
+            emf_max_vpm: None,
+            altitude: |_: &str| {
+                lookups.set(lookups.get() + 1);
+                None
+            },
+        };
+        let node = phoenix_nodes().remove(0);
+        assert_eq!(envelope.envelope_violations(&node).len(), 1);
+        assert_eq!(lookups.get(), 1);
+
+        envelope.z_max_m = f64::INFINITY;
+        assert!(envelope.check_envelope(&node).is_ok());
+        assert_eq!(lookups.get(), 1);
+    }
+
+    #[test]
+    fn clamp_to_safe_drives_power_overage_down() {
+        let mut node = phoenix_nodes().remove(0);
+        node.power_w = 200.0;
+        node.duty_cycle = 0.9;
+
+        // Error keeps today's semantics: refuse and leave the duty alone.
+        let mut controller = phoenix_controller();
+        let mut frozen = node.clone();
+        assert!(matches!(
+            controller.update_node_duty(&mut frozen, EcoBand::Green, 0.0),
+            Err(SafetyError::HostBudgetExceeded { .. })
+        ));
+        assert_eq!(frozen.duty_cycle, 0.9);
+
+        controller.violation_policy = ViolationPolicy::ClampToSafe {
+            safe_duty: Some(0.2),
+        };
+        controller.slew_limit = Some(SlewLimit::symmetric(0.25));
+        let mut duties = Vec::new();
+        for _ in 0..4 {
+            let report = controller
+                .update_node_duty(&mut node, EcoBand::Green, 0.0)
+                .unwrap();
+            let handling = report.violation.unwrap();
+            assert_eq!(
+                handling.action,
+                ViolationAction::ClampedToSafe { target: 0.2 }
+            );
+            assert!(handling.violations[0].contains("host budget exceeded"));
+            assert_eq!(report.contributions, DutyContributions::default());
+            duties.push(node.duty_cycle);
+        }
+        // Slew-limited steps of 0.25 down to the safe duty, then held there.
+        let expected = [0.65, 0.4, 0.2, 0.2];
+        assert!(
+            duties
+                .iter()
+                .zip(expected)
+                .all(|(u, e)| (u - e).abs() < 1e-12),
+            "{duties:?}"
+        );
+
+        // Without a configured duty the envelope's u_min is the target.
+        controller.violation_policy = ViolationPolicy::ClampToSafe { safe_duty: None };
+        controller.slew_limit = None;
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+        assert_eq!(report.duty_after, controller.envelope.u_min);
+        assert_eq!(node.duty_cycle, 0.0);
+    }
+
+    #[test]
+    fn skip_node_reports_every_failure_and_keeps_duty() {
+        let mut controller = phoenix_controller();
+        let mut node = phoenix_nodes().remove(1);
+        node.duty_cycle = 0.6;
+        node.row.ecoimpact_score = 0.4;
+        let phi_dw = 5.0e-6;
+
+        controller.violation_policy = ViolationPolicy::SkipNode;
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Red, phi_dw)
+            .unwrap();
+        assert_eq!(node.duty_cycle, 0.6);
+        assert_eq!(report.duty_after, report.duty_before);
+        let handling = report.violation.unwrap();
+        assert_eq!(handling.action, ViolationAction::Skipped);
+        assert_eq!(handling.violations.len(), 2);
+        assert!(handling.violations[0].contains("ecoimpact_score"));
+        assert!(handling.violations[1].contains("dw ceiling exceeded"));
+
+        // A DW breach alone is only penalized under Error, but handled here.
+        node.row.ecoimpact_score = 0.9;
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Red, phi_dw)
+            .unwrap();
+        assert_eq!(report.violation.unwrap().violations.len(), 1);
+        controller.violation_policy = ViolationPolicy::Error;
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Red, phi_dw)
+            .unwrap();
+        assert_eq!(report.violation, None);
+        assert!(report.contributions.dw_violation < 0.0);
+    }
+
+    #[test]
+    fn host_budget_errors_carry_power_and_energy() {
+        let controller = phoenix_controller();
+        let mut node = phoenix_nodes().remove(0);
+
+        node.power_w = 200.0;
+        match controller.host_budget.check_host_budget(&node) {
+            Err(SafetyError::HostBudgetExceeded {
+                machine_id,
+                power_w,
+                p_max_w,
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-CANOPY-01");
+                assert_eq!(power_w, 200.0);
+                assert_eq!(p_max_w, 150.0);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // 140 W * 300 s = 42 kJ, under the 100 kJ budget; shrink the budget.
+        node.power_w = 140.0;
+        let mut budget = controller.host_budget.clone();
+        budget.e_step_max_j = 4.0e4;
+        assert_eq!(
+            budget.check_host_budget(&node),
+            Err(SafetyError::EnergyBudgetExceeded {
+                machine_id: "CYB-AIR-CANOPY-01".to_string(),
+                energy_j: 42_000.0,
+                e_max_j: 4.0e4,
+            })
+        );
+    }
+
+    #[test]
+    fn per_pollutant_ceiling_applies_per_node() {
+        let ceiling = MapDwCeiling::new(1.0e-6)
+            .with_ceiling(Pollutant::PM25, 2.0e-6)
+            .with_ceiling(Pollutant::O3, 2.0e-7);
+        let phi_dw = 5.0e-7;
+        assert!(ceiling
+            .check_dw_ceiling_for(Pollutant::PM25, phi_dw)
+            .is_ok());
+        assert_eq!(
+            ceiling.check_dw_ceiling_for(Pollutant::O3, phi_dw),
+            Err(SafetyError::PollutantDwCeilingExceeded {
+                pollutant: Pollutant::O3,
+                phi_dw,
+                phi_dw_max: 2.0e-7,
+            })
+        );
+        // Unlisted pollutants use the default ceiling.
+        assert!(ceiling.check_dw_ceiling_for(Pollutant::NO2, phi_dw).is_ok());
+
+        let base = phoenix_controller();
+        let controller = CorridorController {
+            envelope: base.envelope,
+            host_budget: base.host_budget,
+            eco_band: base.eco_band,
+            dw_ceiling: ceiling,
+            corridor_area_m2: base.corridor_area_m2,
+            m_ref_kg: base.m_ref_kg,
+            k_ref_nb: base.k_ref_nb,
+            eta_m: base.eta_m,
+            eta_k: base.eta_k,
+            eta_w: base.eta_w,
+            eta_b: base.eta_b,
+            eta_p: base.eta_p,
+            eta_dw: base.eta_dw,
+            eta_noise: base.eta_noise,
+            eta_emf: base.eta_emf,
+            noise_ref_db: base.noise_ref_db,
+            emf_ref_vpm: base.emf_ref_vpm,
+            gain_schedule: base.gain_schedule,
+            duty_schedule: base.duty_schedule,
+            geo_weighting: base.geo_weighting,
+            airflow: base.airflow,
+            agent_gate: base.agent_gate,
+            corridor: base.corridor,
+            eco_load_mode: base.eco_load_mode,
+            slew_limit: base.slew_limit,
+            violation_policy: base.violation_policy,
+            stability: base.stability,
+            bee_guard: base.bee_guard,
+            metrics: base.metrics,
+        };
+        let mut nodes = phoenix_nodes();
+        nodes[1].row.pollutant = "O3".to_string();
+
+        let pm = controller
+            .update_node_duty(&mut nodes[0], EcoBand::Green, phi_dw)
+            .unwrap();
+        let o3 = controller
+            .update_node_duty(&mut nodes[1], EcoBand::Green, phi_dw)
+            .unwrap();
+        assert_eq!(pm.contributions.dw_violation, 0.0);
+        // (5e-7 - 2e-7) / 2e-7 = 1.5, times eta_dw = 0.1.
+        assert!((o3.contributions.dw_violation + 0.15).abs() < 1e-12);
+    }
+
+    /// FNV-1a over every duty, contribution and error the law produced for
+    /// a swept corridor, so any change in the numerics, down to the last
+    /// bit, changes the fingerprint.
+    fn duty_law_fingerprint<E, H, B, D, G, R>(
+        controller: &CorridorController<E, H, B, D, G, R>,
+    ) -> u64
+    where
+        E: SafetyEnvelope,
+        H: HostBudget,
+        B: EcoBandClassifier,
+        D: DwCeilingInvariant,
+        G: BeeGuard,
+        R: MetricsRecorder,
+    {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut feed = |bytes: &[u8]| {
+            for b in bytes {
+                hash = (hash ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        let pollutants = ["PM2.5", "O3", "ozone", "NOx", "pm-10", "Unobtainium"];
+        let template = phoenix_nodes().remove(0);
+        // A fixed LCG and exact scalings keep the inputs the same everywhere.
+        let mut state = 0x2123_u64;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        for i in 0..2_000 {
+            let mut node = template.clone();
+            node.row.pollutant = pollutants[i % pollutants.len()].to_string();
+            node.row.ecoimpact_score = 0.69 + 0.32 * next();
+            node.mass_kg = [1.0e-12, 1.0e-9, 1.0e-7, 1.0e-6, 1.0e-4][i % 5] * (0.1 + next());
+            node.karma_bytes = [1.0e4, 1.0e7, 1.0e10, 1.0e12][i % 4] * (0.1 + next());
+            node.duty_cycle = next();
+            node.power_w = 160.0 * next();
+            node.geo_weight = 2.0 * next();
+            node.noise_db = (i % 3 == 0).then(|| 40.0 + 40.0 * next());
+            node.emf_vpm = (i % 5 == 0).then(&mut next);
+            let band = [EcoBand::Green, EcoBand::Amber, EcoBand::Red][i % 3];
+            let phi_dw = [0.0, 5.0e-7, 1.0e-6, 1.5e-6, 4.0e-5][i % 5] * (0.5 + next());
+            match controller.update_node_duty(&mut node, band, phi_dw) {
+                Ok(report) => {
+                    let c = report.contributions;
+                    for v in [
+                        report.duty_after,
+                        c.mass,
+                        c.karma,
+                        c.geo_weight,
+                        c.band_gain,
+                        c.power,
+                        c.dw_violation,
+                        c.noise,
+                        c.emf,
+                    ] {
+                        feed(&v.to_bits().to_le_bytes());
+                    }
+                    feed(&[u8::from(report.clipped), u8::from(report.slew_limited)]);
+                    if let Some(handling) = &report.violation {
+                        feed(handling.violations.join("|").as_bytes());
+                    }
+                }
+                Err(e) => feed(e.to_string().as_bytes()),
+            }
+        }
+        hash
+    }
+
+    #[test]
+    fn duty_law_outputs_are_bit_stable() {
+        let mut controller = phoenix_controller();
+        controller.eta_noise = 0.05;
+        controller.noise_ref_db = 65.0;
+        controller.eta_emf = 0.05;
+        controller.emf_ref_vpm = 0.5;
+        assert_eq!(duty_law_fingerprint(&controller), 0x3362c5aad114be37);
+
+        controller.violation_policy = ViolationPolicy::ClampToSafe { safe_duty: None };
+        controller.slew_limit = Some(SlewLimit::symmetric(0.1));
+        assert_eq!(duty_law_fingerprint(&controller), 0xc6623767a982b8f8);
+
+        let base = phoenix_controller();
+        let per_pollutant = CorridorController {
+            envelope: base.envelope,
+            host_budget: base.host_budget,
+            eco_band: base.eco_band,
+            dw_ceiling: MapDwCeiling::new(1.0e-6)
+                .with_ceiling(Pollutant::PM25, 2.0e-6)
+                .with_ceiling(Pollutant::O3, 2.0e-7),
+            corridor_area_m2: base.corridor_area_m2,
+            m_ref_kg: base.m_ref_kg,
+            k_ref_nb: base.k_ref_nb,
+            eta_m: base.eta_m,
+            eta_k: base.eta_k,
+            eta_w: base.eta_w,
+            eta_b: base.eta_b,
+            eta_p: base.eta_p,
+            eta_dw: base.eta_dw,
+            eta_noise: base.eta_noise,
+            eta_emf: base.eta_emf,
+            noise_ref_db: base.noise_ref_db,
+            emf_ref_vpm: base.emf_ref_vpm,
+            gain_schedule: base.gain_schedule,
+            duty_schedule: base.duty_schedule,
+            geo_weighting: base.geo_weighting,
+            airflow: base.airflow,
+            agent_gate: base.agent_gate,
+            corridor: base.corridor,
+            eco_load_mode: base.eco_load_mode,
+            slew_limit: base.slew_limit,
+            violation_policy: ViolationPolicy::SkipNode,
+            stability: base.stability,
+            bee_guard: base.bee_guard,
+            metrics: base.metrics,
+        };
+        assert_eq!(duty_law_fingerprint(&per_pollutant), 0x8d8ad2ee700fae4a);
+    }
+
+    #[test]
+    fn eco_load_normalizes_weights() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        let half = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        assert_eq!(controller.eco_load(&nodes, 2.0, 2.0).unwrap(), half);
+        let mass_only = controller.eco_load(&nodes, 1.0, 0.0).unwrap();
+        let m_sum: f64 = nodes.iter().map(|n| n.mass_kg).sum();
+        assert!((mass_only - m_sum / controller.m_ref_kg).abs() < 1e-12);
+    }
+
+    /// Pairwise (cascade) summation, the accuracy reference for `eco_load`.
+    fn pairwise_sum(xs: &[f64]) -> f64 {
+        if xs.len() <= 8 {
+            xs.iter().sum()
+        } else {
+            let (lo, hi) = xs.split_at(xs.len() / 2);
+            pairwise_sum(lo) + pairwise_sum(hi)
+        }
+    }
+
+    #[test]
+    fn eco_load_is_accurate_over_mixed_magnitudes() {
+        let controller = phoenix_controller();
+        let template = phoenix_nodes().remove(0);
+        // Nanogram-scale masses interleaved with a few kilogram-scale ones,
+        // where naive summation loses the small terms to rounding.
+        let nodes: Vec<NodeState> = (0..200_000)
+            .map(|i| {
+                let mut n = template.clone();
+                n.mass_kg = match i % 1000 {
+                    0 => 1.0 + (i / 1000) as f64 * 1e-3,
+                    _ => 1e-9 * (1.0 + (i % 7) as f64 / 7.0),
+                };
+                n.karma_bytes = n.mass_kg * 1e9 * (1.0 + (i % 13) as f64);
+                n
+            })
+            .collect();
+        let masses: Vec<f64> = nodes.iter().map(|n| n.mass_kg).collect();
+        let karmas: Vec<f64> = nodes.iter().map(|n| n.karma_bytes).collect();
+        let reference = 0.5 * pairwise_sum(&masses) / controller.m_ref_kg
+            + 0.5 * pairwise_sum(&karmas) / controller.k_ref_nb;
+
+        let load = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        assert!(((load - reference) / reference).abs() < 1e-12);
+        // Order does not matter, and any iterator of references works.
+        let reversed = controller.eco_load(nodes.iter().rev(), 0.5, 0.5).unwrap();
+        assert!(((reversed - reference) / reference).abs() < 1e-12);
+    }
+
+    #[test]
+    fn geo_weighted_eco_load_favours_the_school_node() {
+        let mut controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        // Only the school carries load, and it is the sensitive site.
+        nodes[0].mass_kg = 0.0;
+        nodes[0].karma_bytes = 0.0;
+        nodes[0].geo_weight = 0.2;
+        nodes[1].geo_weight = 1.0;
+
+        let unweighted = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        let weighted = controller.eco_load_weighted(&nodes, 0.5, 0.5).unwrap();
+        // N / sum(w) * w_school = 2 / 1.2.
+        assert!((weighted / unweighted - 2.0 / 1.2).abs() < 1e-12);
+
+        // Weighted below the canopy, the same load counts for less.
+        nodes[0].geo_weight = 1.0;
+        nodes[1].geo_weight = 0.2;
+        let demoted = controller.eco_load_weighted(&nodes, 0.5, 0.5).unwrap();
+        assert!((demoted / unweighted - 0.2 / 1.2 * 2.0).abs() < 1e-12);
+
+        // Equal weights reproduce the unweighted load.
+        for n in nodes.iter_mut() {
+            n.geo_weight = 0.8;
+        }
+        let equal = controller.eco_load_weighted(&nodes, 0.5, 0.5).unwrap();
+        assert!((equal - controller.eco_load(&nodes, 0.5, 0.5).unwrap()).abs() < 1e-12);
+
+        for n in nodes.iter_mut() {
+            n.geo_weight = 0.0;
+        }
+        assert_eq!(
+            controller.eco_load_weighted(&nodes, 0.5, 0.5),
+            Err(SafetyError::ZeroGeoWeights)
+        );
+        nodes[1].geo_weight = -0.5;
+        assert!(matches!(
+            controller.eco_load_weighted(&nodes, 0.5, 0.5),
+            Err(SafetyError::InvalidGeoWeight { geo_weight, .. }) if geo_weight == -0.5
+        ));
+        assert_eq!(controller.eco_load_weighted(&[], 0.5, 0.5), Ok(0.0));
+
+        // The mode selects what the simulator classifies on.
+        let mut nodes = phoenix_nodes();
+        nodes[0].geo_weight = 0.2;
+        nodes[1].geo_weight = 1.0;
+        controller.eco_load_mode = EcoLoadMode::GeoWeighted;
+        let expected = controller.eco_load_weighted(&nodes, 0.5, 0.5).unwrap();
+        let log = controller
+            .run_steps(&mut nodes, 1, |_| StepInput {
+                phi_dw: 0.0,
+                alpha_m: 0.5,
+                alpha_k: 0.5,
+                eco_offset: 0.0,
+                wind: None,
+            })
+            .unwrap();
+        assert_eq!(log[0].eco_load, expected);
+    }
+
+    #[test]
+    fn eco_load_offset_is_floored_at_zero() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        let gross = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+
+        let partial = controller
+            .eco_load_with_offset(&nodes, 0.5, 0.5, 0.25 * gross)
+            .unwrap();
+        assert_eq!(partial.gross, gross);
+        assert_eq!(partial.offset_applied, 0.25 * gross);
+        assert!((partial.net - 0.75 * gross).abs() < 1e-12);
+
+        let floored = controller
+            .eco_load_with_offset(&nodes, 0.5, 0.5, 10.0 * gross)
+            .unwrap();
+        assert_eq!(floored.offset_requested, 10.0 * gross);
+        assert_eq!(floored.offset_applied, gross);
+        assert_eq!(floored.net, 0.0);
+
+        for offset in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                controller.eco_load_with_offset(&nodes, 0.5, 0.5, offset),
+                Err(SafetyError::InvalidEcoOffset { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn eco_load_rejects_bad_references() {
+        let mut controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        controller.m_ref_kg = 0.0;
+        assert_eq!(
+            controller.eco_load(&nodes, 0.5, 0.5),
+            Err(SafetyError::InvalidReference {
+                name: "m_ref_kg",
+                value: 0.0
+            })
+        );
+        assert!(matches!(
+            controller.update_node_duty(&mut nodes[0], EcoBand::Green, 0.0),
+            Err(SafetyError::InvalidReference {
+                name: "m_ref_kg",
+                ..
+            })
+        ));
+
+        controller.m_ref_kg = 1.0e-6;
+        controller.k_ref_nb = -1.0;
+        assert!(matches!(
+            controller.eco_load(&nodes, 0.5, 0.5),
+            Err(SafetyError::InvalidReference {
+                name: "k_ref_nb",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn eco_load_rejects_bad_weights() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        for (a_m, a_k) in [
+            (-0.5, 1.0),
+            (0.0, 0.0),
+            (f64::NAN, 0.5),
+            (0.5, f64::INFINITY),
+        ] {
+            assert!(
+                matches!(
+                    controller.eco_load(&nodes, a_m, a_k),
+                    Err(SafetyError::InvalidLoadWeights { .. })
+                ),
+                "alpha_m={a_m}, alpha_k={a_k}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_node_is_rejected() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        nodes[1].karma_bytes = f64::NAN;
+        match controller.eco_load(&nodes, 0.5, 0.5) {
+            Err(SafetyError::NonFiniteNode {
+                machine_id, field, ..
+            }) => {
+                assert_eq!(machine_id, "CYB-AIR-SCHOOL-05");
+                assert_eq!(field, "karma_bytes");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        nodes[0].mass_kg = f64::INFINITY;
+        assert_eq!(
+            controller.update_node_duty(&mut nodes[0], EcoBand::Green, 0.0),
+            Err(SafetyError::NonFiniteNode {
+                machine_id: "CYB-AIR-CANOPY-01".to_string(),
+                field: "mass_kg",
+                value: f64::INFINITY,
+            })
+        );
+    }
+
+    const NON_FINITE: [f64; 3] = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY];
+
+    #[test]
+    fn checked_mass_and_karma_reject_non_finite() {
+        let setters: [fn(&mut CorridorRow, f64); 6] = [
+            |r, v| r.cin = v,
+            |r, v| r.cout = v,
+            |r, v| r.airflow_m3_per_s = v,
+            |r, v| r.period_s = v,
+            |r, v| r.lambda_hazard = v,
+            |r, v| r.beta_nb_per_kg = v,
+        ];
+        for value in NON_FINITE {
+            for set in setters {
+                let mut row = phoenix_nodes()[0].row.clone();
+                set(&mut row, value);
+                assert!(matches!(
+                    checked_mass_kg(&row, Pollutant::PM25, 310.0),
+                    Err(MassError::NonFinite(SafetyError::NonFiniteNode { .. }))
+                ));
+                assert!(matches!(
+                    checked_karma_bytes(&row, 1.0e-6),
+                    Err(SafetyError::NonFiniteNode { .. })
+                ));
+            }
+            let row = &phoenix_nodes()[0].row;
+            assert!(matches!(
+                checked_karma_bytes(row, value),
+                Err(SafetyError::NonFiniteNode {
+                    field: "mass_kg",
+                    ..
+                })
+            ));
+        }
+
+        // Finite inputs can still overflow.
+        let mut row = phoenix_nodes()[0].row.clone();
+        row.airflow_m3_per_s = f64::MAX;
+        row.period_s = f64::MAX;
+        assert!(matches!(
+            checked_mass_kg(&row, Pollutant::PM25, 310.0),
+            Err(MassError::NonFinite(SafetyError::NonFiniteValue {
+                field: "mass_kg",
+                ..
+            }))
+        ));
+        row.lambda_hazard = f64::MAX;
+        assert!(matches!(
+            checked_karma_bytes(&row, 1.0),
+            Err(SafetyError::NonFiniteValue {
+                field: "karma_bytes",
+                ..
+            })
+        ));
+        let row = &phoenix_nodes()[0].row;
+        let mass_kg = checked_mass_kg(row, Pollutant::PM25, 310.0).unwrap();
+        assert_eq!(
+            mass_kg,
+            compute_mass_kg(row, Pollutant::PM25, 310.0).unwrap()
+        );
+        assert_eq!(
+            checked_karma_bytes(row, mass_kg),
+            Ok(compute_karma_bytes(row, mass_kg))
+        );
+    }
+
+    #[test]
+    fn non_finite_duty_keeps_the_previous_one() {
+        let mut controller = phoenix_controller();
+        controller.eta_m = f64::MAX;
+        let mut node = phoenix_nodes()[0].clone();
+        node.duty_cycle = 0.4;
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+        assert_eq!(node.duty_cycle, 0.4);
+        assert_eq!(report.duty_after, 0.4);
+        let handling = report.violation.unwrap();
+        assert_eq!(handling.action, ViolationAction::HeldPrevious);
+        assert_eq!(
+            handling.violations,
+            ["CYB-AIR-CANOPY-01: computed duty_raw is not finite (inf)"]
+        );
+
+        let assessment = controller.dry_run_node(&node, EcoBand::Green, 0.0);
+        assert!(matches!(
+            assessment.violations[..],
+            [SafetyError::NonFiniteValue {
+                field: "duty_raw",
+                ..
+            }]
+        ));
+        assert_eq!(assessment.projected.unwrap().duty_after, 0.4);
+    }
+
+    #[test]
+    fn no_non_finite_input_reaches_a_duty() {
+        let node_setters: [fn(&mut NodeState, f64); 7] = [
+            |n, v| n.mass_kg = v,
+            |n, v| n.karma_bytes = v,
+            |n, v| n.duty_cycle = v,
+            |n, v| n.power_w = v,
+            |n, v| n.geo_weight = v,
+            |n, v| n.noise_db = Some(v),
+            |n, v| n.emf_vpm = Some(v),
+        ];
+        type Controller = CorridorController<
+            RectSafetyEnvelope<ConstAltitude>,
+            SimpleHostBudget,
+            ThresholdEcoBand,
+            SimpleDwCeiling,
+        >;
+        let gain_setters: [fn(&mut Controller, f64); 8] = [
+            |c, v| c.eta_m = v,
+            |c, v| c.eta_k = v,
+            |c, v| c.eta_w = v,
+            |c, v| c.eta_b = v,
+            |c, v| c.eta_p = v,
+            |c, v| c.eta_dw = v,
+            |c, v| c.eta_noise = v,
+            |c, v| c.eta_emf = v,
+        ];
+        let policies = [
+            ViolationPolicy::Error,
+            ViolationPolicy::ClampToSafe { safe_duty: None },
+            ViolationPolicy::ClampToSafe {
+                safe_duty: Some(f64::NAN),
+            },
+            ViolationPolicy::SkipNode,
+        ];
+
+        let check = |controller: &Controller, mut node: NodeState, phi_dw: f64| {
+            let before = phoenix_nodes()[0].duty_cycle;
+            let result = controller.update_node_duty(&mut node, EcoBand::Green, phi_dw);
+            let after = if node.duty_cycle.is_finite() {
+                node.duty_cycle
+            } else {
+                // Only a rejected node may keep the bad duty it came with.
+                assert!(result.is_err());
+                before
+            };
+            assert!(after.is_finite());
+            if let Ok(report) = result {
+                assert!(report.duty_after.is_finite());
+                let assessment = controller.dry_run_node(&node, EcoBand::Green, phi_dw);
+                if let Some(projected) = assessment.projected {
+                    assert!(projected.duty_after.is_finite());
+                }
+            }
+        };
+
+        for policy in policies {
+            for value in NON_FINITE {
+                let mut controller = phoenix_controller();
+                controller.violation_policy = policy;
+                controller.slew_limit = Some(SlewLimit::symmetric(0.2));
+                for set in node_setters {
+                    let mut node = phoenix_nodes()[0].clone();
+                    set(&mut node, value);
+                    check(&controller, node, 0.0);
+                }
+                check(&controller, phoenix_nodes()[0].clone(), value);
+                for set in gain_setters {
+                    let mut controller = phoenix_controller();
+                    controller.violation_policy = policy;
+                    set(&mut controller, value);
+                    let mut node = phoenix_nodes()[0].clone();
+                    node.noise_db = Some(60.0);
+                    node.emf_vpm = Some(0.5);
+                    check(&controller, node, 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dw_ceiling_error_carries_flux() {
+        let ceiling = SimpleDwCeiling { phi_dw_max: 1.0e-6 };
+        assert_eq!(
+            ceiling.check_dw_ceiling(2.0e-6),
+            Err(SafetyError::DwCeilingExceeded {
+                phi_dw: 2.0e-6,
+                phi_dw_max: 1.0e-6,
+            })
+        );
+    }
+
+    #[test]
+    fn update_node_duty_from_nodes_uses_aggregate_flux() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        let phi = controller.dw_flux_density(&nodes).unwrap();
+
+        let mut a = nodes[0].clone();
+        let mut b = nodes[0].clone();
+        controller
+            .update_node_duty_from_nodes(&mut a, EcoBand::Green, &nodes)
+            .unwrap();
+        controller
+            .update_node_duty(&mut b, EcoBand::Green, phi)
+            .unwrap();
+        assert_eq!(a.duty_cycle, b.duty_cycle);
+    }
+
+    fn flapping_trace() -> Vec<f64> {
+        // Ramp into the Green/Amber boundary, then sit on it with sensor noise.
+        let mut trace: Vec<f64> = (0..10).map(|i| 0.30 + 0.02 * i as f64).collect();
+        for i in 0..100 {
+            trace.push(if i % 2 == 0 { 0.52 } else { 0.48 });
+        }
+        trace
+    }
+
+    #[test]
+    fn shard_trace_id_reaches_the_update_report() {
+        let csv = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpact_score
+CYB-AIR-CANOPY-01,UrbanNanoswarmCanopy,Phoenix-Intersection-A,PM2.5,40,28,ug/m3,3.0,3600,3.0,5.0e8,0.92
+";
+        let loaded = loader::from_named_reader(csv.as_bytes(), "phoenix.csv").unwrap();
+        let trace_id = loaded[0].row.trace_id;
+        assert_eq!(trace_id, Some(loader::row_trace_id("phoenix.csv", 2)));
+
+        let mut node = phoenix_nodes().remove(0);
+        node.row = loaded[0].row.clone();
+        let report = phoenix_controller()
+            .update_node_duty(&mut node, EcoBand::Amber, 0.0)
+            .unwrap();
+        assert_eq!(report.trace_id, trace_id);
+        assert_eq!(node.row.trace_id, trace_id);
+    }
+
+    fn count_transitions<B: EcoBandClassifier>(classifier: &B, trace: &[f64]) -> usize {
+        let mut prev: Option<EcoBand> = None;
+        let mut transitions = 0;
+        for &load in trace {
+            let band = classifier.classify(load);
+            if prev.is_some_and(|p| p != band) {
+                transitions += 1;
+            }
+            prev = Some(band);
+        }
+        transitions
+    }
+
+    #[test]
+    fn hysteretic_band_suppresses_flapping() {
+        let trace = flapping_trace();
+
+        let threshold = ThresholdEcoBand {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+            gain_green: 0.0,
+            gain_amber: 0.2,
+            gain_red: 0.5,
+        };
+        assert!(count_transitions(&threshold, &trace) > 50);
+
+        let hysteretic = HystereticEcoBand::new(0.5, 1.0, 0.05, 0.1, 0.0, 0.2, 0.5, EcoBand::Green);
+        assert_eq!(count_transitions(&hysteretic, &trace), 1);
+        assert_eq!(hysteretic.current_band(), EcoBand::Amber);
+    }
+
+    #[test]
+    fn hysteretic_band_de_escalates_below_gap() {
+        let hysteretic = HystereticEcoBand::new(0.5, 1.0, 0.05, 0.1, 0.0, 0.2, 0.5, EcoBand::Green);
+        assert_eq!(hysteretic.classify(1.2), EcoBand::Red);
+        assert_eq!(hysteretic.classify(0.95), EcoBand::Red);
+        assert_eq!(hysteretic.classify(0.85), EcoBand::Amber);
+        assert_eq!(hysteretic.classify(0.46), EcoBand::Amber);
+        assert_eq!(hysteretic.classify(0.44), EcoBand::Green);
+        assert_eq!(hysteretic.classify(0.3), EcoBand::Green);
+    }
 }
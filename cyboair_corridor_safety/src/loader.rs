@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::calibration::CalibrationTable;
+use crate::CorridorRow;
+
+/// Errors while loading qpudatashard CSVs.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {message}")]
+    Row { line: u64, message: String },
+    /// A value that does not parse as its column's type.
+    #[error("line {line}, column {column}: {message}")]
+    Field {
+        line: u64,
+        column: String,
+        message: String,
+    },
+    #[error("missing required columns: {}", .columns.join(", "))]
+    MissingColumns { columns: Vec<String> },
+    #[error("csv error: {0}")]
+    Csv(String),
+}
+
+impl From<csv::Error> for LoadError {
+    fn from(err: csv::Error) -> Self {
+        match err.position() {
+            Some(pos) => LoadError::Row {
+                line: pos.line(),
+                message: err.to_string(),
+            },
+            None => LoadError::Csv(err.to_string()),
+        }
+    }
+}
+
+fn field_error(
+    line: u64,
+    err: &csv::Error,
+    headers: &csv::StringRecord,
+    record: &csv::StringRecord,
+) -> LoadError {
+    let csv::ErrorKind::Deserialize { err, .. } = err.kind() else {
+        return LoadError::Row {
+            line,
+            message: err.to_string(),
+        };
+    };
+    let Some(i) = err.field().map(|i| i as usize) else {
+        return LoadError::Row {
+            line,
+            message: err.to_string(),
+        };
+    };
+    let value = record.get(i).unwrap_or_default();
+    let message = match err.kind() {
+        csv::DeserializeErrorKind::ParseFloat(_) => format!("invalid float '{value}'"),
+        csv::DeserializeErrorKind::ParseInt(_) => format!("invalid integer '{value}'"),
+        csv::DeserializeErrorKind::ParseBool(_) => format!("invalid bool '{value}'"),
+        kind => kind.to_string(),
+    };
+    LoadError::Field {
+        line,
+        column: headers.get(i).unwrap_or_default().to_string(),
+        message,
+    }
+}
+
+/// Read header-mapped records of any serde row type.
+///
+/// Each `required` entry names a column, or alternative spellings joined
+/// by `|` (e.g. `"ecoimpact_score|ecoimpactscore"`); all absent ones are
+/// reported together before any row is read. Other columns, known or not,
+/// may be present in any order. The first bad row aborts the read with its
+/// 1-based line number and, for unparsable values, the column and text.
+pub fn read_records<T, R>(reader: R, required: &[&str]) -> Result<Vec<T>, LoadError>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    read_mapped(reader, required, |row, _| Ok(row))
+}
+
+/// `read_records`, pairing each row with its 1-based line number, for
+/// reports that point back into the file.
+pub fn read_numbered_records<T, R>(reader: R, required: &[&str]) -> Result<Vec<(u64, T)>, LoadError>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    read_mapped(reader, required, |row, line| Ok((line, row)))
+}
+
+/// `read_records`, passing each row and its line through `map`.
+fn read_mapped<T, U, R>(
+    reader: R,
+    required: &[&str],
+    mut map: impl FnMut(T, u64) -> Result<U, LoadError>,
+) -> Result<Vec<U>, LoadError>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = rdr.headers()?.clone();
+
+    let missing: Vec<String> = required
+        .iter()
+        .filter(|names| !names.split('|').any(|n| headers.iter().any(|h| h == n)))
+        .map(|names| names.split('|').next().unwrap_or_default().to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(LoadError::MissingColumns { columns: missing });
+    }
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    while rdr.read_record(&mut record)? {
+        let line = record.position().map_or(0, |p| p.line());
+        let row = record
+            .deserialize(Some(&headers))
+            .map_err(|e| field_error(line, &e, &headers, &record))?;
+        rows.push(map(row, line)?);
+    }
+    Ok(rows)
+}
+
+/// Convenience wrapper around `read_records` for a file on disk.
+pub fn read_records_from_path<T, P>(path: P, required: &[&str]) -> Result<Vec<T>, LoadError>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    read_records(File::open(path)?, required)
+}
+
+/// Bee extension columns used by the bee-guard shards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BeeExtension {
+    /// 1 if the node sits in a bee foraging microspace.
+    pub bee_flag: u8,
+    /// Additional hazard multiplier for bees.
+    pub bee_weight: f64,
+    pub notes: String,
+}
+
+/// One shard row: the controller schema plus optional bee columns.
+#[derive(Debug, Clone)]
+pub struct ShardRow {
+    pub row: CorridorRow,
+    pub bee: Option<BeeExtension>,
+    /// True if `cin` and `cout` were corrected at ingest; see
+    /// `from_calibrated_reader`.
+    pub calibrated: bool,
+    /// Start of the row's `period_s` window, from the optional
+    /// `window_start_unix_ms` column; see `merge`.
+    pub window_start_unix_ms: Option<u64>,
+}
+
+/// Header-mapped record; aliases cover spellings seen in existing shards.
+#[derive(Debug, Deserialize)]
+struct RawRow {
+    machine_id: String,
+    #[serde(rename = "type")]
+    r#type: String,
+    location: String,
+    pollutant: String,
+    cin: f64,
+    cout: f64,
+    unit: String,
+    airflow_m3_per_s: f64,
+    period_s: f64,
+    lambda_hazard: f64,
+    beta_nb_per_kg: f64,
+    #[serde(alias = "ecoimpactscore")]
+    ecoimpact_score: f64,
+    bee_flag: Option<u8>,
+    bee_weight: Option<f64>,
+    notes: Option<String>,
+    trace_id: Option<Uuid>,
+    shard_version: Option<String>,
+    window_start_unix_ms: Option<u64>,
+}
+
+impl RawRow {
+    /// Rows without a `trace_id` of their own get [`row_trace_id`] of
+    /// `shard_name` and `line`, when the shard is named.
+    fn into_shard_row(self, line: u64, shard_name: Option<&str>) -> Result<ShardRow, LoadError> {
+        let bee = match (self.bee_flag, self.bee_weight) {
+            (Some(bee_flag), Some(bee_weight)) => Some(BeeExtension {
+                bee_flag,
+                bee_weight,
+                notes: self.notes.unwrap_or_default(),
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(LoadError::Row {
+                    line,
+                    message: "bee_flag and bee_weight must be given together".into(),
+                })
+            }
+        };
+        Ok(ShardRow {
+            row: CorridorRow {
+                machine_id: self.machine_id,
+                r#type: self.r#type,
+                location: self.location,
+                pollutant: self.pollutant,
+                cin: self.cin,
+                cout: self.cout,
+                unit: self.unit,
+                airflow_m3_per_s: self.airflow_m3_per_s,
+                period_s: self.period_s,
+                lambda_hazard: self.lambda_hazard,
+                beta_nb_per_kg: self.beta_nb_per_kg,
+                ecoimpact_score: self.ecoimpact_score,
+                trace_id: self
+                    .trace_id
+                    .or_else(|| shard_name.map(|name| row_trace_id(name, line))),
+                shard_version: self.shard_version,
+            },
+            bee,
+            calibrated: false,
+            window_start_unix_ms: self.window_start_unix_ms,
+        })
+    }
+}
+
+/// Columns `RawRow` cannot do without.
+const SHARD_COLUMNS: &[&str] = &[
+    "machine_id",
+    "type",
+    "location",
+    "pollutant",
+    "cin",
+    "cout",
+    "unit",
+    "airflow_m3_per_s",
+    "period_s",
+    "lambda_hazard",
+    "beta_nb_per_kg",
+    "ecoimpact_score|ecoimpactscore",
+];
+
+/// Namespace of [`row_trace_id`]; changing it changes every generated id.
+pub const ROW_TRACE_NAMESPACE: Uuid = Uuid::from_u128(0x2f8d_41c7_9a3e_5b06_8e1d_c4a7_6f20_93b5);
+
+/// Trace id of line `line` of the shard file `shard_name`: a UUIDv5 of
+/// `"{shard_name}:{line}"`, so loading the same file again gives the same
+/// ids.
+pub fn row_trace_id(shard_name: &str, line: u64) -> Uuid {
+    Uuid::new_v5(
+        &ROW_TRACE_NAMESPACE,
+        format!("{shard_name}:{line}").as_bytes(),
+    )
+}
+
+/// Load shard rows from any reader. Columns are matched by header name, so
+/// reordered or extra columns are fine; the first bad row aborts the load
+/// with its 1-based line number. Optional `trace_id` and `shard_version`
+/// columns are kept; without a shard name, rows lacking a `trace_id` have
+/// none (see `from_named_reader`).
+pub fn from_reader<R: Read>(reader: R) -> Result<Vec<ShardRow>, LoadError> {
+    read_mapped(reader, SHARD_COLUMNS, |row: RawRow, line| {
+        row.into_shard_row(line, None)
+    })
+}
+
+/// `from_reader` for the shard file `shard_name`, giving rows without a
+/// `trace_id` column value their [`row_trace_id`].
+pub fn from_named_reader<R: Read>(reader: R, shard_name: &str) -> Result<Vec<ShardRow>, LoadError> {
+    read_mapped(reader, SHARD_COLUMNS, |row: RawRow, line| {
+        row.into_shard_row(line, Some(shard_name))
+    })
+}
+
+/// `from_named_reader` with each row's `cin` and `cout` corrected by its
+/// machine's entry in `calibration`. Rows of machines without one are kept
+/// as read, with `calibrated` false.
+pub fn from_calibrated_reader<R: Read>(
+    reader: R,
+    shard_name: &str,
+    calibration: &CalibrationTable,
+) -> Result<Vec<ShardRow>, LoadError> {
+    read_mapped(reader, SHARD_COLUMNS, |row: RawRow, line| {
+        let mut shard_row = row.into_shard_row(line, Some(shard_name))?;
+        if let Some(row) = calibration.calibrate(&shard_row.row) {
+            shard_row.row = row;
+            shard_row.calibrated = true;
+        }
+        Ok(shard_row)
+    })
+}
+
+/// `from_named_reader` for a file on disk, named by its file name so the
+/// ids do not depend on where the shard is stored.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Vec<ShardRow>, LoadError> {
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    from_named_reader(File::open(path)?, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEN_MACHINES: &str = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpactscore,notes
+CYB-AIR-CANOPY-01,UrbanNanoswarmCanopy,Phoenix-Intersection-A,PM2.5,40,28,ug/m3,3.0,3600,3.0,5.0e8,0.92,\"Lamppost/roof modules over arterial; 12 ug/m3, rush hour.\"
+CYB-AIR-FLEET-02,TeslaswarmFleetPod,Phoenix-BusRoute-Blue,BlackCarbon,5.0,3.8,ug/m3,0.4,5400,3.5,6.0e8,0.89,\"Retrofit intake pods on e-buses; BC reduction at stops.\"
+";
+
+    #[test]
+    fn loads_by_header_with_quoted_commas() {
+        let rows = from_reader(TEN_MACHINES.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        let numbered: Vec<(u64, RawRow)> =
+            read_numbered_records(TEN_MACHINES.as_bytes(), &[]).unwrap();
+        assert_eq!(numbered[1].0, 3);
+        assert_eq!(numbered[1].1.machine_id, "CYB-AIR-FLEET-02");
+        assert_eq!(rows[0].row.machine_id, "CYB-AIR-CANOPY-01");
+        assert_eq!(rows[0].row.ecoimpact_score, 0.92);
+        assert_eq!(rows[1].row.period_s, 5400.0);
+        assert!(rows[0].bee.is_none());
+    }
+
+    #[test]
+    fn reordered_and_extra_columns_are_ignored() {
+        let csv = "\
+extra,ecoimpact_score,machine_id,location,type,pollutant,cout,cin,unit,period_s,airflow_m3_per_s,lambda_hazard,beta_nb_per_kg
+x,0.94,CYB-AIR-SCHOOL-05,Elementary-North,SchoolZoneShield,PM2.5,18,30,ug/m3,2700,1.0,4.0,5.5e8
+";
+        let rows = from_reader(csv.as_bytes()).unwrap();
+        assert_eq!(rows[0].row.cin, 30.0);
+        assert_eq!(rows[0].row.cout, 18.0);
+        assert_eq!(rows[0].row.r#type, "SchoolZoneShield");
+    }
+
+    #[test]
+    fn bee_extension_columns_are_optional() {
+        let csv = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpact_score,bee_flag,bee_weight,notes
+CYB-AIR-APIARY-01,Canopy,Phoenix-Apiary-1,PM2.5,30,20,ug/m3,0.8,3600,3.5,5.0e8,0.93,1,1.5,\"Upwind of apiary, near hives\"
+";
+        let rows = from_reader(csv.as_bytes()).unwrap();
+        let bee = rows[0].bee.as_ref().unwrap();
+        assert_eq!(bee.bee_flag, 1);
+        assert_eq!(bee.bee_weight, 1.5);
+        assert_eq!(bee.notes, "Upwind of apiary, near hives");
+    }
+
+    #[test]
+    fn malformed_numeric_reports_line() {
+        let csv = TEN_MACHINES.replace("0.4,5400", "0.4,54o0");
+        match from_reader(csv.as_bytes()) {
+            Err(err @ LoadError::Field { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "line 3, column period_s: invalid float '54o0'"
+                );
+            }
+            other => panic!("expected field error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_columns_are_listed_together() {
+        let csv = "\
+machine_id,type,location,pollutant,cin,unit,airflow_m3_per_s,period_s,beta_nb_per_kg,ecoimpactscore
+CYB-AIR-CANOPY-01,UrbanNanoswarmCanopy,Phoenix-Intersection-A,PM2.5,40,ug/m3,3.0,3600,5.0e8,0.92
+";
+        match from_reader(csv.as_bytes()) {
+            Err(LoadError::MissingColumns { columns }) => {
+                assert_eq!(columns, ["cout", "lambda_hazard"]);
+            }
+            other => panic!("expected missing columns, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn generic_records_map_by_header() {
+        #[derive(Deserialize)]
+        struct Minimal {
+            machine_id: String,
+            cin: f64,
+        }
+        let rows: Vec<Minimal> =
+            read_records(TEN_MACHINES.as_bytes(), &["machine_id", "cin"]).unwrap();
+        assert_eq!(rows[1].machine_id, "CYB-AIR-FLEET-02");
+        assert_eq!(rows[1].cin, 5.0);
+
+        let bad = TEN_MACHINES.replace(",5.0,3.8,", ",5.0x,3.8,");
+        let err = read_records::<Minimal, _>(bad.as_bytes(), &["cin"])
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "line 3, column cin: invalid float '5.0x'");
+    }
+
+    #[test]
+    fn calibration_is_applied_at_ingest() {
+        let table = CalibrationTable::from_json(
+            r#"{"machines": {"CYB-AIR-CANOPY-01":
+                {"offset": 4.0, "span": 0.5, "last_calibrated_unix_ms": 0}}}"#,
+        )
+        .unwrap();
+        let rows =
+            from_calibrated_reader(TEN_MACHINES.as_bytes(), "ten_machines.csv", &table).unwrap();
+        assert!(rows[0].calibrated);
+        assert_eq!((rows[0].row.cin, rows[0].row.cout), (18.0, 12.0));
+        assert_eq!(
+            rows[0].row.trace_id,
+            Some(row_trace_id("ten_machines.csv", 2))
+        );
+        assert!(!rows[1].calibrated);
+        assert_eq!((rows[1].row.cin, rows[1].row.cout), (5.0, 3.8));
+    }
+
+    #[test]
+    fn named_shards_give_rows_stable_trace_ids() {
+        assert!(from_reader(TEN_MACHINES.as_bytes()).unwrap()[0]
+            .row
+            .trace_id
+            .is_none());
+
+        let rows = from_named_reader(TEN_MACHINES.as_bytes(), "ten_machines.csv").unwrap();
+        let again = from_named_reader(TEN_MACHINES.as_bytes(), "ten_machines.csv").unwrap();
+        assert_eq!(
+            rows[1].row.trace_id,
+            Some(row_trace_id("ten_machines.csv", 3))
+        );
+        assert_eq!(rows[1].row.trace_id, again[1].row.trace_id);
+        assert_ne!(rows[0].row.trace_id, rows[1].row.trace_id);
+        let other = from_named_reader(TEN_MACHINES.as_bytes(), "other.csv").unwrap();
+        assert_ne!(rows[0].row.trace_id, other[0].row.trace_id);
+
+        // Ids and versions in the shard itself win.
+        let csv = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpact_score,trace_id,shard_version
+CYB-AIR-SCHOOL-05,SchoolZoneShield,Elementary-North,PM2.5,30,18,ug/m3,1.0,2700,4.0,5.5e8,0.94,6f1c2a4e-8b3d-4d7a-9e05-1b2c3d4e5f60,2026.10
+CYB-AIR-SCHOOL-06,SchoolZoneShield,Elementary-South,PM2.5,30,18,ug/m3,1.0,2700,4.0,5.5e8,0.94,,
+";
+        let rows = from_named_reader(csv.as_bytes(), "schools.csv").unwrap();
+        assert_eq!(
+            rows[0].row.trace_id,
+            Some(Uuid::parse_str("6f1c2a4e-8b3d-4d7a-9e05-1b2c3d4e5f60").unwrap())
+        );
+        assert_eq!(rows[0].row.shard_version.as_deref(), Some("2026.10"));
+        assert_eq!(rows[1].row.trace_id, Some(row_trace_id("schools.csv", 3)));
+        assert_eq!(rows[1].row.shard_version, None);
+    }
+
+    #[test]
+    fn missing_path_is_io_error() {
+        assert!(matches!(
+            from_path("/nonexistent/shard.csv"),
+            Err(LoadError::Io(_))
+        ));
+    }
+}
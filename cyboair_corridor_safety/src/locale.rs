@@ -0,0 +1,634 @@
+//! Presentation conventions for exported reports.
+//!
+//! A [`ReportLocale`] sets the decimal separator, digit grouping, unit
+//! label glyphs and timestamp format of the localized CSV exports: the
+//! karma ledger ([`KarmaLedger::write_csv_localized`]), band history
+//! ([`BandHistory::write_csv_localized`]) and dry-run assessments
+//! ([`CorridorAssessment::write_csv_localized`]). It applies to those
+//! exports only; JSON, the wire formats, shards and persistence keep their
+//! fixed ASCII, dot-decimal, Unix-millisecond form.
+//!
+//! Localized exports round-trip. Numbers are written as the shortest
+//! decimal that parses back to the same `f64`, timestamps to the
+//! millisecond, and each export has a reader that recovers the exact
+//! values. A comma decimal separator switches the CSV field delimiter to
+//! `;`, so consumers splitting on commas never see a number cut in two.
+//!
+//! [`KarmaLedger::write_csv_localized`]: crate::KarmaLedger::write_csv_localized
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ledger::{civil_from_days, days_from_civil};
+use crate::{
+    BandHistory, BandTransition, ConcentrationUnit, CorridorAssessment, EcoBand, NodeState,
+};
+
+const MINUTE_MS: i64 = 60_000;
+const DAY_MS: u64 = 86_400_000;
+
+#[derive(Debug, Error)]
+pub enum LocaleError {
+    #[error("invalid report locale: {field}: {reason}")]
+    Invalid { field: &'static str, reason: String },
+    #[error("not a number in this locale: {0:?}")]
+    Number(String),
+    #[error("not a timestamp in this locale: {0:?}")]
+    Timestamp(String),
+    #[error("unknown eco band: {0:?}")]
+    Band(String),
+    #[error("assessment node {index} is {assessed}, but the node given is {given}")]
+    NodeMismatch {
+        index: usize,
+        assessed: String,
+        given: String,
+    },
+    #[error("report csv error: {0}")]
+    Csv(#[from] csv::Error),
+}
+
+/// Glyphs of unit labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UnitStyle {
+    /// "ug/m3", as in shards.
+    #[default]
+    Ascii,
+    /// "µg/m³".
+    Unicode,
+}
+
+/// Order of the date fields in a localized timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampStyle {
+    /// `2026-07-01T13:05:00.250Z`.
+    #[default]
+    Rfc3339,
+    /// `01.07.2026 15:05:00.250` for day-month-year with `.` at UTC+2.
+    /// Daylight saving is not modelled, as for duty schedules.
+    Localized {
+        order: DateOrder,
+        date_separator: char,
+        utc_offset_minutes: i32,
+    },
+}
+
+/// Number, unit and timestamp conventions of a report; see the module
+/// docs. The default is the plain export: `.` decimals, no grouping,
+/// ASCII units and RFC 3339 timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ReportLocale {
+    /// `.` or `,`.
+    pub decimal_separator: char,
+    /// Thousands separator of integer digits, e.g. `.`, `'` or a narrow
+    /// no-break space; `None` writes digits ungrouped.
+    pub digit_grouping: Option<char>,
+    pub units: UnitStyle,
+    pub timestamps: TimestampStyle,
+}
+
+impl Default for ReportLocale {
+    fn default() -> Self {
+        ReportLocale {
+            decimal_separator: '.',
+            digit_grouping: None,
+            units: UnitStyle::Ascii,
+            timestamps: TimestampStyle::Rfc3339,
+        }
+    }
+}
+
+impl ReportLocale {
+    /// Separators that keep numbers and fields apart, and a UTC offset
+    /// of at most 14 hours.
+    pub fn validate(&self) -> Result<(), LocaleError> {
+        if !matches!(self.decimal_separator, '.' | ',') {
+            return Err(invalid(
+                "decimal_separator",
+                format!("{:?} is neither '.' nor ','", self.decimal_separator),
+            ));
+        }
+        if let Some(g) = self.digit_grouping {
+            if g == self.decimal_separator || g.is_ascii_digit() || matches!(g, '-' | '+' | '"') {
+                return Err(invalid(
+                    "digit_grouping",
+                    format!("{g:?} cannot separate digit groups"),
+                ));
+            }
+        }
+        if let TimestampStyle::Localized {
+            date_separator,
+            utc_offset_minutes,
+            ..
+        } = self.timestamps
+        {
+            if date_separator.is_ascii_digit() || matches!(date_separator, ' ' | ':') {
+                return Err(invalid(
+                    "timestamps.date_separator",
+                    format!("{date_separator:?} cannot separate date fields"),
+                ));
+            }
+            if utc_offset_minutes.abs() > 14 * 60 {
+                return Err(invalid(
+                    "timestamps.utc_offset_minutes",
+                    format!("{utc_offset_minutes} is more than 14 hours"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// CSV field delimiter: `;` when a comma is taken by numbers.
+    pub fn field_delimiter(&self) -> u8 {
+        if self.decimal_separator == ',' || self.digit_grouping == Some(',') {
+            b';'
+        } else {
+            b','
+        }
+    }
+
+    /// Shortest round-tripping decimal of `value`, with this locale's
+    /// separators. Non-finite values are written as Rust spells them.
+    pub fn format_number(&self, value: f64) -> String {
+        let plain = value.to_string();
+        if !value.is_finite() {
+            return plain;
+        }
+        let (int, frac) = match plain.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (plain.as_str(), None),
+        };
+        let (sign, digits) = match int.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", int),
+        };
+        let mut out = format!("{sign}{}", self.group(digits));
+        if let Some(frac) = frac {
+            out.push(self.decimal_separator);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    /// Inverse of [`format_number`](Self::format_number). Grouping is only
+    /// accepted between groups of three integer digits, and any other `.`
+    /// or `,` is refused, so a number in another locale's convention is an
+    /// error rather than a different value.
+    pub fn parse_number(&self, s: &str) -> Result<f64, LocaleError> {
+        let err = || LocaleError::Number(s.to_string());
+        let s = s.trim();
+        let (int, frac) = match s.split_once(self.decimal_separator) {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (s, None),
+        };
+        let mut plain = self.ungroup(int).ok_or_else(err)?;
+        if let Some(frac) = frac {
+            if frac.contains(['.', ',']) {
+                return Err(err());
+            }
+            plain.push('.');
+            plain.push_str(frac);
+        }
+        plain.parse().map_err(|_| err())
+    }
+
+    /// `count` with this locale's digit grouping.
+    pub fn format_count(&self, count: u64) -> String {
+        self.group(&count.to_string())
+    }
+
+    pub fn parse_count(&self, s: &str) -> Result<u64, LocaleError> {
+        self.ungroup(s.trim())
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| LocaleError::Number(s.to_string()))
+    }
+
+    /// `int` without its grouping, if every group after the first has
+    /// three digits and no other `.` or `,` is in it.
+    fn ungroup(&self, int: &str) -> Option<String> {
+        if int.contains(|c| matches!(c, '.' | ',') && Some(c) != self.digit_grouping) {
+            return None;
+        }
+        let Some(sep) = self.digit_grouping else {
+            return Some(int.to_string());
+        };
+        let mut groups = int.split(sep);
+        let first = groups.next().unwrap_or_default();
+        let leading = first.trim_start_matches(['-', '+']).len();
+        let mut plain = first.to_string();
+        for group in groups {
+            if group.len() != 3 || !(1..=3).contains(&leading) {
+                return None;
+            }
+            plain.push_str(group);
+        }
+        Some(plain)
+    }
+
+    fn group(&self, digits: &str) -> String {
+        let Some(sep) = self.digit_grouping else {
+            return digits.to_string();
+        };
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3 * sep.len_utf8());
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(sep);
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// `unit`'s label in this locale's glyphs.
+    pub fn unit_label(&self, unit: ConcentrationUnit) -> &'static str {
+        unit.label(self.units)
+    }
+
+    /// `unix_ms` to the millisecond. Localized timestamps are only
+    /// defined from 1970-01-01 local time on; earlier instants are written
+    /// as that midnight.
+    pub fn format_timestamp(&self, unix_ms: u64) -> String {
+        match self.timestamps {
+            TimestampStyle::Rfc3339 => {
+                let (date, time) = civil(unix_ms);
+                format!("{}-{:02}-{:02}T{time}Z", date.0, date.1, date.2)
+            }
+            TimestampStyle::Localized {
+                order,
+                date_separator: sep,
+                utc_offset_minutes,
+            } => {
+                let local = (unix_ms as i64 + i64::from(utc_offset_minutes) * MINUTE_MS).max(0);
+                let ((y, m, d), time) = civil(local as u64);
+                let date = match order {
+                    DateOrder::DayMonthYear => format!("{d:02}{sep}{m:02}{sep}{y:04}"),
+                    DateOrder::MonthDayYear => format!("{m:02}{sep}{d:02}{sep}{y:04}"),
+                    DateOrder::YearMonthDay => format!("{y:04}{sep}{m:02}{sep}{d:02}"),
+                };
+                format!("{date} {time}")
+            }
+        }
+    }
+
+    /// Inverse of [`format_timestamp`](Self::format_timestamp).
+    pub fn parse_timestamp(&self, s: &str) -> Result<u64, LocaleError> {
+        let err = || LocaleError::Timestamp(s.to_string());
+        let (fields, time, offset_minutes) = match self.timestamps {
+            TimestampStyle::Rfc3339 => {
+                let (date, time) = s
+                    .trim()
+                    .strip_suffix('Z')
+                    .and_then(|s| s.split_once('T'))
+                    .ok_or_else(err)?;
+                let fields: Vec<&str> = date.split('-').collect();
+                let [y, m, d] = fields[..] else {
+                    return Err(err());
+                };
+                ([y, m, d], time, 0)
+            }
+            TimestampStyle::Localized {
+                order,
+                date_separator,
+                utc_offset_minutes,
+            } => {
+                let (date, time) = s.trim().split_once(' ').ok_or_else(err)?;
+                let fields: Vec<&str> = date.split(date_separator).collect();
+                let [a, b, c] = fields[..] else {
+                    return Err(err());
+                };
+                let ymd = match order {
+                    DateOrder::DayMonthYear => [c, b, a],
+                    DateOrder::MonthDayYear => [c, a, b],
+                    DateOrder::YearMonthDay => [a, b, c],
+                };
+                (ymd, time, utc_offset_minutes)
+            }
+        };
+        let number = |f: &str| f.parse::<u64>().map_err(|_| err());
+        let [y, m, d] = [number(fields[0])?, number(fields[1])?, number(fields[2])?];
+        if y < 1970 || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+            return Err(err());
+        }
+        let (hms, millis) = time.split_once('.').ok_or_else(err)?;
+        let hms: Vec<&str> = hms.split(':').collect();
+        let [h, min, sec] = hms[..] else {
+            return Err(err());
+        };
+        let [h, min, sec, millis] = [number(h)?, number(min)?, number(sec)?, number(millis)?];
+        if h > 23 || min > 59 || sec > 59 || millis > 999 {
+            return Err(err());
+        }
+        let local =
+            days_from_civil(y, m, d) * DAY_MS + ((h * 60 + min) * 60 + sec) * 1_000 + millis;
+        let utc = local as i64 - i64::from(offset_minutes) * MINUTE_MS;
+        u64::try_from(utc).map_err(|_| err())
+    }
+
+    pub(crate) fn csv_writer<W: io::Write>(&self, writer: W) -> csv::Writer<W> {
+        csv::WriterBuilder::new()
+            .delimiter(self.field_delimiter())
+            .from_writer(writer)
+    }
+
+    pub(crate) fn csv_reader<R: io::Read>(&self, reader: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .delimiter(self.field_delimiter())
+            .from_reader(reader)
+    }
+}
+
+fn invalid(field: &'static str, reason: String) -> LocaleError {
+    LocaleError::Invalid { field, reason }
+}
+
+/// Date and `HH:MM:SS.mmm` of `unix_ms`.
+fn civil(unix_ms: u64) -> ((u64, u64, u64), String) {
+    let date = civil_from_days(unix_ms / DAY_MS);
+    let ms = unix_ms % DAY_MS;
+    let time = format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    );
+    (date, time)
+}
+
+fn band_name(band: EcoBand) -> &'static str {
+    match band {
+        EcoBand::Green => "Green",
+        EcoBand::Amber => "Amber",
+        EcoBand::Red => "Red",
+    }
+}
+
+fn parse_band(s: &str) -> Result<EcoBand, LocaleError> {
+    match s {
+        "Green" => Ok(EcoBand::Green),
+        "Amber" => Ok(EcoBand::Amber),
+        "Red" => Ok(EcoBand::Red),
+        _ => Err(LocaleError::Band(s.to_string())),
+    }
+}
+
+impl BandHistory {
+    /// Retained transitions as `from,to,at`, oldest first; `from` is
+    /// empty for the first band observed.
+    pub fn write_csv_localized<W: io::Write>(
+        &self,
+        writer: W,
+        locale: &ReportLocale,
+    ) -> Result<(), LocaleError> {
+        locale.validate()?;
+        let mut csv = locale.csv_writer(writer);
+        csv.write_record(["from", "to", "at"])?;
+        for t in self.transitions() {
+            csv.write_record([
+                t.from.map_or("", band_name),
+                band_name(t.to),
+                &locale.format_timestamp(t.at_ms),
+            ])?;
+        }
+        csv.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+
+    /// Transitions written by [`write_csv_localized`](Self::write_csv_localized).
+    pub fn read_csv_localized<R: io::Read>(
+        reader: R,
+        locale: &ReportLocale,
+    ) -> Result<Vec<BandTransition>, LocaleError> {
+        locale.validate()?;
+        let mut transitions = Vec::new();
+        for record in locale.csv_reader(reader).records() {
+            let record = record?;
+            let field = |i| record.get(i).unwrap_or_default();
+            transitions.push(BandTransition {
+                from: match field(0) {
+                    "" => None,
+                    band => Some(parse_band(band)?),
+                },
+                to: parse_band(field(1))?,
+                at_ms: locale.parse_timestamp(field(2))?,
+            });
+        }
+        Ok(transitions)
+    }
+}
+
+impl CorridorAssessment {
+    /// One line per node: its pollutant, inlet and outlet concentration
+    /// with the unit label in the locale's glyphs, the projected duty
+    /// before and after (empty without a projection), and the
+    /// [`SafetyError::kind`](crate::SafetyError::kind)s of its violations,
+    /// space-separated. `nodes` are the ones assessed, in the same order.
+    /// A unit the shard spelled in a way [`ConcentrationUnit`] does not
+    /// know is written as spelled.
+    pub fn write_csv_localized<W: io::Write>(
+        &self,
+        nodes: &[NodeState],
+        writer: W,
+        locale: &ReportLocale,
+    ) -> Result<(), LocaleError> {
+        locale.validate()?;
+        let mut csv = locale.csv_writer(writer);
+        csv.write_record([
+            "machine_id",
+            "pollutant",
+            "cin",
+            "cout",
+            "unit",
+            "duty_before",
+            "duty_after",
+            "violations",
+        ])?;
+        for (index, assessed) in self.nodes.iter().enumerate() {
+            let row = match nodes.get(index) {
+                Some(node) if node.row.machine_id == assessed.machine_id => &node.row,
+                other => {
+                    return Err(LocaleError::NodeMismatch {
+                        index,
+                        assessed: assessed.machine_id.clone(),
+                        given: other.map_or("nothing".into(), |n| n.row.machine_id.clone()),
+                    })
+                }
+            };
+            let unit = row
+                .unit
+                .parse()
+                .map_or(row.unit.as_str(), |u| locale.unit_label(u));
+            let duty = |f: fn(&crate::UpdateReport) -> f64| {
+                assessed
+                    .projected
+                    .as_ref()
+                    .map_or(String::new(), |p| locale.format_number(f(p)))
+            };
+            let kinds: Vec<&str> = assessed.violations.iter().map(|e| e.kind()).collect();
+            csv.write_record([
+                &assessed.machine_id,
+                &row.pollutant,
+                &locale.format_number(row.cin),
+                &locale.format_number(row.cout),
+                unit,
+                &duty(|p| p.duty_before),
+                &duty(|p| p.duty_after),
+                &kinds.join(" "),
+            ])?;
+        }
+        csv.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+
+    fn european() -> ReportLocale {
+        ReportLocale {
+            decimal_separator: ',',
+            digit_grouping: Some('.'),
+            units: UnitStyle::Unicode,
+            timestamps: TimestampStyle::Localized {
+                order: DateOrder::DayMonthYear,
+                date_separator: '.',
+                utc_offset_minutes: 120,
+            },
+        }
+    }
+
+    #[test]
+    fn numbers_and_timestamps_round_trip_in_each_locale() {
+        let de = european();
+        assert_eq!(de.format_number(1234567.25), "1.234.567,25");
+        assert_eq!(de.format_number(-0.001), "-0,001");
+        assert_eq!(de.format_count(1_000), "1.000");
+        assert_eq!(de.field_delimiter(), b';');
+        // A dot-decimal reading is refused rather than taken as grouping.
+        assert!(de.parse_number("1.5").is_err());
+        assert!(de.parse_number("1,5,0").is_err());
+
+        // 2026-07-01T13:05:00.250Z.
+        let t = 1_782_911_100_250;
+        assert_eq!(de.format_timestamp(t), "01.07.2026 15:05:00.250");
+        let us = ReportLocale {
+            timestamps: TimestampStyle::Localized {
+                order: DateOrder::MonthDayYear,
+                date_separator: '/',
+                utc_offset_minutes: -420,
+            },
+            ..ReportLocale::default()
+        };
+        assert_eq!(us.format_timestamp(t), "07/01/2026 06:05:00.250");
+        let plain = ReportLocale::default();
+        assert_eq!(plain.format_timestamp(t), "2026-07-01T13:05:00.250Z");
+
+        for locale in [de, us, plain] {
+            for v in [0.0, 1e-3, 5e5, 2.0 / 3.0, -1234.5678, 1e21, 5e-324] {
+                let s = locale.format_number(v);
+                assert_eq!(
+                    locale.parse_number(&s).unwrap().to_bits(),
+                    v.to_bits(),
+                    "{s}"
+                );
+            }
+            for t in [t, 86_400_000, 4_102_444_799_999] {
+                assert_eq!(
+                    locale.parse_timestamp(&locale.format_timestamp(t)).unwrap(),
+                    t
+                );
+            }
+        }
+
+        let err = ReportLocale {
+            digit_grouping: Some(','),
+            decimal_separator: ',',
+            ..ReportLocale::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            LocaleError::Invalid {
+                field: "digit_grouping",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unit_labels_follow_the_style() {
+        let unicode = european();
+        let ascii = ReportLocale::default();
+        assert_eq!(ascii.unit_label(ConcentrationUnit::UgPerM3), "ug/m3");
+        assert_eq!(unicode.unit_label(ConcentrationUnit::UgPerM3), "µg/m³");
+        assert_eq!(unicode.unit_label(ConcentrationUnit::MgPerM3), "mg/m³");
+        assert_eq!(unicode.unit_label(ConcentrationUnit::Ppb), "ppb");
+        for unit in [
+            ConcentrationUnit::NgPerM3,
+            ConcentrationUnit::UgPerM3,
+            ConcentrationUnit::MgPerM3,
+            ConcentrationUnit::MoleFraction,
+        ] {
+            assert_eq!(
+                unicode.unit_label(unit).parse::<ConcentrationUnit>(),
+                Ok(unit)
+            );
+        }
+
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        let assessment = controller.dry_run(&nodes, EcoBand::Amber, 5.0e-7);
+        let mut out = Vec::new();
+        assessment
+            .write_csv_localized(&nodes, &mut out, &unicode)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let line = out.lines().nth(1).unwrap();
+        assert!(
+            line.starts_with("CYB-AIR-CANOPY-01;PM2.5;40;28;µg/m³;"),
+            "{line}"
+        );
+
+        let err = assessment
+            .write_csv_localized(&nodes[1..], Vec::new(), &unicode)
+            .unwrap_err();
+        assert!(matches!(err, LocaleError::NodeMismatch { index: 0, .. }));
+    }
+
+    #[test]
+    fn band_history_round_trips_through_comma_decimal_csv() {
+        let mut history = BandHistory::new(8);
+        history.record(EcoBand::Green, 1_782_864_000_000);
+        history.record(EcoBand::Red, 1_782_911_100_250);
+        history.record(EcoBand::Amber, 1_782_950_000_001);
+        let de = european();
+        let mut out = Vec::new();
+        history.write_csv_localized(&mut out, &de).unwrap();
+        let text = String::from_utf8(out.clone()).unwrap();
+        assert_eq!(
+            text.lines().nth(2),
+            Some("Green;Red;01.07.2026 15:05:00.250")
+        );
+        let back = BandHistory::read_csv_localized(out.as_slice(), &de).unwrap();
+        assert_eq!(back, history.transitions().copied().collect::<Vec<_>>());
+    }
+}
@@ -1,15 +1,24 @@
 use std::error::Error;
 
 use cyboair_corridor_safety::{
-    compute_karma_bytes, compute_mass_kg, CorridorController, CorridorRow, EcoBandClassifier,
-    NodeState, RectSafetyEnvelope, SimpleDwCeiling, SimpleHostBudget, ThresholdEcoBand,
+    merge_colocated, net_mass_removed, ControllerConfig, CorridorRow, EcoBandClassifier,
+    MapAltitude, MergeOptions, NodeState, OperationalMode,
+};
+#[cfg(feature = "mqtt")]
+use cyboair_corridor_safety::{
+    mqtt::{self, MqttConfig},
+    replay::{TraceNode, TraceStep},
+    EcoBand,
+};
+#[cfg(feature = "persistence")]
+use cyboair_corridor_safety::{
+    persistence::{self, PersistError},
+    SystemClock,
 };
 
-fn phoenix_altitude_m(_loc: &str) -> f64 {
-    // Simple placeholder: Phoenix mean elevation ~ 331 m.
-    // Replace with real DEM-based lookup in production.
-    331.0
-}
+/// Saved duty cycles older than this describe a different corridor state.
+#[cfg(feature = "persistence")]
+const MAX_STATE_AGE_MS: u64 = 6 * 3_600_000;
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Example: two nodes from a Phoenix-like shard.
@@ -26,6 +35,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         lambda_hazard: 3.0,
         beta_nb_per_kg: 5.0e8,
         ecoimpact_score: 0.92,
+        trace_id: None,
+        shard_version: None,
     };
 
     let row_school = CorridorRow {
@@ -41,110 +52,171 @@ fn main() -> Result<(), Box<dyn Error>> {
         lambda_hazard: 4.0,
         beta_nb_per_kg: 5.5e8,
         ecoimpact_score: 0.94,
+        trace_id: None,
+        shard_version: None,
     };
 
-    // Physics parameters (Phoenix summer).
-    let temperature_k = 310.0_f64;
-    let molar_mass_kg_per_mol = 0.048_f64; // surrogate for O3/NO2; for PM2.5 this is a proxy.
-
-    let mut node_canopy = NodeState {
-        row: row_canopy,
-        mass_kg: 0.0,
-        karma_bytes: 0.0,
-        duty_cycle: 0.5,
-        power_w: 50.0,
-        geo_weight: 0.8,
-    };
-
-    let mut node_school = NodeState {
-        row: row_school,
-        mass_kg: 0.0,
-        karma_bytes: 0.0,
-        duty_cycle: 0.7,
-        power_w: 35.0,
-        geo_weight: 1.0,
-    };
-
-    // Populate mass and Karma using CEIM/NanoKarma operators.
-    for node in [&mut node_canopy, &mut node_school] {
-        let m = compute_mass_kg(&node.row, temperature_k, molar_mass_kg_per_mol);
-        let k = compute_karma_bytes(&node.row, m);
-        node.mass_kg = m;
-        node.karma_bytes = k;
+    // Redundant sensors on one machine would otherwise count its mass twice.
+    let (rows, report) = merge_colocated(vec![row_canopy, row_school], &MergeOptions::default())?;
+    for group in &report.groups {
+        println!(
+            "merged {} rows of {}/{}",
+            group.rows.len(),
+            group.machine_id,
+            group.pollutant
+        );
     }
 
-    // Safety envelope: down-hanging urban band, high ecoimpact nodes.
-    let envelope = RectSafetyEnvelope {
-        u_min: 0.0,
-        u_max: 1.0,
-        z_min_m: 5.0,
-        z_max_m: 600.0,
-        ecoimpact_min: 0.7,
-        ecoimpact_max: 1.0,
-        altitude_m: phoenix_altitude_m,
-    };
+    // Gains, references, envelope bounds, budgets and the removal baseline
+    // come from the deployment config.
+    let config = ControllerConfig::from_toml(include_str!("../config/phoenix.toml"))?;
 
-    // Host budgets (per node) — illustrative.
-    let host_budget = SimpleHostBudget {
-        p_max_w: 150.0,
-        e_step_max_j: 1.0e5,
-        step_dt_s: 300.0,
-    };
+    // Physics parameters (Phoenix summer).
+    let temperature_k = 310.0_f64;
 
-    // Eco-band classifier: thresholds in normalized eco-load units.
-    let eco_band = ThresholdEcoBand {
-        theta_green_amber: 0.5,
-        theta_amber_red: 1.0,
-        gain_green: 0.0,
-        gain_amber: 0.2,
-        gain_red: 0.5,
-    };
+    // Populate mass and Karma using CEIM/NanoKarma operators, against the
+    // no-actuation baseline.
+    let mut nodes = Vec::with_capacity(rows.len());
+    let mut removals = Vec::with_capacity(rows.len());
+    for row in rows {
+        // (duty_cycle, power_w, geo_weight): the school zone is weighted up.
+        let (duty_cycle, power_w, geo_weight) = match row.machine_id.as_str() {
+            "CYB-AIR-SCHOOL-05" => (0.7, 35.0, 1.0),
+            _ => (0.5, 50.0, 0.8),
+        };
+        let pollutant = row.pollutant_kind()?;
+        let removal = net_mass_removed(&row, pollutant, temperature_k, &config.mass_baseline)?;
+        let karma_bytes = removal.karma_bytes(&row, config.karma_basis);
+        removals.push(removal);
+        nodes.push(NodeState {
+            row,
+            mass_kg: removal.gross_kg,
+            karma_bytes,
+            duty_cycle,
+            power_w,
+            geo_weight,
+            noise_db: None,
+            emf_vpm: None,
+            suspicion: None,
+            mode: OperationalMode::Active,
+        });
+    }
 
-    // DW ceiling invariant: example maximum flux density.
-    let dw_ceiling = SimpleDwCeiling { phi_dw_max: 1.0e-6 };
-
-    // Reference scales from shard orders of magnitude.
-    let controller = CorridorController {
-        envelope,
-        host_budget,
-        eco_band,
-        dw_ceiling,
-        m_ref_kg: 1.0e-6,
-        k_ref_nb: 1.0e10,
-        eta_m: 0.1,
-        eta_k: 0.1,
-        eta_w: 0.2,
-        eta_b: 0.2,
-        eta_p: 0.05,
-        eta_dw: 0.1,
-    };
+    // Site elevations are surveyed, replace with a DEM-backed provider in production.
+    let controller = config.build(
+        MapAltitude::new()
+            .with_site("Phoenix-Intersection-A", 331.0)
+            .with_site("Elementary-North", 340.0),
+    )?;
 
     // Corridor eco-load and band.
-    let nodes_slice = [node_canopy.clone(), node_school.clone()];
-    let eco_load = controller.eco_load(&nodes_slice, 0.5, 0.5);
-    let band = controller.eco_band.classify(eco_load);
+    #[cfg(feature = "persistence")]
+    let state = resume(&mut nodes)?;
 
-    // Example DW flux density (would be computed from in/out flows in production).
-    let phi_dw = 5.0e-7; // below ceiling, no violation.
+    let eco_load = controller.eco_load(&nodes, 0.5, 0.5)?;
+    let band = controller.eco_band.classify(eco_load);
 
-    // Update nodes.
-    let mut nodes = vec![node_canopy, node_school];
+    // Update nodes; DW flux density is aggregated from the same CEIM masses,
+    // which duty updates do not change.
+    let phi_dw = controller.dw_flux_density(&nodes)?;
     for node in nodes.iter_mut() {
         controller.update_node_duty(node, band, phi_dw)?;
     }
 
     // Emit control summary.
-    for node in nodes.iter() {
+    for (node, removal) in nodes.iter().zip(&removals) {
         println!(
-            "{},{},{},{:.6e},{:.6e},{:.3}",
+            "{},{},{},{:.6e},{:.6e},{:.6e},{:.6e},{:.3}",
             node.row.machine_id,
             node.row.location,
             node.row.pollutant,
-            node.mass_kg,
+            removal.gross_kg,
+            removal.baseline_kg,
+            removal.net_kg,
             node.karma_bytes,
             node.duty_cycle
         );
     }
 
+    #[cfg(feature = "mqtt")]
+    publish_telemetry(&nodes, band, eco_load)?;
+
+    #[cfg(feature = "persistence")]
+    if let Some((mut conn, step_counter)) = state {
+        persistence::save_corridor_state(&mut conn, &nodes, step_counter + 1, &SystemClock)?;
+    }
+
+    Ok(())
+}
+
+/// Open the state database named by `CYBOAIR_STATE_DB`, if set, and put the
+/// last run's duty cycles back on `nodes`. Returns the connection and the
+/// saved step counter.
+#[cfg(feature = "persistence")]
+fn resume(nodes: &mut [NodeState]) -> Result<Option<(rusqlite::Connection, u64)>, Box<dyn Error>> {
+    let Some(path) = std::env::var_os("CYBOAIR_STATE_DB") else {
+        return Ok(None);
+    };
+    let conn = rusqlite::Connection::open(path)?;
+    let step_counter = match persistence::load_corridor_state(&conn, &SystemClock, MAX_STATE_AGE_MS)
+    {
+        Ok(Some(snapshot)) => {
+            snapshot.restore_duty_cycles(nodes);
+            snapshot.step_counter
+        }
+        Ok(None) => 0,
+        Err(err @ PersistError::Stale { .. }) => {
+            eprintln!("starting from configured duty cycles: {err}");
+            0
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok(Some((conn, step_counter)))
+}
+
+/// Publish each node's result to the broker named by `CYBOAIR_MQTT_BROKER`,
+/// if set, waiting briefly for delivery before exiting.
+#[cfg(feature = "mqtt")]
+fn publish_telemetry(
+    nodes: &[NodeState],
+    band: EcoBand,
+    eco_load: f64,
+) -> Result<(), Box<dyn Error>> {
+    let Ok(broker_url) = std::env::var("CYBOAIR_MQTT_BROKER") else {
+        return Ok(());
+    };
+    let config = MqttConfig {
+        broker_url,
+        client_id: std::env::var("CYBOAIR_MQTT_CLIENT_ID")
+            .unwrap_or_else(|_| "cyboair-corridor".to_string()),
+        username: std::env::var("CYBOAIR_MQTT_USERNAME").ok(),
+        password: std::env::var("CYBOAIR_MQTT_PASSWORD").ok(),
+        topic_prefix: std::env::var("CYBOAIR_MQTT_PREFIX")
+            .unwrap_or_else(|_| "cyboair/corridor".to_string()),
+        outbox_capacity: 256,
+        keep_alive_s: 30,
+    };
+    let mut publisher = mqtt::connect(&config)?;
+    let step = TraceStep {
+        step: 0,
+        eco_load,
+        eco_offset_applied: 0.0,
+        band,
+        nodes: nodes
+            .iter()
+            .map(|n| TraceNode {
+                machine_id: n.row.machine_id.clone(),
+                duty_cycle: n.duty_cycle,
+                mass_kg: n.mass_kg,
+                karma_bytes: n.karma_bytes,
+                error: None,
+            })
+            .collect(),
+    };
+    publisher.publish_step(&step)?;
+    let unsent = publisher.close(std::time::Duration::from_secs(5));
+    if unsent > 0 {
+        eprintln!("mqtt: {unsent} telemetry messages not delivered");
+    }
     Ok(())
 }
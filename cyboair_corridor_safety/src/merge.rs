@@ -0,0 +1,423 @@
+//! Merging co-located shard rows.
+//!
+//! Redundant sensors on one machine give several rows with the same
+//! `machine_id` and `pollutant`; loaded as they are, each becomes a node
+//! and the corridor counts the machine's mass and karma more than once.
+//! [`merge_colocated`] folds each such group into one row:
+//!
+//! * `cin` and `cout` are the period-weighted means of the group's rows;
+//! * `period_s` is the length of the union of the rows' windows, so
+//!   overlapping windows count once and disjoint or adjacent ones add up;
+//! * every other field, airflow included, comes from the group's first row,
+//!   and the merged row takes that row's place in the output.
+//!
+//! A row's window starts at its `window_start_unix_ms` and lasts
+//! `period_s`. Rows without a start are taken to cover the same window, so
+//! a group of them is the same measurement from several sensors. A group
+//! cannot be merged if its units differ, its airflows differ by more than
+//! [`MergeOptions::airflow_tolerance`], or only some of its rows give a
+//! window start; every such conflict is reported together.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::loader::ShardRow;
+use crate::CorridorRow;
+
+/// A row `merge_colocated` can fold into its co-located duplicates.
+pub trait MergeRow: Clone {
+    fn corridor_fields(&self) -> MergeFields<'_>;
+    /// Start of the row's measurement window, if the shard gives one.
+    fn window_start_unix_ms(&self) -> Option<u64>;
+    /// Overwrite the merged quantities.
+    fn set_merged(&mut self, cin: f64, cout: f64, period_s: f64, window_start_unix_ms: Option<u64>);
+}
+
+/// The fields merging reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeFields<'a> {
+    pub machine_id: &'a str,
+    pub pollutant: &'a str,
+    pub unit: &'a str,
+    pub cin: f64,
+    pub cout: f64,
+    pub airflow_m3_per_s: f64,
+    pub period_s: f64,
+}
+
+impl MergeRow for CorridorRow {
+    fn corridor_fields(&self) -> MergeFields<'_> {
+        MergeFields {
+            machine_id: &self.machine_id,
+            pollutant: &self.pollutant,
+            unit: &self.unit,
+            cin: self.cin,
+            cout: self.cout,
+            airflow_m3_per_s: self.airflow_m3_per_s,
+            period_s: self.period_s,
+        }
+    }
+
+    fn window_start_unix_ms(&self) -> Option<u64> {
+        None
+    }
+
+    fn set_merged(&mut self, cin: f64, cout: f64, period_s: f64, _: Option<u64>) {
+        self.cin = cin;
+        self.cout = cout;
+        self.period_s = period_s;
+    }
+}
+
+impl MergeRow for ShardRow {
+    fn corridor_fields(&self) -> MergeFields<'_> {
+        self.row.corridor_fields()
+    }
+
+    fn window_start_unix_ms(&self) -> Option<u64> {
+        self.window_start_unix_ms
+    }
+
+    fn set_merged(&mut self, cin: f64, cout: f64, period_s: f64, start: Option<u64>) {
+        self.row.set_merged(cin, cout, period_s, start);
+        self.window_start_unix_ms = start;
+    }
+}
+
+/// Tolerances for `merge_colocated`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeOptions {
+    /// Largest relative airflow difference, against the group's first row,
+    /// that still merges.
+    pub airflow_tolerance: f64,
+}
+
+impl Default for MergeOptions {
+    /// 5 % airflow tolerance.
+    fn default() -> Self {
+        MergeOptions {
+            airflow_tolerance: 0.05,
+        }
+    }
+}
+
+/// One group of rows folded into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedGroup {
+    pub machine_id: String,
+    pub pollutant: String,
+    /// Positions of the merged rows in the input, ascending.
+    pub rows: Vec<usize>,
+    /// True if any two of the rows' windows overlap.
+    pub overlapping: bool,
+    /// `period_s` of the merged row.
+    pub period_s: f64,
+}
+
+/// What `merge_colocated` combined; groups of one row are not listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    pub groups: Vec<MergedGroup>,
+}
+
+impl MergeReport {
+    /// Rows removed by merging.
+    pub fn rows_removed(&self) -> usize {
+        self.groups.iter().map(|g| g.rows.len() - 1).sum()
+    }
+}
+
+/// Why a group of co-located rows could not be merged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    UnitMismatch {
+        machine_id: String,
+        pollutant: String,
+        units: Vec<String>,
+    },
+    AirflowMismatch {
+        machine_id: String,
+        pollutant: String,
+        airflow_m3_per_s: Vec<f64>,
+        tolerance: f64,
+    },
+    /// Some rows give a window start and some do not.
+    PartialWindows {
+        machine_id: String,
+        pollutant: String,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeConflict::UnitMismatch {
+                machine_id,
+                pollutant,
+                units,
+            } => write!(f, "{machine_id}/{pollutant}: units differ: {}", units.join(", ")),
+            MergeConflict::AirflowMismatch {
+                machine_id,
+                pollutant,
+                airflow_m3_per_s,
+                tolerance,
+            } => write!(
+                f,
+                "{machine_id}/{pollutant}: airflows {airflow_m3_per_s:?} m^3/s differ by more than {tolerance}"
+            ),
+            MergeConflict::PartialWindows {
+                machine_id,
+                pollutant,
+            } => write!(
+                f,
+                "{machine_id}/{pollutant}: only some rows give window_start_unix_ms"
+            ),
+        }
+    }
+}
+
+/// Every group `merge_colocated` could not merge.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{} co-located row conflicts: {}", .conflicts.len(), .conflicts.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct MergeError {
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Fold rows sharing `machine_id` and `pollutant`; see the module docs.
+pub fn merge_colocated<T: MergeRow>(
+    rows: Vec<T>,
+    options: &MergeOptions,
+) -> Result<(Vec<T>, MergeReport), MergeError> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_key: HashMap<(&str, &str), usize> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let f = row.corridor_fields();
+        let g = *by_key
+            .entry((f.machine_id, f.pollutant))
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[g].push(i);
+    }
+
+    let mut conflicts = Vec::new();
+    let mut report = MergeReport::default();
+    // (first row, cin, cout, period_s, window start) per merged group.
+    let mut merged = Vec::new();
+    for group in groups.into_iter().filter(|g| g.len() > 1) {
+        let members: Vec<&T> = group.iter().map(|&i| &rows[i]).collect();
+        let first = members[0].corridor_fields();
+        let machine_id = first.machine_id.to_string();
+        let pollutant = first.pollutant.to_string();
+        let fields: Vec<MergeFields> = members.iter().map(|r| r.corridor_fields()).collect();
+
+        let mut ok = true;
+        if fields.iter().any(|f| f.unit != first.unit) {
+            conflicts.push(MergeConflict::UnitMismatch {
+                machine_id: machine_id.clone(),
+                pollutant: pollutant.clone(),
+                units: fields.iter().map(|f| f.unit.to_string()).collect(),
+            });
+            ok = false;
+        }
+        let airflow_ok = |f: &MergeFields| {
+            (f.airflow_m3_per_s - first.airflow_m3_per_s).abs()
+                <= options.airflow_tolerance * first.airflow_m3_per_s.abs()
+        };
+        if !fields.iter().all(airflow_ok) {
+            conflicts.push(MergeConflict::AirflowMismatch {
+                machine_id: machine_id.clone(),
+                pollutant: pollutant.clone(),
+                airflow_m3_per_s: fields.iter().map(|f| f.airflow_m3_per_s).collect(),
+                tolerance: options.airflow_tolerance,
+            });
+            ok = false;
+        }
+        let starts: Vec<Option<u64>> = members.iter().map(|r| r.window_start_unix_ms()).collect();
+        let windowed = starts.iter().filter(|s| s.is_some()).count();
+        if windowed != 0 && windowed != starts.len() {
+            conflicts.push(MergeConflict::PartialWindows {
+                machine_id: machine_id.clone(),
+                pollutant: pollutant.clone(),
+            });
+            ok = false;
+        }
+        if !ok {
+            continue;
+        }
+
+        // Windows in seconds from the earliest start (0 for all if unknown).
+        let origin = starts.iter().flatten().min().copied();
+        let mut windows: Vec<(f64, f64)> = fields
+            .iter()
+            .zip(&starts)
+            .map(|(f, s)| {
+                let start = s.zip(origin).map_or(0.0, |(s, o)| (s - o) as f64 / 1000.0);
+                (start, start + f.period_s.max(0.0))
+            })
+            .collect();
+        windows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (mut union, mut overlapping) = (0.0, false);
+        let mut current: Option<(f64, f64)> = None;
+        for (start, end) in windows {
+            current = match current {
+                Some((s, e)) if start < e => {
+                    overlapping = true;
+                    Some((s, e.max(end)))
+                }
+                Some((s, e)) => {
+                    union += e - s;
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+        if let Some((s, e)) = current {
+            union += e - s;
+        }
+
+        let weight: f64 = fields.iter().map(|f| f.period_s).sum();
+        let mean = |value: fn(&MergeFields) -> f64| {
+            if weight > 0.0 {
+                fields.iter().map(|f| value(f) * f.period_s).sum::<f64>() / weight
+            } else {
+                fields.iter().map(value).sum::<f64>() / fields.len() as f64
+            }
+        };
+        merged.push((group[0], mean(|f| f.cin), mean(|f| f.cout), union, origin));
+        report.groups.push(MergedGroup {
+            machine_id,
+            pollutant,
+            rows: group,
+            overlapping,
+            period_s: union,
+        });
+    }
+    if !conflicts.is_empty() {
+        return Err(MergeError { conflicts });
+    }
+
+    let mut drop = vec![false; rows.len()];
+    for g in &report.groups {
+        for &i in &g.rows[1..] {
+            drop[i] = true;
+        }
+    }
+    let mut out: Vec<T> = rows;
+    for (first, cin, cout, period_s, start) in merged {
+        out[first].set_merged(cin, cout, period_s, start);
+    }
+    let mut i = 0;
+    out.retain(|_| {
+        i += 1;
+        !drop[i - 1]
+    });
+    Ok((out, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    fn shard_row(cin: f64, cout: f64, start: Option<u64>) -> ShardRow {
+        let mut row = phoenix_nodes()[0].row.clone();
+        row.cin = cin;
+        row.cout = cout;
+        row.period_s = 3600.0;
+        ShardRow {
+            row,
+            bee: None,
+            calibrated: false,
+            window_start_unix_ms: start,
+        }
+    }
+
+    fn school() -> ShardRow {
+        ShardRow {
+            row: phoenix_nodes()[1].row.clone(),
+            bee: None,
+            calibrated: false,
+            window_start_unix_ms: None,
+        }
+    }
+
+    #[test]
+    fn overlapping_duplicates_average_over_one_window() {
+        let rows = vec![
+            shard_row(40.0, 28.0, Some(0)),
+            school(),
+            shard_row(44.0, 30.0, Some(HOUR_MS / 2)),
+        ];
+        let (rows, report) = merge_colocated(rows, &MergeOptions::default()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].row.machine_id, "CYB-AIR-SCHOOL-05");
+        let merged = &rows[0];
+        assert_eq!((merged.row.cin, merged.row.cout), (42.0, 29.0));
+        assert_eq!(merged.row.period_s, 5400.0);
+        assert_eq!(merged.window_start_unix_ms, Some(0));
+        assert_eq!(report.rows_removed(), 1);
+        assert_eq!(report.groups[0].rows, [0, 2]);
+        assert!(report.groups[0].overlapping);
+
+        // Redundant sensors without window starts read the same window.
+        let rows = vec![shard_row(40.0, 28.0, None), shard_row(44.0, 30.0, None)];
+        let (rows, report) = merge_colocated(rows, &MergeOptions::default()).unwrap();
+        assert_eq!(rows[0].row.period_s, 3600.0);
+        assert!(report.groups[0].overlapping);
+    }
+
+    #[test]
+    fn adjacent_windows_add_their_periods() {
+        let mut later = shard_row(30.0, 20.0, Some(HOUR_MS));
+        later.row.period_s = 1800.0;
+        let rows = vec![shard_row(42.0, 27.0, Some(0)), later];
+        let (rows, report) = merge_colocated(rows, &MergeOptions::default()).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row.period_s, 5400.0);
+        assert_eq!(rows[0].row.cin, (42.0 * 3600.0 + 30.0 * 1800.0) / 5400.0);
+        assert!(!report.groups[0].overlapping);
+
+        // Removed mass is unchanged by merging adjacent windows.
+        let removed = |r: &CorridorRow| (r.cin - r.cout) * r.period_s;
+        assert!((removed(&rows[0].row) - (15.0 * 3600.0 + 10.0 * 1800.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn conflicting_duplicates_are_all_reported() {
+        let mut ppb = shard_row(40.0, 28.0, Some(0));
+        ppb.row.unit = "ppb".into();
+        let mut other = school();
+        other.row.airflow_m3_per_s *= 1.5;
+        let rows = vec![
+            shard_row(40.0, 28.0, Some(0)),
+            ppb,
+            school(),
+            other,
+            school(),
+        ];
+        let err = merge_colocated(rows, &MergeOptions::default()).unwrap_err();
+        assert_eq!(err.conflicts.len(), 2);
+        assert!(matches!(
+            &err.conflicts[0],
+            MergeConflict::UnitMismatch { units, .. } if units == &["ugm3", "ppb"]
+        ));
+        assert!(matches!(
+            &err.conflicts[1],
+            MergeConflict::AirflowMismatch { machine_id, .. } if machine_id == "CYB-AIR-SCHOOL-05"
+        ));
+
+        let rows = vec![shard_row(40.0, 28.0, Some(0)), shard_row(40.0, 28.0, None)];
+        assert!(matches!(
+            merge_colocated(rows, &MergeOptions::default())
+                .unwrap_err()
+                .conflicts[..],
+            [MergeConflict::PartialWindows { .. }]
+        ));
+    }
+}
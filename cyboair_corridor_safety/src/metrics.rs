@@ -0,0 +1,268 @@
+//! Prometheus export of controller state (feature `metrics`).
+//!
+//! [`PrometheusRecorder`] is a [`MetricsRecorder`]: attach it with
+//! `CorridorController::with_metrics` and serve [`PrometheusRecorder::render`]
+//! from an HTTP handler. Per-node series are labelled by `machine_id` and
+//! `pollutant`; past `max_machine_ids` distinct machines, further nodes
+//! share the [`OVERFLOW_MACHINE_ID`] series so a misbehaving shard cannot
+//! blow up the scrape.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use prometheus::{
+    Encoder, Gauge, GaugeVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+use crate::{EcoBand, MetricsRecorder, NodeState, SafetyError, UpdateReport};
+
+/// `machine_id` label shared by nodes beyond the cardinality cap.
+pub const OVERFLOW_MACHINE_ID: &str = "_overflow";
+
+/// Prometheus gauges and counters fed by a corridor controller.
+#[derive(Clone)]
+pub struct PrometheusRecorder {
+    registry: Registry,
+    eco_load: Gauge,
+    eco_band: Gauge,
+    duty_cycle: GaugeVec,
+    mass_kg: GaugeVec,
+    karma_bytes: GaugeVec,
+    safety_errors: IntCounterVec,
+    bee_rights_clamped: IntCounter,
+    label_overflow: IntCounter,
+    max_machine_ids: usize,
+    machine_ids: Arc<Mutex<HashSet<String>>>,
+}
+
+impl fmt::Debug for PrometheusRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrometheusRecorder")
+            .field("max_machine_ids", &self.max_machine_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PrometheusRecorder {
+    /// Recorder with its own registry, labelling at most `max_machine_ids`
+    /// machines individually.
+    pub fn new(max_machine_ids: usize) -> Result<Self, prometheus::Error> {
+        Self::with_registry(Registry::new(), max_machine_ids)
+    }
+
+    /// Recorder registering into an existing `registry`.
+    pub fn with_registry(
+        registry: Registry,
+        max_machine_ids: usize,
+    ) -> Result<Self, prometheus::Error> {
+        let node_labels = &["machine_id", "pollutant"];
+        let eco_load = Gauge::new(
+            "cyboair_corridor_eco_load",
+            "Corridor eco-load from the last eco_load call (Equation 3)",
+        )?;
+        let eco_band = Gauge::new(
+            "cyboair_corridor_eco_band",
+            "Eco-band of the last duty update: 0 green, 1 amber, 2 red",
+        )?;
+        let duty_cycle = GaugeVec::new(
+            Opts::new(
+                "cyboair_node_duty_cycle",
+                "Duty cycle after the last update",
+            ),
+            node_labels,
+        )?;
+        let mass_kg = GaugeVec::new(
+            Opts::new(
+                "cyboair_node_mass_removed_kg",
+                "CEIM mass removed per period at the last update",
+            ),
+            node_labels,
+        )?;
+        let karma_bytes = GaugeVec::new(
+            Opts::new("cyboair_node_karma_bytes", "Node karma at the last update"),
+            node_labels,
+        )?;
+        let safety_errors = IntCounterVec::new(
+            Opts::new(
+                "cyboair_safety_errors_total",
+                "Failed controller checks by SafetyError kind",
+            ),
+            &["kind"],
+        )?;
+        let bee_rights_clamped = IntCounter::new(
+            "cyboair_bee_rights_clamped_total",
+            "Duty updates overridden by the bee guard",
+        )?;
+        let label_overflow = IntCounter::new(
+            "cyboair_metrics_label_overflow_total",
+            "Node updates recorded under the overflow machine_id",
+        )?;
+
+        registry.register(Box::new(eco_load.clone()))?;
+        registry.register(Box::new(eco_band.clone()))?;
+        registry.register(Box::new(duty_cycle.clone()))?;
+        registry.register(Box::new(mass_kg.clone()))?;
+        registry.register(Box::new(karma_bytes.clone()))?;
+        registry.register(Box::new(safety_errors.clone()))?;
+        registry.register(Box::new(bee_rights_clamped.clone()))?;
+        registry.register(Box::new(label_overflow.clone()))?;
+        // Every kind is scraped from the start, at zero until it occurs.
+        for kind in SafetyError::KINDS {
+            safety_errors.with_label_values(&[kind]);
+        }
+
+        Ok(PrometheusRecorder {
+            registry,
+            eco_load,
+            eco_band,
+            duty_cycle,
+            mass_kg,
+            karma_bytes,
+            safety_errors,
+            bee_rights_clamped,
+            label_overflow,
+            max_machine_ids,
+            machine_ids: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        // Encoding into memory only fails on malformed metric families,
+        // which the constructors above rule out.
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("encode metrics");
+        String::from_utf8(buf).expect("text exposition is UTF-8")
+    }
+
+    /// Label to use for `machine_id`, admitting new machines up to the cap.
+    fn machine_label<'a>(&self, machine_id: &'a str) -> &'a str {
+        let mut seen = self.machine_ids.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.contains(machine_id) {
+            return machine_id;
+        }
+        if seen.len() < self.max_machine_ids {
+            seen.insert(machine_id.to_string());
+            return machine_id;
+        }
+        self.label_overflow.inc();
+        OVERFLOW_MACHINE_ID
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn record_eco_load(&self, eco_load: f64) {
+        self.eco_load.set(eco_load);
+    }
+
+    fn record_update(&self, node: &NodeState, report: &UpdateReport) {
+        self.eco_band.set(match report.eco_band {
+            EcoBand::Green => 0.0,
+            EcoBand::Amber => 1.0,
+            EcoBand::Red => 2.0,
+        });
+        let labels = [
+            self.machine_label(&node.row.machine_id),
+            node.row.pollutant.as_str(),
+        ];
+        self.duty_cycle
+            .with_label_values(&labels)
+            .set(report.duty_after);
+        self.mass_kg.with_label_values(&labels).set(node.mass_kg);
+        self.karma_bytes
+            .with_label_values(&labels)
+            .set(node.karma_bytes);
+        if report.bee_rights_clamped.is_some() {
+            self.bee_rights_clamped.inc();
+        }
+    }
+
+    fn record_error(&self, error: &SafetyError) {
+        self.safety_errors.with_label_values(&[error.kind()]).inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::StepInput;
+
+    fn step(_: usize) -> StepInput {
+        StepInput {
+            phi_dw: 5.0e-7,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        }
+    }
+
+    #[test]
+    fn scrape_has_corridor_node_and_error_series_after_a_step() {
+        let recorder = PrometheusRecorder::new(100).unwrap();
+        let controller = phoenix_controller().with_metrics(recorder.clone());
+        let mut nodes = phoenix_nodes();
+        nodes[1].row.ecoimpact_score = 0.1; // outside the envelope
+
+        let log = controller.run_steps(&mut nodes, 1, step).unwrap();
+        let text = recorder.render();
+        let has = |prefix: &str| text.lines().any(|l| l.starts_with(prefix));
+
+        assert!(has(&format!(
+            "cyboair_corridor_eco_load {}",
+            log[0].eco_load
+        )));
+        assert!(has("cyboair_corridor_eco_band 2"));
+        assert!(has(&format!(
+            "cyboair_node_duty_cycle{{machine_id=\"CYB-AIR-CANOPY-01\",pollutant=\"PM2.5\"}} {}",
+            nodes[0].duty_cycle
+        )));
+        assert!(has(
+            "cyboair_node_mass_removed_kg{machine_id=\"CYB-AIR-CANOPY-01\",pollutant=\"PM2.5\"}"
+        ));
+        assert!(has(
+            "cyboair_node_karma_bytes{machine_id=\"CYB-AIR-CANOPY-01\",pollutant=\"PM2.5\"}"
+        ));
+        // The rejected node never gets a duty series, only an error count.
+        assert!(!text.contains("CYB-AIR-SCHOOL-05"));
+        assert!(has(
+            "cyboair_safety_errors_total{kind=\"envelope_violation\"} 1"
+        ));
+        assert!(has(
+            "cyboair_safety_errors_total{kind=\"battery_reserve\"} 0"
+        ));
+        assert!(has("cyboair_metrics_label_overflow_total 0"));
+    }
+
+    #[test]
+    fn machines_past_the_cap_share_the_overflow_series() {
+        let recorder = PrometheusRecorder::new(1).unwrap();
+        let controller = phoenix_controller().with_metrics(recorder.clone());
+        let mut nodes = phoenix_nodes();
+        controller.run_steps(&mut nodes, 2, step).unwrap();
+
+        let text = recorder.render();
+        assert!(text.contains("machine_id=\"CYB-AIR-CANOPY-01\""));
+        assert!(!text.contains("CYB-AIR-SCHOOL-05"));
+        assert!(text.contains(&format!("machine_id=\"{OVERFLOW_MACHINE_ID}\"")));
+        // One overflowed update per step.
+        assert!(text
+            .lines()
+            .any(|l| l == "cyboair_metrics_label_overflow_total 2"));
+    }
+
+    #[test]
+    fn every_error_kind_is_listed() {
+        let err = SafetyError::InvalidEcoOffset { offset: -1.0 };
+        assert!(SafetyError::KINDS.contains(&err.kind()));
+        assert_eq!(SafetyError::KINDS.len(), 20);
+    }
+}
@@ -0,0 +1,212 @@
+//! Operational mode of a node: whether the controller may actuate it.
+//!
+//! A node out of [`OperationalMode::Active`] keeps reporting telemetry, and
+//! its measured mass and karma still count toward the corridor, but the
+//! duty law does not run for it: its duty is pinned to 0 and the envelope
+//! and host-budget checks, which only concern actuation, are skipped.
+//! Entering a restricted mode is always allowed; leaving one needs an
+//! [`ExitAuthorizer`] to agree.
+//!
+//! [`OperationalMode::Quarantined`] goes further and also drops the node
+//! from eco-load aggregation; see [`crate::quarantine`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope, SafetyError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum OperationalMode {
+    #[default]
+    Active,
+    /// Telemetry only; actuation is off.
+    SensingOnly {
+        /// Unix milliseconds when the node entered the mode.
+        since_ms: u64,
+        reason: String,
+    },
+    /// Taken out of service by an operator.
+    Disabled,
+    /// Sensing-only after failing `check` on too many consecutive steps.
+    /// A node that fails the same check every tick has at least one bad
+    /// sensor, and nothing says which: its mass and karma are as suspect
+    /// as the reading that tripped the check, so they are left out of the
+    /// corridor's eco-load rather than let one broken node move the band.
+    Quarantined {
+        /// Runtime step on which the node was quarantined.
+        since_step: u64,
+        /// [`SafetyError::kind`] of the failing check.
+        check: String,
+    },
+}
+
+impl OperationalMode {
+    pub fn is_active(&self) -> bool {
+        matches!(self, OperationalMode::Active)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationalMode::Active => "active",
+            OperationalMode::SensingOnly { .. } => "sensing_only",
+            OperationalMode::Disabled => "disabled",
+            OperationalMode::Quarantined { .. } => "quarantined",
+        }
+    }
+
+    /// Whether the node's mass and karma count toward the eco-load.
+    pub fn counts_toward_load(&self) -> bool {
+        !matches!(self, OperationalMode::Quarantined { .. })
+    }
+}
+
+/// Governance decision on returning a node to `Active`.
+pub trait ExitAuthorizer {
+    /// Whether `machine_id` may leave `mode`.
+    fn authorize_exit(&self, machine_id: &str, mode: &OperationalMode) -> bool;
+}
+
+impl<F: Fn(&str, &OperationalMode) -> bool> ExitAuthorizer for F {
+    fn authorize_exit(&self, machine_id: &str, mode: &OperationalMode) -> bool {
+        self(machine_id, mode)
+    }
+}
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// Put an `Active` node into sensing-only mode and pin its duty to 0.
+    /// Returns false, changing nothing, if the node is already out of
+    /// `Active`: a disabled node stays disabled, and a sensing-only one
+    /// keeps its original `since_ms` and reason.
+    pub fn enter_sensing_only(
+        &self,
+        node: &mut NodeState,
+        since_ms: u64,
+        reason: impl Into<String>,
+    ) -> bool {
+        if !node.mode.is_active() {
+            return false;
+        }
+        node.mode = OperationalMode::SensingOnly {
+            since_ms,
+            reason: reason.into(),
+        };
+        node.duty_cycle = 0.0;
+        true
+    }
+
+    /// Take `node` out of service and pin its duty to 0.
+    pub fn disable_node(&self, node: &mut NodeState) {
+        node.mode = OperationalMode::Disabled;
+        node.duty_cycle = 0.0;
+    }
+
+    /// Return `node` to `Active` if `authorizer` agrees. The duty stays at
+    /// 0 until the next update. A node already `Active` is left alone.
+    pub fn exit_mode(
+        &self,
+        node: &mut NodeState,
+        authorizer: &impl ExitAuthorizer,
+    ) -> Result<(), SafetyError> {
+        if node.mode.is_active() {
+            return Ok(());
+        }
+        if !authorizer.authorize_exit(&node.row.machine_id, &node.mode) {
+            return Err(SafetyError::UnauthorizedModeExit {
+                machine_id: node.row.machine_id.clone(),
+                mode: node.mode.clone(),
+            });
+        }
+        node.mode = OperationalMode::Active;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{checked_karma_bytes, checked_mass_kg, EcoBand, Pollutant, StepInput};
+
+    fn input(_: usize) -> StepInput {
+        StepInput {
+            phi_dw: 5.0e-7,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        }
+    }
+
+    #[test]
+    fn sensing_only_nodes_keep_reporting_at_zero_duty() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        // Far over the host budget: would fail the check if actuated.
+        nodes[0].power_w = 1.0e6;
+        assert!(controller.enter_sensing_only(&mut nodes[0], 1_000, "bee EMF overload"));
+        assert!(!controller.enter_sensing_only(&mut nodes[0], 2_000, "again"));
+        assert_eq!(
+            nodes[0].mode,
+            OperationalMode::SensingOnly {
+                since_ms: 1_000,
+                reason: "bee EMF overload".into()
+            }
+        );
+
+        for step in 0..5 {
+            // New telemetry still lands on the node.
+            nodes[0].row.cin = 40.0 + f64::from(step);
+            nodes[0].mass_kg = checked_mass_kg(&nodes[0].row, Pollutant::PM25, 310.0).unwrap();
+            nodes[0].karma_bytes = checked_karma_bytes(&nodes[0].row, nodes[0].mass_kg).unwrap();
+            let log = controller.run_steps(&mut nodes, 1, input).unwrap();
+            let record = &log[0].nodes[0];
+            assert!(record.error.is_none(), "{:?}", record.error);
+            assert_eq!(record.duty_cycle, 0.0);
+            assert_eq!(record.mass_kg, nodes[0].mass_kg);
+            assert!(log[0].nodes[1].duty_cycle > 0.0);
+        }
+        let report = controller
+            .update_node_duty(&mut nodes[0], EcoBand::Red, 5.0e-7)
+            .unwrap();
+        assert_eq!((report.duty_before, report.duty_after), (0.0, 0.0));
+
+        let assessment = controller.dry_run_node(&nodes[0], EcoBand::Red, 5.0e-7);
+        assert!(assessment.violations.is_empty(), "{:?}", assessment);
+        assert_eq!(assessment.projected.unwrap().duty_after, 0.0);
+    }
+
+    #[test]
+    fn leaving_a_mode_needs_authorization() {
+        let controller = phoenix_controller();
+        let mut node = phoenix_nodes().remove(0);
+        controller.disable_node(&mut node);
+        assert!(!controller.enter_sensing_only(&mut node, 0, "downgrade"));
+
+        let refuse = |_: &str, _: &OperationalMode| false;
+        let err = controller.exit_mode(&mut node, &refuse).unwrap_err();
+        assert_eq!(err.kind(), "unauthorized_mode_exit");
+        assert_eq!(node.mode, OperationalMode::Disabled);
+
+        let granted = |id: &str, mode: &OperationalMode| {
+            id == "CYB-AIR-CANOPY-01" && *mode == OperationalMode::Disabled
+        };
+        controller.exit_mode(&mut node, &granted).unwrap();
+        assert!(node.mode.is_active());
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Amber, 5.0e-7)
+            .unwrap();
+        assert!(report.duty_after > 0.0);
+    }
+}
@@ -0,0 +1,454 @@
+//! MQTT publishing of per-node telemetry, so site brokers receive the
+//! controller's output directly instead of through a stdout relay.
+//!
+//! Messages go through a bounded outbox that is only drained while the
+//! broker connection is up. Publishing never blocks the control loop: when
+//! the outbox is full the oldest message is dropped and counted.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::replay::{TraceNode, TraceStep};
+use crate::EcoBand;
+
+/// Broker connection and topic layout, loadable from TOML or JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttConfig {
+    /// `mqtt://host[:port]` or `tcp://host[:port]`; the port defaults to
+    /// 1883.
+    pub broker_url: String,
+    /// Also keys the broker session, so keep it stable across restarts.
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Telemetry goes to `{topic_prefix}/{machine_id}/telemetry`.
+    pub topic_prefix: String,
+    /// Messages held while the broker is unreachable.
+    #[serde(default = "default_outbox_capacity")]
+    pub outbox_capacity: usize,
+    #[serde(default = "default_keep_alive_s")]
+    pub keep_alive_s: u64,
+}
+
+fn default_outbox_capacity() -> usize {
+    256
+}
+
+fn default_keep_alive_s() -> u64 {
+    30
+}
+
+/// Errors while setting up the MQTT publisher.
+#[derive(Debug, Error)]
+pub enum MqttError {
+    #[error("invalid broker url {url:?}: {message}")]
+    InvalidBrokerUrl { url: String, message: String },
+    #[error("password given without a username")]
+    PasswordWithoutUsername,
+}
+
+impl MqttConfig {
+    /// `{topic_prefix}/{client_id}/status`: retained `online` while
+    /// connected, `offline` (the last will) once the publisher is gone.
+    pub fn status_topic(&self) -> String {
+        format!("{}/{}/status", self.topic_prefix, self.client_id)
+    }
+
+    /// Connection options with credentials, keep-alive and the last will.
+    /// The session is persistent so QoS 1 messages in flight survive a
+    /// reconnect.
+    pub fn options(&self) -> Result<MqttOptions, MqttError> {
+        let (host, port) = parse_broker_url(&self.broker_url)?;
+        let mut options = MqttOptions::new(self.client_id.clone(), host, port);
+        options
+            .set_keep_alive(Duration::from_secs(self.keep_alive_s.max(5)))
+            .set_clean_session(false)
+            .set_last_will(LastWill::new(
+                self.status_topic(),
+                "offline",
+                QoS::AtLeastOnce,
+                true,
+            ));
+        match (&self.username, &self.password) {
+            (Some(user), password) => {
+                options.set_credentials(user.clone(), password.clone().unwrap_or_default());
+            }
+            (None, Some(_)) => return Err(MqttError::PasswordWithoutUsername),
+            (None, None) => {}
+        }
+        Ok(options)
+    }
+}
+
+fn parse_broker_url(url: &str) -> Result<(String, u16), MqttError> {
+    let invalid = |message: &str| MqttError::InvalidBrokerUrl {
+        url: url.to_string(),
+        message: message.to_string(),
+    };
+    let rest = url
+        .strip_prefix("mqtt://")
+        .or_else(|| url.strip_prefix("tcp://"))
+        .ok_or_else(|| invalid("scheme must be mqtt:// or tcp://"))?;
+    let authority = rest.trim_end_matches('/');
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse().map_err(|_| invalid("port is not a number"))?,
+        ),
+        None => (authority, 1883),
+    };
+    if host.is_empty() || host.contains('/') {
+        return Err(invalid("missing host"));
+    }
+    Ok((host.to_string(), port))
+}
+
+/// Topic and payload waiting to be handed to the transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// Transport behind a `TelemetryPublisher`. Messages are published
+/// retained at QoS 1.
+pub trait TelemetrySink {
+    /// Whether messages handed over now will reach the broker.
+    fn is_connected(&self) -> bool;
+    /// Take `message` without blocking; false if it cannot right now.
+    fn try_send(&mut self, message: &OutboxMessage) -> bool;
+    /// Release the connection once the outbox is drained.
+    fn close(&mut self) {}
+}
+
+/// One node's telemetry for one controller step.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTelemetry<'a> {
+    pub step: usize,
+    pub band: EcoBand,
+    pub eco_load: f64,
+    #[serde(flatten)]
+    pub node: &'a TraceNode,
+}
+
+/// Publishes one retained message per node through a bounded outbox.
+#[derive(Debug)]
+pub struct TelemetryPublisher<S> {
+    sink: S,
+    topic_prefix: String,
+    capacity: usize,
+    outbox: VecDeque<OutboxMessage>,
+    dropped: u64,
+}
+
+impl<S: TelemetrySink> TelemetryPublisher<S> {
+    /// An outbox of `capacity` messages (at least one).
+    pub fn new(sink: S, topic_prefix: impl Into<String>, capacity: usize) -> Self {
+        TelemetryPublisher {
+            sink,
+            topic_prefix: topic_prefix.into(),
+            capacity: capacity.max(1),
+            outbox: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn telemetry_topic(&self, machine_id: &str) -> String {
+        format!("{}/{}/telemetry", self.topic_prefix, machine_id)
+    }
+
+    /// Queue `payload` as JSON for `machine_id` and send what the
+    /// transport will take.
+    pub fn publish<T: Serialize>(
+        &mut self,
+        machine_id: &str,
+        payload: &T,
+    ) -> Result<(), serde_json::Error> {
+        let message = OutboxMessage {
+            topic: self.telemetry_topic(machine_id),
+            payload: serde_json::to_vec(payload)?,
+        };
+        if self.outbox.len() >= self.capacity {
+            self.outbox.pop_front();
+            self.dropped += 1;
+        }
+        self.outbox.push_back(message);
+        self.flush();
+        Ok(())
+    }
+
+    /// Publish every node of `step` as a `NodeTelemetry`.
+    pub fn publish_step(&mut self, step: &TraceStep) -> Result<(), serde_json::Error> {
+        for node in &step.nodes {
+            let telemetry = NodeTelemetry {
+                step: step.step,
+                band: step.band,
+                eco_load: step.eco_load,
+                node,
+            };
+            self.publish(&node.machine_id, &telemetry)?;
+        }
+        Ok(())
+    }
+
+    /// Hand queued messages to the transport, oldest first, while it is
+    /// connected and accepting. Returns how many were sent.
+    pub fn flush(&mut self) -> usize {
+        let mut sent = 0;
+        while self.sink.is_connected() {
+            let Some(message) = self.outbox.front() else {
+                break;
+            };
+            if !self.sink.try_send(message) {
+                break;
+            }
+            self.outbox.pop_front();
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Messages waiting for the broker.
+    pub fn pending(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Messages dropped because the outbox was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Keep flushing for up to `timeout`, then close the transport.
+    /// Returns the messages still unsent.
+    pub fn close(mut self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        while !self.outbox.is_empty() && Instant::now() < deadline {
+            if self.flush() == 0 {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        self.sink.close();
+        self.outbox.len()
+    }
+}
+
+/// `TelemetrySink` over a rumqttc client whose event loop runs on its own
+/// thread.
+pub struct RumqttSink {
+    client: Client,
+    status_topic: String,
+    connected: Arc<AtomicBool>,
+    stopping: Arc<AtomicBool>,
+    event_loop: Option<JoinHandle<()>>,
+}
+
+impl TelemetrySink for RumqttSink {
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    fn try_send(&mut self, message: &OutboxMessage) -> bool {
+        self.client
+            .try_publish(
+                message.topic.as_str(),
+                QoS::AtLeastOnce,
+                true,
+                message.payload.clone(),
+            )
+            .is_ok()
+    }
+
+    fn close(&mut self) {
+        let _ = self.client.try_publish(
+            self.status_topic.as_str(),
+            QoS::AtLeastOnce,
+            true,
+            "offline",
+        );
+        self.stopping.store(true, Ordering::Relaxed);
+        let _ = self.client.try_disconnect();
+        if let Some(event_loop) = self.event_loop.take() {
+            let _ = event_loop.join();
+        }
+    }
+}
+
+/// Connect to the broker in `config` on a background thread. The thread
+/// reconnects after failures, marks the publisher `online` on each
+/// connect, and ends once the publisher is closed.
+pub fn connect(config: &MqttConfig) -> Result<TelemetryPublisher<RumqttSink>, MqttError> {
+    let options = config.options()?;
+    // Small request channel: the outbox, not rumqttc, holds the backlog.
+    let (client, mut connection) = Client::new(options, 16);
+    let connected = Arc::new(AtomicBool::new(false));
+    let stopping = Arc::new(AtomicBool::new(false));
+    let status_topic = config.status_topic();
+
+    let event_loop = {
+        let client = client.clone();
+        let connected = Arc::clone(&connected);
+        let stopping = Arc::clone(&stopping);
+        let status_topic = status_topic.clone();
+        thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        connected.store(true, Ordering::Relaxed);
+                        let _ = client.try_publish(
+                            status_topic.as_str(),
+                            QoS::AtLeastOnce,
+                            true,
+                            "online",
+                        );
+                    }
+                    Ok(Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    // Unreachable broker at shutdown: nothing left to send.
+                    Err(_) if stopping.load(Ordering::Relaxed) => break,
+                    Err(_) => {
+                        connected.store(false, Ordering::Relaxed);
+                        thread::sleep(Duration::from_secs(1));
+                    }
+                }
+            }
+            connected.store(false, Ordering::Relaxed);
+        })
+    };
+
+    let sink = RumqttSink {
+        client,
+        status_topic,
+        connected,
+        stopping,
+        event_loop: Some(event_loop),
+    };
+    Ok(TelemetryPublisher::new(
+        sink,
+        config.topic_prefix.clone(),
+        config.outbox_capacity,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts up to `budget` messages while connected.
+    #[derive(Default)]
+    struct MockSink {
+        connected: bool,
+        budget: usize,
+        sent: Vec<OutboxMessage>,
+    }
+
+    impl TelemetrySink for MockSink {
+        fn is_connected(&self) -> bool {
+            self.connected
+        }
+
+        fn try_send(&mut self, message: &OutboxMessage) -> bool {
+            if self.budget == 0 {
+                return false;
+            }
+            self.budget -= 1;
+            self.sent.push(message.clone());
+            true
+        }
+    }
+
+    fn step(step: usize, duty_cycle: f64) -> TraceStep {
+        TraceStep {
+            step,
+            eco_load: 0.4,
+            eco_offset_applied: 0.0,
+            band: EcoBand::Amber,
+            nodes: vec![TraceNode {
+                machine_id: "CYB-AIR-CANOPY-01".into(),
+                duty_cycle,
+                mass_kg: 1.0e-6,
+                karma_bytes: 5.0e2,
+                error: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn outbox_drops_oldest_while_disconnected_and_drains_on_reconnect() {
+        let mut publisher = TelemetryPublisher::new(MockSink::default(), "phx/corridor", 3);
+        for i in 0..5 {
+            publisher.publish_step(&step(i, 0.1 * i as f64)).unwrap();
+        }
+        assert_eq!(publisher.pending(), 3);
+        assert_eq!(publisher.dropped(), 2);
+
+        publisher.sink.connected = true;
+        publisher.sink.budget = 2;
+        assert_eq!(publisher.flush(), 2);
+        assert_eq!(publisher.pending(), 1);
+        publisher.sink.budget = 10;
+        assert_eq!(publisher.flush(), 1);
+
+        let sent = &publisher.sink().sent;
+        assert!(sent
+            .iter()
+            .all(|m| m.topic == "phx/corridor/CYB-AIR-CANOPY-01/telemetry"));
+        let steps: Vec<_> = sent
+            .iter()
+            .map(|m| {
+                serde_json::from_slice::<serde_json::Value>(&m.payload).unwrap()["step"].clone()
+            })
+            .collect();
+        assert_eq!(steps, [2, 3, 4]);
+        let last: serde_json::Value = serde_json::from_slice(&sent[2].payload).unwrap();
+        assert_eq!(last["band"], "Amber");
+        assert_eq!(last["machine_id"], "CYB-AIR-CANOPY-01");
+    }
+
+    #[test]
+    fn broker_config_builds_options_with_last_will() {
+        let config: MqttConfig = toml::from_str(
+            r#"
+            broker_url = "mqtt://broker.site.local:8883"
+            client_id = "corridor-phx"
+            username = "cyboair"
+            password = "secret"
+            topic_prefix = "phx/corridor"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.outbox_capacity, 256);
+        let options = config.options().unwrap();
+        assert_eq!(
+            options.broker_address(),
+            ("broker.site.local".to_string(), 8883)
+        );
+        assert_eq!(
+            options.credentials(),
+            Some(("cyboair".to_string(), "secret".to_string()))
+        );
+        let will = options.last_will().unwrap();
+        assert_eq!(will.topic, "phx/corridor/corridor-phx/status");
+        assert_eq!(&will.message[..], b"offline");
+        assert!(will.retain);
+
+        assert_eq!(
+            parse_broker_url("tcp://10.0.0.5").unwrap(),
+            ("10.0.0.5".to_string(), 1883)
+        );
+        assert!(parse_broker_url("http://broker").is_err());
+        assert!(parse_broker_url("mqtt://:1883").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use rayon::prelude::*;
+
+use crate::{
+    check_node_finite, BeeGuard, CorridorController, DwCeilingInvariant, EcoBand,
+    EcoBandClassifier, HostBudget, MetricsRecorder, NodeState, SafetyEnvelope, SafetyError,
+    UpdateReport,
+};
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope + Sync,
+    H: HostBudget + Sync,
+    B: EcoBandClassifier + Sync,
+    D: DwCeilingInvariant + Sync,
+    G: BeeGuard + Sync,
+    R: MetricsRecorder + Sync,
+{
+    /// Parallel-reduction variant of `eco_load`, with the same validation.
+    /// Summation order differs from the serial path, so results agree only
+    /// to rounding.
+    pub fn eco_load_parallel(
+        &self,
+        nodes: &[NodeState],
+        alpha_m: f64,
+        alpha_k: f64,
+    ) -> Result<f64, SafetyError> {
+        let (a_m, a_k) = self.load_weights(alpha_m, alpha_k)?;
+        let (m_sum, k_sum) = nodes
+            .par_iter()
+            .map(|n| check_node_finite(n).map(|()| (n.mass_kg, n.karma_bytes)))
+            .try_reduce(|| (0.0, 0.0), |a, b| Ok((a.0 + b.0, a.1 + b.1)))?;
+        Ok(a_m * m_sum / self.m_ref_kg + a_k * k_sum / self.k_ref_nb)
+    }
+
+    /// Apply `update_node_duty` to every node in parallel. Node updates are
+    /// independent, so duties match the serial path exactly; a failing node
+    /// keeps its duty and its error is returned in place.
+    pub fn update_nodes_parallel(
+        &self,
+        nodes: &mut [NodeState],
+        eco_band: EcoBand,
+        phi_dw: f64,
+    ) -> Vec<Result<UpdateReport, SafetyError>> {
+        nodes
+            .par_iter_mut()
+            .map(|node| self.update_node_duty(node, eco_band, phi_dw))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{EcoBand, EcoBandClassifier, NodeState};
+
+    fn synthetic_nodes(count: usize) -> Vec<NodeState> {
+        let base = phoenix_nodes();
+        (0..count)
+            .map(|i| {
+                let mut n = base[i % base.len()].clone();
+                n.row.machine_id = format!("CYB-AIR-SYN-{i:05}");
+                n.duty_cycle = (i % 97) as f64 / 97.0;
+                n.mass_kg *= 1.0 + (i % 13) as f64 / 13.0;
+                n.karma_bytes *= 1.0 + (i % 7) as f64 / 7.0;
+                n.power_w = 20.0 + (i % 100) as f64;
+                n.geo_weight = (i % 11) as f64 / 10.0;
+                if i % 1000 == 17 {
+                    n.row.ecoimpact_score = 0.1; // outside envelope
+                }
+                n
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_matches_serial_on_10k_nodes() {
+        let controller = phoenix_controller();
+        let mut serial = synthetic_nodes(10_000);
+        let mut parallel = serial.clone();
+
+        let load = controller.eco_load(&serial, 0.5, 0.5).unwrap();
+        let load_par = controller.eco_load_parallel(&parallel, 0.5, 0.5).unwrap();
+        assert!((load - load_par).abs() <= 1e-9 * load.abs());
+        let band = controller.eco_band.classify(load);
+        assert_eq!(band, EcoBand::Red);
+
+        let serial_out: Vec<_> = serial
+            .iter_mut()
+            .map(|n| controller.update_node_duty(n, band, 5.0e-7))
+            .collect();
+        let parallel_out = controller.update_nodes_parallel(&mut parallel, band, 5.0e-7);
+
+        assert_eq!(serial_out, parallel_out);
+        assert_eq!(parallel_out.iter().filter(|r| r.is_err()).count(), 10);
+        for (a, b) in serial.iter().zip(parallel.iter()) {
+            assert_eq!(a.duty_cycle.to_bits(), b.duty_cycle.to_bits());
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! SQLite persistence of corridor node state between controller runs, so a
+//! restart resumes from the converged duty cycles instead of re-ramping.
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::clock::Clock;
+use crate::{CorridorRow, NodeState};
+
+/// Schema steps; entry `i` takes a database from `user_version` i to i + 1.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE corridor_run (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        step_counter INTEGER NOT NULL,
+        saved_at_ms INTEGER NOT NULL
+    );
+    CREATE TABLE node_state (
+        machine_id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        type TEXT NOT NULL,
+        location TEXT NOT NULL,
+        pollutant TEXT NOT NULL,
+        cin REAL NOT NULL,
+        cout REAL NOT NULL,
+        unit TEXT NOT NULL,
+        airflow_m3_per_s REAL NOT NULL,
+        period_s REAL NOT NULL,
+        lambda_hazard REAL NOT NULL,
+        beta_nb_per_kg REAL NOT NULL,
+        ecoimpact_score REAL NOT NULL,
+        mass_kg REAL NOT NULL,
+        karma_bytes REAL NOT NULL,
+        duty_cycle REAL NOT NULL,
+        power_w REAL NOT NULL,
+        geo_weight REAL NOT NULL,
+        noise_db REAL,
+        emf_vpm REAL
+    );",
+    "ALTER TABLE node_state ADD COLUMN trace_id BLOB;
+    ALTER TABLE node_state ADD COLUMN shard_version TEXT;",
+    // JSON-encoded `Suspicion`.
+    "ALTER TABLE node_state ADD COLUMN suspicion TEXT;",
+    // JSON-encoded `OperationalMode`; NULL while `Active`.
+    "ALTER TABLE node_state ADD COLUMN mode TEXT;",
+];
+
+/// Schema version this build reads and writes.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Errors while saving or loading corridor state.
+#[derive(Debug, Error)]
+pub enum PersistError {
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    /// Written by a newer build.
+    #[error("state database has schema version {found}, newer than supported {supported}")]
+    UnsupportedSchema { found: u32, supported: u32 },
+    #[error("node state json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("saved corridor state is {age_ms} ms old, more than the allowed {max_age_ms} ms")]
+    Stale {
+        saved_at_ms: u64,
+        age_ms: u64,
+        max_age_ms: u64,
+    },
+}
+
+/// Corridor state as of the last save.
+#[derive(Debug, Clone)]
+pub struct CorridorSnapshot {
+    /// In the order they were saved.
+    pub nodes: Vec<NodeState>,
+    pub step_counter: u64,
+    pub saved_at_ms: u64,
+}
+
+impl CorridorSnapshot {
+    /// Copy saved duty cycles onto `nodes` with a matching `machine_id`,
+    /// returning how many were restored. Nodes not in the snapshot keep
+    /// their duty cycle.
+    pub fn restore_duty_cycles(&self, nodes: &mut [NodeState]) -> usize {
+        let mut restored = 0;
+        for node in nodes {
+            if let Some(saved) = self
+                .nodes
+                .iter()
+                .find(|s| s.row.machine_id == node.row.machine_id)
+            {
+                node.duty_cycle = saved.duty_cycle;
+                restored += 1;
+            }
+        }
+        restored
+    }
+}
+
+/// Bring the schema up to `SCHEMA_VERSION`, one transaction per step.
+pub fn migrate(conn: &Connection) -> Result<(), PersistError> {
+    let found: u32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if found > SCHEMA_VERSION {
+        return Err(PersistError::UnsupportedSchema {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(found as usize) {
+        conn.execute_batch(&format!(
+            "BEGIN; {step}; PRAGMA user_version = {}; COMMIT;",
+            version + 1
+        ))?;
+    }
+    Ok(())
+}
+
+/// Replace the saved state with `nodes` and `step_counter`, stamped with
+/// `clock`. Nodes are keyed by `machine_id`, which must be unique.
+///
+/// Values round-trip exactly, except that SQLite stores NaN as NULL, which
+/// then fails to load.
+pub fn save_corridor_state(
+    conn: &mut Connection,
+    nodes: &[NodeState],
+    step_counter: u64,
+    clock: &dyn Clock,
+) -> Result<(), PersistError> {
+    migrate(conn)?;
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM node_state", [])?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO node_state VALUES
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+              ?21, ?22, ?23, ?24)",
+        )?;
+        for (position, node) in nodes.iter().enumerate() {
+            let row = &node.row;
+            insert.execute(params![
+                row.machine_id,
+                position,
+                row.r#type,
+                row.location,
+                row.pollutant,
+                row.cin,
+                row.cout,
+                row.unit,
+                row.airflow_m3_per_s,
+                row.period_s,
+                row.lambda_hazard,
+                row.beta_nb_per_kg,
+                row.ecoimpact_score,
+                node.mass_kg,
+                node.karma_bytes,
+                node.duty_cycle,
+                node.power_w,
+                node.geo_weight,
+                node.noise_db,
+                node.emf_vpm,
+                row.trace_id,
+                row.shard_version,
+                node.suspicion
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+                (!node.mode.is_active())
+                    .then(|| serde_json::to_string(&node.mode))
+                    .transpose()?,
+            ])?;
+        }
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO corridor_run (id, step_counter, saved_at_ms) VALUES (0, ?1, ?2)",
+        params![step_counter, clock.now_unix_ms()],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The last saved state, or `None` if nothing was saved. State saved more
+/// than `max_age_ms` before `clock` is refused with `PersistError::Stale`,
+/// since its duty cycles no longer describe the corridor.
+pub fn load_corridor_state(
+    conn: &Connection,
+    clock: &dyn Clock,
+    max_age_ms: u64,
+) -> Result<Option<CorridorSnapshot>, PersistError> {
+    migrate(conn)?;
+    let run: Option<(u64, u64)> = conn
+        .query_row(
+            "SELECT step_counter, saved_at_ms FROM corridor_run WHERE id = 0",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    let Some((step_counter, saved_at_ms)) = run else {
+        return Ok(None);
+    };
+    let age_ms = clock.now_unix_ms().saturating_sub(saved_at_ms);
+    if age_ms > max_age_ms {
+        return Err(PersistError::Stale {
+            saved_at_ms,
+            age_ms,
+            max_age_ms,
+        });
+    }
+
+    let mut select = conn.prepare(
+        "SELECT machine_id, type, location, pollutant, cin, cout, unit, airflow_m3_per_s,
+                period_s, lambda_hazard, beta_nb_per_kg, ecoimpact_score, mass_kg,
+                karma_bytes, duty_cycle, power_w, geo_weight, noise_db, emf_vpm, trace_id,
+                shard_version, suspicion, mode
+         FROM node_state ORDER BY position",
+    )?;
+    let nodes = select
+        .query_map([], |r| {
+            Ok(NodeState {
+                row: CorridorRow {
+                    machine_id: r.get(0)?,
+                    r#type: r.get(1)?,
+                    location: r.get(2)?,
+                    pollutant: r.get(3)?,
+                    cin: r.get(4)?,
+                    cout: r.get(5)?,
+                    unit: r.get(6)?,
+                    airflow_m3_per_s: r.get(7)?,
+                    period_s: r.get(8)?,
+                    lambda_hazard: r.get(9)?,
+                    beta_nb_per_kg: r.get(10)?,
+                    ecoimpact_score: r.get(11)?,
+                    trace_id: r.get(19)?,
+                    shard_version: r.get(20)?,
+                },
+                mass_kg: r.get(12)?,
+                karma_bytes: r.get(13)?,
+                duty_cycle: r.get(14)?,
+                power_w: r.get(15)?,
+                geo_weight: r.get(16)?,
+                noise_db: r.get(17)?,
+                emf_vpm: r.get(18)?,
+                suspicion: json_column(r, 21)?,
+                mode: json_column(r, 22)?.unwrap_or_default(),
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(Some(CorridorSnapshot {
+        nodes,
+        step_counter,
+        saved_at_ms,
+    }))
+}
+
+/// A nullable JSON text column.
+fn json_column<T: DeserializeOwned>(r: &Row<'_>, index: usize) -> rusqlite::Result<Option<T>> {
+    r.get::<_, Option<String>>(index)?
+        .map(|s| serde_json::from_str(&s))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::tests::phoenix_nodes;
+    use crate::{Heuristic, OperationalMode, Suspicion};
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    fn nodes() -> Vec<NodeState> {
+        let mut nodes = phoenix_nodes();
+        nodes[0].duty_cycle = 0.437_912_345_678_901_2;
+        nodes[0].noise_db = Some(61.5);
+        nodes[1].emf_vpm = Some(0.3);
+        nodes[1].row.trace_id = Some(uuid::Uuid::from_u128(0x5eed));
+        nodes[1].row.shard_version = Some("2026.10".into());
+        nodes[1].suspicion = Some(Suspicion {
+            score: 0.75,
+            heuristics: vec![Heuristic::FlatLine],
+        });
+        nodes[1].mode = OperationalMode::SensingOnly {
+            since_ms: 1_749_000_000_000,
+            reason: "flat-lined sensor".into(),
+        };
+        nodes
+    }
+
+    #[test]
+    fn state_round_trips_through_sqlite() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let clock = ManualClock::new(1_750_000_000_000);
+        assert!(load_corridor_state(&conn, &clock, HOUR_MS)
+            .unwrap()
+            .is_none());
+
+        save_corridor_state(&mut conn, &nodes(), 42, &clock).unwrap();
+        clock.advance(HOUR_MS);
+        let snapshot = load_corridor_state(&conn, &clock, HOUR_MS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.step_counter, 42);
+        assert_eq!(snapshot.saved_at_ms, 1_750_000_000_000);
+        // Debug prints floats in shortest round-trip form, so equal output
+        // means bit-identical values.
+        assert_eq!(format!("{:?}", snapshot.nodes), format!("{:?}", nodes()));
+
+        // A later save replaces the node set.
+        save_corridor_state(&mut conn, &nodes()[1..], 43, &clock).unwrap();
+        let snapshot = load_corridor_state(&conn, &clock, HOUR_MS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.nodes.len(), 1);
+        assert_eq!(snapshot.nodes[0].row.machine_id, "CYB-AIR-SCHOOL-05");
+
+        let mut fresh = phoenix_nodes();
+        assert_eq!(snapshot.restore_duty_cycles(&mut fresh), 1);
+        assert_eq!(fresh[1].duty_cycle, nodes()[1].duty_cycle);
+    }
+
+    #[test]
+    fn stale_state_is_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let clock = ManualClock::new(1_750_000_000_000);
+        save_corridor_state(&mut conn, &nodes(), 7, &clock).unwrap();
+        clock.advance(HOUR_MS + 1);
+        match load_corridor_state(&conn, &clock, HOUR_MS) {
+            Err(PersistError::Stale {
+                age_ms, max_age_ms, ..
+            }) => {
+                assert_eq!(age_ms, HOUR_MS + 1);
+                assert_eq!(max_age_ms, HOUR_MS);
+            }
+            other => panic!("expected stale state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1))
+            .unwrap();
+        assert!(matches!(
+            load_corridor_state(&conn, &ManualClock::new(0), HOUR_MS),
+            Err(PersistError::UnsupportedSchema { .. })
+        ));
+    }
+}
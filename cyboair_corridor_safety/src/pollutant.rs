@@ -0,0 +1,171 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Bee hazard weight for pollutants without a listed default.
+pub const BEE_LAMBDA_UNLISTED: f64 = 1.0;
+/// Bee NanoKarmaBytes per kg for pollutants without a listed default.
+pub const BEE_BETA_UNLISTED: f64 = 2.0e8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown pollutant: {0:?}")]
+pub struct UnknownPollutant(pub String);
+
+/// Pollutant species tracked in qpudatashards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Pollutant {
+    PM25,
+    PM10,
+    O3,
+    NO2,
+    /// Reported as NO2-equivalent.
+    NOx,
+    /// Reported as toluene-equivalent.
+    VOC,
+    SO2,
+    CO,
+}
+
+impl Pollutant {
+    /// Molar mass in kg/mol for gas-phase species; `None` for particulates,
+    /// whose shard concentrations are already mass per volume.
+    pub fn molar_mass(self) -> Option<f64> {
+        match self {
+            Pollutant::PM25 | Pollutant::PM10 => None,
+            Pollutant::O3 => Some(0.048),
+            Pollutant::NO2 | Pollutant::NOx => Some(0.046),
+            Pollutant::VOC => Some(0.092),
+            Pollutant::SO2 => Some(0.064),
+            Pollutant::CO => Some(0.028),
+        }
+    }
+
+    pub fn is_particulate(self) -> bool {
+        self.molar_mass().is_none()
+    }
+
+    /// Default bee hazard weight lambda_bee.
+    pub fn bee_lambda(self) -> f64 {
+        match self {
+            Pollutant::PM25 => 3.0,
+            Pollutant::VOC => 4.0,
+            Pollutant::O3 | Pollutant::NO2 | Pollutant::NOx => 2.5,
+            Pollutant::PM10 => 1.5,
+            Pollutant::SO2 | Pollutant::CO => BEE_LAMBDA_UNLISTED,
+        }
+    }
+
+    /// Default bee NanoKarmaBytes per kg, beta_bee.
+    pub fn bee_beta_nb_per_kg(self) -> f64 {
+        match self {
+            Pollutant::PM25 => 6.0e8,
+            Pollutant::VOC => 7.0e8,
+            Pollutant::O3 | Pollutant::NO2 | Pollutant::NOx => 4.0e8,
+            Pollutant::PM10 => 3.0e8,
+            Pollutant::SO2 | Pollutant::CO => BEE_BETA_UNLISTED,
+        }
+    }
+
+    /// Canonical shard spelling.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Pollutant::PM25 => "PM2.5",
+            Pollutant::PM10 => "PM10",
+            Pollutant::O3 => "O3",
+            Pollutant::NO2 => "NO2",
+            Pollutant::NOx => "NOx",
+            Pollutant::VOC => "VOC",
+            Pollutant::SO2 => "SO2",
+            Pollutant::CO => "CO",
+        }
+    }
+}
+
+impl FromStr for Pollutant {
+    type Err = UnknownPollutant;
+
+    /// Case-insensitive, ignoring `.`, `_`, `-` and spaces. Runs on every
+    /// duty update, so it normalizes into a stack buffer as long as the
+    /// longest alias instead of allocating.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut buf = [0u8; 8];
+        let mut len = 0;
+        for b in s.trim().bytes() {
+            if matches!(b, b'.' | b'_' | b'-' | b' ') {
+                continue;
+            }
+            // Longer or non-ASCII labels match no alias.
+            if len == buf.len() || !b.is_ascii() {
+                return Err(UnknownPollutant(s.to_string()));
+            }
+            buf[len] = b.to_ascii_uppercase();
+            len += 1;
+        }
+        match &buf[..len] {
+            b"PM25" => Ok(Pollutant::PM25),
+            b"PM10" | b"DUSTPM10" | b"DUST" => Ok(Pollutant::PM10),
+            b"O3" | b"OZONE" => Ok(Pollutant::O3),
+            b"NO2" => Ok(Pollutant::NO2),
+            b"NOX" => Ok(Pollutant::NOx),
+            b"VOC" | b"VOCS" | b"TVOC" => Ok(Pollutant::VOC),
+            b"SO2" => Ok(Pollutant::SO2),
+            b"CO" => Ok(Pollutant::CO),
+            _ => Err(UnknownPollutant(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Pollutant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_parse() {
+        let cases = [
+            ("PM2.5", Pollutant::PM25),
+            ("pm_2_5", Pollutant::PM25),
+            ("PM25", Pollutant::PM25),
+            ("DustPM10", Pollutant::PM10),
+            ("ozone", Pollutant::O3),
+            ("NOx", Pollutant::NOx),
+            ("VOCs", Pollutant::VOC),
+            ("so2", Pollutant::SO2),
+            ("CO", Pollutant::CO),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<Pollutant>(), Ok(expected), "{s}");
+        }
+        assert!("radon".parse::<Pollutant>().is_err());
+        for label in ["PM2.5-fine", "Dust-PM10x", "ozöne", "  ", "O\t3"] {
+            assert_eq!(
+                label.parse::<Pollutant>(),
+                Err(UnknownPollutant(label.to_string()))
+            );
+        }
+        assert_eq!(" dust_pm-10 ".parse::<Pollutant>(), Ok(Pollutant::PM10));
+    }
+
+    #[test]
+    fn particulates_have_no_molar_mass() {
+        assert!(Pollutant::PM25.is_particulate());
+        assert!(Pollutant::PM10.is_particulate());
+        assert_eq!(Pollutant::O3.molar_mass(), Some(0.048));
+    }
+
+    #[test]
+    fn bee_defaults_match_guard_tables() {
+        assert_eq!(Pollutant::PM25.bee_lambda(), 3.0);
+        assert_eq!(Pollutant::VOC.bee_beta_nb_per_kg(), 7.0e8);
+        assert_eq!(Pollutant::PM10.bee_lambda(), 1.5);
+        assert_eq!(Pollutant::CO.bee_beta_nb_per_kg(), BEE_BETA_UNLISTED);
+    }
+}
@@ -0,0 +1,493 @@
+//! Quarantine for nodes that keep failing the same safety check.
+//!
+//! A node with a broken sensor fails its check on every tick, and without
+//! intervention the controller re-evaluates and re-logs it forever.
+//! [`Quarantine`] counts consecutive failures per node and per
+//! [`SafetyError::kind`]; once a count reaches
+//! [`QuarantinePolicy::failure_threshold`] the node is put in
+//! [`OperationalMode::Quarantined`], which pins its duty to 0 and drops it
+//! from the eco-load.
+//!
+//! A quarantined node keeps receiving telemetry and is re-probed with a dry
+//! run of the check that tripped it. A failed probe doubles the wait before
+//! the next one, up to [`QuarantinePolicy::max_backoff_steps`]; a passing
+//! probe is followed by another on the next step, and after
+//! [`QuarantinePolicy::restore_after_passes`] passes in a row the node is
+//! returned to `Active`. Every transition is returned as a
+//! [`QuarantineEvent`], to be appended to an event chain under
+//! [`QUARANTINE`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBand, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, OperationalMode, SafetyEnvelope, StepRecord,
+};
+
+/// Kind of the chain entries quarantine transitions are appended as.
+pub const QUARANTINE: &str = "quarantine";
+
+/// When to quarantine a node and when to let it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinePolicy {
+    /// Consecutive failures of one check that quarantine a node; 0 is
+    /// treated as 1.
+    pub failure_threshold: u32,
+    /// Consecutive passing probes that restore a node; 0 is treated as 1.
+    pub restore_after_passes: u32,
+    /// Steps from quarantine, or from a failed probe, to the next probe
+    /// before any backoff; 0 is treated as 1.
+    pub initial_backoff_steps: u64,
+    /// Longest wait between probes.
+    pub max_backoff_steps: u64,
+    /// Failures kept per quarantined node; older ones are dropped.
+    pub max_history: usize,
+}
+
+impl Default for QuarantinePolicy {
+    fn default() -> Self {
+        QuarantinePolicy {
+            failure_threshold: 5,
+            restore_after_passes: 3,
+            initial_backoff_steps: 2,
+            max_backoff_steps: 64,
+            max_history: 32,
+        }
+    }
+}
+
+/// One failed check on a node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureRecord {
+    pub step: u64,
+    /// [`SafetyError::kind`](crate::SafetyError::kind).
+    pub check: String,
+    pub message: String,
+    /// Whether this was a re-probe of a quarantined node.
+    pub probe: bool,
+}
+
+/// A quarantined node and why.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantinedNode {
+    pub machine_id: String,
+    /// The check that tripped the quarantine.
+    pub check: String,
+    pub since_step: u64,
+    /// The failures that led to the quarantine, then failed probes, oldest
+    /// first.
+    pub history: Vec<FailureRecord>,
+    pub next_probe_step: u64,
+    pub consecutive_passes: u32,
+}
+
+/// A quarantine transition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum QuarantineEvent {
+    Quarantined {
+        step: u64,
+        machine_id: String,
+        check: String,
+        failures: u32,
+    },
+    ProbeFailed {
+        step: u64,
+        machine_id: String,
+        check: String,
+        message: String,
+        next_probe_step: u64,
+    },
+    ProbePassed {
+        step: u64,
+        machine_id: String,
+        check: String,
+        consecutive_passes: u32,
+    },
+    Restored {
+        step: u64,
+        machine_id: String,
+        check: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Held {
+    node: QuarantinedNode,
+    backoff_steps: u64,
+}
+
+/// Failure streaks and quarantined nodes, keyed by `machine_id`.
+#[derive(Debug, Clone, Default)]
+pub struct Quarantine {
+    pub policy: QuarantinePolicy,
+    /// Recent failures of each check on each node still in a streak.
+    streaks: HashMap<String, BTreeMap<&'static str, Vec<FailureRecord>>>,
+    held: HashMap<String, Held>,
+}
+
+impl Quarantine {
+    pub fn new(policy: QuarantinePolicy) -> Self {
+        Quarantine {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Consecutive failures of `check` on `machine_id` so far.
+    pub fn streak(&self, machine_id: &str, check: &str) -> u32 {
+        self.streaks
+            .get(machine_id)
+            .and_then(|s| s.get(check))
+            .map_or(0, |f| f.len() as u32)
+    }
+
+    pub fn is_quarantined(&self, machine_id: &str) -> bool {
+        self.held.contains_key(machine_id)
+    }
+
+    /// Quarantined nodes, by `machine_id`.
+    pub fn quarantined(&self) -> Vec<QuarantinedNode> {
+        let mut nodes: Vec<_> = self.held.values().map(|h| h.node.clone()).collect();
+        nodes.sort_by(|a, b| a.machine_id.cmp(&b.machine_id));
+        nodes
+    }
+
+    /// Update streaks from `record`, the outcome of step `step` over
+    /// `nodes`, then probe quarantined nodes that are due at `phi_dw`.
+    /// Nodes that reach the threshold are quarantined and nodes that pass
+    /// enough probes restored, in `nodes` as well as here.
+    ///
+    /// A node found in `Quarantined` mode but not held here, e.g. after a
+    /// restore from a snapshot, is adopted and probed on the next step. A
+    /// held node whose mode was changed from outside is released without
+    /// an event.
+    pub fn observe<E, H, B, D, G, R>(
+        &mut self,
+        controller: &CorridorController<E, H, B, D, G, R>,
+        nodes: &mut [NodeState],
+        record: &StepRecord,
+        step: u64,
+        phi_dw: f64,
+    ) -> Vec<QuarantineEvent>
+    where
+        E: SafetyEnvelope,
+        H: HostBudget,
+        B: EcoBandClassifier,
+        D: DwCeilingInvariant,
+        G: BeeGuard,
+        R: MetricsRecorder,
+    {
+        let mut events = Vec::new();
+        for (node, result) in nodes.iter_mut().zip(&record.nodes) {
+            let id = &node.row.machine_id;
+            match (&node.mode, self.held.contains_key(id)) {
+                (OperationalMode::Quarantined { since_step, check }, false) => {
+                    let held = Held {
+                        node: QuarantinedNode {
+                            machine_id: id.clone(),
+                            check: check.clone(),
+                            since_step: *since_step,
+                            history: Vec::new(),
+                            next_probe_step: step + 1,
+                            consecutive_passes: 0,
+                        },
+                        backoff_steps: self.initial_backoff(),
+                    };
+                    self.held.insert(id.clone(), held);
+                    continue;
+                }
+                (OperationalMode::Quarantined { .. }, true) => {
+                    self.probe(controller, node, record.band, step, phi_dw, &mut events);
+                    continue;
+                }
+                (_, true) => {
+                    self.held.remove(id);
+                }
+                (_, false) => {}
+            }
+            if !node.mode.is_active() {
+                self.streaks.remove(id);
+                continue;
+            }
+            let Some(error) = &result.error else {
+                self.streaks.remove(id);
+                continue;
+            };
+            let check = error.kind();
+            let streaks = self.streaks.entry(id.clone()).or_default();
+            let mut failures = streaks.remove(check).unwrap_or_default();
+            streaks.clear();
+            failures.push(FailureRecord {
+                step,
+                check: check.to_string(),
+                message: error.to_string(),
+                probe: false,
+            });
+            if failures.len() < self.policy.failure_threshold.max(1) as usize {
+                streaks.insert(check, failures);
+                continue;
+            }
+            self.streaks.remove(id);
+            let count = failures.len() as u32;
+            trim(&mut failures, self.policy.max_history);
+            let held = Held {
+                node: QuarantinedNode {
+                    machine_id: id.clone(),
+                    check: check.to_string(),
+                    since_step: step,
+                    history: failures,
+                    next_probe_step: step + self.initial_backoff(),
+                    consecutive_passes: 0,
+                },
+                backoff_steps: self.initial_backoff(),
+            };
+            self.held.insert(id.clone(), held);
+            node.mode = OperationalMode::Quarantined {
+                since_step: step,
+                check: check.to_string(),
+            };
+            node.duty_cycle = 0.0;
+            events.push(QuarantineEvent::Quarantined {
+                step,
+                machine_id: id.clone(),
+                check: check.to_string(),
+                failures: count,
+            });
+        }
+        events
+    }
+
+    fn probe<E, H, B, D, G, R>(
+        &mut self,
+        controller: &CorridorController<E, H, B, D, G, R>,
+        node: &mut NodeState,
+        band: EcoBand,
+        step: u64,
+        phi_dw: f64,
+        events: &mut Vec<QuarantineEvent>,
+    ) where
+        E: SafetyEnvelope,
+        H: HostBudget,
+        B: EcoBandClassifier,
+        D: DwCeilingInvariant,
+        G: BeeGuard,
+        R: MetricsRecorder,
+    {
+        let policy = self.policy;
+        let Some(held) = self.held.get_mut(&node.row.machine_id) else {
+            return;
+        };
+        if step < held.node.next_probe_step {
+            return;
+        }
+        let machine_id = held.node.machine_id.clone();
+        let check = held.node.check.clone();
+        let mut probe = node.clone();
+        probe.mode = OperationalMode::Active;
+        let assessment = controller.dry_run_node(&probe, band, phi_dw);
+        let failure = assessment.violations.iter().find(|e| e.kind() == check);
+        if let Some(error) = failure {
+            let message = error.to_string();
+            held.node.consecutive_passes = 0;
+            held.node.next_probe_step = step + held.backoff_steps;
+            held.backoff_steps = held
+                .backoff_steps
+                .saturating_mul(2)
+                .min(policy.max_backoff_steps.max(1));
+            held.node.history.push(FailureRecord {
+                step,
+                check: check.clone(),
+                message: message.clone(),
+                probe: true,
+            });
+            trim(&mut held.node.history, policy.max_history);
+            events.push(QuarantineEvent::ProbeFailed {
+                step,
+                machine_id,
+                check,
+                message,
+                next_probe_step: held.node.next_probe_step,
+            });
+            return;
+        }
+        held.node.consecutive_passes += 1;
+        if held.node.consecutive_passes < policy.restore_after_passes.max(1) {
+            held.node.next_probe_step = step + 1;
+            events.push(QuarantineEvent::ProbePassed {
+                step,
+                machine_id,
+                check,
+                consecutive_passes: held.node.consecutive_passes,
+            });
+            return;
+        }
+        self.held.remove(&machine_id);
+        let release =
+            |_: &str, mode: &OperationalMode| matches!(mode, OperationalMode::Quarantined { .. });
+        if controller.exit_mode(node, &release).is_ok() {
+            events.push(QuarantineEvent::Restored {
+                step,
+                machine_id,
+                check,
+            });
+        }
+    }
+
+    fn initial_backoff(&self) -> u64 {
+        self.policy
+            .initial_backoff_steps
+            .clamp(1, self.policy.max_backoff_steps.max(1))
+    }
+}
+
+/// Drop the oldest failures beyond `max`.
+fn trim(history: &mut Vec<FailureRecord>, max: usize) {
+    let excess = history.len().saturating_sub(max);
+    history.drain(..excess);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{ChainAppender, EventChain, StepInput};
+
+    fn input(_: usize) -> StepInput {
+        StepInput {
+            phi_dw: 5.0e-7,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        }
+    }
+
+    #[test]
+    fn a_failing_node_is_quarantined_probed_with_backoff_and_restored() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        let mut quarantine = Quarantine::new(QuarantinePolicy {
+            failure_threshold: 3,
+            restore_after_passes: 2,
+            initial_backoff_steps: 1,
+            max_backoff_steps: 4,
+            max_history: 8,
+        });
+        let mut chain = EventChain::new();
+        // A stuck power sensor: over the host budget on every tick.
+        nodes[0].power_w = 1.0e6;
+        let tick = |quarantine: &mut Quarantine,
+                    chain: &mut EventChain,
+                    nodes: &mut Vec<NodeState>,
+                    step: u64| {
+            let record = controller.run_steps(nodes, 1, input).unwrap().remove(0);
+            let events = quarantine.observe(&controller, nodes, &record, step, 5.0e-7);
+            for event in &events {
+                chain.append(QUARANTINE, event).unwrap();
+            }
+            (record, events)
+        };
+
+        for step in 0..2 {
+            let (record, events) = tick(&mut quarantine, &mut chain, &mut nodes, step);
+            assert!(record.nodes[0].error.is_some());
+            assert!(events.is_empty());
+        }
+        let (_, events) = tick(&mut quarantine, &mut chain, &mut nodes, 2);
+        assert_eq!(
+            events,
+            [QuarantineEvent::Quarantined {
+                step: 2,
+                machine_id: "CYB-AIR-CANOPY-01".into(),
+                check: "host_budget_exceeded".into(),
+                failures: 3,
+            }]
+        );
+        assert_eq!(nodes[0].duty_cycle, 0.0);
+        assert!(!nodes[0].mode.counts_toward_load());
+        // Dropped from the eco-load, so the corridor sees only the school.
+        let load = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        assert_eq!(load, controller.eco_load(&nodes[1..], 0.5, 0.5).unwrap());
+
+        let listed = quarantine.quarantined();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].since_step, 2);
+        assert_eq!(
+            listed[0].history.iter().map(|f| f.step).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+
+        // Probes at 3, 4 and 6 fail; the wait doubles up to 4 steps.
+        let mut probes = Vec::new();
+        for step in 3..=9 {
+            let (record, events) = tick(&mut quarantine, &mut chain, &mut nodes, step);
+            assert!(record.nodes[0].error.is_none());
+            assert_eq!(record.nodes[0].duty_cycle, 0.0);
+            for event in events {
+                let QuarantineEvent::ProbeFailed {
+                    step,
+                    next_probe_step,
+                    ..
+                } = event
+                else {
+                    panic!("{event:?}");
+                };
+                probes.push((step, next_probe_step));
+            }
+        }
+        assert_eq!(probes, [(3, 4), (4, 6), (6, 10)]);
+        let listed = quarantine.quarantined();
+        assert_eq!(listed[0].history.len(), 6);
+        assert!(listed[0].history[3..].iter().all(|f| f.probe));
+
+        // The sensor is fixed; two passing probes in a row restore the node.
+        nodes[0].power_w = 50.0;
+        let (_, events) = tick(&mut quarantine, &mut chain, &mut nodes, 10);
+        assert!(matches!(
+            events[..],
+            [QuarantineEvent::ProbePassed {
+                consecutive_passes: 1,
+                ..
+            }]
+        ));
+        let (_, events) = tick(&mut quarantine, &mut chain, &mut nodes, 11);
+        assert!(matches!(
+            events[..],
+            [QuarantineEvent::Restored { step: 11, .. }]
+        ));
+        assert!(nodes[0].mode.is_active());
+        assert!(quarantine.quarantined().is_empty());
+        let (record, _) = tick(&mut quarantine, &mut chain, &mut nodes, 12);
+        assert!(record.nodes[0].error.is_none());
+        assert!(record.nodes[0].duty_cycle > 0.0);
+
+        assert_eq!(chain.verify().unwrap().len, 6);
+        assert!(chain.entries().iter().all(|e| e.kind == QUARANTINE));
+        assert_eq!(chain.entries()[0].payload["event"], "quarantined");
+    }
+
+    #[test]
+    fn a_different_failure_restarts_the_streak() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        let mut quarantine = Quarantine::new(QuarantinePolicy {
+            failure_threshold: 2,
+            ..QuarantinePolicy::default()
+        });
+        let mut tick = |nodes: &mut Vec<NodeState>, step: u64| {
+            let record = controller.run_steps(nodes, 1, input).unwrap().remove(0);
+            quarantine.observe(&controller, nodes, &record, step, 5.0e-7)
+        };
+        nodes[0].power_w = 1.0e6;
+        assert!(tick(&mut nodes, 0).is_empty());
+        nodes[0].power_w = 50.0;
+        nodes[0].row.ecoimpact_score = 0.1;
+        assert!(tick(&mut nodes, 1).is_empty());
+        nodes[0].row.ecoimpact_score = 0.92;
+        assert!(tick(&mut nodes, 2).is_empty());
+        assert!(nodes[0].mode.is_active());
+    }
+}
@@ -0,0 +1,173 @@
+//! Duty-cycle trajectories from a starting duty to a target over a horizon.
+
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+/// How a node moves from its current duty to a target duty over a horizon.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RampShape {
+    /// Jump at the start of the horizon.
+    Step,
+    Linear,
+    /// Smoothstep: slow at both ends.
+    SCurve,
+    /// First-order approach with time constant `tau_s`, rescaled so the
+    /// target is reached exactly at the horizon.
+    ExpApproach {
+        tau_s: f64,
+    },
+}
+
+impl RampShape {
+    /// Fraction of the way to the target at `fraction` of the horizon, for
+    /// `fraction` in (0, 1).
+    fn progress(self, fraction: f64, horizon_s: f64) -> f64 {
+        match self {
+            RampShape::Step => 1.0,
+            RampShape::Linear => fraction,
+            RampShape::SCurve => fraction * fraction * (3.0 - 2.0 * fraction),
+            RampShape::ExpApproach { tau_s } => {
+                let scale = -(-horizon_s / tau_s).exp_m1();
+                if scale > 0.0 {
+                    -(-fraction * horizon_s / tau_s).exp_m1() / scale
+                } else {
+                    fraction
+                }
+            }
+        }
+    }
+}
+
+impl Hash for RampShape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let RampShape::ExpApproach { tau_s } = self {
+            // `+ 0.0` folds -0.0 into 0.0 so equal values hash alike.
+            (tau_s + 0.0).to_bits().hash(state);
+        }
+    }
+}
+
+/// Duty `t_since_start_s` seconds into a ramp from `from` to `to` over
+/// `horizon_s`. Exact at the ends: `from` at t <= 0 and `to` at
+/// t >= `horizon_s`, whatever the shape. A non-positive horizon jumps
+/// straight to `to`.
+pub fn duty_at(t_since_start_s: f64, from: f64, to: f64, horizon_s: f64, shape: RampShape) -> f64 {
+    if t_since_start_s >= horizon_s {
+        return to;
+    }
+    if t_since_start_s <= 0.0 {
+        return from;
+    }
+    let progress = shape.progress(t_since_start_s / horizon_s, horizon_s);
+    from + (to - from) * progress.clamp(0.0, 1.0)
+}
+
+/// A ramp in progress for one node.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DutyRamp {
+    pub from: f64,
+    pub to: f64,
+    pub horizon_s: f64,
+    pub shape: RampShape,
+}
+
+impl DutyRamp {
+    /// See [`duty_at`].
+    pub fn duty_at(&self, t_since_start_s: f64) -> f64 {
+        duty_at(
+            t_since_start_s,
+            self.from,
+            self.to,
+            self.horizon_s,
+            self.shape,
+        )
+    }
+
+    pub fn is_done(&self, t_since_start_s: f64) -> bool {
+        t_since_start_s >= self.horizon_s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trajectory(shape: RampShape) -> Vec<f64> {
+        (0..=4)
+            .map(|i| duty_at(f64::from(i) * 25.0, 0.2, 0.6, 100.0, shape))
+            .collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-12, "{actual:?} vs {expected:?}");
+        }
+    }
+
+    #[test]
+    fn each_shape_follows_its_trajectory() {
+        assert_eq!(trajectory(RampShape::Step), [0.2, 0.6, 0.6, 0.6, 0.6]);
+        assert_close(&trajectory(RampShape::Linear), &[0.2, 0.3, 0.4, 0.5, 0.6]);
+        // Smoothstep 0.15625, 0.5, 0.84375 of the 0.4 span.
+        assert_close(
+            &trajectory(RampShape::SCurve),
+            &[0.2, 0.2625, 0.4, 0.5375, 0.6],
+        );
+
+        // tau = horizon / 4: 1 - e^-1 of the way at the first quarter,
+        // rescaled by 1 - e^-4.
+        let exp = trajectory(RampShape::ExpApproach { tau_s: 25.0 });
+        let scale = 1.0 - (-4.0f64).exp();
+        let expected: Vec<_> = (0..=4)
+            .map(|i| 0.2 + 0.4 * (1.0 - (-f64::from(i)).exp()) / scale)
+            .collect();
+        assert_close(&exp, &expected);
+        assert!(exp[1] > trajectory(RampShape::Linear)[1]);
+        assert_eq!(exp[4], 0.6);
+
+        // Ramping down mirrors ramping up, and the ends are exact.
+        for shape in [
+            RampShape::Step,
+            RampShape::Linear,
+            RampShape::SCurve,
+            RampShape::ExpApproach { tau_s: 1.0e-3 },
+            RampShape::ExpApproach { tau_s: 1.0e9 },
+        ] {
+            assert_eq!(duty_at(0.0, 0.7, 0.1, 60.0, shape), 0.7);
+            assert_eq!(duty_at(60.0, 0.7, 0.1, 60.0, shape), 0.1);
+            assert_eq!(duty_at(600.0, 0.7, 0.1, 60.0, shape), 0.1);
+            let mid = duty_at(30.0, 0.7, 0.1, 60.0, shape);
+            assert!((0.1 - 1e-12..=0.7).contains(&mid), "{shape:?}: {mid}");
+        }
+        // A very long time constant is linear in the limit.
+        let slow = duty_at(
+            30.0,
+            0.0,
+            1.0,
+            60.0,
+            RampShape::ExpApproach { tau_s: 1.0e9 },
+        );
+        assert!((slow - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shapes_round_trip_through_json() {
+        let shapes = [
+            RampShape::Step,
+            RampShape::SCurve,
+            RampShape::ExpApproach { tau_s: 30.0 },
+        ];
+        let json = serde_json::to_string(&shapes).unwrap();
+        assert_eq!(
+            json,
+            r#"["step","s_curve",{"exp_approach":{"tau_s":30.0}}]"#
+        );
+        let back: Vec<RampShape> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, shapes);
+    }
+}
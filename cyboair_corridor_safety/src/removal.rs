@@ -0,0 +1,303 @@
+//! Mass removed against a no-actuation baseline.
+//!
+//! [`compute_mass_kg`](crate::compute_mass_kg) credits the whole
+//! `cin - cout` drop to the machine, though some of it would have left the
+//! air anyway. A [`RemovalBaseline`] estimates that share, and
+//! [`net_mass_removed`] reports gross, baseline and net mass, with
+//! `net = max(gross - baseline, 0)`. [`KarmaBasis::Net`] credits
+//! NanoKarmaBytes for the net figure only; the default, `Gross`, keeps the
+//! original accounting. `mass_kg` fed to the duty law stays gross.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::units::{self, UnitError};
+use crate::{ceim, compute_karma_bytes, CorridorRow, Pollutant};
+
+/// Mass a node's air would have lost over one row period without the
+/// machine.
+pub trait RemovalBaseline {
+    /// Baseline removal in kg, given `kg_per_m3`, the factor converting the
+    /// row's concentration unit to kg/m^3.
+    fn baseline_removal_kg(&self, row: &CorridorRow, kg_per_m3: f64) -> f64;
+}
+
+/// Nothing is removed without the machine; net equals gross.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZeroBaseline;
+
+impl RemovalBaseline for ZeroBaseline {
+    fn baseline_removal_kg(&self, _row: &CorridorRow, _kg_per_m3: f64) -> f64 {
+        0.0
+    }
+}
+
+/// First-order ambient decay of the inlet air over `residence_s`: the
+/// baseline is the inlet mass times `1 - exp(-rate * residence_s)`.
+/// Pollutants without a rate, and unknown labels, do not decay.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DecayBaseline {
+    /// Time the air would spend in the unit's footprint, s.
+    pub residence_s: f64,
+    /// Ambient decay rates, 1/s.
+    pub rates_per_s: HashMap<Pollutant, f64>,
+}
+
+impl DecayBaseline {
+    pub fn new(residence_s: f64) -> Self {
+        DecayBaseline {
+            residence_s,
+            rates_per_s: HashMap::new(),
+        }
+    }
+
+    pub fn with_rate(mut self, pollutant: Pollutant, rate_per_s: f64) -> Self {
+        self.rates_per_s.insert(pollutant, rate_per_s);
+        self
+    }
+
+    /// Share of the inlet mass that decays on its own.
+    pub fn decayed_fraction(&self, pollutant: Pollutant) -> f64 {
+        let rate = self.rates_per_s.get(&pollutant).copied().unwrap_or(0.0);
+        1.0 - (-rate * self.residence_s).exp()
+    }
+}
+
+impl RemovalBaseline for DecayBaseline {
+    fn baseline_removal_kg(&self, row: &CorridorRow, kg_per_m3: f64) -> f64 {
+        let Ok(pollutant) = row.pollutant_kind() else {
+            return 0.0;
+        };
+        let inlet_kg = kg_per_m3 * row.cin.max(0.0) * row.airflow_m3_per_s * row.period_s;
+        inlet_kg * self.decayed_fraction(pollutant)
+    }
+}
+
+/// Baseline model selected by `ControllerConfig::mass_baseline`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum MassBaseline {
+    #[default]
+    Zero,
+    Decay(DecayBaseline),
+}
+
+impl MassBaseline {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let MassBaseline::Decay(decay) = self else {
+            return Ok(());
+        };
+        let invalid = |reason: String| {
+            Err(ConfigError::Invalid {
+                field: "mass_baseline",
+                reason,
+            })
+        };
+        if !(decay.residence_s.is_finite() && decay.residence_s >= 0.0) {
+            return invalid(format!(
+                "residence_s must be finite and >= 0, got {}",
+                decay.residence_s
+            ));
+        }
+        for (pollutant, rate) in &decay.rates_per_s {
+            if !(rate.is_finite() && *rate >= 0.0) {
+                return invalid(format!(
+                    "rate for {pollutant} must be finite and >= 0, got {rate}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RemovalBaseline for MassBaseline {
+    fn baseline_removal_kg(&self, row: &CorridorRow, kg_per_m3: f64) -> f64 {
+        match self {
+            MassBaseline::Zero => ZeroBaseline.baseline_removal_kg(row, kg_per_m3),
+            MassBaseline::Decay(decay) => decay.baseline_removal_kg(row, kg_per_m3),
+        }
+    }
+}
+
+/// Which removal figure earns NanoKarmaBytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum KarmaBasis {
+    #[default]
+    Gross,
+    Net,
+}
+
+/// One row's removal against its baseline, kg.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MassRemoval {
+    /// `compute_mass_kg`: the whole measured drop.
+    pub gross_kg: f64,
+    /// Removal expected without the machine; may exceed `gross_kg`.
+    pub baseline_kg: f64,
+    /// `max(gross_kg - baseline_kg, 0)`.
+    pub net_kg: f64,
+}
+
+impl MassRemoval {
+    pub fn credited_kg(&self, basis: KarmaBasis) -> f64 {
+        match basis {
+            KarmaBasis::Gross => self.gross_kg,
+            KarmaBasis::Net => self.net_kg,
+        }
+    }
+
+    /// `compute_karma_bytes` for the mass `basis` credits.
+    pub fn karma_bytes(&self, row: &CorridorRow, basis: KarmaBasis) -> f64 {
+        compute_karma_bytes(row, self.credited_kg(basis))
+    }
+}
+
+/// Gross, baseline and net mass for `row` at standard pressure.
+pub fn net_mass_removed(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    baseline: &dyn RemovalBaseline,
+) -> Result<MassRemoval, UnitError> {
+    net_mass_removed_at_pressure(
+        row,
+        pollutant,
+        temperature_k,
+        units::P_STANDARD_PA,
+        baseline,
+    )
+}
+
+/// `net_mass_removed` at an explicit ambient pressure, Pa.
+pub fn net_mass_removed_at_pressure(
+    row: &CorridorRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    pressure_pa: f64,
+    baseline: &dyn RemovalBaseline,
+) -> Result<MassRemoval, UnitError> {
+    let kg_per_m3 = row.concentration_unit()?.kg_per_m3_factor_for_at_pressure(
+        pollutant,
+        temperature_k,
+        pressure_pa,
+    )?;
+    let gross_kg = ceim::mass_kg(
+        row.cin,
+        row.cout,
+        kg_per_m3,
+        row.airflow_m3_per_s,
+        row.period_s,
+    );
+    let baseline_kg = baseline.baseline_removal_kg(row, kg_per_m3);
+    Ok(MassRemoval {
+        gross_kg,
+        baseline_kg,
+        net_kg: (gross_kg - baseline_kg).max(0.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_nodes;
+    use crate::{compute_mass_kg, ControllerConfig};
+
+    fn decay() -> DecayBaseline {
+        DecayBaseline::new(2.0)
+            .with_rate(Pollutant::PM25, 0.05)
+            .with_rate(Pollutant::O3, 0.5)
+    }
+
+    #[test]
+    fn zero_baseline_matches_gross_accounting() {
+        for node in phoenix_nodes() {
+            let row = &node.row;
+            let removal = net_mass_removed(row, Pollutant::PM25, 310.0, &ZeroBaseline).unwrap();
+            let mass = compute_mass_kg(row, Pollutant::PM25, 310.0).unwrap();
+            assert_eq!(removal.gross_kg, mass);
+            assert_eq!(removal.net_kg, mass);
+            assert_eq!(removal.baseline_kg, 0.0);
+            for basis in [KarmaBasis::Gross, KarmaBasis::Net] {
+                assert_eq!(
+                    removal.karma_bytes(row, basis),
+                    compute_karma_bytes(row, mass)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn net_never_exceeds_gross() {
+        let baseline = MassBaseline::Decay(decay());
+        for node in phoenix_nodes() {
+            let mut row = node.row.clone();
+            for cout in [0.0, row.cin * 0.5, row.cin * 0.95, row.cin, row.cin * 1.2] {
+                row.cout = cout;
+                let removal = net_mass_removed(&row, Pollutant::PM25, 310.0, &baseline).unwrap();
+                assert!(removal.baseline_kg > 0.0);
+                assert!(removal.net_kg <= removal.gross_kg, "{removal:?}");
+                assert!(removal.net_kg >= 0.0);
+                assert!(
+                    removal.karma_bytes(&row, KarmaBasis::Net)
+                        <= removal.karma_bytes(&row, KarmaBasis::Gross)
+                );
+            }
+        }
+
+        // 10% of the inlet decays anyway; a 50% drop nets 40% of the inlet.
+        let mut row = phoenix_nodes()[0].row.clone();
+        row.cout = row.cin * 0.5;
+        let fraction = decay().decayed_fraction(Pollutant::PM25);
+        assert!((fraction - 0.0951626).abs() < 1e-6);
+        let removal = net_mass_removed(&row, Pollutant::PM25, 310.0, &decay()).unwrap();
+        let inlet_kg = removal.gross_kg * 2.0;
+        assert!((removal.net_kg - inlet_kg * (0.5 - fraction)).abs() < 1e-15);
+        assert_eq!(decay().decayed_fraction(Pollutant::CO), 0.0);
+    }
+
+    #[test]
+    fn config_selects_and_validates_the_baseline() {
+        let phoenix = include_str!("../config/phoenix.toml");
+        let cfg = ControllerConfig::from_toml(phoenix).unwrap();
+        assert_eq!(cfg.mass_baseline, MassBaseline::Zero);
+        assert_eq!(cfg.karma_basis, KarmaBasis::Gross);
+
+        let toml = format!(
+            "karma_basis = \"net\"\n{phoenix}\n{}",
+            r#"
+[mass_baseline]
+model = "decay"
+residence_s = 2.0
+
+[mass_baseline.rates_per_s]
+PM25 = 0.05
+"#
+        );
+        let cfg = ControllerConfig::from_toml(&toml).unwrap();
+        assert_eq!(cfg.karma_basis, KarmaBasis::Net);
+        assert_eq!(
+            cfg.mass_baseline,
+            MassBaseline::Decay(DecayBaseline::new(2.0).with_rate(Pollutant::PM25, 0.05))
+        );
+        assert_eq!(
+            ControllerConfig::from_toml(&toml::to_string(&cfg).unwrap()).unwrap(),
+            cfg
+        );
+
+        let bad = toml.replace("PM25 = 0.05", "PM25 = -0.05");
+        assert!(matches!(
+            ControllerConfig::from_toml(&bad),
+            Err(ConfigError::Invalid {
+                field: "mass_baseline",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,422 @@
+//! Deterministic scenario replay against a golden trace.
+//!
+//! A [`Scenario`] pins everything a run depends on: starting nodes, site
+//! altitudes, ambient conditions, controller configuration and the inputs
+//! of every step. [`Scenario::run`] uses no clock and no randomness, so the
+//! same scenario always yields the same [`Trace`]; [`compare`] checks it
+//! against a stored golden trace and names the first difference.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::units::P_STANDARD_PA;
+use crate::{
+    net_mass_removed_at_pressure, ConfigError, ControllerConfig, CorridorRow, EcoBand, MapAltitude,
+    MassRemoval, NodeState, OperationalMode, SafetyError, StepInput, StepRecord, UnitError,
+    UnknownPollutant,
+};
+
+/// Errors from loading or running a scenario.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ReplayError {
+    #[error("scenario json error: {0}")]
+    Json(String),
+    #[error("scenario has no step inputs")]
+    NoInputs,
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Pollutant(#[from] UnknownPollutant),
+    #[error(transparent)]
+    Unit(#[from] UnitError),
+    #[error(transparent)]
+    Safety(#[from] SafetyError),
+}
+
+/// Starting state of one node; mass and karma are derived from its row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScenarioNode {
+    pub row: CorridorRow,
+    pub duty_cycle: f64,
+    pub power_w: f64,
+    pub geo_weight: f64,
+    #[serde(default)]
+    pub noise_db: Option<f64>,
+    #[serde(default)]
+    pub emf_vpm: Option<f64>,
+}
+
+/// A recorded corridor and the exogenous inputs to replay over it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Scenario {
+    pub controller: ControllerConfig,
+    /// Site altitudes for the safety envelope, m.
+    pub altitudes: BTreeMap<String, f64>,
+    pub temperature_k: f64,
+    #[serde(default = "standard_pressure")]
+    pub pressure_pa: f64,
+    pub steps: usize,
+    pub nodes: Vec<ScenarioNode>,
+    /// Input for step `i` is `inputs[i]`; past the end the last one holds.
+    pub inputs: Vec<StepInput>,
+}
+
+fn standard_pressure() -> f64 {
+    P_STANDARD_PA
+}
+
+/// Per-node telemetry of one replayed step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TraceNode {
+    pub machine_id: String,
+    pub duty_cycle: f64,
+    pub mass_kg: f64,
+    pub karma_bytes: f64,
+    /// Display text of the error if the node was skipped.
+    pub error: Option<String>,
+}
+
+/// Corridor telemetry of one replayed step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TraceStep {
+    pub step: usize,
+    pub eco_load: f64,
+    pub eco_offset_applied: f64,
+    pub band: EcoBand,
+    pub nodes: Vec<TraceNode>,
+}
+
+impl From<&StepRecord> for TraceStep {
+    fn from(rec: &StepRecord) -> Self {
+        TraceStep {
+            step: rec.step,
+            eco_load: rec.eco_load,
+            eco_offset_applied: rec.eco_offset_applied,
+            band: rec.band,
+            nodes: rec
+                .nodes
+                .iter()
+                .map(|n| TraceNode {
+                    machine_id: n.machine_id.clone(),
+                    duty_cycle: n.duty_cycle,
+                    mass_kg: n.mass_kg,
+                    karma_bytes: n.karma_bytes,
+                    error: n.error.as_ref().map(|e| e.to_string()),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Telemetry of a whole replay, one entry per step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    /// Each node's starting removal against the baseline, by `machine_id`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub removals: BTreeMap<String, MassRemoval>,
+}
+
+impl Trace {
+    pub fn from_json(s: &str) -> Result<Self, ReplayError> {
+        serde_json::from_str(s).map_err(|e| ReplayError::Json(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, ReplayError> {
+        serde_json::to_string_pretty(self).map_err(|e| ReplayError::Json(e.to_string()))
+    }
+}
+
+impl Scenario {
+    pub fn from_json(s: &str) -> Result<Self, ReplayError> {
+        serde_json::from_str(s).map_err(|e| ReplayError::Json(e.to_string()))
+    }
+
+    /// Starting nodes with CEIM mass and karma at the scenario's conditions,
+    /// karma credited per the controller's `karma_basis`.
+    pub fn initial_nodes(&self) -> Result<Vec<NodeState>, ReplayError> {
+        self.nodes
+            .iter()
+            .map(|n| {
+                let removal = self.removal(n)?;
+                Ok(NodeState {
+                    karma_bytes: removal.karma_bytes(&n.row, self.controller.karma_basis),
+                    row: n.row.clone(),
+                    mass_kg: removal.gross_kg,
+                    duty_cycle: n.duty_cycle,
+                    power_w: n.power_w,
+                    geo_weight: n.geo_weight,
+                    noise_db: n.noise_db,
+                    emf_vpm: n.emf_vpm,
+                    suspicion: None,
+                    mode: OperationalMode::Active,
+                })
+            })
+            .collect()
+    }
+
+    /// Gross, baseline and net mass of each node's row, against the
+    /// controller's `mass_baseline`.
+    pub fn removals(&self) -> Result<BTreeMap<String, MassRemoval>, ReplayError> {
+        self.nodes
+            .iter()
+            .map(|n| Ok((n.row.machine_id.clone(), self.removal(n)?)))
+            .collect()
+    }
+
+    fn removal(&self, node: &ScenarioNode) -> Result<MassRemoval, ReplayError> {
+        Ok(net_mass_removed_at_pressure(
+            &node.row,
+            node.row.pollutant_kind()?,
+            self.temperature_k,
+            self.pressure_pa,
+            &self.controller.mass_baseline,
+        )?)
+    }
+
+    /// Replay every step and collect the telemetry.
+    pub fn run(&self) -> Result<Trace, ReplayError> {
+        let last = *self.inputs.last().ok_or(ReplayError::NoInputs)?;
+        let altitude = MapAltitude {
+            sites: self.altitudes.clone().into_iter().collect(),
+        };
+        let controller = self.controller.build(altitude)?;
+        let mut nodes = self.initial_nodes()?;
+        let log = controller.run_steps(&mut nodes, self.steps, |step| {
+            self.inputs.get(step).copied().unwrap_or(last)
+        })?;
+        Ok(Trace {
+            steps: log.iter().map(TraceStep::from).collect(),
+            removals: self.removals()?,
+        })
+    }
+}
+
+/// Allowed difference between replayed and golden values:
+/// `|actual - golden| <= abs + rel * |golden|`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Tolerance {
+    pub abs: f64,
+    pub rel: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            abs: 1e-12,
+            rel: 1e-9,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn accepts(&self, actual: f64, golden: f64) -> bool {
+        actual == golden || (actual - golden).abs() <= self.abs + self.rel * golden.abs()
+    }
+}
+
+/// First place a replay departs from its golden trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub step: usize,
+    /// `None` for corridor-level fields.
+    pub machine_id: Option<String>,
+    pub field: &'static str,
+    pub golden: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}", self.step)?;
+        if let Some(id) = &self.machine_id {
+            write!(f, ", node {id}")?;
+        }
+        write!(
+            f,
+            ", {}: golden {}, got {}",
+            self.field, self.golden, self.actual
+        )
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Compare `actual` with `golden` step by step, node by node, returning the
+/// first field outside `tol`. Bands, ids and errors must match exactly.
+pub fn compare(actual: &Trace, golden: &Trace, tol: Tolerance) -> Result<(), Divergence> {
+    fn differ<T: fmt::Debug>(
+        step: usize,
+        machine_id: Option<&str>,
+        field: &'static str,
+        golden: T,
+        actual: T,
+    ) -> Result<(), Divergence> {
+        Err(Divergence {
+            step,
+            machine_id: machine_id.map(str::to_string),
+            field,
+            golden: format!("{golden:?}"),
+            actual: format!("{actual:?}"),
+        })
+    }
+
+    for (a, g) in actual.steps.iter().zip(&golden.steps) {
+        let s = g.step;
+        if a.step != g.step {
+            return differ(s, None, "step", g.step, a.step);
+        }
+        for (field, av, gv) in [
+            ("eco_load", a.eco_load, g.eco_load),
+            (
+                "eco_offset_applied",
+                a.eco_offset_applied,
+                g.eco_offset_applied,
+            ),
+        ] {
+            if !tol.accepts(av, gv) {
+                return differ(s, None, field, gv, av);
+            }
+        }
+        if a.band != g.band {
+            return differ(s, None, "band", g.band, a.band);
+        }
+        for (an, gn) in a.nodes.iter().zip(&g.nodes) {
+            let id = Some(gn.machine_id.as_str());
+            if an.machine_id != gn.machine_id {
+                return differ(s, id, "machine_id", &gn.machine_id, &an.machine_id);
+            }
+            for (field, av, gv) in [
+                ("duty_cycle", an.duty_cycle, gn.duty_cycle),
+                ("mass_kg", an.mass_kg, gn.mass_kg),
+                ("karma_bytes", an.karma_bytes, gn.karma_bytes),
+            ] {
+                if !tol.accepts(av, gv) {
+                    return differ(s, id, field, gv, av);
+                }
+            }
+            if an.error != gn.error {
+                return differ(s, id, "error", &gn.error, &an.error);
+            }
+        }
+        if a.nodes.len() != g.nodes.len() {
+            return differ(s, None, "nodes", g.nodes.len(), a.nodes.len());
+        }
+    }
+    if actual.steps.len() != golden.steps.len() {
+        let step = actual.steps.len().min(golden.steps.len());
+        return differ(step, None, "steps", golden.steps.len(), actual.steps.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecayBaseline, KarmaBasis, MassBaseline, Pollutant};
+
+    const SCENARIO: &str = include_str!("../scenarios/phoenix_three_nodes.json");
+    const GOLDEN: &str = include_str!("../scenarios/phoenix_three_nodes.golden.json");
+
+    // Run with UPDATE_GOLDEN=1 after an intended behavior change to rewrite
+    // the golden file, then review its diff.
+    #[test]
+    fn phoenix_scenario_matches_golden_trace() {
+        let trace = Scenario::from_json(SCENARIO).unwrap().run().unwrap();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let path = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/scenarios/phoenix_three_nodes.golden.json"
+            );
+            std::fs::write(path, trace.to_json().unwrap() + "\n").unwrap();
+            return;
+        }
+        let golden = Trace::from_json(GOLDEN).unwrap();
+        if let Err(d) = compare(&trace, &golden, Tolerance::default()) {
+            panic!("replay diverged from golden trace at {d}");
+        }
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let scenario = Scenario::from_json(SCENARIO).unwrap();
+        let a = scenario.run().unwrap();
+        let b = scenario.run().unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.steps.len(), scenario.steps);
+    }
+
+    #[test]
+    fn gain_change_is_reported_at_first_divergent_field() {
+        let golden = Trace::from_json(GOLDEN).unwrap();
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.controller.gains.eta_w += 0.01;
+        let d = compare(&scenario.run().unwrap(), &golden, Tolerance::default()).unwrap_err();
+        assert_eq!(d.step, 0);
+        assert_eq!(d.machine_id.as_deref(), Some("CYB-AIR-CANOPY-01"));
+        assert_eq!(d.field, "duty_cycle");
+        assert!(d
+            .to_string()
+            .starts_with("step 0, node CYB-AIR-CANOPY-01, duty_cycle: golden "));
+
+        let mut short = golden.clone();
+        short.steps.pop();
+        let d = compare(&short, &golden, Tolerance::default()).unwrap_err();
+        assert_eq!((d.step, d.field), (golden.steps.len() - 1, "steps"));
+
+        let mut nudged = golden.clone();
+        nudged.steps[2].eco_load *= 1.0 + 1e-12;
+        assert!(compare(&nudged, &golden, Tolerance::default()).is_ok());
+        nudged.steps[2].eco_load *= 1.0 + 1e-6;
+        let d = compare(&nudged, &golden, Tolerance::default()).unwrap_err();
+        assert_eq!((d.step, d.machine_id, d.field), (2, None, "eco_load"));
+    }
+
+    #[test]
+    fn net_karma_basis_credits_only_net_removal() {
+        let gross = Scenario::from_json(SCENARIO).unwrap();
+        let mut net = gross.clone();
+        net.controller.mass_baseline = MassBaseline::Decay(
+            DecayBaseline::new(2.0)
+                .with_rate(Pollutant::PM25, 0.05)
+                .with_rate(Pollutant::NO2, 0.02)
+                .with_rate(Pollutant::O3, 0.1),
+        );
+        net.controller.karma_basis = KarmaBasis::Net;
+
+        let (g, n) = (gross.initial_nodes().unwrap(), net.initial_nodes().unwrap());
+        for (g, n) in g.iter().zip(&n) {
+            assert_eq!(n.mass_kg, g.mass_kg);
+            assert!(n.karma_bytes < g.karma_bytes);
+        }
+        let removals = net.run().unwrap().removals;
+        assert_eq!(removals.len(), net.nodes.len());
+        for node in &n {
+            let r = &removals[&node.row.machine_id];
+            assert_eq!(r.gross_kg, node.mass_kg);
+            assert!(r.net_kg < r.gross_kg && r.baseline_kg > 0.0);
+        }
+        assert!(gross
+            .run()
+            .unwrap()
+            .removals
+            .values()
+            .all(|r| r.net_kg == r.gross_kg));
+    }
+
+    #[test]
+    fn scenario_without_inputs_is_rejected() {
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.inputs.clear();
+        assert_eq!(scenario.run().unwrap_err(), ReplayError::NoInputs);
+    }
+}
@@ -0,0 +1,788 @@
+//! Long-running async control loop fed by live telemetry.
+//!
+//! Updates arrive on a bounded channel, so producers wait rather than
+//! queue without limit when the loop falls behind. Between ticks only the
+//! latest update per node is kept, which bounds the pending set by the
+//! number of nodes.
+//!
+//! Duty ramps arrive on a separate channel and move a node's duty along
+//! its trajectory, one tick at a time, ahead of each controller step.
+//!
+//! With a [`QuarantinePolicy`], nodes that keep failing a check are
+//! quarantined after each step and re-probed on later ones; see
+//! [`crate::quarantine`].
+//!
+//! A runtime over a config-built controller can swap in a new
+//! [`CorridorBundle`] between ticks. The bundle is validated in full, and
+//! against the nodes being run, before anything changes; a bundle that
+//! fails leaves the running config as it was.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::replay::TraceStep;
+use crate::{
+    checked_karma_bytes, checked_mass_kg, AltitudeProvider, BeeGuard, ChainAppender, ChainError,
+    ChainHead, ConfigChange, ConfigError, ControllerSnapshot, CorridorBundle, CorridorController,
+    DutyRamp, DwCeilingInvariant, EcoBandClassifier, HostBudget, MetricsRecorder, NodeState,
+    Quarantine, QuarantineEvent, QuarantinePolicy, QuarantinedNode, RampShape, RectSafetyEnvelope,
+    SafetyEnvelope, SafetyError, SimpleDwCeiling, SimpleHostBudget, SnapshotError, StepInput,
+    ThresholdEcoBand, QUARANTINE,
+};
+
+/// A live reading for one node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryUpdate {
+    pub machine_id: String,
+    pub cin: f64,
+    pub cout: f64,
+    pub airflow_m3_per_s: f64,
+    pub power_w: f64,
+    /// Unix milliseconds at the sensor.
+    pub timestamp_ms: u64,
+    /// Identifies the telemetry frame; once applied it becomes the node
+    /// row's `trace_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<Uuid>,
+}
+
+/// Move one node to `to` over `horizon_s`, starting from its duty when the
+/// command is received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RampCommand {
+    pub machine_id: String,
+    pub to: f64,
+    pub horizon_s: f64,
+    pub shape: RampShape,
+}
+
+/// Loop timing and the exogenous inputs held fixed between ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuntimeConfig {
+    pub tick: Duration,
+    /// Updates buffered before senders wait.
+    pub channel_capacity: usize,
+    /// Air temperature used to recompute mass from updated concentrations.
+    pub temperature_k: f64,
+    pub alpha_m: f64,
+    pub alpha_k: f64,
+    pub eco_offset: f64,
+}
+
+/// Corridor state after one tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepSummary {
+    #[serde(flatten)]
+    pub trace: TraceStep,
+    /// Updates applied this tick, at most one per node.
+    pub updates_applied: usize,
+    /// Updates replaced by a later one for the same node before the tick.
+    pub updates_superseded: usize,
+    /// Updates for unknown nodes, with non-finite values, or whose
+    /// concentrations could not be converted to finite mass and karma.
+    pub updates_rejected: usize,
+    /// Unix milliseconds of the newest reading applied, if any.
+    pub latest_reading_ms: Option<u64>,
+    /// Ramps still short of their target after this tick.
+    #[serde(default)]
+    pub ramps_active: usize,
+    /// Quarantine transitions this tick, in node order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quarantine_events: Vec<QuarantineEvent>,
+    /// Nodes in quarantine after this tick.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quarantined: Vec<QuarantinedNode>,
+    /// Quarantine transitions that could not be appended to the chain.
+    #[serde(default)]
+    pub chain_errors: usize,
+    /// Bundles swapped in since the previous tick.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_changes: Vec<ConfigChange>,
+    /// Reloads refused since the previous tick, with why.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reload_errors: Vec<String>,
+}
+
+/// Where the runtime appends quarantine transitions.
+trait QuarantineChain: Send + Sync {
+    fn append_event(&self, event: &QuarantineEvent) -> Result<ChainHead, ChainError>;
+}
+
+impl<C: ChainAppender + Send> QuarantineChain for Mutex<C> {
+    fn append_event(&self, event: &QuarantineEvent) -> Result<ChainHead, ChainError> {
+        self.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .append(QUARANTINE, event)
+    }
+}
+
+/// Owns a controller and its nodes and ticks them on an interval.
+pub struct CorridorRuntime<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    controller: CorridorController<E, H, B, D, G, R>,
+    nodes: Vec<NodeState>,
+    index: HashMap<String, usize>,
+    config: RuntimeConfig,
+    updates: mpsc::Receiver<TelemetryUpdate>,
+    pending: HashMap<usize, TelemetryUpdate>,
+    ramp_commands: Option<mpsc::Receiver<RampCommand>>,
+    /// Ramps by node index, with the seconds elapsed since each started.
+    ramps: HashMap<usize, (DutyRamp, f64)>,
+    quarantine: Option<(Quarantine, Arc<dyn QuarantineChain>)>,
+    bundle: Option<CorridorBundle>,
+    reload_requests: Option<mpsc::Receiver<PathBuf>>,
+    /// Set with `reload_requests`, where the controller can be rebuilt
+    /// from a bundle.
+    reloader: Option<Reloader<Self>>,
+    config_changes: Vec<ConfigChange>,
+    reload_errors: Vec<String>,
+    superseded: usize,
+    rejected: usize,
+    step: usize,
+    summary: watch::Sender<Option<StepSummary>>,
+}
+
+impl<E, H, B, D, G, R> CorridorRuntime<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// The runtime, a sender for telemetry and a receiver of the latest
+    /// step summary (`None` until the first tick). Nodes are keyed by
+    /// `machine_id`; a later duplicate shadows an earlier one.
+    pub fn new(
+        controller: CorridorController<E, H, B, D, G, R>,
+        nodes: Vec<NodeState>,
+        config: RuntimeConfig,
+    ) -> (
+        Self,
+        mpsc::Sender<TelemetryUpdate>,
+        watch::Receiver<Option<StepSummary>>,
+    ) {
+        let (tx, rx) = mpsc::channel(config.channel_capacity.max(1));
+        let (summary, summaries) = watch::channel(None);
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.row.machine_id.clone(), i))
+            .collect();
+        let runtime = CorridorRuntime {
+            controller,
+            nodes,
+            index,
+            config,
+            updates: rx,
+            pending: HashMap::new(),
+            ramp_commands: None,
+            ramps: HashMap::new(),
+            quarantine: None,
+            bundle: None,
+            reload_requests: None,
+            reloader: None,
+            config_changes: Vec::new(),
+            reload_errors: Vec::new(),
+            superseded: 0,
+            rejected: 0,
+            step: 0,
+            summary,
+        };
+        (runtime, tx, summaries)
+    }
+
+    /// A sender for duty ramps. Calling this again replaces the previous
+    /// channel. Commands for unknown nodes or with a non-finite target or
+    /// horizon are dropped; a new ramp for a node replaces the one in
+    /// progress.
+    pub fn ramp_sender(&mut self) -> mpsc::Sender<RampCommand> {
+        let (tx, rx) = mpsc::channel(self.config.channel_capacity.max(1));
+        self.ramp_commands = Some(rx);
+        tx
+    }
+
+    /// Quarantine nodes under `policy` from the next tick on, appending
+    /// each transition to `chain`. Calling this again starts over with the
+    /// new policy and chain; nodes already quarantined are re-probed.
+    pub fn enable_quarantine<C>(&mut self, policy: QuarantinePolicy, chain: Arc<Mutex<C>>)
+    where
+        C: ChainAppender + Send + 'static,
+    {
+        self.quarantine = Some((Quarantine::new(policy), chain));
+    }
+
+    /// Nodes in quarantine, with their failure history.
+    pub fn quarantined(&self) -> Vec<QuarantinedNode> {
+        self.quarantine
+            .as_ref()
+            .map_or_else(Vec::new, |(q, _)| q.quarantined())
+    }
+
+    /// State of the controller and nodes after the ticks run so far.
+    pub fn snapshot(&self) -> ControllerSnapshot {
+        self.controller.snapshot(self.step as u64, &self.nodes)
+    }
+
+    /// Resume from `snapshot`, e.g. one saved before a restart. Updates
+    /// held for the next tick, ramps in progress and failure streaks are
+    /// dropped; updates still on the channel are applied to the restored
+    /// nodes, and quarantined nodes are re-probed.
+    pub fn restore(&mut self, snapshot: &ControllerSnapshot) -> Result<(), SnapshotError> {
+        self.nodes = self.controller.restore(snapshot)?;
+        self.index = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.row.machine_id.clone(), i))
+            .collect();
+        self.pending.clear();
+        self.ramps.clear();
+        if let Some((quarantine, _)) = &mut self.quarantine {
+            *quarantine = Quarantine::new(quarantine.policy);
+        }
+        self.step = usize::try_from(snapshot.step).unwrap_or(usize::MAX);
+        Ok(())
+    }
+
+    /// Run until every sender is dropped. See `run_until`.
+    pub async fn run(self) -> Result<Vec<NodeState>, SafetyError> {
+        self.run_until(std::future::pending::<()>()).await
+    }
+
+    /// Tick every `config.tick` (the first tick is immediate) until
+    /// `shutdown` completes or every sender is dropped. Updates already
+    /// queued at that point are still applied in one last tick, and the
+    /// final node states are returned. An error from the controller's
+    /// corridor pass stops the loop.
+    pub async fn run_until<S: Future>(
+        mut self,
+        shutdown: S,
+    ) -> Result<Vec<NodeState>, SafetyError> {
+        let mut interval = tokio::time::interval(self.config.tick);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        tokio::pin!(shutdown);
+        let mut stopping = false;
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut shutdown, if !stopping => {
+                    // Refuse new updates but keep receiving the queued ones.
+                    self.updates.close();
+                    if let Some(commands) = &mut self.ramp_commands {
+                        commands.close();
+                    }
+                    if let Some(requests) = &mut self.reload_requests {
+                        requests.close();
+                    }
+                    stopping = true;
+                }
+                _ = interval.tick() => self.tick()?,
+                update = self.updates.recv() => match update {
+                    Some(update) => self.coalesce(update),
+                    None => break,
+                },
+                command = recv_or_pending(&mut self.ramp_commands) => match command {
+                    Some(command) => self.start_ramp(command),
+                    None => self.ramp_commands = None,
+                },
+                path = recv_or_pending(&mut self.reload_requests) => match path {
+                    Some(path) => self.reload_requested(&path),
+                    None => self.reload_requests = None,
+                },
+            }
+        }
+        if !self.pending.is_empty() || self.superseded > 0 || self.rejected > 0 {
+            self.tick()?;
+        }
+        Ok(self.nodes)
+    }
+
+    fn coalesce(&mut self, update: TelemetryUpdate) {
+        let finite = [
+            update.cin,
+            update.cout,
+            update.airflow_m3_per_s,
+            update.power_w,
+        ]
+        .iter()
+        .all(|v| v.is_finite());
+        let Some(&i) = self.index.get(&update.machine_id).filter(|_| finite) else {
+            self.rejected += 1;
+            return;
+        };
+        match self.pending.get(&i) {
+            Some(held) if held.timestamp_ms > update.timestamp_ms => self.superseded += 1,
+            Some(_) => {
+                self.superseded += 1;
+                self.pending.insert(i, update);
+            }
+            None => {
+                self.pending.insert(i, update);
+            }
+        }
+    }
+
+    fn reload_requested(&mut self, path: &Path) {
+        let Some(reload) = self.reloader else {
+            return;
+        };
+        if let Err(e) = reload(self, path) {
+            self.reload_errors.push(format!("{}: {e}", path.display()));
+        }
+    }
+
+    fn start_ramp(&mut self, command: RampCommand) {
+        let Some(&i) = self.index.get(&command.machine_id) else {
+            return;
+        };
+        if !command.to.is_finite() || !command.horizon_s.is_finite() {
+            return;
+        }
+        let ramp = DutyRamp {
+            from: self.nodes[i].duty_cycle,
+            to: command.to,
+            horizon_s: command.horizon_s,
+            shape: command.shape,
+        };
+        self.ramps.insert(i, (ramp, 0.0));
+    }
+
+    /// Apply held updates, advance ramps by one tick, run one controller
+    /// step, then update the quarantine.
+    fn tick(&mut self) -> Result<(), SafetyError> {
+        let mut applied = 0;
+        let mut latest_reading_ms = None;
+        for (i, update) in std::mem::take(&mut self.pending) {
+            let node = &mut self.nodes[i];
+            let mut row = node.row.clone();
+            row.cin = update.cin;
+            row.cout = update.cout;
+            row.airflow_m3_per_s = update.airflow_m3_per_s;
+            row.trace_id = update.trace_id.or(row.trace_id);
+            let load = row.pollutant_kind().ok().and_then(|p| {
+                let mass_kg = checked_mass_kg(&row, p, self.config.temperature_k).ok()?;
+                Some((mass_kg, checked_karma_bytes(&row, mass_kg).ok()?))
+            });
+            let Some((mass_kg, karma_bytes)) = load else {
+                self.rejected += 1;
+                continue;
+            };
+            node.mass_kg = mass_kg;
+            node.karma_bytes = karma_bytes;
+            node.row = row;
+            node.power_w = update.power_w;
+            applied += 1;
+            latest_reading_ms = latest_reading_ms.max(Some(update.timestamp_ms));
+        }
+
+        let tick_s = self.config.tick.as_secs_f64();
+        let nodes = &mut self.nodes;
+        self.ramps.retain(|&i, (ramp, elapsed_s)| {
+            *elapsed_s += tick_s;
+            nodes[i].duty_cycle = ramp.duty_at(*elapsed_s);
+            !ramp.is_done(*elapsed_s)
+        });
+
+        let input = StepInput {
+            phi_dw: self.controller.dw_flux_density(&self.nodes)?,
+            alpha_m: self.config.alpha_m,
+            alpha_k: self.config.alpha_k,
+            eco_offset: self.config.eco_offset,
+            wind: None,
+        };
+        let log = self.controller.run_steps(&mut self.nodes, 1, |_| input)?;
+        let mut trace = TraceStep::from(&log[0]);
+        trace.step = self.step;
+        let mut quarantine_events = Vec::new();
+        let mut quarantined = Vec::new();
+        let mut chain_errors = 0;
+        if let Some((quarantine, chain)) = &mut self.quarantine {
+            quarantine_events = quarantine.observe(
+                &self.controller,
+                &mut self.nodes,
+                &log[0],
+                self.step as u64,
+                input.phi_dw,
+            );
+            for event in &quarantine_events {
+                if chain.append_event(event).is_err() {
+                    chain_errors += 1;
+                }
+            }
+            quarantined = quarantine.quarantined();
+        }
+        self.step += 1;
+        self.summary.send_replace(Some(StepSummary {
+            trace,
+            updates_applied: applied,
+            updates_superseded: std::mem::take(&mut self.superseded),
+            updates_rejected: std::mem::take(&mut self.rejected),
+            latest_reading_ms,
+            ramps_active: self.ramps.len(),
+            quarantine_events,
+            quarantined,
+            chain_errors,
+            config_changes: std::mem::take(&mut self.config_changes),
+            reload_errors: std::mem::take(&mut self.reload_errors),
+        }));
+        Ok(())
+    }
+}
+
+/// Reloads the bundle at a path into a runtime.
+type Reloader<T> = fn(&mut T, &Path) -> Result<ConfigChange, ConfigError>;
+
+impl<A, G, R>
+    CorridorRuntime<
+        RectSafetyEnvelope<A>,
+        SimpleHostBudget,
+        ThresholdEcoBand,
+        SimpleDwCeiling,
+        G,
+        R,
+    >
+where
+    A: AltitudeProvider,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// The bundle last swapped in, if any.
+    pub fn bundle(&self) -> Option<&CorridorBundle> {
+        self.bundle.as_ref()
+    }
+
+    /// Swap in `bundle` for the next tick, if it validates and its
+    /// corridor holds every node being run; otherwise nothing changes.
+    /// The change is also reported in the next step summary.
+    pub fn apply_bundle(&mut self, bundle: CorridorBundle) -> Result<ConfigChange, ConfigError> {
+        bundle.validate()?;
+        let membership = bundle.membership();
+        if let Some(node) = self
+            .nodes
+            .iter()
+            .find(|n| !membership.contains(&n.row.machine_id))
+        {
+            return Err(ConfigError::Invalid {
+                field: "topology",
+                reason: format!(
+                    "{} is running but not in corridor {}",
+                    node.row.machine_id, bundle.corridor
+                ),
+            });
+        }
+        bundle.apply(&mut self.controller)?;
+        let change = ConfigChange {
+            corridor: bundle.corridor.clone(),
+            changed: match &self.bundle {
+                Some(old) => old.diff(&bundle),
+                None => vec![String::new()],
+            },
+        };
+        self.bundle = Some(bundle);
+        self.config_changes.push(change.clone());
+        Ok(change)
+    }
+
+    /// [`apply_bundle`](Self::apply_bundle) with the bundle at `path`; see
+    /// [`CorridorBundle::from_path`].
+    pub fn reload(&mut self, path: impl AsRef<Path>) -> Result<ConfigChange, ConfigError> {
+        self.apply_bundle(CorridorBundle::from_path(path)?)
+    }
+
+    /// A sender of bundle paths to [`reload`](Self::reload) between
+    /// ticks. Calling this again replaces the previous channel. Refused
+    /// reloads are reported in the next step summary.
+    pub fn reload_sender(&mut self) -> mpsc::Sender<PathBuf> {
+        let (tx, rx) = mpsc::channel(self.config.channel_capacity.max(1));
+        self.reload_requests = Some(rx);
+        self.reloader = Some(|runtime, path| runtime.reload(path));
+        tx
+    }
+}
+
+/// Next message, or never if there is no channel.
+async fn recv_or_pending<T>(rx: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{CorridorTopology, EcoBand, EventChain};
+
+    fn config(channel_capacity: usize) -> RuntimeConfig {
+        RuntimeConfig {
+            tick: Duration::from_secs(60),
+            channel_capacity,
+            temperature_k: 310.0,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+        }
+    }
+
+    fn update(machine_id: &str, cin: f64, timestamp_ms: u64) -> TelemetryUpdate {
+        TelemetryUpdate {
+            machine_id: machine_id.into(),
+            cin,
+            cout: 20.0,
+            airflow_m3_per_s: 2.0,
+            power_w: 40.0,
+            timestamp_ms,
+            trace_id: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn updates_between_ticks_coalesce_to_the_latest() {
+        let (runtime, tx, mut summaries) =
+            CorridorRuntime::new(phoenix_controller(), phoenix_nodes(), config(16));
+        let task = tokio::spawn(runtime.run());
+        summaries.changed().await.unwrap();
+        assert_eq!(summaries.borrow().as_ref().unwrap().trace.step, 0);
+
+        tx.send(update("CYB-AIR-CANOPY-01", 41.0, 1_000))
+            .await
+            .unwrap();
+        tx.send(update("CYB-AIR-CANOPY-01", 43.0, 3_000))
+            .await
+            .unwrap();
+        // Arrived late but older: does not replace the 3 s reading.
+        tx.send(update("CYB-AIR-CANOPY-01", 42.0, 2_000))
+            .await
+            .unwrap();
+        tx.send(update("CYB-AIR-UNKNOWN", 10.0, 3_000))
+            .await
+            .unwrap();
+
+        summaries.changed().await.unwrap();
+        let summary = summaries.borrow_and_update().clone().unwrap();
+        assert_eq!(summary.trace.step, 1);
+        assert_eq!(summary.updates_applied, 1);
+        assert_eq!(summary.updates_superseded, 2);
+        assert_eq!(summary.updates_rejected, 1);
+        assert_eq!(summary.latest_reading_ms, Some(3_000));
+
+        drop(tx);
+        let nodes = task.await.unwrap().unwrap();
+        assert_eq!(nodes[0].row.cin, 43.0);
+        assert_eq!(nodes[0].power_w, 40.0);
+        assert_eq!(nodes[1].row.cin, phoenix_nodes()[1].row.cin);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn full_channel_makes_senders_wait() {
+        let (runtime, tx, summaries) =
+            CorridorRuntime::new(phoenix_controller(), phoenix_nodes(), config(2));
+        tx.try_send(update("CYB-AIR-CANOPY-01", 41.0, 1)).unwrap();
+        tx.try_send(update("CYB-AIR-SCHOOL-05", 31.0, 1)).unwrap();
+        assert!(matches!(
+            tx.try_send(update("CYB-AIR-CANOPY-01", 42.0, 2)),
+            Err(mpsc::error::TrySendError::Full(_))
+        ));
+        let blocked = tokio::time::timeout(
+            Duration::from_secs(1),
+            tx.send(update("CYB-AIR-CANOPY-01", 42.0, 2)),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Shutting down drains what was queued into one last tick.
+        drop(tx);
+        let nodes = runtime.run_until(async {}).await.unwrap();
+        assert_eq!(nodes[0].row.cin, 41.0);
+        assert_eq!(nodes[1].row.cin, 31.0);
+        let summary = summaries.borrow().clone().unwrap();
+        assert_eq!(summary.updates_applied, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ramps_move_duty_along_their_shape_over_ticks() {
+        let (mut runtime, tx, mut summaries) =
+            CorridorRuntime::new(phoenix_controller(), phoenix_nodes(), config(4));
+        let ramps = runtime.ramp_sender();
+        let task = tokio::spawn(runtime.run());
+        summaries.changed().await.unwrap();
+        ramps
+            .send(RampCommand {
+                machine_id: "CYB-AIR-CANOPY-01".into(),
+                to: 0.9,
+                horizon_s: 180.0,
+                shape: RampShape::Linear,
+            })
+            .await
+            .unwrap();
+
+        // Mirror the loop: the ramp sets the duty going into each step.
+        let controller = phoenix_controller();
+        let mut mirror = phoenix_nodes();
+        let step = |nodes: &mut Vec<NodeState>| {
+            let input = StepInput {
+                phi_dw: controller.dw_flux_density(nodes).unwrap(),
+                alpha_m: 0.5,
+                alpha_k: 0.5,
+                eco_offset: 0.0,
+                wind: None,
+            };
+            controller.run_steps(nodes, 1, |_| input).unwrap();
+        };
+        step(&mut mirror);
+        let from = mirror[0].duty_cycle;
+        for (k, active) in [(1, 1), (2, 1), (3, 0)] {
+            mirror[0].duty_cycle = from + (0.9 - from) * f64::from(k) / 3.0;
+            step(&mut mirror);
+            summaries.changed().await.unwrap();
+            let summary = summaries.borrow_and_update().clone().unwrap();
+            assert_eq!(summary.ramps_active, active, "tick {k}");
+            let duties: Vec<_> = summary.trace.nodes.iter().map(|n| n.duty_cycle).collect();
+            let expected: Vec<_> = mirror.iter().map(|n| n.duty_cycle).collect();
+            assert_eq!(duties, expected, "tick {k}");
+        }
+
+        drop((tx, ramps));
+        let nodes = task.await.unwrap().unwrap();
+        assert_eq!(nodes[0].duty_cycle, mirror[0].duty_cycle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_bundles_that_validate_are_swapped_in() {
+        let path = |name: &str| {
+            std::env::temp_dir().join(format!("cyboair-bundle-{}-{name}", std::process::id()))
+        };
+        let (good, stray) = (path("green.toml"), path("stray.json"));
+        let mut bundle =
+            CorridorBundle::from_toml(include_str!("../config/phoenix_bundle.toml")).unwrap();
+        // Thresholds the Phoenix load is far below.
+        bundle.controller.eco_band.theta_green_amber = 1.0e9;
+        bundle.controller.eco_band.theta_amber_red = 2.0e9;
+        std::fs::write(&good, toml::to_string(&bundle).unwrap()).unwrap();
+        // Valid on its own, but drops a node the runtime is running.
+        let mut topology = CorridorTopology::new();
+        topology
+            .register(bundle.topology.get("CYB-AIR-CANOPY-01").unwrap().clone())
+            .unwrap();
+        let mut stray_bundle = CorridorBundle {
+            topology,
+            ..bundle.clone()
+        };
+        stray_bundle.controller.gain_schedule.nodes.clear();
+        std::fs::write(&stray, serde_json::to_string(&stray_bundle).unwrap()).unwrap();
+
+        let (mut runtime, tx, mut summaries) =
+            CorridorRuntime::new(phoenix_controller(), phoenix_nodes(), config(4));
+        let reloads = runtime.reload_sender();
+        let task = tokio::spawn(runtime.run());
+        summaries.changed().await.unwrap();
+        let first = summaries.borrow_and_update().clone().unwrap();
+        assert_ne!(first.trace.band, EcoBand::Green);
+
+        reloads.send(stray.clone()).await.unwrap();
+        summaries.changed().await.unwrap();
+        let summary = summaries.borrow_and_update().clone().unwrap();
+        assert_eq!(summary.reload_errors.len(), 1);
+        assert!(
+            summary.reload_errors[0].contains("CYB-AIR-SCHOOL-05 is running"),
+            "{:?}",
+            summary.reload_errors
+        );
+        assert!(summary.config_changes.is_empty());
+        assert_eq!(summary.trace.band, first.trace.band);
+
+        reloads.send(good.clone()).await.unwrap();
+        summaries.changed().await.unwrap();
+        let summary = summaries.borrow_and_update().clone().unwrap();
+        assert!(summary.reload_errors.is_empty());
+        assert_eq!(
+            summary.config_changes,
+            [ConfigChange {
+                corridor: "phoenix-a".into(),
+                changed: vec![String::new()],
+            }]
+        );
+        assert_eq!(summary.trace.band, EcoBand::Green);
+
+        drop((tx, reloads));
+        task.await.unwrap().unwrap();
+        std::fs::remove_file(good).unwrap();
+        std::fs::remove_file(stray).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failing_nodes_are_quarantined_onto_the_chain_and_released() {
+        let (mut runtime, tx, mut summaries) =
+            CorridorRuntime::new(phoenix_controller(), phoenix_nodes(), config(4));
+        let chain = Arc::new(Mutex::new(EventChain::new()));
+        runtime.enable_quarantine(
+            QuarantinePolicy {
+                failure_threshold: 2,
+                restore_after_passes: 1,
+                initial_backoff_steps: 1,
+                ..QuarantinePolicy::default()
+            },
+            chain.clone(),
+        );
+        let task = tokio::spawn(runtime.run());
+        summaries.changed().await.unwrap();
+
+        // A stuck power sensor fails the host budget on steps 1 and 2.
+        tx.send(TelemetryUpdate {
+            power_w: 1.0e6,
+            ..update("CYB-AIR-CANOPY-01", 41.0, 1_000)
+        })
+        .await
+        .unwrap();
+        summaries.changed().await.unwrap();
+        summaries.changed().await.unwrap();
+        let summary = summaries.borrow_and_update().clone().unwrap();
+        assert_eq!(summary.trace.step, 2);
+        assert!(matches!(
+            summary.quarantine_events[..],
+            [QuarantineEvent::Quarantined { step: 2, .. }]
+        ));
+        assert_eq!(summary.quarantined.len(), 1);
+        assert_eq!(summary.quarantined[0].machine_id, "CYB-AIR-CANOPY-01");
+        assert_eq!(summary.quarantined[0].history.len(), 2);
+        assert_eq!(summary.chain_errors, 0);
+
+        // Still ingesting telemetry: a sane reading lets the probe pass.
+        tx.send(update("CYB-AIR-CANOPY-01", 42.0, 2_000))
+            .await
+            .unwrap();
+        summaries.changed().await.unwrap();
+        let summary = summaries.borrow_and_update().clone().unwrap();
+        assert!(matches!(
+            summary.quarantine_events[..],
+            [QuarantineEvent::Restored { step: 3, .. }]
+        ));
+        assert!(summary.quarantined.is_empty());
+
+        drop(tx);
+        let nodes = task.await.unwrap().unwrap();
+        assert!(nodes[0].mode.is_active());
+        assert_eq!(nodes[0].row.cin, 42.0);
+        let chain = chain.lock().unwrap();
+        assert_eq!(chain.verify().unwrap().len, 2);
+        assert_eq!(chain.entries()[1].payload["event"], "restored");
+    }
+}
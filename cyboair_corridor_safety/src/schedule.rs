@@ -0,0 +1,432 @@
+//! Weekly duty caps, e.g. a noise-ordinance curfew near housing.
+//!
+//! A [`DutySchedule`] is a list of [`ScheduleWindow`]s in node-local time.
+//! Each window opens at `start_minute` on each of its `days` and closes at
+//! `end_minute`, the next day if `end_minute <= start_minute`; a window
+//! opening on Sunday night closes on Monday morning. While a window is
+//! open the node's duty is capped at its `max_duty`, or at 0 if it is
+//! `sensing_only`. Overlapping windows resolve to the lowest cap.
+//!
+//! The controller applies the cap last, after the update law, the slew
+//! limit and the bee guard, and records the window in
+//! [`UpdateReport::schedule_cap`](crate::UpdateReport::schedule_cap).
+//! Local time is UTC plus the node's entry in `utc_offset_minutes`, else
+//! `default_utc_offset_minutes`; daylight saving is not modelled.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::config::ConfigError;
+
+pub const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+/// Largest UTC offset in use, +14:00.
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Days after Monday.
+    fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// One recurring window and the cap it imposes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScheduleWindow {
+    /// Label recorded in the update report.
+    pub name: String,
+    /// Days the window opens on; empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    /// Minutes after local midnight, in [0, 1440).
+    pub start_minute: u32,
+    /// Minutes after local midnight, in [0, 1440); at or before
+    /// `start_minute` means the next day.
+    pub end_minute: u32,
+    /// Duty ceiling while open, in [0, 1].
+    pub max_duty: f64,
+    /// Blowers off while open; the node keeps sensing.
+    #[serde(default)]
+    pub sensing_only: bool,
+}
+
+impl ScheduleWindow {
+    /// The duty ceiling while open.
+    pub fn cap(&self) -> f64 {
+        if self.sensing_only {
+            0.0
+        } else {
+            self.max_duty
+        }
+    }
+
+    fn length_minutes(&self) -> u32 {
+        if self.end_minute > self.start_minute {
+            self.end_minute - self.start_minute
+        } else {
+            MINUTES_PER_DAY - self.start_minute + self.end_minute
+        }
+    }
+
+    /// True if open at `minute_of_week`, counted from local Monday 00:00.
+    pub fn is_open(&self, minute_of_week: u32) -> bool {
+        let length = self.length_minutes();
+        let open_on = |day: Weekday| {
+            let start = day.index() * MINUTES_PER_DAY + self.start_minute;
+            (minute_of_week + MINUTES_PER_WEEK - start) % MINUTES_PER_WEEK < length
+        };
+        if self.days.is_empty() {
+            Weekday::ALL.into_iter().any(open_on)
+        } else {
+            self.days.iter().copied().any(open_on)
+        }
+    }
+}
+
+/// Weekly duty caps; see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DutySchedule {
+    pub windows: Vec<ScheduleWindow>,
+    /// Local time of nodes without an entry in `utc_offset_minutes`.
+    #[serde(default)]
+    pub default_utc_offset_minutes: i32,
+    /// Per-node UTC offsets, keyed by `machine_id`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub utc_offset_minutes: BTreeMap<String, i32>,
+}
+
+impl DutySchedule {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: String| {
+            Err(ConfigError::Invalid {
+                field: "duty_schedule",
+                reason,
+            })
+        };
+        for w in &self.windows {
+            for (name, minute) in [
+                ("start_minute", w.start_minute),
+                ("end_minute", w.end_minute),
+            ] {
+                if minute >= MINUTES_PER_DAY {
+                    return invalid(format!(
+                        "{}.{name} must be below {MINUTES_PER_DAY}, got {minute}",
+                        w.name
+                    ));
+                }
+            }
+            if !(0.0..=1.0).contains(&w.max_duty) {
+                return invalid(format!(
+                    "{}.max_duty must be in [0, 1], got {}",
+                    w.name, w.max_duty
+                ));
+            }
+        }
+        let offsets = std::iter::once(("default", &self.default_utc_offset_minutes)).chain(
+            self.utc_offset_minutes
+                .iter()
+                .map(|(id, o)| (id.as_str(), o)),
+        );
+        for (node, offset) in offsets {
+            if offset.abs() > MAX_UTC_OFFSET_MINUTES {
+                return invalid(format!(
+                    "UTC offset for {node} must be within +/-{MAX_UTC_OFFSET_MINUTES} minutes, got {offset}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn utc_offset_for(&self, machine_id: &str) -> i32 {
+        self.utc_offset_minutes
+            .get(machine_id)
+            .copied()
+            .unwrap_or(self.default_utc_offset_minutes)
+    }
+
+    /// Minutes since local Monday 00:00 for `machine_id` at `now_unix_ms`.
+    pub fn minute_of_week(&self, machine_id: &str, now_unix_ms: u64) -> u32 {
+        let utc_minutes = (now_unix_ms / 60_000) as i64;
+        let local = utc_minutes + i64::from(self.utc_offset_for(machine_id));
+        // 1970-01-01 was a Thursday, three days after Monday.
+        let since_monday = local + 3 * i64::from(MINUTES_PER_DAY);
+        since_monday.rem_euclid(i64::from(MINUTES_PER_WEEK)) as u32
+    }
+
+    /// The open window with the lowest cap for `machine_id`, the first
+    /// listed on ties; `None` outside every window.
+    pub fn binding_window(&self, machine_id: &str, now_unix_ms: u64) -> Option<&ScheduleWindow> {
+        let minute = self.minute_of_week(machine_id, now_unix_ms);
+        self.windows.iter().filter(|w| w.is_open(minute)).fold(
+            None,
+            |lowest: Option<&ScheduleWindow>, w| match lowest {
+                Some(l) if l.cap() <= w.cap() => Some(l),
+                _ => Some(w),
+            },
+        )
+    }
+}
+
+/// A schedule and the clock it is read against.
+#[derive(Clone)]
+pub struct ClockedSchedule {
+    pub schedule: DutySchedule,
+    pub clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl ClockedSchedule {
+    pub fn new(schedule: DutySchedule, clock: impl Clock + Send + Sync + 'static) -> Self {
+        ClockedSchedule {
+            schedule,
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// The window binding `machine_id` now.
+    pub fn binding_window(&self, machine_id: &str) -> Option<&ScheduleWindow> {
+        self.schedule
+            .binding_window(machine_id, self.clock.now_unix_ms())
+    }
+}
+
+impl fmt::Debug for ClockedSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClockedSchedule")
+            .field("schedule", &self.schedule)
+            .field("now_unix_ms", &self.clock.now_unix_ms())
+            .finish()
+    }
+}
+
+/// The schedule window open during a duty update.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScheduleCap {
+    pub window: String,
+    /// The window's cap; 0 if it is sensing-only.
+    pub max_duty: f64,
+    pub sensing_only: bool,
+    /// Duty before the cap.
+    pub proposed: f64,
+    /// Duty after it; below `proposed` only if the cap bound.
+    pub applied: f64,
+}
+
+impl ScheduleCap {
+    pub fn bound(&self) -> bool {
+        self.applied < self.proposed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{
+        ConstAltitude, ControllerConfig, CorridorController, EcoBand, NodeState,
+        RectSafetyEnvelope, SimpleDwCeiling, SimpleHostBudget, SlewLimit, ThresholdEcoBand,
+        UpdateReport,
+    };
+
+    const MINUTE_MS: u64 = 60_000;
+    const HOUR_MS: u64 = 60 * MINUTE_MS;
+    /// Monday 2025-06-02 00:00 UTC.
+    const MONDAY: u64 = 1_748_822_400_000;
+
+    fn curfew() -> DutySchedule {
+        DutySchedule {
+            windows: vec![
+                ScheduleWindow {
+                    name: "noise_curfew".into(),
+                    days: Vec::new(),
+                    start_minute: 22 * 60,
+                    end_minute: 6 * 60,
+                    max_duty: 0.3,
+                    sensing_only: false,
+                },
+                ScheduleWindow {
+                    name: "sunday_night_quiet".into(),
+                    days: vec![Weekday::Sunday],
+                    start_minute: 23 * 60,
+                    end_minute: 2 * 60,
+                    max_duty: 1.0,
+                    sensing_only: true,
+                },
+            ],
+            default_utc_offset_minutes: 0,
+            utc_offset_minutes: BTreeMap::new(),
+        }
+    }
+
+    fn binding(schedule: &DutySchedule, at: u64) -> Option<&str> {
+        schedule
+            .binding_window("CYB-AIR-CANOPY-01", at)
+            .map(|w| w.name.as_str())
+    }
+
+    #[test]
+    fn windows_cross_midnight_and_the_week_boundary() {
+        let schedule = curfew();
+        assert_eq!(schedule.minute_of_week("any", MONDAY), 0);
+        assert_eq!(binding(&schedule, MONDAY + 12 * HOUR_MS), None);
+        assert_eq!(
+            binding(&schedule, MONDAY + 22 * HOUR_MS),
+            Some("noise_curfew")
+        );
+        assert_eq!(
+            binding(&schedule, MONDAY + 29 * HOUR_MS + 59 * MINUTE_MS),
+            Some("noise_curfew")
+        );
+        assert_eq!(binding(&schedule, MONDAY + 30 * HOUR_MS), None);
+        assert_eq!(binding(&schedule, MONDAY + 22 * HOUR_MS - MINUTE_MS), None);
+
+        // Sunday 23:00 to Monday 02:00: the sensing-only window is lower.
+        let sunday_2330 = MONDAY + 6 * 24 * HOUR_MS + 23 * HOUR_MS + 30 * MINUTE_MS;
+        assert_eq!(binding(&schedule, sunday_2330), Some("sunday_night_quiet"));
+        let next_monday = MONDAY + 7 * 24 * HOUR_MS;
+        assert_eq!(
+            binding(&schedule, next_monday + HOUR_MS),
+            Some("sunday_night_quiet")
+        );
+        assert_eq!(
+            binding(&schedule, next_monday + 3 * HOUR_MS),
+            Some("noise_curfew")
+        );
+        // The Sunday window does not open on Monday night.
+        assert_eq!(
+            binding(&schedule, MONDAY + 23 * HOUR_MS + 30 * MINUTE_MS),
+            Some("noise_curfew")
+        );
+
+        // Seven hours west, 05:00 UTC is 22:00 local.
+        let mut west = curfew();
+        west.utc_offset_minutes
+            .insert("CYB-AIR-CANOPY-01".into(), -7 * 60);
+        assert_eq!(binding(&west, MONDAY + 5 * HOUR_MS), Some("noise_curfew"));
+        assert_eq!(binding(&west, MONDAY + 14 * HOUR_MS), None);
+    }
+
+    type Controller = CorridorController<
+        RectSafetyEnvelope<ConstAltitude>,
+        SimpleHostBudget,
+        ThresholdEcoBand,
+        SimpleDwCeiling,
+    >;
+
+    fn step(controller: &Controller, node: &mut NodeState) -> UpdateReport {
+        controller
+            .update_node_duty(node, EcoBand::Green, 0.0)
+            .unwrap()
+    }
+
+    #[test]
+    fn cap_binds_only_inside_the_window() {
+        let clock = ManualClock::new(MONDAY + 12 * HOUR_MS);
+        let mut controller = phoenix_controller();
+        controller.slew_limit = Some(SlewLimit::symmetric(1.0));
+        controller.duty_schedule = Some(ClockedSchedule::new(curfew(), clock.clone()));
+        let mut node = phoenix_nodes()[0].clone();
+        node.duty_cycle = 0.9;
+
+        let day = step(&controller, &mut node.clone());
+        assert_eq!(day.schedule_cap, None);
+        assert!(day.duty_after > 0.3);
+
+        clock.set(MONDAY + 23 * HOUR_MS);
+        let night = step(&controller, &mut node);
+        let cap = night.schedule_cap.clone().unwrap();
+        assert_eq!(cap.window, "noise_curfew");
+        assert!(cap.bound());
+        assert_eq!(cap.proposed, day.duty_after);
+        assert_eq!(night.duty_after, 0.3);
+        assert_eq!(node.duty_cycle, 0.3);
+
+        // Below the cap it is recorded but does not bind.
+        node.duty_cycle = 0.0;
+        controller.eta_m = 0.0;
+        let quiet = step(&controller, &mut node);
+        assert!(!quiet.schedule_cap.unwrap().bound());
+
+        clock.set(MONDAY + 6 * 24 * HOUR_MS + 23 * HOUR_MS + 30 * MINUTE_MS);
+        node.duty_cycle = 0.3;
+        let sunday = step(&controller, &mut node);
+        assert!(sunday.schedule_cap.unwrap().sensing_only);
+        assert_eq!(sunday.duty_after, 0.0);
+    }
+
+    #[test]
+    fn schedule_loads_from_toml_and_is_validated() {
+        let toml = format!(
+            "{}\n{}",
+            include_str!("../config/phoenix.toml"),
+            r#"
+[duty_schedule]
+default_utc_offset_minutes = -420
+
+[[duty_schedule.windows]]
+name = "noise_curfew"
+start_minute = 1320
+end_minute = 360
+max_duty = 0.3
+
+[[duty_schedule.windows]]
+name = "sunday_night_quiet"
+days = ["sunday"]
+start_minute = 1380
+end_minute = 120
+max_duty = 1.0
+sensing_only = true
+"#
+        );
+        let cfg = ControllerConfig::from_toml(&toml).unwrap();
+        let schedule = cfg.duty_schedule.as_ref().unwrap();
+        assert_eq!(schedule.windows[1].days, [Weekday::Sunday]);
+        assert_eq!(
+            ControllerConfig::from_toml(&toml::to_string(&cfg).unwrap()).unwrap(),
+            cfg
+        );
+        let controller = cfg.build(ConstAltitude(331.0)).unwrap();
+        assert_eq!(controller.duty_schedule.unwrap().schedule, schedule.clone());
+
+        let bad = toml.replace("start_minute = 1320", "start_minute = 1440");
+        assert!(matches!(
+            ControllerConfig::from_toml(&bad),
+            Err(ConfigError::Invalid {
+                field: "duty_schedule",
+                reason,
+            }) if reason.starts_with("noise_curfew.start_minute")
+        ));
+        let bad = toml.replace("max_duty = 0.3", "max_duty = 1.3");
+        assert!(ControllerConfig::from_toml(&bad).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBand, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope, SafetyError, StabilityRecord, Wind,
+};
+
+/// Exogenous inputs for one simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StepInput {
+    /// Corridor DW flux density for this step.
+    pub phi_dw: f64,
+    /// Eco-load weight on normalized mass.
+    pub alpha_m: f64,
+    /// Eco-load weight on normalized karma.
+    pub alpha_k: f64,
+    /// Credit subtracted from the eco-load before classification; 0 for none.
+    #[serde(default)]
+    pub eco_offset: f64,
+    /// Wind for the controller's `geo_weighting`; `None` if not measured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind: Option<Wind>,
+}
+
+/// Per-node outcome of one step.
+#[derive(Debug, Clone)]
+pub struct NodeStepRecord {
+    pub machine_id: String,
+    /// Duty going into the step.
+    pub duty_before: f64,
+    pub duty_cycle: f64,
+    /// At `duty_cycle` if the controller has an `airflow` model.
+    pub mass_kg: f64,
+    pub karma_bytes: f64,
+    /// Set if the node was skipped this step.
+    pub error: Option<SafetyError>,
+}
+
+/// Corridor snapshot after one step.
+#[derive(Debug, Clone)]
+pub struct StepRecord {
+    pub step: usize,
+    /// Net eco-load the band was classified on.
+    pub eco_load: f64,
+    /// Part of `StepInput::eco_offset` that was subtracted this step.
+    pub eco_offset_applied: f64,
+    pub band: EcoBand,
+    pub nodes: Vec<NodeStepRecord>,
+    /// Corridor potential before and after the step, if monitored.
+    pub stability: Option<StabilityRecord>,
+}
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// Run `steps` controller ticks over `nodes`, recomputing eco-load and band
+    /// each tick. A node whose update fails keeps its duty cycle, the error is
+    /// recorded, and the rest of the corridor continues. An eco-load error
+    /// (bad references, weights or offset, non-finite node) or a stability
+    /// `Error` policy violation aborts the run.
+    ///
+    /// With an `airflow` model, eco-load is evaluated on each node's mass
+    /// and karma at its duty going into the step, and the records report
+    /// them at the duty coming out.
+    pub fn run_steps<F>(
+        &self,
+        nodes: &mut [NodeState],
+        steps: usize,
+        mut input: F,
+    ) -> Result<Vec<StepRecord>, SafetyError>
+    where
+        F: FnMut(usize) -> StepInput,
+    {
+        let mut log = Vec::with_capacity(steps);
+        for step in 0..steps {
+            let inp = input(step);
+            let load = match &self.airflow {
+                Some(airflow) => {
+                    let projected: Vec<NodeState> =
+                        nodes.iter().map(|n| airflow.project_node(n)).collect();
+                    self.eco_load_with_offset(&projected, inp.alpha_m, inp.alpha_k, inp.eco_offset)?
+                }
+                None => {
+                    self.eco_load_with_offset(&*nodes, inp.alpha_m, inp.alpha_k, inp.eco_offset)?
+                }
+            };
+            let band = self.eco_band.classify(load.net);
+
+            let before: Vec<f64> = nodes.iter().map(|n| n.duty_cycle).collect();
+            let pass = self.update_pass_in_wind(nodes, band, inp.phi_dw, inp.wind)?;
+            let records = nodes
+                .iter()
+                .zip(before)
+                .zip(pass.results)
+                .map(|((node, duty_before), result)| {
+                    let (mass_kg, karma_bytes) = match &self.airflow {
+                        Some(airflow) => {
+                            let p = airflow.project_node(node);
+                            (p.mass_kg, p.karma_bytes)
+                        }
+                        None => (node.mass_kg, node.karma_bytes),
+                    };
+                    NodeStepRecord {
+                        machine_id: node.row.machine_id.clone(),
+                        duty_before,
+                        duty_cycle: node.duty_cycle,
+                        mass_kg,
+                        karma_bytes,
+                        error: result.err(),
+                    }
+                })
+                .collect();
+
+            log.push(StepRecord {
+                step,
+                eco_load: load.net,
+                eco_offset_applied: load.offset_applied,
+                band,
+                nodes: records,
+                stability: pass.stability,
+            });
+        }
+        Ok(log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{StabilityMonitor, StabilityPolicy};
+
+    fn steady(_: usize) -> StepInput {
+        StepInput {
+            phi_dw: 5.0e-7,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        }
+    }
+
+    #[test]
+    fn fifty_steps_converge_within_unit_interval() {
+        let mut controller = phoenix_controller();
+        // Strong power penalty so the fixed point is interior-bounded.
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        controller.eta_w = 0.0;
+        controller.eta_b = 0.0;
+        controller.eta_p = 0.1;
+        let mut nodes = phoenix_nodes();
+
+        let log = controller.run_steps(&mut nodes, 50, steady).unwrap();
+        assert_eq!(log.len(), 50);
+        for rec in &log {
+            for n in &rec.nodes {
+                assert!((0.0..=1.0).contains(&n.duty_cycle));
+                assert!(n.error.is_none());
+            }
+        }
+        let last = &log[49].nodes;
+        let prev = &log[48].nodes;
+        for (a, b) in last.iter().zip(prev.iter()) {
+            assert!((a.duty_cycle - b.duty_cycle).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn failing_node_is_recorded_and_skipped() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        nodes[1].row.ecoimpact_score = 0.1; // outside envelope
+        let stuck_duty = nodes[1].duty_cycle;
+
+        let log = controller.run_steps(&mut nodes, 50, steady).unwrap();
+        for rec in &log {
+            assert!(rec.nodes[0].error.is_none());
+            assert!(rec.nodes[1].error.is_some());
+            assert_eq!(rec.nodes[1].duty_cycle, stuck_duty);
+        }
+        assert!((0.0..=1.0).contains(&nodes[0].duty_cycle));
+    }
+
+    #[test]
+    fn monitored_run_records_potential_per_step() {
+        let mut controller = phoenix_controller();
+        controller.stability = Some(StabilityMonitor::new(StabilityPolicy::Warn, 1e-9));
+        let mut nodes = phoenix_nodes();
+        let log = controller.run_steps(&mut nodes, 5, steady).unwrap();
+        for rec in &log {
+            let s = rec.stability.unwrap();
+            // Mass and karma are fixed, so the load potential is flat.
+            assert_eq!(s.v_before, s.v_after);
+            assert!(!s.increased);
+        }
+        assert!(phoenix_controller()
+            .run_steps(&mut nodes, 1, steady)
+            .unwrap()[0]
+            .stability
+            .is_none());
+    }
+
+    #[test]
+    fn eco_offset_lowers_classified_load_and_is_reported() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        let gross = controller.eco_load(&nodes, 0.5, 0.5).unwrap();
+        let log = controller
+            .run_steps(&mut nodes, 2, |step| StepInput {
+                eco_offset: if step == 0 { 0.4 * gross } else { 2.0 * gross },
+                ..steady(step)
+            })
+            .unwrap();
+        assert!((log[0].eco_load - 0.6 * gross).abs() < 1e-9 * gross);
+        assert!((log[0].eco_offset_applied - 0.4 * gross).abs() < 1e-9 * gross);
+        assert_eq!(log[1].eco_load, 0.0);
+        assert_eq!(log[1].eco_offset_applied, gross);
+        assert_eq!(log[1].band, controller.eco_band.classify(0.0));
+    }
+
+    #[test]
+    fn invalid_weights_abort_the_run() {
+        let controller = phoenix_controller();
+        let mut nodes = phoenix_nodes();
+        let result = controller.run_steps(&mut nodes, 5, |_| StepInput {
+            phi_dw: 5.0e-7,
+            alpha_m: 0.0,
+            alpha_k: 0.0,
+            eco_offset: 0.0,
+            wind: None,
+        });
+        assert!(matches!(
+            result,
+            Err(SafetyError::InvalidLoadWeights { .. })
+        ));
+    }
+}
@@ -0,0 +1,349 @@
+//! Optional smoothing of telemetry-derived mass and karma.
+//!
+//! One glitched `cin` reading gives a one-period mass spike, which the
+//! duty law integrates into a lasting actuation jump. Before the update,
+//! [`NodeSmoothing::apply`] passes each of `mass_kg` and `karma_bytes`
+//! through two stages:
+//!
+//! 1. an outlier gate: a reading more than `mad_k` median absolute
+//!    deviations from the median of the last `window` raw readings is
+//!    replaced by that median and flagged;
+//! 2. an EWMA, `s = alpha * x + (1 - alpha) * s_prev`, seeded with the
+//!    first reading.
+//!
+//! The gate stays open until the window holds `MIN_GATE_SAMPLES` readings.
+//! Raw readings, not replacements, enter the window, so a genuine level
+//! shift is accepted once it makes up most of it. State is per node and
+//! owned by the caller, like [`BandHistory`](crate::BandHistory), and
+//! serializes for persistence between runs.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::NodeState;
+
+/// Readings a window needs before the outlier gate applies.
+pub const MIN_GATE_SAMPLES: usize = 3;
+
+/// Smoothing parameters, shared by every node.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SmoothingConfig {
+    /// EWMA weight of the newest reading, in (0, 1]; 1 disables smoothing.
+    pub alpha: f64,
+    /// Raw readings kept for the outlier gate; at least `MIN_GATE_SAMPLES`.
+    pub window: usize,
+    /// Gate width in median absolute deviations; > 0.
+    pub mad_k: f64,
+}
+
+impl Default for SmoothingConfig {
+    /// alpha 0.5 over an 8-reading window, gating beyond 3.5 MADs.
+    fn default() -> Self {
+        SmoothingConfig {
+            alpha: 0.5,
+            window: 8,
+            mad_k: 3.5,
+        }
+    }
+}
+
+impl SmoothingConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field, reason: String| Err(ConfigError::Invalid { field, reason });
+        if !(self.alpha > 0.0 && self.alpha <= 1.0) {
+            return invalid(
+                "smoothing.alpha",
+                format!("must be in (0, 1], got {}", self.alpha),
+            );
+        }
+        if self.window < MIN_GATE_SAMPLES {
+            return invalid(
+                "smoothing.window",
+                format!("must be at least {MIN_GATE_SAMPLES}, got {}", self.window),
+            );
+        }
+        if !(self.mad_k.is_finite() && self.mad_k > 0.0) {
+            return invalid(
+                "smoothing.mad_k",
+                format!("must be finite and > 0, got {}", self.mad_k),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// What the two stages did with one reading.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SmoothedValue {
+    pub raw: f64,
+    /// Window median that replaced `raw`, if the gate rejected it.
+    pub replaced_with: Option<f64>,
+    /// Value written back to the node.
+    pub smoothed: f64,
+}
+
+impl SmoothedValue {
+    pub fn is_outlier(&self) -> bool {
+        self.replaced_with.is_some()
+    }
+}
+
+/// Outlier window and EWMA for one quantity.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SeriesSmoothing {
+    /// Last raw readings, oldest first.
+    pub recent: VecDeque<f64>,
+    pub ewma: Option<f64>,
+}
+
+impl SeriesSmoothing {
+    /// Gate and smooth `raw`. A non-finite reading is passed through
+    /// untouched and not recorded, so the controller's checks still see it.
+    pub fn update(&mut self, config: &SmoothingConfig, raw: f64) -> SmoothedValue {
+        if !raw.is_finite() {
+            return SmoothedValue {
+                raw,
+                replaced_with: None,
+                smoothed: raw,
+            };
+        }
+        let replaced_with = if self.recent.len() >= MIN_GATE_SAMPLES {
+            let median = median(self.recent.iter().copied().collect());
+            let mad = median_abs_deviation(&self.recent, median);
+            ((raw - median).abs() > config.mad_k * mad).then_some(median)
+        } else {
+            None
+        };
+
+        self.recent.push_back(raw);
+        while self.recent.len() > config.window.max(1) {
+            self.recent.pop_front();
+        }
+
+        let x = replaced_with.unwrap_or(raw);
+        let smoothed = match self.ewma {
+            Some(prev) => config.alpha * x + (1.0 - config.alpha) * prev,
+            None => x,
+        };
+        self.ewma = Some(smoothed);
+        SmoothedValue {
+            raw,
+            replaced_with,
+            smoothed,
+        }
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_abs_deviation(values: &VecDeque<f64>, median_value: f64) -> f64 {
+    median(values.iter().map(|v| (v - median_value).abs()).collect())
+}
+
+/// Smoothing state for one node; the caller keeps one per node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeSmoothing {
+    pub mass: SeriesSmoothing,
+    pub karma: SeriesSmoothing,
+}
+
+/// One node's smoothing step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SmoothingReport {
+    pub machine_id: String,
+    pub mass_kg: SmoothedValue,
+    pub karma_bytes: SmoothedValue,
+}
+
+impl SmoothingReport {
+    /// True if either reading was rejected by the gate.
+    pub fn has_outlier(&self) -> bool {
+        self.mass_kg.is_outlier() || self.karma_bytes.is_outlier()
+    }
+}
+
+impl NodeSmoothing {
+    /// Replace `node.mass_kg` and `node.karma_bytes` with their smoothed
+    /// values; call after computing them from the latest row and before
+    /// `update_node_duty`.
+    pub fn apply(&mut self, config: &SmoothingConfig, node: &mut NodeState) -> SmoothingReport {
+        let mass_kg = self.mass.update(config, node.mass_kg);
+        let karma_bytes = self.karma.update(config, node.karma_bytes);
+        node.mass_kg = mass_kg.smoothed;
+        node.karma_bytes = karma_bytes.smoothed;
+        SmoothingReport {
+            machine_id: node.row.machine_id.clone(),
+            mass_kg,
+            karma_bytes,
+        }
+    }
+}
+
+/// [`NodeSmoothing::apply`] for every node, keeping state by `machine_id`
+/// in `states`; nodes seen for the first time start fresh.
+pub fn smooth_nodes(
+    config: &SmoothingConfig,
+    states: &mut BTreeMap<String, NodeSmoothing>,
+    nodes: &mut [NodeState],
+) -> Vec<SmoothingReport> {
+    nodes
+        .iter_mut()
+        .map(|node| {
+            states
+                .entry(node.row.machine_id.clone())
+                .or_default()
+                .apply(config, node)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{compute_karma_bytes, compute_mass_kg, EcoBand, Pollutant};
+
+    const STEPS: usize = 20;
+    const SPIKE_STEP: usize = 8;
+
+    /// Canopy duty over `STEPS` steps, with `cin` wobbling around 40 and
+    /// jumping to 400 at `SPIKE_STEP` if `spike`.
+    fn duty_trajectory(spike: bool, smoothing: Option<SmoothingConfig>) -> Vec<f64> {
+        let mut controller = phoenix_controller();
+        controller.m_ref_kg = 1.0e-2;
+        controller.eta_m = 1.0;
+        controller.eta_k = 0.0;
+        controller.eta_w = 0.0;
+        controller.eta_b = 0.0;
+        controller.eta_p = 0.0;
+        let mut node = phoenix_nodes()[0].clone();
+        node.duty_cycle = 0.2;
+        let mut state = NodeSmoothing::default();
+
+        (0..STEPS)
+            .map(|step| {
+                node.row.cin = if spike && step == SPIKE_STEP {
+                    400.0
+                } else {
+                    40.0 + [0.0, 0.5, -0.5, 0.25][step % 4]
+                };
+                node.mass_kg = compute_mass_kg(&node.row, Pollutant::PM25, 310.0).unwrap();
+                node.karma_bytes = compute_karma_bytes(&node.row, node.mass_kg);
+                if let Some(config) = &smoothing {
+                    let report = state.apply(config, &mut node);
+                    assert_eq!(report.has_outlier(), spike && step == SPIKE_STEP);
+                }
+                controller
+                    .update_node_duty(&mut node, EcoBand::Green, 0.0)
+                    .unwrap();
+                node.duty_cycle
+            })
+            .collect()
+    }
+
+    fn max_gap(a: &[f64], b: &[f64]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn spike_is_gated_out_of_the_duty_trajectory() {
+        let config = SmoothingConfig::default();
+        let clean = duty_trajectory(false, None);
+        let raw_spike = duty_trajectory(true, None);
+        let smoothed_spike = duty_trajectory(true, Some(config));
+        let smoothed_clean = duty_trajectory(false, Some(config));
+
+        assert!(max_gap(&clean, &raw_spike) > 0.25);
+        assert!(max_gap(&smoothed_clean, &smoothed_spike) < 0.01);
+        assert!(max_gap(&clean, &smoothed_spike) < 0.01);
+    }
+
+    #[test]
+    fn level_shift_is_accepted_and_state_round_trips() {
+        let config = SmoothingConfig {
+            alpha: 1.0,
+            window: 5,
+            mad_k: 3.0,
+        };
+        let mut series = SeriesSmoothing::default();
+        let flagged: Vec<bool> = [1.0, 1.1, 0.9, 1.0, 5.0, 5.1, 4.9, 5.0, 5.0]
+            .into_iter()
+            .map(|x| series.update(&config, x).is_outlier())
+            .collect();
+        assert_eq!(
+            flagged,
+            [false, false, false, false, true, true, true, false, false]
+        );
+        assert_eq!(series.ewma, Some(5.0));
+
+        let json = serde_json::to_string(&series).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SeriesSmoothing>(&json).unwrap(),
+            series
+        );
+
+        let nan = series.update(&config, f64::NAN);
+        assert!(nan.smoothed.is_nan() && !nan.is_outlier());
+        assert_eq!(series.recent.len(), 5);
+    }
+
+    #[test]
+    fn states_are_kept_per_node_and_config_is_validated() {
+        let config = SmoothingConfig::default();
+        let mut nodes = phoenix_nodes();
+        let mut states = BTreeMap::new();
+        smooth_nodes(&config, &mut states, &mut nodes);
+        assert_eq!(
+            states.keys().collect::<Vec<_>>(),
+            ["CYB-AIR-CANOPY-01", "CYB-AIR-SCHOOL-05"]
+        );
+
+        assert_eq!(config.validate(), Ok(()));
+        for (bad, field) in [
+            (
+                SmoothingConfig {
+                    alpha: 0.0,
+                    ..config
+                },
+                "smoothing.alpha",
+            ),
+            (
+                SmoothingConfig {
+                    window: 2,
+                    ..config
+                },
+                "smoothing.window",
+            ),
+            (
+                SmoothingConfig {
+                    mad_k: -1.0,
+                    ..config
+                },
+                "smoothing.mad_k",
+            ),
+        ] {
+            assert!(matches!(
+                bad.validate(),
+                Err(ConfigError::Invalid { field: f, .. }) if f == field
+            ));
+        }
+    }
+}
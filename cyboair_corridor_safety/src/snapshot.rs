@@ -0,0 +1,315 @@
+//! Save and restore a controller's mutable runtime state.
+//!
+//! The controller's configuration is rebuilt by the caller; a
+//! [`ControllerSnapshot`] carries only what evolves while it runs: node
+//! duties (which are also the slew limiter's state), caller-owned
+//! smoothing, the eco-band classifier's remembered band and history, host
+//! budget windows and the step counter. Restoring it into an identically
+//! configured controller and feeding the same inputs reproduces the
+//! unforked run exactly.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::baseline::BaselineModel;
+use crate::history::BandHistory;
+use crate::liability::LiabilityWindow;
+use crate::smoothing::NodeSmoothing;
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBand, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope,
+};
+
+/// Layout version written by this build.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    /// The snapshot's layout is not one this build reads or migrates.
+    #[error("snapshot version {found} is not supported (this build reads {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// A stateful component found no state for itself in the snapshot,
+    /// e.g. it was taken from a differently configured controller.
+    #[error("snapshot has no state for {component}")]
+    Missing { component: &'static str },
+    #[error("snapshot json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Eco-band classifier state; see [`EcoBandClassifier::band_state`].
+/// Each field is set by the classifier that owns it, so wrappers and the
+/// classifier they wrap share one value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BandState {
+    /// Band remembered by a `HystereticEcoBand`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<EcoBand>,
+    /// Transitions recorded by a `RecordingEcoBand`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<BandHistory>,
+    /// Learned baseline of a `SeasonalEcoBand`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineModel>,
+}
+
+/// Host budget state; see [`HostBudget::budget_state`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetState {
+    /// Window of a `KarmaLiabilityBudget`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liability: Option<LiabilityWindow>,
+    /// State of charge by machine id, of a `BatteryHostBudget`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_soc: Option<BTreeMap<String, f64>>,
+    /// Members of a `CompositeHostBudget`, by member name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub members: BTreeMap<String, BudgetState>,
+}
+
+/// Mutable runtime state of a controller and its nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControllerSnapshot {
+    pub version: u32,
+    /// Steps completed when the snapshot was taken.
+    pub step: u64,
+    pub nodes: Vec<NodeState>,
+    /// Smoothing state by machine id, if the caller smooths readings.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub smoothing: BTreeMap<String, NodeSmoothing>,
+    pub eco_band: BandState,
+    pub host_budget: BudgetState,
+}
+
+impl ControllerSnapshot {
+    pub fn with_smoothing(mut self, smoothing: BTreeMap<String, NodeSmoothing>) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, SnapshotError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parse a snapshot of any layout this build knows, migrating older
+    /// ones to [`SNAPSHOT_VERSION`]. Version 1 is the first layout, so
+    /// anything else is refused for now.
+    pub fn from_json(s: &str) -> Result<Self, SnapshotError> {
+        let value: Value = serde_json::from_str(s)?;
+        let found = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .map_or(0, |v| u32::try_from(v).unwrap_or(u32::MAX));
+        match found {
+            SNAPSHOT_VERSION => Ok(serde_json::from_value(value)?),
+            found => Err(SnapshotError::UnsupportedVersion {
+                found,
+                supported: SNAPSHOT_VERSION,
+            }),
+        }
+    }
+
+    fn check_version(&self) -> Result<(), SnapshotError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: self.version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// State of this controller and `nodes` after `step` steps.
+    pub fn snapshot(&self, step: u64, nodes: &[NodeState]) -> ControllerSnapshot {
+        ControllerSnapshot {
+            version: SNAPSHOT_VERSION,
+            step,
+            nodes: nodes.to_vec(),
+            smoothing: BTreeMap::new(),
+            eco_band: self.eco_band.band_state(),
+            host_budget: self.host_budget.budget_state(),
+        }
+    }
+
+    /// Put the classifier and host budget back into the snapshot's state
+    /// and return its nodes. Nothing is changed if the version is wrong;
+    /// a missing component may leave earlier ones restored.
+    pub fn restore(
+        &mut self,
+        snapshot: &ControllerSnapshot,
+    ) -> Result<Vec<NodeState>, SnapshotError> {
+        snapshot.check_version()?;
+        self.eco_band.restore_band_state(&snapshot.eco_band)?;
+        self.host_budget
+            .restore_budget_state(&snapshot.host_budget)?;
+        Ok(snapshot.nodes.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liability::{KarmaLiabilityBudget, LiabilityConfig};
+    use crate::simulation::StepInput;
+    use crate::smoothing::{smooth_nodes, SmoothingConfig};
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{
+        compute_karma_bytes, compute_mass_kg, ConstAltitude, HystereticEcoBand, Pollutant,
+        RectSafetyEnvelope, SimpleDwCeiling, SlewLimit,
+    };
+
+    type Controller = CorridorController<
+        RectSafetyEnvelope<ConstAltitude>,
+        KarmaLiabilityBudget,
+        HystereticEcoBand,
+        SimpleDwCeiling,
+    >;
+
+    const STEPS: u64 = 100;
+    const FORK: u64 = 50;
+    const STEP_MS: u64 = 60_000;
+
+    fn controller() -> Controller {
+        let base = phoenix_controller();
+        let power = base.host_budget.clone();
+        let mut controller = base
+            .with_eco_band(HystereticEcoBand::new(
+                0.8,
+                1.6,
+                0.2,
+                0.3,
+                1.0,
+                1.5,
+                2.0,
+                EcoBand::Green,
+            ))
+            .with_host_budget(KarmaLiabilityBudget::new(
+                LiabilityConfig {
+                    cap_karma_bytes: 5.0e4,
+                    window_ms: 10 * STEP_MS,
+                    bucket_ms: 2 * STEP_MS,
+                    max_lateness_ms: 0,
+                },
+                power,
+            ));
+        controller.slew_limit = Some(SlewLimit::symmetric(0.05));
+        controller
+    }
+
+    /// Step `step`: new readings, smoothing, one controller tick, then
+    /// the liability record. Returns what the step decided.
+    fn advance(
+        controller: &mut Controller,
+        nodes: &mut [NodeState],
+        smoothing: &mut BTreeMap<String, NodeSmoothing>,
+        step: u64,
+    ) -> (EcoBand, Vec<u64>) {
+        for (i, node) in nodes.iter_mut().enumerate() {
+            let phase = step as f64 * 0.21 + i as f64;
+            node.row.cin = 40.0 + 30.0 * phase.sin() + if step % 17 == 3 { 200.0 } else { 0.0 };
+            node.mass_kg = compute_mass_kg(&node.row, Pollutant::PM25, 310.0).unwrap();
+            node.karma_bytes = compute_karma_bytes(&node.row, node.mass_kg);
+        }
+        smooth_nodes(&SmoothingConfig::default(), smoothing, nodes);
+        let input = StepInput {
+            phi_dw: 0.0,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind: None,
+        };
+        let record = controller.run_steps(nodes, 1, |_| input).unwrap().remove(0);
+        controller.host_budget.record(nodes, step * STEP_MS);
+        let duties = nodes.iter().map(|n| n.duty_cycle.to_bits()).collect();
+        (record.band, duties)
+    }
+
+    #[test]
+    fn forked_run_matches_the_unforked_one_bit_for_bit() {
+        let mut unforked = controller();
+        let mut nodes = phoenix_nodes();
+        let mut smoothing = BTreeMap::new();
+        let mut saved = None;
+        let mut expected = Vec::new();
+        for step in 0..STEPS {
+            if step == FORK {
+                let snapshot = unforked
+                    .snapshot(step, &nodes)
+                    .with_smoothing(smoothing.clone());
+                saved = Some(snapshot.to_json().unwrap());
+            }
+            let outcome = advance(&mut unforked, &mut nodes, &mut smoothing, step);
+            if step >= FORK {
+                expected.push(outcome);
+            }
+        }
+        // The run must exercise every piece of state it restores.
+        let bands: Vec<EcoBand> = expected.iter().map(|(band, _)| *band).collect();
+        assert!(bands.contains(&EcoBand::Green) && bands.contains(&EcoBand::Red));
+        assert!(!unforked.host_budget.window().buckets.is_empty());
+
+        let snapshot = ControllerSnapshot::from_json(&saved.unwrap()).unwrap();
+        let mut forked = controller();
+        let mut nodes = forked.restore(&snapshot).unwrap();
+        let mut smoothing = snapshot.smoothing.clone();
+        let replayed: Vec<_> = (snapshot.step..STEPS)
+            .map(|step| advance(&mut forked, &mut nodes, &mut smoothing, step))
+            .collect();
+
+        assert_eq!(replayed, expected);
+        assert_eq!(
+            forked.eco_band.current_band(),
+            unforked.eco_band.current_band()
+        );
+        assert_eq!(forked.host_budget.window(), unforked.host_budget.window());
+    }
+
+    #[test]
+    fn unknown_versions_and_missing_state_are_refused() {
+        let mut controller = controller();
+        let mut snapshot = controller.snapshot(0, &phoenix_nodes());
+        let mut json: Value = serde_json::from_str(&snapshot.to_json().unwrap()).unwrap();
+        json["version"] = 2.into();
+        assert!(matches!(
+            ControllerSnapshot::from_json(&json.to_string()),
+            Err(SnapshotError::UnsupportedVersion {
+                found: 2,
+                supported: 1
+            })
+        ));
+        json.as_object_mut().unwrap().remove("version");
+        assert!(matches!(
+            ControllerSnapshot::from_json(&json.to_string()),
+            Err(SnapshotError::UnsupportedVersion { found: 0, .. })
+        ));
+
+        // Taken from a controller without a hysteretic band or a window.
+        snapshot.eco_band = BandState::default();
+        assert!(matches!(
+            controller.restore(&snapshot),
+            Err(SnapshotError::Missing {
+                component: "hysteretic band"
+            })
+        ));
+        snapshot.eco_band.current = Some(EcoBand::Red);
+        snapshot.host_budget = BudgetState::default();
+        assert!(matches!(
+            controller.restore(&snapshot),
+            Err(SnapshotError::Missing {
+                component: "liability window"
+            })
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BeeGuard, CorridorController, DwCeilingInvariant, EcoBand, EcoBandClassifier, HostBudget,
+    MetricsRecorder, NodeState, SafetyEnvelope, SafetyError, UpdateReport, Wind,
+};
+
+/// Corridor potential V(nodes), given the reference scales M_ref and K_ref.
+pub type Potential = fn(nodes: &[NodeState], m_ref_kg: f64, k_ref_nb: f64) -> f64;
+
+/// V = sum_i (M_i/M_ref)^2 + (K_i/K_ref)^2.
+///
+/// Independent of duty, so it only moves when the caller refreshes node mass
+/// and karma between passes (e.g. from telemetry).
+pub fn load_potential(nodes: &[NodeState], m_ref_kg: f64, k_ref_nb: f64) -> f64 {
+    nodes
+        .iter()
+        .map(|n| (n.mass_kg / m_ref_kg).powi(2) + (n.karma_bytes / k_ref_nb).powi(2))
+        .sum()
+}
+
+/// Load left uncaptured by the current duty cycles:
+/// V = sum_i (1 - u_i)^2 [(M_i/M_ref)^2 + (K_i/K_ref)^2].
+pub fn residual_potential(nodes: &[NodeState], m_ref_kg: f64, k_ref_nb: f64) -> f64 {
+    nodes
+        .iter()
+        .map(|n| {
+            let load = (n.mass_kg / m_ref_kg).powi(2) + (n.karma_bytes / k_ref_nb).powi(2);
+            (1.0 - n.duty_cycle).powi(2) * load
+        })
+        .sum()
+}
+
+/// What to do when an update pass increases V beyond the tolerance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum StabilityPolicy {
+    /// Keep the new duties and flag the pass.
+    Warn,
+    /// Restore the duties from before the pass.
+    ClampToCurrent,
+    /// Restore the duties and fail the pass with `LyapunovIncrease`.
+    Error,
+}
+
+/// Lyapunov non-increase monitor over controller update passes.
+#[derive(Debug, Clone, Copy)]
+pub struct StabilityMonitor {
+    pub potential: Potential,
+    /// Allowed increase of V per pass.
+    pub tolerance: f64,
+    pub policy: StabilityPolicy,
+}
+
+impl StabilityMonitor {
+    /// Monitor over `load_potential`.
+    pub fn new(policy: StabilityPolicy, tolerance: f64) -> Self {
+        StabilityMonitor {
+            potential: load_potential,
+            tolerance,
+            policy,
+        }
+    }
+}
+
+/// Potential before and after one update pass.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StabilityRecord {
+    pub v_before: f64,
+    pub v_after: f64,
+    /// True if V rose by more than the tolerance.
+    pub increased: bool,
+    /// True if the pre-pass duties were put back.
+    pub duties_restored: bool,
+}
+
+/// Outcome of updating every node in a corridor once.
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    /// Per-node result, in node order.
+    pub results: Vec<Result<UpdateReport, SafetyError>>,
+    /// Set if the controller has a stability monitor.
+    pub stability: Option<StabilityRecord>,
+}
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// Update every node once, then apply the stability policy if V increased.
+    /// Per-node failures are reported in place; only `StabilityPolicy::Error`
+    /// fails the pass as a whole.
+    pub fn update_pass(
+        &self,
+        nodes: &mut [NodeState],
+        eco_band: EcoBand,
+        phi_dw: f64,
+    ) -> Result<PassReport, SafetyError> {
+        self.update_pass_in_wind(nodes, eco_band, phi_dw, None)
+    }
+
+    /// `update_pass` with this step's wind; see `update_node_duty_in_wind`.
+    pub fn update_pass_in_wind(
+        &self,
+        nodes: &mut [NodeState],
+        eco_band: EcoBand,
+        phi_dw: f64,
+        wind: Option<Wind>,
+    ) -> Result<PassReport, SafetyError> {
+        let before = self.stability.as_ref().map(|m| {
+            let duties: Vec<f64> = nodes.iter().map(|n| n.duty_cycle).collect();
+            (duties, (m.potential)(nodes, self.m_ref_kg, self.k_ref_nb))
+        });
+
+        let results = nodes
+            .iter_mut()
+            .map(|n| self.update_node_duty_in_wind(n, eco_band, phi_dw, wind))
+            .collect();
+
+        let stability = match (&self.stability, before) {
+            (Some(monitor), Some((duties, v_before))) => {
+                let v_after = (monitor.potential)(nodes, self.m_ref_kg, self.k_ref_nb);
+                let increased = v_after > v_before + monitor.tolerance;
+                let restore = increased && monitor.policy != StabilityPolicy::Warn;
+                if restore {
+                    for (n, u) in nodes.iter_mut().zip(duties) {
+                        n.duty_cycle = u;
+                    }
+                }
+                if increased && monitor.policy == StabilityPolicy::Error {
+                    return Err(SafetyError::LyapunovIncrease {
+                        v_before,
+                        v_after,
+                        tolerance: monitor.tolerance,
+                    });
+                }
+                Some(StabilityRecord {
+                    v_before,
+                    v_after,
+                    increased,
+                    duties_restored: restore,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(PassReport { results, stability })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+
+    #[test]
+    fn load_potential_matches_definition() {
+        let controller = phoenix_controller();
+        let nodes = phoenix_nodes();
+        let expected: f64 = nodes
+            .iter()
+            .map(|n| (n.mass_kg / 1.0e-6).powi(2) + (n.karma_bytes / 1.0e10).powi(2))
+            .sum();
+        let v = load_potential(&nodes, controller.m_ref_kg, controller.k_ref_nb);
+        assert!((v - expected).abs() < 1e-9 * expected);
+    }
+
+    /// Gains that drive duty down, so more load goes uncaptured.
+    fn destabilizing_controller(
+        policy: StabilityPolicy,
+    ) -> CorridorController<
+        crate::RectSafetyEnvelope<crate::ConstAltitude>,
+        crate::SimpleHostBudget,
+        crate::ThresholdEcoBand,
+        crate::SimpleDwCeiling,
+    > {
+        let mut controller = phoenix_controller();
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        controller.eta_w = 0.0;
+        controller.eta_b = 0.0;
+        controller.eta_p = 1.0;
+        controller.stability = Some(StabilityMonitor {
+            potential: residual_potential,
+            tolerance: 1e-9,
+            policy,
+        });
+        controller
+    }
+
+    #[test]
+    fn clamp_policy_keeps_previous_duties() {
+        let controller = destabilizing_controller(StabilityPolicy::ClampToCurrent);
+        let mut nodes = phoenix_nodes();
+        let before: Vec<f64> = nodes.iter().map(|n| n.duty_cycle).collect();
+
+        let pass = controller
+            .update_pass(&mut nodes, EcoBand::Green, 0.0)
+            .unwrap();
+        let rec = pass.stability.unwrap();
+        assert!(rec.increased);
+        assert!(rec.duties_restored);
+        assert!(rec.v_after > rec.v_before);
+        for (n, u) in nodes.iter().zip(before) {
+            assert_eq!(n.duty_cycle, u);
+        }
+        // Per-node reports still show what the law proposed.
+        assert!(pass
+            .results
+            .iter()
+            .all(|r| r.as_ref().unwrap().duty_after < 0.5));
+    }
+
+    #[test]
+    fn warn_policy_flags_but_applies() {
+        let controller = destabilizing_controller(StabilityPolicy::Warn);
+        let mut nodes = phoenix_nodes();
+        let pass = controller
+            .update_pass(&mut nodes, EcoBand::Green, 0.0)
+            .unwrap();
+        let rec = pass.stability.unwrap();
+        assert!(rec.increased && !rec.duties_restored);
+        assert!(nodes.iter().all(|n| n.duty_cycle < 0.5));
+    }
+
+    #[test]
+    fn error_policy_fails_the_pass() {
+        let controller = destabilizing_controller(StabilityPolicy::Error);
+        let mut nodes = phoenix_nodes();
+        assert!(matches!(
+            controller.update_pass(&mut nodes, EcoBand::Green, 0.0),
+            Err(SafetyError::LyapunovIncrease { .. })
+        ));
+        assert!(nodes.iter().all(|n| n.duty_cycle == 0.5));
+    }
+
+    #[test]
+    fn stabilizing_gains_pass_the_monitor() {
+        let mut controller = destabilizing_controller(StabilityPolicy::Error);
+        controller.eta_p = 0.0;
+        controller.eta_m = 0.1;
+        let mut nodes = phoenix_nodes();
+        let rec = controller
+            .update_pass(&mut nodes, EcoBand::Green, 0.0)
+            .unwrap()
+            .stability
+            .unwrap();
+        assert!(!rec.increased);
+        assert!(rec.v_after <= rec.v_before);
+    }
+}
@@ -0,0 +1,49 @@
+/// Compensated (Kahan-Babuska / Neumaier) running sum.
+///
+/// Carries the low-order bits each addition rounds off, so the error stays
+/// at a few ulps of the result independent of the number of terms or their
+/// order, e.g. summing millions of ~1e-9 kg masses onto a large total.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct KahanSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl KahanSum {
+    pub(crate) fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        // Recover what the addition lost from whichever operand is smaller.
+        if self.sum.abs() >= x.abs() {
+            self.compensation += (self.sum - t) + x;
+        } else {
+            self.compensation += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    pub(crate) fn total(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_terms_lost_to_rounding() {
+        // Naively 1 + 1e-16 + ... == 1, and 1e100 - 1e100 cancels the 1.
+        let mut sum = KahanSum::default();
+        for x in [1.0, 1e100, 1.0, -1e100] {
+            sum.add(x);
+        }
+        assert_eq!(sum.total(), 2.0);
+
+        let mut sum = KahanSum::default();
+        sum.add(1.0);
+        for _ in 0..10_000 {
+            sum.add(1e-16);
+        }
+        assert!((sum.total() - (1.0 + 1e-12)).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,248 @@
+//! Proptest strategies for shard rows and node states.
+//!
+//! `any_*` strategies cover the whole value space, NaN, infinities, unknown
+//! units and `cout > cin` included, for tests that check bad input is
+//! refused. `valid_*` strategies only produce what a well-formed shard
+//! holds: a recognized unit and pollutant pair, finite non-negative
+//! readings with `cout <= cin`, positive airflow and period, scores in
+//! [0, 1], and node states whose mass and karma follow from their row.
+
+use proptest::num::f64::ANY as ANY_F64;
+use proptest::prelude::*;
+use uuid::Uuid;
+
+use crate::{compute_karma_bytes, compute_mass_kg, CorridorRow, NodeState, OperationalMode};
+
+/// Pollutant labels `Pollutant` parses.
+pub const POLLUTANT_LABELS: &[&str] = &["PM2.5", "PM10", "O3", "NO2", "NOx", "VOC", "SO2", "CO"];
+
+/// Unit labels valid for every pollutant.
+pub const MASS_UNIT_LABELS: &[&str] = &["ugm3", "ug/m3", "ng/m3", "mg/m3"];
+
+/// Unit labels valid for gases: the mass units plus mixing ratios.
+pub const GAS_UNIT_LABELS: &[&str] = &["ugm3", "ug/m3", "ng/m3", "mg/m3", "ppb", "ppm"];
+
+/// Temperature the `valid_node_state` masses are computed at.
+pub const VALID_TEMPERATURE_K: f64 = 293.15;
+
+fn label(labels: &'static [&'static str]) -> impl Strategy<Value = String> {
+    proptest::sample::select(labels).prop_map(str::to_string)
+}
+
+/// A recognized label half the time, arbitrary text otherwise.
+fn any_label(labels: &'static [&'static str]) -> impl Strategy<Value = String> {
+    prop_oneof![label(labels), any::<String>()]
+}
+
+fn any_trace_id() -> impl Strategy<Value = Option<Uuid>> {
+    proptest::option::of(any::<u128>().prop_map(Uuid::from_u128))
+}
+
+/// Any row, including ones no check would accept.
+pub fn any_corridor_row() -> impl Strategy<Value = CorridorRow> {
+    (
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any_label(POLLUTANT_LABELS),
+            any_label(MASS_UNIT_LABELS),
+        ),
+        [ANY_F64; 7],
+        any_trace_id(),
+        proptest::option::of(any::<String>()),
+    )
+        .prop_map(
+            |(
+                (machine_id, r#type, location, pollutant, unit),
+                [cin, cout, airflow_m3_per_s, period_s, lambda_hazard, beta_nb_per_kg, ecoimpact_score],
+                trace_id,
+                shard_version,
+            )| CorridorRow {
+                machine_id,
+                r#type,
+                location,
+                pollutant,
+                cin,
+                cout,
+                unit,
+                airflow_m3_per_s,
+                period_s,
+                lambda_hazard,
+                beta_nb_per_kg,
+                ecoimpact_score,
+                trace_id,
+                shard_version,
+            },
+        )
+}
+
+/// A row a well-formed shard could hold; its mass is always computable.
+pub fn valid_corridor_row() -> impl Strategy<Value = CorridorRow> {
+    let species = label(POLLUTANT_LABELS).prop_flat_map(|pollutant| {
+        let units = if pollutant.starts_with("PM") {
+            MASS_UNIT_LABELS
+        } else {
+            GAS_UNIT_LABELS
+        };
+        (Just(pollutant), label(units))
+    });
+    (
+        "CYB-AIR-[A-Z]{3,8}-[0-9]{2}",
+        species,
+        0.0..=500.0f64,
+        0.0..=1.0f64,
+        (0.01..=10.0f64, 1.0..=86_400.0f64),
+        (0.0..=10.0f64, 0.0..=1.0e9f64, 0.0..=1.0f64),
+        any_trace_id(),
+    )
+        .prop_map(
+            |(
+                machine_id,
+                (pollutant, unit),
+                cin,
+                removed,
+                (airflow_m3_per_s, period_s),
+                (lambda_hazard, beta_nb_per_kg, ecoimpact_score),
+                trace_id,
+            )| CorridorRow {
+                machine_id,
+                r#type: "UrbanNanoswarmCanopy".to_string(),
+                location: "Phoenix-Intersection-A".to_string(),
+                pollutant,
+                cin,
+                cout: cin * (1.0 - removed),
+                unit,
+                airflow_m3_per_s,
+                period_s,
+                lambda_hazard,
+                beta_nb_per_kg,
+                ecoimpact_score,
+                trace_id,
+                shard_version: None,
+            },
+        )
+}
+
+/// Any node state; mass and karma are unrelated to the row.
+pub fn any_node_state() -> impl Strategy<Value = NodeState> {
+    (
+        any_corridor_row(),
+        [ANY_F64; 5],
+        proptest::option::of(ANY_F64),
+        proptest::option::of(ANY_F64),
+    )
+        .prop_map(
+            |(row, [mass_kg, karma_bytes, duty_cycle, power_w, geo_weight], noise_db, emf_vpm)| {
+                NodeState {
+                    row,
+                    mass_kg,
+                    karma_bytes,
+                    duty_cycle,
+                    power_w,
+                    geo_weight,
+                    noise_db,
+                    emf_vpm,
+                    suspicion: None,
+                    mode: OperationalMode::Active,
+                }
+            },
+        )
+}
+
+/// A node of a `valid_corridor_row`, with mass and karma computed at
+/// [`VALID_TEMPERATURE_K`], duty in [0, 1], and finite non-negative power,
+/// geographic weight and exposure readings.
+pub fn valid_node_state() -> impl Strategy<Value = NodeState> {
+    (
+        valid_corridor_row(),
+        0.0..=1.0f64,
+        0.0..=200.0f64,
+        0.0..=2.0f64,
+        proptest::option::of(20.0..=120.0f64),
+        proptest::option::of(0.0..=10.0f64),
+    )
+        .prop_map(
+            |(row, duty_cycle, power_w, geo_weight, noise_db, emf_vpm)| {
+                let pollutant = row.pollutant_kind().expect("valid pollutant label");
+                let mass_kg = compute_mass_kg(&row, pollutant, VALID_TEMPERATURE_K)
+                    .expect("valid unit for the pollutant");
+                NodeState {
+                    karma_bytes: compute_karma_bytes(&row, mass_kg),
+                    row,
+                    mass_kg,
+                    duty_cycle,
+                    power_w,
+                    geo_weight,
+                    noise_db,
+                    emf_vpm,
+                    suspicion: None,
+                    mode: OperationalMode::Active,
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::phoenix_controller;
+    use crate::{check_row_finite, EcoBand, SlewLimit, ViolationPolicy};
+
+    fn any_band() -> impl Strategy<Value = EcoBand> {
+        prop_oneof![
+            Just(EcoBand::Green),
+            Just(EcoBand::Amber),
+            Just(EcoBand::Red)
+        ]
+    }
+
+    fn any_policy() -> impl Strategy<Value = ViolationPolicy> {
+        prop_oneof![
+            Just(ViolationPolicy::Error),
+            Just(ViolationPolicy::SkipNode),
+            proptest::option::of(0.0..=1.0f64)
+                .prop_map(|safe_duty| ViolationPolicy::ClampToSafe { safe_duty }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn valid_rows_are_finite_and_removing(row in valid_corridor_row()) {
+            prop_assert!(check_row_finite(&row).is_ok());
+            prop_assert!(row.cout <= row.cin);
+            prop_assert!(row.concentration_unit().is_ok());
+        }
+
+        #[test]
+        fn duty_projection_stays_in_unit_interval(
+            mut node in valid_node_state(),
+            band in any_band(),
+            phi_dw in 0.0..=1.0e-4f64,
+            policy in any_policy(),
+            slew in proptest::option::of(0.0..=0.5f64),
+        ) {
+            let mut controller = phoenix_controller();
+            controller.violation_policy = policy;
+            controller.slew_limit = slew.map(SlewLimit::symmetric);
+            if let Ok(report) = controller.update_node_duty(&mut node, band, phi_dw) {
+                prop_assert!((0.0..=1.0).contains(&report.duty_after));
+                prop_assert_eq!(node.duty_cycle.to_bits(), report.duty_after.to_bits());
+            }
+        }
+
+        #[test]
+        fn accepted_duty_is_in_unit_interval_for_any_node(
+            mut node in any_node_state(),
+            band in any_band(),
+            phi_dw in ANY_F64,
+        ) {
+            // Under the default policy nothing outside the envelope or
+            // non-finite gets through, whatever the input.
+            let controller = phoenix_controller();
+            if let Ok(report) = controller.update_node_duty(&mut node, band, phi_dw) {
+                prop_assert!((0.0..=1.0).contains(&report.duty_after));
+            }
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! Registry of the machines in each corridor.
+//!
+//! Nothing in a shard or a telemetry stream stops two sources claiming the
+//! same `machine_id` for machines in different places. A
+//! [`CorridorTopology`] records each machine once, with its location,
+//! coordinates, type and corridor, and checks incoming ids against it:
+//! [`CorridorTopology::admit_shard`] for loaded rows and
+//! [`CorridorTopology::admit`] for telemetry. Ids it does not know are
+//! refused or registered according to its [`UnknownMachinePolicy`].
+//!
+//! Metadata left unset (`None`) is compatible with any value, so a machine
+//! first seen in telemetry can later be completed from a shard row; two
+//! different set values for one machine are a conflict.
+//!
+//! A controller built [`in_topology`](CorridorController::in_topology)
+//! refuses to update machines outside its corridor.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    BeeGuard, CorridorController, CorridorRow, DwCeilingInvariant, EcoBandClassifier, HostBudget,
+    MetricsRecorder, SafetyEnvelope, ShardRow,
+};
+
+/// WGS 84 position of a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Coordinates {
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+}
+
+/// One registered machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MachineRecord {
+    pub machine_id: String,
+    pub corridor: String,
+    /// Shard location, e.g. "Phoenix-Intersection-A".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<Coordinates>,
+    /// Shard `type`, e.g. "UrbanNanoswarmCanopy".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_type: Option<String>,
+}
+
+impl MachineRecord {
+    /// A record with only an id and a corridor.
+    pub fn new(machine_id: impl Into<String>, corridor: impl Into<String>) -> Self {
+        MachineRecord {
+            machine_id: machine_id.into(),
+            corridor: corridor.into(),
+            location: None,
+            coordinates: None,
+            machine_type: None,
+        }
+    }
+
+    /// The record `row` implies for a machine in `corridor`.
+    pub fn from_row(row: &CorridorRow, corridor: impl Into<String>) -> Self {
+        MachineRecord {
+            location: Some(row.location.clone()),
+            machine_type: Some(row.r#type.clone()),
+            ..MachineRecord::new(row.machine_id.clone(), corridor)
+        }
+    }
+
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    pub fn with_coordinates(mut self, lat_deg: f64, lon_deg: f64) -> Self {
+        self.coordinates = Some(Coordinates { lat_deg, lon_deg });
+        self
+    }
+
+    pub fn with_machine_type(mut self, machine_type: impl Into<String>) -> Self {
+        self.machine_type = Some(machine_type.into());
+        self
+    }
+
+    /// First field both records set to different values.
+    fn conflict_with(&self, other: &MachineRecord) -> Option<&'static str> {
+        fn differ<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+        if self.corridor != other.corridor {
+            Some("corridor")
+        } else if differ(&self.location, &other.location) {
+            Some("location")
+        } else if differ(&self.coordinates, &other.coordinates) {
+            Some("coordinates")
+        } else if differ(&self.machine_type, &other.machine_type) {
+            Some("machine_type")
+        } else {
+            None
+        }
+    }
+
+    /// `self` with fields it leaves unset taken from `other`.
+    fn completed_by(&self, other: &MachineRecord) -> MachineRecord {
+        MachineRecord {
+            machine_id: self.machine_id.clone(),
+            corridor: self.corridor.clone(),
+            location: self.location.clone().or_else(|| other.location.clone()),
+            coordinates: self.coordinates.or(other.coordinates),
+            machine_type: self
+                .machine_type
+                .clone()
+                .or_else(|| other.machine_type.clone()),
+        }
+    }
+}
+
+/// What happens to an id the topology does not know.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum UnknownMachinePolicy {
+    /// Refuse it with [`TopologyError::UnknownMachine`].
+    #[default]
+    Reject,
+    /// Register it in `corridor` and report it as
+    /// [`Admission::AutoRegistered`], so the caller can warn.
+    AutoRegister { corridor: String },
+}
+
+/// Result of [`CorridorTopology::register`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registration {
+    Added,
+    /// Set fields the record lacked; nothing conflicted.
+    Completed,
+    /// The record added nothing.
+    Unchanged,
+}
+
+/// How an incoming id was let in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Known,
+    /// Unknown, and registered under `UnknownMachinePolicy::AutoRegister`.
+    AutoRegistered,
+}
+
+#[derive(Debug, Error)]
+pub enum TopologyError {
+    #[error("{machine_id} is already registered with a different {field}")]
+    Conflict {
+        machine_id: String,
+        field: &'static str,
+        registered: Box<MachineRecord>,
+        incoming: Box<MachineRecord>,
+    },
+    #[error("{machine_id} is not registered in the topology")]
+    UnknownMachine { machine_id: String },
+    #[error("topology json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Machines by id, and what to do with ids not among them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CorridorTopology {
+    #[serde(default)]
+    pub unknown_policy: UnknownMachinePolicy,
+    machines: BTreeMap<String, MachineRecord>,
+}
+
+impl CorridorTopology {
+    pub fn new() -> Self {
+        CorridorTopology::default()
+    }
+
+    pub fn with_unknown_policy(mut self, policy: UnknownMachinePolicy) -> Self {
+        self.unknown_policy = policy;
+        self
+    }
+
+    /// Add `record`, or complete the machine's existing record with it.
+    /// Registering the same record again changes nothing; a record that
+    /// contradicts the registered one is refused and nothing changes.
+    pub fn register(&mut self, record: MachineRecord) -> Result<Registration, TopologyError> {
+        let Some(registered) = self.machines.get_mut(&record.machine_id) else {
+            self.machines.insert(record.machine_id.clone(), record);
+            return Ok(Registration::Added);
+        };
+        if let Some(field) = registered.conflict_with(&record) {
+            return Err(TopologyError::Conflict {
+                machine_id: record.machine_id.clone(),
+                field,
+                registered: Box::new(registered.clone()),
+                incoming: Box::new(record),
+            });
+        }
+        let completed = registered.completed_by(&record);
+        if completed == *registered {
+            return Ok(Registration::Unchanged);
+        }
+        *registered = completed;
+        Ok(Registration::Completed)
+    }
+
+    pub fn get(&self, machine_id: &str) -> Option<&MachineRecord> {
+        self.machines.get(machine_id)
+    }
+
+    /// Registered machines, by id.
+    pub fn machines(&self) -> impl Iterator<Item = &MachineRecord> {
+        self.machines.values()
+    }
+
+    /// Ids of the machines in `corridor`.
+    pub fn membership(&self, corridor: &str) -> CorridorMembership {
+        CorridorMembership {
+            corridor: corridor.to_string(),
+            machines: self
+                .machines
+                .values()
+                .filter(|m| m.corridor == corridor)
+                .map(|m| m.machine_id.clone())
+                .collect(),
+        }
+    }
+
+    /// Check a telemetry id: known ids pass, unknown ones follow the
+    /// policy.
+    pub fn admit(&mut self, machine_id: &str) -> Result<Admission, TopologyError> {
+        if self.machines.contains_key(machine_id) {
+            return Ok(Admission::Known);
+        }
+        self.admit_unknown(|corridor| MachineRecord::new(machine_id, corridor))
+    }
+
+    /// Check a shard row: its location and type must match the machine's
+    /// record, and an unknown machine follows the policy.
+    pub fn admit_row(&mut self, row: &CorridorRow) -> Result<Admission, TopologyError> {
+        match self.machines.get(&row.machine_id) {
+            Some(registered) => {
+                let corridor = registered.corridor.clone();
+                self.register(MachineRecord::from_row(row, corridor))?;
+                Ok(Admission::Known)
+            }
+            None => self.admit_unknown(|corridor| MachineRecord::from_row(row, corridor)),
+        }
+    }
+
+    /// [`admit_row`](Self::admit_row) for every row of a loaded shard,
+    /// returning the ids auto-registered. Two rows giving one machine
+    /// different locations conflict. All or nothing: on error the
+    /// topology is unchanged.
+    pub fn admit_shard(&mut self, rows: &[ShardRow]) -> Result<Vec<String>, TopologyError> {
+        let mut staged = self.clone();
+        let mut registered = Vec::new();
+        for row in rows {
+            if staged.admit_row(&row.row)? == Admission::AutoRegistered {
+                registered.push(row.row.machine_id.clone());
+            }
+        }
+        *self = staged;
+        Ok(registered)
+    }
+
+    fn admit_unknown(
+        &mut self,
+        record: impl FnOnce(String) -> MachineRecord,
+    ) -> Result<Admission, TopologyError> {
+        match &self.unknown_policy {
+            UnknownMachinePolicy::Reject => Err(TopologyError::UnknownMachine {
+                machine_id: record(String::new()).machine_id,
+            }),
+            UnknownMachinePolicy::AutoRegister { corridor } => {
+                self.register(record(corridor.clone()))?;
+                Ok(Admission::AutoRegistered)
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, TopologyError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, TopologyError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+/// Machines one controller may update, from
+/// [`CorridorTopology::membership`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CorridorMembership {
+    pub corridor: String,
+    pub machines: BTreeSet<String>,
+}
+
+impl CorridorMembership {
+    pub fn contains(&self, machine_id: &str) -> bool {
+        self.machines.contains(machine_id)
+    }
+}
+
+impl<E, H, B, D, G, R> CorridorController<E, H, B, D, G, R>
+where
+    E: SafetyEnvelope,
+    H: HostBudget,
+    B: EcoBandClassifier,
+    D: DwCeilingInvariant,
+    G: BeeGuard,
+    R: MetricsRecorder,
+{
+    /// Restrict updates to the machines `topology` places in `corridor`
+    /// now; machines registered later are not picked up.
+    pub fn in_topology(mut self, topology: &CorridorTopology, corridor: &str) -> Self {
+        self.corridor = Some(topology.membership(corridor));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{EcoBand, SafetyError};
+
+    fn shard_row(row: CorridorRow) -> ShardRow {
+        ShardRow {
+            row,
+            bee: None,
+            calibrated: false,
+            window_start_unix_ms: None,
+        }
+    }
+
+    #[test]
+    fn conflicting_registrations_are_refused() {
+        let mut topology = CorridorTopology::new();
+        let canopy = MachineRecord::new("CYB-AIR-CANOPY-01", "phoenix")
+            .with_location("Phoenix-Intersection-A")
+            .with_coordinates(33.45, -112.07);
+        assert_eq!(
+            topology.register(canopy.clone()).unwrap(),
+            Registration::Added
+        );
+        assert_eq!(
+            topology.register(canopy.clone()).unwrap(),
+            Registration::Unchanged
+        );
+        assert_eq!(
+            topology
+                .register(canopy.clone().with_machine_type("UrbanNanoswarmCanopy"))
+                .unwrap(),
+            Registration::Completed
+        );
+
+        let elsewhere = canopy.clone().with_coordinates(32.22, -110.97);
+        match topology.register(elsewhere).unwrap_err() {
+            TopologyError::Conflict {
+                field, registered, ..
+            } => {
+                assert_eq!(field, "coordinates");
+                assert_eq!(registered.coordinates, canopy.coordinates);
+            }
+            e => panic!("unexpected {e}"),
+        }
+        let tucson = MachineRecord::new("CYB-AIR-CANOPY-01", "tucson");
+        assert!(matches!(
+            topology.register(tucson),
+            Err(TopologyError::Conflict {
+                field: "corridor",
+                ..
+            })
+        ));
+        assert_eq!(
+            topology
+                .get("CYB-AIR-CANOPY-01")
+                .unwrap()
+                .machine_type
+                .as_deref(),
+            Some("UrbanNanoswarmCanopy")
+        );
+    }
+
+    #[test]
+    fn unknown_machines_follow_the_policy() {
+        let rows: Vec<_> = phoenix_nodes()
+            .into_iter()
+            .map(|n| shard_row(n.row))
+            .collect();
+
+        let mut strict = CorridorTopology::new();
+        assert!(matches!(
+            strict.admit("CYB-AIR-CANOPY-01"),
+            Err(TopologyError::UnknownMachine { .. })
+        ));
+        assert!(strict.admit_shard(&rows).is_err());
+        assert_eq!(strict.machines().count(), 0);
+
+        let mut lenient =
+            CorridorTopology::new().with_unknown_policy(UnknownMachinePolicy::AutoRegister {
+                corridor: "phoenix".into(),
+            });
+        assert_eq!(
+            lenient.admit_shard(&rows).unwrap(),
+            ["CYB-AIR-CANOPY-01", "CYB-AIR-SCHOOL-05"]
+        );
+        assert!(lenient.admit_shard(&rows).unwrap().is_empty());
+        assert_eq!(
+            lenient.admit("CYB-AIR-CANOPY-01").unwrap(),
+            Admission::Known
+        );
+
+        // One id at two locations within a shard: all or nothing.
+        let mut moved = rows[0].clone();
+        moved.row.location = "Tucson-Intersection-B".into();
+        let mut fresh = lenient.clone();
+        fresh.machines.clear();
+        let err = fresh.admit_shard(&[rows[0].clone(), moved]).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                TopologyError::Conflict {
+                    field: "location",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert_eq!(fresh.machines().count(), 0);
+
+        let json = lenient.to_json().unwrap();
+        assert_eq!(CorridorTopology::from_json(&json).unwrap(), lenient);
+    }
+
+    #[test]
+    fn controller_refuses_machines_outside_its_corridor() {
+        let nodes = phoenix_nodes();
+        let mut topology = CorridorTopology::new();
+        topology
+            .register(MachineRecord::from_row(&nodes[0].row, "phoenix"))
+            .unwrap();
+        topology
+            .register(MachineRecord::from_row(&nodes[1].row, "tucson"))
+            .unwrap();
+        let controller = phoenix_controller().in_topology(&topology, "phoenix");
+
+        let mut inside = nodes[0].clone();
+        assert!(controller
+            .update_node_duty(&mut inside, EcoBand::Green, 0.0)
+            .is_ok());
+        let mut outside = nodes[1].clone();
+        let before = outside.duty_cycle;
+        let err = controller
+            .update_node_duty(&mut outside, EcoBand::Green, 0.0)
+            .unwrap_err();
+        assert!(
+            matches!(&err, SafetyError::OutsideCorridor { corridor, .. } if corridor == "phoenix"),
+            "{err}"
+        );
+        assert_eq!(err.kind(), "outside_corridor");
+        assert_eq!(outside.duty_cycle, before);
+
+        let assessment = controller.dry_run_node(&nodes[1], EcoBand::Green, 0.0);
+        assert!(assessment.projected.is_none());
+        assert_eq!(assessment.violations[0].kind(), "outside_corridor");
+    }
+}
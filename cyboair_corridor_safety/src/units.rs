@@ -0,0 +1,329 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::locale::UnitStyle;
+use crate::pollutant::Pollutant;
+
+/// Universal gas constant, J mol^-1 K^-1.
+pub const R_GAS: f64 = 8.3145;
+/// Standard pressure, Pa; mixing-ratio conversions use it unless a
+/// pressure is given explicitly.
+pub const P_STANDARD_PA: f64 = 101_325.0;
+
+/// Errors from concentration-unit parsing and conversion.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum UnitError {
+    #[error("unknown concentration unit: {0:?}")]
+    Unknown(String),
+    #[error("mixing-ratio conversion needs T > 0 and MW > 0 (got T={temperature_k}, MW={molar_mass_kg_per_mol})")]
+    InvalidGasParameters {
+        temperature_k: f64,
+        molar_mass_kg_per_mol: f64,
+    },
+    #[error("mixing-ratio conversion needs a positive, finite pressure (got {pressure_pa} Pa)")]
+    InvalidPressure { pressure_pa: f64 },
+    #[error("{unit} is a mixing ratio, but {pollutant} has no molar mass")]
+    NoMolarMass {
+        unit: ConcentrationUnit,
+        pollutant: Pollutant,
+    },
+}
+
+/// Concentration units accepted in qpudatashards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ConcentrationUnit {
+    NgPerM3,
+    UgPerM3,
+    MgPerM3,
+    Ppb,
+    Ppm,
+    /// Mole (volume) fraction, mol/mol.
+    MoleFraction,
+}
+
+impl ConcentrationUnit {
+    /// True for volumetric mixing ratios, which need T and MW to become mass.
+    pub fn is_mixing_ratio(self) -> bool {
+        matches!(
+            self,
+            ConcentrationUnit::Ppb | ConcentrationUnit::Ppm | ConcentrationUnit::MoleFraction
+        )
+    }
+
+    /// Canonical shard spelling.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConcentrationUnit::NgPerM3 => "ng/m3",
+            ConcentrationUnit::UgPerM3 => "ug/m3",
+            ConcentrationUnit::MgPerM3 => "mg/m3",
+            ConcentrationUnit::Ppb => "ppb",
+            ConcentrationUnit::Ppm => "ppm",
+            ConcentrationUnit::MoleFraction => "mol/mol",
+        }
+    }
+
+    /// Label for reports: the shard spelling, or with `µ` and `³` glyphs.
+    pub fn label(self, style: UnitStyle) -> &'static str {
+        match (style, self) {
+            (UnitStyle::Ascii, _) => self.as_str(),
+            (UnitStyle::Unicode, ConcentrationUnit::NgPerM3) => "ng/m³",
+            (UnitStyle::Unicode, ConcentrationUnit::UgPerM3) => "µg/m³",
+            (UnitStyle::Unicode, ConcentrationUnit::MgPerM3) => "mg/m³",
+            (UnitStyle::Unicode, unit) => unit.as_str(),
+        }
+    }
+
+    /// Factor converting one reported unit to kg/m^3 at standard pressure.
+    pub fn kg_per_m3_factor(
+        self,
+        temperature_k: f64,
+        molar_mass_kg_per_mol: f64,
+    ) -> Result<f64, UnitError> {
+        self.kg_per_m3_factor_at_pressure(temperature_k, P_STANDARD_PA, molar_mass_kg_per_mol)
+    }
+
+    /// Factor converting one reported unit to kg/m^3.
+    ///
+    /// Mass units are exact; mixing ratios use the ideal gas law,
+    /// rho = p * MW / (R * T).
+    pub fn kg_per_m3_factor_at_pressure(
+        self,
+        temperature_k: f64,
+        pressure_pa: f64,
+        molar_mass_kg_per_mol: f64,
+    ) -> Result<f64, UnitError> {
+        let scale = match self {
+            ConcentrationUnit::NgPerM3 => return Ok(1e-12),
+            ConcentrationUnit::UgPerM3 => return Ok(1e-9),
+            ConcentrationUnit::MgPerM3 => return Ok(1e-6),
+            ConcentrationUnit::Ppb => 1e-9,
+            ConcentrationUnit::Ppm => 1e-6,
+            ConcentrationUnit::MoleFraction => 1.0,
+        };
+        if !(temperature_k > 0.0 && molar_mass_kg_per_mol > 0.0) {
+            return Err(UnitError::InvalidGasParameters {
+                temperature_k,
+                molar_mass_kg_per_mol,
+            });
+        }
+        if !(pressure_pa > 0.0 && pressure_pa.is_finite()) {
+            return Err(UnitError::InvalidPressure { pressure_pa });
+        }
+        Ok(pressure_pa * molar_mass_kg_per_mol / (R_GAS * temperature_k) * scale)
+    }
+
+    /// Factor to kg/m^3 for a known pollutant; the molar mass is looked up
+    /// only for mixing ratios, so particulate mass units never need one.
+    pub fn kg_per_m3_factor_for(
+        self,
+        pollutant: Pollutant,
+        temperature_k: f64,
+    ) -> Result<f64, UnitError> {
+        self.kg_per_m3_factor_for_at_pressure(pollutant, temperature_k, P_STANDARD_PA)
+    }
+
+    /// `kg_per_m3_factor_for` at an explicit pressure.
+    pub fn kg_per_m3_factor_for_at_pressure(
+        self,
+        pollutant: Pollutant,
+        temperature_k: f64,
+        pressure_pa: f64,
+    ) -> Result<f64, UnitError> {
+        if !self.is_mixing_ratio() {
+            return self.kg_per_m3_factor_at_pressure(temperature_k, pressure_pa, 0.0);
+        }
+        match pollutant.molar_mass() {
+            Some(mw) => self.kg_per_m3_factor_at_pressure(temperature_k, pressure_pa, mw),
+            None => Err(UnitError::NoMolarMass {
+                unit: self,
+                pollutant,
+            }),
+        }
+    }
+}
+
+impl FromStr for ConcentrationUnit {
+    type Err = UnitError;
+
+    /// Accepts both the compact ("ugm3") and slashed ("ug/m3") spellings,
+    /// and report labels ("µg/m³").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let norm = s
+            .trim()
+            .to_ascii_lowercase()
+            .replace("^", "")
+            .replace('³', "3");
+        match norm.as_str() {
+            "ngm3" | "ng/m3" => Ok(ConcentrationUnit::NgPerM3),
+            "ugm3" | "ug/m3" | "µg/m3" | "μg/m3" | "µgm3" | "μgm3" => {
+                Ok(ConcentrationUnit::UgPerM3)
+            }
+            "mgm3" | "mg/m3" => Ok(ConcentrationUnit::MgPerM3),
+            "ppb" => Ok(ConcentrationUnit::Ppb),
+            "ppm" => Ok(ConcentrationUnit::Ppm),
+            "mol/mol" | "molmol" | "molefraction" | "mole fraction" | "mole_fraction" => {
+                Ok(ConcentrationUnit::MoleFraction)
+            }
+            _ => Err(UnitError::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ConcentrationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Conversion from a shard unit string to kg/m^3 per reported unit, at
+/// standard pressure.
+pub fn unit_to_kg_factor(
+    unit: &str,
+    temperature_k: f64,
+    molar_mass_kg_per_mol: f64,
+) -> Result<f64, UnitError> {
+    unit_to_kg_factor_at_pressure(unit, temperature_k, P_STANDARD_PA, molar_mass_kg_per_mol)
+}
+
+/// `unit_to_kg_factor` at an explicit pressure; the entry point for the
+/// guard binaries, so every tool converts shard units the same way.
+pub fn unit_to_kg_factor_at_pressure(
+    unit: &str,
+    temperature_k: f64,
+    pressure_pa: f64,
+    molar_mass_kg_per_mol: f64,
+) -> Result<f64, UnitError> {
+    unit.parse::<ConcentrationUnit>()?
+        .kg_per_m3_factor_at_pressure(temperature_k, pressure_pa, molar_mass_kg_per_mol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_spelling_parses() {
+        let cases = [
+            ("ugm3", ConcentrationUnit::UgPerM3),
+            ("ug/m3", ConcentrationUnit::UgPerM3),
+            ("ug/m^3", ConcentrationUnit::UgPerM3),
+            ("µg/m3", ConcentrationUnit::UgPerM3),
+            ("μg/m3", ConcentrationUnit::UgPerM3),
+            (" UG/M3 ", ConcentrationUnit::UgPerM3),
+            ("mgm3", ConcentrationUnit::MgPerM3),
+            ("mg/m3", ConcentrationUnit::MgPerM3),
+            ("mg/m^3", ConcentrationUnit::MgPerM3),
+            ("ppb", ConcentrationUnit::Ppb),
+            ("PPB", ConcentrationUnit::Ppb),
+            ("ppm", ConcentrationUnit::Ppm),
+            ("PPM", ConcentrationUnit::Ppm),
+            ("ng/m3", ConcentrationUnit::NgPerM3),
+            ("ngm3", ConcentrationUnit::NgPerM3),
+            ("ng/m^3", ConcentrationUnit::NgPerM3),
+            ("mol/mol", ConcentrationUnit::MoleFraction),
+            ("mole fraction", ConcentrationUnit::MoleFraction),
+            ("Mole_Fraction", ConcentrationUnit::MoleFraction),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<ConcentrationUnit>(), Ok(expected), "{s}");
+        }
+    }
+
+    #[test]
+    fn canonical_spelling_round_trips() {
+        for u in [
+            ConcentrationUnit::NgPerM3,
+            ConcentrationUnit::UgPerM3,
+            ConcentrationUnit::MgPerM3,
+            ConcentrationUnit::Ppb,
+            ConcentrationUnit::Ppm,
+            ConcentrationUnit::MoleFraction,
+        ] {
+            assert_eq!(u.to_string().parse::<ConcentrationUnit>(), Ok(u));
+        }
+    }
+
+    #[test]
+    fn unknown_unit_is_an_error() {
+        assert_eq!(
+            unit_to_kg_factor("g/ft3", 300.0, 0.048),
+            Err(UnitError::Unknown("g/ft3".to_string()))
+        );
+        assert!(unit_to_kg_factor("", 300.0, 0.048).is_err());
+    }
+
+    #[test]
+    fn mass_units_are_exact() {
+        assert_eq!(unit_to_kg_factor("ugm3", 0.0, 0.0), Ok(1e-9));
+        assert_eq!(unit_to_kg_factor("mg/m3", 0.0, 0.0), Ok(1e-6));
+        assert_eq!(unit_to_kg_factor("ng/m3", 0.0, 0.0), Ok(1e-12));
+        // Pressure only matters for mixing ratios.
+        assert_eq!(
+            unit_to_kg_factor_at_pressure("ng/m3", 0.0, 0.0, 0.0),
+            Ok(1e-12)
+        );
+    }
+
+    #[test]
+    fn particulate_mixing_ratio_is_rejected() {
+        assert_eq!(
+            ConcentrationUnit::Ppb.kg_per_m3_factor_for(Pollutant::PM25, 300.0),
+            Err(UnitError::NoMolarMass {
+                unit: ConcentrationUnit::Ppb,
+                pollutant: Pollutant::PM25,
+            })
+        );
+        assert_eq!(
+            ConcentrationUnit::UgPerM3.kg_per_m3_factor_for(Pollutant::PM25, 300.0),
+            Ok(1e-9)
+        );
+    }
+
+    #[test]
+    fn mixing_ratio_needs_gas_parameters() {
+        assert!(matches!(
+            unit_to_kg_factor("ppb", 0.0, 0.048),
+            Err(UnitError::InvalidGasParameters { .. })
+        ));
+        let ppb = unit_to_kg_factor("ppb", 298.15, 0.048).unwrap();
+        let ppm = unit_to_kg_factor("ppm", 298.15, 0.048).unwrap();
+        assert!((ppm / ppb - 1000.0).abs() < 1e-9);
+        // 1 ppb O3 at 25 C is ~1.96 ug/m3.
+        assert!((ppb / 1e-9 - 1.962).abs() < 0.01);
+    }
+
+    #[test]
+    fn mixing_ratios_scale_with_each_other_and_pressure() {
+        let ppm = unit_to_kg_factor("ppm", 298.15, 0.028).unwrap();
+        let frac = unit_to_kg_factor("mol/mol", 298.15, 0.028).unwrap();
+        assert!((frac / ppm - 1e6).abs() < 1e-3);
+        // 1 ppm CO at 25 C and 1 atm is ~1.145 mg/m3.
+        assert!((ppm / 1e-6 - 1.145).abs() < 0.005);
+
+        // Phoenix sits near 97 kPa; density scales linearly with pressure.
+        let high = unit_to_kg_factor_at_pressure("ppm", 298.15, 97_000.0, 0.028).unwrap();
+        assert!((high / ppm - 97_000.0 / P_STANDARD_PA).abs() < 1e-12);
+        assert_eq!(
+            ConcentrationUnit::Ppm.kg_per_m3_factor_for_at_pressure(
+                Pollutant::CO,
+                298.15,
+                97_000.0
+            ),
+            Ok(high)
+        );
+    }
+
+    #[test]
+    fn mixing_ratio_needs_positive_pressure() {
+        for p in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                unit_to_kg_factor_at_pressure("ppm", 298.15, p, 0.028),
+                Err(UnitError::InvalidPressure { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Geo weight that follows the wind toward sensitive receptors.
+//!
+//! `NodeState::geo_weight` is fixed per node, but a node upwind of a school
+//! matters most while the wind carries its air there. With a
+//! [`DynamicGeoWeight`] on the controller and a [`Wind`] for the step, a
+//! node's effective weight is its static weight plus, for each receptor
+//! within `max_distance_m` whose bearing from the node is within
+//! `half_angle_deg` of the downwind direction,
+//! `base_weight * (1 - distance / max_distance_m)`.
+//!
+//! Without wind, in calm air, or for a node whose `location` has no
+//! position, the static weight is used. Positions are on a local plane in
+//! metres east and north of any fixed origin.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::NodeState;
+
+/// Wind for one step, meteorological convention.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Wind {
+    /// Direction the wind blows from, degrees clockwise from north.
+    pub from_deg: f64,
+    pub speed_mps: f64,
+}
+
+impl Wind {
+    /// Direction the wind blows toward, in [0, 360).
+    pub fn downwind_deg(&self) -> f64 {
+        (self.from_deg + 180.0).rem_euclid(360.0)
+    }
+}
+
+/// A point on the local plane, m.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SitePosition {
+    pub east_m: f64,
+    pub north_m: f64,
+}
+
+impl SitePosition {
+    pub fn new(east_m: f64, north_m: f64) -> Self {
+        SitePosition { east_m, north_m }
+    }
+
+    pub fn distance_m(&self, to: &SitePosition) -> f64 {
+        (to.east_m - self.east_m).hypot(to.north_m - self.north_m)
+    }
+
+    /// Bearing to `to`, degrees clockwise from north, in [0, 360).
+    pub fn bearing_deg(&self, to: &SitePosition) -> f64 {
+        (to.east_m - self.east_m)
+            .atan2(to.north_m - self.north_m)
+            .to_degrees()
+            .rem_euclid(360.0)
+    }
+}
+
+/// A school, clinic or similar site whose exposure weights nearby nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Receptor {
+    pub name: String,
+    pub position: SitePosition,
+    /// Boost given to a node right next to it, directly upwind.
+    pub base_weight: f64,
+}
+
+/// Static and wind-adjusted geo weight of one duty update.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GeoWeighting {
+    /// `NodeState::geo_weight`.
+    pub static_weight: f64,
+    /// Weight Equation 5 ran with.
+    pub effective: f64,
+}
+
+/// Wind-dependent geo weighting; see the module docs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DynamicGeoWeight {
+    /// Node positions keyed by `CorridorRow::location`.
+    pub sites: HashMap<String, SitePosition>,
+    pub receptors: Vec<Receptor>,
+    /// Receptors further than this do not boost a node, m.
+    pub max_distance_m: f64,
+    /// Half-width of the downwind cone, degrees in (0, 180].
+    pub half_angle_deg: f64,
+}
+
+impl DynamicGeoWeight {
+    pub fn new(max_distance_m: f64, half_angle_deg: f64) -> Self {
+        DynamicGeoWeight {
+            sites: HashMap::new(),
+            receptors: Vec::new(),
+            max_distance_m,
+            half_angle_deg,
+        }
+    }
+
+    pub fn with_site(mut self, location: impl Into<String>, position: SitePosition) -> Self {
+        self.sites.insert(location.into(), position);
+        self
+    }
+
+    pub fn with_receptor(
+        mut self,
+        name: impl Into<String>,
+        position: SitePosition,
+        base_weight: f64,
+    ) -> Self {
+        self.receptors.push(Receptor {
+            name: name.into(),
+            position,
+            base_weight,
+        });
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: String| {
+            Err(ConfigError::Invalid {
+                field: "geo_weighting",
+                reason,
+            })
+        };
+        if !(self.max_distance_m.is_finite() && self.max_distance_m > 0.0) {
+            return invalid(format!(
+                "max_distance_m must be finite and > 0, got {}",
+                self.max_distance_m
+            ));
+        }
+        if !(self.half_angle_deg > 0.0 && self.half_angle_deg <= 180.0) {
+            return invalid(format!(
+                "half_angle_deg must be in (0, 180], got {}",
+                self.half_angle_deg
+            ));
+        }
+        for r in &self.receptors {
+            if !(r.base_weight.is_finite() && r.base_weight >= 0.0) {
+                return invalid(format!(
+                    "receptor {} base_weight must be finite and >= 0, got {}",
+                    r.name, r.base_weight
+                ));
+            }
+        }
+        let positions = self
+            .sites
+            .iter()
+            .chain(self.receptors.iter().map(|r| (&r.name, &r.position)));
+        for (name, p) in positions {
+            if !(p.east_m.is_finite() && p.north_m.is_finite()) {
+                return invalid(format!("position of {name} must be finite"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The node's effective weight in `wind`.
+    pub fn weighting(&self, node: &NodeState, wind: Option<Wind>) -> GeoWeighting {
+        let static_weight = node.geo_weight;
+        let boost = match (wind, self.sites.get(&node.row.location)) {
+            (Some(wind), Some(site)) if wind.speed_mps > 0.0 => self.boost(site, wind),
+            _ => 0.0,
+        };
+        GeoWeighting {
+            static_weight,
+            effective: static_weight + boost,
+        }
+    }
+
+    fn boost(&self, site: &SitePosition, wind: Wind) -> f64 {
+        let downwind = wind.downwind_deg();
+        self.receptors
+            .iter()
+            .filter_map(|r| {
+                let distance = site.distance_m(&r.position);
+                if distance > self.max_distance_m {
+                    return None;
+                }
+                let off =
+                    (site.bearing_deg(&r.position) - downwind + 180.0).rem_euclid(360.0) - 180.0;
+                let downwind_of_node = distance == 0.0 || off.abs() <= self.half_angle_deg;
+                downwind_of_node.then(|| r.base_weight * (1.0 - distance / self.max_distance_m))
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{phoenix_controller, phoenix_nodes};
+    use crate::{EcoBand, StepInput};
+
+    /// A school at the origin, one node 200 m west of it and one 200 m east.
+    fn school_between() -> DynamicGeoWeight {
+        DynamicGeoWeight::new(500.0, 30.0)
+            .with_site("West", SitePosition::new(-200.0, 0.0))
+            .with_site("East", SitePosition::new(200.0, 0.0))
+            .with_receptor("Elementary-North", SitePosition::new(0.0, 0.0), 1.0)
+    }
+
+    fn west_and_east() -> Vec<NodeState> {
+        let mut nodes = phoenix_nodes();
+        nodes[0].row.location = "West".into();
+        nodes[1].row.location = "East".into();
+        for node in &mut nodes {
+            node.geo_weight = 0.5;
+        }
+        nodes
+    }
+
+    const WESTERLY: Wind = Wind {
+        from_deg: 270.0,
+        speed_mps: 4.0,
+    };
+
+    #[test]
+    fn rotating_the_wind_moves_the_boost_across_the_school() {
+        let geo = school_between();
+        let nodes = west_and_east();
+        let weights = |wind| -> Vec<f64> {
+            nodes
+                .iter()
+                .map(|n| geo.weighting(n, wind).effective)
+                .collect()
+        };
+
+        // From the west the west node is upwind: 0.5 + 1.0 * (1 - 200/500).
+        assert_eq!(weights(Some(WESTERLY)), [1.1, 0.5]);
+        let easterly = Wind {
+            from_deg: 90.0,
+            ..WESTERLY
+        };
+        assert_eq!(weights(Some(easterly)), [0.5, 1.1]);
+
+        // Outside the cone, in calm air and without wind: static.
+        let northerly = Wind {
+            from_deg: 0.0,
+            ..WESTERLY
+        };
+        assert_eq!(weights(Some(northerly)), [0.5, 0.5]);
+        let calm = Wind {
+            speed_mps: 0.0,
+            ..WESTERLY
+        };
+        assert_eq!(weights(Some(calm)), [0.5, 0.5]);
+        assert_eq!(weights(None), [0.5, 0.5]);
+
+        // Too far away.
+        let far = DynamicGeoWeight {
+            max_distance_m: 150.0,
+            ..school_between()
+        };
+        assert_eq!(far.weighting(&nodes[0], Some(WESTERLY)).effective, 0.5);
+    }
+
+    #[test]
+    fn controller_uses_and_reports_the_effective_weight() {
+        let mut controller = phoenix_controller();
+        // Only the geo term moves the duty.
+        controller.eta_m = 0.0;
+        controller.eta_k = 0.0;
+        controller.eta_b = 0.0;
+        controller.eta_p = 0.0;
+        controller.eta_dw = 0.0;
+        controller.geo_weighting = Some(school_between());
+        let input = |wind| StepInput {
+            phi_dw: 0.0,
+            alpha_m: 0.5,
+            alpha_k: 0.5,
+            eco_offset: 0.0,
+            wind,
+        };
+
+        let duties = |wind: Option<Wind>| {
+            let mut nodes = west_and_east();
+            controller
+                .run_steps(&mut nodes, 1, |_| input(wind))
+                .unwrap();
+            nodes.iter().map(|n| n.duty_cycle).collect::<Vec<_>>()
+        };
+        let (west, east, still) = (
+            duties(Some(WESTERLY)),
+            duties(Some(Wind {
+                from_deg: 90.0,
+                ..WESTERLY
+            })),
+            duties(None),
+        );
+        assert!(west[0] > still[0] && west[1] == still[1]);
+        assert!(east[1] > still[1] && east[0] == still[0]);
+
+        let mut node = west_and_east()[0].clone();
+        let report = controller
+            .update_node_duty_in_wind(&mut node, EcoBand::Green, 0.0, Some(WESTERLY))
+            .unwrap();
+        let weighting = report.geo_weight.unwrap();
+        assert_eq!(weighting.static_weight, 0.5);
+        assert_eq!(weighting.effective, 1.1);
+        assert!(
+            (report.contributions.geo_weight - controller.eta_w * weighting.effective).abs()
+                < 1e-15
+        );
+
+        let report = controller
+            .update_node_duty(&mut node, EcoBand::Green, 0.0)
+            .unwrap();
+        assert_eq!(report.geo_weight.unwrap().effective, 0.5);
+    }
+
+    #[test]
+    fn bad_geometry_is_rejected() {
+        assert_eq!(school_between().validate(), Ok(()));
+        for bad in [
+            DynamicGeoWeight::new(0.0, 30.0),
+            DynamicGeoWeight::new(500.0, 200.0),
+            school_between().with_receptor("Clinic", SitePosition::new(f64::NAN, 0.0), 1.0),
+            school_between().with_receptor("Clinic", SitePosition::new(0.0, 0.0), -1.0),
+        ] {
+            assert!(matches!(
+                bad.validate(),
+                Err(ConfigError::Invalid {
+                    field: "geo_weighting",
+                    ..
+                })
+            ));
+        }
+    }
+}
@@ -1,9 +1,25 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+mod shard;
+
+/// Reference CEIM math every computation here delegates to.
+pub use cyboair_corridor_safety::ceim;
+use cyboair_corridor_safety::ceim::{ecoimpact, ecoimpact_load};
+pub use cyboair_corridor_safety::pollutant::{Pollutant, UnknownPollutant};
+pub use cyboair_corridor_safety::units::{
+    unit_to_kg_factor, unit_to_kg_factor_at_pressure, ConcentrationUnit, UnitError, P_STANDARD_PA,
+};
+pub use shard::{read_rows, read_rows_from_path, write_rows, ShardError};
+
 /// Core row schema, aligned with Cybo-Air / EcoNet qpudatashards.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GovernanceRow {
     pub machine_id: String,
+    #[serde(rename = "type")]
     pub r#type: String,
     pub location: String,
     pub pollutant: String,
@@ -14,60 +30,773 @@ pub struct GovernanceRow {
     pub period_s: f64,
     pub lambda_hazard: f64,
     pub beta_nb_per_kg: f64,
+    #[serde(alias = "ecoimpactscore")]
     pub ecoimpact_score: f64,
 }
 
-/// Deterministic unit operator C_u (kg/m3 per reported unit).
-pub fn unit_to_kg_factor(unit: &str, temperature_k: f64, molar_mass_kg_per_mol: f64) -> f64 {
-    match unit {
-        "ug/m3" => 1e-9,
-        "mg/m3" => 1e-6,
-        "ppb" => {
-            let r = 8.3145_f64;
-            molar_mass_kg_per_mol / (r * temperature_k) * 1e-9
-        }
-        _ => 0.0,
+impl GovernanceRow {
+    /// Parsed concentration unit, shared with the corridor controller.
+    pub fn concentration_unit(&self) -> Result<ConcentrationUnit, UnitError> {
+        self.unit.parse()
+    }
+
+    /// Parsed pollutant species.
+    pub fn pollutant_kind(&self) -> Result<Pollutant, UnknownPollutant> {
+        self.pollutant.parse()
     }
 }
 
-/// CEIM-style conserved mass operator Mx = Cu * Q * t.
+/// CEIM-style conserved mass operator Mx = Cu * Q * t, at standard
+/// pressure. Unknown units are an error rather than silently zero mass;
+/// the molar mass is only needed (and looked up) for gas-phase mixing
+/// ratios.
 pub fn compute_mass_kg(
     row: &GovernanceRow,
+    pollutant: Pollutant,
     temperature_k: f64,
-    molar_mass_kg_per_mol: f64,
-) -> f64 {
-    let alpha = unit_to_kg_factor(&row.unit, temperature_k, molar_mass_kg_per_mol);
-    let delta_c = (row.cin - row.cout).max(0.0);
-    let c_u = alpha * delta_c;
-    c_u * row.airflow_m3_per_s * row.period_s
+) -> Result<f64, UnitError> {
+    compute_mass_kg_at_pressure(row, pollutant, temperature_k, P_STANDARD_PA)
+}
+
+/// `compute_mass_kg` at an explicit ambient pressure, Pa.
+pub fn compute_mass_kg_at_pressure(
+    row: &GovernanceRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    pressure_pa: f64,
+) -> Result<f64, UnitError> {
+    let alpha = row.concentration_unit()?.kg_per_m3_factor_for_at_pressure(
+        pollutant,
+        temperature_k,
+        pressure_pa,
+    )?;
+    Ok(ceim::mass_kg(
+        row.cin,
+        row.cout,
+        alpha,
+        row.airflow_m3_per_s,
+        row.period_s,
+    ))
 }
 
 /// Hazard-weighted NanoKarmaBytes Kx = lambda * beta * Mx.
 pub fn compute_karma_bytes(row: &GovernanceRow, mass_kg: f64) -> f64 {
-    row.lambda_hazard * row.beta_nb_per_kg * mass_kg
+    ceim::karma_bytes(row.lambda_hazard, row.beta_nb_per_kg, mass_kg)
+}
+
+/// Numeric row fields, for finiteness checks.
+fn numeric_fields(row: &GovernanceRow) -> [(&'static str, f64); 7] {
+    [
+        ("cin", row.cin),
+        ("cout", row.cout),
+        ("airflow_m3_per_s", row.airflow_m3_per_s),
+        ("period_s", row.period_s),
+        ("lambda_hazard", row.lambda_hazard),
+        ("beta_nb_per_kg", row.beta_nb_per_kg),
+        ("ecoimpact_score", row.ecoimpact_score),
+    ]
 }
 
-/// Normalized ecoimpact index Sx in [0,1].
-pub fn compute_ecoimpact(mass_kg: f64, karma_bytes: f64, k0: f64, alpha: f64) -> f64 {
-    if k0 <= 0.0 {
-        return 0.0;
+fn finite(field: &'static str, value: f64) -> Result<f64, ValidationError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(ValidationError::NonFinite { field, value })
     }
-    let k_ratio = karma_bytes / k0;
-    1.0 - (-alpha * k_ratio).exp()
 }
 
-/// Governance check: ensure mass and Karma are physically plausible.
-pub fn validate_row(
+/// `compute_mass_kg` that refuses NaN or infinite row values, which would
+/// otherwise give NaN mass (or zero, for a NaN `cin`), and an overflowing
+/// result.
+pub fn checked_mass_kg(
     row: &GovernanceRow,
+    pollutant: Pollutant,
     temperature_k: f64,
-    molar_mass_kg_per_mol: f64,
-) -> Result<(), Box<dyn Error>> {
-    let m = compute_mass_kg(row, temperature_k, molar_mass_kg_per_mol);
-    if m < 0.0 {
-        return Err("Negative mass violates CEIM conservation".into());
+) -> Result<f64, ValidationError> {
+    checked_mass_kg_at_pressure(row, pollutant, temperature_k, P_STANDARD_PA)
+}
+
+/// `checked_mass_kg` at an explicit ambient pressure, Pa.
+pub fn checked_mass_kg_at_pressure(
+    row: &GovernanceRow,
+    pollutant: Pollutant,
+    temperature_k: f64,
+    pressure_pa: f64,
+) -> Result<f64, ValidationError> {
+    for (field, value) in numeric_fields(row) {
+        finite(field, value)?;
+    }
+    let mass_kg = compute_mass_kg_at_pressure(row, pollutant, temperature_k, pressure_pa)?;
+    finite("mass_kg", mass_kg)
+}
+
+/// `compute_karma_bytes` that refuses a non-finite hazard, Karma/kg or
+/// mass, and an overflowing result.
+pub fn checked_karma_bytes(row: &GovernanceRow, mass_kg: f64) -> Result<f64, ValidationError> {
+    finite("lambda_hazard", row.lambda_hazard)?;
+    finite("beta_nb_per_kg", row.beta_nb_per_kg)?;
+    finite("mass_kg", mass_kg)?;
+    finite("karma_bytes", compute_karma_bytes(row, mass_kg))
+}
+
+/// Reference scales and weights for `compute_ecoimpact_with`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EcoImpactParams {
+    /// Steepness of the saturation curve.
+    pub alpha: f64,
+    /// Mass scale M0, kg.
+    pub mass_ref_kg: f64,
+    /// NanoKarmaBytes scale K0.
+    pub karma_ref: f64,
+    pub w_mass: f64,
+    pub w_karma: f64,
+}
+
+impl EcoImpactParams {
+    /// Karma alone, as `compute_ecoimpact` has always scored rows.
+    pub fn karma_only(k0: f64, alpha: f64) -> Self {
+        EcoImpactParams {
+            alpha,
+            mass_ref_kg: 1.0,
+            karma_ref: k0,
+            w_mass: 0.0,
+            w_karma: 1.0,
+        }
+    }
+}
+
+/// Inputs `compute_ecoimpact_with` refuses to score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EcoImpactError {
+    /// A reference scale (`mass_ref_kg` or `karma_ref`) is zero or negative.
+    NonPositiveScale { name: &'static str, value: f64 },
+    /// An input or parameter is NaN or infinite.
+    NonFinite { name: &'static str, value: f64 },
+}
+
+impl std::fmt::Display for EcoImpactError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcoImpactError::NonPositiveScale { name, value } => {
+                write!(f, "{name} must be positive, got {value}")
+            }
+            EcoImpactError::NonFinite { name, value } => {
+                write!(f, "{name} must be finite, got {value}")
+            }
+        }
+    }
+}
+
+impl Error for EcoImpactError {}
+
+/// Normalized ecoimpact index
+/// Sx = 1 - exp(-alpha * (w_m * M / M0 + w_k * K / K0)), clamped to [0,1].
+pub fn compute_ecoimpact_with(
+    mass_kg: f64,
+    karma_bytes: f64,
+    params: &EcoImpactParams,
+) -> Result<f64, EcoImpactError> {
+    for (name, value) in [
+        ("mass_kg", mass_kg),
+        ("karma_bytes", karma_bytes),
+        ("alpha", params.alpha),
+        ("mass_ref_kg", params.mass_ref_kg),
+        ("karma_ref", params.karma_ref),
+        ("w_mass", params.w_mass),
+        ("w_karma", params.w_karma),
+    ] {
+        if !value.is_finite() {
+            return Err(EcoImpactError::NonFinite { name, value });
+        }
+    }
+    for (name, value) in [
+        ("mass_ref_kg", params.mass_ref_kg),
+        ("karma_ref", params.karma_ref),
+    ] {
+        if value <= 0.0 {
+            return Err(EcoImpactError::NonPositiveScale { name, value });
+        }
+    }
+    let load = ecoimpact_load(
+        mass_kg,
+        params.mass_ref_kg,
+        params.w_mass,
+        karma_bytes,
+        params.karma_ref,
+        params.w_karma,
+    );
+    Ok(ecoimpact(load, params.alpha))
+}
+
+/// Karma-only ecoimpact index Sx in [0,1]; `mass_kg` is ignored.
+///
+/// Kept for existing callers. `k0 <= 0` scores 0.0, as it always has; a
+/// NaN or infinite input scores 1.0, so a broken reading is never taken
+/// for a clean one. New code should use the checked form so
+/// misconfiguration is not mistaken for "no impact".
+pub fn compute_ecoimpact(_mass_kg: f64, karma_bytes: f64, k0: f64, alpha: f64) -> f64 {
+    match compute_ecoimpact_with(0.0, karma_bytes, &EcoImpactParams::karma_only(k0, alpha)) {
+        Ok(s) => s,
+        Err(EcoImpactError::NonFinite { .. }) => 1.0,
+        Err(EcoImpactError::NonPositiveScale { .. }) => 0.0,
+    }
+}
+
+/// How far `cout` may exceed `cin`, as a fraction of `cin`, before the
+/// row counts as a concentration inversion rather than sensor noise.
+pub const INVERSION_TOLERANCE: f64 = 0.05;
+
+/// A physically implausible governance row.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ValidationError {
+    #[error(transparent)]
+    UnknownPollutant(#[from] UnknownPollutant),
+    /// The unit could not be parsed, or not converted for this pollutant.
+    #[error(transparent)]
+    UnknownUnit(#[from] UnitError),
+    #[error("negative mass {mass_kg} kg violates CEIM conservation")]
+    NegativeMass { mass_kg: f64 },
+    #[error(
+        "negative hazard ({lambda_hazard}) or Karma/kg ({beta_nb_per_kg}) violates governance spec"
+    )]
+    NegativeHazard {
+        lambda_hazard: f64,
+        beta_nb_per_kg: f64,
+    },
+    #[error("outlet concentration {cout} exceeds inlet {cin} beyond tolerance")]
+    ConcentrationInversion { cin: f64, cout: f64 },
+    #[error("airflow ({airflow_m3_per_s} m3/s) and period ({period_s} s) must be positive")]
+    NonPositiveFlow {
+        airflow_m3_per_s: f64,
+        period_s: f64,
+    },
+    #[error("ecoimpact score {0} is outside [0, 1]")]
+    EcoimpactOutOfRange(f64),
+    /// A row value, or the mass or karma computed from it, is NaN or
+    /// infinite.
+    #[error("{field} is not finite ({value})")]
+    NonFinite { field: &'static str, value: f64 },
+}
+
+/// Every check `validate_row` applies, in order. Non-finite values come
+/// first, since comparisons against NaN pass every later check. The mass
+/// check needs a known pollutant and unit, so it is skipped when either is
+/// missing.
+pub fn row_violations(row: &GovernanceRow, temperature_k: f64) -> Vec<ValidationError> {
+    let mut errors: Vec<ValidationError> = numeric_fields(row)
+        .into_iter()
+        .filter_map(|(field, value)| finite(field, value).err())
+        .collect();
+    match row.pollutant_kind() {
+        Ok(pollutant) => match compute_mass_kg(row, pollutant, temperature_k) {
+            Ok(mass_kg) if mass_kg < 0.0 => errors.push(ValidationError::NegativeMass { mass_kg }),
+            Ok(mass_kg) if errors.is_empty() => errors.extend(finite("mass_kg", mass_kg).err()),
+            Ok(_) => {}
+            Err(e) => errors.push(e.into()),
+        },
+        Err(e) => {
+            errors.push(e.into());
+            if let Err(e) = row.concentration_unit() {
+                errors.push(e.into());
+            }
+        }
     }
     if row.lambda_hazard < 0.0 || row.beta_nb_per_kg < 0.0 {
-        return Err("Negative hazard or Karma/kg violates governance spec".into());
+        errors.push(ValidationError::NegativeHazard {
+            lambda_hazard: row.lambda_hazard,
+            beta_nb_per_kg: row.beta_nb_per_kg,
+        });
+    }
+    if row.cout - row.cin > INVERSION_TOLERANCE * row.cin.abs() {
+        errors.push(ValidationError::ConcentrationInversion {
+            cin: row.cin,
+            cout: row.cout,
+        });
+    }
+    if !(row.airflow_m3_per_s > 0.0 && row.period_s > 0.0) {
+        errors.push(ValidationError::NonPositiveFlow {
+            airflow_m3_per_s: row.airflow_m3_per_s,
+            period_s: row.period_s,
+        });
+    }
+    if !(0.0..=1.0).contains(&row.ecoimpact_score) {
+        errors.push(ValidationError::EcoimpactOutOfRange(row.ecoimpact_score));
+    }
+    errors
+}
+
+/// Governance check: ensure mass and Karma are physically plausible.
+///
+/// Returns the first of `row_violations`; use that (or `validate_all`) to
+/// see every problem. Callers returning `Box<dyn Error>` can keep using `?`.
+pub fn validate_row(row: &GovernanceRow, temperature_k: f64) -> Result<(), ValidationError> {
+    match row_violations(row, temperature_k).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Rows that failed validation, keyed by machine id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub checked: usize,
+    /// Every violation found across that machine's rows; a machine with
+    /// several pollutant rows can contribute several.
+    pub failures: BTreeMap<String, Vec<ValidationError>>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn violations(&self) -> usize {
+        self.failures.values().map(Vec::len).sum()
+    }
+}
+
+/// Run `row_violations` over every row, collecting failures rather than
+/// stopping at the first.
+pub fn validate_all(rows: &[GovernanceRow], temperature_k: f64) -> ValidationReport {
+    let mut report = ValidationReport {
+        checked: rows.len(),
+        ..ValidationReport::default()
+    };
+    for row in rows {
+        let errors = row_violations(row, temperature_k);
+        if !errors.is_empty() {
+            report
+                .failures
+                .entry(row.machine_id.clone())
+                .or_default()
+                .extend(errors);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> EcoImpactParams {
+        EcoImpactParams {
+            alpha: 1.0,
+            mass_ref_kg: 0.01,
+            karma_ref: 1.0e6,
+            w_mass: 0.5,
+            w_karma: 0.5,
+        }
+    }
+
+    #[test]
+    fn monotone_in_mass_and_karma() {
+        let p = params();
+        let mut last = -1.0;
+        for m in [0.0, 0.001, 0.01, 0.05] {
+            let s = compute_ecoimpact_with(m, 2.0e5, &p).unwrap();
+            assert!(s > last, "mass {m}: {s} <= {last}");
+            last = s;
+        }
+        let mut last = -1.0;
+        for k in [0.0, 1.0e5, 1.0e6, 5.0e6] {
+            let s = compute_ecoimpact_with(0.002, k, &p).unwrap();
+            assert!(s > last, "karma {k}: {s} <= {last}");
+            last = s;
+        }
+    }
+
+    #[test]
+    fn result_is_clamped_to_unit_interval() {
+        let p = params();
+        assert_eq!(compute_ecoimpact_with(0.0, 0.0, &p), Ok(0.0));
+        // Negative loads would otherwise go below zero.
+        assert_eq!(compute_ecoimpact_with(-1.0, -1.0e6, &p), Ok(0.0));
+        let s = compute_ecoimpact_with(1.0e3, 1.0e12, &p).unwrap();
+        assert!(s <= 1.0 && s > 0.999);
+    }
+
+    #[test]
+    fn bad_scales_and_inputs_are_errors() {
+        let mut p = params();
+        p.karma_ref = 0.0;
+        assert_eq!(
+            compute_ecoimpact_with(0.01, 1.0, &p),
+            Err(EcoImpactError::NonPositiveScale {
+                name: "karma_ref",
+                value: 0.0
+            })
+        );
+        let mut p = params();
+        p.mass_ref_kg = -1.0;
+        assert!(matches!(
+            compute_ecoimpact_with(0.01, 1.0, &p),
+            Err(EcoImpactError::NonPositiveScale {
+                name: "mass_ref_kg",
+                ..
+            })
+        ));
+        assert!(matches!(
+            compute_ecoimpact_with(f64::NAN, 1.0, &params()),
+            Err(EcoImpactError::NonFinite {
+                name: "mass_kg",
+                ..
+            })
+        ));
+        let mut p = params();
+        p.alpha = f64::INFINITY;
+        assert!(matches!(
+            compute_ecoimpact_with(0.01, 1.0, &p),
+            Err(EcoImpactError::NonFinite { name: "alpha", .. })
+        ));
+    }
+
+    #[test]
+    fn validate_all_collects_every_failure() {
+        let csv = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpact_score,site_notes
+CYB-AIR-01,Canopy,A,PM2.5,40,28,ug/m3,3.0,3600,3.0,5.0e8,0.92,ok
+CYB-AIR-02,Canopy,B,Unobtainium,40,28,ug/m3,3.0,3600,3.0,5.0e8,0.92,unknown species
+CYB-AIR-03,Canopy,C,PM2.5,40,28,furlongs,3.0,3600,3.0,5.0e8,0.92,unknown unit
+CYB-AIR-03,Canopy,C,PM10,40,28,ug/m3,3.0,3600,-1.0,5.0e8,0.92,negative hazard
+";
+        let rows = read_rows(csv.as_bytes()).unwrap();
+        let report = validate_all(&rows, 298.15);
+        assert_eq!(report.checked, 4);
+        assert!(!report.is_ok());
+        assert_eq!(report.violations(), 3);
+        assert!(!report.failures.contains_key("CYB-AIR-01"));
+        assert!(matches!(
+            report.failures["CYB-AIR-02"][..],
+            [ValidationError::UnknownPollutant(_)]
+        ));
+        assert!(matches!(
+            report.failures["CYB-AIR-03"][..],
+            [
+                ValidationError::UnknownUnit(UnitError::Unknown(_)),
+                ValidationError::NegativeHazard { .. }
+            ]
+        ));
+    }
+
+    fn valid_row() -> GovernanceRow {
+        GovernanceRow {
+            machine_id: "CYB-AIR-01".into(),
+            r#type: "Canopy".into(),
+            location: "A".into(),
+            pollutant: "PM2.5".into(),
+            cin: 40.0,
+            cout: 28.0,
+            unit: "ug/m3".into(),
+            airflow_m3_per_s: 3.0,
+            period_s: 3600.0,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 5.0e8,
+            ecoimpact_score: 0.92,
+        }
+    }
+
+    #[test]
+    fn valid_row_passes() {
+        assert_eq!(validate_row(&valid_row(), 298.15), Ok(()));
+        assert!(row_violations(&valid_row(), 298.15).is_empty());
+    }
+
+    #[test]
+    fn each_physical_violation_is_reported() {
+        let mut row = valid_row();
+        row.cout = 41.0; // within 5% of cin: sensor noise
+        assert_eq!(validate_row(&row, 298.15), Ok(()));
+        row.cout = 45.0;
+        assert_eq!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::ConcentrationInversion {
+                cin: 40.0,
+                cout: 45.0
+            })
+        );
+
+        let mut row = valid_row();
+        row.airflow_m3_per_s = -3.0;
+        assert!(matches!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::NegativeMass { .. })
+        ));
+        assert!(matches!(
+            row_violations(&row, 298.15)[..],
+            [
+                ValidationError::NegativeMass { .. },
+                ValidationError::NonPositiveFlow { .. }
+            ]
+        ));
+
+        let mut row = valid_row();
+        row.period_s = 0.0;
+        assert!(matches!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::NonPositiveFlow { period_s, .. }) if period_s == 0.0
+        ));
+
+        let mut row = valid_row();
+        row.ecoimpact_score = 1.2;
+        assert_eq!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::EcoimpactOutOfRange(1.2))
+        );
+
+        let mut row = valid_row();
+        row.beta_nb_per_kg = -1.0;
+        assert!(matches!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::NegativeHazard { .. })
+        ));
+
+        let mut row = valid_row();
+        row.unit = "furlongs".into();
+        assert!(matches!(
+            validate_row(&row, 298.15),
+            Err(ValidationError::UnknownUnit(_))
+        ));
+    }
+
+    #[test]
+    fn non_finite_values_never_reach_mass_or_karma() {
+        let setters: [fn(&mut GovernanceRow, f64); 7] = [
+            |r, v| r.cin = v,
+            |r, v| r.cout = v,
+            |r, v| r.airflow_m3_per_s = v,
+            |r, v| r.period_s = v,
+            |r, v| r.lambda_hazard = v,
+            |r, v| r.beta_nb_per_kg = v,
+            |r, v| r.ecoimpact_score = v,
+        ];
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            for set in setters {
+                let mut row = valid_row();
+                set(&mut row, value);
+                let errors = row_violations(&row, 298.15);
+                assert!(
+                    matches!(errors[0], ValidationError::NonFinite { .. }),
+                    "{errors:?}"
+                );
+                assert!(matches!(
+                    checked_mass_kg(&row, Pollutant::PM25, 298.15),
+                    Err(ValidationError::NonFinite { .. })
+                ));
+            }
+            assert_eq!(
+                checked_karma_bytes(&valid_row(), value)
+                    .unwrap_err()
+                    .to_string(),
+                format!("mass_kg is not finite ({value})")
+            );
+            // The unchecked form scores these rows as full impact rather
+            // than failing.
+            let params = EcoImpactParams::karma_only(1.0e9, 1.0);
+            assert!(compute_ecoimpact_with(0.0, value, &params).is_err());
+            assert_eq!(compute_ecoimpact(0.0, value, 1.0e9, 1.0), 1.0);
+            assert_eq!(compute_ecoimpact(0.0, 5.0e8, 1.0e9, value), 1.0);
+        }
+
+        // Finite inputs whose mass overflows.
+        let mut row = valid_row();
+        row.airflow_m3_per_s = f64::MAX;
+        row.period_s = f64::MAX;
+        assert!(matches!(
+            row_violations(&row, 298.15)[..],
+            [ValidationError::NonFinite {
+                field: "mass_kg",
+                ..
+            }]
+        ));
+        row = valid_row();
+        row.lambda_hazard = f64::MAX;
+        row.beta_nb_per_kg = f64::MAX;
+        assert!(matches!(
+            checked_karma_bytes(&row, 1.0),
+            Err(ValidationError::NonFinite {
+                field: "karma_bytes",
+                ..
+            })
+        ));
+
+        let row = valid_row();
+        let mass_kg = checked_mass_kg(&row, Pollutant::PM25, 298.15).unwrap();
+        assert_eq!(Ok(mass_kg), compute_mass_kg(&row, Pollutant::PM25, 298.15));
+        assert_eq!(
+            checked_karma_bytes(&row, mass_kg),
+            Ok(compute_karma_bytes(&row, mass_kg))
+        );
+    }
+
+    #[test]
+    fn mass_matches_corridor_controller_for_every_unit() {
+        let cases = [
+            ("PM2.5", "ng/m3"),
+            ("PM2.5", "ug/m3"),
+            ("PM10", "mg/m3"),
+            ("O3", "ppb"),
+            ("CO", "ppm"),
+            ("CO", "mol/mol"),
+        ];
+        for (pollutant, unit) in cases {
+            let mut row = valid_row();
+            row.pollutant = pollutant.into();
+            row.unit = unit.into();
+            if unit == "mol/mol" {
+                row.cin = 40.0e-6;
+                row.cout = 28.0e-6;
+            }
+            let corridor = cyboair_corridor_safety::CorridorRow {
+                machine_id: row.machine_id.clone(),
+                r#type: row.r#type.clone(),
+                location: row.location.clone(),
+                pollutant: row.pollutant.clone(),
+                cin: row.cin,
+                cout: row.cout,
+                unit: row.unit.clone(),
+                airflow_m3_per_s: row.airflow_m3_per_s,
+                period_s: row.period_s,
+                lambda_hazard: row.lambda_hazard,
+                beta_nb_per_kg: row.beta_nb_per_kg,
+                ecoimpact_score: row.ecoimpact_score,
+                trace_id: None,
+                shard_version: None,
+            };
+            let p = row.pollutant_kind().unwrap();
+            for pressure_pa in [P_STANDARD_PA, 97_000.0] {
+                let ours = compute_mass_kg_at_pressure(&row, p, 310.0, pressure_pa).unwrap();
+                let theirs = cyboair_corridor_safety::compute_mass_kg_at_pressure(
+                    &corridor,
+                    p,
+                    310.0,
+                    pressure_pa,
+                )
+                .unwrap();
+                assert!(ours > 0.0, "{pollutant} {unit}");
+                assert_eq!(ours, theirs, "{pollutant} {unit} at {pressure_pa} Pa");
+            }
+            // The string-level helper the guard binaries use agrees too.
+            let factor = unit_to_kg_factor_at_pressure(
+                unit,
+                310.0,
+                P_STANDARD_PA,
+                p.molar_mass().unwrap_or(0.0),
+            )
+            .unwrap();
+            let expected = factor * (row.cin - row.cout) * row.airflow_m3_per_s * row.period_s;
+            let ours = compute_mass_kg(&row, p, 310.0).unwrap();
+            assert!(
+                (ours - expected).abs() <= 1e-12 * expected,
+                "{pollutant} {unit}"
+            );
+        }
+    }
+
+    #[test]
+    fn boxed_error_callers_still_compile() {
+        fn legacy(row: &GovernanceRow) -> Result<(), Box<dyn Error>> {
+            validate_row(row, 298.15)?;
+            Ok(())
+        }
+        let mut row = valid_row();
+        row.ecoimpact_score = -0.1;
+        let err = legacy(&row).unwrap_err();
+        assert!(err.to_string().contains("ecoimpact"), "{err}");
+    }
+
+    #[test]
+    fn legacy_wrapper_is_karma_only() {
+        let expected = 1.0 - (-2.0_f64 * 0.5).exp();
+        for m in [0.0, 10.0, 1.0e6] {
+            assert!((compute_ecoimpact(m, 5.0e5, 1.0e6, 2.0) - expected).abs() < 1e-12);
+        }
+        assert_eq!(compute_ecoimpact(1.0, 5.0e5, 0.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn ceim_entry_points_match_the_reference() {
+        for case in ceim::REFERENCE_ROWS {
+            let row = GovernanceRow {
+                machine_id: case.name.into(),
+                r#type: "Reference".into(),
+                location: "Reference".into(),
+                pollutant: case.pollutant.as_str().into(),
+                cin: case.cin,
+                cout: case.cout,
+                unit: case.unit.as_str().into(),
+                airflow_m3_per_s: case.airflow_m3_per_s,
+                period_s: case.period_s,
+                lambda_hazard: case.lambda_hazard,
+                beta_nb_per_kg: case.beta_nb_per_kg,
+                ecoimpact_score: 0.9,
+            };
+            let (p, t, pa) = (case.pollutant, case.temperature_k, case.pressure_pa);
+            let mut mass = vec![
+                ("ceim::mass_kg", case.mass_kg()),
+                (
+                    "eibon_core::compute_mass_kg_at_pressure",
+                    compute_mass_kg_at_pressure(&row, p, t, pa).unwrap(),
+                ),
+                (
+                    "eibon_core::checked_mass_kg_at_pressure",
+                    checked_mass_kg_at_pressure(&row, p, t, pa).unwrap(),
+                ),
+            ];
+            if pa == P_STANDARD_PA {
+                mass.extend([
+                    (
+                        "eibon_core::compute_mass_kg",
+                        compute_mass_kg(&row, p, t).unwrap(),
+                    ),
+                    (
+                        "eibon_core::checked_mass_kg",
+                        checked_mass_kg(&row, p, t).unwrap(),
+                    ),
+                ]);
+            }
+            ceim::assert_agree("mass_kg", case.name, &mass);
+
+            let m = case.mass_kg();
+            ceim::assert_agree(
+                "karma_bytes",
+                case.name,
+                &[
+                    ("ceim::karma_bytes", case.karma_bytes()),
+                    (
+                        "eibon_core::compute_karma_bytes",
+                        compute_karma_bytes(&row, m),
+                    ),
+                    (
+                        "eibon_core::checked_karma_bytes",
+                        checked_karma_bytes(&row, m).unwrap(),
+                    ),
+                ],
+            );
+        }
+
+        for (i, case) in ceim::REFERENCE_ECOIMPACT.iter().enumerate() {
+            let params = EcoImpactParams {
+                alpha: case.alpha,
+                mass_ref_kg: case.mass_ref_kg,
+                karma_ref: case.karma_ref,
+                w_mass: case.w_mass,
+                w_karma: case.w_karma,
+            };
+            let mut sites = vec![
+                ("ceim::ecoimpact", case.ecoimpact()),
+                (
+                    "eibon_core::compute_ecoimpact_with",
+                    compute_ecoimpact_with(case.mass_kg, case.karma_bytes, &params).unwrap(),
+                ),
+            ];
+            if case.w_mass == 0.0 && case.w_karma == 1.0 && case.mass_ref_kg == 1.0 {
+                sites.push((
+                    "eibon_core::compute_ecoimpact",
+                    compute_ecoimpact(case.mass_kg, case.karma_bytes, case.karma_ref, case.alpha),
+                ));
+            }
+            ceim::assert_agree("ecoimpact", &format!("case {i}"), &sites);
+        }
     }
-    Ok(())
 }
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::GovernanceRow;
+
+/// Errors while reading or writing qpudatashard CSVs.
+#[derive(Debug)]
+pub enum ShardError {
+    Io(std::io::Error),
+    /// A data row could not be parsed; `line` is 1-based and counts the
+    /// header.
+    Row {
+        line: u64,
+        message: String,
+    },
+    Csv(String),
+}
+
+impl fmt::Display for ShardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardError::Io(e) => write!(f, "io error: {e}"),
+            ShardError::Row { line, message } => write!(f, "line {line}: {message}"),
+            ShardError::Csv(message) => write!(f, "csv error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ShardError {}
+
+impl From<std::io::Error> for ShardError {
+    fn from(err: std::io::Error) -> Self {
+        ShardError::Io(err)
+    }
+}
+
+impl From<csv::Error> for ShardError {
+    fn from(err: csv::Error) -> Self {
+        match err.position() {
+            Some(pos) => row_error(pos.line(), &err, None),
+            None => ShardError::Csv(err.to_string()),
+        }
+    }
+}
+
+fn row_error(line: u64, err: &csv::Error, headers: Option<&csv::StringRecord>) -> ShardError {
+    let message = match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => {
+            let column = err
+                .field()
+                .and_then(|i| headers.and_then(|h| h.get(i as usize)));
+            match column {
+                Some(name) => format!("column {name}: {}", err.kind()),
+                None => err.to_string(),
+            }
+        }
+        _ => err.to_string(),
+    };
+    ShardError::Row { line, message }
+}
+
+/// Read governance rows from any reader. Columns are matched by header
+/// name, so reordered or extra columns are fine; the first bad row aborts
+/// the read with its line number and, where known, the offending column.
+pub fn read_rows<R: Read>(reader: R) -> Result<Vec<GovernanceRow>, ShardError> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = rdr.headers()?.clone();
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    while rdr.read_record(&mut record)? {
+        let line = record.position().map_or(0, |p| p.line());
+        let row: GovernanceRow = record
+            .deserialize(Some(&headers))
+            .map_err(|e| row_error(line, &e, Some(&headers)))?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Convenience wrapper around `read_rows` for a file on disk.
+pub fn read_rows_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<GovernanceRow>, ShardError> {
+    read_rows(File::open(path)?)
+}
+
+/// Write rows with the canonical header, e.g. to re-emit a corrected
+/// shard. Extra columns seen on read are not preserved.
+pub fn write_rows<W: Write>(writer: W, rows: &[GovernanceRow]) -> Result<(), ShardError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARD: &str = "\
+machine_id,type,location,pollutant,cin,cout,unit,airflow_m3_per_s,period_s,lambda_hazard,beta_nb_per_kg,ecoimpactscore,notes
+CYB-AIR-CANOPY-01,UrbanNanoswarmCanopy,Phoenix-Intersection-A,PM2.5,40,28,ug/m3,3.0,3600,3.0,5.0e8,0.92,\"Lamppost/roof modules; 12 ug/m3, rush hour.\"
+CYB-AIR-FLEET-02,TeslaswarmFleetPod,Phoenix-BusRoute-Blue,BlackCarbon,5.0,3.8,ug/m3,0.4,5400,3.5,6.0e8,0.89,\"Retrofit intake pods on e-buses.\"
+";
+
+    #[test]
+    fn reads_by_header_ignoring_extra_columns() {
+        let rows = read_rows(SHARD.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].machine_id, "CYB-AIR-CANOPY-01");
+        assert_eq!(rows[0].r#type, "UrbanNanoswarmCanopy");
+        assert_eq!(rows[0].ecoimpact_score, 0.92);
+        assert_eq!(rows[1].period_s, 5400.0);
+    }
+
+    #[test]
+    fn malformed_row_reports_line_and_column() {
+        let csv = SHARD.replace("0.4,5400", "0.4,54o0");
+        match read_rows(csv.as_bytes()) {
+            Err(ShardError::Row { line, message }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("period_s"), "{message}");
+            }
+            other => panic!("expected row error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn written_shard_reads_back() {
+        let rows = read_rows(SHARD.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        write_rows(&mut out, &rows).unwrap();
+
+        let text = String::from_utf8(out.clone()).unwrap();
+        assert!(text.starts_with("machine_id,type,location,"), "{text}");
+        assert!(!text.contains("notes"));
+
+        let back = read_rows(out.as_slice()).unwrap();
+        assert_eq!(back.len(), rows.len());
+        for (a, b) in back.iter().zip(&rows) {
+            assert_eq!(a.machine_id, b.machine_id);
+            assert_eq!(a.cin, b.cin);
+            assert_eq!(a.beta_nb_per_kg, b.beta_nb_per_kg);
+        }
+    }
+
+    #[test]
+    fn missing_path_is_io_error() {
+        assert!(matches!(
+            read_rows_from_path("/nonexistent/shard.csv"),
+            Err(ShardError::Io(_))
+        ));
+    }
+}
@@ -1,5 +1,12 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
+// `fx-only` compiles out the f32 API for FPU-less parts. Only the clippy
+// lint below keeps float arithmetic out of the fixed-point code, so check
+// such builds with `cargo clippy --features fx-only`; the manifest that
+// builds this file has to declare the feature.
+#![cfg_attr(feature = "fx-only", deny(clippy::float_arithmetic))]
 
+#[cfg(not(feature = "fx-only"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct QpuRow {
     pub node_id: [u8; 16],
     pub region: [u8; 16],
@@ -15,33 +22,848 @@ pub struct QpuRow {
     pub beta_nb_per_kg: f32,
 }
 
+#[cfg(not(feature = "fx-only"))]
+impl QpuRow {
+    fn inputs_finite(&self) -> bool {
+        [
+            self.cin,
+            self.cout,
+            self.q,
+            self.area,
+            self.beta_band,
+            self.dt,
+            self.lambda_hazard,
+            self.beta_nb_per_kg,
+        ]
+        .iter()
+        .all(|v| v.is_finite())
+    }
+}
+
+/// Duty update was clamped up to 0.
+pub const FLAG_DUTY_SAT_LOW: u8 = 1 << 0;
+/// Duty update was clamped down to 1.
+pub const FLAG_DUTY_SAT_HIGH: u8 = 1 << 1;
+/// At least one row had a NaN or infinite input; such rows are left out of
+/// the totals.
+pub const FLAG_NON_FINITE_ROW: u8 = 1 << 2;
+/// At least one row had cout > cin (the node emitted rather than removed).
+pub const FLAG_CONC_INVERSION: u8 = 1 << 3;
+/// `step_node_constrained` cut the duty change to the ramp limit.
+pub const FLAG_RATE_LIMITED: u8 = 1 << 4;
+/// `step_node_constrained` held the duty to honour a min-on/min-off dwell.
+pub const FLAG_DWELL_HOLD: u8 = 1 << 5;
+
+#[cfg(not(feature = "fx-only"))]
 pub struct NodeTelemetry {
     pub m_removed_kg: f32,
     pub nk_bytes: f32,
+    /// nk_bytes / k_ref, clamped to [0, 1]; 0 if k_ref is not positive.
     pub ecoimpact_score: f32,
     pub duty_next: f32,
+    /// `FLAG_*` bits.
+    pub flags: u8,
 }
 
+#[cfg(not(feature = "fx-only"))]
+/// Per-row contribution, in the same units as the node totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RowTelemetry {
+    pub m_band_kg: f32,
+    pub nk_bytes: f32,
+}
+
+#[cfg(not(feature = "fx-only"))]
 pub fn step_node(rows: &[QpuRow], u_k: f32, p_watts: f32,
-                 eta_cost: f32, gamma_mass: f32) -> NodeTelemetry {
+                 eta_cost: f32, gamma_mass: f32, k_ref: f32) -> NodeTelemetry {
+    step_node_detailed(rows, u_k, p_watts, eta_cost, gamma_mass, k_ref, &mut [])
+}
+
+#[cfg(not(feature = "fx-only"))]
+/// `step_node`, also writing each row's (mass, karma) into `per_row[i]`.
+/// Only the first `min(rows.len(), per_row.len())` entries are written;
+/// every row still counts toward the totals. Non-finite rows are reported
+/// as computed but excluded from the totals.
+pub fn step_node_detailed(rows: &[QpuRow], u_k: f32, p_watts: f32,
+                          eta_cost: f32, gamma_mass: f32, k_ref: f32,
+                          per_row: &mut [RowTelemetry]) -> NodeTelemetry {
     let mut m_total = 0.0f32;
     let mut k_total = 0.0f32;
-    for r in rows {
+    let mut flags = 0u8;
+    for (i, r) in rows.iter().enumerate() {
         let m = (r.cin - r.cout) * r.q * r.dt; // kg
         let j = m / (r.area * r.dt).max(1e-6); // kg m^-2 s^-1
         let m_band = j * r.area * r.beta_band * r.dt;
         let k = r.beta_nb_per_kg * r.lambda_hazard * m_band;
+        if let Some(slot) = per_row.get_mut(i) {
+            *slot = RowTelemetry { m_band_kg: m_band, nk_bytes: k };
+        }
+        if !r.inputs_finite() {
+            flags |= FLAG_NON_FINITE_ROW;
+            continue;
+        }
+        if r.cout > r.cin {
+            flags |= FLAG_CONC_INVERSION;
+        }
         m_total += m_band;
         k_total += k;
     }
     let grad = gamma_mass * m_total - eta_cost * p_watts;
     let mut u_next = u_k + grad;
-    if u_next < 0.0 { u_next = 0.0; }
-    if u_next > 1.0 { u_next = 1.0; }
+    if u_next < 0.0 { u_next = 0.0; flags |= FLAG_DUTY_SAT_LOW; }
+    if u_next > 1.0 { u_next = 1.0; flags |= FLAG_DUTY_SAT_HIGH; }
+    let ecoimpact_score = if k_ref > 0.0 {
+        (k_total / k_ref).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
     NodeTelemetry {
         m_removed_kg: m_total,
         nk_bytes: k_total,
-        ecoimpact_score: k_total, // normalized upstream in CEIM
+        ecoimpact_score,
         duty_next: u_next,
+        flags,
+    }
+}
+
+/// Actuator limits for `step_node_constrained`. The blower is on whenever
+/// duty is above zero.
+#[cfg(not(feature = "fx-only"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DutyConstraints {
+    /// Largest |duty_next - u_k| per step; negative is treated as zero.
+    pub max_delta_per_step: f32,
+    pub min_on_steps: u16,
+    pub min_off_steps: u16,
+}
+
+/// Per-node state carried between `step_node_constrained` calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DutyState {
+    /// Steps spent in the current on/off mode, including the last one. A
+    /// fresh state (0) enforces the full dwell before the first switch.
+    pub steps_in_mode: u16,
+}
+
+/// `step_node` for real actuators: the gradient update is applied, then
+/// the ramp limit, and if that would switch the blower on or off before
+/// the current mode has lasted its minimum dwell, `u_k` is held instead.
+#[cfg(not(feature = "fx-only"))]
+#[allow(clippy::too_many_arguments)]
+pub fn step_node_constrained(rows: &[QpuRow], u_k: f32, p_watts: f32,
+                             eta_cost: f32, gamma_mass: f32, k_ref: f32,
+                             limits: &DutyConstraints, state: &mut DutyState) -> NodeTelemetry {
+    let mut t = step_node(rows, u_k, p_watts, eta_cost, gamma_mass, k_ref);
+    let max_delta = limits.max_delta_per_step.max(0.0);
+    let delta = t.duty_next - u_k;
+    if delta.abs() > max_delta {
+        t.duty_next = u_k + delta.clamp(-max_delta, max_delta);
+        t.flags |= FLAG_RATE_LIMITED;
+    }
+    let was_on = u_k > 0.0;
+    if was_on != (t.duty_next > 0.0) {
+        let dwell = if was_on { limits.min_on_steps } else { limits.min_off_steps };
+        if state.steps_in_mode < dwell {
+            t.duty_next = u_k;
+            t.flags |= FLAG_DWELL_HOLD;
+        } else {
+            state.steps_in_mode = 0;
+        }
+    }
+    state.steps_in_mode = state.steps_in_mode.saturating_add(1);
+    t
+}
+
+/// Eco-band, ordered by severity; same semantics as the std crate's
+/// `EcoBand`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EcoBand {
+    Green,
+    Amber,
+    Red,
+}
+
+/// Upper bounds, in normalized eco-load, of Green and Amber.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandThresholds {
+    pub theta_green_amber: f32,
+    pub theta_amber_red: f32,
+}
+
+#[cfg(not(feature = "fx-only"))]
+impl BandThresholds {
+    /// Green below `theta_green_amber`, Red at or above `theta_amber_red`.
+    /// A non-finite load is Red.
+    pub fn classify(&self, eco_load: f32) -> EcoBand {
+        if eco_load < self.theta_green_amber {
+            EcoBand::Green
+        } else if eco_load < self.theta_amber_red {
+            EcoBand::Amber
+        } else {
+            EcoBand::Red
+        }
+    }
+}
+
+#[cfg(not(feature = "fx-only"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorridorSummary {
+    pub m_total_kg: f32,
+    pub nk_total: f32,
+    pub eco_load: f32,
+    pub band: EcoBand,
+    /// Node with the largest eco-load contribution; `None` for an empty
+    /// corridor.
+    pub worst_node: Option<usize>,
+}
+
+/// Corridor totals for a gateway's nodes. The eco-load weighs mass and
+/// karma equally, as the std crate does with alpha_m = alpha_k:
+/// 0.5 M/m_ref + 0.5 K/k_ref.
+///
+/// An empty slice gives zero totals, Green and no worst node. Non-finite
+/// telemetry or references make the load non-finite and hence Red, and a
+/// node with a non-finite contribution is reported as the worst.
+#[cfg(not(feature = "fx-only"))]
+pub fn aggregate_corridor(telemetry: &[NodeTelemetry], m_ref: f32, k_ref: f32,
+                          thresholds: &BandThresholds) -> CorridorSummary {
+    let load = |m: f32, k: f32| 0.5 * m / m_ref + 0.5 * k / k_ref;
+    let mut m_total = 0.0f32;
+    let mut k_total = 0.0f32;
+    let mut worst: Option<(usize, f32)> = None;
+    for (i, t) in telemetry.iter().enumerate() {
+        m_total += t.m_removed_kg;
+        k_total += t.nk_bytes;
+        let l = load(t.m_removed_kg, t.nk_bytes);
+        let replace = match worst {
+            None => true,
+            Some((_, w)) => w.is_finite() && (!l.is_finite() || l > w),
+        };
+        if replace {
+            worst = Some((i, l));
+        }
+    }
+    let eco_load = if telemetry.is_empty() { 0.0 } else { load(m_total, k_total) };
+    CorridorSummary {
+        m_total_kg: m_total,
+        nk_total: k_total,
+        eco_load,
+        band: thresholds.classify(eco_load),
+        worst_node: worst.map(|(i, _)| i),
+    }
+}
+
+/// Q16.16 fixed point: `Q16_ONE` represents 1.0.
+pub type Q16 = i32;
+pub const Q16_ONE: Q16 = 1 << 16;
+
+/// `QpuRow` for parts without an FPU. Every field is Q16.16, but the very
+/// small SI values are rescaled to stay representable: concentrations are
+/// in ug/m^3 and the karma factor is per mg. `area` only matters when
+/// `area * dt` is zero, which zeroes the row as in `step_node`.
+pub struct QpuRowFx {
+    pub node_id: [u8; 16],
+    pub region: [u8; 16],
+    pub band: u8,
+    pub pollutant: u8,
+    pub cin_ug_m3: Q16,
+    pub cout_ug_m3: Q16,
+    pub q: Q16,    // m^3/s
+    pub area: Q16, // m^2
+    pub beta_band: Q16,
+    pub dt: Q16, // s
+    pub lambda_hazard: Q16,
+    pub beta_nb_per_mg: Q16,
+}
+
+/// `NodeTelemetry` in fixed point. Mass is Q16.16 mg; karma is in whole
+/// units, as a step's karma routinely exceeds the Q16.16 range.
+pub struct NodeTelemetryFx {
+    pub m_removed_mg: Q16,
+    pub nk_bytes: i64,
+    /// nk_bytes / k_ref in [0, Q16_ONE]; 0 if k_ref is not positive.
+    pub ecoimpact_score: Q16,
+    pub duty_next: Q16,
+    /// `FLAG_*` bits; `FLAG_NON_FINITE_ROW` is never set.
+    pub flags: u8,
+}
+
+fn mul_q16(a: i64, b: i64) -> i64 {
+    a.saturating_mul(b) >> 16
+}
+
+fn sat_q16(x: i64) -> Q16 {
+    x.clamp(i32::MIN as i64, i32::MAX as i64) as Q16
+}
+
+/// Fixed-point `step_node`. `gamma_mass` stays per kg as in the float
+/// path; `eta_cost` is per W; `k_ref` is in whole karma units. All
+/// arithmetic is integer and saturating.
+pub fn step_node_fx(rows: &[QpuRowFx], u_k: Q16, p_watts: Q16,
+                    eta_cost: Q16, gamma_mass: Q16, k_ref: i64) -> NodeTelemetryFx {
+    let mut m_total: i64 = 0; // Q16 mg
+    let mut k_total: i64 = 0; // Q16 karma
+    let mut flags = 0u8;
+    for r in rows {
+        if r.cout_ug_m3 > r.cin_ug_m3 {
+            flags |= FLAG_CONC_INVERSION;
+        }
+        if mul_q16(r.area as i64, r.dt as i64) == 0 {
+            continue;
+        }
+        let dc = (r.cin_ug_m3 as i64) - (r.cout_ug_m3 as i64);
+        let m_ug = mul_q16(mul_q16(dc, r.q as i64), r.dt as i64);
+        let m_band = mul_q16(m_ug / 1000, r.beta_band as i64);
+        let k = mul_q16(mul_q16(r.beta_nb_per_mg as i64, r.lambda_hazard as i64), m_band);
+        m_total = m_total.saturating_add(m_band);
+        k_total = k_total.saturating_add(k);
+    }
+    let gain = mul_q16(gamma_mass as i64, m_total) / 1_000_000;
+    let grad = gain.saturating_sub(mul_q16(eta_cost as i64, p_watts as i64));
+    let mut u_next = (u_k as i64).saturating_add(grad);
+    if u_next < 0 { u_next = 0; flags |= FLAG_DUTY_SAT_LOW; }
+    if u_next > Q16_ONE as i64 { u_next = Q16_ONE as i64; flags |= FLAG_DUTY_SAT_HIGH; }
+    let nk_bytes = k_total >> 16;
+    NodeTelemetryFx {
+        m_removed_mg: sat_q16(m_total),
+        nk_bytes,
+        ecoimpact_score: score_q16(nk_bytes, k_ref),
+        duty_next: u_next as Q16,
+        flags,
+    }
+}
+
+fn score_q16(mut nk: i64, mut k_ref: i64) -> Q16 {
+    if k_ref <= 0 || nk <= 0 {
+        return 0;
+    }
+    if nk >= k_ref {
+        return Q16_ONE;
+    }
+    // nk < k_ref; drop low bits so the shift below cannot overflow.
+    while k_ref > (1 << 46) {
+        nk >>= 1;
+        k_ref >>= 1;
+    }
+    ((nk << 16) / k_ref) as Q16
+}
+
+/// A float input that has no fixed-point value.
+#[cfg(not(feature = "fx-only"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonFiniteField(pub &'static str);
+
+#[cfg(not(feature = "fx-only"))]
+fn q16_from_f32(field: &'static str, x: f32) -> Result<Q16, NonFiniteField> {
+    if !x.is_finite() {
+        return Err(NonFiniteField(field));
+    }
+    // `as` saturates at the i32 range.
+    Ok((x * Q16_ONE as f32) as Q16)
+}
+
+/// Converts SI units to the rescaled fields; values beyond the Q16.16
+/// range saturate.
+#[cfg(not(feature = "fx-only"))]
+impl TryFrom<&QpuRow> for QpuRowFx {
+    type Error = NonFiniteField;
+
+    fn try_from(r: &QpuRow) -> Result<Self, Self::Error> {
+        Ok(QpuRowFx {
+            node_id: r.node_id,
+            region: r.region,
+            band: r.band,
+            pollutant: r.pollutant,
+            cin_ug_m3: q16_from_f32("cin", r.cin * 1e9)?,
+            cout_ug_m3: q16_from_f32("cout", r.cout * 1e9)?,
+            q: q16_from_f32("q", r.q)?,
+            area: q16_from_f32("area", r.area)?,
+            beta_band: q16_from_f32("beta_band", r.beta_band)?,
+            dt: q16_from_f32("dt", r.dt)?,
+            lambda_hazard: q16_from_f32("lambda_hazard", r.lambda_hazard)?,
+            beta_nb_per_mg: q16_from_f32("beta_nb_per_kg", r.beta_nb_per_kg * 1e-6)?,
+        })
+    }
+}
+
+#[cfg(not(feature = "fx-only"))]
+impl From<&NodeTelemetryFx> for NodeTelemetry {
+    fn from(t: &NodeTelemetryFx) -> Self {
+        let one = Q16_ONE as f32;
+        NodeTelemetry {
+            m_removed_kg: t.m_removed_mg as f32 / one * 1e-6,
+            nk_bytes: t.nk_bytes as f32,
+            ecoimpact_score: t.ecoimpact_score as f32 / one,
+            duty_next: t.duty_next as f32 / one,
+            flags: t.flags,
+        }
+    }
+}
+
+/// Bytes per `QpuRow` frame on the radio link.
+///
+/// Layout, all little-endian:
+///
+/// | offset | size | field            |
+/// |--------|------|------------------|
+/// | 0      | 16   | `node_id`        |
+/// | 16     | 16   | `region`         |
+/// | 32     | 1    | `band`           |
+/// | 33     | 1    | `pollutant`      |
+/// | 34     | 4    | `cin` (f32)      |
+/// | 38     | 4    | `cout`           |
+/// | 42     | 4    | `q`              |
+/// | 46     | 4    | `area`           |
+/// | 50     | 4    | `beta_band`      |
+/// | 54     | 4    | `dt`             |
+/// | 58     | 4    | `lambda_hazard`  |
+/// | 62     | 4    | `beta_nb_per_kg` |
+/// | 66     | 2    | CRC-16 of bytes 0..66 |
+///
+/// The CRC is CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF).
+pub const QPU_FRAME_LEN: usize = 68;
+#[cfg(not(feature = "fx-only"))]
+const QPU_PAYLOAD_LEN: usize = QPU_FRAME_LEN - 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ends partway through a frame, or an encode target is too
+    /// small.
+    ShortBuffer,
+    /// Frame `index` fails its checksum.
+    BadCrc { index: usize },
+    /// Frame `index` carries a NaN or infinite `field`.
+    NonFiniteField { index: usize, field: &'static str },
+}
+
+pub fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in bytes {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(not(feature = "fx-only"))]
+const QPU_F32_FIELDS: [&str; 8] = [
+    "cin", "cout", "q", "area", "beta_band", "dt", "lambda_hazard", "beta_nb_per_kg",
+];
+
+/// Decode consecutive frames from `bytes` into `out`, returning the number
+/// of rows written. At most `out.len()` frames are read; the rest can be
+/// decoded from `bytes[n * QPU_FRAME_LEN..]`.
+///
+/// A `bytes` that is not a whole number of frames is `ShortBuffer`,
+/// returned before any row is written. Otherwise frames are checked in
+/// order, and on a bad one `out[..index]` already holds the rows before
+/// it; callers that need all-or-nothing batches drop them.
+#[cfg(not(feature = "fx-only"))]
+pub fn decode_rows(bytes: &[u8], out: &mut [QpuRow]) -> Result<usize, DecodeError> {
+    if !bytes.len().is_multiple_of(QPU_FRAME_LEN) {
+        return Err(DecodeError::ShortBuffer);
+    }
+    let mut n = 0;
+    let frames = bytes.chunks_exact(QPU_FRAME_LEN).zip(out.iter_mut());
+    for (index, (frame, slot)) in frames.enumerate() {
+        let (payload, crc) = frame.split_at(QPU_PAYLOAD_LEN);
+        if crc16_ccitt(payload) != u16::from_le_bytes([crc[0], crc[1]]) {
+            return Err(DecodeError::BadCrc { index });
+        }
+        let mut floats = [0.0f32; 8];
+        for (i, (v, field)) in floats.iter_mut().zip(QPU_F32_FIELDS).enumerate() {
+            let at = 34 + 4 * i;
+            *v = f32::from_le_bytes(payload[at..at + 4].try_into().unwrap());
+            if !v.is_finite() {
+                return Err(DecodeError::NonFiniteField { index, field });
+            }
+        }
+        let mut node_id = [0u8; 16];
+        let mut region = [0u8; 16];
+        node_id.copy_from_slice(&payload[0..16]);
+        region.copy_from_slice(&payload[16..32]);
+        let [cin, cout, q, area, beta_band, dt, lambda_hazard, beta_nb_per_kg] = floats;
+        *slot = QpuRow {
+            node_id,
+            region,
+            band: payload[32],
+            pollutant: payload[33],
+            cin,
+            cout,
+            q,
+            area,
+            beta_band,
+            dt,
+            lambda_hazard,
+            beta_nb_per_kg,
+        };
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Encode `rows` in the `decode_rows` layout, returning bytes written.
+/// Floats are written as given, non-finite ones included.
+#[cfg(not(feature = "fx-only"))]
+pub fn encode_rows(rows: &[QpuRow], out: &mut [u8]) -> Result<usize, DecodeError> {
+    let len = rows.len() * QPU_FRAME_LEN;
+    if out.len() < len {
+        return Err(DecodeError::ShortBuffer);
+    }
+    for (r, frame) in rows.iter().zip(out.chunks_exact_mut(QPU_FRAME_LEN)) {
+        frame[0..16].copy_from_slice(&r.node_id);
+        frame[16..32].copy_from_slice(&r.region);
+        frame[32] = r.band;
+        frame[33] = r.pollutant;
+        let floats = [
+            r.cin, r.cout, r.q, r.area, r.beta_band, r.dt, r.lambda_hazard, r.beta_nb_per_kg,
+        ];
+        for (i, v) in floats.iter().enumerate() {
+            frame[34 + 4 * i..38 + 4 * i].copy_from_slice(&v.to_le_bytes());
+        }
+        let crc = crc16_ccitt(&frame[..QPU_PAYLOAD_LEN]);
+        frame[QPU_PAYLOAD_LEN..].copy_from_slice(&crc.to_le_bytes());
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "fx-only"))]
+    fn row(cin: f32, cout: f32) -> QpuRow {
+        QpuRow {
+            node_id: [0; 16],
+            region: [0; 16],
+            band: 0,
+            pollutant: 0,
+            cin,
+            cout,
+            q: 2.0,
+            area: 4.0,
+            beta_band: 0.5,
+            dt: 10.0,
+            lambda_hazard: 3.0,
+            beta_nb_per_kg: 100.0,
+        }
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn per_row_outputs_match_hand_computation() {
+        let rows = [row(0.5, 0.25), row(1.0, 0.5)];
+        let mut per_row = [RowTelemetry::default(); 3];
+        let t = step_node_detailed(&rows, 0.5, 0.0, 0.0, 0.0, 1000.0, &mut per_row);
+
+        // (0.25 kg/m3 * 2 m3/s * 10 s) * 0.5 band share = 2.5 kg; * 100 * 3.
+        assert!((per_row[0].m_band_kg - 2.5).abs() < 1e-5);
+        assert!((per_row[0].nk_bytes - 750.0).abs() < 1e-2);
+        assert!((per_row[1].m_band_kg - 5.0).abs() < 1e-5);
+        assert!((per_row[1].nk_bytes - 1500.0).abs() < 1e-2);
+        assert_eq!(per_row[2], RowTelemetry::default());
+
+        assert!((t.m_removed_kg - 7.5).abs() < 1e-5);
+        assert!((t.nk_bytes - 2250.0).abs() < 1e-2);
+        assert_eq!(t.ecoimpact_score, 1.0);
+        assert_eq!(t.duty_next, 0.5);
+        assert_eq!(t.flags, 0);
+
+        // A larger reference leaves the score unsaturated: 2250 / 4500.
+        let t = step_node(&rows, 0.5, 0.0, 0.0, 0.0, 4500.0);
+        assert!((t.ecoimpact_score - 0.5).abs() < 1e-6);
+        assert_eq!(step_node(&rows, 0.5, 0.0, 0.0, 0.0, 0.0).ecoimpact_score, 0.0);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn flags_report_saturation_and_bad_rows() {
+        let rows = [row(0.5, 0.25)];
+        // grad = 0.1 * 2.5 = 0.25 pushes 0.9 past 1.
+        assert_eq!(step_node(&rows, 0.9, 0.0, 0.0, 0.1, 1.0).flags, FLAG_DUTY_SAT_HIGH);
+        // grad = -1 * 1 W pulls 0.2 below 0.
+        let t = step_node(&rows, 0.2, 1.0, 1.0, 0.0, 1.0);
+        assert_eq!(t.flags, FLAG_DUTY_SAT_LOW);
+        assert_eq!(t.duty_next, 0.0);
+
+        let mut nan = row(0.5, 0.25);
+        nan.q = f32::NAN;
+        let rows = [row(0.2, 0.3), nan, row(0.5, 0.25)];
+        let mut per_row = [RowTelemetry::default(); 3];
+        let t = step_node_detailed(&rows, 0.5, 0.0, 0.0, 0.0, 1e6, &mut per_row);
+        assert_eq!(t.flags, FLAG_CONC_INVERSION | FLAG_NON_FINITE_ROW);
+        assert!(per_row[1].m_band_kg.is_nan());
+        // The NaN row is excluded; the inverted row subtracts 1 kg.
+        assert!((t.m_removed_kg - 1.5).abs() < 1e-5);
+        assert!(t.nk_bytes.is_finite());
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[allow(clippy::too_many_arguments)]
+    fn si_row(cin: f32, cout: f32, q: f32, beta_band: f32, dt: f32,
+              lambda_hazard: f32, beta_nb_per_kg: f32) -> QpuRow {
+        QpuRow {
+            node_id: [0; 16],
+            region: [0; 16],
+            band: 0,
+            pollutant: 0,
+            cin,
+            cout,
+            q,
+            area: 2.0,
+            beta_band,
+            dt,
+            lambda_hazard,
+            beta_nb_per_kg,
+        }
+    }
+
+    /// Fixed and float paths agree to 1e-3 relative on corridor-scale rows;
+    /// the error is dominated by Q16.16 rounding of the inputs.
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn fixed_point_tracks_float_within_bound() {
+        let rows = [
+            si_row(40e-9, 28e-9, 3.0, 0.8, 3600.0, 3.0, 5.0e8), // PM2.5 canopy
+            si_row(5.0e-9, 3.8e-9, 0.4, 1.0, 5400.0, 3.5, 6.0e8), // black carbon pod
+            si_row(80e-9, 60e-9, 1.5, 0.5, 600.0, 2.0, 4.0e8), // NO2 wall
+        ];
+        let fx_rows: [QpuRowFx; 3] = [
+            QpuRowFx::try_from(&rows[0]).unwrap(),
+            QpuRowFx::try_from(&rows[1]).unwrap(),
+            QpuRowFx::try_from(&rows[2]).unwrap(),
+        ];
+        let rel = |a: f32, b: f32| ((a - b) / b).abs();
+
+        for (u, p, eta, gamma, k_ref) in [
+            (0.5, 50.0, 0.001, 500.0, 1.0e6),
+            (0.2, 10.0, 0.002, 2000.0, 1.0e5),
+        ] {
+            let f = step_node(&rows, u, p, eta, gamma, k_ref);
+            let x = step_node_fx(
+                &fx_rows,
+                (u * 65536.0) as Q16,
+                (p * 65536.0) as Q16,
+                (eta * 65536.0) as Q16,
+                (gamma * 65536.0) as Q16,
+                k_ref as i64,
+            );
+            let x = NodeTelemetry::from(&x);
+            assert!(rel(x.m_removed_kg, f.m_removed_kg) < 1e-3);
+            assert!(rel(x.nk_bytes, f.nk_bytes) < 1e-3);
+            assert!((x.ecoimpact_score - f.ecoimpact_score).abs() < 1e-3);
+            assert!((x.duty_next - f.duty_next).abs() < 1e-3);
+            assert_eq!(x.flags, f.flags);
+        }
+
+        let mut bad = si_row(40e-9, 28e-9, 3.0, 0.8, 3600.0, 3.0, 5.0e8);
+        bad.dt = f32::INFINITY;
+        assert_eq!(QpuRowFx::try_from(&bad).err(), Some(NonFiniteField("dt")));
+    }
+
+    #[test]
+    fn fixed_point_saturates_and_clamps_duty() {
+        let one = Q16_ONE;
+        let row = QpuRowFx {
+            node_id: [0; 16],
+            region: [0; 16],
+            band: 0,
+            pollutant: 0,
+            cin_ug_m3: i32::MAX,
+            cout_ug_m3: 0,
+            q: i32::MAX,
+            area: one,
+            beta_band: one,
+            dt: i32::MAX,
+            lambda_hazard: one,
+            beta_nb_per_mg: one,
+        };
+        let t = step_node_fx(&[row], one / 2, 0, 0, one, 1000);
+        assert_eq!(t.m_removed_mg, i32::MAX);
+        assert_eq!(t.duty_next, one);
+        assert_eq!(t.flags, FLAG_DUTY_SAT_HIGH);
+        assert_eq!(t.ecoimpact_score, one);
+
+        let t = step_node_fx(&[], one / 4, 10 * one, one, 0, 1000);
+        assert_eq!(t.duty_next, 0);
+        assert_eq!(t.flags, FLAG_DUTY_SAT_LOW);
+        assert_eq!(t.ecoimpact_score, 0);
+    }
+
+    #[test]
+    fn crc_matches_reference_check_value() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    fn frames() -> ([QpuRow; 3], [u8; 3 * QPU_FRAME_LEN]) {
+        let mut rows = [
+            si_row(40e-9, 28e-9, 3.0, 0.8, 3600.0, 3.0, 5.0e8),
+            si_row(5.0e-9, 3.8e-9, 0.4, 1.0, 5400.0, 3.5, 6.0e8),
+            si_row(80e-9, 60e-9, 1.5, 0.5, 600.0, 2.0, 4.0e8),
+        ];
+        for (i, r) in rows.iter_mut().enumerate() {
+            r.node_id = [i as u8 + 1; 16];
+            r.region = *b"Phoenix-Corr-A\0\0";
+            r.band = i as u8;
+            r.pollutant = 2 * i as u8;
+        }
+        let mut bytes = [0u8; 3 * QPU_FRAME_LEN];
+        assert_eq!(encode_rows(&rows, &mut bytes), Ok(bytes.len()));
+        (rows, bytes)
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn frames_round_trip() {
+        let (rows, bytes) = frames();
+        assert_eq!(&bytes[34..38], &40e-9f32.to_le_bytes());
+        let mut out = [QpuRow::default(); 4];
+        assert_eq!(decode_rows(&bytes, &mut out), Ok(3));
+        assert_eq!(&out[..3], &rows);
+
+        // A short output slice decodes a prefix; the rest resumes later.
+        let mut two = [QpuRow::default(); 2];
+        assert_eq!(decode_rows(&bytes, &mut two), Ok(2));
+        assert_eq!(decode_rows(&bytes[2 * QPU_FRAME_LEN..], &mut two), Ok(1));
+        assert_eq!(two[0], rows[2]);
+
+        assert_eq!(decode_rows(&bytes[..100], &mut out), Err(DecodeError::ShortBuffer));
+        assert_eq!(encode_rows(&rows, &mut [0u8; 100]), Err(DecodeError::ShortBuffer));
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn corrupted_frame_keeps_earlier_rows() {
+        let (rows, mut bytes) = frames();
+        bytes[QPU_FRAME_LEN + 40] ^= 0x10;
+        let mut out = [QpuRow::default(); 3];
+        assert_eq!(decode_rows(&bytes, &mut out), Err(DecodeError::BadCrc { index: 1 }));
+        assert_eq!(out[0], rows[0]);
+        assert_eq!(out[1], QpuRow::default());
+
+        // A NaN with a valid checksum is caught by the finiteness check.
+        let (_, mut bytes) = frames();
+        let mut bad = rows;
+        bad[2].dt = f32::NAN;
+        encode_rows(&bad, &mut bytes).unwrap();
+        let mut out = [QpuRow::default(); 3];
+        assert_eq!(
+            decode_rows(&bytes, &mut out),
+            Err(DecodeError::NonFiniteField { index: 2, field: "dt" })
+        );
+        assert_eq!(&out[..2], &rows[..2]);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn rapid_toggling_is_stretched_to_dwell_minimums() {
+        let rows = [row(0.5, 0.25)];
+        let limits = DutyConstraints {
+            max_delta_per_step: 1.0,
+            min_on_steps: 3,
+            min_off_steps: 2,
+        };
+        // Settled off, so the first switch is allowed.
+        let mut state = DutyState { steps_in_mode: 2 };
+        let mut u = 0.0;
+        let mut duties = [0.0f32; 8];
+        for (i, d) in duties.iter_mut().enumerate() {
+            // "On" drives the gradient to +2.5, "off" to -10.
+            let t = if i % 2 == 0 {
+                step_node_constrained(&rows, u, 0.0, 0.0, 1.0, 1.0, &limits, &mut state)
+            } else {
+                step_node_constrained(&rows, u, 10.0, 1.0, 0.0, 1.0, &limits, &mut state)
+            };
+            u = t.duty_next;
+            *d = u;
+        }
+        assert_eq!(duties, [1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0]);
+
+        let t = step_node_constrained(&rows, u, 10.0, 1.0, 0.0, 1.0, &limits, &mut state);
+        assert_eq!(t.duty_next, 1.0);
+        assert_eq!(t.flags & FLAG_DWELL_HOLD, FLAG_DWELL_HOLD);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn duty_ramps_at_most_max_delta() {
+        let rows = [row(0.5, 0.25)];
+        let limits = DutyConstraints {
+            max_delta_per_step: 0.25,
+            min_on_steps: 0,
+            min_off_steps: 0,
+        };
+        let mut state = DutyState::default();
+        let mut u = 0.0;
+        for expected in [0.25, 0.5, 0.75, 1.0] {
+            let t = step_node_constrained(&rows, u, 0.0, 0.0, 1.0, 1.0, &limits, &mut state);
+            assert_eq!(t.duty_next, expected);
+            assert_eq!(t.flags & FLAG_RATE_LIMITED != 0, expected < 1.0);
+            u = t.duty_next;
+        }
+        assert_eq!(state.steps_in_mode, 4);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    fn telemetry(m_removed_kg: f32, nk_bytes: f32) -> NodeTelemetry {
+        NodeTelemetry {
+            m_removed_kg,
+            nk_bytes,
+            ecoimpact_score: 0.0,
+            duty_next: 0.5,
+            flags: 0,
+        }
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn corridor_bands_switch_at_thresholds() {
+        let th = BandThresholds {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+        };
+        // Two nodes at 0.25 of each reference: 0.5 * 0.5 + 0.5 * 0.5.
+        let nodes = [telemetry(0.25, 250.0), telemetry(0.25, 250.0)];
+        let s = aggregate_corridor(&nodes, 1.0, 1000.0, &th);
+        assert_eq!(s.m_total_kg, 0.5);
+        assert_eq!(s.nk_total, 500.0);
+        assert_eq!(s.eco_load, 0.5);
+        assert_eq!(s.band, EcoBand::Amber);
+
+        assert_eq!(aggregate_corridor(&nodes[..1], 1.0, 1000.0, &th).band, EcoBand::Green);
+        let heavy = [telemetry(0.5, 500.0), telemetry(0.5, 500.0)];
+        assert_eq!(aggregate_corridor(&heavy, 1.0, 1000.0, &th).band, EcoBand::Red);
+        assert_eq!(aggregate_corridor(&nodes, 0.0, 1000.0, &th).band, EcoBand::Red);
+
+        let empty = aggregate_corridor(&[], 1.0, 1000.0, &th);
+        assert_eq!(empty.eco_load, 0.0);
+        assert_eq!(empty.band, EcoBand::Green);
+        assert_eq!(empty.worst_node, None);
+    }
+
+    #[cfg(not(feature = "fx-only"))]
+    #[test]
+    fn worst_node_has_largest_contribution() {
+        let th = BandThresholds {
+            theta_green_amber: 0.5,
+            theta_amber_red: 1.0,
+        };
+        // Node 1 has the most mass but node 2 wins on karma.
+        let nodes = [
+            telemetry(0.1, 100.0),
+            telemetry(0.3, 0.0),
+            telemetry(0.0, 400.0),
+            telemetry(0.1, 100.0),
+        ];
+        assert_eq!(aggregate_corridor(&nodes, 1.0, 1000.0, &th).worst_node, Some(2));
+
+        let mut faulty = nodes;
+        faulty[3].nk_bytes = f32::NAN;
+        let s = aggregate_corridor(&faulty, 1.0, 1000.0, &th);
+        assert_eq!(s.worst_node, Some(3));
+        assert_eq!(s.band, EcoBand::Red);
     }
 }